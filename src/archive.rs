@@ -0,0 +1,241 @@
+//! `--archive-dir`：处理成功的原始文件按处理日期归档，避免仪器导出目录越积越多。
+//!
+//! 原始文件移入 `<归档目录>/<YYYY-MM-DD>/`；`--archive-link` 时改为硬链接、原文件保留，
+//! `--archive-zip` 时改为压缩进 `<归档目录>/<YYYY-MM-DD>.zip`。指定 `--archive-keep-days`
+//! 时删除早于该天数的日期目录与压缩包，其他文件不动。
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDate, TimeDelta};
+use clap::Args;
+use tracing::info;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::batch;
+use crate::error::{Context, Result};
+
+/// 日期目录与压缩包的命名格式。
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// 归档相关的全局参数。
+#[derive(Debug, Clone, Default, Args)]
+pub struct ArchiveArgs {
+    /// 处理成功的原始文件移入该目录下按处理日期命名的子目录（YYYY-MM-DD）
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "in_place")]
+    pub archive_dir: Option<PathBuf>,
+
+    /// 归档时创建硬链接，原始文件留在原处（须与归档目录在同一磁盘分区）
+    #[arg(
+        long,
+        global = true,
+        requires = "archive_dir",
+        conflicts_with = "archive_zip"
+    )]
+    pub archive_link: bool,
+
+    /// 把原始文件压缩进按处理日期命名的 zip 文件（YYYY-MM-DD.zip）
+    #[arg(long, global = true, requires = "archive_dir")]
+    pub archive_zip: bool,
+
+    /// 删除归档目录中早于该天数的日期目录与 zip 文件
+    #[arg(long, global = true, value_name = "DAYS", requires = "archive_dir")]
+    pub archive_keep_days: Option<u32>,
+}
+
+impl ArchiveArgs {
+    /// 指定了 `--archive-dir` 时归档处理成功的原始文件，并按 `--archive-keep-days` 清理旧归档。
+    pub(crate) fn store(&self, input: &Path) -> Result<()> {
+        let Some(dir) = &self.archive_dir else {
+            return Ok(());
+        };
+        let today = Local::now().date_naive();
+        let archived = self.store_on(dir, input, today)?;
+        info!("原始文件已归档: {}", archived.display());
+        if let Some(days) = self.archive_keep_days {
+            for removed in prune(dir, today, days)? {
+                info!("已删除过期的归档: {}", removed.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// 把 `input` 归入 `dir` 中 `date` 对应的目录或压缩包，返回归档后的路径。
+    fn store_on(&self, dir: &Path, input: &Path, date: NaiveDate) -> Result<PathBuf> {
+        let stamp = date.format(DATE_FORMAT).to_string();
+        if self.archive_zip {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("无法创建归档目录: {}", dir.display()))?;
+            let zip_path = dir.join(format!("{stamp}.zip"));
+            let entry = add_to_zip(&zip_path, input)
+                .with_context(|| format!("无法写入归档压缩包: {}", zip_path.display()))?;
+            fs::remove_file(input)
+                .with_context(|| format!("无法删除已归档的原始文件: {}", input.display()))?;
+            return Ok(zip_path.join(entry));
+        }
+        let day = dir.join(stamp);
+        if !self.archive_link {
+            return batch::move_into(input, &day);
+        }
+        fs::create_dir_all(&day).with_context(|| format!("无法创建归档目录: {}", day.display()))?;
+        let target = day.join(batch::unique_name(input, |name| day.join(name).exists())?);
+        fs::hard_link(input, &target)
+            .with_context(|| format!("无法创建硬链接: {}", target.display()))?;
+        Ok(target)
+    }
+}
+
+/// 把文件压缩追加到 `zip_path`（不存在时创建）；同名条目已存在时在文件名后加时间戳。
+/// 返回条目名。
+fn add_to_zip(zip_path: &Path, input: &Path) -> Result<String> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(zip_path)?;
+    let (mut zip, names) = if file.metadata()?.len() == 0 {
+        (ZipWriter::new(file), HashSet::new())
+    } else {
+        let names: HashSet<String> = ZipArchive::new(&file)?
+            .file_names()
+            .map(str::to_string)
+            .collect();
+        (ZipWriter::new_append(file)?, names)
+    };
+    let entry = batch::unique_name(input, |name| names.contains(&*name.to_string_lossy()))?
+        .to_string_lossy()
+        .into_owned();
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    zip.start_file(entry.as_str(), options)?;
+    io::copy(&mut File::open(input)?, &mut zip)?;
+    zip.finish()?;
+    Ok(entry)
+}
+
+/// 删除 `dir` 中日期早于 `today` 前 `days` 天的日期目录与 zip 文件，返回删除的路径。
+fn prune(dir: &Path, today: NaiveDate, days: u32) -> Result<Vec<PathBuf>> {
+    let oldest = today - TimeDelta::days(days.into());
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("无法读取目录: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (stamp, is_zip) = match name.strip_suffix(".zip") {
+            Some(stamp) => (stamp, true),
+            None => (name.as_str(), false),
+        };
+        let Ok(date) = NaiveDate::parse_from_str(stamp, DATE_FORMAT) else {
+            continue;
+        };
+        if date >= oldest || stamp != date.format(DATE_FORMAT).to_string() {
+            continue;
+        }
+        if is_zip && path.is_file() {
+            fs::remove_file(&path)
+        } else if !is_zip && path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            continue;
+        }
+        .with_context(|| format!("无法删除过期的归档: {}", path.display()))?;
+        removed.push(path);
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, DATE_FORMAT).expect("date")
+    }
+
+    fn args(dir: &Path) -> ArchiveArgs {
+        ArchiveArgs {
+            archive_dir: Some(dir.to_path_buf()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn moves_or_links_into_dated_folders() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("archive");
+        let input = dir.path().join("a.xlsx");
+        fs::write(&input, "1")?;
+
+        let moved = args(&archive).store_on(&archive, &input, date("2026-01-05"))?;
+        assert_eq!(moved, archive.join("2026-01-05").join("a.xlsx"));
+        assert!(!input.exists() && moved.exists());
+
+        fs::write(&input, "2")?;
+        let link = ArchiveArgs {
+            archive_link: true,
+            ..args(&archive)
+        };
+        let linked = link.store_on(&archive, &input, date("2026-01-05"))?;
+        assert_ne!(linked, moved);
+        assert!(input.exists());
+        assert_eq!(fs::read_to_string(&linked)?, "2");
+        Ok(())
+    }
+
+    #[test]
+    fn compresses_into_daily_zip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("archive");
+        let zip = ArchiveArgs {
+            archive_zip: true,
+            ..args(&archive)
+        };
+        for content in ["first", "second"] {
+            let input = dir.path().join("a.csv");
+            fs::write(&input, content)?;
+            zip.store_on(&archive, &input, date("2026-01-05"))?;
+            assert!(!input.exists());
+        }
+
+        let mut zip = ZipArchive::new(File::open(archive.join("2026-01-05.zip"))?)?;
+        assert_eq!(zip.len(), 2);
+        let mut text = String::new();
+        zip.by_name("a.csv")?.read_to_string(&mut text)?;
+        assert_eq!(text, "first");
+        Ok(())
+    }
+
+    #[test]
+    fn prunes_only_expired_dated_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["2026-01-01", "2026-01-04", "notes"] {
+            fs::create_dir(dir.path().join(name))?;
+        }
+        for name in ["2025-12-31.zip", "2026-01-05.zip", "2026-1-1.zip"] {
+            fs::write(dir.path().join(name), "")?;
+        }
+        let removed = prune(dir.path(), date("2026-01-05"), 3)?;
+        assert_eq!(
+            removed,
+            [
+                dir.path().join("2025-12-31.zip"),
+                dir.path().join("2026-01-01")
+            ]
+        );
+        assert!(dir.path().join("2026-01-04").exists());
+        assert!(dir.path().join("2026-1-1.zip").exists());
+        assert!(dir.path().join("notes").exists());
+        Ok(())
+    }
+}
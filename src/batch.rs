@@ -0,0 +1,446 @@
+//! 批量处理：把命令行参数（文件、目录或通配符）展开为待处理文件列表，
+//! 逐个处理并在最后汇总成功/失败情况。
+//!
+//! 无法打开的文件（仍在复制、压缩包损坏）可按 `--open-retries` 等待重试，仍失败时用
+//! `--quarantine` 移入同级的 `quarantine/` 目录，旁边写一份错误说明，不再混在待处理文件中。
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{error, info, warn};
+
+use crate::error::{Context, DtToolsError, Result, invalid};
+use crate::i18n::Message;
+use crate::{progress, resume};
+
+/// 已处理输出文件的前缀；展开目录时跳过这些文件，避免重复处理。
+pub(crate) const OUTPUT_PREFIX: &str = "processed_";
+
+/// 无法打开的文件移入的子目录（与原文件同级）。
+pub(crate) const QUARANTINE_DIR: &str = "quarantine";
+
+/// 隔离文件旁错误说明的后缀：`<文件名>.error.txt`。
+const ERROR_SIDECAR_SUFFIX: &str = ".error.txt";
+
+/// 第一次重试前的等待时间，之后每次加倍。
+const FIRST_BACKOFF: Duration = Duration::from_secs(2);
+/// 重试等待时间的上限。
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// 无法打开的文件的处理方式。
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct OpenFailures {
+    /// 重试次数
+    pub retries: u32,
+    /// 重试后仍无法打开时移入隔离目录
+    pub quarantine: bool,
+}
+
+/// 第 `attempt` 次重试（从 1 起计）前的等待时间：2、4、8… 秒，最长 60 秒。
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    FIRST_BACKOFF
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+/// 是否为无法打开输入文件的错误（重试或隔离的对象）。
+pub(crate) fn is_open_failure(err: &DtToolsError) -> bool {
+    matches!(err.kind(), DtToolsError::FileOpen { .. })
+}
+
+/// `file` 移入其他目录时使用的文件名；`taken` 判断为已占用时在文件名后加时间戳。
+pub(crate) fn unique_name(file: &Path, taken: impl Fn(&OsStr) -> bool) -> Result<OsString> {
+    let name = file
+        .file_name()
+        .ok_or_else(|| invalid!("无效的文件名: {}", file.display()))?;
+    if !taken(name) {
+        return Ok(name.to_os_string());
+    }
+    let mut unique = file.file_stem().unwrap_or_default().to_os_string();
+    unique.push(format!("_{}", chrono::Local::now().format("%Y%m%d%H%M%S")));
+    if let Some(extension) = file.extension() {
+        unique.push(".");
+        unique.push(extension);
+    }
+    Ok(unique)
+}
+
+/// 把文件移入 `dir`（不存在时创建）；同名文件已存在时在文件名后加时间戳。返回新路径。
+pub(crate) fn move_into(file: &Path, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("无法创建目录: {}", dir.display()))?;
+    let target = dir.join(unique_name(file, |name| dir.join(name).exists())?);
+    fs::rename(file, &target)
+        .with_context(|| format!("无法移动文件到 {}: {}", dir.display(), file.display()))?;
+    Ok(target)
+}
+
+/// 把无法打开的文件移入同级的 `quarantine/`，并写出 `<文件名>.error.txt`（原路径、时间、
+/// 尝试次数与完整的错误链）。返回移入后的路径。
+pub(crate) fn quarantine(file: &Path, err: &DtToolsError, attempts: u32) -> Result<PathBuf> {
+    let dir = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.join(QUARANTINE_DIR),
+        _ => PathBuf::from(QUARANTINE_DIR),
+    };
+    let target = move_into(file, &dir)?;
+    let mut sidecar = target.clone().into_os_string();
+    sidecar.push(ERROR_SIDECAR_SUFFIX);
+    let text = format!(
+        "文件: {}\n时间: {}\n尝试次数: {attempts}\n错误: {err:#}\n",
+        file.display(),
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+    );
+    let sidecar = PathBuf::from(sidecar);
+    fs::write(&sidecar, text).map_err(|err| DtToolsError::write(&sidecar, err))?;
+    Ok(target)
+}
+
+/// 展开后的输入列表；`batch` 为 true 表示参数中含目录/通配符或多个文件。
+pub(crate) struct Inputs {
+    pub files: Vec<PathBuf>,
+    pub batch: bool,
+}
+
+pub(crate) fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+pub(crate) fn is_processed_output(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|n| n.as_encoded_bytes().starts_with(OUTPUT_PREFIX.as_bytes()))
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// 简单通配符匹配：`*` 匹配任意多个字符，`?` 匹配单个字符。
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// 目录中（不含子目录）满足 `keep` 的文件，跳过已处理的输出文件，按路径排序。
+pub(crate) fn list_dir(dir: &Path, keep: impl Fn(&Path) -> bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("无法读取目录: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_file() && !is_processed_output(&path) && keep(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// 把参数展开为文件列表。目录只收集扩展名在 `extensions` 中的文件，
+/// 通配符只作用于最后一级文件名（如 `data/*.xlsx`）。
+pub(crate) fn expand_inputs(args: &[PathBuf], extensions: &[&str]) -> Result<Inputs> {
+    let mut files = Vec::new();
+    let mut batch = args.len() > 1;
+
+    for arg in args {
+        let name = arg
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if arg.is_dir() {
+            batch = true;
+            files.extend(list_dir(arg, |p| has_extension(p, extensions))?);
+        } else if is_glob(&name) {
+            batch = true;
+            let parent = match arg.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p,
+                _ => Path::new("."),
+            };
+            let matched = list_dir(parent, |p| {
+                p.file_name()
+                    .is_some_and(|n| wildcard_match(&name, &n.to_string_lossy()))
+            })?;
+            if matched.is_empty() {
+                warn!(
+                    "{}",
+                    Message::NoMatchingFiles.render(&[("pattern", &arg.display())])
+                );
+            }
+            files.extend(matched);
+        } else {
+            files.push(arg.clone());
+        }
+    }
+
+    Ok(Inputs { files, batch })
+}
+
+/// 逐个处理文件，单个文件失败不会中断后续文件；最后打印汇总。无法打开的文件按 `open`
+/// 重试或隔离，隔离的文件仍计为失败。`state` 中已完成的文件跳过，处理成功的文件记入 `state`。
+/// 有任何文件失败时返回错误。
+pub(crate) fn run_batch(
+    files: &[PathBuf],
+    open: OpenFailures,
+    state: &mut resume::State,
+    process: impl FnMut(&Path) -> Result<PathBuf>,
+) -> Result<()> {
+    run_batch_with(files, open, state, thread::sleep, process)
+}
+
+fn run_batch_with(
+    files: &[PathBuf],
+    open: OpenFailures,
+    state: &mut resume::State,
+    mut sleep: impl FnMut(Duration),
+    mut process: impl FnMut(&Path) -> Result<PathBuf>,
+) -> Result<()> {
+    let mut results = Vec::with_capacity(files.len());
+    let mut skipped = 0;
+    let progress = progress::bar(files.len(), "批量处理", "个文件");
+    for file in files {
+        progress.set_message(file.display().to_string());
+        // 读不了的文件交给 process 报告错误
+        let digest = state.digest(file).ok().flatten();
+        if let Some(output) = digest
+            .as_deref()
+            .and_then(|digest| state.completed(digest))
+            .map(Path::to_path_buf)
+        {
+            info!(
+                "已处理过，跳过（去掉 --resume 重新处理）: {} -> {}",
+                file.display(),
+                output.display()
+            );
+            state.skip(file, &output);
+            results.push((file, Ok(output)));
+            skipped += 1;
+            progress.inc(1);
+            continue;
+        }
+        info!(
+            "{}",
+            Message::Processing.render(&[("file", &file.display())])
+        );
+        let mut result = process(file);
+        let mut attempts = 1;
+        while attempts <= open.retries && result.as_ref().is_err_and(is_open_failure) {
+            let wait = backoff(attempts);
+            warn!(
+                "无法打开，{} 秒后重试（第 {attempts}/{} 次）: {}",
+                wait.as_secs(),
+                open.retries,
+                file.display()
+            );
+            sleep(wait);
+            attempts += 1;
+            result = process(file);
+        }
+        if let (Ok(output), Some(digest)) = (&result, digest) {
+            state.record(digest, file, output);
+        }
+        if let Err(err) = &result {
+            if open.quarantine && is_open_failure(err) {
+                match quarantine(file, err, attempts) {
+                    Ok(moved) => warn!("已移入隔离目录: {}", moved.display()),
+                    Err(err) => error!("无法移入隔离目录: {}: {err:#}", file.display()),
+                }
+            }
+            error!(
+                "{}",
+                Message::FileFailed
+                    .render(&[("file", &file.display()), ("error", &format!("{err:#}"))])
+            );
+        }
+        results.push((file, result));
+        progress.inc(1);
+    }
+    drop(progress);
+
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    info!(
+        "{}",
+        Message::BatchSummary.render(&[
+            ("total", &results.len()),
+            ("succeeded", &(results.len() - failed)),
+            ("failed", &failed),
+        ])
+    );
+    if skipped > 0 {
+        info!("其中 {skipped} 个文件此前已处理完成，已跳过");
+    }
+    for (file, result) in &results {
+        match result {
+            Ok(out) => info!(
+                "{}",
+                Message::BatchSucceeded
+                    .render(&[("file", &file.display()), ("output", &out.display())])
+            ),
+            Err(err) => info!(
+                "{}",
+                Message::BatchFailed
+                    .render(&[("file", &file.display()), ("error", &format!("{err:#}"))])
+            ),
+        }
+    }
+
+    let first = results.iter().find_map(|(file, result)| {
+        let err = result.as_ref().err()?;
+        Some(format!("{}: {err:#}", file.display()))
+    });
+    match first {
+        Some(first) => Err(DtToolsError::Batch {
+            failed,
+            total: results.len(),
+            first,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::bail;
+
+    #[test]
+    fn wildcard_matches_names() {
+        assert!(wildcard_match("*.xlsx", "a.xlsx"));
+        assert!(wildcard_match("voc?_*.xlsx", "voc1_2025.xlsx"));
+        assert!(!wildcard_match("*.xlsx", "a.xls"));
+        assert!(!wildcard_match("a?.xlsx", "a.xlsx"));
+    }
+
+    #[test]
+    fn expands_directory_and_skips_outputs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["b.xlsx", "a.XLSX", "processed_a.xlsx", "notes.txt"] {
+            fs::write(dir.path().join(name), b"")?;
+        }
+        let inputs = expand_inputs(&[dir.path().to_path_buf()], &["xlsx"])?;
+        assert!(inputs.batch);
+        let names: Vec<_> = inputs
+            .files
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["a.XLSX", "b.xlsx"]);
+        Ok(())
+    }
+
+    #[test]
+    fn retries_and_quarantines_unreadable_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let good = dir.path().join("a.xlsx");
+        let bad = dir.path().join("b.xlsx");
+        fs::write(&good, b"")?;
+        fs::write(&bad, b"PK")?;
+        let open = OpenFailures {
+            retries: 2,
+            quarantine: true,
+        };
+        let mut waits = Vec::new();
+        let mut calls = 0;
+        let result = run_batch_with(
+            &[good.clone(), bad.clone()],
+            open,
+            &mut resume::State::default(),
+            |wait| waits.push(wait.as_secs()),
+            |file| {
+                calls += 1;
+                if file == bad {
+                    Err(DtToolsError::file_open(file, "invalid zip archive"))
+                } else {
+                    Ok(file.to_path_buf())
+                }
+            },
+        );
+        let err = result.unwrap_err();
+        assert!(matches!(err.kind(), DtToolsError::Batch { failed: 1, .. }));
+        assert_eq!(calls, 4);
+        assert_eq!(waits, [2, 4]);
+
+        let moved = dir.path().join(QUARANTINE_DIR).join("b.xlsx");
+        assert!(!bad.exists() && moved.exists() && good.exists());
+        let sidecar = fs::read_to_string(dir.path().join(QUARANTINE_DIR).join("b.xlsx.error.txt"))?;
+        assert!(sidecar.contains("尝试次数: 3"), "{sidecar}");
+        assert!(sidecar.contains("invalid zip archive"), "{sidecar}");
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_retry_other_errors() {
+        let mut calls = 0;
+        let open = OpenFailures {
+            retries: 3,
+            quarantine: false,
+        };
+        let result = run_batch_with(
+            &[PathBuf::from("a.xlsx")],
+            open,
+            &mut resume::State::default(),
+            |_| {},
+            |_| {
+                calls += 1;
+                Err(DtToolsError::MissingColumn {
+                    name: "时间".to_string(),
+                })
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+        assert_eq!(backoff(10), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn skips_files_completed_in_earlier_run() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let files: Vec<PathBuf> = ["a.xlsx", "b.xlsx"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for file in &files {
+            fs::write(file, file.to_string_lossy().as_bytes())?;
+        }
+        let run = |resume: bool, fail: &Path| -> Result<Vec<PathBuf>> {
+            let mut state = resume::State::load(dir.path(), "proton", "r1", resume, true)?;
+            let mut processed = Vec::new();
+            let _ = run_batch(&files, OpenFailures::default(), &mut state, |file| {
+                if file == fail {
+                    bail!("中断");
+                }
+                let output = file.with_extension("out");
+                fs::write(&output, "")?;
+                processed.push(file.to_path_buf());
+                Ok(output)
+            });
+            Ok(processed)
+        };
+
+        assert_eq!(run(true, &files[1])?, [files[0].clone()]);
+        assert_eq!(run(true, Path::new(""))?, [files[1].clone()]);
+        assert!(run(true, Path::new(""))?.is_empty());
+        assert_eq!(run(false, Path::new(""))?, files);
+        Ok(())
+    }
+}
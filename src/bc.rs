@@ -0,0 +1,127 @@
+//! `dttools bc`：把 AE33 黑碳仪的导出文件转换为国家平台的上传模板。
+//!
+//! AE33 每分钟记录一行，BC1–BC7 为 370–950 nm 七个波长的等效黑碳浓度（ng/m³）。BC 取 880 nm
+//! 的 BC6，源表没有 UVPM 列时按 BC1 − BC6 计算；两者默认按 75% 的数据捕获率求小时均值。
+//! 读取、配置与输出格式见 [`crate::instrument`]，站点配置从 `bc_config.toml`（查找顺序见 [`crate::config`]）读取：
+//!
+//! ```toml
+//! station_code = "4401000010003"
+//! unit = "μg/m³"                 # 模板的浓度单位（默认 ng/m³）
+//!
+//! [codes]                        # 键为 BC、UVPM，内置为空
+//! "BC" = "a34xxx"
+//! ```
+
+use chrono::TimeDelta;
+
+use crate::cli::GlobalArgs;
+use crate::error::Result;
+use crate::instrument::{self, ColumnDef, Derive, Instrument, InstrumentArgs};
+use crate::units::Unit;
+
+/// 黑碳仪的内置定义。因子编码因平台而异，须在配置文件的 `[codes]` 中给出。
+pub const INSTRUMENT: Instrument = Instrument {
+    name: "bc",
+    title: "BC",
+    config_file: "bc_config.toml",
+    columns: &[
+        ColumnDef {
+            name: "BC",
+            code: "",
+            headers: &["BC6(ng/m³)", "BC6"],
+            required: true,
+            derive: None,
+        },
+        ColumnDef {
+            name: "UVPM",
+            code: "",
+            headers: &[],
+            required: false,
+            derive: Some(Derive::Difference("BC1", "BC6")),
+        },
+    ],
+    source_unit: Unit::NgM3,
+    unit: Unit::NgM3,
+    interval: TimeDelta::minutes(1),
+    resample: Some(TimeDelta::hours(1)),
+};
+
+pub fn execute(args: &InstrumentArgs, global: &GlobalArgs) -> Result<()> {
+    instrument::execute(&INSTRUMENT, args, global)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+    use std::fs;
+
+    use super::*;
+    use crate::instrument::{InstrumentOptions, transform};
+    use crate::proton::{self, CsvEncoding};
+    use tempfile::tempdir;
+
+    /// AE33 导出样例：01 时 60 分钟齐全，02 时只有 30 分钟。
+    fn ae33_csv() -> String {
+        let mut csv = "Date(yyyy/MM/dd);Time(hh:mm:ss);BC1;BC6;Status\n".to_string();
+        for minute in 0..90 {
+            let (hour, minute) = (1 + minute / 60, minute % 60);
+            let bc6 = if hour == 1 { 1000 + minute } else { 2000 };
+            writeln!(
+                csv,
+                "2026/01/05;{hour:02}:{minute:02}:00;{};{bc6};0",
+                bc6 + 500
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    #[test]
+    fn averages_minutes_to_hours() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("AE33_20260105.csv");
+        fs::write(&input, ae33_csv())?;
+
+        let source = proton::read_source(&input, b';', CsvEncoding::Auto)?;
+        let options = InstrumentOptions::from_toml_str(&INSTRUMENT, "unit = \"μg/m³\"\n")?;
+        let out = transform(&source, &options)?;
+
+        assert_eq!(out.report.source_rows, 90);
+        assert_eq!(out.report.rows, 2);
+        assert_eq!(out.report.computed, ["UVPM"]);
+        assert_eq!(
+            out.table,
+            [
+                ["时间", "BC", "UVPM"],
+                ["2026-01-05 01:00:00", "1.0295", "0.5"],
+                ["2026-01-05 02:00:00", "", ""],
+            ]
+        );
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("A3"), "BC");
+        assert_eq!(sheet.get_value("C3"), "UVPM");
+        assert_eq!(sheet.get_value("B5"), "μg/m³");
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_minutes_without_resampling() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("AE33_20260105.csv");
+        fs::write(&input, ae33_csv())?;
+
+        let source = proton::read_source(&input, b';', CsvEncoding::Auto)?;
+        let options = InstrumentOptions::from_toml_str(
+            &INSTRUMENT,
+            "resample = \"none\"\nmin_capture = 50\n",
+        )?;
+        let out = transform(&source, &options)?;
+        assert_eq!(out.report.rows, 90);
+        assert_eq!(out.table[1], ["2026-01-05 01:00:00", "1000", "500"]);
+
+        let options = InstrumentOptions::from_toml_str(&INSTRUMENT, "min_capture = 50\n")?;
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[2], ["2026-01-05 02:00:00", "2000", "500"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,598 @@
+//! dttools 统一命令行：`dttools <子命令> [参数]`。
+
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+use tracing::{info, warn};
+
+use crate::error::{Context, Result, bail};
+use crate::i18n::{Lang, Message};
+use crate::{
+    batch, bc, config, diff, eemcg, instrument, metals, ocec, pivot, proton, report, resume,
+    sample, split, validate, workbook,
+};
+
+pub use crate::workbook::{OutputFormat, Writer};
+
+/// 各子命令共用的全局参数。
+#[derive(Debug, Clone, Default, Args)]
+pub struct GlobalArgs {
+    /// 输出文件路径（仅用于单个输入文件）
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_name = "FILE",
+        conflicts_with = "output_dir"
+    )]
+    pub output: Option<PathBuf>,
+
+    /// 输出目录（默认为当前目录），不存在时自动创建
+    #[arg(long, global = true, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// 输出工作簿格式
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Xlsx)]
+    pub format: OutputFormat,
+
+    /// xlsx 的写出方式：memory 在内存中生成整个工作簿后保存；streaming 逐行写出数据行，
+    /// 适合行数很多的 proton / merge 输出（仅支持 xlsx，不能与 --charts 同用）
+    #[arg(long, global = true, value_enum, default_value_t = Writer::Memory)]
+    pub writer: Writer,
+
+    /// 输出更详细的处理信息，包括每条规则匹配与单元格修改
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// 只输出错误信息
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// 另外把日志以 JSON Lines 格式追加写入该文件
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// 只处理、不写出文件
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// 同时把处理后的数据表导出为 UTF-8 CSV（与输出文件同名）
+    #[arg(long, global = true)]
+    pub also_csv: bool,
+
+    /// 导出 CSV 使用的分隔符，如 `,`（默认）、`;` 或 `tab`（导出为 .tsv）
+    #[arg(long, global = true, value_name = "CHAR", value_parser = parse_delimiter)]
+    pub csv_delimiter: Option<u8>,
+
+    /// 直接改写输入文件（先保留一份带时间戳的 .bak 备份），不生成 processed_ 文件
+    #[arg(long, global = true, conflicts_with_all = ["output", "output_dir"])]
+    pub in_place: bool,
+
+    /// 即使输入文件已由 dttools 处理过，也再次处理
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// 批量处理时无法打开的文件（仍在复制、文件损坏）的重试次数，等待时间按 2、4、8… 秒加倍
+    #[arg(long, global = true, value_name = "N", default_value_t = 0)]
+    pub open_retries: u32,
+
+    /// 批量处理时把重试后仍无法打开的文件移入同级的 quarantine/ 目录，并写出错误说明
+    #[arg(long, global = true)]
+    pub quarantine: bool,
+
+    /// 断点续传：跳过此前以相同规则/配置处理完成、内容未变且输出仍在的文件
+    #[arg(long, global = true)]
+    pub resume: bool,
+
+    /// 把每个单元格的变更（工作表、地址、原值、新值、规则、红色填充）写成 JSON 报告
+    #[arg(long, global = true, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// 结束时把运行摘要（状态、退出码、行数、变更数、警告数）以一行 JSON 写到标准输出，
+    /// 其余终端输出改写到标准错误
+    #[arg(long, global = true)]
+    pub json_summary: bool,
+
+    /// 常见错误、批量汇总与交互提示的语言（默认按 DTTOOLS_LANG、LC_ALL、LC_MESSAGES、LANG 判断）；
+    /// 其余输出仍为中文
+    #[arg(long, global = true, value_enum)]
+    pub lang: Option<Lang>,
+
+    /// 结束时等待按回车键再退出，便于查看结果（从资源管理器拖放文件启动时默认开启）
+    #[arg(long, global = true, conflicts_with = "no_pause")]
+    pub pause: bool,
+
+    /// 结束时不等待按键
+    #[arg(long, global = true)]
+    pub no_pause: bool,
+
+    /// 未指定输出位置时把输出写在输入文件旁边（从资源管理器启动时）
+    #[arg(skip)]
+    pub(crate) output_beside_input: bool,
+
+    /// 本次运行处理过的文件，用于运行摘要
+    #[arg(skip)]
+    pub(crate) processed: report::Recorder,
+
+    /// 为每个项目画一张时间序列图（PNG），写在输出文件旁边（proton 与仪器转换）
+    #[cfg(feature = "plots")]
+    #[arg(long, global = true)]
+    pub plots: bool,
+
+    #[command(flatten)]
+    pub archive: crate::archive::ArchiveArgs,
+
+    #[cfg(feature = "upload")]
+    #[command(flatten)]
+    pub upload: crate::upload::UploadArgs,
+
+    #[cfg(feature = "post")]
+    #[command(flatten)]
+    pub post: crate::post::PostArgs,
+
+    #[cfg(feature = "notify")]
+    #[command(flatten)]
+    pub notify: crate::notify::NotifyArgs,
+}
+
+impl GlobalArgs {
+    /// 批量处理中无法打开的文件的处理方式；试运行时不移动文件。
+    pub(crate) fn open_failures(&self) -> batch::OpenFailures {
+        batch::OpenFailures {
+            retries: self.open_retries,
+            quarantine: self.quarantine && !self.dry_run,
+        }
+    }
+
+    /// 批量处理的续传状态，状态文件位于第一个输入文件的输出目录（`--in-place` 或从资源管理器
+    /// 启动时为输入文件所在目录）。`settings` 为本次使用的规则或配置，改变后不再跳过。
+    pub(crate) fn resume_state(
+        &self,
+        tool: &str,
+        settings: &impl fmt::Debug,
+        files: &[PathBuf],
+    ) -> Result<resume::State> {
+        let dir = files
+            .first()
+            .map(|file| self.workbook_output_path(file))
+            .and_then(|output| output.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        let settings = workbook::fingerprint(&format!("{settings:?}"));
+        resume::State::load(&dir, tool, &settings, self.resume, !self.dry_run)
+    }
+
+    /// 检查输出参数是否适用于本次输入：`--output` 只能对应一个输入文件；
+    /// `--in-place` 要求输入文件的格式与输出格式相同。
+    pub(crate) fn check_inputs(&self, inputs: &batch::Inputs) -> Result<()> {
+        if self.output.is_some() && inputs.batch {
+            bail!("{}", Message::OutputNeedsSingleInput);
+        }
+        if self.in_place {
+            let extension = self.format.extension();
+            if let Some(file) = inputs
+                .files
+                .iter()
+                .find(|file| !batch::has_extension(file, &[extension]))
+            {
+                bail!(
+                    "{}",
+                    Message::InPlaceFormat
+                        .render(&[("extension", &extension), ("file", &file.display())])
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// 输入文件带有已处理标记时拒绝处理（避免规则被重复应用），除非指定了 `--force`。
+    /// CSV、xls 等格式不能带标记，按输出文件命名的这类输入只给出警告。
+    pub(crate) fn check_unprocessed(&self, input: &Path) -> Result<()> {
+        if self.force {
+            return Ok(());
+        }
+        if !workbook::carries_marker(input) && batch::is_processed_output(input) {
+            warn!(
+                "{} 看起来是处理过的输出，但此格式不能带已处理标记，无法确认是否会重复处理",
+                input.display()
+            );
+        }
+        if let Some(marker) = workbook::processed_marker(input)? {
+            let marker = format!("{}={marker}", workbook::PROCESSED_PROPERTY);
+            bail!(
+                "{}",
+                Message::AlreadyProcessed.render(&[("marker", &marker)])
+            );
+        }
+        Ok(())
+    }
+
+    /// 写出输出文件前的准备：创建输出目录；原地改写时先把原文件复制为
+    /// `<文件名>.<时间戳>.bak`。
+    pub(crate) fn prepare_output(&self, output: &Path) -> Result<()> {
+        ensure_parent_dir(output)?;
+        if self.in_place && output.exists() {
+            let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S").to_string();
+            let backup = backup_path(output, &timestamp);
+            std::fs::copy(output, &backup)
+                .with_context(|| format!("无法备份原文件: {}", output.display()))?;
+            info!("原文件已备份为: {}", backup.display());
+        }
+        Ok(())
+    }
+
+    /// 指定了 `--report` 时写出 JSON 变更报告（试运行时同样写出）。处理过的文件同时记入运行摘要。
+    pub(crate) fn write_report(&self, tool: &str, files: &[report::ProcessedFile]) -> Result<()> {
+        self.processed.record(files);
+        let Some(path) = &self.report else {
+            return Ok(());
+        };
+        ensure_parent_dir(path)?;
+        report::write_json(path, tool, self.dry_run, files)?;
+        info!("变更报告已写入: {}", path.display());
+        Ok(())
+    }
+
+    /// 非试运行时在输出目录（未指定时为第一个输出文件所在目录）的 `manifest.json` 中
+    /// 记录写出的每个文件及其 SHA-256；已有清单按源文件合并，不会被覆盖。
+    pub(crate) fn write_manifest(&self, tool: &str, files: &[report::ProcessedFile]) -> Result<()> {
+        if self.dry_run || files.is_empty() {
+            return Ok(());
+        }
+        let dir = match &self.output_dir {
+            Some(dir) => dir.as_path(),
+            None => files[0].output.parent().unwrap_or(Path::new("")),
+        };
+        let path = dir.join(report::MANIFEST_FILE);
+        report::write_manifest(&path, tool, files)?;
+        info!("清单已写入: {}", path.display());
+        Ok(())
+    }
+
+    /// 运行结束时调用：指定了 `--json-summary` 时把本次运行的摘要以一行 JSON 写到标准输出；
+    /// 指定了 `--notify-email` 时发送结果邮件。
+    pub(crate) fn finish(&self, tool: &str, result: &Result<()>) {
+        let files = self.processed.files();
+        let summary = report::Summary::new(tool, self.dry_run, &files, result);
+        #[cfg(feature = "notify")]
+        self.notify.send(&summary, &files, result);
+        if !self.json_summary {
+            return;
+        }
+        println!(
+            "{}",
+            serde_json::to_string(&summary).expect("summary is serializable")
+        );
+    }
+
+    /// 需要附加导出 CSV 时返回使用的分隔符。
+    pub(crate) fn csv_export(&self) -> Option<u8> {
+        self.also_csv.then(|| self.csv_delimiter.unwrap_or(b','))
+    }
+
+    /// 输入文件对应的输出工作簿路径，扩展名由 `--format` 决定。
+    pub(crate) fn workbook_output_path(&self, input: &Path) -> PathBuf {
+        self.output_path(input, self.format.extension())
+    }
+
+    /// 输入文件对应的输出路径：`--output` 指定的路径；`--in-place` 时为输入文件本身
+    /// （扩展名换为 `extension`）；否则为 `<output_dir>/processed_<文件名主干>.<extension>`，
+    /// 从资源管理器启动且未指定输出目录时写在输入文件旁边。
+    pub(crate) fn output_path(&self, input: &Path, extension: &str) -> PathBuf {
+        if let Some(output) = &self.output {
+            return output.clone();
+        }
+        if self.in_place {
+            return input.with_extension(extension);
+        }
+        let mut name = OsString::from(batch::OUTPUT_PREFIX);
+        name.push(input.file_stem().unwrap_or("output".as_ref()));
+        name.push(format!(".{extension}"));
+        match &self.output_dir {
+            Some(dir) => dir.join(name),
+            None if self.output_beside_input => input.with_file_name(name),
+            None => PathBuf::from(name),
+        }
+    }
+}
+
+/// 解析分隔符参数：单个 ASCII 字符，或 `tab`/`\t`。
+pub(crate) fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err(format!("分隔符必须是单个 ASCII 字符或 tab: {value}")),
+    }
+}
+
+/// 原地改写前的备份路径：`a.xlsx` → `a.xlsx.<timestamp>.bak`。
+fn backup_path(path: &Path, timestamp: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{timestamp}.bak"));
+    path.with_file_name(name)
+}
+
+/// 创建输出文件所在的目录（如果不存在）。
+pub(crate) fn ensure_parent_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.exists() => std::fs::create_dir_all(dir)
+            .with_context(|| format!("无法创建输出目录: {}", dir.display())),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "dttools", version, about = "环境监测数据 Excel 处理工具集")]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// VOCs/NMHC 工作表重命名与单元格修正
+    Eemcg(eemcg::EemcgArgs),
+    /// 离子色谱数据转换为上传模板
+    Proton(proton::ProtonArgs),
+    /// 把多个离子色谱导出文件合并为一份上传模板
+    Merge(proton::MergeArgs),
+    /// Sunset OC/EC 分析仪数据转换为上传模板
+    Ocec(instrument::InstrumentArgs),
+    /// 在线重金属分析仪（如 Xact）数据转换为上传模板
+    Metals(instrument::InstrumentArgs),
+    /// AE33 黑碳仪数据求小时均值并转换为上传模板
+    Bc(instrument::InstrumentArgs),
+    /// 按时间列把处理后的文件拆分为每天（或每月）一个文件
+    Split(split::SplitArgs),
+    /// 逐个单元格比较两个工作簿（差异工作簿写到 -o 指定的文件）
+    Diff(diff::DiffArgs),
+    /// 在长表（时间、项目、数值各一列）与宽表（每个项目一列）之间转换
+    Pivot(pivot::PivotArgs),
+    /// 检查上传文件的表头、因子编码、时间与数值是否符合模板
+    Validate(validate::ValidateArgs),
+    /// 生成样例输入工作簿，用于试用各个处理分支
+    GenSample(sample::SampleArgs),
+    /// 管理配置文件（init 生成带注释的 dttools.toml）
+    Config(config::ConfigArgs),
+    /// 监视目录，自动处理新导出的文件
+    #[cfg(feature = "watch")]
+    Watch(crate::watch::WatchArgs),
+    /// 以 HTTP 服务的方式按需处理上传的文件（POST /eemcg、/proton 等）
+    #[cfg(feature = "serve")]
+    Serve(crate::serve::ServeArgs),
+}
+
+impl Command {
+    /// 子命令名称，用于运行摘要。
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Eemcg(_) => "eemcg",
+            Self::Proton(_) => "proton",
+            Self::Merge(_) => "merge",
+            Self::Ocec(_) => "ocec",
+            Self::Metals(_) => "metals",
+            Self::Bc(_) => "bc",
+            Self::Split(_) => "split",
+            Self::Diff(_) => "diff",
+            Self::Pivot(_) => "pivot",
+            Self::Validate(_) => "validate",
+            Self::GenSample(_) => "gen-sample",
+            Self::Config(_) => "config",
+            #[cfg(feature = "watch")]
+            Self::Watch(_) => "watch",
+            #[cfg(feature = "serve")]
+            Self::Serve(_) => "serve",
+        }
+    }
+}
+
+pub fn run(args: impl IntoIterator<Item = OsString>) -> Result<()> {
+    let mut cli: Cli = parse(args);
+    crate::console::init(&mut cli.global);
+    let global = &cli.global;
+    let result = crate::logging::init(global)
+        .and_then(|()| check_writer(cli.command.name(), streams(&cli.command), global))
+        .and_then(|()| match &cli.command {
+            Command::Eemcg(args) => eemcg::execute(args, global),
+            Command::Proton(args) => proton::execute(args, global),
+            Command::Merge(args) => proton::execute_merge(args, global),
+            Command::Ocec(args) => ocec::execute(args, global),
+            Command::Metals(args) => metals::execute(args, global),
+            Command::Bc(args) => bc::execute(args, global),
+            Command::Split(args) => split::execute(args, global),
+            Command::Diff(args) => diff::execute(args, global),
+            Command::Pivot(args) => pivot::execute(args, global),
+            Command::Validate(args) => validate::execute(args, global),
+            Command::GenSample(args) => sample::execute(args, global),
+            Command::Config(args) => config::execute(args, global),
+            #[cfg(feature = "watch")]
+            Command::Watch(args) => crate::watch::execute(args, global),
+            #[cfg(feature = "serve")]
+            Command::Serve(args) => crate::serve::execute(args, global),
+        });
+    global.finish(cli.command.name(), &result);
+    result
+}
+
+/// 命令能否流式写出：proton、merge 及转发给 proton 的 watch（转发给 eemcg 时不能）。
+fn streams(command: &Command) -> bool {
+    match command {
+        Command::Proton(_) | Command::Merge(_) => true,
+        #[cfg(feature = "watch")]
+        Command::Watch(args) => matches!(args.tool, crate::watch::WatchTool::Proton(_)),
+        _ => false,
+    }
+}
+
+/// `--writer streaming` 只用于 xlsx 输出，且只有 `supported` 的命令 `name` 可用。
+pub(crate) fn check_writer(name: &str, supported: bool, global: &GlobalArgs) -> Result<()> {
+    if global.writer == Writer::Memory {
+        return Ok(());
+    }
+    if global.format != OutputFormat::Xlsx {
+        bail!("--writer streaming 只支持 --format xlsx");
+    }
+    if !supported {
+        bail!("{name} 不支持 --writer streaming，仅 proton、merge 与 watch proton 可用");
+    }
+    Ok(())
+}
+
+/// 解析命令行参数。参数有误时以退出码 1 结束，而不是 clap 默认的 2
+/// （2 表示校验未通过，见 [`crate::error::VALIDATION_EXIT_CODE`]）。
+pub(crate) fn parse<P: Parser>(args: impl IntoIterator<Item = OsString>) -> P {
+    P::try_parse_from(args).unwrap_or_else(|err| {
+        let _ = err.print();
+        if err.use_stderr() {
+            crate::console::pause_on_usage_error();
+        }
+        std::process::exit(if err.use_stderr() { 1 } else { 0 })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn global_flags_after_subcommand() {
+        let cli = Cli::parse_from(["dttools", "eemcg", "a.xlsx", "--output-dir", "out", "-v"]);
+        assert!(cli.global.verbose);
+        assert_eq!(
+            cli.global.output_path(Path::new("dir/a.xlsx"), "xlsx"),
+            Path::new("out").join("processed_a.xlsx")
+        );
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn only_watch_proton_streams() {
+        let watch = |tool: &str| {
+            Cli::parse_from(["dttools", "watch", "in", "--writer", "streaming", tool]).command
+        };
+        assert!(streams(&watch("proton")));
+        assert!(!streams(&watch("eemcg")));
+    }
+
+    #[test]
+    fn dropped_files_write_beside_input() {
+        let mut cli = Cli::parse_from(["dttools", "proton", "D:/站点数据/3月 离子.xlsx"]);
+        let input = Path::new("D:/站点数据/3月 离子.xlsx");
+        assert_eq!(
+            cli.global.output_path(input, "xlsx"),
+            Path::new("processed_3月 离子.xlsx")
+        );
+        cli.global.output_beside_input = true;
+        assert_eq!(
+            cli.global.output_path(input, "xlsx"),
+            Path::new("D:/站点数据/processed_3月 离子.xlsx")
+        );
+        assert!(
+            !Cli::parse_from(["dttools", "proton", "a.xlsx", "--no-pause"])
+                .global
+                .pause
+        );
+        assert!(
+            Cli::try_parse_from(["dttools", "eemcg", "a.xlsx", "--pause", "--no-pause"]).is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_name_keeps_non_utf8_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let input = Path::new(std::ffi::OsStr::from_bytes(b"\xd5\xbe\xb5\xe3.xlsx"));
+        let output = GlobalArgs::default().output_path(input, "xlsx");
+        assert_eq!(
+            output.as_os_str().as_bytes(),
+            b"processed_\xd5\xbe\xb5\xe3.xlsx"
+        );
+    }
+
+    #[test]
+    fn output_overrides_name_and_rejects_batch() {
+        let cli = Cli::parse_from(["dttools", "proton", "a.xlsx", "-o", "up/today.xlsx"]);
+        assert_eq!(
+            cli.global.output_path(Path::new("a.xlsx"), "xlsx"),
+            Path::new("up/today.xlsx")
+        );
+        let inputs = batch::Inputs {
+            files: vec![PathBuf::from("a.xlsx"), PathBuf::from("b.xlsx")],
+            batch: true,
+        };
+        assert!(cli.global.check_inputs(&inputs).is_err());
+    }
+
+    #[test]
+    fn in_place_rewrites_input_and_keeps_backup() -> Result<()> {
+        let cli = Cli::parse_from(["dttools", "eemcg", "data/a.xlsx", "--in-place"]);
+        let input = Path::new("data/a.xlsx");
+        assert_eq!(cli.global.workbook_output_path(input), input);
+        assert_eq!(
+            backup_path(input, "20260105143932"),
+            Path::new("data/a.xlsx.20260105143932.bak")
+        );
+        let inputs = batch::Inputs {
+            files: vec![PathBuf::from("a.xlsx"), PathBuf::from("b.xls")],
+            batch: true,
+        };
+        assert!(cli.global.check_inputs(&inputs).is_err());
+        assert!(
+            Cli::try_parse_from(["dttools", "eemcg", "a.xlsx", "--in-place", "-o", "b.xlsx"])
+                .is_err()
+        );
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("a.xlsx");
+        std::fs::write(&file, "original")?;
+        cli.global.prepare_output(&file)?;
+        let backups: Vec<_> = std::fs::read_dir(dir.path())?
+            .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(backups.len(), 2);
+        let backup = backups.iter().find(|name| name.ends_with(".bak")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(backup))?,
+            "original"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merge_output_is_positional() {
+        let cli = Cli::parse_from(["dttools", "merge", "month.xlsx", "d1.xlsx", "d2.csv"]);
+        assert!(cli.global.output.is_none());
+        let Command::Merge(args) = cli.command else {
+            panic!("expected merge");
+        };
+        assert_eq!(args.output, Path::new("month.xlsx"));
+        assert_eq!(args.proton.inputs.len(), 2);
+    }
+
+    #[test]
+    fn delimiter_argument() {
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert_eq!(parse_delimiter(";"), Ok(b';'));
+        assert!(parse_delimiter(";;").is_err());
+    }
+
+    #[test]
+    fn creates_missing_output_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let out = dir.path().join("a/b/processed_x.xlsx");
+        ensure_parent_dir(&out)?;
+        assert!(dir.path().join("a/b").is_dir());
+        Ok(())
+    }
+}
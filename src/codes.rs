@@ -0,0 +1,230 @@
+//! 国家平台的污染物因子编码表：气态污染物（a21xxx）、颗粒物组分（a06xxx）与挥发性有机物
+//! （a24xxx 烷烃、烯烃与炔烃，a25xxx 芳香烃）。
+//!
+//! 用于按名称查找因子编码（proton 模板第 4 行的内置编码）、按编码反查名称（检查配置与上传文件中
+//! 编码与监测项目是否对应），以及 eemcg 缺测值改写时 `-999#` 后的编码。名称按名称词典
+//! （[`crate::species`]）统一后比较，名称后的括号单位忽略。
+
+use crate::species::{self, Dictionary};
+
+/// 一个监测因子。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Factor {
+    pub code: &'static str,
+    /// 标准名称，与名称词典一致
+    pub name: &'static str,
+    /// 该因子缺测时写在 `-999#` 后的编码；`None` 表示没有规定
+    pub missing: Option<&'static str>,
+}
+
+const fn factor(code: &'static str, name: &'static str) -> Factor {
+    Factor {
+        code,
+        name,
+        missing: None,
+    }
+}
+
+const fn with_missing(code: &'static str, name: &'static str, missing: &'static str) -> Factor {
+    Factor {
+        code,
+        name,
+        missing: Some(missing),
+    }
+}
+
+/// 气态污染物。
+pub const GASES: &[Factor] = &[
+    factor("a21001", "NH₃"),
+    factor("a21002", "NOx"),
+    factor("a21003", "NO"),
+    factor("a21004", "NO₂"),
+    factor("a21005", "CO"),
+    factor("a21024", "HCl"),
+    factor("a21026", "SO₂"),
+    factor("a21510", "HNO₂"),
+    factor("a21511", "HNO₃"),
+];
+
+/// 颗粒物组分：水溶性离子。
+pub const PARTICULATE: &[Factor] = &[
+    factor("a06005", "SO₄²⁻"),
+    factor("a06006", "NO₃⁻"),
+    factor("a06008", "Cl⁻"),
+    factor("a06009", "NH₄⁺"),
+    factor("a06010", "Ca²⁺"),
+    factor("a06011", "Mg²⁺"),
+    factor("a06012", "Na⁺"),
+    factor("a06013", "K⁺"),
+    factor("a06019", "NO₂⁻"),
+];
+
+/// 挥发性有机物与总烃、甲烷、非甲烷总烃。
+pub const VOCS: &[Factor] = &[
+    factor("a05002", "甲烷"),
+    factor("a24087", "总烃"),
+    factor("a24088", "非甲烷总烃"),
+    factor("a24001", "乙烷"),
+    factor("a24002", "乙烯"),
+    factor("a24003", "丙烷"),
+    factor("a24004", "丙烯"),
+    with_missing("a24514", "异丁烷", "a24041"),
+    factor("a24006", "正丁烷"),
+    factor("a24008", "反-2-丁烯"),
+    factor("a24009", "1-丁烯"),
+    factor("a24010", "顺-2-丁烯"),
+    with_missing("a24011", "乙炔", "a24537"),
+    factor("a24012", "环戊烷"),
+    factor("a24013", "异戊烷"),
+    with_missing("a24510", "正戊烷", "a24504"),
+    factor("a24015", "反-2-戊烯"),
+    factor("a24016", "1-戊烯"),
+    factor("a24017", "顺-2-戊烯"),
+    factor("a24018", "2,2-二甲基丁烷"),
+    factor("a24019", "2,3-二甲基丁烷"),
+    factor("a24020", "2-甲基戊烷"),
+    factor("a24021", "3-甲基戊烷"),
+    factor("a24022", "异戊二烯"),
+    factor("a24023", "正己烷"),
+    factor("a24024", "甲基环戊烷"),
+    factor("a24025", "2,4-二甲基戊烷"),
+    factor("a24027", "环己烷"),
+    factor("a24028", "2-甲基己烷"),
+    factor("a24029", "2,3-二甲基戊烷"),
+    factor("a24030", "3-甲基己烷"),
+    factor("a24031", "2,2,4-三甲基戊烷"),
+    factor("a24032", "正庚烷"),
+    factor("a24033", "甲基环己烷"),
+    factor("a24034", "2,3,4-三甲基戊烷"),
+    factor("a24036", "2-甲基庚烷"),
+    factor("a24037", "3-甲基庚烷"),
+    factor("a24038", "正辛烷"),
+    factor("a24043", "正壬烷"),
+    factor("a25002", "苯"),
+    factor("a25003", "甲苯"),
+    factor("a25004", "乙苯"),
+    factor("a25005", "间/对-二甲苯"),
+    factor("a25006", "苯乙烯"),
+    factor("a25007", "邻-二甲苯"),
+    factor("a25008", "异丙苯"),
+    factor("a25009", "正丙苯"),
+    factor("a25010", "间乙基甲苯"),
+    factor("a25011", "对乙基甲苯"),
+    with_missing("a25014", "1,3,5-三甲苯", "a25501"),
+];
+
+/// 全部编码表。
+pub const TABLES: [&[Factor]; 3] = [GASES, PARTICULATE, VOCS];
+
+/// 所有登记的因子。
+pub fn factors() -> impl Iterator<Item = &'static Factor> {
+    TABLES.into_iter().flatten()
+}
+
+/// 按编码查找。
+pub fn lookup(code: &str) -> Option<&'static Factor> {
+    let code = code.trim();
+    factors().find(|factor| factor.code == code)
+}
+
+/// 按名称查找；名称先按内置名称词典统一，名称后的括号单位（如 `总烃(ppbv)`）忽略。
+pub fn by_name(name: &str) -> Option<&'static Factor> {
+    by_name_in(name, Dictionary::builtin())
+}
+
+/// 同 [`by_name`]，名称按 `dictionary`（如加入了配置 `[species]` 的词典）统一。
+pub fn by_name_in(name: &str, dictionary: &Dictionary) -> Option<&'static Factor> {
+    let find = |name: &str| {
+        let key = species::normalize(dictionary.lookup(name).unwrap_or(name));
+        factors().find(|factor| species::normalize(factor.name) == key)
+    };
+    find(name).or_else(|| find(species::split_unit(name)?.0))
+}
+
+/// `name`（按 `dictionary` 统一）已登记且其编码不是 `code` 时返回登记的因子；名称未登记时
+/// 不检查。
+pub fn mismatch(name: &str, code: &str, dictionary: &Dictionary) -> Option<&'static Factor> {
+    by_name_in(name, dictionary).filter(|factor| factor.code != code.trim())
+}
+
+/// 编码为 `code` 的因子缺测时写入的 `marker#编码`；编码表中没有规定时返回 `None`。
+pub fn missing_flag(code: &str, marker: &str) -> Option<String> {
+    let missing = lookup(code)?.missing?;
+    Some(crate::flags::join(marker, missing))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{bc, ocec};
+
+    #[test]
+    fn codes_and_names_are_unique() {
+        let mut codes = HashSet::new();
+        let mut names = HashSet::new();
+        for factor in factors() {
+            assert!(codes.insert(factor.code), "{}", factor.code);
+            assert!(
+                names.insert(species::normalize(factor.name)),
+                "{}",
+                factor.name
+            );
+        }
+    }
+
+    #[test]
+    fn looks_up_by_code_and_name() -> crate::error::Result<()> {
+        assert_eq!(lookup(" a06006 ").map(|f| f.name), Some("NO₃⁻"));
+        assert_eq!(lookup("a99999"), None);
+        assert_eq!(by_name("Nitrate").map(|f| f.code), Some("a06006"));
+        assert_eq!(by_name("间、对-二甲苯").map(|f| f.code), Some("a25005"));
+        assert_eq!(by_name("总烃(ppbv)").map(|f| f.code), Some("a24087"));
+        assert_eq!(by_name("Pb"), None);
+
+        let builtin = Dictionary::builtin();
+        assert_eq!(
+            mismatch("SO₄²⁻", "a06006", builtin).map(|f| f.code),
+            Some("a06005")
+        );
+        assert_eq!(mismatch("SO₄²⁻", "a06005", builtin), None);
+        assert_eq!(mismatch("Pb", "a06006", builtin), None);
+
+        // 配置的 [species] 写法也能查到
+        let configured = Dictionary::with_entries(
+            [("NO₃⁻".to_string(), vec!["站点硝酸根".to_string()])].into(),
+        )?;
+        assert_eq!(by_name("站点硝酸根"), None);
+        assert_eq!(
+            by_name_in("站点硝酸根", &configured).map(|f| f.code),
+            Some("a06006")
+        );
+
+        assert_eq!(
+            missing_flag("a24514", "-999").as_deref(),
+            Some("-999#a24041")
+        );
+        assert_eq!(missing_flag("a24001", "-999"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn instrument_codes_are_registered() {
+        for column in ocec::INSTRUMENT
+            .columns
+            .iter()
+            .chain(bc::INSTRUMENT.columns)
+        {
+            if !column.code.is_empty() {
+                let builtin = Dictionary::builtin();
+                assert_eq!(
+                    mismatch(column.name, column.code, builtin),
+                    None,
+                    "{}",
+                    column.name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! 配置文件的查找与 `dttools config` 子命令。
+//!
+//! 各工具的配置（`proton_config.toml`、`rules.toml`、`<子命令>_config.toml`）按以下顺序查找，
+//! 使用第一个找到的：
+//!
+//! 1. 命令行参数（`--config`、`--rules`）指定的文件，指定时不再查找其他位置；
+//! 2. 环境变量 `DTTOOLS_CONFIG_DIR` 指定的目录；
+//! 3. 工作目录；
+//! 4. 程序所在的目录；
+//! 5. 用户配置目录：Windows 为 `%APPDATA%\dttools`，其他系统为 `$XDG_CONFIG_HOME/dttools`
+//!    （默认 `~/.config/dttools`）。
+//!
+//! 每个目录中先找该工具自己的文件，再找合并配置 `dttools.toml` 中对应的小节（`[proton]`、
+//! `[eemcg]`、`[ocec]`、`[metals]`、`[bc]`，写法与各自的文件相同）。都找不到时使用内置值。
+//! `dttools config init` 生成带注释的 `dttools.toml`，`dttools config check` 检查配置并列出
+//! 各个问题所在的行（见 [`check`]）。
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
+use tracing::{debug, info};
+
+use crate::cli::{self, GlobalArgs};
+use crate::error::{Context, DtToolsError, Result, bail, config_error};
+use crate::instrument::Instrument;
+use crate::{bc, eemcg, metals, ocec, proton};
+
+pub mod check;
+
+/// 指定查找目录的环境变量。
+pub const CONFIG_DIR_ENV: &str = "DTTOOLS_CONFIG_DIR";
+
+/// 合并配置的文件名。
+pub const COMBINED_FILE: &str = "dttools.toml";
+
+/// `dttools config init` 写出的内容。
+const SCAFFOLD: &str = include_str!("../dttools.example.toml");
+
+/// 有配置文件的工具。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Tool {
+    Proton,
+    Eemcg,
+    Ocec,
+    Metals,
+    Bc,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 5] = [
+        Self::Proton,
+        Self::Eemcg,
+        Self::Ocec,
+        Self::Metals,
+        Self::Bc,
+    ];
+
+    /// 合并配置中的小节名。
+    pub fn section(self) -> &'static str {
+        match self {
+            Self::Proton => "proton",
+            Self::Eemcg => "eemcg",
+            Self::Ocec => "ocec",
+            Self::Metals => "metals",
+            Self::Bc => "bc",
+        }
+    }
+
+    /// 工具自己的配置文件名。
+    pub fn file_name(self) -> &'static str {
+        match self.instrument() {
+            Some(instrument) => instrument.config_file,
+            None if self == Self::Eemcg => eemcg::RULES_FILE,
+            None => proton::CONFIG_FILE,
+        }
+    }
+
+    pub(crate) fn instrument(self) -> Option<&'static Instrument> {
+        match self {
+            Self::Ocec => Some(&ocec::INSTRUMENT),
+            Self::Metals => Some(&metals::INSTRUMENT),
+            Self::Bc => Some(&bc::INSTRUMENT),
+            Self::Proton | Self::Eemcg => None,
+        }
+    }
+
+    /// 按查找顺序找到的配置。
+    pub fn discover(self) -> Result<Option<Found>> {
+        discover(None, self.file_name(), self.section())
+    }
+}
+
+/// 找到的配置。
+#[derive(Debug, Clone)]
+pub struct Found {
+    /// 配置文件路径
+    pub path: PathBuf,
+    /// 来自合并配置时为小节名
+    pub section: Option<String>,
+    /// 文件内容
+    pub content: String,
+}
+
+impl Found {
+    /// 把配置（合并配置时为其中的小节）解析为 `T`。
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        let Some(section) = &self.section else {
+            return Ok(toml::from_str(&self.content)?);
+        };
+        let mut table: toml::Table = toml::from_str(&self.content)?;
+        let value = table
+            .remove(section)
+            .ok_or_else(|| config_error!("缺少 [{section}] 小节"))?;
+        Ok(T::deserialize(value)?)
+    }
+}
+
+/// 配置中的相对路径（如 `template`、`calibration`）按配置文件 `config` 所在目录解析，
+/// 而不是工作目录。
+pub(crate) fn resolve_path(config: &Path, path: &Path) -> PathBuf {
+    match config.parent() {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl fmt::Display for Found {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(section) = &self.section {
+            write!(f, " [{section}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// 读取 `path`，文件不存在时返回 `None`。
+pub fn read_file(path: &Path) -> Result<Option<Found>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("无法读取配置文件: {}", path.display()))?;
+    Ok(Some(Found {
+        path: path.to_path_buf(),
+        section: None,
+        content,
+    }))
+}
+
+/// 查找 `tool` 的配置：`explicit` 为命令行指定的文件；否则按查找顺序在各目录中找 `file_name`
+/// 或 `dttools.toml` 的 `[tool]` 小节。都找不到时返回 `None`。
+pub fn discover(explicit: Option<&Path>, file_name: &str, tool: &str) -> Result<Option<Found>> {
+    let found = match explicit {
+        Some(path) => read_file(path)?,
+        None => discover_in(&search_dirs(), file_name, tool)?,
+    };
+    match &found {
+        Some(found) => debug!("使用配置: {found}"),
+        None => debug!("没有找到 {file_name} 或 {COMBINED_FILE} 的 [{tool}]，使用内置配置"),
+    }
+    Ok(found)
+}
+
+fn discover_in(dirs: &[PathBuf], file_name: &str, tool: &str) -> Result<Option<Found>> {
+    for dir in dirs {
+        if let Some(found) = read_file(&dir.join(file_name))? {
+            return Ok(Some(found));
+        }
+        if let Some(found) = read_section(&dir.join(COMBINED_FILE), tool)? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+/// 合并配置 `path` 中的 `[tool]` 小节；文件或小节不存在时返回 `None`。
+fn read_section(path: &Path, tool: &str) -> Result<Option<Found>> {
+    let Some(mut found) = read_file(path)? else {
+        return Ok(None);
+    };
+    let table: toml::Table = toml::from_str(&found.content)
+        .with_context(|| format!("配置文件格式错误: {}", path.display()))?;
+    match table.get(tool) {
+        None => Ok(None),
+        Some(toml::Value::Table(_)) => {
+            found.section = Some(tool.to_string());
+            Ok(Some(found))
+        }
+        Some(_) => Err(config_error!("{}: [{tool}] 必须是一个小节", path.display())),
+    }
+}
+
+/// 同一目录中的其他文件（如 `proton_config.txt`）按同样的顺序查找，返回第一个存在的路径。
+pub(crate) fn find_file(file_name: &str) -> Option<PathBuf> {
+    search_dirs()
+        .into_iter()
+        .map(|dir| dir.join(file_name))
+        .find(|path| path.exists())
+}
+
+/// 查找配置的目录，按优先级排列；工作目录为空路径。
+pub fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::var_os(CONFIG_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs.push(PathBuf::new());
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(dir);
+    }
+    if let Some(dir) = user_config_dir() {
+        dirs.push(dir);
+    }
+    dirs
+}
+
+/// 用户配置目录：Windows 为 `%APPDATA%\dttools`，其他系统为 `$XDG_CONFIG_HOME/dttools`
+/// 或 `~/.config/dttools`。
+pub fn user_config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    base.map(|dir| dir.join("dttools"))
+}
+
+/// `dttools config` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ConfigCommand {
+    /// 生成带注释的合并配置文件 dttools.toml
+    Init(InitArgs),
+    /// 检查配置，列出各个问题及其所在的行
+    Check(check::CheckArgs),
+}
+
+/// `dttools config init` 的参数。已有文件时需要 `--force` 才会覆盖。
+#[derive(Debug, Clone, Args)]
+pub struct InitArgs {
+    /// 写入的文件（默认为工作目录下的 dttools.toml）
+    #[arg(value_name = "FILE", conflicts_with = "user")]
+    pub path: Option<PathBuf>,
+
+    /// 写入用户配置目录（Windows 为 %APPDATA%\dttools，其他系统为 ~/.config/dttools）
+    #[arg(long)]
+    pub user: bool,
+}
+
+pub fn execute(args: &ConfigArgs, global: &GlobalArgs) -> Result<()> {
+    match &args.command {
+        ConfigCommand::Init(args) => init(args, global),
+        ConfigCommand::Check(args) => check::execute(args, global),
+    }
+}
+
+fn init(args: &InitArgs, global: &GlobalArgs) -> Result<()> {
+    let path = match (&args.path, args.user) {
+        (Some(path), _) => path.clone(),
+        (None, true) => user_config_dir()
+            .ok_or_else(|| config_error!("无法确定用户配置目录"))?
+            .join(COMBINED_FILE),
+        (None, false) => PathBuf::from(COMBINED_FILE),
+    };
+    if path.exists() && !global.force {
+        bail!("文件已存在: {}（覆盖请加 --force）", path.display());
+    }
+    if global.dry_run {
+        info!("[试运行] 将生成配置文件: {}", path.display());
+        return Ok(());
+    }
+    cli::ensure_parent_dir(&path)?;
+    fs::write(&path, SCAFFOLD).map_err(|err| DtToolsError::write(&path, err))?;
+    info!("已生成配置文件: {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instrument::InstrumentOptions;
+    use crate::proton::ProtonOptions;
+    use crate::{bc, eemcg, metals, ocec};
+
+    #[test]
+    fn searches_directories_in_order() -> Result<()> {
+        let first = tempfile::tempdir()?;
+        let second = tempfile::tempdir()?;
+        let dirs = [first.path().to_path_buf(), second.path().to_path_buf()];
+        assert!(discover_in(&dirs, "proton_config.toml", "proton")?.is_none());
+
+        fs::write(
+            second.path().join("proton_config.toml"),
+            "station_code = \"4401000010002\"",
+        )?;
+        let found = discover_in(&dirs, "proton_config.toml", "proton")?.unwrap();
+        assert_eq!(found.path, second.path().join("proton_config.toml"));
+
+        fs::write(
+            first.path().join(COMBINED_FILE),
+            "[eemcg]\n[proton]\nstation_code = \"4401000010001\"\n",
+        )?;
+        let found = discover_in(&dirs, "proton_config.toml", "proton")?.unwrap();
+        assert_eq!(found.section.as_deref(), Some("proton"));
+        assert_eq!(
+            found.to_string(),
+            format!("{} [proton]", first.path().join(COMBINED_FILE).display())
+        );
+        assert_eq!(
+            ProtonOptions::from_found(Some(found))?.station_code,
+            "4401000010001"
+        );
+
+        // 同一目录中工具自己的文件优先于合并配置的小节
+        fs::write(first.path().join("proton_config.toml"), "")?;
+        let found = discover_in(&dirs, "proton_config.toml", "proton")?.unwrap();
+        assert_eq!(found.section, None);
+
+        // 合并配置中没有该工具的小节时继续查找后面的目录
+        assert!(discover_in(&dirs, "ocec_config.toml", "ocec")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn scaffold_loads_for_every_tool() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(COMBINED_FILE);
+        let global = GlobalArgs::default();
+        init(
+            &InitArgs {
+                path: Some(path.clone()),
+                user: false,
+            },
+            &global,
+        )?;
+        let dirs = [dir.path().to_path_buf()];
+        let proton = discover_in(&dirs, "proton_config.toml", "proton")?;
+        ProtonOptions::from_found(proton)?;
+        let rules = discover_in(&dirs, eemcg::RULES_FILE, "eemcg")?;
+        eemcg::RuleSet::from_found(rules)?;
+        for instrument in [&ocec::INSTRUMENT, &metals::INSTRUMENT, &bc::INSTRUMENT] {
+            let found = discover_in(&dirs, instrument.config_file, instrument.name)?;
+            assert!(found.is_some(), "{}", instrument.name);
+            InstrumentOptions::from_found(instrument, found)?;
+        }
+
+        let err = init(
+            &InitArgs {
+                path: Some(path),
+                user: false,
+            },
+            &global,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        Ok(())
+    }
+}
@@ -0,0 +1,565 @@
+//! `dttools config check`：检查配置，一次列出所有问题及其所在的行。
+//!
+//! 检查使用各工具自己的配置解析。配置被拆成可以单独去掉的各项（小节中的键、`[codes]` 等表中的
+//! 一行、`[[replace]]` 等数组中的一个元素），二分查找解析出错的最短前缀，找出出错的项并报告其所在的
+//! 行（按 toml_edit 记下的位置）；去掉这一项后继续查找，直到其余各项一起解析没有错误为止，因此一个
+//! 错误不会掩盖后面的错误。
+//!
+//! 此外检查站点编码与因子编码（`[codes]`）的写法、重复的因子编码、与编码表不符的编码，以及合并配置中
+//! 未知的小节。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use clap::Args;
+use regex::Regex;
+use toml_edit::{ImDocument, Key, TableLike};
+use tracing::info;
+
+use super::{COMBINED_FILE, Found, Tool};
+use crate::cli::GlobalArgs;
+use crate::eemcg::RuleSet;
+use crate::error::{DtToolsError, Result, bail};
+use crate::instrument::InstrumentOptions;
+use crate::proton::ProtonOptions;
+
+/// 因子编码的写法：一个小写字母加 5 位数字，如 a06006。
+static FACTOR_CODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z]\d{5}$").expect("valid factor code regex"));
+
+/// `dttools config check` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct CheckArgs {
+    /// 要检查的配置文件（默认检查各工具按查找顺序找到的配置）
+    #[arg(value_name = "FILE")]
+    pub files: Vec<PathBuf>,
+
+    /// 只检查该工具的配置；指定 FILE 时为 FILE 所属的工具（默认按文件名判断）
+    #[arg(long, value_enum)]
+    pub tool: Option<Tool>,
+}
+
+/// 配置中的一个问题。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// 所在的行（1 起计），无法定位时为 `None`
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "第 {line} 行: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// 检查时可以单独去掉的一项。
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    /// 小节中的键
+    Key(String),
+    /// 表中的一行，如 `[codes]` 中的 `"NO₃⁻" = "a06006"`
+    Entry(String, String),
+    /// 表数组中的一个元素，如第 2 个 `[[replace]]`
+    Element(String, usize),
+}
+
+/// 检查 `tool` 的一份配置（合并配置时为其中的小节）。
+pub fn check(found: &Found, tool: Tool) -> Vec<Finding> {
+    let document: toml::Table = match toml::from_str(&found.content) {
+        Ok(document) => document,
+        Err(err) => return vec![parse_error(&found.content, &err)],
+    };
+    let table = match &found.section {
+        Some(section) => match document.get(section) {
+            Some(toml::Value::Table(table)) => table.clone(),
+            _ => toml::Table::new(),
+        },
+        None => document,
+    };
+    let layout = Layout::new(&found.content);
+    let prefix: Vec<String> = found.section.iter().cloned().collect();
+    let locate = |item: &Item| layout.item_line(&prefix, item);
+
+    let mut findings = check_table(tool, &table, &found.path, locate);
+    if tool != Tool::Eemcg {
+        findings.extend(check_codes(&table, locate));
+    }
+    findings.sort_by_key(|finding| finding.line.unwrap_or(usize::MAX));
+    findings
+}
+
+/// 检查合并配置：未知的小节，以及 `tools` 中出现在文件里的各个小节。
+pub fn check_combined(found: &Found, tools: &[Tool]) -> Vec<Finding> {
+    let document: toml::Table = match toml::from_str(&found.content) {
+        Ok(document) => document,
+        Err(err) => return vec![parse_error(&found.content, &err)],
+    };
+    let mut findings = unknown_sections(&found.content, &document);
+    for tool in tools {
+        if document.contains_key(tool.section()) {
+            let section = Found {
+                section: Some(tool.section().to_string()),
+                ..found.clone()
+            };
+            findings.extend(check(&section, *tool));
+        }
+    }
+    findings.sort_by_key(|finding| finding.line.unwrap_or(usize::MAX));
+    findings
+}
+
+/// 逐项找出配置解析中的错误：二分查找解析失败的最短前缀，其中最后一项即为出错的项；
+/// 记下后去掉这一项，再查找下一个，直到其余各项一起解析没有错误。每个错误只需解析
+/// O(log n) 次。
+fn check_table(
+    tool: Tool,
+    table: &toml::Table,
+    path: &Path,
+    locate: impl Fn(&Item) -> Option<usize>,
+) -> Vec<Finding> {
+    let mut items = items(table);
+    let mut findings = Vec::new();
+    while let Err(message) = parse(tool, &only(&items), path) {
+        let parses = |k: usize| parse(tool, &only(&items[..k]), path);
+        let lengths: Vec<usize> = (1..=items.len()).collect();
+        let index = lengths.partition_point(|&k| parses(k).is_ok());
+        let Some(Err(message)) = lengths.get(index).map(|&k| parses(k)) else {
+            findings.push(Finding {
+                line: None,
+                message,
+            });
+            break;
+        };
+        let (item, _) = items.remove(index);
+        findings.push(Finding {
+            line: locate(&item),
+            message,
+        });
+    }
+    findings
+}
+
+/// 用工具自己的配置解析检查 `table`；相对路径按配置文件 `path` 所在目录解析。
+fn parse(tool: Tool, table: &toml::Table, path: &Path) -> Result<(), String> {
+    let table = table.clone();
+    let result = match tool.instrument() {
+        Some(instrument) => InstrumentOptions::from_table(instrument, table, path).map(drop),
+        None if tool == Tool::Eemcg => RuleSet::from_table(table).map(drop),
+        None => ProtonOptions::from_table(table, path).map(drop),
+    };
+    result.map_err(|err| format!("{err:#}"))
+}
+
+/// 可以单独去掉的各项及其值：非空的表按行，表数组按元素，其余按键。
+fn items(table: &toml::Table) -> Vec<(Item, toml::Value)> {
+    let mut items = Vec::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(entries) if !entries.is_empty() => {
+                items.extend(entries.iter().map(|(entry, value)| {
+                    (Item::Entry(key.clone(), entry.clone()), value.clone())
+                }));
+            }
+            toml::Value::Array(elements)
+                if !elements.is_empty() && elements.iter().all(toml::Value::is_table) =>
+            {
+                items.extend(
+                    elements
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| (Item::Element(key.clone(), i), value.clone())),
+                );
+            }
+            _ => items.push((Item::Key(key.clone()), value.clone())),
+        }
+    }
+    items
+}
+
+/// 只含 `items` 中各项的表。
+fn only(items: &[(Item, toml::Value)]) -> toml::Table {
+    let mut table = toml::Table::new();
+    for (item, value) in items {
+        match item {
+            Item::Key(key) => {
+                table.insert(key.clone(), value.clone());
+            }
+            Item::Entry(key, entry) => {
+                if let toml::Value::Table(entries) = table
+                    .entry(key.clone())
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                {
+                    entries.insert(entry.clone(), value.clone());
+                }
+            }
+            Item::Element(key, _) => {
+                if let toml::Value::Array(elements) = table
+                    .entry(key.clone())
+                    .or_insert_with(|| toml::Value::Array(Vec::new()))
+                {
+                    elements.push(value.clone());
+                }
+            }
+        }
+    }
+    table
+}
+
+/// 站点编码与 `[codes]` 中因子编码的写法、重复的因子编码，以及与编码表（[`crate::codes`]）不符的编码。
+fn check_codes(table: &toml::Table, locate: impl Fn(&Item) -> Option<usize>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if let Some(toml::Value::String(code)) = table.get("station_code")
+        && !code.trim().chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        findings.push(Finding {
+            line: locate(&Item::Key("station_code".to_string())),
+            message: format!("站点编码只能包含字母和数字: {code:?}"),
+        });
+    }
+    let Some(toml::Value::Table(codes)) = table.get("codes") else {
+        return findings;
+    };
+    // 按在文件中出现的顺序检查，重复的编码报告在后出现的一行
+    let mut entries: Vec<(Option<usize>, &str, &str)> = codes
+        .iter()
+        .filter_map(|(species, code)| {
+            let line = locate(&Item::Entry("codes".to_string(), species.clone()));
+            Some((line, species.as_str(), code.as_str()?.trim()))
+        })
+        .collect();
+    entries.sort_by_key(|(line, _, _)| line.unwrap_or(usize::MAX));
+    let mut seen: BTreeMap<&str, &str> = BTreeMap::new();
+    for (line, species, code) in entries {
+        if !code.is_empty() && !FACTOR_CODE.is_match(code) {
+            findings.push(Finding {
+                line,
+                message: format!(
+                    "[codes] 中 {species} 的因子编码应为一个字母加 5 位数字（如 a06006）: {code:?}"
+                ),
+            });
+        } else if let Some(other) = seen.insert(code, species) {
+            findings.push(Finding {
+                line,
+                message: format!("[codes] 中 {species} 与 {other} 的因子编码相同: {code}"),
+            });
+        }
+    }
+    findings
+}
+
+/// 合并配置中不属于任何工具的小节（多半是拼写错误）。
+fn unknown_sections(content: &str, document: &toml::Table) -> Vec<Finding> {
+    let layout = Layout::new(content);
+    let known: Vec<&str> = Tool::ALL.iter().map(|tool| tool.section()).collect();
+    document
+        .keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .map(|key| Finding {
+            line: layout.item_line(&[], &Item::Key(key.clone())),
+            message: format!("未知的小节: [{key}]（可用的小节: {}）", known.join("、")),
+        })
+        .collect()
+}
+
+/// TOML 语法错误。
+fn parse_error(content: &str, err: &toml::de::Error) -> Finding {
+    Finding {
+        line: err.span().map(|span| line_of(content, span.start)),
+        message: err.message().trim().to_string(),
+    }
+}
+
+/// 原文中各项所在的行：按 toml_edit 解析时记下的位置定位，内联表、带引号或点号的键与表头都能定位。
+struct Layout<'a> {
+    content: &'a str,
+    document: Option<ImDocument<&'a str>>,
+}
+
+impl<'a> Layout<'a> {
+    fn new(content: &'a str) -> Self {
+        Self {
+            content,
+            document: ImDocument::parse(content).ok(),
+        }
+    }
+
+    /// 一项所在的行，`prefix` 为该工具的表（合并配置中的小节）。找不到这一项自己的位置时
+    /// 退回到所在的键或表头。
+    fn item_line(&self, prefix: &[String], item: &Item) -> Option<usize> {
+        let mut table: &dyn TableLike = self.document.as_ref()?.as_table();
+        for key in prefix {
+            table = table.get(key)?.as_table_like()?;
+        }
+        let (Item::Key(key) | Item::Entry(key, _) | Item::Element(key, _)) = item;
+        let value = table.get(key)?;
+        let span = match item {
+            Item::Key(_) => None,
+            Item::Entry(_, entry) => value
+                .as_table_like()
+                .and_then(|entries| entries.key(entry))
+                .and_then(Key::span),
+            Item::Element(_, i) => match value {
+                toml_edit::Item::ArrayOfTables(elements) => {
+                    elements.get(*i).and_then(toml_edit::Table::span)
+                }
+                toml_edit::Item::Value(toml_edit::Value::Array(elements)) => {
+                    elements.get(*i).and_then(toml_edit::Value::span)
+                }
+                _ => None,
+            },
+        };
+        let span = span
+            .or_else(|| value.as_table().and_then(toml_edit::Table::span))
+            .or_else(|| table.key(key).and_then(Key::span))
+            .or_else(|| value.span())?;
+        Some(line_of(self.content, span.start))
+    }
+}
+
+/// 原文中第 `offset` 个字节所在的行（1 起计）。
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+/// 按文件名判断配置属于哪个工具，如 `proton_config.site.toml` 为 proton。
+fn tool_for(path: &Path) -> Option<Tool> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    Tool::ALL.into_iter().find(|tool| {
+        let stem = tool.file_name().trim_end_matches(".toml");
+        name.starts_with(stem)
+    })
+}
+
+/// 是否为合并配置（`dttools.toml`，或以 `dttools` 开头的文件，如 `dttools.example.toml`）。
+fn is_combined(path: &Path) -> bool {
+    let stem = COMBINED_FILE.trim_end_matches(".toml");
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().to_lowercase().starts_with(stem))
+}
+
+pub fn execute(args: &CheckArgs, global: &GlobalArgs) -> Result<()> {
+    let tools: Vec<Tool> = match args.tool {
+        Some(tool) => vec![tool],
+        None => Tool::ALL.to_vec(),
+    };
+    let mut reports: Vec<(String, Vec<Finding>)> = Vec::new();
+    if args.files.is_empty() {
+        let mut combined: Vec<PathBuf> = Vec::new();
+        for tool in tools {
+            let Some(found) = tool.discover()? else {
+                info!(
+                    "没有找到 {} 或 {COMBINED_FILE} 的 [{}]，使用内置配置",
+                    tool.file_name(),
+                    tool.section()
+                );
+                continue;
+            };
+            let mut findings = Vec::new();
+            if found.section.is_some() && !combined.contains(&found.path) {
+                combined.push(found.path.clone());
+                if let Ok(document) = toml::from_str(&found.content) {
+                    findings.extend(unknown_sections(&found.content, &document));
+                }
+            }
+            findings.extend(check(&found, tool));
+            reports.push((found.to_string(), findings));
+        }
+    } else {
+        for path in &args.files {
+            let Some(found) = super::read_file(path)? else {
+                bail!("找不到配置文件: {}", path.display());
+            };
+            let findings = if is_combined(path) {
+                check_combined(&found, &tools)
+            } else {
+                let Some(tool) = args.tool.or_else(|| tool_for(path)) else {
+                    bail!(
+                        "无法判断 {} 是哪个工具的配置，请用 --tool 指定",
+                        path.display()
+                    );
+                };
+                check(&found, tool)
+            };
+            reports.push((found.to_string(), findings));
+        }
+    }
+
+    let mut failed = 0;
+    let mut total = 0;
+    for (name, findings) in &reports {
+        if findings.is_empty() {
+            info!("配置检查通过: {name}");
+            continue;
+        }
+        failed += 1;
+        total += findings.len();
+        if !global.quiet {
+            // 标准输出留给运行摘要时，问题列表写到标准错误
+            let mut out: Box<dyn Write> = if global.json_summary {
+                Box::new(io::stderr())
+            } else {
+                Box::new(io::stdout())
+            };
+            writeln!(out, "{name}: {} 个问题", findings.len())?;
+            for finding in findings {
+                writeln!(out, "  {finding}")?;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(DtToolsError::Validation {
+            files: failed,
+            findings: total,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(content: &str) -> Found {
+        Found {
+            path: PathBuf::from("site.toml"),
+            section: None,
+            content: content.to_string(),
+        }
+    }
+
+    fn lines(findings: &[Finding]) -> Vec<Option<usize>> {
+        findings.iter().map(|finding| finding.line).collect()
+    }
+
+    #[test]
+    fn reports_every_problem_with_its_line() {
+        let findings = check(
+            &found(
+                r#"station_code = "44 01"
+intervall = "1h"
+interval = "15m"
+flagged = "keep"
+
+[codes]
+"NO₃⁻" = "a0600"
+"SO₄²⁻" = "a06006"
+"XYZ" = "a06009"
+"NH₄⁺" = "a06006"
+"#,
+            ),
+            Tool::Proton,
+        );
+        assert_eq!(
+            lines(&findings),
+            [Some(1), Some(2), Some(4), Some(7), Some(9), Some(10)],
+            "{findings:#?}"
+        );
+        assert!(findings[0].message.contains("站点编码"));
+        assert!(findings[1].message.contains("intervall"));
+        assert!(findings[2].message.contains("keep"));
+        assert!(findings[3].message.contains("a0600"));
+        // 配置的编码即使与编码表不同也不报告，重复的编码仍报告
+        assert!(findings[4].message.contains("未知的监测项目: XYZ"));
+        assert!(findings[5].message.contains("SO₄²⁻"));
+
+        assert!(
+            check(
+                &found("interval = \"15m\"\nresample = \"1h\"\n"),
+                Tool::Proton
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn locates_problems_in_combined_sections() {
+        let content = r#"[protn]
+station_code = "4401000010003"
+
+[eemcg]
+pipeline = ["replace", "replace"]
+
+[[eemcg.replace]]
+find = "a"
+replace = "b"
+
+[[eemcg.replace]]
+find = ""
+replace = "c"
+
+[eemcg.brackets]
+protect = ["("]
+
+[ocec]
+decimals = "2"
+"#;
+        let findings = check_combined(&found(content), &Tool::ALL);
+        assert_eq!(
+            lines(&findings),
+            [Some(1), Some(5), Some(11), Some(16), Some(19)],
+            "{findings:#?}"
+        );
+        assert!(findings[0].message.contains("未知的小节: [protn]"));
+        assert!(findings[1].message.contains("重复"));
+        assert!(findings[2].message.contains("须指定 find 或 pattern 之一"));
+        assert!(findings[3].message.contains("正则表达式"));
+    }
+
+    #[test]
+    fn locates_inline_tables_and_dotted_keys() {
+        let findings = check(
+            &found(
+                r#"replace = [
+    { find = "a", replace = "b" },
+    { find = "", replace = "c" },
+]
+"#,
+            ),
+            Tool::Eemcg,
+        );
+        assert_eq!(lines(&findings), [Some(3)], "{findings:#?}");
+
+        let findings = check(
+            &found("station_code = \"4401000010003\"\ncodes.\"NO₃⁻\" = \"a0600\"\n"),
+            Tool::Proton,
+        );
+        assert_eq!(lines(&findings), [Some(2)], "{findings:#?}");
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_line() {
+        let findings = check(&found("station_code = \"1\"\n[codes\n"), Tool::Ocec);
+        assert_eq!(lines(&findings), [Some(2)]);
+        assert_eq!(
+            findings[0].to_string(),
+            format!("第 2 行: {}", findings[0].message)
+        );
+    }
+
+    #[test]
+    fn example_configs_pass() -> Result<()> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        for name in [
+            "proton_config.example.toml",
+            "rules.example.toml",
+            "ocec_config.example.toml",
+            "metals_config.example.toml",
+            "bc_config.example.toml",
+        ] {
+            let path = root.join(name);
+            let found = super::super::read_file(&path)?.unwrap();
+            let tool = tool_for(&path).unwrap();
+            assert_eq!(check(&found, tool), [], "{name}");
+        }
+        let found = super::super::read_file(&root.join("dttools.example.toml"))?.unwrap();
+        assert_eq!(check_combined(&found, &Tool::ALL), []);
+        Ok(())
+    }
+}
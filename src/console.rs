@@ -0,0 +1,101 @@
+//! 从资源管理器启动时的处理：把文件拖放到 `dtEEMCG.exe`/`dtproton.exe` 上运行时，
+//! 控制台窗口在程序结束后会立即关闭，看不到处理结果和错误信息。
+//!
+//! 这种情况下（Windows 上控制台只属于本进程）默认在退出前等待按回车键（`--pause`/`--no-pause`
+//! 可改变），且未指定 `--output`/`--output-dir` 时输出文件写在各输入文件旁边，
+//! 而不是不确定的当前目录。
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::cli::GlobalArgs;
+use crate::error::Result;
+use crate::i18n::Message;
+
+static PAUSE: AtomicBool = AtomicBool::new(false);
+
+/// 程序是否由资源管理器启动（拖放文件或双击），即控制台窗口只属于本进程。
+#[cfg(windows)]
+pub fn launched_from_explorer() -> bool {
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetConsoleProcessList(list: *mut u32, count: u32) -> u32;
+    }
+    let mut list = [0u32; 2];
+    // SAFETY: 缓冲区长度与传入的 count 一致，函数只写入这段缓冲区。
+    let count = unsafe { GetConsoleProcessList(list.as_mut_ptr(), list.len() as u32) };
+    count == 1
+}
+
+/// 程序是否由资源管理器启动（拖放文件或双击），即控制台窗口只属于本进程。
+#[cfg(not(windows))]
+pub fn launched_from_explorer() -> bool {
+    false
+}
+
+/// 按启动方式补全全局参数，并决定退出前是否等待按键。
+pub(crate) fn init(global: &mut GlobalArgs) {
+    let explorer = launched_from_explorer();
+    global.output_beside_input = explorer;
+    PAUSE.store(pause_enabled(global, explorer), Ordering::Relaxed);
+}
+
+fn pause_enabled(global: &GlobalArgs, explorer: bool) -> bool {
+    global.pause || (explorer && !global.no_pause)
+}
+
+/// 需要时提示并等待按回车键，让控制台窗口保持打开。标准输入不是终端时不等待。
+pub fn pause_if_requested() {
+    if PAUSE.load(Ordering::Relaxed) {
+        pause();
+    }
+}
+
+fn pause() {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return;
+    }
+    eprint!("{}", Message::PressEnter);
+    let _ = io::stderr().flush();
+    let _ = stdin.lock().read_line(&mut String::new());
+}
+
+/// 参数解析失败时的等待：此时还不知道 `--pause`，只按启动方式决定。
+pub(crate) fn pause_on_usage_error() {
+    if launched_from_explorer() {
+        pause();
+    }
+}
+
+/// 程序的出口：报告错误，需要时等待按键，然后以对应的退出码退出。
+pub fn exit(result: Result<()>) -> ! {
+    let code = match &result {
+        Ok(()) => 0,
+        Err(err) => {
+            crate::logging::report_error(&Message::ProcessingFailed.to_string(), err);
+            err.exit_code()
+        }
+    };
+    pause_if_requested();
+    std::process::exit(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pauses_by_default_only_when_launched_from_explorer() {
+        let mut global = GlobalArgs::default();
+        assert!(!pause_enabled(&global, false));
+        assert!(pause_enabled(&global, true));
+        global.no_pause = true;
+        assert!(!pause_enabled(&global, true));
+        global = GlobalArgs {
+            pause: true,
+            ..Default::default()
+        };
+        assert!(pause_enabled(&global, false));
+    }
+}
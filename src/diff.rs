@@ -0,0 +1,347 @@
+//! `dttools diff`：逐个工作表、逐个单元格比较两个工作簿的值，在终端列出差异，
+//! 或写出一个标出不同单元格的差异工作簿，用于核对 Rust 版与原 Go 工具的输出是否一致。
+
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use serde::Serialize;
+use tracing::info;
+use umya_spreadsheet::helper::coordinate::coordinate_from_index;
+use umya_spreadsheet::structs::PatternValues;
+use umya_spreadsheet::{Spreadsheet, Worksheet};
+
+use crate::cli::{self, GlobalArgs};
+use crate::error::{Context, Result};
+use crate::workbook;
+
+/// 差异工作簿中列出全部差异的工作表名称。
+pub const DIFF_SHEET: &str = "差异";
+
+const DIFF_SHEET_HEADERS: [&str; 4] = ["工作表", "单元格", "A 的值", "B 的值"];
+
+/// 差异工作簿中不同单元格的填充色（橙色，与处理时使用的红、黄、紫、灰区分）。
+const DIFF_FILL: &str = "ffffc000";
+
+/// `dttools diff` 的参数。差异工作簿写到 `-o/--output` 指定的文件。
+#[derive(Debug, Clone, Args)]
+pub struct DiffArgs {
+    /// 原工作簿（如 Go 工具的输出）
+    #[arg(value_name = "A")]
+    pub left: PathBuf,
+
+    /// 对比的工作簿（如 Rust 版的输出）
+    #[arg(value_name = "B")]
+    pub right: PathBuf,
+
+    /// 只比较指定名称的工作表，可重复使用
+    #[arg(long, value_name = "NAME")]
+    pub sheet: Vec<String>,
+
+    /// 两边都是数字时允许的最大绝对误差（默认 0，即数值相等；"1.5" 与 "1.50" 视为相同）
+    #[arg(long, value_name = "X", default_value_t = 0.0)]
+    pub tolerance: f64,
+}
+
+/// 一个值不同的单元格；某一边为空单元格时值为空字符串。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CellDiff {
+    pub sheet: String,
+    pub address: String,
+    pub left: String,
+    pub right: String,
+}
+
+/// 两个工作簿的比较结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WorkbookDiff {
+    /// 只在 A 中存在的工作表
+    pub only_left: Vec<String>,
+    /// 只在 B 中存在的工作表
+    pub only_right: Vec<String>,
+    pub cells: Vec<CellDiff>,
+}
+
+impl WorkbookDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_left.is_empty() && self.only_right.is_empty() && self.cells.is_empty()
+    }
+}
+
+/// 比较两个工作簿中同名工作表的单元格值（`sheets` 为空时比较全部工作表）。
+/// 差异按工作表（A 中的顺序）、行、列排列。
+pub fn compare(
+    left: &Spreadsheet,
+    right: &Spreadsheet,
+    sheets: &[String],
+    tolerance: f64,
+) -> WorkbookDiff {
+    let selected = |name: &str| sheets.is_empty() || sheets.iter().any(|s| s == name);
+    let mut diff = WorkbookDiff::default();
+    for sheet in left.get_sheet_collection() {
+        let name = sheet.get_name();
+        if !selected(name) {
+            continue;
+        }
+        match right.get_sheet_by_name(name) {
+            Some(other) => diff
+                .cells
+                .extend(compare_sheet(name, sheet, other, tolerance)),
+            None => diff.only_left.push(name.to_string()),
+        }
+    }
+    diff.only_right = right
+        .get_sheet_collection()
+        .iter()
+        .map(|sheet| sheet.get_name())
+        .filter(|name| selected(name) && left.get_sheet_by_name(name).is_none())
+        .map(str::to_string)
+        .collect();
+    diff
+}
+
+fn compare_sheet(name: &str, left: &Worksheet, right: &Worksheet, tolerance: f64) -> Vec<CellDiff> {
+    // (行, 列) 排序，差异按行输出
+    let cells: BTreeSet<(u32, u32)> = [left, right]
+        .into_iter()
+        .flat_map(|sheet| sheet.get_cell_collection())
+        .map(|cell| {
+            let coordinate = cell.get_coordinate();
+            (*coordinate.get_row_num(), *coordinate.get_col_num())
+        })
+        .collect();
+    cells
+        .into_iter()
+        .filter_map(|(row, col)| {
+            let a = left.get_value((col, row));
+            let b = right.get_value((col, row));
+            (!same_value(&a, &b, tolerance)).then(|| CellDiff {
+                sheet: name.to_string(),
+                address: coordinate_from_index(&col, &row),
+                left: a,
+                right: b,
+            })
+        })
+        .collect()
+}
+
+/// 文本相同，或两边都是数字且相差不超过 `tolerance`。
+fn same_value(a: &str, b: &str, tolerance: f64) -> bool {
+    if a == b {
+        return true;
+    }
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => (x - y).abs() <= tolerance,
+        _ => false,
+    }
+}
+
+/// 在终端列出差异：A 的值为红色，B 的值为绿色（输出不是终端时不带颜色）。
+fn print_diff(out: &mut impl Write, diff: &WorkbookDiff, color: bool) -> io::Result<()> {
+    let paint = |code: &str, text: String| {
+        if color {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text
+        }
+    };
+    for name in &diff.only_left {
+        writeln!(out, "{}", paint("31", format!("只在 A 中的工作表: {name}")))?;
+    }
+    for name in &diff.only_right {
+        writeln!(out, "{}", paint("32", format!("只在 B 中的工作表: {name}")))?;
+    }
+    for cell in &diff.cells {
+        writeln!(
+            out,
+            "{}: {} {}",
+            paint("1", format!("{}!{}", cell.sheet, cell.address)),
+            paint("31", format!("-{:?}", cell.left)),
+            paint("32", format!("+{:?}", cell.right)),
+        )?;
+    }
+    Ok(())
+}
+
+/// 差异工作簿：B 的副本，不同的单元格填充为橙色，并在最前面加上列出全部差异的“差异”工作表。
+fn diff_workbook(mut book: Spreadsheet, diff: &WorkbookDiff) -> Spreadsheet {
+    for cell in &diff.cells {
+        if let Some(sheet) = book.get_sheet_by_name_mut(&cell.sheet) {
+            let fill = sheet
+                .get_style_mut(cell.address.as_str())
+                .get_fill_mut()
+                .get_pattern_fill_mut();
+            fill.set_pattern_type(PatternValues::Solid);
+            fill.get_foreground_color_mut().set_argb(DIFF_FILL);
+            fill.get_background_color_mut().set_argb(DIFF_FILL);
+        }
+    }
+
+    let mut summary = Worksheet::default();
+    summary.set_name(DIFF_SHEET);
+    for (col, header) in DIFF_SHEET_HEADERS.iter().enumerate() {
+        summary.get_cell_mut((col as u32 + 1, 1)).set_value(*header);
+    }
+    let sheet_rows = diff
+        .only_left
+        .iter()
+        .map(|name| [name.as_str(), "", "（工作表）", ""])
+        .chain(
+            diff.only_right
+                .iter()
+                .map(|name| [name.as_str(), "", "", "（工作表）"]),
+        );
+    let cell_rows = diff.cells.iter().map(|cell| {
+        [
+            cell.sheet.as_str(),
+            cell.address.as_str(),
+            cell.left.as_str(),
+            cell.right.as_str(),
+        ]
+    });
+    for (i, values) in sheet_rows.chain(cell_rows).enumerate() {
+        for (col, value) in values.into_iter().enumerate() {
+            summary
+                .get_cell_mut((col as u32 + 1, i as u32 + 2))
+                .set_value_string(value);
+        }
+    }
+    let mut sheets = vec![summary];
+    // 再次比较差异工作簿时不保留原有的“差异”工作表
+    sheets.extend(
+        book.get_sheet_collection()
+            .iter()
+            .filter(|sheet| sheet.get_name() != DIFF_SHEET)
+            .cloned(),
+    );
+    let mut out = umya_spreadsheet::new_file_empty_worksheet();
+    for sheet in sheets {
+        out.add_sheet(sheet).expect("sheet names are unique");
+    }
+    out.set_active_sheet(0);
+    out
+}
+
+pub fn execute(args: &DiffArgs, global: &GlobalArgs) -> Result<()> {
+    let left = workbook::read(&args.left)?;
+    let right = workbook::read(&args.right)?;
+    let diff = compare(&left, &right, &args.sheet, args.tolerance);
+
+    if !global.quiet {
+        let stdout = io::stdout();
+        let color = stdout.is_terminal();
+        print_diff(&mut stdout.lock(), &diff, color).context("无法输出差异")?;
+    }
+    if diff.is_empty() {
+        info!(
+            "两个工作簿的值相同: {} 与 {}",
+            args.left.display(),
+            args.right.display()
+        );
+    } else {
+        info!(
+            "共 {} 个单元格不同，{} 个工作表只在一边存在",
+            diff.cells.len(),
+            diff.only_left.len() + diff.only_right.len()
+        );
+    }
+
+    if let Some(output) = &global.output {
+        if global.dry_run {
+            info!("试运行完成，未写入差异工作簿: {}", output.display());
+            return Ok(());
+        }
+        cli::ensure_parent_dir(output)?;
+        workbook::write(&diff_workbook(right, &diff), output, global.format)?;
+        info!("差异工作簿已保存为: {}", output.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(sheets: &[(&str, &[(&str, &str)])]) -> Spreadsheet {
+        let mut book = umya_spreadsheet::new_file_empty_worksheet();
+        for (name, cells) in sheets {
+            let sheet = book.new_sheet(*name).expect("new sheet");
+            for (address, value) in *cells {
+                sheet.get_cell_mut(*address).set_value(*value);
+            }
+        }
+        book
+    }
+
+    #[test]
+    fn compares_cells_per_sheet() {
+        let a = book(&[
+            (
+                "NMHC监测仪",
+                &[("A1", "总烃"), ("C3", "1.2(H)"), ("D3", "1.5")],
+            ),
+            ("旧表", &[]),
+        ]);
+        let b = book(&[
+            (
+                "NMHC监测仪",
+                &[("A1", "总烃"), ("C3", "1.2"), ("D3", "1.50"), ("B4", "x")],
+            ),
+            ("变更记录", &[]),
+        ]);
+
+        let diff = compare(&a, &b, &[], 0.0);
+        assert_eq!(diff.only_left, ["旧表"]);
+        assert_eq!(diff.only_right, ["变更记录"]);
+        let cells: Vec<_> = diff
+            .cells
+            .iter()
+            .map(|c| (c.address.as_str(), c.left.as_str(), c.right.as_str()))
+            .collect();
+        assert_eq!(cells, [("C3", "1.2(H)", "1.2"), ("B4", "", "x")]);
+
+        let only = compare(&a, &b, &["旧表".to_string()], 0.0);
+        assert_eq!(only.only_left, ["旧表"]);
+        assert!(only.cells.is_empty() && only.only_right.is_empty());
+
+        assert!(same_value("0.30000000000000004", "0.3", 1e-9));
+        assert!(!same_value("0.31", "0.3", 1e-9));
+    }
+
+    #[test]
+    fn prints_and_writes_diff_workbook() -> Result<()> {
+        let a = book(&[("Sheet1", &[("B2", "-999")])]);
+        let b = book(&[("Sheet1", &[("B2", "-999#a24041")])]);
+        let diff = compare(&a, &b, &[], 0.0);
+
+        let mut out = Vec::new();
+        print_diff(&mut out, &diff, false)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "Sheet1!B2: -\"-999\" +\"-999#a24041\"\n"
+        );
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("diff.xlsx");
+        workbook::write(
+            &diff_workbook(b, &diff),
+            &path,
+            workbook::OutputFormat::Xlsx,
+        )?;
+        let back = umya_spreadsheet::reader::xlsx::read(&path)?;
+        let summary = back.get_sheet(&0).expect("summary sheet");
+        assert_eq!(summary.get_name(), DIFF_SHEET);
+        assert_eq!(summary.get_value("C2"), "-999");
+        assert_eq!(summary.get_value("D2"), "-999#a24041");
+        let sheet = back.get_sheet_by_name("Sheet1").expect("sheet");
+        let fill = sheet
+            .get_style("B2")
+            .get_fill()
+            .and_then(|fill| fill.get_pattern_fill())
+            .and_then(|fill| fill.get_foreground_color())
+            .map(|color| color.get_argb().to_string());
+        assert_eq!(fill.as_deref(), Some(DIFF_FILL));
+        Ok(())
+    }
+}
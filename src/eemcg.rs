@@ -1,37 +1,85 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result, anyhow};
-use calamine::{Data, Reader, open_workbook_auto};
+use clap::{Args, Parser, ValueEnum};
 use regex::Regex;
+use tracing::{debug, info, warn};
+use umya_spreadsheet::structs::{
+    ConditionalFormatValues, ConditionalFormatting, ConditionalFormattingRule, Formula,
+};
+use umya_spreadsheet::{Comment, RichText, Spreadsheet, Worksheet};
+
+use crate::batch;
+use crate::cli::{self, GlobalArgs, OutputFormat};
+use crate::error::{DtToolsError, Result, invalid};
+use crate::export;
+use crate::progress;
+use crate::report::{self, CellChange, Note};
+use crate::workbook;
+
+pub mod expr;
+mod pipeline;
+mod rules;
+
+pub use pipeline::{
+    ApplyFlagCodes, CellState, ComputeValues, ConvertUnits, FlagMissing, NormalizeSpecies,
+    Pipeline, RenameSheets, ReplaceStrings, SheetCells, Step, StripParentheses, Transform,
+};
+pub use rules::{
+    BracketNotes, BracketRule, BracketStyle, ComputeRule, ConvertRule, FlagCodes, MissingValueRule,
+    RULES_FILE, ReplaceRule, RuleSet,
+};
 
 #[derive(Debug, Clone)]
 struct CellUpdate {
+    original: String,
     value: String,
     make_red_fill: bool,
+    rules: Vec<String>,
+    notes: Vec<String>,
 }
 
-// simplify complex types for clippy
 type CellUpdateMap = HashMap<(u32, u32), CellUpdate>;
-type FindTargetResult = (usize, usize, CellUpdateMap);
-
-fn datatype_to_string(cell: Option<&Data>) -> String {
-    match cell {
-        None => String::new(),
-        Some(Data::Empty) => String::new(),
-        Some(Data::String(s)) => s.clone(),
-        Some(Data::Float(n)) => {
-            if n.fract() == 0.0 {
-                format!("{:.0}", n)
-            } else {
-                n.to_string()
-            }
-        }
-        Some(Data::Int(n)) => n.to_string(),
-        Some(Data::Bool(b)) => b.to_string(),
-        Some(Data::Error(e)) => format!("{e:?}"),
-        Some(Data::DateTime(f)) => f.to_string(),
-        Some(other) => format!("{other:?}"),
+
+/// [`SheetSelection::Auto`] 选择的工作表名称（重命名前或重命名后）所匹配的正则表达式。
+pub const AUTO_SHEET_PATTERNS: [&str; 2] = ["VOCs", "NMHC"];
+
+/// 要处理的工作表。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SheetSelection {
+    /// 只处理活动工作表
+    #[default]
+    Active,
+    /// 处理全部工作表
+    All,
+    /// 按名称选择（重命名前或重命名后的名称均可）
+    Named(Vec<String>),
+    /// 名称（重命名前或重命名后）匹配任一正则表达式的工作表
+    Matching(Vec<String>),
+    /// 名称匹配 [`AUTO_SHEET_PATTERNS`] 的工作表，与哪个工作表处于活动状态无关；
+    /// 都不匹配时处理活动工作表
+    Auto,
+}
+
+/// 一次 eemcg 转换的结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransformReport {
+    /// 被重命名的工作表（原名称, 新名称）
+    pub renamed_sheets: Vec<(String, String)>,
+    /// 被处理的工作表（重命名之后的名称），按工作簿中的顺序
+    pub sheets: Vec<String>,
+    /// 被处理的工作表的行数之和（含表头行）
+    pub rows: usize,
+    /// 被修改的单元格，按工作表、行、列排序
+    pub changes: Vec<CellChange>,
+    /// 删除的括号内容，顺序同 `changes`
+    pub notes: Vec<Note>,
+}
+
+impl TransformReport {
+    /// 某个工作表中被修改的单元格数。
+    pub fn changes_in(&self, sheet: &str) -> usize {
+        self.changes.iter().filter(|c| c.sheet == sheet).count()
     }
 }
 
@@ -50,197 +98,1291 @@ fn to_a1(col_1based: u32, row_1based: u32) -> String {
     format!("{}{}", column_number_to_name(col_1based), row_1based)
 }
 
-fn find_target_cells(file_path: &Path, active_sheet_name: &str) -> Result<FindTargetResult> {
-    let mut workbook = open_workbook_auto(file_path)
-        .with_context(|| format!("无法打开文件: {}", file_path.display()))?;
+/// 工作表的全部单元格值（到最后一个已用的行/列），用于导出 CSV。
+fn sheet_table(sheet: &umya_spreadsheet::Worksheet) -> Vec<Vec<String>> {
+    let (max_col, max_row) = sheet.get_highest_column_and_row();
+    (1..=max_row)
+        .map(|row| {
+            (1..=max_col)
+                .map(|col| sheet.get_value((col, row)))
+                .collect()
+        })
+        .collect()
+}
+
+/// 工作表中的非空单元格值，键为 (行, 列)，均为 1 起计。
+type SheetValues = HashMap<(u32, u32), String>;
+
+/// 收集工作表中已存在且非空的单元格，不遍历整个矩形区域。
+fn used_values(sheet: &Worksheet) -> SheetValues {
+    sheet
+        .get_cell_collection()
+        .into_iter()
+        .filter_map(|cell| {
+            let value = cell.get_value();
+            if value.is_empty() {
+                return None;
+            }
+            let coordinate = cell.get_coordinate();
+            Some((
+                (*coordinate.get_row_num(), *coordinate.get_col_num()),
+                value.into_owned(),
+            ))
+        })
+        .collect()
+}
+
+/// 对每个非空单元格执行流水线，返回需要修改的单元格。空单元格不会被任何内置步骤改写，
+/// 因此只处理已用的单元格，耗时与内存只与非空单元格数成正比。
+fn find_target_cells(values: &SheetValues, pipeline: &mut Pipeline) -> CellUpdateMap {
+    // 尺寸与 Go 版本一致：最后一个非空行，各行最后一个非空单元格列号的最大值
+    let (height, width) = values.keys().fold((0, 0), |(height, width), &(row, col)| {
+        (height.max(row), width.max(col))
+    });
+    let value_at = |row: u32, col: u32| values.get(&(row, col)).cloned().unwrap_or_default();
+    pipeline.begin_sheet(&SheetCells::new(height, width, &value_at));
+
+    let mut updates = CellUpdateMap::new();
+    let progress = progress::bar(values.len(), "检查单元格", "个");
+    for (&(row, col), original_value) in values {
+        progress.inc(1);
+        let mut state = CellState::new(row, col, original_value.clone());
+        pipeline.apply_cell(&mut state);
+
+        // 只标红不改值的检查（如一致性检查）命中的单元格也要记录，以便标红
+        if state.value != *original_value || state.flagged {
+            updates.insert(
+                (row, col),
+                CellUpdate {
+                    original: original_value.clone(),
+                    value: if state.value == *original_value {
+                        state.value
+                    } else {
+                        state.value.trim().to_string()
+                    },
+                    make_red_fill: state.red_fill,
+                    rules: state.rules,
+                    notes: state.notes,
+                },
+            );
+        }
+    }
+
+    updates
+}
+
+/// 修改过的单元格（规则要求标红的）的标示方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum HighlightMode {
+    /// 直接设置单元格的填充色
+    #[default]
+    Fill,
+    /// 添加一条条件格式，单元格自身的样式不变，可在 Excel 中统一修改颜色或删除
+    Conditional,
+}
+
+/// 标示方式与颜色（ARGB，如 `ffff0000`）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    pub mode: HighlightMode,
+    pub color: String,
+}
 
-    let range = workbook
-        .worksheet_range(active_sheet_name)
-        .with_context(|| format!("无法读取工作表: {active_sheet_name}"))?;
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            mode: HighlightMode::Fill,
+            color: "ffff0000".to_string(),
+        }
+    }
+}
 
-    let (height, width) = range.get_size();
-    if height == 0 || width == 0 {
-        return Ok((height, 0, HashMap::new()));
+/// 解析 `--highlight-color`：`RRGGBB` 或 `AARRGGBB`，可带 `#`，返回 ARGB。
+fn parse_color(value: &str) -> std::result::Result<String, String> {
+    let hex = value.trim().trim_start_matches('#');
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("颜色必须是十六进制的 RRGGBB: {value}"));
+    }
+    match hex.len() {
+        6 => Ok(format!("ff{}", hex.to_ascii_lowercase())),
+        8 => Ok(hex.to_ascii_lowercase()),
+        _ => Err(format!("颜色必须是十六进制的 RRGGBB: {value}")),
     }
+}
 
-    // 尽量模拟 Go 版本：
-    // maxRow = len(GetRows(activeSheet))（通常到最后一个非空行）
-    // maxColumn = max(len(row))（每一行的最后一个非空单元格列号）
-    let mut max_column = 0usize;
-    for row in 0..height {
-        let mut last_non_empty = 0usize;
-        for col in 0..width {
-            let v = datatype_to_string(range.get((row, col)));
-            if !v.is_empty() {
-                last_non_empty = col + 1;
+/// 纯色填充的样式。
+fn fill_style(style: &mut umya_spreadsheet::Style, color: &str) {
+    let fill = style.get_fill_mut().get_pattern_fill_mut();
+    fill.set_pattern_type(umya_spreadsheet::structs::PatternValues::Solid);
+    fill.get_foreground_color_mut().set_argb(color);
+    fill.get_background_color_mut().set_argb(color);
+}
+
+/// 标示需要标红的单元格：填充模式只覆盖单元格的填充，保留原有的字体、边框和数字格式等样式；
+/// 条件格式模式为这些单元格添加一条恒为真的条件格式。
+fn highlight_cells(sheet: &mut Worksheet, updates: &CellUpdateMap, highlight: &Highlight) {
+    let mut cells: Vec<_> = updates
+        .iter()
+        .filter(|(_, upd)| upd.make_red_fill)
+        .map(|(&position, _)| position)
+        .collect();
+    if cells.is_empty() {
+        return;
+    }
+    match highlight.mode {
+        HighlightMode::Fill => {
+            for (row, col) in cells {
+                fill_style(
+                    sheet.get_cell_mut((col, row)).get_style_mut(),
+                    &highlight.color,
+                );
             }
         }
-        if last_non_empty > max_column {
-            max_column = last_non_empty;
+        HighlightMode::Conditional => {
+            cells.sort_unstable();
+            let sqref = cells
+                .iter()
+                .map(|&(row, col)| to_a1(col, row))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let mut style = umya_spreadsheet::Style::default();
+            fill_style(&mut style, &highlight.color);
+            let mut formula = Formula::default();
+            formula.set_string_value("TRUE");
+            let mut rule = ConditionalFormattingRule::default();
+            rule.set_type(ConditionalFormatValues::Expression)
+                .set_priority(1)
+                .set_formula(formula)
+                .set_style(style);
+            let mut formatting = ConditionalFormatting::default();
+            formatting.get_sequence_of_references_mut().set_sqref(sqref);
+            formatting.add_conditional_collection(rule);
+            sheet.add_conditional_formatting_collection(formatting);
         }
     }
-    if max_column == 0 {
-        max_column = width;
-    }
+}
 
-    let re = Regex::new(r"\([^)]*\)").context("无法编译正则表达式")?;
+fn apply_updates(sheet: &mut Worksheet, updates: &CellUpdateMap) {
+    let progress = progress::bar(updates.len(), "写入单元格", "个");
+    for (&(row, col), upd) in updates {
+        progress.inc(1);
+        if upd.value != upd.original {
+            sheet.get_cell_mut((col, row)).set_value(upd.value.as_str());
+        }
+    }
+}
 
-    // 获取第3行特定单元格的值（A1 计数）
-    let i3_value = datatype_to_string(range.get((2, 8)));
-    let k3_value = datatype_to_string(range.get((2, 10)));
-    let q3_value = datatype_to_string(range.get((2, 16)));
-    let ay3_value = datatype_to_string(range.get((2, 50)));
+fn updates_to_changes(sheet_name: &str, updates: &CellUpdateMap) -> Vec<CellChange> {
+    let mut updates: Vec<_> = updates.iter().collect();
+    updates.sort_by_key(|&(&(row, col), _)| (row, col));
+    updates
+        .into_iter()
+        // 只标红、值未改变的单元格不是修改
+        .filter(|(_, upd)| upd.value != upd.original)
+        .map(|(&(row, col), upd)| CellChange {
+            sheet: sheet_name.to_string(),
+            address: to_a1(col, row),
+            before: upd.original.clone(),
+            after: upd.value.clone(),
+            red_fill: upd.make_red_fill,
+            rules: upd.rules.clone(),
+        })
+        .collect()
+}
 
-    let mut updates: HashMap<(u32, u32), CellUpdate> = HashMap::new();
+fn updates_to_notes(sheet_name: &str, updates: &CellUpdateMap) -> Vec<Note> {
+    let mut updates: Vec<_> = updates.iter().collect();
+    updates.sort_by_key(|&(&(row, col), _)| (row, col));
+    updates
+        .into_iter()
+        .flat_map(|(&(row, col), upd)| {
+            upd.notes.iter().map(move |text| Note {
+                sheet: sheet_name.to_string(),
+                address: to_a1(col, row),
+                text: text.clone(),
+            })
+        })
+        .collect()
+}
 
-    for row_1based in 1..=height {
-        for col_1based in 1..=max_column {
-            let original_value = datatype_to_string(range.get((row_1based - 1, col_1based - 1)));
-            let mut value = original_value.clone();
-            let mut make_red_fill = false;
+fn transform_sheet(
+    sheet: &mut Worksheet,
+    pipeline: &mut Pipeline,
+    highlight: &Highlight,
+    report: &mut TransformReport,
+) {
+    let updates = find_target_cells(&used_values(sheet), pipeline);
+    apply_updates(sheet, &updates);
+    highlight_cells(sheet, &updates, highlight);
+    report
+        .changes
+        .extend(updates_to_changes(sheet.get_name(), &updates));
+    report
+        .notes
+        .extend(updates_to_notes(sheet.get_name(), &updates));
+}
 
-            // 替换指定字符串，不设置红色背景
-            if value.contains("甲烷非甲烷分析仪") {
-                value = value.replace("甲烷非甲烷分析仪", "NMHC监测仪");
-            }
-            if value.contains("VOCs在线监测仪") {
-                value = value.replace("VOCs在线监测仪", "VOCs监测仪");
-            }
-            if value.contains("总烃(ppbv)") {
-                value = value.replace("总烃(ppbv)", "总烃(ppbC)");
-            }
-            if value.contains("总烃(ppbvC)") {
-                value = value.replace("总烃(ppbvC)", "总烃(ppbC)");
-            }
-            // 针对NMHC监测仪sheet的D1单元格，精确替换"总烃(ppbvC)"为"总烃(ppbC)"
-            if row_1based == 1 && col_1based == 4 && value == "总烃(ppbvC)" {
-                value = "总烃(ppbC)".to_string();
-            }
-            if value.contains("间、对-二甲苯") {
-                value = value.replace("间、对-二甲苯", "间/对-二甲苯");
-            }
-            if value.contains("邻二甲苯") {
-                value = value.replace("邻二甲苯", "邻-二甲苯");
+/// 把删除的括号内容写为所在单元格的批注；单元格已有批注时追加在其后。
+fn add_note_comments(book: &mut Spreadsheet, notes: &[Note]) {
+    // 同一单元格的多条备注合为一条批注
+    for cell_notes in notes.chunk_by(|a, b| a.sheet == b.sheet && a.address == b.address) {
+        let note = &cell_notes[0];
+        let note_text = cell_notes
+            .iter()
+            .map(|note| note.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let Some(sheet) = book.get_sheet_by_name_mut(&note.sheet) else {
+            continue;
+        };
+        let existing = sheet
+            .get_comments_mut()
+            .iter_mut()
+            .find(|comment| comment.get_coordinate().to_string() == note.address);
+        let mut text = RichText::default();
+        match existing {
+            Some(comment) => match comment.get_text().get_rich_text() {
+                Some(rich_text) => {
+                    text.set_text(format!("{}\n{note_text}", rich_text.get_text()));
+                    comment.get_text_mut().set_rich_text(text);
+                }
+                // 纯文本批注无法读出，保留原批注
+                None => warn!(
+                    "{}!{} 已有批注，删除的备注未写入: {note_text}",
+                    note.sheet, note.address
+                ),
+            },
+            None => {
+                text.set_text(note_text);
+                let mut comment = Comment::default();
+                comment
+                    .new_comment(note.address.as_str())
+                    .set_author("dttools")
+                    .get_text_mut()
+                    .set_rich_text(text);
+                sheet.add_comments(comment);
             }
+        }
+    }
+}
 
-            // 新增需求：处理特定列的 -999 替换（从第4行开始）
-            if row_1based >= 4 && value.contains("-999") {
-                if col_1based == 9 && i3_value == "a24514" {
-                    value = "-999#a24041".to_string();
-                }
-                if col_1based == 11 && k3_value == "a24011" {
-                    value = "-999#a24537".to_string();
-                }
-                if col_1based == 17 && q3_value == "a24510" {
-                    value = "-999#a24504".to_string();
+fn transform_indices(
+    book: &mut Spreadsheet,
+    pipeline: &mut Pipeline,
+    highlight: &Highlight,
+    indices: &[usize],
+) -> TransformReport {
+    let mut report = TransformReport::default();
+    pipeline.apply_book(book, &mut report);
+    for index in indices {
+        if let Some(sheet) = book.get_sheet_mut(index) {
+            report.sheets.push(sheet.get_name().to_string());
+            report.rows += sheet.get_highest_row() as usize;
+            transform_sheet(sheet, pipeline, highlight, &mut report);
+        }
+    }
+    report
+}
+
+/// 对内存中的工作簿执行 eemcg 转换：重命名工作表，并按规则修改活动工作表的单元格。
+pub fn transform(book: &mut Spreadsheet, rules: &RuleSet) -> TransformReport {
+    let active = *book.get_workbook_view().get_active_tab() as usize;
+    transform_indices(
+        book,
+        &mut Pipeline::from_rules(rules),
+        &Highlight::default(),
+        &[active],
+    )
+}
+
+/// 与 [`transform`] 相同，但处理 `selection` 选中的工作表。选中的工作表不存在时返回错误。
+pub fn transform_sheets(
+    book: &mut Spreadsheet,
+    rules: &RuleSet,
+    selection: &SheetSelection,
+) -> Result<TransformReport> {
+    transform_with(book, &mut Pipeline::from_rules(rules), selection)
+}
+
+/// 用自定义的流水线（如加入了站点特有步骤的 [`Pipeline`]）处理 `selection` 选中的工作表。
+/// 同一条流水线可以依次处理多个工作簿，批量处理时无需为每个文件重新构建。
+pub fn transform_with(
+    book: &mut Spreadsheet,
+    pipeline: &mut Pipeline,
+    selection: &SheetSelection,
+) -> Result<TransformReport> {
+    transform_highlighted(book, pipeline, selection, &Highlight::default())
+}
+
+/// 与 [`transform_with`] 相同，但按 `highlight` 标示修改过的单元格（默认为红色填充）。
+pub fn transform_highlighted(
+    book: &mut Spreadsheet,
+    pipeline: &mut Pipeline,
+    selection: &SheetSelection,
+    highlight: &Highlight,
+) -> Result<TransformReport> {
+    let active = *book.get_workbook_view().get_active_tab() as usize;
+    let indices: Vec<usize> = match selection {
+        SheetSelection::Active => vec![active],
+        SheetSelection::All => (0..book.get_sheet_collection().len())
+            .filter(|&index| !is_generated_sheet(book, index))
+            .collect(),
+        SheetSelection::Named(names) => {
+            let mut indices = Vec::new();
+            for name in names {
+                let index = book
+                    .get_sheet_collection()
+                    .iter()
+                    .position(|sheet| {
+                        let sheet_name = sheet.get_name();
+                        sheet_name == name
+                            || pipeline.renamed(sheet_name).as_deref() == Some(name.as_str())
+                    })
+                    .ok_or_else(|| DtToolsError::SheetNotFound {
+                        name: name.to_string(),
+                    })?;
+                if !indices.contains(&index) {
+                    indices.push(index);
                 }
-                if col_1based == 51 && ay3_value == "a25014" {
-                    value = "-999#a25501".to_string();
+            }
+            indices.sort_unstable();
+            indices
+        }
+        SheetSelection::Matching(patterns) => {
+            let patterns = patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern)
+                        .map_err(|err| invalid!("无效的工作表名称正则表达式 {pattern}: {err}"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let indices = matching_sheets(book, pipeline, &patterns);
+            if indices.is_empty() {
+                return Err(DtToolsError::SheetNotFound {
+                    name: patterns
+                        .iter()
+                        .map(Regex::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" | "),
+                });
+            }
+            indices
+        }
+        SheetSelection::Auto => {
+            let patterns =
+                AUTO_SHEET_PATTERNS.map(|pattern| Regex::new(pattern).expect("valid regex"));
+            let indices = matching_sheets(book, pipeline, &patterns);
+            if indices.is_empty() {
+                vec![active]
+            } else {
+                if !indices.contains(&active) {
+                    info!("活动工作表不是 VOCs/NMHC 工作表，改为按名称选择工作表");
                 }
+                indices
             }
+        }
+    };
+    Ok(transform_indices(book, pipeline, highlight, &indices))
+}
 
-            // 如果是第3行及之后，删除括号及其中的内容，并设置红色背景
-            if row_1based >= 3 && re.is_match(&value) {
-                value = re.replace_all(&value, "").to_string();
-                make_red_fill = true;
-            }
+/// 之前输出中追加的变更记录、备注与（旧版本的）处理信息工作表，不参与处理。
+fn is_generated_sheet(book: &Spreadsheet, index: usize) -> bool {
+    book.get_sheet(&index).is_some_and(|sheet| {
+        [
+            report::CHANGE_SHEET,
+            report::NOTE_SHEET,
+            report::METADATA_SHEET,
+        ]
+        .contains(&sheet.get_name())
+    })
+}
 
-            if value != original_value {
-                updates.insert(
-                    (row_1based as u32, col_1based as u32),
-                    CellUpdate {
-                        value: value.trim().to_string(),
-                        make_red_fill,
-                    },
-                );
+/// 名称（重命名前或经 `pipeline` 重命名后）匹配任一 `patterns` 的工作表，按工作簿中的顺序。
+fn matching_sheets(book: &Spreadsheet, pipeline: &Pipeline, patterns: &[Regex]) -> Vec<usize> {
+    book.get_sheet_collection()
+        .iter()
+        .enumerate()
+        .filter(|&(index, sheet)| {
+            let name = sheet.get_name();
+            let renamed = pipeline.renamed(name);
+            !is_generated_sheet(book, index)
+                && patterns.iter().any(|re| {
+                    re.is_match(name) || renamed.as_deref().is_some_and(|to| re.is_match(to))
+                })
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn process_excel(
+    file_path: &Path,
+    args: &EemcgArgs,
+    rules: &RuleSet,
+    pipeline: &mut Pipeline,
+    global: &GlobalArgs,
+) -> Result<report::ProcessedFile> {
+    global.check_unprocessed(file_path)?;
+    // 只读取一次工作簿：在内存中完成重命名和单元格修改后直接写出。
+    let mut book = workbook::read(file_path)?;
+
+    let report = transform_highlighted(
+        &mut book,
+        pipeline,
+        &args.sheet_selection(),
+        &args.highlight(global.format),
+    )?;
+
+    let renamed_message = if global.dry_run {
+        "[试运行] 工作表名称将从"
+    } else {
+        "工作表名称已从"
+    };
+    for (from, to) in &report.renamed_sheets {
+        info!("{renamed_message} '{from}' 替换为 '{to}'");
+    }
+    for sheet in &report.sheets {
+        let message = format!(
+            "工作表 '{sheet}': 共 {} 个单元格需要修改",
+            report.changes_in(sheet)
+        );
+        // 处理多个工作表时总是显示各表的修改数
+        if report.sheets.len() > 1 {
+            info!("{message}");
+        } else {
+            debug!("{message}");
+        }
+    }
+
+    let output_path = global.workbook_output_path(file_path);
+    if global.dry_run {
+        report::print_planned_changes(&report.changes);
+        return Ok(report::ProcessedFile {
+            input: file_path.to_path_buf(),
+            output: output_path,
+            exports: Vec::new(),
+            rows: report.rows,
+            warnings: 0,
+            changes: report.changes,
+            problems: Vec::new(),
+            skipped: false,
+        });
+    }
+
+    report::log_changes(&report.changes);
+    if !args.no_change_log {
+        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        report::append_change_sheet(&mut book, &report.changes, &timestamp);
+    }
+
+    match rules.brackets.notes {
+        BracketNotes::Discard => {}
+        BracketNotes::Comment => add_note_comments(&mut book, &report.notes),
+        BracketNotes::Sheet => report::append_note_sheet(&mut book, &report.notes),
+    }
+
+    workbook::mark_processed(&mut book, rules);
+    global.prepare_output(&output_path)?;
+    workbook::write(&book, &output_path, global.format)?;
+
+    let mut exports = Vec::new();
+    if let Some(delimiter) = global.csv_export() {
+        // 处理了多个工作表时，每个工作表导出一个 CSV：processed_<文件名>_<工作表>.csv
+        for name in &report.sheets {
+            let Some(sheet) = book.get_sheet_by_name(name) else {
+                continue;
+            };
+            let mut csv_path = export::csv_path(&output_path, delimiter);
+            if report.sheets.len() > 1 {
+                let stem = csv_path.file_stem().unwrap_or_default().to_string_lossy();
+                let ext = csv_path.extension().unwrap_or_default().to_string_lossy();
+                csv_path = csv_path.with_file_name(format!("{stem}_{name}.{ext}"));
             }
+            export::write_csv(&csv_path, &sheet_table(sheet), delimiter)?;
+            info!("数据表已导出为: {}", csv_path.display());
+            exports.push(csv_path);
         }
     }
 
-    Ok((height, max_column, updates))
+    #[cfg(feature = "upload")]
+    global.upload.send(&output_path)?;
+    #[cfg(feature = "post")]
+    if global.post.url.is_some() {
+        let tables: Vec<_> = report
+            .sheets
+            .iter()
+            .filter_map(|name| Some((name, sheet_table(book.get_sheet_by_name(name)?))))
+            .collect();
+        let sheets = tables
+            .iter()
+            .map(|(name, table)| crate::post::SheetRows::new(name, table))
+            .collect();
+        global.post.send("eemcg", file_path, &output_path, sheets)?;
+    }
+    global.archive.store(file_path)?;
+
+    Ok(report::ProcessedFile {
+        input: file_path.to_path_buf(),
+        output: output_path,
+        exports,
+        rows: report.rows,
+        warnings: 0,
+        changes: report.changes,
+        problems: Vec::new(),
+        skipped: false,
+    })
 }
 
-fn process_excel(file_path: &Path) -> Result<PathBuf> {
-    // 先用 umya 读取，以获取“活动工作表名称”，并在写入前完成工作表重命名。
-    let mut book = umya_spreadsheet::reader::xlsx::read(file_path)
-        .with_context(|| format!("无法打开文件(写入模式): {}", file_path.display()))?;
+/// 目录、通配符输入中收集的文件扩展名。
+pub(crate) const INPUT_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xls", "ods"];
+
+/// `dttools eemcg` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct EemcgArgs {
+    /// 输入文件（xlsx/xls/ods）、目录或通配符（如 data/*.xlsx）
+    #[arg(value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    /// 替换规则文件；默认按查找顺序找 rules.toml 或 dttools.toml 的 [eemcg]，都没有时使用内置规则
+    #[arg(long, value_name = "FILE")]
+    pub rules: Option<PathBuf>,
+
+    /// 处理全部工作表（默认处理名称含 VOCs 或 NMHC 的工作表，没有时处理活动工作表）
+    #[arg(long, conflicts_with_all = ["sheet", "sheet_pattern", "active_sheet"])]
+    pub all_sheets: bool,
 
-    let active_sheet_name_original = book.get_active_sheet().get_name().to_string();
+    /// 处理指定名称的工作表，可重复使用
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["sheet_pattern", "active_sheet"])]
+    pub sheet: Vec<String>,
+
+    /// 处理名称匹配该正则表达式的工作表，可重复使用
+    #[arg(long, value_name = "REGEX", conflicts_with = "active_sheet")]
+    pub sheet_pattern: Vec<String>,
+
+    /// 只处理活动工作表（即最后保存时选中的工作表），不按名称选择
+    #[arg(long)]
+    pub active_sheet: bool,
+
+    /// 不在输出工作簿中追加隐藏的“变更记录”工作表
+    #[arg(long)]
+    pub no_change_log: bool,
+
+    /// 修改过的单元格的标示方式
+    #[arg(long, value_enum, default_value_t = HighlightMode::Fill)]
+    pub highlight: HighlightMode,
+
+    /// 标示颜色（RRGGBB），如 FF8800
+    #[arg(long, value_name = "RRGGBB", default_value = "FF0000", value_parser = parse_color)]
+    pub highlight_color: String,
+}
 
-    // 重命名工作表（与 Go 版本一致）
-    if let Some(sheet) = book.get_sheet_by_name_mut("甲烷非甲烷分析仪") {
-        sheet.set_name("NMHC监测仪".to_string());
-        println!("工作表名称已从 '甲烷非甲烷分析仪' 替换为 'NMHC监测仪'");
+impl EemcgArgs {
+    fn highlight(&self, format: OutputFormat) -> Highlight {
+        let mut mode = self.highlight;
+        if mode == HighlightMode::Conditional && format == OutputFormat::Ods {
+            warn!("ODS 输出不支持条件格式，改为直接填充颜色");
+            mode = HighlightMode::Fill;
+        }
+        Highlight {
+            mode,
+            color: self.highlight_color.clone(),
+        }
     }
-    if let Some(sheet) = book.get_sheet_by_name_mut("VOCs在线监测仪") {
-        sheet.set_name("VOCs监测仪".to_string());
-        println!("工作表名称已从 'VOCs在线监测仪' 替换为 'VOCs监测仪'");
+
+    fn sheet_selection(&self) -> SheetSelection {
+        if self.all_sheets {
+            SheetSelection::All
+        } else if !self.sheet.is_empty() {
+            SheetSelection::Named(self.sheet.clone())
+        } else if !self.sheet_pattern.is_empty() {
+            SheetSelection::Matching(self.sheet_pattern.clone())
+        } else if self.active_sheet {
+            SheetSelection::Active
+        } else {
+            SheetSelection::Auto
+        }
     }
+}
 
-    // 如果活动表正好被重命名，后续写入时要用新名字；
-    // 但 calamine 读取输入文件时仍需要用“旧名字”。
-    let active_sheet_name_final = match active_sheet_name_original.as_str() {
-        "甲烷非甲烷分析仪" => "NMHC监测仪".to_string(),
-        "VOCs在线监测仪" => "VOCs监测仪".to_string(),
-        other => other.to_string(),
-    };
+pub fn execute(args: &EemcgArgs, global: &GlobalArgs) -> Result<()> {
+    if args.inputs.is_empty() {
+        warn!("请提供文件名、目录或通配符作为参数，例如：dtEEMCG 45vocs2.xlsx 或 dtEEMCG data/");
+        return Ok(());
+    }
+
+    #[cfg(feature = "parquet")]
+    if global.format == OutputFormat::Parquet {
+        crate::error::bail!(
+            "--format parquet 只能用于 proton 与各仪器的转换，eemcg 请使用 xlsx 或 ods"
+        );
+    }
 
-    let (_max_row, _max_column, updates) =
-        find_target_cells(file_path, &active_sheet_name_original)?;
-
-    // 把更新写入到（可能已重命名后的）活动工作表
-    let sheet = book
-        .get_sheet_by_name_mut(&active_sheet_name_final)
-        .ok_or_else(|| anyhow!("找不到工作表: {}", active_sheet_name_final))?;
-
-    // 红色填充样式
-    let mut red_style = umya_spreadsheet::Style::default();
-    red_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .set_pattern_type(umya_spreadsheet::structs::PatternValues::Solid);
-    red_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .get_foreground_color_mut()
-        .set_argb("ffff0000");
-    red_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .get_background_color_mut()
-        .set_argb("ffff0000");
-
-    for (&(row, col), upd) in &updates {
-        let addr = to_a1(col, row);
-        let cell = sheet.get_cell_mut(addr.as_str());
-        cell.set_value(upd.value.as_str());
-        if upd.make_red_fill {
-            cell.set_style(red_style.clone());
-        }
-    }
-
-    let base_name = file_path
-        .file_name()
-        .ok_or_else(|| anyhow!("无法获取文件名"))?
-        .to_string_lossy();
-    let output_path = PathBuf::from(format!("processed_{base_name}"));
-    umya_spreadsheet::writer::xlsx::write(&book, &output_path)
-        .with_context(|| format!("无法保存文件: {}", output_path.display()))?;
-
-    Ok(output_path)
+    let rules = RuleSet::discover(args.rules.as_deref())?;
+    // 流水线（含编译好的正则与查找表）只构建一次，批量模式下各文件共用。
+    let mut pipeline = Pipeline::from_rules(&rules);
+    let inputs = batch::expand_inputs(&args.inputs, INPUT_EXTENSIONS)?;
+    global.check_inputs(&inputs)?;
+    if !inputs.batch {
+        let processed = process_excel(&inputs.files[0], args, &rules, &mut pipeline, global)?;
+        if global.dry_run {
+            info!("试运行完成，未写入文件: {}", processed.output.display());
+        } else {
+            info!("文件已处理并保存为: {}", processed.output.display());
+        }
+        return global.write_report("eemcg", &[processed]);
+    }
+
+    let mut processed = Vec::new();
+    let mut state = global.resume_state("eemcg", &rules, &inputs.files)?;
+    let result = batch::run_batch(&inputs.files, global.open_failures(), &mut state, |file| {
+        let file = process_excel(file, args, &rules, &mut pipeline, global)?;
+        let output = file.output.clone();
+        processed.push(file);
+        Ok(output)
+    });
+    processed.extend(state.skipped_files());
+    global.write_report("eemcg", &processed)?;
+    global.write_manifest("eemcg", &processed)?;
+    result
+}
+
+/// 独立的 `dtEEMCG` 程序入口，参数与 `dttools eemcg` 相同。
+#[derive(Debug, Parser)]
+#[command(
+    name = "dtEEMCG",
+    version,
+    about = "VOCs/NMHC 工作表重命名与单元格修正"
+)]
+struct StandaloneCli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(flatten)]
+    args: EemcgArgs,
 }
 
 pub fn run(args: impl IntoIterator<Item = std::ffi::OsString>) -> Result<()> {
-    let mut args = args.into_iter();
-    let _exe = args.next();
+    let mut cli: StandaloneCli = cli::parse(args);
+    crate::console::init(&mut cli.global);
+    let result = crate::logging::init(&cli.global)
+        .and_then(|()| cli::check_writer("eemcg", false, &cli.global))
+        .and_then(|()| execute(&cli.args, &cli.global));
+    cli.global.finish("eemcg", &result);
+    result
+}
 
-    let Some(input) = args.next() else {
-        eprintln!("请提供文件名作为参数，例如：dtEEMCG 45vocs2.xlsx");
-        return Ok(());
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// 用内置规则处理单个文件。
+    fn process_builtin(path: &Path, global: &GlobalArgs) -> Result<report::ProcessedFile> {
+        let rules = RuleSet::builtin();
+        process_excel(
+            path,
+            &default_args(),
+            &rules,
+            &mut Pipeline::from_rules(&rules),
+            global,
+        )
+    }
+
+    fn default_args() -> EemcgArgs {
+        StandaloneCli::parse_from(["dtEEMCG"]).args
+    }
+
+    #[test]
+    fn transform_in_memory_reports_changes() {
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.set_name("VOCs在线监测仪".to_string());
+        sheet.get_cell_mut("A1").set_value("邻二甲苯");
+        sheet.get_cell_mut("B3").set_value("12(备注)");
+
+        let report = transform(&mut book, &RuleSet::builtin());
+
+        assert_eq!(
+            report.renamed_sheets,
+            [("VOCs在线监测仪".to_string(), "VOCs监测仪".to_string())]
+        );
+        assert_eq!(report.sheets, ["VOCs监测仪"]);
+        let addresses: Vec<_> = report.changes.iter().map(|c| c.address.as_str()).collect();
+        assert_eq!(addresses, ["A1", "B3"]);
+        assert!(report.changes[1].red_fill);
+        let sheet = book.get_sheet_by_name("VOCs监测仪").expect("sheet");
+        assert_eq!(sheet.get_value("A1"), "邻-二甲苯");
+        assert_eq!(sheet.get_value("B3"), "12");
+    }
+
+    #[test]
+    fn pipeline_is_reused_across_books() -> Result<()> {
+        let mut pipeline = Pipeline::from_rules(&RuleSet::builtin());
+        let mut triggered = umya_spreadsheet::new_file();
+        let sheet = triggered.get_active_sheet_mut();
+        sheet.get_cell_mut("I3").set_value("a24514");
+        sheet.get_cell_mut("I5").set_value("-999");
+        let mut plain = umya_spreadsheet::new_file();
+        plain
+            .get_active_sheet_mut()
+            .get_cell_mut("I5")
+            .set_value("-999");
+
+        transform_with(&mut triggered, &mut pipeline, &SheetSelection::Active)?;
+        let report = transform_with(&mut plain, &mut pipeline, &SheetSelection::Active)?;
+
+        // 上一个工作簿中生效的缺测值规则不会带到下一个工作簿
+        assert!(report.changes.is_empty());
+        assert_eq!(plain.get_active_sheet().get_value("I5"), "-999");
+        assert_eq!(triggered.get_active_sheet().get_value("I5"), "-999#a24041");
+        Ok(())
+    }
+
+    #[test]
+    fn transform_converts_units() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[convert]]
+            column = "B"
+            from = "ppbv"
+            to = "ppbC"
+            carbon_number = 7
+            "#,
+        )?;
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("B3").set_value("a24038");
+        sheet.get_cell_mut("B4").set_value_number(1.5);
+        sheet.get_cell_mut("B5").set_value_number(-999);
+        sheet.get_cell_mut("C4").set_value_number(1.5);
+
+        let report = transform(&mut book, &rules);
+
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(report.changes[0].rules, ["convert#1"]);
+        let sheet = book.get_active_sheet();
+        assert_eq!(sheet.get_value("B4"), "10.5");
+        assert_eq!(sheet.get_value("B5"), "-999");
+        assert_eq!(sheet.get_value("C4"), "1.5");
+        Ok(())
+    }
+
+    #[test]
+    fn consistency_fills_without_logging_changes() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[replace]]
+            find = "1"
+            replace = "1"
+            red_fill = true
+            whole_cell = true
+
+            [[consistency]]
+            left = ["B"]
+            right = ["C"]
+            "#,
+        )?;
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("B4").set_value("2");
+        sheet.get_cell_mut("C4").set_value("1.5 ");
+        sheet.get_cell_mut("D4").set_value("1");
+
+        let report = transform(&mut book, &rules);
+
+        // 值未改变的单元格不记为修改；一致性检查命中的单元格仍标红
+        assert!(report.changes.is_empty());
+        let sheet = book.get_active_sheet();
+        let red = |address: &str| {
+            sheet
+                .get_cell(address)
+                .and_then(|cell| cell.get_style().get_fill())
+                .and_then(|fill| fill.get_pattern_fill())
+                .and_then(|fill| fill.get_foreground_color())
+                .is_some_and(|color| color.get_argb() == "ffff0000")
+        };
+        assert!(red("C4"));
+        assert!(!red("D4"));
+        assert_eq!(sheet.get_value("C4"), "1.5 ");
+        Ok(())
+    }
+
+    #[test]
+    fn transform_applies_flag_codes() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[flag_codes]]
+            factor = "a24088"
+            missing = "a24041"
+            "#,
+        )?;
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("C3").set_value("a24088");
+        sheet.get_cell_mut("C4").set_value("1.2(C)");
+        sheet.get_cell_mut("C5").set_value_number(-999);
+        sheet.get_cell_mut("C6").set_value("-999#a24041");
+        sheet.get_cell_mut("D4").set_value("1.2(C)");
+
+        let report = transform(&mut book, &rules);
+
+        let sheet = book.get_active_sheet();
+        assert_eq!(sheet.get_value("C4"), "1.2#C");
+        assert_eq!(sheet.get_value("C5"), "-999#a24041");
+        assert_eq!(sheet.get_value("C6"), "-999#a24041");
+        // 没有码表的列仍按括号规则处理
+        assert_eq!(sheet.get_value("D4"), "1.2");
+        let rules: Vec<_> = report.changes.iter().map(|c| c.rules.join(",")).collect();
+        assert_eq!(rules, ["flag#1", "brackets", "flag#1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_sheet_visits_used_cells_only() {
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("A3").set_value("1.2(H)");
+        // 远处的单元格不应导致遍历整个 ZZ × 200000 区域
+        sheet.get_cell_mut("ZZ200000").set_value("x(1)");
+        sheet.get_cell_mut("B3").set_value("");
+
+        let report = transform(&mut book, &RuleSet::builtin());
+        let changed: Vec<_> = report.changes.iter().map(|c| c.address.as_str()).collect();
+        assert_eq!(changed, ["A3", "ZZ200000"]);
+        let sheet = book.get_active_sheet();
+        assert_eq!(sheet.get_value("ZZ200000"), "x");
+    }
+
+    #[test]
+    fn transform_selected_sheets() -> Result<()> {
+        let mut book = umya_spreadsheet::new_file();
+        book.get_active_sheet_mut()
+            .get_cell_mut("A3")
+            .set_value("x(1)");
+        let sheet = book
+            .new_sheet("甲烷非甲烷分析仪")
+            .map_err(|e| invalid!("{e}"))?;
+        sheet.get_cell_mut("A3").set_value("y(2)");
+        let sheet = book.new_sheet("其他").map_err(|e| invalid!("{e}"))?;
+        sheet.get_cell_mut("A3").set_value("z(3)");
+
+        let all = transform_sheets(&mut book.clone(), &RuleSet::builtin(), &SheetSelection::All)?;
+        assert_eq!(all.sheets, ["Sheet1", "NMHC监测仪", "其他"]);
+        assert_eq!(all.changes_in("其他"), 1);
+
+        let named = SheetSelection::Named(vec!["其他".to_string(), "NMHC监测仪".to_string()]);
+        let report = transform_sheets(&mut book.clone(), &RuleSet::builtin(), &named)?;
+        assert_eq!(report.sheets, ["NMHC监测仪", "其他"]);
+        assert_eq!(report.changes.len(), 2);
+
+        let missing = SheetSelection::Named(vec!["不存在".to_string()]);
+        assert!(transform_sheets(&mut book, &RuleSet::builtin(), &missing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn auto_selection_ignores_active_sheet() -> Result<()> {
+        let mut book = umya_spreadsheet::new_file();
+        book.get_active_sheet_mut().set_name("说明");
+        book.get_active_sheet_mut()
+            .get_cell_mut("A3")
+            .set_value("x(1)");
+        for name in ["VOCs在线监测仪", "甲烷非甲烷分析仪"] {
+            let sheet = book.new_sheet(name).map_err(|e| invalid!("{e}"))?;
+            sheet.get_cell_mut("A3").set_value("y(2)");
+        }
+        let rules = RuleSet::builtin();
+
+        let auto = transform_sheets(&mut book.clone(), &rules, &SheetSelection::Auto)?;
+        assert_eq!(auto.sheets, ["VOCs监测仪", "NMHC监测仪"]);
+
+        let active = transform_sheets(&mut book.clone(), &rules, &SheetSelection::Active)?;
+        assert_eq!(active.sheets, ["说明"]);
+
+        let nmhc = SheetSelection::Matching(vec!["^NMHC".to_string()]);
+        let report = transform_sheets(&mut book.clone(), &rules, &nmhc)?;
+        assert_eq!(report.sheets, ["NMHC监测仪"]);
+        let none = SheetSelection::Matching(vec!["^PM".to_string()]);
+        assert!(transform_sheets(&mut book.clone(), &rules, &none).is_err());
+
+        // 没有 VOCs/NMHC 工作表时处理活动工作表
+        let mut plain = umya_spreadsheet::new_file();
+        let report = transform_sheets(&mut plain, &rules, &SheetSelection::Auto)?;
+        assert_eq!(report.sheets, ["Sheet1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn renames_versioned_sheets_from_rules() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[rename_sheets]]
+            pattern = '^VOCs在线监测仪_(\d{4})$'
+            to = "VOCs监测仪$1"
+
+            [[rename_sheets]]
+            from = "甲烷非甲烷分析仪"
+            to = "NMHC监测仪"
+            "#,
+        )?;
+        let mut book = umya_spreadsheet::new_file();
+        book.get_active_sheet_mut().set_name("VOCs在线监测仪_2024");
+        book.new_sheet("甲烷非甲烷分析仪")
+            .map_err(|e| invalid!("{e}"))?;
+        book.new_sheet("VOCs在线监测仪_旧")
+            .map_err(|e| invalid!("{e}"))?;
+
+        let named = SheetSelection::Named(vec!["VOCs监测仪2024".to_string()]);
+        let report = transform_sheets(&mut book, &rules, &named)?;
+        assert_eq!(
+            report.renamed_sheets,
+            [
+                (
+                    "VOCs在线监测仪_2024".to_string(),
+                    "VOCs监测仪2024".to_string()
+                ),
+                ("甲烷非甲烷分析仪".to_string(), "NMHC监测仪".to_string()),
+            ]
+        );
+        assert_eq!(report.sheets, ["VOCs监测仪2024"]);
+        assert!(book.get_sheet_by_name("VOCs在线监测仪_旧").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn imports_non_xlsx_input_and_writes_ods() -> Result<()> {
+        let dir = tempdir()?;
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.set_name("VOCs在线监测仪");
+        sheet.get_cell_mut("A1").set_value("间、对-二甲苯");
+        sheet.get_cell_mut("B3").set_value("0.5(校准)");
+        let input_path = dir.path().join("input.ods");
+        workbook::write(&book, &input_path, OutputFormat::Ods)?;
+
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().to_path_buf()),
+            format: OutputFormat::Ods,
+            ..Default::default()
+        };
+        let processed = process_builtin(&input_path, &global)?.output;
+        assert_eq!(processed, dir.path().join("processed_input.ods"));
+
+        let out = workbook::read(&processed)?;
+        let sheet = out.get_sheet_by_name("VOCs监测仪").expect("renamed sheet");
+        assert_eq!(sheet.get_value("A1"), "间/对-二甲苯");
+        assert_eq!(sheet.get_value("B3"), "0.5");
+        Ok(())
+    }
 
-    let file_path = PathBuf::from(input);
-    let output = process_excel(&file_path)?;
-    println!("文件已处理并保存为: {}", output.display());
-    Ok(())
+    #[test]
+    fn red_fill_keeps_existing_style() -> Result<()> {
+        let dir = tempdir()?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("A1").set_value("header");
+        let cell = sheet.get_cell_mut("B3");
+        cell.set_value("1.5(待核)");
+        let style = cell.get_style_mut();
+        style.get_font_mut().set_bold(true);
+        style
+            .get_borders_mut()
+            .get_bottom_mut()
+            .set_border_style(umya_spreadsheet::Border::BORDER_THIN);
+        style
+            .get_number_format_mut()
+            .set_format_code(umya_spreadsheet::NumberingFormat::FORMAT_NUMBER_00);
+        let input_path = dir.path().join("input.xlsx");
+        umya_spreadsheet::writer::xlsx::write(&book, &input_path)?;
+
+        let processed = process_builtin(&input_path, &global)?.output;
+        let out = umya_spreadsheet::reader::xlsx::read(&processed)?;
+        let cell = out.get_active_sheet().get_cell("B3").expect("B3");
+        assert_eq!(cell.get_value(), "1.5");
+        let style = cell.get_style();
+        assert_eq!(style.get_font().map(|f| *f.get_bold()), Some(true));
+        assert_eq!(
+            style
+                .get_borders()
+                .map(|b| b.get_bottom().get_border_style()),
+            Some(umya_spreadsheet::Border::BORDER_THIN)
+        );
+        assert_eq!(
+            style.get_number_format().map(|n| n.get_format_code()),
+            Some(umya_spreadsheet::NumberingFormat::FORMAT_NUMBER_00)
+        );
+        let fill = style
+            .get_fill()
+            .and_then(|f| f.get_pattern_fill())
+            .expect("fill");
+        assert_eq!(
+            fill.get_foreground_color().map(|c| c.get_argb()),
+            Some("ffff0000")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sheet_without_column_a_keeps_absolute_addresses() -> Result<()> {
+        let dir = tempdir()?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("B1").set_value("header");
+        sheet.get_cell_mut("I3").set_value("a24514");
+        sheet.get_cell_mut("I4").set_value_number(-999);
+        sheet.get_cell_mut("J4").set_value_number(-999);
+        let input_path = dir.path().join("input.xlsx");
+        umya_spreadsheet::writer::xlsx::write(&book, &input_path)?;
+
+        let processed = process_builtin(&input_path, &global)?.output;
+        let out = umya_spreadsheet::reader::xlsx::read(&processed)?;
+        let sheet_out = out.get_active_sheet();
+        assert_eq!(sheet_out.get_value("I4"), "-999#a24041");
+        assert_eq!(sheet_out.get_value("J4"), "-999");
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_to_reprocess_output() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("a.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        book.get_active_sheet_mut()
+            .get_cell_mut("A3")
+            .set_value("1.5(备注)");
+        umya_spreadsheet::writer::xlsx::write(&book, &input)?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().join("out")),
+            ..Default::default()
+        };
+        let rules = RuleSet::builtin();
+        let mut pipeline = Pipeline::from_rules(&rules);
+
+        let output = process_excel(&input, &default_args(), &rules, &mut pipeline, &global)?.output;
+        let err =
+            process_excel(&output, &default_args(), &rules, &mut pipeline, &global).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+
+        let forced = GlobalArgs {
+            force: true,
+            ..global
+        };
+        process_excel(&output, &default_args(), &rules, &mut pipeline, &forced)?;
+        Ok(())
+    }
+
+    #[test]
+    fn parses_highlight_colors() {
+        assert_eq!(parse_color("FF8800").as_deref(), Ok("ffff8800"));
+        assert_eq!(parse_color("#0072b2").as_deref(), Ok("ff0072b2"));
+        assert_eq!(parse_color("800072B2").as_deref(), Ok("800072b2"));
+        assert!(parse_color("orange").is_err());
+        assert!(parse_color("FF88").is_err());
+    }
+
+    #[test]
+    fn highlights_with_conditional_format() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("a.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("B4").set_value("1.5(备注)");
+        sheet.get_cell_mut("C5").set_value("2(备注)");
+        umya_spreadsheet::writer::xlsx::write(&book, &input)?;
+
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().join("out")),
+            ..Default::default()
+        };
+        let args = EemcgArgs {
+            highlight: HighlightMode::Conditional,
+            highlight_color: parse_color("FF8800").map_err(|err| invalid!("{err}"))?,
+            ..default_args()
+        };
+        let rules = RuleSet::builtin();
+        let mut pipeline = Pipeline::from_rules(&rules);
+        let output = process_excel(&input, &args, &rules, &mut pipeline, &global)?.output;
+
+        let out = umya_spreadsheet::reader::xlsx::read(&output)?;
+        let sheet = out.get_active_sheet();
+        assert_eq!(sheet.get_value("B4"), "1.5");
+        assert!(sheet.get_style("B4").get_fill().is_none());
+        let formatting = sheet.get_conditional_formatting_collection();
+        assert_eq!(formatting.len(), 1);
+        assert_eq!(
+            formatting[0].get_sequence_of_references().get_sqref(),
+            "B4 C5"
+        );
+        let rule = &formatting[0].get_conditional_collection()[0];
+        let fill = rule
+            .get_style()
+            .and_then(|style| style.get_background_color())
+            .map(|color| color.get_argb().to_string());
+        assert_eq!(fill.as_deref(), Some("ffff8800"));
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_stripped_remarks() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("a.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("A4").set_value("1.5(手工)(复核)");
+        sheet.get_cell_mut("B4").set_value("2（停电）");
+        umya_spreadsheet::writer::xlsx::write(&book, &input)?;
+
+        let process = |notes: &str| -> Result<Spreadsheet> {
+            let rules = RuleSet::from_toml_str(&format!("[brackets]\nnotes = \"{notes}\""))?;
+            let global = GlobalArgs {
+                output_dir: Some(dir.path().join(notes)),
+                ..Default::default()
+            };
+            let mut pipeline = Pipeline::from_rules(&rules);
+            let output = process_excel(&input, &default_args(), &rules, &mut pipeline, &global)?;
+            Ok(umya_spreadsheet::reader::xlsx::read(&output.output)?)
+        };
+
+        let out = process("comment")?;
+        let comments = out.get_active_sheet().get_comments_to_hashmap();
+        let text = |address: &str| {
+            comments[address]
+                .get_text()
+                .get_rich_text()
+                .map(|text| text.get_text().to_string())
+        };
+        assert_eq!(text("A4").as_deref(), Some("手工\n复核"));
+        assert_eq!(text("B4").as_deref(), Some("停电"));
+        assert!(out.get_sheet_by_name(report::NOTE_SHEET).is_none());
+
+        let out = process("sheet")?;
+        assert!(out.get_active_sheet().get_comments().is_empty());
+        let notes = out
+            .get_sheet_by_name(report::NOTE_SHEET)
+            .ok_or_else(|| invalid!("missing note sheet"))?;
+        assert_eq!(notes.get_value("B2"), "A4");
+        assert_eq!(notes.get_value("C3"), "复核");
+        assert_eq!(notes.get_value("C4"), "停电");
+
+        let out = process("discard")?;
+        assert!(out.get_active_sheet().get_comments().is_empty());
+        assert_eq!(out.get_active_sheet().get_value("A4"), "1.5");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_excel_end_to_end() -> Result<()> {
+        let dir = tempdir()?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        // build input workbook
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.get_cell_mut("A1").set_value("header");
+        // give it the name that gets replaced by the program
+        sheet.set_name("甲烷非甲烷分析仪".to_string());
+
+        // row 3 markers
+        sheet.get_cell_mut("I3").set_value("a24514");
+        sheet.get_cell_mut("K3").set_value("a24011");
+        sheet.get_cell_mut("Q3").set_value("a24510");
+        sheet.get_cell_mut("AY3").set_value("a25014");
+
+        // -999 values to be replaced in row4
+        sheet.get_cell_mut("I4").set_value("-999");
+        sheet.get_cell_mut("K4").set_value("-999");
+        sheet.get_cell_mut("Q4").set_value("-999");
+        sheet.get_cell_mut("AY4").set_value("-999");
+
+        // parentheses to remove from row >=3
+        sheet.get_cell_mut("A3").set_value("foo(bar)");
+        // total hydrocarbon replacement
+        sheet.get_cell_mut("B2").set_value("总烃(ppbv)");
+
+        let input_path = dir.path().join("input.xlsx");
+        umya_spreadsheet::writer::xlsx::write(&book, &input_path)?;
+
+        // run the processing
+        let processed = process_builtin(&input_path, &global)?.output;
+        assert_eq!(processed, dir.path().join("processed_input.xlsx"));
+        assert!(processed.exists(), "processed file should exist");
+
+        // open processed file and check expectations
+        let out = umya_spreadsheet::reader::xlsx::read(&processed)?;
+        // renamed sheet should exist
+        let sheet_out = out
+            .get_sheet_by_name("NMHC监测仪")
+            .ok_or_else(|| invalid!("Expected renamed sheet not found"))?;
+
+        // check -999 replacements
+        assert_eq!(
+            sheet_out.get_cell("I4").expect("I4").get_value().as_ref(),
+            "-999#a24041",
+        );
+        assert_eq!(
+            sheet_out.get_cell("K4").expect("K4").get_value().as_ref(),
+            "-999#a24537",
+        );
+        assert_eq!(
+            sheet_out.get_cell("Q4").expect("Q4").get_value().as_ref(),
+            "-999#a24504",
+        );
+        assert_eq!(
+            sheet_out.get_cell("AY4").expect("AY4").get_value().as_ref(),
+            "-999#a25501",
+        );
+
+        // parentheses removed
+        assert_eq!(
+            sheet_out.get_cell("A3").expect("A3").get_value().as_ref(),
+            "foo",
+        );
+
+        // total hydrocarbon replaced
+        assert_eq!(
+            sheet_out.get_cell("B2").expect("B2").get_value().as_ref(),
+            "总烃(ppbC)",
+        );
+
+        // hidden change log lists every modified cell with the rule that changed it
+        let log = out
+            .get_sheet_by_name(report::CHANGE_SHEET)
+            .ok_or_else(|| invalid!("Expected change log sheet not found"))?;
+        assert!(matches!(
+            log.get_state(),
+            umya_spreadsheet::structs::SheetStateValues::Hidden
+        ));
+        assert_eq!(log.get_value("B2"), "B2");
+        assert_eq!(log.get_value("E2"), "replace#3");
+        assert_eq!(log.get_value("B3"), "A3");
+        assert_eq!(log.get_value("E3"), "brackets");
+        assert_eq!(log.get_value("E4"), "missing_value#1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn standalone_rejects_streaming_writer() {
+        let args = [
+            "dtEEMCG",
+            "--quiet",
+            "--writer",
+            "streaming",
+            "missing.xlsx",
+        ];
+        let err = run(args.map(std::ffi::OsString::from)).unwrap_err();
+        assert!(err.to_string().contains("--writer streaming"), "{err}");
+    }
 }
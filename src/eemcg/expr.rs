@@ -0,0 +1,621 @@
+//! `[[compute]]` 规则使用的表达式：由单元格数值 `value` 计算新值。
+//!
+//! ```text
+//! if value < 0 then "-999#a24041" else round(value, 3)
+//! value * 1.88
+//! if value > 500 and value != 9999 then value / 1000 else value
+//! ```
+//!
+//! - 字面量：数值（`1.88`、`1e-3`）、双引号字符串（`"-999#a24041"`，`\"` 与 `\\` 转义）、`true`、`false`
+//! - 变量：`value`，单元格的数值
+//! - 运算：`+ - * /`（`+` 也可连接文本）、`< <= > >= == !=`、`and`、`or`、`not`、括号
+//! - 条件：`if 条件 then 值 else 值`
+//! - 函数：`round(x, 位数)`、`abs(x)`、`floor(x)`、`ceil(x)`、`min(a, b, …)`、`max(a, b, …)`
+
+use std::fmt;
+
+/// 表达式的值。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Number(_) => "数值",
+            Self::Text(_) => "文本",
+            Self::Bool(_) => "布尔值",
+        }
+    }
+
+    fn number(self) -> Result<f64, String> {
+        match self {
+            Self::Number(value) => Ok(value),
+            other => Err(format!("需要数值，得到{}", other.type_name())),
+        }
+    }
+
+    fn boolean(self) -> Result<bool, String> {
+        match self {
+            Self::Bool(value) => Ok(value),
+            other => Err(format!("条件须为布尔值，得到{}", other.type_name())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Round,
+    Abs,
+    Floor,
+    Ceil,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "round" => Self::Round,
+            "abs" => Self::Abs,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            _ => return None,
+        })
+    }
+
+    /// 允许的参数个数范围。
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Self::Round => (1, 2),
+            Self::Abs | Self::Floor | Self::Ceil => (1, 1),
+            Self::Min | Self::Max => (1, usize::MAX),
+        }
+    }
+
+    fn call(self, args: Vec<f64>) -> f64 {
+        match self {
+            Self::Round => {
+                let factor = 10f64.powi(args.get(1).copied().unwrap_or(0.0) as i32);
+                (args[0] * factor).round() / factor
+            }
+            Self::Abs => args[0].abs(),
+            Self::Floor => args[0].floor(),
+            Self::Ceil => args[0].ceil(),
+            Self::Min => args.into_iter().fold(f64::INFINITY, f64::min),
+            Self::Max => args.into_iter().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(Value),
+    Value,
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    If(Box<Node>, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+/// 解析好的表达式。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    root: Node,
+}
+
+/// 表达式的语法错误：出错位置（字符序号，0 起计）与原因。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第 {} 个字符处{}", self.position + 1, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Symbol(&'static str),
+    End,
+}
+
+const SYMBOLS: [&str; 14] = [
+    "<=", ">=", "==", "!=", "<", ">", "+", "-", "*", "/", "(", ")", ",", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let error = |position, message: &str| ParseError {
+        position,
+        message: message.to_string(),
+    };
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && matches!(chars[j], '+' | '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse().map_err(|_| error(start, "的数值无效"))?;
+            tokens.push((start, Token::Number(value)));
+        } else if c == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(error(start, "的字符串没有结束的引号")),
+                    Some('"') => break,
+                    Some('\\') if matches!(chars.get(i + 1), Some('"' | '\\')) => {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&c) => {
+                        text.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((start, Token::Text(text)));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((start, Token::Ident(chars[start..i].iter().collect())));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| error(start, &format!("有无法识别的字符 '{c}'")))?;
+            if *symbol == "=" {
+                return Err(error(start, "应为 ==（比较相等）"));
+            }
+            i += symbol.chars().count();
+            tokens.push((start, Token::Symbol(symbol)));
+        }
+    }
+    tokens.push((chars.len(), Token::End));
+    Ok(tokens)
+}
+
+/// 表达式树的最大深度，过深的输入在解析和求值时会耗尽栈。
+const MAX_DEPTH: usize = 100;
+
+/// 递归下降解析器，优先级从低到高：if、or、and、not、比较、加减、乘除、负号。
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// 当前位置的嵌套深度，连续的二元运算每项也算一层
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].1
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.pos].0
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position(),
+            message: message.into(),
+        })
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].1.clone();
+        if token != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(name) if name == keyword)
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if !self.is_keyword(keyword) {
+            return self.error(format!("应为 {keyword}"));
+        }
+        self.next();
+        Ok(())
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if !self.is_symbol(symbol) {
+            return self.error(format!("应为 {symbol}"));
+        }
+        self.next();
+        Ok(())
+    }
+
+    /// 进入下一层；超过 [`MAX_DEPTH`] 时报错。
+    fn enter(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return self.error(format!("表达式嵌套超过 {MAX_DEPTH} 层"));
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<Node, ParseError> {
+        self.enter()?;
+        let node = self.conditional()?;
+        self.depth -= 1;
+        Ok(node)
+    }
+
+    fn conditional(&mut self) -> Result<Node, ParseError> {
+        if !self.is_keyword("if") {
+            return self.or();
+        }
+        self.next();
+        let condition = self.expression()?;
+        self.expect_keyword("then")?;
+        let then = self.expression()?;
+        self.expect_keyword("else")?;
+        let otherwise = self.expression()?;
+        Ok(Node::If(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn or(&mut self) -> Result<Node, ParseError> {
+        let depth = self.depth;
+        let mut left = self.and()?;
+        while self.is_keyword("or") {
+            self.next();
+            self.enter()?;
+            left = Node::Binary(BinaryOp::Or, Box::new(left), Box::new(self.and()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Node, ParseError> {
+        let depth = self.depth;
+        let mut left = self.not()?;
+        while self.is_keyword("and") {
+            self.next();
+            self.enter()?;
+            left = Node::Binary(BinaryOp::And, Box::new(left), Box::new(self.not()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Node, ParseError> {
+        if self.is_keyword("not") {
+            self.next();
+            self.enter()?;
+            let inner = self.not()?;
+            self.depth -= 1;
+            return Ok(Node::Not(Box::new(inner)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Node, ParseError> {
+        let left = self.sum()?;
+        let op = match self.peek() {
+            Token::Symbol("<") => BinaryOp::Lt,
+            Token::Symbol("<=") => BinaryOp::Le,
+            Token::Symbol(">") => BinaryOp::Gt,
+            Token::Symbol(">=") => BinaryOp::Ge,
+            Token::Symbol("==") => BinaryOp::Eq,
+            Token::Symbol("!=") => BinaryOp::Ne,
+            _ => return Ok(left),
+        };
+        self.next();
+        Ok(Node::Binary(op, Box::new(left), Box::new(self.sum()?)))
+    }
+
+    fn sum(&mut self) -> Result<Node, ParseError> {
+        let depth = self.depth;
+        let mut left = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Token::Symbol("+") => BinaryOp::Add,
+                Token::Symbol("-") => BinaryOp::Sub,
+                _ => {
+                    self.depth = depth;
+                    return Ok(left);
+                }
+            };
+            self.next();
+            self.enter()?;
+            left = Node::Binary(op, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, ParseError> {
+        let depth = self.depth;
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Symbol("*") => BinaryOp::Mul,
+                Token::Symbol("/") => BinaryOp::Div,
+                _ => {
+                    self.depth = depth;
+                    return Ok(left);
+                }
+            };
+            self.next();
+            self.enter()?;
+            left = Node::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseError> {
+        if self.is_symbol("-") {
+            self.next();
+            self.enter()?;
+            let inner = self.unary()?;
+            self.depth -= 1;
+            return Ok(Node::Neg(Box::new(inner)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseError> {
+        let position = self.position();
+        match self.next() {
+            Token::Number(value) => Ok(Node::Literal(Value::Number(value))),
+            Token::Text(text) => Ok(Node::Literal(Value::Text(text))),
+            Token::Symbol("(") => {
+                let inner = self.expression()?;
+                self.expect_symbol(")")?;
+                Ok(inner)
+            }
+            Token::Ident(name) => match name.as_str() {
+                "value" => Ok(Node::Value),
+                "true" => Ok(Node::Literal(Value::Bool(true))),
+                "false" => Ok(Node::Literal(Value::Bool(false))),
+                _ => {
+                    let Some(function) = Function::parse(&name) else {
+                        self.pos -= 1;
+                        return self.error(format!("有未知的名称 {name}"));
+                    };
+                    self.call(function, &name, position)
+                }
+            },
+            Token::End => self.error("表达式不完整"),
+            Token::Symbol(symbol) => {
+                self.pos -= 1;
+                self.error(format!("不应出现 {symbol}"))
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        function: Function,
+        name: &str,
+        position: usize,
+    ) -> Result<Node, ParseError> {
+        self.expect_symbol("(")?;
+        let mut args = Vec::new();
+        if !self.is_symbol(")") {
+            args.push(self.expression()?);
+            while self.is_symbol(",") {
+                self.next();
+                args.push(self.expression()?);
+            }
+        }
+        self.expect_symbol(")")?;
+        let (min, max) = function.arity();
+        if args.len() < min || args.len() > max {
+            return Err(ParseError {
+                position,
+                message: format!("的 {name} 参数个数不对"),
+            });
+        }
+        Ok(Node::Call(function, args))
+    }
+}
+
+impl Expr {
+    /// 解析表达式。
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            depth: 0,
+        };
+        let root = parser.expression()?;
+        if *parser.peek() != Token::End {
+            return parser.error("有多余的内容");
+        }
+        Ok(Self { root })
+    }
+
+    /// 以 `value` 为单元格数值求值；类型不符或结果不是有限数时返回错误说明。
+    pub fn eval(&self, value: f64) -> Result<Value, String> {
+        eval(&self.root, value)
+    }
+}
+
+fn eval(node: &Node, value: f64) -> Result<Value, String> {
+    let result = match node {
+        Node::Literal(literal) => literal.clone(),
+        Node::Value => Value::Number(value),
+        Node::Neg(inner) => Value::Number(-eval(inner, value)?.number()?),
+        Node::Not(inner) => Value::Bool(!eval(inner, value)?.boolean()?),
+        Node::If(condition, then, otherwise) => {
+            if eval(condition, value)?.boolean()? {
+                eval(then, value)?
+            } else {
+                eval(otherwise, value)?
+            }
+        }
+        Node::Binary(BinaryOp::And, left, right) => {
+            Value::Bool(eval(left, value)?.boolean()? && eval(right, value)?.boolean()?)
+        }
+        Node::Binary(BinaryOp::Or, left, right) => {
+            Value::Bool(eval(left, value)?.boolean()? || eval(right, value)?.boolean()?)
+        }
+        Node::Binary(op, left, right) => binary(*op, eval(left, value)?, eval(right, value)?)?,
+        Node::Call(function, args) => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, value)?.number())
+                .collect::<Result<Vec<_>, _>>()?;
+            Value::Number(function.call(args))
+        }
+    };
+    match result {
+        Value::Number(number) if !number.is_finite() => Err("结果不是有限的数值".to_string()),
+        result => Ok(result),
+    }
+}
+
+fn binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    use BinaryOp::*;
+    Ok(match (op, left, right) {
+        (Add, Value::Text(left), right) => Value::Text(left + &text(right)),
+        (Add, left, Value::Text(right)) => Value::Text(text(left) + &right),
+        (Eq, left, right) => Value::Bool(left == right),
+        (Ne, left, right) => Value::Bool(left != right),
+        (op, left, right) => {
+            let (left, right) = (left.number()?, right.number()?);
+            match op {
+                Add => Value::Number(left + right),
+                Sub => Value::Number(left - right),
+                Mul => Value::Number(left * right),
+                Div => Value::Number(left / right),
+                Lt => Value::Bool(left < right),
+                Le => Value::Bool(left <= right),
+                Gt => Value::Bool(left > right),
+                Ge => Value::Bool(left >= right),
+                Eq | Ne | And | Or => unreachable!("handled above"),
+            }
+        }
+    })
+}
+
+/// 连接文本时数值的写法。
+fn text(value: Value) -> String {
+    match value {
+        Value::Number(number) => crate::units::format_number(number, None),
+        Value::Text(text) => text,
+        Value::Bool(flag) => flag.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, value: f64) -> Result<Value, String> {
+        Expr::parse(source)
+            .map_err(|err| err.to_string())?
+            .eval(value)
+    }
+
+    #[test]
+    fn evaluates_conditions_and_functions() {
+        let source = r#"if value < 0 then "-999#a24041" else round(value, 3)"#;
+        assert_eq!(eval(source, -999.0), Ok(Value::Text("-999#a24041".into())));
+        assert_eq!(eval(source, 1.23456), Ok(Value::Number(1.235)));
+        assert_eq!(eval("value * 1.88", 2.0), Ok(Value::Number(3.76)));
+        assert_eq!(eval("-value + 2 * 3", 1.0), Ok(Value::Number(5.0)));
+        assert_eq!(eval("(1 + 2) * 3", 0.0), Ok(Value::Number(9.0)));
+        assert_eq!(eval("max(value, 0.5, 1e-1)", 0.2), Ok(Value::Number(0.5)));
+        assert_eq!(
+            eval("not value > 1 and value != 0 or false", 0.5),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            eval(r##"round(value) + "#C""##, 2.6),
+            Ok(Value::Text("3#C".into()))
+        );
+        assert_eq!(eval(r#""a\"b\\""#, 0.0), Ok(Value::Text("a\"b\\".into())));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let parse = |source: &str| Expr::parse(source).unwrap_err().to_string();
+        assert_eq!(parse("value *"), "第 8 个字符处表达式不完整");
+        assert_eq!(parse("valeu * 2"), "第 1 个字符处有未知的名称 valeu");
+        assert_eq!(parse("value = 1"), "第 7 个字符处应为 ==（比较相等）");
+        assert_eq!(parse("if value then 1"), "第 16 个字符处应为 else");
+        assert_eq!(
+            parse("round(1, 2, 3)"),
+            "第 1 个字符处的 round 参数个数不对"
+        );
+        assert_eq!(parse("1 2"), "第 3 个字符处有多余的内容");
+        assert_eq!(parse("\"abc"), "第 1 个字符处的字符串没有结束的引号");
+        assert!(Expr::parse(&format!("{}1{}", "(".repeat(99), ")".repeat(99))).is_ok());
+        assert!(parse(&"(".repeat(100_000)).ends_with("表达式嵌套超过 100 层"));
+        assert!(parse(&"-".repeat(100_000)).ends_with("表达式嵌套超过 100 层"));
+        assert!(parse(&"1+".repeat(100_000)).ends_with("表达式嵌套超过 100 层"));
+
+        assert_eq!(eval("value / 0", 1.0), Err("结果不是有限的数值".into()));
+        assert_eq!(
+            eval("if value then 1 else 2", 1.0).unwrap_err(),
+            "条件须为布尔值，得到数值"
+        );
+        assert_eq!(eval(r#""a" * 2"#, 1.0).unwrap_err(), "需要数值，得到文本");
+    }
+}
@@ -0,0 +1,1127 @@
+//! eemcg 转换流水线。
+//!
+//! 转换由一组按顺序执行的步骤（[`Transform`]）组成：先对整个工作簿执行一次
+//! [`Transform::apply_book`]（如重命名工作表），再对每个工作表依次调用
+//! [`Transform::begin_sheet`]，然后把每个单元格按步骤顺序交给 [`Transform::apply_cell`]，
+//! 前一步的结果作为后一步的输入。
+//!
+//! 内置步骤由 `rules.toml` 中的 `pipeline` 选择并排序（见 [`Step`]）；站点特有的处理可以
+//! 实现 [`Transform`] 后用 [`Pipeline::push`] 或 [`Pipeline::insert`] 加入，无需改动逐单元格的循环。
+
+use std::collections::HashSet;
+
+use regex::Regex;
+use serde::Deserialize;
+use tracing::warn;
+use umya_spreadsheet::Spreadsheet;
+use umya_spreadsheet::helper::coordinate::coordinate_from_index;
+
+use super::TransformReport;
+use super::expr::Expr;
+use super::rules::{
+    BracketRule, ColumnSpec, ComputeRule, ConsistencyRule, ConvertRule, FlagCodes,
+    MissingValueRule, ReplaceRule, RuleSet, SheetRename,
+};
+use crate::species::Dictionary;
+use crate::units::{self, Conditions};
+
+/// 内置的转换步骤，即 `rules.toml` 中 `pipeline` 的取值。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// 按 `[[rename_sheets]]` 重命名工作表
+    RenameSheets,
+    /// `[[replace]]` 查找/替换
+    Replace,
+    /// 按名称词典（`[species]`）统一监测项目名称；不在默认顺序中，须在 `pipeline` 中列出
+    Species,
+    /// `[[missing_value]]` 缺测值改写
+    MissingValue,
+    /// `[[flag_codes]]` 标记码改写
+    FlagCodes,
+    /// 按 `[brackets]` 删除括号及其内容并标红
+    Brackets,
+    /// `[[convert]]` 单位换算
+    Convert,
+    /// `[[compute]]` 表达式计算
+    Compute,
+    /// `[[consistency]]` 跨列一致性检查
+    Consistency,
+}
+
+impl Step {
+    /// 默认的步骤顺序，与早期固定的处理顺序一致。
+    pub const DEFAULT_ORDER: [Step; 8] = [
+        Step::RenameSheets,
+        Step::Replace,
+        Step::MissingValue,
+        Step::FlagCodes,
+        Step::Brackets,
+        Step::Convert,
+        Step::Compute,
+        Step::Consistency,
+    ];
+
+    /// 按规则创建此步骤。
+    fn build(self, rules: &RuleSet) -> Box<dyn Transform> {
+        match self {
+            Self::RenameSheets => Box::new(RenameSheets::new(rules.rename_sheets.clone())),
+            Self::Replace => Box::new(ReplaceStrings::new(rules.replace.clone())),
+            Self::Species => Box::new(NormalizeSpecies::new(rules.species.clone())),
+            Self::MissingValue => Box::new(FlagMissing::new(rules.missing_value.clone())),
+            Self::FlagCodes => Box::new(ApplyFlagCodes::new(rules.flag_codes.clone())),
+            Self::Brackets => Box::new(StripParentheses::new(rules.brackets.clone())),
+            Self::Convert => Box::new(ConvertUnits::new(rules)),
+            Self::Compute => Box::new(ComputeValues::new(rules.compute.clone())),
+            Self::Consistency => Box::new(CheckConsistency::new(rules.consistency.clone())),
+        }
+    }
+}
+
+/// 正在处理的工作表：尺寸与按位置读取单元格值。
+pub struct SheetCells<'a> {
+    /// 最后一个非空行
+    pub height: u32,
+    /// 各行最后一个非空单元格列号的最大值
+    pub width: u32,
+    cell: &'a dyn Fn(u32, u32) -> String,
+}
+
+impl<'a> SheetCells<'a> {
+    pub(super) fn new(height: u32, width: u32, cell: &'a dyn Fn(u32, u32) -> String) -> Self {
+        Self {
+            height,
+            width,
+            cell,
+        }
+    }
+
+    /// 第 `row` 行第 `col` 列（均为 1 起计）的原始值。
+    pub fn value(&self, row: u32, col: u32) -> String {
+        (self.cell)(row, col)
+    }
+}
+
+/// 一个单元格在流水线中的当前状态。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellState {
+    /// 行号，1 起计
+    pub row: u32,
+    /// 列号，1 起计
+    pub col: u32,
+    /// 经过前面各步骤后的值
+    pub value: String,
+    /// 是否设置红色背景
+    pub red_fill: bool,
+    /// 不改值、只标红的检查（如一致性检查）是否命中；值未改变的单元格只在此时标红
+    pub flagged: bool,
+    /// 命中的规则编号，写入变更记录
+    pub rules: Vec<String>,
+    /// 被删除的批注文字（如括号中的备注），可写为批注或备注工作表
+    pub notes: Vec<String>,
+}
+
+impl CellState {
+    pub(super) fn new(row: u32, col: u32, value: String) -> Self {
+        Self {
+            row,
+            col,
+            value,
+            red_fill: false,
+            flagged: false,
+            rules: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+}
+
+/// 一个转换步骤。
+pub trait Transform {
+    /// 步骤名称，用于日志。
+    fn name(&self) -> &str;
+
+    /// 此步骤会把名为 `name` 的工作表改成的名称；不重命名时返回 `None`。用于按重命名后的
+    /// 名称选择工作表。
+    fn rename_sheet(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// 在处理工作表之前对整个工作簿执行一次。
+    fn apply_book(&mut self, _book: &mut Spreadsheet, _report: &mut TransformReport) {}
+
+    /// 开始处理一个工作表，可在此按表中内容准备本表的状态。
+    fn begin_sheet(&mut self, _sheet: &SheetCells<'_>) {}
+
+    /// 处理一个非空单元格；空单元格不经过流水线。
+    fn apply_cell(&self, _cell: &mut CellState) {}
+}
+
+/// 按顺序执行的一组转换步骤。
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+    /// 按 `rules.pipeline` 中的顺序创建内置步骤。
+    pub fn from_rules(rules: &RuleSet) -> Self {
+        Self {
+            steps: rules
+                .pipeline
+                .iter()
+                .map(|step| step.build(rules))
+                .collect(),
+        }
+    }
+
+    /// 在末尾加入一个步骤。
+    pub fn push(&mut self, step: impl Transform + 'static) -> &mut Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// 在第 `index` 个步骤之前加入一个步骤。
+    pub fn insert(&mut self, index: usize, step: impl Transform + 'static) -> &mut Self {
+        self.steps.insert(index, Box::new(step));
+        self
+    }
+
+    /// 各步骤的名称，按执行顺序。
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().map(|step| step.name())
+    }
+
+    /// 名为 `name` 的工作表经各步骤重命名后的名称；没有步骤重命名它时返回 `None`。
+    pub fn renamed(&self, name: &str) -> Option<String> {
+        let mut renamed = None;
+        for step in &self.steps {
+            let current = renamed.as_deref().unwrap_or(name);
+            if let Some(next) = step.rename_sheet(current) {
+                renamed = Some(next);
+            }
+        }
+        renamed
+    }
+
+    pub(super) fn apply_book(&mut self, book: &mut Spreadsheet, report: &mut TransformReport) {
+        for step in &mut self.steps {
+            step.apply_book(book, report);
+        }
+    }
+
+    pub(super) fn begin_sheet(&mut self, sheet: &SheetCells<'_>) {
+        for step in &mut self.steps {
+            step.begin_sheet(sheet);
+        }
+    }
+
+    pub(super) fn apply_cell(&self, cell: &mut CellState) {
+        for step in &self.steps {
+            step.apply_cell(cell);
+        }
+    }
+}
+
+/// 按 `[[rename_sheets]]` 规则重命名工作表，每个工作表使用第一条匹配的规则。
+pub struct RenameSheets {
+    /// 各条规则及编译好的 `pattern`
+    rules: Vec<(SheetRename, Option<Regex>)>,
+}
+
+impl RenameSheets {
+    pub fn new(rules: Vec<SheetRename>) -> Self {
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|rule| {
+                    // 载入规则时已检查过，无效的表达式在此忽略
+                    let re = rule.pattern.as_deref().and_then(|p| Regex::new(p).ok());
+                    (rule, re)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Transform for RenameSheets {
+    fn name(&self) -> &str {
+        "rename_sheets"
+    }
+
+    fn rename_sheet(&self, name: &str) -> Option<String> {
+        self.rules.iter().find_map(|(rule, re)| match re {
+            Some(re) => re.captures(name).map(|caps| {
+                let mut to = String::new();
+                caps.expand(&rule.to, &mut to);
+                to
+            }),
+            None => (rule.from.as_deref() == Some(name)).then(|| rule.to.clone()),
+        })
+    }
+
+    fn apply_book(&mut self, book: &mut Spreadsheet, report: &mut TransformReport) {
+        let names: Vec<String> = book
+            .get_sheet_collection()
+            .iter()
+            .map(|sheet| sheet.get_name().to_string())
+            .collect();
+        for (index, from) in names.iter().enumerate() {
+            let Some(to) = self.rename_sheet(from).filter(|to| to != from) else {
+                continue;
+            };
+            if book.get_sheet_by_name(&to).is_some() {
+                warn!("工作表 '{to}' 已存在，'{from}' 未重命名");
+                continue;
+            }
+            if let Some(sheet) = book.get_sheet_mut(&index) {
+                sheet.set_name(to.clone());
+                report.renamed_sheets.push((from.clone(), to));
+            }
+        }
+    }
+}
+
+/// 按 `[[replace]]` 规则替换子串或正则表达式的匹配；指定了 `columns` 的规则只作用于这些列。
+pub struct ReplaceStrings {
+    rules: Vec<ReplaceRule>,
+    /// 各规则编译好的 `pattern`（无效的表达式已在读取规则时报告）
+    patterns: Vec<Option<Regex>>,
+    /// 各规则在本表中生效的列；`None` 为全部列
+    columns: Vec<Option<Vec<u32>>>,
+}
+
+impl ReplaceStrings {
+    pub fn new(rules: Vec<ReplaceRule>) -> Self {
+        let patterns = rules
+            .iter()
+            .map(|rule| rule.regex().ok().flatten())
+            .collect();
+        let mut replace = Self {
+            rules,
+            patterns,
+            columns: Vec::new(),
+        };
+        // 未开始工作表时，按因子编码指定的列尚无法确定
+        replace.begin_sheet(&SheetCells::new(0, 0, &|_, _| String::new()));
+        replace
+    }
+}
+
+impl Transform for ReplaceStrings {
+    fn name(&self) -> &str {
+        "replace"
+    }
+
+    fn begin_sheet(&mut self, sheet: &SheetCells<'_>) {
+        self.columns = self
+            .rules
+            .iter()
+            .map(|rule| {
+                (!rule.columns.is_empty())
+                    .then(|| resolve_columns(&rule.columns, rule.code_row, sheet))
+            })
+            .collect();
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !rule.applies_to_row(cell.row)
+                || self.columns[i]
+                    .as_ref()
+                    .is_some_and(|columns| !columns.contains(&cell.col))
+            {
+                continue;
+            }
+            let replaced = match (&rule.pattern, &self.patterns[i]) {
+                (Some(_), Some(re)) => re
+                    .is_match(&cell.value)
+                    .then(|| re.replace_all(&cell.value, &rule.replace).into_owned()),
+                (Some(_), None) => None,
+                (None, _) => rule.apply(&cell.value),
+            };
+            if let Some(value) = replaced {
+                cell.value = value;
+                if rule.red_fill {
+                    cell.red_fill = true;
+                }
+                cell.rules.push(format!("replace#{}", i + 1));
+            }
+        }
+    }
+}
+
+/// 把名称词典中的其他写法（如 `间、对-二甲苯`、`m,p-xylene`）改为标准名称，名称后的括号
+/// 单位保留；数值等不在词典中的单元格不变。
+pub struct NormalizeSpecies {
+    species: Dictionary,
+}
+
+impl NormalizeSpecies {
+    pub fn new(species: Dictionary) -> Self {
+        Self { species }
+    }
+}
+
+impl Transform for NormalizeSpecies {
+    fn name(&self) -> &str {
+        "species"
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        if let Some(name) = self.species.canonical(&cell.value) {
+            cell.value = name;
+            cell.rules.push("species".to_string());
+        }
+    }
+}
+
+/// 按 `[[missing_value]]` 规则改写触发单元格所在列的缺测值（从触发单元格的下一行开始）。
+pub struct FlagMissing {
+    rules: Vec<MissingValueRule>,
+    /// 本表中生效的规则：(规则序号, (列, 行))
+    active: Vec<(usize, (u32, u32))>,
+}
+
+impl FlagMissing {
+    pub fn new(rules: Vec<MissingValueRule>) -> Self {
+        Self {
+            rules,
+            active: Vec::new(),
+        }
+    }
+}
+
+impl Transform for FlagMissing {
+    fn name(&self) -> &str {
+        "missing_value"
+    }
+
+    /// 只保留触发单元格的值与预期编码一致的规则。
+    fn begin_sheet(&mut self, sheet: &SheetCells<'_>) {
+        self.active = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter_map(|(i, rule)| {
+                let (col, row) = rule.trigger_position()?;
+                (sheet.value(row, col) == rule.expect).then_some((i, (col, row)))
+            })
+            .collect();
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        for &(i, (trigger_col, trigger_row)) in &self.active {
+            let rule = &self.rules[i];
+            if cell.col == trigger_col
+                && cell.row > trigger_row
+                && cell.value.contains(&rule.marker)
+            {
+                cell.value = rule.replacement.clone();
+                cell.rules.push(format!("missing_value#{}", i + 1));
+            }
+        }
+    }
+}
+
+/// 按 `[[flag_codes]]` 码表改写带标记的数值和缺测标记，须在删除括号之前。
+pub struct ApplyFlagCodes {
+    tables: Vec<FlagCodes>,
+    /// 本表中使用码表的列：(码表序号, 列号)，列由 code_row 行中的因子编码确定
+    columns: Vec<(usize, u32)>,
+}
+
+impl ApplyFlagCodes {
+    pub fn new(tables: Vec<FlagCodes>) -> Self {
+        Self {
+            tables,
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl Transform for ApplyFlagCodes {
+    fn name(&self) -> &str {
+        "flag_codes"
+    }
+
+    fn begin_sheet(&mut self, sheet: &SheetCells<'_>) {
+        self.columns.clear();
+        for (i, table) in self.tables.iter().enumerate() {
+            if table.code_row > sheet.height {
+                continue;
+            }
+            for col in 1..=sheet.width {
+                if sheet.value(table.code_row, col) == table.factor {
+                    self.columns.push((i, col));
+                }
+            }
+        }
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        for &(i, column) in &self.columns {
+            let table = &self.tables[i];
+            if cell.col == column
+                && cell.row > table.code_row
+                && let Some(flagged) = table.apply(&cell.value)
+            {
+                cell.value = flagged;
+                cell.rules.push(format!("flag#{}", i + 1));
+            }
+        }
+    }
+}
+
+/// 按 `[brackets]` 删除括号及其中的内容，并设置红色背景（默认第 3 行及之后）。
+pub struct StripParentheses {
+    re: Regex,
+    /// 生效的列号；为空表示全部列
+    columns: Vec<u32>,
+    /// 编译好的 `protect` 正则表达式
+    protect: Vec<Regex>,
+    rule: BracketRule,
+}
+
+impl StripParentheses {
+    pub fn new(rule: BracketRule) -> Self {
+        let pattern = rule
+            .styles
+            .iter()
+            .map(|style| style.pattern())
+            .collect::<Vec<_>>()
+            .join("|");
+        Self {
+            re: Regex::new(&pattern).expect("valid regex"),
+            columns: rule.column_indices().unwrap_or_default(),
+            // 载入规则时已检查过，无效的表达式在此忽略
+            protect: rule
+                .protect
+                .iter()
+                .filter_map(|pattern| Regex::new(pattern).ok())
+                .collect(),
+            rule,
+        }
+    }
+
+    /// `exclude` 中的文本与匹配 `protect` 的文本在 `value` 中出现的字节范围。
+    fn protected(&self, value: &str) -> Vec<(usize, usize)> {
+        let excluded = self.rule.exclude.iter().flat_map(|text| {
+            value
+                .match_indices(text.as_str())
+                .map(|(start, found)| (start, start + found.len()))
+        });
+        let matched = self.protect.iter().flat_map(|re| {
+            re.find_iter(value)
+                .map(|found| (found.start(), found.end()))
+        });
+        excluded.chain(matched).collect()
+    }
+}
+
+impl Default for StripParentheses {
+    fn default() -> Self {
+        Self::new(BracketRule::default())
+    }
+}
+
+impl Transform for StripParentheses {
+    fn name(&self) -> &str {
+        "brackets"
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        if !self.rule.applies_to_row(cell.row)
+            || !(self.columns.is_empty() || self.columns.contains(&cell.col))
+            || !self.re.is_match(&cell.value)
+        {
+            return;
+        }
+        let protected = self.protected(&cell.value);
+        let mut stripped = String::with_capacity(cell.value.len());
+        let mut last = 0;
+        for found in self.re.find_iter(&cell.value) {
+            if protected
+                .iter()
+                .any(|&(start, end)| found.start() >= start && found.end() <= end)
+            {
+                continue;
+            }
+            stripped.push_str(&cell.value[last..found.start()]);
+            last = found.end();
+            // 去掉两侧的括号
+            let mut note = found.as_str().chars();
+            note.next();
+            note.next_back();
+            let note = note.as_str().trim();
+            if !note.is_empty() {
+                cell.notes.push(note.to_string());
+            }
+        }
+        if last == 0 {
+            return;
+        }
+        stripped.push_str(&cell.value[last..]);
+        cell.value = stripped;
+        cell.red_fill = true;
+        cell.rules.push("brackets".to_string());
+    }
+}
+
+/// 按 `[[convert]]` 规则换算数值单元格的单位；缺测标记（如 -999）不换算。
+pub struct ConvertUnits {
+    /// (规则序号, 列号, 规则)
+    rules: Vec<(usize, u32, ConvertRule)>,
+    conditions: Conditions,
+    /// `[[missing_value]]` 规则中的缺测标记
+    markers: Vec<String>,
+}
+
+impl ConvertUnits {
+    pub fn new(rules: &RuleSet) -> Self {
+        Self {
+            rules: rules
+                .convert
+                .iter()
+                .enumerate()
+                .filter_map(|(i, rule)| Some((i, rule.column_index()?, rule.clone())))
+                .collect(),
+            conditions: rules.conditions,
+            markers: rules
+                .missing_value
+                .iter()
+                .map(|rule| rule.marker.clone())
+                .collect(),
+        }
+    }
+}
+
+impl Transform for ConvertUnits {
+    fn name(&self) -> &str {
+        "convert"
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        if self
+            .markers
+            .iter()
+            .any(|marker| cell.value.contains(marker))
+        {
+            return;
+        }
+        for (i, column, rule) in &self.rules {
+            if cell.col == *column
+                && cell.row >= rule.first_row
+                && let Some(converted) = rule.apply(&cell.value, &self.conditions)
+            {
+                cell.value = converted;
+                cell.rules.push(format!("convert#{}", i + 1));
+            }
+        }
+    }
+}
+
+/// 按 `[[compute]]` 规则用表达式改写数值单元格。
+pub struct ComputeValues {
+    /// (规则, 解析好的表达式)，与配置中的顺序一一对应
+    rules: Vec<(ComputeRule, Expr)>,
+    /// 各规则在本表中生效的列
+    columns: Vec<Vec<u32>>,
+}
+
+impl ComputeValues {
+    /// `rules` 须为载入规则时检查过的（见 [`RuleSet`]），表达式有误时 panic，而不是跳过该规则
+    /// 使之后的 `compute#N` 编号错位。
+    pub fn new(rules: Vec<ComputeRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let expr = rule
+                    .parse_expr()
+                    .expect("expression is checked when loading the rules");
+                (rule, expr)
+            })
+            .collect();
+        let mut compute = Self {
+            rules,
+            columns: Vec::new(),
+        };
+        compute.begin_sheet(&SheetCells::new(0, 0, &|_, _| String::new()));
+        compute
+    }
+}
+
+impl Transform for ComputeValues {
+    fn name(&self) -> &str {
+        "compute"
+    }
+
+    fn begin_sheet(&mut self, sheet: &SheetCells<'_>) {
+        self.columns = self
+            .rules
+            .iter()
+            .map(|(rule, _)| resolve_columns(&rule.columns, rule.code_row, sheet))
+            .collect();
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        for (i, ((rule, expr), columns)) in self.rules.iter().zip(&self.columns).enumerate() {
+            if cell.row < rule.first_row || !columns.contains(&cell.col) {
+                continue;
+            }
+            match rule.apply(expr, &cell.value) {
+                Ok(Some(value)) => {
+                    cell.value = value;
+                    cell.rules.push(format!("compute#{}", i + 1));
+                }
+                Ok(None) => {}
+                Err(err) => warn!(
+                    "第 {} 条计算规则无法处理 {}（{}）: {err}",
+                    i + 1,
+                    coordinate_from_index(&cell.col, &cell.row),
+                    cell.value
+                ),
+            }
+        }
+    }
+}
+
+/// 按 `[[consistency]]` 规则检查同一行中各列的关系，把违反规则的行中 `right` 各列的单元格标红。
+/// 数值不改写；比较的是各步骤处理前的原始值。
+pub struct CheckConsistency {
+    rules: Vec<ConsistencyRule>,
+    /// 本表中违反规则的单元格：(行, 列, 规则序号)
+    violations: HashSet<(u32, u32, usize)>,
+}
+
+impl CheckConsistency {
+    pub fn new(rules: Vec<ConsistencyRule>) -> Self {
+        Self {
+            rules,
+            violations: HashSet::new(),
+        }
+    }
+}
+
+/// `specs` 在本表中对应的列，因子编码按 `code_row` 行查找；找不到的因子编码给出警告。
+fn resolve_columns(specs: &[String], code_row: u32, sheet: &SheetCells<'_>) -> Vec<u32> {
+    let mut columns = Vec::new();
+    for spec in specs {
+        match ColumnSpec::parse(spec) {
+            Some(ColumnSpec::Columns(start, end)) => columns.extend(start..=end),
+            Some(ColumnSpec::Factor(code)) => {
+                let found = (1..=sheet.width)
+                    .filter(|&col| sheet.value(code_row, col).trim() == code)
+                    .collect::<Vec<_>>();
+                if found.is_empty() {
+                    warn!("第 {code_row} 行中找不到因子编码 {code}，规则中的这一项不生效");
+                }
+                columns.extend(found);
+            }
+            None => {}
+        }
+    }
+    columns
+}
+
+/// 各列中有效数值（非负数）之和；都没有有效数值时返回 `None`。
+fn column_sum(sheet: &SheetCells<'_>, row: u32, columns: &[u32]) -> Option<f64> {
+    columns
+        .iter()
+        .filter_map(|&col| sheet.value(row, col).trim().parse::<f64>().ok())
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .fold(None, |sum, value| Some(sum.unwrap_or(0.0) + value))
+}
+
+impl Transform for CheckConsistency {
+    fn name(&self) -> &str {
+        "consistency"
+    }
+
+    fn begin_sheet(&mut self, sheet: &SheetCells<'_>) {
+        self.violations.clear();
+        for (i, rule) in self.rules.iter().enumerate() {
+            let left = resolve_columns(&rule.left, rule.code_row, sheet);
+            let right = resolve_columns(&rule.right, rule.code_row, sheet);
+            if left.is_empty() || right.is_empty() {
+                continue;
+            }
+            for row in rule.first_row..=sheet.height {
+                let (Some(lhs), Some(rhs)) = (
+                    column_sum(sheet, row, &left),
+                    column_sum(sheet, row, &right),
+                ) else {
+                    continue;
+                };
+                if lhs > rhs + rule.tolerance {
+                    warn!(
+                        "第 {row} 行不满足 {}: {} > {}",
+                        rule.label(),
+                        units::format_number(lhs, None),
+                        units::format_number(rhs, None)
+                    );
+                    self.violations
+                        .extend(right.iter().map(|&col| (row, col, i)));
+                }
+            }
+        }
+    }
+
+    fn apply_cell(&self, cell: &mut CellState) {
+        for i in 0..self.rules.len() {
+            if self.violations.contains(&(cell.row, cell.col, i)) {
+                cell.red_fill = true;
+                cell.flagged = true;
+                cell.rules.push(format!("consistency#{}", i + 1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在每个单元格后追加后缀的示例插件。
+    struct Suffix;
+
+    impl Transform for Suffix {
+        fn name(&self) -> &str {
+            "suffix"
+        }
+
+        fn apply_cell(&self, cell: &mut CellState) {
+            if cell.row >= 3 {
+                cell.value.push('!');
+                cell.rules.push("suffix".to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn builds_steps_in_configured_order() -> crate::error::Result<()> {
+        let rules = RuleSet::from_toml_str(r#"pipeline = ["brackets", "replace"]"#)?;
+        let mut pipeline = Pipeline::from_rules(&rules);
+        pipeline.insert(1, Suffix);
+        assert_eq!(
+            pipeline.names().collect::<Vec<_>>(),
+            ["brackets", "suffix", "replace"]
+        );
+
+        let mut cell = CellState::new(4, 1, "12(C)".to_string());
+        pipeline.apply_cell(&mut cell);
+        assert_eq!(cell.value, "12!");
+        assert!(cell.red_fill);
+        assert_eq!(cell.rules, ["brackets", "suffix"]);
+        Ok(())
+    }
+
+    #[test]
+    fn strips_configured_brackets() -> crate::error::Result<()> {
+        let strip = |step: &StripParentheses, row, col, value: &str| {
+            let mut cell = CellState::new(row, col, value.to_string());
+            step.apply_cell(&mut cell);
+            (cell.value, cell.red_fill)
+        };
+
+        let builtin = StripParentheses::default();
+        assert_eq!(
+            strip(&builtin, 4, 2, "1.5（备注）"),
+            ("1.5".to_string(), true)
+        );
+        assert_eq!(
+            strip(&builtin, 4, 2, "1.5(备注)"),
+            ("1.5".to_string(), true)
+        );
+        assert_eq!(
+            strip(&builtin, 2, 2, "1.5(备注)"),
+            ("1.5(备注)".to_string(), false)
+        );
+
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [brackets]
+            styles = ["full_width"]
+            min_row = 5
+            columns = ["B"]
+            exclude = ["苯并(a)芘", "（标）"]
+            "#,
+        )?;
+        let step = StripParentheses::new(rules.brackets);
+        assert_eq!(strip(&step, 5, 2, "1.5（备注）"), ("1.5".to_string(), true));
+        assert_eq!(
+            strip(&step, 5, 2, "1.5(备注)"),
+            ("1.5(备注)".to_string(), false)
+        );
+        assert_eq!(
+            strip(&step, 4, 2, "1.5（备注）"),
+            ("1.5（备注）".to_string(), false)
+        );
+        assert_eq!(
+            strip(&step, 5, 3, "1.5（备注）"),
+            ("1.5（备注）".to_string(), false)
+        );
+        assert_eq!(
+            strip(&step, 5, 2, "2（标）（手工）"),
+            ("2（标）".to_string(), true)
+        );
+        assert_eq!(
+            strip(&step, 5, 2, "苯并(a)芘"),
+            ("苯并(a)芘".to_string(), false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_protected_species_names() -> crate::error::Result<()> {
+        let strip = |step: &StripParentheses, value: &str| {
+            let mut cell = CellState::new(4, 2, value.to_string());
+            step.apply_cell(&mut cell);
+            (cell.value, cell.red_fill)
+        };
+
+        let builtin = StripParentheses::default();
+        for name in ["苯并(a)芘", "茚并(1,2,3-cd)芘", "二苯并（a,h）蒽"] {
+            assert_eq!(strip(&builtin, name), (name.to_string(), false));
+        }
+        assert_eq!(
+            strip(&builtin, "苯并(k)荧蒽(手工)"),
+            ("苯并(k)荧蒽".to_string(), true)
+        );
+
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [brackets]
+            protect = ['^\(\d+\)$']
+            "#,
+        )?;
+        let step = StripParentheses::new(rules.brackets);
+        assert_eq!(strip(&step, "(12)"), ("(12)".to_string(), false));
+        assert_eq!(strip(&step, "苯并(a)芘"), ("苯并芘".to_string(), true));
+        Ok(())
+    }
+
+    #[test]
+    fn marks_rows_violating_consistency() -> crate::error::Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[consistency]]
+            name = "NMHC ≤ THC"
+            left = ["a24088"]
+            right = ["a24087"]
+
+            [[consistency]]
+            left = ["D:E"]
+            right = ["B"]
+            tolerance = 0.5
+            "#,
+        )?;
+        let cells = [
+            ["时间", "THC", "NMHC", "乙烷", "丙烷"],
+            ["", "", "", "", ""],
+            ["", "a24087", "a24088", "a24001", "a24002"],
+            ["01:00", "2.0", "1.5", "1", "1"],
+            ["02:00", "1.0", "1.2", "0.8", "0.8"],
+            ["03:00", "-99", "1.2", "9", "9"],
+        ];
+        let value_at = |row: u32, col: u32| cells[row as usize - 1][col as usize - 1].to_string();
+        let mut step = CheckConsistency::new(rules.consistency);
+        step.begin_sheet(&SheetCells::new(6, 5, &value_at));
+
+        let check = |row, col| {
+            let mut cell = CellState::new(row, col, value_at(row, col));
+            step.apply_cell(&mut cell);
+            (cell.value == value_at(row, col), cell.red_fill, cell.rules)
+        };
+        // 第 4 行：NMHC 1.5 ≤ THC 2.0；乙烷+丙烷 2 ≤ 2.0 + 0.5
+        assert_eq!(check(4, 2), (true, false, vec![]));
+        // 第 5 行两条规则都不满足
+        assert_eq!(
+            check(5, 2),
+            (
+                true,
+                true,
+                vec!["consistency#1".to_string(), "consistency#2".to_string()]
+            )
+        );
+        assert_eq!(check(5, 3), (true, false, vec![]));
+        // 第 6 行 THC 无效，不检查
+        assert_eq!(check(6, 2), (true, false, vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn scopes_replace_rules_to_columns() -> crate::error::Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[replace]]
+            find = "ND"
+            replace = "-999"
+            columns = ["C", "a24002"]
+            min_row = 4
+            whole_cell = true
+
+            [[replace]]
+            find = "."
+            replace = ","
+            columns = ["B"]
+            "#,
+        )?;
+        let cells = [
+            ["时间", "THC", "NMHC", "丙烷"],
+            ["", "", "", ""],
+            ["", "a24087", "a24088", "a24002"],
+            ["01:00", "1.5", "ND", "ND"],
+            ["ND", "ND", " ND ", "NDX"],
+        ];
+        let value_at = |row: u32, col: u32| cells[row as usize - 1][col as usize - 1].to_string();
+        let mut step = ReplaceStrings::new(rules.replace);
+        step.begin_sheet(&SheetCells::new(5, 4, &value_at));
+
+        let apply = |row, col| {
+            let mut cell = CellState::new(row, col, value_at(row, col));
+            step.apply_cell(&mut cell);
+            cell.value
+        };
+        assert_eq!(apply(4, 3), "-999");
+        assert_eq!(apply(4, 4), "-999");
+        assert_eq!(apply(5, 3), "-999");
+        // 只替换整格等于 find 的单元格，且只在指定的列
+        assert_eq!(apply(5, 4), "NDX");
+        assert_eq!(apply(5, 1), "ND");
+        assert_eq!(apply(5, 2), "ND");
+        assert_eq!(apply(3, 3), "a24088");
+        assert_eq!(apply(4, 2), "1,5");
+        assert_eq!(apply(4, 1), "01:00");
+        Ok(())
+    }
+
+    #[test]
+    fn normalizes_species_names() -> crate::error::Result<()> {
+        let rules = RuleSet::from_toml_str("[species]\n\"甲苯\" = [\"Tol\"]\n")?;
+        let step = NormalizeSpecies::new(rules.species);
+        let apply = |value: &str| {
+            let mut cell = CellState::new(2, 2, value.to_string());
+            step.apply_cell(&mut cell);
+            (cell.value, cell.rules)
+        };
+        assert_eq!(
+            apply("m,p-Xylene(ppbv)"),
+            (
+                "间/对-二甲苯(ppbv)".to_string(),
+                vec!["species".to_string()]
+            )
+        );
+        assert_eq!(apply("TOL").0, "甲苯");
+        assert_eq!(apply("邻-二甲苯"), ("邻-二甲苯".to_string(), vec![]));
+        assert_eq!(apply("0.35").0, "0.35");
+
+        // 默认流水线不含 species：英文名称不变，二甲苯的写法仍由内置替换规则统一
+        let pipeline = Pipeline::from_rules(&RuleSet::builtin());
+        assert!(pipeline.names().all(|name| name != "species"));
+        let apply = |value: &str| {
+            let mut cell = CellState::new(2, 2, value.to_string());
+            pipeline.apply_cell(&mut cell);
+            cell.value
+        };
+        for header in ["Benzene(ppbv)", "Toluene", "NO3", "Sodium"] {
+            assert_eq!(apply(header), header);
+        }
+        assert_eq!(apply("邻二甲苯浓度"), "邻-二甲苯浓度");
+        assert_eq!(apply("间、对-二甲苯(ppbv)"), "间/对-二甲苯(ppbv)");
+        Ok(())
+    }
+
+    #[test]
+    fn computes_values_with_expressions() -> crate::error::Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[compute]]
+            columns = ["a24041"]
+            expr = 'if value < 0 then "-999#a24041" else round(value, 3)'
+            code_row = 1
+            first_row = 2
+
+            [[compute]]
+            columns = ["C"]
+            expr = "value * 1.88"
+            decimals = 2
+            first_row = 2
+
+            [[compute]]
+            columns = ["C"]
+            expr = "value > 1"
+            first_row = 2
+            "#,
+        )?;
+        let cells = [
+            ["", "a24041", "a24088"],
+            ["01:00", "-999", "1.5"],
+            ["02:00", "1.23456", "x"],
+        ];
+        let value_at = |row: u32, col: u32| cells[row as usize - 1][col as usize - 1].to_string();
+        let mut step = ComputeValues::new(rules.compute);
+        step.begin_sheet(&SheetCells::new(3, 3, &value_at));
+        let apply = |row, col| {
+            let mut cell = CellState::new(row, col, value_at(row, col));
+            step.apply_cell(&mut cell);
+            (cell.value, cell.rules)
+        };
+        assert_eq!(
+            apply(2, 2),
+            ("-999#a24041".to_string(), vec!["compute#1".to_string()])
+        );
+        assert_eq!(apply(3, 2).0, "1.235");
+        // 第 3 条规则结果为布尔值，单元格保持第 2 条的结果
+        assert_eq!(
+            apply(2, 3),
+            ("2.82".to_string(), vec!["compute#2".to_string()])
+        );
+        assert_eq!(apply(3, 3), ("x".to_string(), vec![]));
+
+        let err =
+            RuleSet::from_toml_str("[[compute]]\ncolumns = ['C']\nexpr = 'value *'").unwrap_err();
+        assert!(err.to_string().contains("表达式不完整"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn replaces_regex_matches_with_capture_groups() -> crate::error::Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[replace]]
+            pattern = '\s*[,，]\s*'
+            replace = ", "
+
+            [[replace]]
+            pattern = '(?<name>.+?)\s*\((?:ppbv|ppbC)\)'
+            replace = "${name}"
+            whole_cell = true
+            red_fill = true
+            "#,
+        )?;
+        let step = ReplaceStrings::new(rules.replace);
+        let apply = |value: &str| {
+            let mut cell = CellState::new(3, 2, value.to_string());
+            step.apply_cell(&mut cell);
+            (cell.value, cell.red_fill, cell.rules)
+        };
+        assert_eq!(
+            apply("苯,甲苯 ，乙苯"),
+            (
+                "苯, 甲苯, 乙苯".to_string(),
+                false,
+                vec!["replace#1".to_string()]
+            )
+        );
+        assert_eq!(
+            apply(" 乙烷 (ppbv) "),
+            ("乙烷".to_string(), true, vec!["replace#2".to_string()])
+        );
+        assert_eq!(apply("乙烷 (ppbv) 平均").0, "乙烷 (ppbv) 平均");
+        assert!(RuleSet::from_toml_str("[[replace]]\npattern = '('\nreplace = ''").is_err());
+        assert!(
+            RuleSet::from_toml_str("[[replace]]\nfind = 'a'\npattern = 'a'\nreplace = ''").is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1138 @@
+//! eemcg 替换规则。
+//!
+//! 规则从 `rules.toml`（或合并配置 `dttools.toml` 的 `[eemcg]`，查找顺序见 [`crate::config`]）
+//! 读取；找不到时使用内置规则（与原先硬编码的替换一致）。
+//! 文件中没有出现的小节同样使用该小节的内置规则。
+//!
+//! ```toml
+//! pipeline = ["rename_sheets", "replace", "missing_value", "flag_codes", "brackets", "convert", "compute", "consistency"]
+//!                             # 处理步骤及其顺序（可选，默认如左）
+//!
+//! [[rename_sheets]]
+//! from = "甲烷非甲烷分析仪"   # 要重命名的工作表名称（与 pattern 二选一）
+//! pattern = '^VOCs在线监测仪(_\d+)?$' # 或匹配工作表名称的正则表达式
+//! to = "VOCs监测仪"           # 新名称，pattern 时可用 $1、${name} 引用捕获组（必填）
+//!
+//! [[replace]]
+//! find = "甲烷非甲烷分析仪"   # 要查找的子串（与 pattern 二选一）
+//! pattern = '\s*\(ppbv\)$'    # 或正则表达式，替换所有匹配
+//! replace = "NMHC监测仪"      # 替换后的文本，pattern 时可用 $1、${name} 引用捕获组（必填）
+//! red_fill = false            # 命中后是否设置红色背景（可选，默认 false）
+//! min_row = 1                 # 生效的起始行，1 起计、含本行（可选）
+//! max_row = 2                 # 生效的结束行，含本行（可选）
+//! columns = ["AB", "F:H"]     # 生效的列：列名、列范围或 code_row 行中的因子编码（可选，默认全部列）
+//! code_row = 3                # columns 中因子编码所在行（可选，默认 3）
+//! whole_cell = false          # 只替换内容（去掉首尾空白后）恰好等于 find 的单元格（可选，默认 false）
+//!
+//! [[missing_value]]
+//! trigger = "I3"              # 触发单元格（必填）
+//! expect = "a24514"           # 触发单元格的值等于它时规则生效（必填）
+//! replacement = "-999#a24041" # 同列、触发单元格以下含 marker 的单元格改写为此值（可选，默认取编码表中 expect 的缺测编码）
+//! marker = "-999"             # 缺测标记（可选，默认 "-999"）
+//!
+//! [[flag_codes]]
+//! factor = "a24088"           # 因子编码，code_row 行中等于它的列使用这张码表（必填）
+//! code_row = 3                # 因子编码所在行（可选，默认 3）
+//! missing = "a24041"          # 缺测标记改写为 -999#a24041（可选，默认不改写）
+//! marker = "-999"             # 缺测标记（可选，默认 "-999"）
+//! calibration = "C"           # 12.3(C) 改写为 12.3#C（可选，默认 "C"）
+//! maintenance = "M"           # 12.3(M)、12.3(RM) 的标记码（可选，默认 "M"）
+//! invalid = "N"               # 12.3(N) 的标记码（可选，默认 "N"）
+//!
+//! [[convert]]
+//! column = "C"                # 要换算的列（必填）
+//! from = "ppbv"               # 原单位：ppbv、ppbC 或 μg/m³（必填）
+//! to = "ppbC"                 # 目标单位（必填）
+//! carbon_number = 1           # 碳原子数，涉及 ppbC 时必填
+//! molecular_weight = 16.04    # 分子量 g/mol，涉及 μg/m³ 时必填
+//! first_row = 4               # 数据起始行，1 起计（可选，默认 4）
+//! decimals = 3                # 换算结果保留的小数位数（可选，默认最多 4 位）
+//!
+//! [[compute]]                 # 用表达式计算新值（语法见 [`super::expr`]）
+//! columns = ["a24041"]        # 列名、列范围或 code_row 行中的因子编码（必填）
+//! expr = 'if value < 0 then "-999#a24041" else round(value, 3)' # 表达式，value 为单元格数值（必填）
+//! code_row = 3                # 因子编码所在行（可选，默认 3）
+//! first_row = 4               # 数据起始行，1 起计（可选，默认 4）
+//! decimals = 3                # 数值结果保留的小数位数（可选，默认最多 4 位）
+//!
+//! [[consistency]]             # 跨列一致性检查：left 各列之和不应超过 right 各列之和
+//! name = "NMHC ≤ THC"         # 名称，写入日志（可选）
+//! left = ["a24088"]           # 列名（如 C）、列范围（如 F:BD）或 code_row 行中的因子编码（必填）
+//! right = ["a24087"]          # 同上（必填）
+//! tolerance = 0.0             # 允许 left 超出 right 的量（可选，默认 0）
+//! code_row = 3                # 因子编码所在行（可选，默认 3）
+//! first_row = 4               # 数据起始行，1 起计（可选，默认 4）
+//!
+//! [brackets]                  # 删除括号的范围（可选）
+//! styles = ["ascii", "full_width"] # 要删除的括号：ascii 为 ()，full_width 为 （）（可选，默认两者）
+//! min_row = 3                 # 生效的起始行，1 起计、含本行（可选，默认 3）
+//! max_row = 1000              # 生效的结束行，含本行（可选）
+//! columns = ["B", "C"]        # 生效的列（可选，默认全部列）
+//! exclude = ["苯并(a)芘"]      # 这些文本中的括号保留（可选）
+//! protect = ['并[(（][0-9a-z,，\-]+[)）]'] # 匹配这些正则表达式的文本中的括号保留（可选，默认如左）
+//! notes = "comment"           # 删除的括号内容：discard 丢弃、comment 写为批注、sheet 写入“备注”工作表（可选，默认 discard）
+//!
+//! [conditions]                # 换算 μg/m³ 时假定的状态（可选）
+//! temperature = 25.0          # ℃，默认 25
+//! pressure = 101.325          # kPa，默认 101.325
+//!
+//! [species]                   # 名称词典中增加的写法，键为标准名称（可选，见 crate::species）
+//! "间/对-二甲苯" = ["mp-Xylene"]
+//! ```
+//!
+//! 工作表按第一条匹配的 `rename_sheets` 规则重命名。
+//! `replace` 规则按文件中的顺序依次应用，前一条规则的结果会作为后一条规则的输入；
+//! 默认之后依次应用 `missing_value` 与 `flag_codes` 规则、删除括号，最后对数值单元格应用
+//! `convert` 规则（`flag_codes` 与 `convert` 没有内置规则），顺序可由 `pipeline` 调整。
+//! 按名称词典统一监测项目名称的 `species` 步骤默认不执行，需要时在 `pipeline` 中列出。
+//! 已是 `值#标记码` 形式的单元格不会再被标记。
+//! `consistency` 检查不改写数值，只把违反规则的行中 `right` 各列的单元格标红，比较的是
+//! 各步骤处理前的原始数值；缺测标记、带标记的值等非数值与负数不计入。
+
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use super::expr::{Expr, ParseError, Value};
+use super::pipeline::Step;
+use crate::codes;
+use crate::config::{self, Found};
+use crate::error::{Context, Result, config_error};
+use crate::flags::{self, DataFlag};
+use crate::species::Dictionary;
+use crate::units::{self, Conditions, Species, Unit};
+use crate::workbook;
+
+/// 默认的规则文件名（位于工作目录）。
+pub const RULES_FILE: &str = "rules.toml";
+
+/// 一条工作表重命名规则：名称等于 `from` 或匹配 `pattern` 的工作表改名为 `to`。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SheetRename {
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    pub to: String,
+}
+
+/// Excel 工作表名称中不允许的字符。
+const INVALID_SHEET_CHARS: [char; 7] = ['[', ']', ':', '*', '?', '/', '\\'];
+
+impl SheetRename {
+    fn builtin(from: &str, to: &str) -> Self {
+        Self {
+            from: Some(from.to_string()),
+            pattern: None,
+            to: to.to_string(),
+        }
+    }
+}
+
+/// 一条查找/替换规则：查找子串 `find`，或匹配正则表达式 `pattern`（二选一）。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplaceRule {
+    #[serde(default)]
+    pub find: String,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    pub replace: String,
+    #[serde(default)]
+    pub red_fill: bool,
+    #[serde(default)]
+    pub min_row: Option<u32>,
+    #[serde(default)]
+    pub max_row: Option<u32>,
+    /// 生效的列（见 [`ColumnSpec`]）；为空时适用于全部列
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default = "default_code_row")]
+    pub code_row: u32,
+    /// 只替换内容恰好等于 `find` 的单元格，整格改为 `replace`
+    #[serde(default)]
+    pub whole_cell: bool,
+}
+
+impl ReplaceRule {
+    fn builtin(find: &str, replace: &str) -> Self {
+        Self {
+            find: find.to_string(),
+            pattern: None,
+            replace: replace.to_string(),
+            red_fill: false,
+            min_row: None,
+            max_row: None,
+            columns: Vec::new(),
+            code_row: default_code_row(),
+            whole_cell: false,
+        }
+    }
+
+    /// 规则是否适用于给定的行（1 起计）。
+    pub fn applies_to_row(&self, row_1based: u32) -> bool {
+        self.min_row.is_none_or(|min| row_1based >= min)
+            && self.max_row.is_none_or(|max| row_1based <= max)
+    }
+
+    /// 编译 `pattern`；`whole_cell` 时须匹配整个单元格（首尾空白除外）。没有 `pattern` 时返回 `None`。
+    pub fn regex(&self) -> Result<Option<Regex>> {
+        let Some(pattern) = &self.pattern else {
+            return Ok(None);
+        };
+        let pattern = if self.whole_cell {
+            format!(r"^\s*(?:{pattern})\s*$")
+        } else {
+            pattern.clone()
+        };
+        Ok(Some(Regex::new(&pattern)?))
+    }
+
+    /// 按 `find` 替换后的文本；不命中时返回 `None`。使用 `pattern` 的规则见 [`Self::regex`]。
+    pub fn apply(&self, value: &str) -> Option<String> {
+        if self.whole_cell {
+            (value.trim() == self.find).then(|| self.replace.clone())
+        } else {
+            value
+                .contains(&self.find)
+                .then(|| value.replace(&self.find, &self.replace))
+        }
+    }
+}
+
+/// 缺测值改写规则：触发单元格的值为 `expect` 时，同列中位于触发单元格以下、
+/// 含有 `marker` 的单元格改写为 `replacement`。`replacement` 省略时取编码表中 `expect`
+/// 的缺测编码（见 [`codes::missing_flag`]）。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MissingValueRule {
+    pub trigger: String,
+    pub expect: String,
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default = "default_marker")]
+    pub marker: String,
+}
+
+fn default_marker() -> String {
+    "-999".to_string()
+}
+
+impl MissingValueRule {
+    fn builtin(trigger: &str, expect: &str) -> Self {
+        let marker = default_marker();
+        Self {
+            trigger: trigger.to_string(),
+            expect: expect.to_string(),
+            replacement: codes::missing_flag(expect, &marker)
+                .expect("builtin trigger codes have missing codes"),
+            marker,
+        }
+    }
+
+    /// 触发单元格的位置 (列, 行)，均为 1 起计；地址无效时返回 `None`。
+    pub fn trigger_position(&self) -> Option<(u32, u32)> {
+        parse_a1(&self.trigger)
+    }
+}
+
+/// 一张标记码表：`code_row` 行的值为 `factor` 的列中，其下方带标记的数值（如 `12.3(C)`）和
+/// 缺测标记改写为 HJ 212 的 `值#标记码`。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlagCodes {
+    pub factor: String,
+    #[serde(default = "default_code_row")]
+    pub code_row: u32,
+    #[serde(default)]
+    pub missing: Option<String>,
+    #[serde(default = "default_marker")]
+    pub marker: String,
+    #[serde(default)]
+    pub calibration: Option<String>,
+    #[serde(default)]
+    pub maintenance: Option<String>,
+    #[serde(default)]
+    pub invalid: Option<String>,
+}
+
+fn default_code_row() -> u32 {
+    3
+}
+
+impl FlagCodes {
+    /// 某类标记的标记码，未配置时为标记字母。
+    pub fn code(&self, flag: DataFlag) -> &str {
+        let code = match flag {
+            DataFlag::Calibration => &self.calibration,
+            DataFlag::Maintenance => &self.maintenance,
+            DataFlag::Invalid => &self.invalid,
+        };
+        code.as_deref().unwrap_or(flag.letter())
+    }
+
+    /// 按码表改写单元格文本；无需改写时返回 `None`。
+    pub fn apply(&self, text: &str) -> Option<String> {
+        if let Some((value, flag)) = flags::parse_marked(text) {
+            let flagged = flags::join(value, self.code(flag));
+            return (flagged != text).then_some(flagged);
+        }
+        if text.trim() == self.marker {
+            return self
+                .missing
+                .as_deref()
+                .map(|code| flags::join(&self.marker, code));
+        }
+        None
+    }
+
+    fn codes(&self) -> impl Iterator<Item = &str> {
+        [
+            &self.missing,
+            &self.calibration,
+            &self.maintenance,
+            &self.invalid,
+        ]
+        .into_iter()
+        .filter_map(|code| code.as_deref())
+    }
+}
+
+/// 单位换算规则：把 `column` 列自 `first_row` 行起的数值从 `from` 换算为 `to`。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConvertRule {
+    pub column: String,
+    pub from: Unit,
+    pub to: Unit,
+    #[serde(default)]
+    pub molecular_weight: Option<f64>,
+    #[serde(default)]
+    pub carbon_number: Option<u32>,
+    #[serde(default = "default_first_row")]
+    pub first_row: u32,
+    #[serde(default)]
+    pub decimals: Option<u32>,
+}
+
+fn default_first_row() -> u32 {
+    4
+}
+
+impl ConvertRule {
+    /// 列号，1 起计；列名无效时返回 `None`。
+    pub fn column_index(&self) -> Option<u32> {
+        workbook::column_index(self.column.trim())
+    }
+
+    pub fn species(&self) -> Species {
+        Species {
+            molecular_weight: self.molecular_weight,
+            carbon_number: self.carbon_number,
+        }
+    }
+
+    /// 换算单元格文本；不是数值时返回 `None`。
+    pub fn apply(&self, text: &str, conditions: &Conditions) -> Option<String> {
+        let value: f64 = text.trim().parse().ok()?;
+        let converted =
+            units::convert(value, self.from, self.to, &self.species(), conditions).ok()?;
+        Some(units::format_number(converted, self.decimals))
+    }
+}
+
+/// 计算规则：`columns` 各列自 `first_row` 起的数值单元格改写为表达式 `expr` 的结果；
+/// 非数值的单元格不变。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComputeRule {
+    pub columns: Vec<String>,
+    pub expr: String,
+    #[serde(default = "default_code_row")]
+    pub code_row: u32,
+    #[serde(default = "default_first_row")]
+    pub first_row: u32,
+    #[serde(default)]
+    pub decimals: Option<u32>,
+}
+
+impl ComputeRule {
+    pub fn parse_expr(&self) -> std::result::Result<Expr, ParseError> {
+        Expr::parse(&self.expr)
+    }
+
+    /// 计算数值单元格的新文本；不是数值时返回 `Ok(None)`，表达式出错或结果为布尔值时返回错误说明。
+    pub fn apply(&self, expr: &Expr, text: &str) -> std::result::Result<Option<String>, String> {
+        let Some(value) = text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+        else {
+            return Ok(None);
+        };
+        match expr.eval(value)? {
+            Value::Number(number) => Ok(Some(units::format_number(number, self.decimals))),
+            Value::Text(text) => Ok(Some(text)),
+            Value::Bool(_) => Err("结果须为数值或文本，得到布尔值".to_string()),
+        }
+    }
+}
+
+/// 跨列一致性规则：同一行中 `left` 各列数值之和不应超过 `right` 各列之和加 `tolerance`，
+/// 如 NMHC ≤ THC、PM₂.₅ ≤ PM₁₀、各 VOC 组分之和 ≤ TVOC。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConsistencyRule {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    #[serde(default)]
+    pub tolerance: f64,
+    #[serde(default = "default_code_row")]
+    pub code_row: u32,
+    #[serde(default = "default_first_row")]
+    pub first_row: u32,
+}
+
+/// 一侧中的一项：列或因子编码。列号为 1~3 个字母（最大 `XFD`），因子编码须含数字（如
+/// `a24088`），因此 `NMHC` 这类名称不会被当作列号。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnSpec {
+    /// 列范围，1 起计、含两端；单列时两端相同
+    Columns(u32, u32),
+    /// `code_row` 行中等于此值的各列
+    Factor(String),
+}
+
+/// Excel 的最大列号（`XFD`）。
+const MAX_COLUMN: u32 = 16384;
+
+impl ColumnSpec {
+    /// 解析 `C`、`F:BD` 或因子编码（如 `a24088`）；都不是时返回 `None`。
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        if let Some((start, end)) = spec.split_once(':') {
+            let start = Self::column(start.trim())?;
+            let end = Self::column(end.trim())?;
+            return (start <= end).then_some(Self::Columns(start, end));
+        }
+        if let Some(col) = Self::column(spec) {
+            return Some(Self::Columns(col, col));
+        }
+        let code = !spec.is_empty()
+            && spec.chars().all(|c| c.is_ascii_alphanumeric())
+            && spec.chars().any(|c| c.is_ascii_digit());
+        code.then(|| Self::Factor(spec.to_string()))
+    }
+
+    fn column(letters: &str) -> Option<u32> {
+        if letters.len() > 3 {
+            return None;
+        }
+        workbook::column_index(letters).filter(|&col| col <= MAX_COLUMN)
+    }
+}
+
+impl ConsistencyRule {
+    /// 日志中的名称：`name`，缺省时为 `left ≤ right`。
+    pub fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{} ≤ {}", self.left.join("+"), self.right.join("+")))
+    }
+}
+
+/// 可删除的括号样式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BracketStyle {
+    /// 半角括号 `(…)`
+    Ascii,
+    /// 全角括号 `（…）`
+    FullWidth,
+}
+
+impl BracketStyle {
+    /// 匹配一对此样式的括号及其中内容的正则表达式。
+    pub fn pattern(self) -> &'static str {
+        match self {
+            Self::Ascii => r"\([^)]*\)",
+            Self::FullWidth => r"（[^）]*）",
+        }
+    }
+}
+
+/// 删除的括号内容的去向。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BracketNotes {
+    /// 丢弃
+    #[default]
+    Discard,
+    /// 写为所在单元格的批注
+    Comment,
+    /// 写入“备注”工作表（工作表、单元格、备注）
+    Sheet,
+}
+
+/// 删除括号的规则：`min_row`–`max_row` 行、`columns` 列中 `styles` 样式的括号及其内容被删除，
+/// 单元格设置红色背景；`exclude` 中的文本与匹配 `protect` 的文本所含的括号保留。
+/// 删除的内容按 `notes` 保留为批注或备注。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BracketRule {
+    pub styles: Vec<BracketStyle>,
+    pub min_row: u32,
+    pub max_row: Option<u32>,
+    pub columns: Vec<String>,
+    pub exclude: Vec<String>,
+    pub protect: Vec<String>,
+    pub notes: BracketNotes,
+}
+
+/// 稠环化合物名称中的位置标记，如 苯并(a)芘、茚并(1,2,3-cd)芘、二苯并(a,h)蒽。
+const FUSED_RING_LOCANTS: &str = r"并[(（][0-9a-z,，\-]+[)）]";
+
+impl Default for BracketRule {
+    fn default() -> Self {
+        Self {
+            styles: vec![BracketStyle::Ascii, BracketStyle::FullWidth],
+            min_row: 3,
+            max_row: None,
+            columns: Vec::new(),
+            exclude: Vec::new(),
+            protect: vec![FUSED_RING_LOCANTS.to_string()],
+            notes: BracketNotes::Discard,
+        }
+    }
+}
+
+impl BracketRule {
+    /// 生效的列号，1 起计；为空表示全部列。列名无效时返回 `None`。
+    pub fn column_indices(&self) -> Option<Vec<u32>> {
+        self.columns
+            .iter()
+            .map(|column| workbook::column_index(column.trim()))
+            .collect()
+    }
+
+    /// 规则是否适用于给定的行（1 起计）。
+    pub fn applies_to_row(&self, row_1based: u32) -> bool {
+        row_1based >= self.min_row && self.max_row.is_none_or(|max| row_1based <= max)
+    }
+}
+
+/// 解析 A1 形式的单元格地址，返回 (列, 行)，均为 1 起计。
+fn parse_a1(address: &str) -> Option<(u32, u32)> {
+    let address = address.trim();
+    let split = address.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = address.split_at(split);
+    let col = workbook::column_index(letters)?;
+    let row: u32 = digits.parse().ok()?;
+    (row > 0).then_some((col, row))
+}
+
+/// 一组 eemcg 规则。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSet {
+    #[serde(default = "builtin_rename_sheets")]
+    pub rename_sheets: Vec<SheetRename>,
+    #[serde(default = "builtin_replace")]
+    pub replace: Vec<ReplaceRule>,
+    #[serde(default = "builtin_missing_value")]
+    pub missing_value: Vec<MissingValueRule>,
+    #[serde(default)]
+    pub flag_codes: Vec<FlagCodes>,
+    #[serde(default)]
+    pub convert: Vec<ConvertRule>,
+    #[serde(default)]
+    pub compute: Vec<ComputeRule>,
+    #[serde(default)]
+    pub consistency: Vec<ConsistencyRule>,
+    #[serde(default)]
+    pub brackets: BracketRule,
+    #[serde(default)]
+    pub conditions: Conditions,
+    #[serde(default)]
+    pub species: Dictionary,
+    #[serde(default = "default_pipeline")]
+    pub pipeline: Vec<Step>,
+}
+
+fn default_pipeline() -> Vec<Step> {
+    Step::DEFAULT_ORDER.to_vec()
+}
+
+/// 与 Go 版本一致的工作表重命名。
+fn builtin_rename_sheets() -> Vec<SheetRename> {
+    vec![
+        SheetRename::builtin("甲烷非甲烷分析仪", "NMHC监测仪"),
+        SheetRename::builtin("VOCs在线监测仪", "VOCs监测仪"),
+    ]
+}
+
+fn builtin_replace() -> Vec<ReplaceRule> {
+    vec![
+        ReplaceRule::builtin("甲烷非甲烷分析仪", "NMHC监测仪"),
+        ReplaceRule::builtin("VOCs在线监测仪", "VOCs监测仪"),
+        ReplaceRule::builtin("总烃(ppbv)", "总烃(ppbC)"),
+        ReplaceRule::builtin("总烃(ppbvC)", "总烃(ppbC)"),
+        ReplaceRule::builtin("间、对-二甲苯", "间/对-二甲苯"),
+        ReplaceRule::builtin("邻二甲苯", "邻-二甲苯"),
+    ]
+}
+
+fn builtin_missing_value() -> Vec<MissingValueRule> {
+    vec![
+        MissingValueRule::builtin("I3", "a24514"),
+        MissingValueRule::builtin("K3", "a24011"),
+        MissingValueRule::builtin("Q3", "a24510"),
+        MissingValueRule::builtin("AY3", "a25014"),
+    ]
+}
+
+impl RuleSet {
+    /// 内置规则，与早期硬编码的替换一致。
+    pub fn builtin() -> Self {
+        Self {
+            rename_sheets: builtin_rename_sheets(),
+            replace: builtin_replace(),
+            missing_value: builtin_missing_value(),
+            flag_codes: Vec::new(),
+            convert: Vec::new(),
+            compute: Vec::new(),
+            consistency: Vec::new(),
+            brackets: BracketRule::default(),
+            conditions: Conditions::default(),
+            species: Dictionary::default(),
+            pipeline: default_pipeline(),
+        }
+    }
+
+    /// 从 TOML 文本解析规则并检查其有效性。
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        Self::checked(toml::from_str(content).context("规则文件格式错误")?)
+    }
+
+    /// 由已解析的 TOML 表构造，供 `dttools config check` 逐项检查。
+    pub(crate) fn from_table(table: toml::Table) -> Result<Self> {
+        Self::checked(RuleSet::deserialize(toml::Value::Table(table)).context("规则文件格式错误")?)
+    }
+
+    /// 检查规则的有效性。
+    fn checked(mut rules: RuleSet) -> Result<Self> {
+        for (i, rule) in rules.rename_sheets.iter().enumerate() {
+            match (&rule.from, &rule.pattern) {
+                (Some(from), None) if !from.is_empty() => {}
+                (None, Some(pattern)) => {
+                    Regex::new(pattern).with_context(|| {
+                        format!("第 {} 条工作表重命名规则的 pattern 无效: {pattern}", i + 1)
+                    })?;
+                }
+                _ => {
+                    return Err(config_error!(
+                        "第 {} 条工作表重命名规则须指定 from 或 pattern 之一",
+                        i + 1
+                    ));
+                }
+            }
+            if rule.to.trim().is_empty() || rule.to.contains(INVALID_SHEET_CHARS) {
+                return Err(config_error!(
+                    "第 {} 条工作表重命名规则的 to 不是有效的工作表名称: {:?}",
+                    i + 1,
+                    rule.to
+                ));
+            }
+        }
+        for (i, rule) in rules.replace.iter().enumerate() {
+            match &rule.pattern {
+                None if rule.find.is_empty() => {
+                    return Err(config_error!(
+                        "第 {} 条替换规则须指定 find 或 pattern 之一",
+                        i + 1
+                    ));
+                }
+                Some(_) if !rule.find.is_empty() => {
+                    return Err(config_error!(
+                        "第 {} 条替换规则不能同时指定 find 与 pattern",
+                        i + 1
+                    ));
+                }
+                Some(pattern) => {
+                    rule.regex().with_context(|| {
+                        format!("第 {} 条替换规则的 pattern 无效: {pattern}", i + 1)
+                    })?;
+                    // 能匹配空串的正则（如 `x*`）会在每个字符之间插入替换文本
+                    if Regex::new(pattern).is_ok_and(|regex| regex.is_match("")) {
+                        return Err(config_error!(
+                            "第 {} 条替换规则的 pattern 能匹配空字符串: {pattern}",
+                            i + 1
+                        ));
+                    }
+                }
+                None => {}
+            }
+            if let (Some(min), Some(max)) = (rule.min_row, rule.max_row)
+                && min > max
+            {
+                return Err(config_error!(
+                    "第 {} 条替换规则的 min_row({min}) 大于 max_row({max})",
+                    i + 1
+                ));
+            }
+            if let Some(spec) = rule
+                .columns
+                .iter()
+                .find(|spec| ColumnSpec::parse(spec).is_none())
+            {
+                return Err(config_error!("第 {} 条替换规则中的列无效: {spec:?}", i + 1));
+            }
+            if rule.code_row == 0 {
+                return Err(config_error!(
+                    "第 {} 条替换规则的 code_row 必须大于 0",
+                    i + 1
+                ));
+            }
+        }
+        for (i, rule) in rules.missing_value.iter_mut().enumerate() {
+            if rule.trigger_position().is_none() {
+                return Err(config_error!(
+                    "第 {} 条缺测值规则的 trigger 不是有效的单元格地址: {}",
+                    i + 1,
+                    rule.trigger
+                ));
+            }
+            if rule.marker.is_empty() {
+                return Err(config_error!("第 {} 条缺测值规则的 marker 不能为空", i + 1));
+            }
+            if rule.replacement.is_empty() {
+                rule.replacement =
+                    codes::missing_flag(&rule.expect, &rule.marker).ok_or_else(|| {
+                        config_error!(
+                            "第 {} 条缺测值规则未指定 replacement，编码表中也没有 {} 的缺测编码",
+                            i + 1,
+                            rule.expect
+                        )
+                    })?;
+            }
+        }
+        for (i, table) in rules.flag_codes.iter().enumerate() {
+            if table.factor.trim().is_empty() || table.code_row == 0 || table.marker.is_empty() {
+                return Err(config_error!(
+                    "第 {} 张标记码表的 factor、marker 不能为空，code_row 必须大于 0",
+                    i + 1
+                ));
+            }
+            if let Some(code) = table
+                .codes()
+                .find(|code| code.trim().is_empty() || code.contains('#'))
+            {
+                return Err(config_error!(
+                    "第 {} 张标记码表中的标记码无效: {code:?}",
+                    i + 1
+                ));
+            }
+        }
+        for (i, rule) in rules.convert.iter().enumerate() {
+            if rule.column_index().is_none() {
+                return Err(config_error!(
+                    "第 {} 条换算规则的 column 不是有效的列名: {}",
+                    i + 1,
+                    rule.column
+                ));
+            }
+            if rule.first_row == 0 {
+                return Err(config_error!(
+                    "第 {} 条换算规则的 first_row 必须大于 0",
+                    i + 1
+                ));
+            }
+            // 用 1 试算一次，提前发现缺少分子量或碳原子数
+            units::convert(1.0, rule.from, rule.to, &rule.species(), &rules.conditions)
+                .with_context(|| format!("第 {} 条换算规则", i + 1))?;
+        }
+        for (i, rule) in rules.compute.iter().enumerate() {
+            if rule.columns.is_empty() {
+                return Err(config_error!("第 {} 条计算规则的 columns 不能为空", i + 1));
+            }
+            if let Some(spec) = rule
+                .columns
+                .iter()
+                .find(|spec| ColumnSpec::parse(spec).is_none())
+            {
+                return Err(config_error!("第 {} 条计算规则中的列无效: {spec:?}", i + 1));
+            }
+            if rule.code_row == 0 || rule.first_row == 0 {
+                return Err(config_error!(
+                    "第 {} 条计算规则的 code_row、first_row 必须大于 0",
+                    i + 1
+                ));
+            }
+            if let Err(err) = rule.parse_expr() {
+                return Err(config_error!(
+                    "第 {} 条计算规则的表达式有误，{err}: {}",
+                    i + 1,
+                    rule.expr
+                ));
+            }
+        }
+        for (i, rule) in rules.consistency.iter().enumerate() {
+            if rule.left.is_empty() || rule.right.is_empty() {
+                return Err(config_error!(
+                    "第 {} 条一致性规则的 left、right 不能为空",
+                    i + 1
+                ));
+            }
+            if let Some(spec) = rule
+                .left
+                .iter()
+                .chain(&rule.right)
+                .find(|spec| ColumnSpec::parse(spec).is_none())
+            {
+                return Err(config_error!(
+                    "第 {} 条一致性规则中的列无效: {spec:?}",
+                    i + 1
+                ));
+            }
+            if rule.code_row == 0 || rule.first_row == 0 {
+                return Err(config_error!(
+                    "第 {} 条一致性规则的 code_row、first_row 必须大于 0",
+                    i + 1
+                ));
+            }
+            if !(rule.tolerance.is_finite() && rule.tolerance >= 0.0) {
+                return Err(config_error!(
+                    "第 {} 条一致性规则的 tolerance 不能为负数",
+                    i + 1
+                ));
+            }
+        }
+        let brackets = &rules.brackets;
+        if brackets.styles.is_empty() || brackets.min_row == 0 {
+            return Err(config_error!(
+                "[brackets] 的 styles 不能为空，min_row 必须大于 0"
+            ));
+        }
+        if let Some(max) = brackets.max_row
+            && brackets.min_row > max
+        {
+            return Err(config_error!(
+                "[brackets] 的 min_row({}) 大于 max_row({max})",
+                brackets.min_row
+            ));
+        }
+        if brackets.column_indices().is_none() {
+            return Err(config_error!(
+                "[brackets] 的 columns 中有无效的列名: {:?}",
+                brackets.columns
+            ));
+        }
+        if brackets.exclude.iter().any(String::is_empty) {
+            return Err(config_error!("[brackets] 的 exclude 中不能有空文本"));
+        }
+        for pattern in &brackets.protect {
+            Regex::new(pattern)
+                .with_context(|| format!("[brackets] 的 protect 中的正则表达式无效: {pattern}"))?;
+        }
+        for (i, step) in rules.pipeline.iter().enumerate() {
+            if rules.pipeline[..i].contains(step) {
+                return Err(config_error!("pipeline 中的步骤重复: {step:?}"));
+            }
+        }
+        let kelvin = rules.conditions.temperature + 273.15;
+        if kelvin <= 0.0 || rules.conditions.pressure <= 0.0 {
+            return Err(config_error!("[conditions] 的温度或压力无效"));
+        }
+        Ok(rules)
+    }
+
+    /// 读取规则文件；文件不存在时返回内置规则。
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_found(config::read_file(path)?)
+    }
+
+    /// 按查找顺序（见 [`crate::config`]）读取规则，`explicit` 为 `--rules` 指定的文件；
+    /// 都找不到时返回内置规则。
+    pub fn discover(explicit: Option<&Path>) -> Result<Self> {
+        Self::from_found(config::discover(explicit, RULES_FILE, "eemcg")?)
+    }
+
+    pub(crate) fn from_found(found: Option<Found>) -> Result<Self> {
+        let Some(found) = found else {
+            return Ok(Self::builtin());
+        };
+        found
+            .deserialize()
+            .context("规则文件格式错误")
+            .and_then(Self::checked)
+            .with_context(|| format!("规则文件: {found}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules_with_row_range() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[replace]]
+            find = "foo"
+            replace = "bar"
+            red_fill = true
+            min_row = 4
+            "#,
+        )?;
+        let rule = &rules.replace[0];
+        assert!(rule.red_fill);
+        assert!(!rule.applies_to_row(3));
+        assert!(rule.applies_to_row(4));
+        assert!(rule.applies_to_row(100));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_missing_value_rules() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[missing_value]]
+            trigger = "ab3"
+            expect = "a24088"
+            replacement = "-999#a24099"
+            "#,
+        )?;
+        assert_eq!(rules.replace, builtin_replace());
+        assert_eq!(rules.missing_value.len(), 1);
+        assert_eq!(rules.missing_value[0].trigger_position(), Some((28, 3)));
+        assert_eq!(rules.missing_value[0].marker, "-999");
+
+        let bad = RuleSet::from_toml_str(
+            r#"
+            [[missing_value]]
+            trigger = "3I"
+            expect = "a"
+            replacement = "b"
+            "#,
+        );
+        assert!(bad.is_err());
+
+        // 省略 replacement 时取编码表中的缺测编码
+        let rules = RuleSet::from_toml_str(
+            "[[missing_value]]\ntrigger = \"I3\"\nexpect = \"a24011\"\nmarker = \"-9999\"\n",
+        )?;
+        assert_eq!(rules.missing_value[0].replacement, "-9999#a24537");
+        let err =
+            RuleSet::from_toml_str("[[missing_value]]\ntrigger = \"I3\"\nexpect = \"a24001\"\n")
+                .unwrap_err();
+        assert!(err.to_string().contains("a24001 的缺测编码"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn parses_sheet_renames() -> Result<()> {
+        assert_eq!(
+            RuleSet::from_toml_str("")?.rename_sheets,
+            builtin_rename_sheets()
+        );
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[rename_sheets]]
+            pattern = '^VOCs在线监测仪(_\d+)?$'
+            to = "VOCs监测仪"
+            "#,
+        )?;
+        assert_eq!(rules.rename_sheets.len(), 1);
+
+        for bad in [
+            "to = \"x\"",
+            "from = \"a\"\npattern = \"a\"\nto = \"x\"",
+            "pattern = \"(\"\nto = \"x\"",
+            "from = \"a\"\nto = \"a/b\"",
+            "from = \"a\"\nto = \" \"",
+        ] {
+            assert!(RuleSet::from_toml_str(&format!("[[rename_sheets]]\n{bad}")).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_replace_pattern_matching_empty() {
+        for pattern in ["x*", "a|", "^", "(ND)?"] {
+            let err = RuleSet::from_toml_str(&format!(
+                "[[replace]]\npattern = '{pattern}'\nreplace = \"-\""
+            ))
+            .unwrap_err();
+            assert!(
+                err.to_string().contains("能匹配空字符串"),
+                "{pattern}: {err}"
+            );
+        }
+        assert!(RuleSet::from_toml_str("[[replace]]\npattern = 'x+'\nreplace = \"-\"").is_ok());
+    }
+
+    #[test]
+    fn rejects_inverted_row_range() {
+        let err = RuleSet::from_toml_str(
+            r#"
+            [[replace]]
+            find = "foo"
+            replace = "bar"
+            min_row = 5
+            max_row = 2
+            "#,
+        );
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn flag_codes_rewrite_marked_values() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[flag_codes]]
+            factor = "a24088"
+            missing = "a24041"
+            maintenance = "Md"
+            "#,
+        )?;
+        let table = &rules.flag_codes[0];
+        assert_eq!(table.code_row, 3);
+        assert_eq!(table.apply("12.3(C)").as_deref(), Some("12.3#C"));
+        assert_eq!(table.apply("0.8(RM)").as_deref(), Some("0.8#Md"));
+        assert_eq!(table.apply("0.8#M").as_deref(), Some("0.8#Md"));
+        assert_eq!(table.apply("-999").as_deref(), Some("-999#a24041"));
+        assert_eq!(table.apply("-999#a24041"), None);
+        assert_eq!(table.apply("12.3#C"), None);
+        assert_eq!(table.apply("12.3"), None);
+
+        let bad = RuleSet::from_toml_str(
+            r#"
+            [[flag_codes]]
+            factor = "a24088"
+            calibration = "C#1"
+            "#,
+        );
+        assert!(bad.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_convert_rules() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[convert]]
+            column = "c"
+            from = "ppbv"
+            to = "μg/m³"
+            molecular_weight = 92.14
+            decimals = 2
+
+            [conditions]
+            temperature = 20.0
+            "#,
+        )?;
+        let rule = &rules.convert[0];
+        assert_eq!(rule.column_index(), Some(3));
+        assert_eq!(rule.first_row, 4);
+        assert_eq!(rules.conditions.pressure, 101.325);
+        assert_eq!(rule.apply("1", &rules.conditions).as_deref(), Some("3.83"));
+        assert_eq!(rule.apply("-999#a24041", &rules.conditions), None);
+
+        let missing_carbon = RuleSet::from_toml_str(
+            r#"
+            [[convert]]
+            column = "C"
+            from = "ppbv"
+            to = "ppbC"
+            "#,
+        );
+        assert!(missing_carbon.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_bracket_rule() -> Result<()> {
+        assert_eq!(RuleSet::builtin().brackets, BracketRule::default());
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [brackets]
+            styles = ["ascii"]
+            columns = ["b", "AA"]
+            "#,
+        )?;
+        assert_eq!(rules.brackets.styles, [BracketStyle::Ascii]);
+        assert_eq!(rules.brackets.min_row, 3);
+        assert_eq!(rules.brackets.column_indices(), Some(vec![2, 27]));
+
+        for bad in [
+            "styles = []",
+            "styles = [\"square\"]",
+            "min_row = 4\nmax_row = 3",
+            "columns = [\"3\"]",
+            "exclude = [\"\"]",
+            "protect = [\"并(\"]",
+        ] {
+            assert!(RuleSet::from_toml_str(&format!("[brackets]\n{bad}")).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parses_pipeline_order() -> Result<()> {
+        assert_eq!(RuleSet::builtin().pipeline, Step::DEFAULT_ORDER);
+        let rules = RuleSet::from_toml_str(r#"pipeline = ["replace", "convert"]"#)?;
+        assert_eq!(rules.pipeline, [Step::Replace, Step::Convert]);
+        assert!(RuleSet::from_toml_str(r#"pipeline = ["replace", "replace"]"#).is_err());
+        assert!(RuleSet::from_toml_str(r#"pipeline = ["uppercase"]"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_consistency_rules() -> Result<()> {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[consistency]]
+            left = ["F:BD", "a24088"]
+            right = ["C"]
+            "#,
+        )?;
+        let rule = &rules.consistency[0];
+        assert_eq!(rule.label(), "F:BD+a24088 ≤ C");
+        assert_eq!((rule.code_row, rule.first_row), (3, 4));
+        assert_eq!(ColumnSpec::parse("F:BD"), Some(ColumnSpec::Columns(6, 56)));
+        assert_eq!(
+            ColumnSpec::parse("a24088"),
+            Some(ColumnSpec::Factor("a24088".to_string()))
+        );
+        assert_eq!(
+            ColumnSpec::parse("XFD"),
+            Some(ColumnSpec::Columns(16384, 16384))
+        );
+        for bad in ["NMHC", "XFE", "PM2.5", ""] {
+            assert_eq!(ColumnSpec::parse(bad), None, "{bad}");
+        }
+
+        for bad in [
+            "left = []\nright = [\"C\"]",
+            "left = [\"D:B\"]\nright = [\"C\"]",
+            "left = [\"B\"]\nright = [\"C\"]\ntolerance = -1",
+            "left = [\"B\"]\nright = [\"C\"]\nfirst_row = 0",
+        ] {
+            assert!(RuleSet::from_toml_str(&format!("[[consistency]]\n{bad}")).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn missing_file_falls_back_to_builtin() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let rules = RuleSet::load(&dir.path().join(RULES_FILE))?;
+        assert_eq!(rules.replace.len(), RuleSet::builtin().replace.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+//! 库的错误类型。
+//!
+//! 库函数返回 [`DtToolsError`]，调用方可以按类别处理失败（如找不到列、无法解析的时间）。
+//! 处理过程中附加的上下文（如正在处理的文件）包装在 [`DtToolsError::Context`] 中，
+//! [`DtToolsError::kind`] 返回被包装的原始错误。`{:#}` 格式输出完整的错误链，
+//! 如 `配置文件: site.toml: 未知的监测项目: X`。
+//!
+//! 程序的退出码由 [`DtToolsError::exit_code`] 决定：0 成功，1 其他错误，
+//! [`VALIDATION_EXIT_CODE`] 校验未通过，[`IO_EXIT_CODE`] 读写文件失败，
+//! [`PARTIAL_EXIT_CODE`] 部分成功（宽松模式跳过了数据问题，或批量处理中部分文件失败）。
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::Message;
+
+/// 库函数的结果类型。
+pub type Result<T, E = DtToolsError> = std::result::Result<T, E>;
+
+type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+/// 校验未通过（`dttools validate` 发现问题）时程序的退出码。
+pub const VALIDATION_EXIT_CODE: i32 = 2;
+
+/// 无法读写文件时程序的退出码。
+pub const IO_EXIT_CODE: i32 = 3;
+
+/// 部分成功时程序的退出码。
+pub const PARTIAL_EXIT_CODE: i32 = 4;
+
+/// dttools 的错误。
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DtToolsError {
+    /// 无法打开或读取输入文件
+    FileOpen { path: PathBuf, source: BoxError },
+    /// 工作簿中找不到工作表
+    SheetNotFound { name: String },
+    /// 源表中找不到必需的列
+    MissingColumn { name: String },
+    /// 无法解析的时间
+    TimeParse { value: String },
+    /// 无法写入输出文件
+    Write { path: PathBuf, source: BoxError },
+    /// 配置文件、规则文件或版式定义有误
+    Config { message: String },
+    /// 参数或数据不符合要求
+    Invalid { message: String },
+    /// 宽松模式下已输出结果，但跳过了 `problems` 个数据问题
+    Partial { problems: usize },
+    /// 批量处理中 `total` 个文件有 `failed` 个失败（各文件的错误已逐个报告）；`first` 为第一个
+    /// 失败的文件及其完整的错误链，供结果通知使用
+    Batch {
+        failed: usize,
+        total: usize,
+        first: String,
+    },
+    /// `files` 个文件未通过校验，共 `findings` 个问题
+    Validation { files: usize, findings: usize },
+    /// 读写过程中的 I/O 错误
+    Io(io::Error),
+    /// 其他依赖库返回的错误（如上传、推送）
+    External(BoxError),
+    /// 附加了上下文说明的错误
+    Context {
+        message: String,
+        source: Box<DtToolsError>,
+    },
+}
+
+impl DtToolsError {
+    /// 去掉上下文后的原始错误，用于按类别处理。
+    pub fn kind(&self) -> &DtToolsError {
+        match self {
+            Self::Context { source, .. } => source.kind(),
+            other => other,
+        }
+    }
+
+    /// 程序的退出码。批量处理中只有部分文件失败时为 [`PARTIAL_EXIT_CODE`]，全部失败时为 1。
+    pub fn exit_code(&self) -> i32 {
+        match self.kind() {
+            Self::Validation { .. } => VALIDATION_EXIT_CODE,
+            Self::FileOpen { .. } | Self::Write { .. } | Self::Io(_) => IO_EXIT_CODE,
+            Self::Partial { .. } => PARTIAL_EXIT_CODE,
+            Self::Batch { failed, total, .. } if failed < total => PARTIAL_EXIT_CODE,
+            _ => 1,
+        }
+    }
+
+    /// 退出码对应的结果状态，用于机器可读的运行摘要（`--json-summary`）。
+    pub fn status(&self) -> &'static str {
+        match self.exit_code() {
+            VALIDATION_EXIT_CODE => "validation_failed",
+            IO_EXIT_CODE => "io_error",
+            PARTIAL_EXIT_CODE => "partial",
+            _ => "error",
+        }
+    }
+
+    /// 无法打开 `path` 的错误。
+    pub(crate) fn file_open(path: &Path, err: impl Into<BoxError>) -> Self {
+        Self::FileOpen {
+            path: path.to_path_buf(),
+            source: err.into(),
+        }
+    }
+
+    /// 无法写入 `path` 的错误。
+    pub(crate) fn write(path: &Path, err: impl Into<BoxError>) -> Self {
+        Self::Write {
+            path: path.to_path_buf(),
+            source: err.into(),
+        }
+    }
+
+    /// 包装其他库返回的错误。
+    pub fn external(err: impl Into<BoxError>) -> Self {
+        Self::External(err.into())
+    }
+}
+
+impl fmt::Display for DtToolsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FileOpen { path, .. } => {
+                f.write_str(&Message::FileOpen.render(&[("path", &path.display())]))?
+            }
+            Self::SheetNotFound { name } => {
+                f.write_str(&Message::SheetNotFound.render(&[("name", name)]))?
+            }
+            Self::MissingColumn { name } => {
+                f.write_str(&Message::MissingColumn.render(&[("name", name)]))?
+            }
+            Self::TimeParse { value } => {
+                f.write_str(&Message::TimeParse.render(&[("value", value)]))?
+            }
+            Self::Write { path, .. } => {
+                f.write_str(&Message::Write.render(&[("path", &path.display())]))?
+            }
+            Self::Config { message } | Self::Invalid { message } => f.write_str(message)?,
+            Self::Partial { problems } => {
+                f.write_str(&Message::Partial.render(&[("problems", problems)]))?
+            }
+            Self::Batch { failed, total, .. } => {
+                f.write_str(&Message::Batch.render(&[("failed", failed), ("total", total)]))?
+            }
+            Self::Validation { files, findings } => f.write_str(
+                &Message::Validation.render(&[("files", files), ("findings", findings)]),
+            )?,
+            Self::Io(err) => write!(f, "{err}")?,
+            Self::External(err) => write!(f, "{err}")?,
+            Self::Context { message, .. } => f.write_str(message)?,
+        }
+        if f.alternate() {
+            let mut source = self.source();
+            while let Some(err) = source {
+                write!(f, ": {err}")?;
+                source = err.source();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for DtToolsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::FileOpen { source, .. } | Self::Write { source, .. } => Some(source.as_ref()),
+            Self::Io(err) => err.source(),
+            Self::External(err) => err.source(),
+            Self::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DtToolsError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for DtToolsError {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Config {
+            message: err.to_string().trim_end().to_string(),
+        }
+    }
+}
+
+macro_rules! external_errors {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl From<$ty> for DtToolsError {
+                fn from(err: $ty) -> Self {
+                    Self::External(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+external_errors!(
+    calamine::Error,
+    calamine::XlsxError,
+    calamine::OdsError,
+    csv::Error,
+    serde_json::Error,
+    regex::Error,
+    chrono::ParseError,
+    umya_spreadsheet::XlsxError,
+    zip::result::ZipError,
+    std::string::FromUtf8Error,
+);
+
+#[cfg(feature = "upload")]
+external_errors!(ssh2::Error, suppaftp::FtpError, suppaftp::native_tls::Error);
+
+#[cfg(feature = "postgres")]
+external_errors!(postgres::Error);
+
+#[cfg(feature = "watch")]
+external_errors!(notify::Error);
+
+/// 为错误附加上下文说明，用法同 `anyhow::Context`。
+pub(crate) trait Context<T> {
+    fn context<C: fmt::Display>(self, context: C) -> Result<T>;
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, f: F) -> Result<T>;
+}
+
+impl<T, E: Into<DtToolsError>> Context<T> for std::result::Result<T, E> {
+    fn context<C: fmt::Display>(self, context: C) -> Result<T> {
+        self.map_err(|err| DtToolsError::Context {
+            message: context.to_string(),
+            source: Box::new(err.into()),
+        })
+    }
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, f: F) -> Result<T> {
+        self.map_err(|err| DtToolsError::Context {
+            message: f().to_string(),
+            source: Box::new(err.into()),
+        })
+    }
+}
+
+impl<T> Context<T> for Option<T> {
+    fn context<C: fmt::Display>(self, context: C) -> Result<T> {
+        self.ok_or_else(|| DtToolsError::Invalid {
+            message: context.to_string(),
+        })
+    }
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, f: F) -> Result<T> {
+        self.ok_or_else(|| DtToolsError::Invalid {
+            message: f().to_string(),
+        })
+    }
+}
+
+/// 构造 [`DtToolsError::Invalid`]，参数同 `format!`。
+macro_rules! invalid {
+    ($($arg:tt)*) => {
+        $crate::error::DtToolsError::Invalid { message: format!($($arg)*) }
+    };
+}
+
+/// 构造 [`DtToolsError::Config`]，参数同 `format!`。
+macro_rules! config_error {
+    ($($arg:tt)*) => {
+        $crate::error::DtToolsError::Config { message: format!($($arg)*) }
+    };
+}
+
+/// 返回 [`DtToolsError::Invalid`]，参数同 `format!`。
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return Err($crate::error::invalid!($($arg)*))
+    };
+}
+
+pub(crate) use {bail, config_error, invalid};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_sees_through_context() {
+        let err: Result<()> = Err(DtToolsError::MissingColumn {
+            name: "NO₃⁻".to_string(),
+        });
+        let err = err.context("工作表 'Sheet1'").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            DtToolsError::MissingColumn { name } if name == "NO₃⁻"
+        ));
+        assert_eq!(err.to_string(), "工作表 'Sheet1'");
+        assert_eq!(format!("{err:#}"), "工作表 'Sheet1': 找不到'NO₃⁻'列");
+    }
+
+    #[test]
+    fn wraps_io_errors() {
+        let err: Result<String> =
+            std::fs::read_to_string("/nonexistent/dttools").context("无法读取配置文件");
+        let err = err.unwrap_err();
+        assert!(matches!(err.kind(), DtToolsError::Io(_)));
+        assert!(format!("{err:#}").starts_with("无法读取配置文件: "));
+        assert_eq!(err.exit_code(), IO_EXIT_CODE);
+        assert_eq!(err.status(), "io_error");
+    }
+
+    #[test]
+    fn exit_codes() {
+        let batch = |failed| DtToolsError::Batch {
+            failed,
+            total: 3,
+            first: String::new(),
+        };
+        assert_eq!(batch(1).exit_code(), PARTIAL_EXIT_CODE);
+        assert_eq!(batch(3).exit_code(), 1);
+        let validation = DtToolsError::Validation {
+            files: 1,
+            findings: 4,
+        };
+        assert_eq!(validation.exit_code(), VALIDATION_EXIT_CODE);
+        assert_eq!(DtToolsError::Partial { problems: 2 }.status(), "partial");
+        assert_eq!(invalid!("x").exit_code(), 1);
+    }
+}
@@ -0,0 +1,49 @@
+//! 处理结果的附加导出：把数据表写为 UTF-8 CSV/TSV，供下游导入程序直接读取。
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{DtToolsError, Result};
+
+/// 与输出工作簿同名的 CSV 路径；分隔符为 tab 时使用 `.tsv` 扩展名。
+pub(crate) fn csv_path(output: &Path, delimiter: u8) -> PathBuf {
+    output.with_extension(if delimiter == b'\t' { "tsv" } else { "csv" })
+}
+
+pub(crate) fn write_csv(path: &Path, rows: &[Vec<String>], delimiter: u8) -> Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|err| DtToolsError::write(path, err))?;
+    for row in rows {
+        writer
+            .write_record(row)
+            .map_err(|err| DtToolsError::write(path, err))?;
+    }
+    writer
+        .flush()
+        .map_err(|err| DtToolsError::write(path, err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_quoted_csv_next_to_output() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = csv_path(&dir.path().join("processed_a.xlsx"), b',');
+        assert_eq!(path, dir.path().join("processed_a.csv"));
+        let rows = vec![
+            vec!["时间".to_string(), "NO₃⁻".to_string()],
+            vec!["2026-01-05 01:00:00".to_string(), "1,5".to_string()],
+        ];
+        write_csv(&path, &rows, b',')?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "时间,NO₃⁻\n2026-01-05 01:00:00,\"1,5\"\n"
+        );
+        Ok(())
+    }
+}
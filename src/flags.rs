@@ -0,0 +1,202 @@
+//! HJ 212 风格的数据标记：上传数据用 `值#标记码` 表示带标记的数值，如缺测 `-999#a24041`、
+//! 校准 `12.3#C`。
+//!
+//! 仪器导出的数据常把标记写在括号里，如 `12.3(C)`、`0.8(RM)`；这里统一识别为校准（C）、
+//! 维护（M，含 RM）和无效（N）三类。低于检出限的值（`<0.01`、`ND`）另行识别，
+//! 按 [`BelowLimitValues`] 替换。
+
+use std::fmt;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// 数据标记的类别。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataFlag {
+    /// 校准（C）
+    Calibration,
+    /// 维护（M；仪器导出中的 RM 也归入此类）
+    Maintenance,
+    /// 无效（N）
+    Invalid,
+}
+
+impl DataFlag {
+    /// 标记字母，即默认的标记码。
+    pub fn letter(self) -> &'static str {
+        match self {
+            Self::Calibration => "C",
+            Self::Maintenance => "M",
+            Self::Invalid => "N",
+        }
+    }
+}
+
+impl FromStr for DataFlag {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "C" => Ok(Self::Calibration),
+            "M" | "RM" => Ok(Self::Maintenance),
+            "N" => Ok(Self::Invalid),
+            other => Err(format!("未知的数据标记: {other}（可选 C、M、RM、N）")),
+        }
+    }
+}
+
+impl fmt::Display for DataFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.letter())
+    }
+}
+
+/// 拆分 `值#标记码`；不含 `#`、值不是数值或标记码为空时返回 `None`。
+pub fn split(text: &str) -> Option<(&str, &str)> {
+    let (value, code) = text.trim().split_once('#')?;
+    let (value, code) = (value.trim(), code.trim());
+    (value.parse::<f64>().is_ok() && !code.is_empty()).then_some((value, code))
+}
+
+/// 组合为 `值#标记码`。
+pub fn join(value: &str, code: &str) -> String {
+    format!("{value}#{code}")
+}
+
+/// 识别带标记的数值：`12.3(C)`、`12.3 (RM)` 或 `12.3#C`，返回数值部分与标记。
+/// 只有括号标记、没有数值时（如 `(C)`）也返回 `None`。
+pub fn parse_marked(text: &str) -> Option<(&str, DataFlag)> {
+    let text = text.trim();
+    let (value, flag) = match text.strip_suffix(')') {
+        Some(rest) => rest.rsplit_once('(')?,
+        None => split(text)?,
+    };
+    let value = value.trim();
+    value.parse::<f64>().ok()?;
+    Some((value, flag.parse().ok()?))
+}
+
+/// 低于检出限的值写入的默认标记码（见 [`BelowLimitValues::Flag`]）。
+pub const DEFAULT_BELOW_LIMIT_CODE: &str = "DL";
+
+/// 无效数据的默认代码（proton 的 `invalid_code`，仪器导出中也常见）。
+pub const DEFAULT_INVALID_CODE: &str = "-999";
+
+/// 不带检出限的“未检出”写法（不区分大小写）。
+const NOT_DETECTED: [&str; 7] = ["ND", "N.D.", "BDL", "<DL", "<LOD", "<MDL", "未检出"];
+
+/// 低于检出限的值的处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BelowLimitValues {
+    /// 清空
+    #[default]
+    Drop,
+    /// 写为检出限的一半
+    Half,
+    /// 写为 0
+    Zero,
+    /// 写为 `检出限#标记码`，如 `0.01#DL`
+    Flag,
+}
+
+impl BelowLimitValues {
+    /// 按此方式替换检出限为 `limit` 的值；返回 `None` 表示清空。检出限未知时只有
+    /// [`Zero`](Self::Zero) 能替换。
+    pub fn substitute(self, limit: Option<f64>, code: &str) -> Option<String> {
+        match self {
+            Self::Drop => None,
+            Self::Half => limit.map(|limit| (limit / 2.0).to_string()),
+            Self::Zero => Some("0".to_string()),
+            Self::Flag => limit.map(|limit| join(&limit.to_string(), code)),
+        }
+    }
+
+    /// 实测数值 `value` 是否低于检出限 `limit` 且应按此方式替换。[`Drop`](Self::Drop) 只清空
+    /// `<0.01`、`ND` 等写法，不清空实测数值；负值（如无效码 `-999`）不是实测值，不替换。
+    pub fn replaces_measured(self, value: f64, limit: Option<f64>) -> bool {
+        self != Self::Drop && value >= 0.0 && limit.is_some_and(|limit| value < limit)
+    }
+}
+
+/// 识别低于检出限的值：`<0.01`、`＜ 0.01` 返回 `Some(Some(0.01))`；`ND`、`N.D.`、`BDL`、
+/// `<DL`、`<LOD`、`<MDL`、`未检出` 返回 `Some(None)`（检出限未知）；其余返回 `None`。
+pub fn parse_below_limit(text: &str) -> Option<Option<f64>> {
+    let text = text.trim();
+    if NOT_DETECTED
+        .iter()
+        .any(|marker| marker.eq_ignore_ascii_case(text))
+    {
+        return Some(None);
+    }
+    let limit = text.strip_prefix(['<', '＜'])?.trim();
+    let limit: f64 = limit.parse().ok()?;
+    (limit.is_finite() && limit > 0.0).then_some(Some(limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_marked_values() {
+        assert_eq!(
+            parse_marked("12.3(C)"),
+            Some(("12.3", DataFlag::Calibration))
+        );
+        assert_eq!(
+            parse_marked(" 0.8 (RM) "),
+            Some(("0.8", DataFlag::Maintenance))
+        );
+        assert_eq!(parse_marked("5#N"), Some(("5", DataFlag::Invalid)));
+        assert_eq!(parse_marked("(C)"), None);
+        assert_eq!(parse_marked("1.2(备注)"), None);
+        assert_eq!(parse_marked("-999#a24041"), None);
+    }
+
+    #[test]
+    fn parses_below_limit_values() {
+        assert_eq!(parse_below_limit("<0.01"), Some(Some(0.01)));
+        assert_eq!(parse_below_limit(" ＜ 0.5 "), Some(Some(0.5)));
+        assert_eq!(parse_below_limit("nd"), Some(None));
+        assert_eq!(parse_below_limit("未检出"), Some(None));
+        assert_eq!(parse_below_limit("<0"), None);
+        assert_eq!(parse_below_limit("0.01"), None);
+        assert_eq!(parse_below_limit("N/A"), None);
+
+        let code = DEFAULT_BELOW_LIMIT_CODE;
+        assert_eq!(BelowLimitValues::Drop.substitute(Some(0.01), code), None);
+        assert_eq!(
+            BelowLimitValues::Half
+                .substitute(Some(0.01), code)
+                .as_deref(),
+            Some("0.005")
+        );
+        assert_eq!(BelowLimitValues::Half.substitute(None, code), None);
+        assert_eq!(
+            BelowLimitValues::Zero.substitute(None, code).as_deref(),
+            Some("0")
+        );
+        assert_eq!(
+            BelowLimitValues::Flag
+                .substitute(Some(0.01), code)
+                .as_deref(),
+            Some("0.01#DL")
+        );
+        assert!(BelowLimitValues::Half.replaces_measured(0.004, Some(0.01)));
+        assert!(!BelowLimitValues::Half.replaces_measured(0.01, Some(0.01)));
+        assert!(!BelowLimitValues::Half.replaces_measured(0.004, None));
+        assert!(!BelowLimitValues::Drop.replaces_measured(0.004, Some(0.01)));
+        assert!(!BelowLimitValues::Half.replaces_measured(-999.0, Some(0.01)));
+    }
+
+    #[test]
+    fn splits_flagged_values() {
+        assert_eq!(split("-999#a24041"), Some(("-999", "a24041")));
+        assert_eq!(join("-999", "a24041"), "-999#a24041");
+        assert_eq!(split("-999"), None);
+        assert_eq!(split("abc#C"), None);
+        assert_eq!(split("1#"), None);
+    }
+}
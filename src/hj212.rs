@@ -0,0 +1,405 @@
+//! `--hj212`：把处理后的每个时间点写为一条 HJ 212-2017 上报报文，追加到文本文件或通过 TCP
+//! 发送到省级前置机。
+//!
+//! 报文为 `##` + 4 位数据段长度 + 数据段 + 4 位 CRC（十六进制）+ `\r\n`，数据段如
+//! `QN=20260105013000123;ST=22;CN=2061;PW=123456;MN=4401000010003;Flag=4;CP=&&DataTime=20260105010000;a06006-Avg=1.6,a06006-Flag=C;…&&`。
+//! 各项目的数值取自数据表，标记由 `值#标记码` 换为协议的数据标记（没有标记时为 N，见
+//! [`protocol_flag`]）；缺失与无效的值（如 `-999`、`-999#a24041`）和没有因子编码的列不写入。`Flag=4` 表示 2017 版协议、不要求应答。数据段超过 1024 字节时按因子拆为多包，
+//! 各包的 QN 相同，`Flag=6`（置拆包位 D）并带总包数 PNUM 与包号 PNO。
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use clap::Args;
+use tracing::info;
+
+use crate::error::{Context, DtToolsError, Result, invalid};
+use crate::flags;
+
+/// 数据段的最大长度（HJ 212-2017 规定为 1024 字节）。
+const MAX_DATA_LEN: usize = 1024;
+/// 数据段中的 Flag：协议版本 2017（V=1），不要求应答、不拆包。
+const FLAG: u8 = 4;
+/// 拆包位 D。
+const FLAG_SPLIT: u8 = 2;
+/// 连接与发送超时。
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// HJ 212 报文相关的参数。
+#[derive(Debug, Clone, Args)]
+pub struct Hj212Args {
+    /// 把每个时间点写为 HJ 212-2017 报文：追加到文件，或以 tcp://主机:端口 发送到前置机
+    #[arg(long, value_name = "FILE|tcp://HOST:PORT")]
+    pub hj212: Option<String>,
+
+    /// 报文中的访问密码 PW
+    #[arg(long, value_name = "PW", default_value = "123456")]
+    pub hj212_password: String,
+
+    /// 报文中的系统编码 ST（22 为环境空气质量监测）
+    #[arg(long, value_name = "ST", default_value = "22")]
+    pub hj212_st: String,
+
+    /// 报文中的命令编码 CN（2061 小时数据，2051 分钟数据，2031 日数据）
+    #[arg(long, value_name = "CN", default_value = "2061")]
+    pub hj212_cn: String,
+}
+
+/// 报文中除数据区以外的字段。
+#[derive(Debug, Clone, Copy)]
+pub struct Header<'a> {
+    /// 系统编码 ST
+    pub st: &'a str,
+    /// 命令编码 CN
+    pub cn: &'a str,
+    /// 访问密码 PW
+    pub pw: &'a str,
+    /// 设备唯一标识 MN（站点编码）
+    pub mn: &'a str,
+}
+
+impl Hj212Args {
+    /// 指定了 `--hj212` 时写出数据表各行的报文；`codes` 为各浓度列（数据表第 2 列起）的因子编码。
+    pub(crate) fn send(
+        &self,
+        station: &str,
+        table: &[Vec<String>],
+        codes: &[&str],
+        missing: &[&str],
+    ) -> Result<()> {
+        let Some(destination) = &self.hj212 else {
+            return Ok(());
+        };
+        let header = Header {
+            st: &self.hj212_st,
+            cn: &self.hj212_cn,
+            pw: &self.hj212_password,
+            mn: station,
+        };
+        let packets = packets(header, table, codes, missing, Local::now().naive_local())?;
+        let content = packets.concat();
+        match destination.strip_prefix("tcp://") {
+            Some(address) => send_tcp(address, content.as_bytes())
+                .with_context(|| format!("无法发送 HJ 212 报文到 {address}"))?,
+            None => {
+                let path = Path::new(destination);
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| file.write_all(content.as_bytes()))
+                    .map_err(|err| DtToolsError::write(path, err))?;
+            }
+        }
+        info!("已写出 {} 条 HJ 212 报文到: {destination}", packets.len());
+        Ok(())
+    }
+}
+
+fn send_tcp(address: &str, content: &[u8]) -> Result<()> {
+    let socket = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid!("无法解析地址: {address}"))?;
+    let mut stream = TcpStream::connect_timeout(&socket, TIMEOUT)?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(content)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// 数据表（首行为表头，首列为 `%Y-%m-%d %H:%M:%S` 格式的时间）各行的报文。第 i 行报文的请求
+/// 编号 QN 为 `qn` 加 i 毫秒，保证各行报文的 QN 不重复；一行拆为多包时各包 QN 相同。
+pub fn packets(
+    header: Header<'_>,
+    table: &[Vec<String>],
+    codes: &[&str],
+    missing: &[&str],
+    qn: NaiveDateTime,
+) -> Result<Vec<String>> {
+    let mut packets = Vec::new();
+    for (i, row) in table.iter().skip(1).enumerate() {
+        let Some((time, values)) = row.split_first() else {
+            continue;
+        };
+        let time = NaiveDateTime::parse_from_str(time.trim(), "%Y-%m-%d %H:%M:%S")
+            .map_err(|_| invalid!("无法解析时间: {time}"))?;
+        let fields: Vec<String> = codes
+            .iter()
+            .zip(values)
+            .filter_map(|(code, text)| {
+                let text = text.trim();
+                if code.is_empty() || text.is_empty() || missing.contains(&text) {
+                    return None;
+                }
+                let (value, flag) = match flags::split(text) {
+                    Some((value, code)) => (value, protocol_flag(code)),
+                    None => (text, "N"),
+                };
+                if missing.contains(&value) {
+                    return None;
+                }
+                value
+                    .parse::<f64>()
+                    .is_ok()
+                    .then(|| format!("{code}-Avg={value},{code}-Flag={flag}"))
+            })
+            .collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let qn = qn + TimeDelta::milliseconds(i as i64);
+        let head = format!(
+            "QN={};ST={};CN={};PW={};MN={};",
+            qn.format("%Y%m%d%H%M%S%3f"),
+            header.st,
+            header.cn,
+            header.pw,
+            header.mn,
+        );
+        let time = time.format("%Y%m%d%H%M%S").to_string();
+        let cp = |fields: &[String]| format!("CP=&&DataTime={time};{}&&", fields.join(";"));
+
+        let data = format!("{head}Flag={FLAG};{}", cp(&fields));
+        if data.len() <= MAX_DATA_LEN {
+            packets.push(frame(&data)?);
+            continue;
+        }
+        // 包数不超过因子数，按其位数预留 PNUM 与 PNO 的长度
+        let width = fields.len().to_string().len();
+        let reserved = format!(
+            "{head}Flag={};PNUM={n};PNO={n};",
+            FLAG | FLAG_SPLIT,
+            n = "9".repeat(width)
+        )
+        .len();
+        let mut chunks: Vec<&[String]> = Vec::new();
+        let mut start = 0;
+        for end in 1..=fields.len() {
+            if reserved + cp(&fields[start..end]).len() > MAX_DATA_LEN {
+                if end - 1 == start {
+                    return Err(invalid!(
+                        "HJ 212 数据段超过 {MAX_DATA_LEN} 字节，无法拆包: {}",
+                        fields[start]
+                    ));
+                }
+                chunks.push(&fields[start..end - 1]);
+                start = end - 1;
+            }
+        }
+        chunks.push(&fields[start..]);
+        for (pno, chunk) in chunks.iter().enumerate() {
+            let data = format!(
+                "{head}Flag={};PNUM={};PNO={};{}",
+                FLAG | FLAG_SPLIT,
+                chunks.len(),
+                pno + 1,
+                cp(chunk)
+            );
+            packets.push(frame(&data)?);
+        }
+    }
+    Ok(packets)
+}
+
+/// `值#标记码` 中的标记码对应的 HJ 212-2017 数据标记（N 正常、F 停运、M 维护、S 手工输入、
+/// D 故障、C 校准、T 超限、B 通讯异常）。协议中的标记原样使用，但本工具的无效标记 N 记为 D；
+/// 低于检出限（DL）的替换值记为 N；其他标记码记为 D。
+pub fn protocol_flag(code: &str) -> &'static str {
+    match code.trim() {
+        "F" => "F",
+        "M" => "M",
+        "S" => "S",
+        "C" => "C",
+        "T" => "T",
+        "B" => "B",
+        flags::DEFAULT_BELOW_LIMIT_CODE => "N",
+        _ => "D",
+    }
+}
+
+/// 加上包头、长度、CRC 与包尾。
+pub fn frame(data: &str) -> Result<String> {
+    if data.len() > MAX_DATA_LEN {
+        return Err(invalid!(
+            "HJ 212 数据段超过 {MAX_DATA_LEN} 字节（{} 字节）",
+            data.len()
+        ));
+    }
+    Ok(format!(
+        "##{:04}{data}{:04X}\r\n",
+        data.len(),
+        crc16(data.as_bytes())
+    ))
+}
+
+/// HJ 212 规定的 CRC16（初值 0xFFFF，多项式 0xA001）。
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc = (crc >> 8) ^ u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn frames_packet_from_standard_example() -> Result<()> {
+        // HJ 212-2017 附录中的示例报文
+        let data = "QN=20160801085857223;ST=32;CN=1062;PW=100000;\
+                    MN=010000A8900016F000169DC0;Flag=5;CP=&&RtdInterval=30&&";
+        assert_eq!(frame(data)?, format!("##0101{data}1C80\r\n"));
+        assert!(frame(&"x".repeat(1024)).is_ok());
+        assert!(frame(&"x".repeat(1025)).is_err());
+        Ok(())
+    }
+
+    fn table() -> Vec<Vec<String>> {
+        [
+            ["时间", "NO₃⁻", "SO₄²⁻", "Cl⁻"],
+            ["2026-01-05 01:00:00", "1.6#C", "4.20", "-999"],
+            ["2026-01-05 02:00:00", "", "", ""],
+            ["2026-01-05 03:00:00", "n/a", "0.01#DL", "0.3"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect()
+    }
+
+    const HEADER: Header<'static> = Header {
+        st: "22",
+        cn: "2061",
+        pw: "123456",
+        mn: "4401000010003",
+    };
+
+    #[test]
+    fn writes_one_packet_per_time() -> Result<()> {
+        let qn = NaiveDateTime::parse_from_str("2026-01-05 04:00:00", "%Y-%m-%d %H:%M:%S")?;
+        let packets = packets(HEADER, &table(), &["a06006", "a06005", ""], &["-999"], qn)?;
+        assert_eq!(packets.len(), 2);
+        let data = "QN=20260105040000000;ST=22;CN=2061;PW=123456;MN=4401000010003;Flag=4;\
+                    CP=&&DataTime=20260105010000;a06006-Avg=1.6,a06006-Flag=C;\
+                    a06005-Avg=4.20,a06005-Flag=N&&";
+        assert_eq!(packets[0], frame(data)?);
+        assert!(packets[1].contains("QN=20260105040000002;"));
+        assert!(packets[1].contains("DataTime=20260105030000;a06005-Avg=0.01,a06005-Flag=N&&"));
+        Ok(())
+    }
+
+    #[test]
+    fn maps_marks_to_protocol_flags() {
+        assert_eq!(protocol_flag("C"), "C");
+        assert_eq!(protocol_flag("DL"), "N");
+        assert_eq!(protocol_flag("N"), "D");
+        assert_eq!(protocol_flag("H"), "D");
+    }
+
+    #[test]
+    fn skips_invalid_values_with_marks() -> Result<()> {
+        let qn = NaiveDateTime::parse_from_str("2026-01-05 04:00:00", "%Y-%m-%d %H:%M:%S")?;
+        let table = vec![
+            Vec::new(),
+            vec![
+                "2026-01-05 01:00:00".to_string(),
+                "-999#N".to_string(),
+                "2#N".to_string(),
+            ],
+        ];
+        let packets = packets(HEADER, &table, &["a06006", "a06005"], &["-999"], qn)?;
+        assert_eq!(packets.len(), 1);
+        assert!(!packets[0].contains("a06006"));
+        assert!(packets[0].contains("a06005-Avg=2,a06005-Flag=D"));
+        Ok(())
+    }
+
+    #[test]
+    fn splits_long_rows_into_packets() -> Result<()> {
+        let qn = NaiveDateTime::parse_from_str("2026-01-05 04:00:00", "%Y-%m-%d %H:%M:%S")?;
+        let codes: Vec<String> = (0..40).map(|i| format!("a24{i:03}")).collect();
+        let codes: Vec<&str> = codes.iter().map(String::as_str).collect();
+        let row = |values: usize| {
+            let mut row = vec!["2026-01-05 01:00:00".to_string()];
+            row.extend((0..values).map(|i| format!("{}.5", 100 + i)));
+            vec![Vec::new(), row]
+        };
+        let data_len = |packet: &str| packet[2..6].parse::<usize>().unwrap();
+
+        // 一个数据段刚好不超过 1024 字节时不拆包
+        let fits = (1..=codes.len())
+            .rev()
+            .find(|&n| {
+                packets(HEADER, &row(n), &codes, &[], qn)
+                    .is_ok_and(|p| p.len() == 1 && p[0].contains("Flag=4;CP"))
+            })
+            .expect("fits");
+        let packet = &packets(HEADER, &row(fits), &codes, &[], qn)?[0];
+        assert!(data_len(packet) <= 1024);
+
+        // 再多一个因子（约 1025 字节以上）时拆为两包
+        let split = packets(HEADER, &row(fits + 1), &codes, &[], qn)?;
+        assert_eq!(split.len(), 2);
+        for (pno, packet) in split.iter().enumerate() {
+            assert!(data_len(packet) <= 1024, "{packet}");
+            assert!(packet.contains("QN=20260105040000000;"));
+            assert!(packet.contains(&format!(
+                "Flag=6;PNUM=2;PNO={};CP=&&DataTime=20260105010000;",
+                pno + 1
+            )));
+        }
+        let fields = split
+            .iter()
+            .map(|packet| packet.matches("-Avg=").count())
+            .sum::<usize>();
+        assert_eq!(fields, fits + 1);
+
+        let all = packets(HEADER, &row(codes.len()), &codes, &[], qn)?;
+        assert!(all.len() >= 2);
+        assert!(all.iter().all(|packet| data_len(packet) <= 1024));
+        Ok(())
+    }
+
+    #[test]
+    fn streams_packets_over_tcp() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = String::new();
+            stream.read_to_string(&mut received).unwrap();
+            received
+        });
+        let args = Hj212Args {
+            hj212: Some(format!("tcp://{address}")),
+            hj212_password: "123456".to_string(),
+            hj212_st: "22".to_string(),
+            hj212_cn: "2061".to_string(),
+        };
+        args.send(
+            "4401000010003",
+            &table(),
+            &["a06006", "a06005", "a06004"],
+            &["-999"],
+        )?;
+        let received = server.join().unwrap();
+        assert_eq!(received.matches("\r\n").count(), 2);
+        assert!(received.starts_with("##"));
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+//! 终端提示与错误信息的语言：中文（默认）或英文。
+//!
+//! 语言由 `--lang` 指定；未指定时依次查看环境变量 `DTTOOLS_LANG`、`LC_ALL`、`LC_MESSAGES`、
+//! `LANG`，第一个非空的值以 `en` 开头时为英文，否则为中文。消息目录 [`Message`] 集中列出
+//! 各条消息的两种写法，`{name}` 形式的占位符由 [`Message::render`] 替换。
+//!
+//! 只覆盖 [`Message`] 中列出的消息：常见的固定错误（文件无法打开或写入、找不到工作表或列、
+//! 时间无法解析、已处理过的输入、批量处理失败、参数组合无效）、批量处理的进度与汇总和交互提示。
+//! 其余输出（各工具的日志与警告、配置文件与规则的错误说明、`--help`）仍为中文。
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use clap::ValueEnum;
+
+/// 提示信息的语言。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Lang {
+    /// 中文
+    #[default]
+    Zh,
+    /// 英文
+    En,
+}
+
+/// 依次查看的环境变量。
+const LANG_VARS: [&str; 4] = ["DTTOOLS_LANG", "LC_ALL", "LC_MESSAGES", "LANG"];
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// 当前的语言。
+pub fn current() -> Lang {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Lang::En,
+        _ => Lang::Zh,
+    }
+}
+
+/// 设置之后的提示信息使用的语言（进程内全局）。
+pub fn set(lang: Lang) {
+    CURRENT.store(lang as u8, Ordering::Relaxed);
+}
+
+/// 按环境变量判断语言。
+pub fn detect() -> Lang {
+    detect_from(|name| std::env::var(name).ok())
+}
+
+fn detect_from(var: impl Fn(&str) -> Option<String>) -> Lang {
+    let value = LANG_VARS
+        .iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_default();
+    if value.trim().to_ascii_lowercase().starts_with("en") {
+        Lang::En
+    } else {
+        Lang::Zh
+    }
+}
+
+/// 按 `--lang` 设置语言，未指定时按环境变量判断。
+pub(crate) fn init(lang: Option<Lang>) {
+    set(lang.unwrap_or_else(detect));
+}
+
+/// 消息目录。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    ProcessingFailed,
+    FileOpen,
+    SheetNotFound,
+    MissingColumn,
+    TimeParse,
+    Write,
+    Partial,
+    Batch,
+    Validation,
+    OutputNeedsSingleInput,
+    InPlaceFormat,
+    AlreadyProcessed,
+    NoMatchingFiles,
+    Processing,
+    FileFailed,
+    BatchSummary,
+    BatchSucceeded,
+    BatchFailed,
+    SheetHeaders,
+    AskColumn,
+    InvalidColumn,
+    PressEnter,
+}
+
+impl Message {
+    #[cfg(test)]
+    const ALL: [Message; 22] = [
+        Self::ProcessingFailed,
+        Self::FileOpen,
+        Self::SheetNotFound,
+        Self::MissingColumn,
+        Self::TimeParse,
+        Self::Write,
+        Self::Partial,
+        Self::Batch,
+        Self::Validation,
+        Self::OutputNeedsSingleInput,
+        Self::InPlaceFormat,
+        Self::AlreadyProcessed,
+        Self::NoMatchingFiles,
+        Self::Processing,
+        Self::FileFailed,
+        Self::BatchSummary,
+        Self::BatchSucceeded,
+        Self::BatchFailed,
+        Self::SheetHeaders,
+        Self::AskColumn,
+        Self::InvalidColumn,
+        Self::PressEnter,
+    ];
+
+    /// 消息的中文与英文写法。
+    fn templates(self) -> (&'static str, &'static str) {
+        match self {
+            Self::ProcessingFailed => ("处理 Excel 文件时出错", "Failed to process Excel file"),
+            Self::FileOpen => ("无法打开文件: {path}", "cannot open file: {path}"),
+            Self::SheetNotFound => ("找不到工作表: {name}", "sheet not found: {name}"),
+            Self::MissingColumn => ("找不到'{name}'列", "column '{name}' not found"),
+            Self::TimeParse => ("无法解析时间格式: {value}", "cannot parse time: {value}"),
+            Self::Write => ("无法写入文件: {path}", "cannot write file: {path}"),
+            Self::Partial => (
+                "已输出结果，但跳过了 {problems} 个数据问题",
+                "output written, but {problems} data problems were skipped",
+            ),
+            Self::Batch => (
+                "{failed} 个文件处理失败（共 {total} 个）",
+                "{failed} of {total} files failed",
+            ),
+            Self::Validation => (
+                "{files} 个文件未通过校验，共 {findings} 个问题",
+                "{files} files failed validation with {findings} findings",
+            ),
+            Self::OutputNeedsSingleInput => (
+                "--output 只能用于单个输入文件，批量处理请使用 --output-dir",
+                "--output only works with a single input file; use --output-dir for batches",
+            ),
+            Self::InPlaceFormat => (
+                "--in-place 只能改写 .{extension} 文件（由 --format 决定）: {file}",
+                "--in-place can only rewrite .{extension} files (set by --format): {file}",
+            ),
+            Self::AlreadyProcessed => (
+                "文件已由 dttools 处理过（{marker}），再次处理可能重复改写数据；确需处理请加 --force",
+                "file was already processed by dttools ({marker}); processing it again may apply the rules twice, add --force to proceed",
+            ),
+            Self::NoMatchingFiles => ("没有匹配的文件: {pattern}", "no files match: {pattern}"),
+            Self::Processing => ("正在处理: {file}", "Processing: {file}"),
+            Self::FileFailed => ("处理失败: {file}: {error}", "Failed: {file}: {error}"),
+            Self::BatchSummary => (
+                "处理完成: 共 {total} 个文件, 成功 {succeeded} 个, 失败 {failed} 个",
+                "Done: {total} files, {succeeded} succeeded, {failed} failed",
+            ),
+            Self::BatchSucceeded => ("  [成功] {file} -> {output}", "  [ok] {file} -> {output}"),
+            Self::BatchFailed => ("  [失败] {file}: {error}", "  [failed] {file}: {error}"),
+            Self::SheetHeaders => ("工作表 '{sheet}' 的表头:", "Headers of sheet '{sheet}':"),
+            Self::AskColumn => (
+                "请输入'{header}'所在的列（如 D，直接回车放弃）: ",
+                "Column of '{header}' (e.g. D, Enter to give up): ",
+            ),
+            Self::InvalidColumn => ("无效的列: {column}", "invalid column: {column}"),
+            Self::PressEnter => ("按回车键退出…", "Press Enter to exit..."),
+        }
+    }
+
+    /// 消息在 `lang` 下的写法，占位符尚未替换。
+    pub fn template(self, lang: Lang) -> &'static str {
+        let (zh, en) = self.templates();
+        match lang {
+            Lang::Zh => zh,
+            Lang::En => en,
+        }
+    }
+
+    /// 按当前语言输出消息，`args` 为各占位符的值。
+    pub fn render(self, args: &[(&str, &dyn fmt::Display)]) -> String {
+        self.render_in(current(), args)
+    }
+
+    /// 按 `lang` 输出消息。
+    pub fn render_in(self, lang: Lang, args: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut text = self.template(lang).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        text
+    }
+}
+
+/// 没有占位符的消息按当前语言输出。
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.template(current()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_language_from_environment() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(detect_from(env(&[])), Lang::Zh);
+        assert_eq!(detect_from(env(&[("LANG", "en_US.UTF-8")])), Lang::En);
+        assert_eq!(detect_from(env(&[("LANG", "zh_CN.UTF-8")])), Lang::Zh);
+        assert_eq!(
+            detect_from(env(&[("LC_ALL", ""), ("LANG", "en_GB.UTF-8")])),
+            Lang::En
+        );
+        assert_eq!(
+            detect_from(env(&[("DTTOOLS_LANG", "zh"), ("LANG", "en_US.UTF-8")])),
+            Lang::Zh
+        );
+    }
+
+    #[test]
+    fn renders_catalog_in_both_languages() {
+        let placeholders = |text: &str| {
+            let mut names: Vec<String> = text
+                .split('{')
+                .skip(1)
+                .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_string()))
+                .collect();
+            names.sort();
+            names
+        };
+        for message in Message::ALL {
+            let (zh, en) = message.templates();
+            assert!(!zh.is_empty() && !en.is_empty(), "{message:?}");
+            assert_eq!(placeholders(zh), placeholders(en), "{message:?}");
+        }
+        assert_eq!(
+            Message::MissingColumn.render_in(Lang::En, &[("name", &"NO₃⁻")]),
+            "column 'NO₃⁻' not found"
+        );
+        assert_eq!(
+            Message::Batch.render_in(Lang::Zh, &[("failed", &1), ("total", &3)]),
+            "1 个文件处理失败（共 3 个）"
+        );
+    }
+}
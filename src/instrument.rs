@@ -0,0 +1,1306 @@
+//! 在线仪器导出文件（OC/EC、重金属、黑碳等）转换为上传模板的公共流程。
+//!
+//! 每种仪器由一个 [`Instrument`] 描述：第 3 行 A 列的名称、默认的配置文件、内置的监测项目
+//! 及其因子编码和源数据表头、源数据与模板的浓度单位。转换时读取第一个工作表（或 CSV 文件），
+//! 按表头找到时间列和各监测项目列，格式化时间、清空非数值并换算单位，高频数据可按时段求平均，
+//! 按 [`Template`] 输出模板，内置版式与离子色谱相同：第 1–2 行为红色说明，第 3–5 行为橙色的
+//! 监测项目、因子编码与单位，数据从第 6 行开始。
+//!
+//! 站点配置（`<子命令>_config.toml`，可用 `--config` 指定其他文件）：
+//!
+//! ```toml
+//! a2_text = "……"                 # 第 2 行说明文字（可选）
+//! station_code = "4401000010003" # 站点编码，写入第 4 行 A 列（可选）
+//! decimals = 2                   # 浓度保留的小数位数（可选，默认按原值输出）
+//! decimal_mark = "comma"         # 源数据的小数点：point 为 1,234.5、comma 为 1.234,5（可选，默认 point）
+//! below_limit = "half"           # 低于检出限的值（<0.01、ND）：drop 清空、half 检出限的一半、zero 写 0（可选，配置了 [detection_limit] 时默认 half，否则默认 drop）
+//! source_unit = "ng/m³"          # 源数据的浓度单位（可选，默认按仪器）
+//! unit = "μg/m³"                 # 模板的浓度单位，与 source_unit 不同时换算（可选，默认按仪器）
+//! columns = ["Pb", "As", "Cd"]   # 输出的监测项目及其顺序（可选，默认为内置的全部项目）
+//! interval = "1m"                # 源数据的时间间隔（可选，默认按仪器）
+//! resample = "1h"                # 按此时段求平均，"none" 为逐行输出（可选，默认按仪器）
+//! min_capture = 75               # 求平均时的最低数据捕获率，百分比（可选，默认 75）
+//! template = "layout.toml"       # 输出模板的版式定义，见 upload_template.example.toml（可选，默认为内置版式）
+//!
+//! [codes]                        # 因子编码，键为监测项目（可选，只需列出要改的项）
+//! "Pb" = "a20xxx"
+//!
+//! [aliases]                      # 源数据表头的别名，键为监测项目或 "时间"（可选）
+//! "Pb" = ["Lead"]
+//!
+//! [detection_limit]              # 检出限（源数据单位，也可写作 [mdl]），用于不带检出限的 ND 与低于检出限的实测值（可选）
+//! "Pb" = 0.5
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use calamine::Data;
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+use clap::Args;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+use umya_spreadsheet::Spreadsheet;
+use umya_spreadsheet::helper::coordinate::coordinate_from_index;
+
+use crate::cli::{self, GlobalArgs};
+use crate::config::{self, Found};
+use crate::error::{Context, DtToolsError, Result, config_error, invalid};
+use crate::flags::{self, BelowLimitValues};
+use crate::proton::{self, CsvEncoding, HeaderIndex, Source};
+use crate::report::{self, CellChange};
+use crate::template::{ColumnHeader, Template, Vars};
+use crate::units::{self, Conditions, DecimalMark, Species, Unit};
+use crate::{batch, export, long, progress, workbook};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 写为长表与画图时跳过的无效数据代码。
+const INVALID_CODES: &[&str] = &[flags::DEFAULT_INVALID_CODE];
+
+/// 日期与时间在同一列时的表头。
+const DATETIME_HEADERS: [&str; 5] = [
+    "时间",
+    "Start Date/Time",
+    "StartDateTime",
+    "Date/Time",
+    "DateTime",
+];
+
+/// 日期、时间分为两列时的表头；只有时间列时按同一列处理。
+/// AE33 黑碳仪导出为 `Date(yyyy/MM/dd)` 与 `Time(hh:mm:ss)`。
+const DATE_HEADERS: [&str; 4] = ["Start Date", "Date", "日期", "Date(yyyy/MM/dd)"];
+const TIME_OF_DAY_HEADERS: [&str; 3] = ["Start Time", "Time", "Time(hh:mm:ss)"];
+
+/// 源数据中时间的写法；分析仪软件常用美式的 月/日/年。
+const SOURCE_TIME_FORMATS: [&str; 10] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %I:%M %p",
+    "%d.%m.%Y %H:%M:%S",
+];
+
+/// 默认的站点编码（第 4 行 A 列）。
+const DEFAULT_STATION_CODE: &str = "4401000010003";
+
+/// 小数位数的上限。
+const MAX_DECIMALS: u32 = 10;
+
+/// 一种仪器的内置定义。
+#[derive(Debug)]
+pub struct Instrument {
+    /// 子命令名，也用于 JSON 报告与 `--post`
+    pub name: &'static str,
+    /// 第 3 行 A 列
+    pub title: &'static str,
+    /// 默认的站点配置文件名（查找顺序见 [`crate::config`]）
+    pub config_file: &'static str,
+    /// 内置的监测项目，按输出顺序
+    pub columns: &'static [ColumnDef],
+    /// 源数据的默认浓度单位
+    pub source_unit: Unit,
+    /// 模板的默认浓度单位
+    pub unit: Unit,
+    /// 源数据的时间间隔
+    pub interval: TimeDelta,
+    /// 默认按此时段求平均（如分钟数据求小时均值）；`None` 表示逐行输出
+    pub resample: Option<TimeDelta>,
+}
+
+/// 源表中没有某列时的计算方法；各项为监测项目名称或源数据表头。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Derive {
+    /// 各项之和，如 TC = OC + EC
+    Sum(&'static [&'static str]),
+    /// 前一项减后一项，如 UVPM = BC1 − BC6
+    Difference(&'static str, &'static str),
+}
+
+impl Derive {
+    fn terms(&self) -> Vec<&'static str> {
+        match self {
+            Derive::Sum(terms) => terms.to_vec(),
+            Derive::Difference(a, b) => vec![a, b],
+        }
+    }
+
+    /// 由各项的值计算；任一项缺测时为 `None`。
+    fn apply(&self, values: &[Option<f64>]) -> Option<f64> {
+        match self {
+            Derive::Sum(_) => values.iter().copied().sum(),
+            Derive::Difference(..) => Some(values[0]? - values[1]?),
+        }
+    }
+}
+
+/// 一个内置的监测项目。
+#[derive(Debug)]
+pub struct ColumnDef {
+    /// 第 3 行表头，也是配置中的键
+    pub name: &'static str,
+    /// 默认的因子编码；空字符串表示须在配置中给出
+    pub code: &'static str,
+    /// 源数据中的表头；此外总是尝试 `名称(单位)`、`名称 单位` 与 `名称`
+    pub headers: &'static [&'static str],
+    /// 缺少该列时是否报错
+    pub required: bool,
+    /// 源表中没有该列时的计算方法
+    pub derive: Option<Derive>,
+}
+
+/// 模板中的一个监测项目列。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub code: String,
+    /// 源数据中的候选表头，依次尝试
+    pub headers: Vec<String>,
+    pub required: bool,
+    pub derive: Option<Derive>,
+    /// 检出限（源数据单位），用于源数据只写 `ND` 的值以及低于检出限的实测值
+    pub detection_limit: Option<f64>,
+}
+
+impl Column {
+    /// 不在内置定义中的监测项目（如配置中新增的元素），缺少时输出留空。
+    fn custom(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            code: String::new(),
+            headers: Vec::new(),
+            required: false,
+            derive: None,
+            detection_limit: None,
+        }
+    }
+}
+
+impl From<&ColumnDef> for Column {
+    fn from(def: &ColumnDef) -> Self {
+        Self {
+            name: def.name.to_string(),
+            code: def.code.to_string(),
+            headers: def.headers.iter().map(|h| h.to_string()).collect(),
+            required: def.required,
+            derive: def.derive,
+            detection_limit: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    a2_text: Option<String>,
+    station_code: Option<String>,
+    decimals: Option<u32>,
+    decimal_mark: Option<DecimalMark>,
+    below_limit: Option<BelowLimitValues>,
+    source_unit: Option<Unit>,
+    unit: Option<Unit>,
+    columns: Option<Vec<String>>,
+    interval: Option<String>,
+    resample: Option<String>,
+    min_capture: Option<u32>,
+    template: Option<PathBuf>,
+    #[serde(default)]
+    codes: BTreeMap<String, String>,
+    #[serde(default)]
+    aliases: BTreeMap<String, Vec<String>>,
+    #[serde(default, alias = "mdl")]
+    detection_limit: BTreeMap<String, f64>,
+}
+
+impl ConfigFile {
+    /// 把相对路径改为相对配置文件 `path` 所在的目录。
+    fn resolve_paths(&mut self, path: &Path) {
+        if let Some(template) = &mut self.template {
+            *template = config::resolve_path(path, template);
+        }
+    }
+}
+
+/// 仪器数据的转换选项。
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentOptions {
+    /// 第 3 行 A 列
+    pub title: String,
+    /// 输出第 2 行的说明文字
+    pub a2_text: String,
+    /// 站点编码（第 4 行 A 列）
+    pub station_code: String,
+    /// 输出的监测项目，顺序同第 3 行表头（B 列起）
+    pub columns: Vec<Column>,
+    /// 时间列的表头别名
+    pub time_aliases: Vec<String>,
+    /// 浓度保留的小数位数；`None` 表示按原值输出
+    pub decimals: Option<u32>,
+    /// 源数据中数值的小数点；千位分隔符、前导 `+` 与科学计数法在检查数值前规范化
+    pub decimal_mark: DecimalMark,
+    /// 低于检出限的值（如 `<0.01`、`ND`）的处理方式；不支持 [`BelowLimitValues::Flag`]
+    pub below_limit: BelowLimitValues,
+    /// 源数据的浓度单位
+    pub source_unit: Unit,
+    /// 模板的浓度单位
+    pub unit: Unit,
+    /// 源数据的时间间隔，用于计算每个时段应有的数据个数
+    pub interval: TimeDelta,
+    /// 按此时段求平均，输出为时段起始时间的一行；`None` 表示逐行输出
+    pub resample: Option<TimeDelta>,
+    /// 求平均时的最低数据捕获率（百分比）
+    pub min_capture: u32,
+    /// 输出模板的版式
+    pub template: Template,
+}
+
+impl InstrumentOptions {
+    /// `instrument` 的内置选项。
+    pub fn new(instrument: &Instrument) -> Self {
+        Self {
+            title: instrument.title.to_string(),
+            a2_text: format!(
+                "请参考 {}_config.example.toml 创建配置文件 {}",
+                instrument.name, instrument.config_file
+            ),
+            station_code: DEFAULT_STATION_CODE.to_string(),
+            columns: instrument.columns.iter().map(Column::from).collect(),
+            time_aliases: Vec::new(),
+            decimals: None,
+            decimal_mark: DecimalMark::Point,
+            below_limit: BelowLimitValues::Drop,
+            source_unit: instrument.source_unit,
+            unit: instrument.unit,
+            interval: instrument.interval,
+            resample: instrument.resample,
+            min_capture: proton::DEFAULT_MIN_CAPTURE,
+            template: Template::builtin(),
+        }
+    }
+
+    /// 解析 TOML 配置；未出现的项使用 `instrument` 的内置值。
+    pub fn from_toml_str(instrument: &Instrument, content: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(content).context("配置文件格式错误")?;
+        Self::from_config(instrument, file)
+    }
+
+    /// 由已解析的 TOML 表构造，供 `dttools config check` 逐项检查；`path` 为配置文件。
+    pub(crate) fn from_table(
+        instrument: &Instrument,
+        table: toml::Table,
+        path: &Path,
+    ) -> Result<Self> {
+        let mut file =
+            ConfigFile::deserialize(toml::Value::Table(table)).context("配置文件格式错误")?;
+        file.resolve_paths(path);
+        Self::from_config(instrument, file)
+    }
+
+    /// 从 `path` 读取配置，文件不存在时使用内置值。
+    pub fn load(instrument: &Instrument, path: &Path) -> Result<Self> {
+        Self::from_found(instrument, config::read_file(path)?)
+    }
+
+    /// 按查找顺序（见 [`crate::config`]）读取配置，`explicit` 为 `--config` 指定的文件。
+    pub fn discover(instrument: &Instrument, explicit: Option<&Path>) -> Result<Self> {
+        let found = config::discover(explicit, instrument.config_file, instrument.name)?;
+        Self::from_found(instrument, found)
+    }
+
+    pub(crate) fn from_found(instrument: &Instrument, found: Option<Found>) -> Result<Self> {
+        let Some(found) = found else {
+            return Ok(Self::new(instrument));
+        };
+        found
+            .deserialize()
+            .context("配置文件格式错误")
+            .and_then(|mut file: ConfigFile| {
+                file.resolve_paths(&found.path);
+                Self::from_config(instrument, file)
+            })
+            .with_context(|| format!("配置文件: {found}"))
+    }
+
+    fn from_config(instrument: &Instrument, file: ConfigFile) -> Result<Self> {
+        let mut options = Self::new(instrument);
+        if let Some(a2_text) = file.a2_text {
+            options.a2_text = a2_text.trim().to_string();
+        }
+        if let Some(station_code) = file.station_code {
+            options.set_station_code(&station_code)?;
+        }
+        if let Some(decimals) = file.decimals {
+            options.set_decimals(decimals)?;
+        }
+        if let Some(mark) = file.decimal_mark {
+            options.decimal_mark = mark;
+        }
+        if let Some(mode) = file.below_limit {
+            options
+                .set_below_limit(mode)
+                .map_err(|err| config_error!("{err}"))?;
+        }
+        if let Some(unit) = file.source_unit {
+            options.source_unit = check_unit(unit)?;
+        }
+        if let Some(unit) = file.unit {
+            options.unit = check_unit(unit)?;
+        }
+        if let Some(interval) = file.interval {
+            options.interval =
+                proton::parse_interval(&interval).map_err(|err| config_error!("{err}"))?;
+        }
+        if let Some(period) = file.resample {
+            options.resample = match period.trim() {
+                "" | "none" => None,
+                period => {
+                    Some(proton::parse_interval(period).map_err(|err| config_error!("{err}"))?)
+                }
+            };
+        }
+        if let Some(min_capture) = file.min_capture {
+            if min_capture > 100 {
+                return Err(config_error!(
+                    "min_capture 必须在 0 到 100 之间: {min_capture}"
+                ));
+            }
+            options.min_capture = min_capture;
+        }
+        if let Some(path) = file.template {
+            options.template = Template::load(&path)?;
+        }
+        if let Some(names) = file.columns {
+            if names.is_empty() {
+                return Err(config_error!("columns 不能为空"));
+            }
+            let mut columns: Vec<Column> = Vec::new();
+            for name in &names {
+                let name = name.trim();
+                if name.is_empty() || name == "时间" {
+                    return Err(config_error!("columns 中的监测项目无效: {name:?}"));
+                }
+                if columns
+                    .iter()
+                    .any(|column| column.name.eq_ignore_ascii_case(name))
+                {
+                    return Err(config_error!("columns 中的监测项目重复: {name}"));
+                }
+                columns.push(
+                    options
+                        .columns
+                        .iter()
+                        .find(|column| column.name.eq_ignore_ascii_case(name))
+                        .cloned()
+                        .unwrap_or_else(|| Column::custom(name)),
+                );
+            }
+            options.columns = columns;
+        }
+        for (name, code) in &file.codes {
+            if code.trim().is_empty() {
+                return Err(config_error!("[codes] 中 {name} 的编码不能为空"));
+            }
+            options.column_mut("codes", name)?.code = code.trim().to_string();
+        }
+        for (name, aliases) in file.aliases {
+            if aliases.iter().any(|alias| alias.trim().is_empty()) {
+                return Err(config_error!("[aliases] 中 {name} 的别名不能为空"));
+            }
+            if name == "时间" {
+                options.time_aliases = aliases;
+            } else {
+                options
+                    .column_mut("aliases", &name)?
+                    .headers
+                    .extend(aliases);
+            }
+        }
+        for (name, &limit) in &file.detection_limit {
+            if !(limit.is_finite() && limit > 0.0) {
+                return Err(config_error!(
+                    "[detection_limit] 中 {name} 的检出限必须大于 0: {limit}"
+                ));
+            }
+            options.column_mut("detection_limit", name)?.detection_limit = Some(limit);
+        }
+        // 配置了检出限而没有指定处理方式时，低于检出限的值写为检出限的一半
+        if file.below_limit.is_none() && !file.detection_limit.is_empty() {
+            options.below_limit = BelowLimitValues::Half;
+        }
+        Ok(options)
+    }
+
+    /// 配置中按名称（不区分大小写）指定的监测项目。
+    fn column_mut(&mut self, section: &str, name: &str) -> Result<&mut Column> {
+        self.columns
+            .iter_mut()
+            .find(|column| column.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| config_error!("[{section}] 中未知的监测项目: {name}"))
+    }
+
+    /// 覆盖站点编码（如来自 `--station`）。
+    pub fn set_station_code(&mut self, code: &str) -> Result<()> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Err(invalid!("站点编码不能为空"));
+        }
+        self.station_code = code.to_string();
+        Ok(())
+    }
+
+    /// 设置小数位数（如来自 `--decimals`）。
+    pub fn set_decimals(&mut self, decimals: u32) -> Result<()> {
+        if decimals > MAX_DECIMALS {
+            return Err(invalid!("小数位数不能超过 {MAX_DECIMALS}: {decimals}"));
+        }
+        self.decimals = Some(decimals);
+        Ok(())
+    }
+
+    /// 设置低于检出限的值的处理方式（如来自 `--below-limit`）。
+    pub fn set_below_limit(&mut self, mode: BelowLimitValues) -> Result<()> {
+        if mode == BelowLimitValues::Flag {
+            return Err(invalid!(
+                "仪器数据的低于检出限的值只能 drop、half 或 zero，flag 只适用于 proton"
+            ));
+        }
+        self.below_limit = mode;
+        Ok(())
+    }
+
+    /// 设置模板的浓度单位（如来自 `--unit`）。
+    pub fn set_unit(&mut self, unit: Unit) -> Result<()> {
+        self.unit = check_unit(unit)?;
+        Ok(())
+    }
+}
+
+/// 仪器数据只支持质量浓度单位。
+fn check_unit(unit: Unit) -> Result<Unit> {
+    match unit {
+        Unit::UgM3 | Unit::NgM3 => Ok(unit),
+        other => Err(invalid!("浓度单位只能是 μg/m³ 或 ng/m³: {other}")),
+    }
+}
+
+/// 一次转换的结果。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransformReport {
+    /// 源工作表名称
+    pub source_sheet: String,
+    /// 输出的数据行数
+    pub rows: usize,
+    /// 读取到的源数据行数；求平均时多于输出的行数
+    pub source_rows: usize,
+    /// 源表中被改写（时间格式化、低于检出限）或清空（非数值）的单元格
+    pub changes: Vec<CellChange>,
+    /// 替换或清空的低于检出限的值个数（见 [`InstrumentOptions::below_limit`]）
+    pub below_limit: usize,
+    /// 源表中没有、按其他监测项目之和计算的列
+    pub computed: Vec<String>,
+    /// 源表中找不到、输出留空的列
+    pub missing: Vec<String>,
+}
+
+/// 转换输出：上传模板工作簿、对应的数据表（首行为表头）与转换报告。
+#[derive(Debug, Clone)]
+pub struct Transformed {
+    pub workbook: Spreadsheet,
+    pub table: Vec<Vec<String>>,
+    pub report: TransformReport,
+}
+
+/// 时间列的位置：同一列，或日期、时间分为两列。
+enum TimeColumns {
+    Combined(usize),
+    Split { date: usize, time: usize },
+}
+
+/// 计算列中的一项：已读取（并按检出限替换过）的配置列，或不在配置中的源表列。
+#[derive(Clone, Copy)]
+enum Term {
+    Column(usize),
+    Source(usize),
+}
+
+/// 源数据中的时间单元格格式化为 `YYYY-MM-DD HH:MM:SS`；无法解析时返回 `None`。
+fn parse_time(text: &str) -> Option<String> {
+    let text = text.trim();
+    SOURCE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        // Excel 日期单元格读出为序列号
+        .or_else(|| workbook::parse_serial(text))
+        .map(|time| time.format(TIME_FORMAT).to_string())
+}
+
+/// 时间列的单元格文本；Excel 日期时间单元格按工作簿的日期系统换算，未设置日期格式的
+/// 序列号由 [`parse_time`] 换算，小于 1 的序列号为一天中的时刻。
+fn cell_text(cell: Option<&Data>) -> String {
+    let serial = match cell {
+        Some(Data::DateTime(time)) if time.as_f64() >= 1.0 => {
+            return proton::time_cell_text(cell);
+        }
+        Some(Data::DateTime(time)) => time.as_f64(),
+        Some(Data::Float(value)) => *value,
+        other => return proton::datatype_to_string(other),
+    };
+    if (0.0..1.0).contains(&serial) {
+        // 日期、时间分列时，时间单元格为一天中的比例
+        workbook::serial_datetime(serial)
+            .format("%H:%M:%S")
+            .to_string()
+    } else {
+        proton::datatype_to_string(Some(&Data::Float(serial)))
+    }
+}
+
+fn cell_ref(col_0: usize, row_0: usize) -> String {
+    coordinate_from_index(&(col_0 as u32 + 1), &(row_0 as u32 + 1))
+}
+
+/// 源表中的时间列。
+fn locate_time(index: &HeaderIndex, options: &InstrumentOptions) -> Result<TimeColumns> {
+    let combined = index.find(
+        DATETIME_HEADERS
+            .iter()
+            .copied()
+            .chain(options.time_aliases.iter().map(String::as_str)),
+    );
+    match (
+        combined,
+        index.find(DATE_HEADERS),
+        index.find(TIME_OF_DAY_HEADERS),
+    ) {
+        (Some(col), _, _) | (None, None, Some(col)) => Ok(TimeColumns::Combined(col)),
+        (None, Some(date), Some(time)) => Ok(TimeColumns::Split { date, time }),
+        _ => Err(invalid!("找不到时间列（如 '时间'、'Start Date/Time'）")),
+    }
+}
+
+/// 源表中名为 `name` 的列：依次尝试 `headers`、`名称(单位)`、`名称 单位` 与 `名称`。
+fn find_column(
+    index: &HeaderIndex,
+    name: &str,
+    headers: &[String],
+    source_unit: &str,
+) -> Option<usize> {
+    let with_unit = [
+        format!("{name}({source_unit})"),
+        format!("{name}{source_unit}"),
+    ];
+    index.find(
+        headers
+            .iter()
+            .chain(&with_unit)
+            .map(String::as_str)
+            .chain([name]),
+    )
+}
+
+/// 按 `period` 对 `rows` 求平均，输出为时段起始时间的一行。`interval` 为源数据的时间间隔，
+/// 有效数值个数占应有个数的比例低于 `min_capture`（百分比）时，该时段该列留空。
+fn resample(
+    rows: Vec<(String, Vec<Option<f64>>)>,
+    period: TimeDelta,
+    interval: TimeDelta,
+    min_capture: u32,
+) -> Result<Vec<(String, Vec<Option<f64>>)>> {
+    let period_ms = period.num_milliseconds();
+    let interval_ms = interval.num_milliseconds();
+    if period_ms < interval_ms || period_ms % interval_ms != 0 {
+        return Err(invalid!("求平均的时段必须是数据时间间隔的整数倍"));
+    }
+    let expected = (period_ms / interval_ms) as usize;
+    let period_secs = period.num_seconds();
+
+    let mut buckets: BTreeMap<NaiveDateTime, Vec<Vec<f64>>> = BTreeMap::new();
+    for (time, values) in rows {
+        let time = NaiveDateTime::parse_from_str(&time, TIME_FORMAT)?;
+        let seconds = time.and_utc().timestamp();
+        let start = DateTime::from_timestamp(seconds - seconds.rem_euclid(period_secs), 0)
+            .expect("timestamp within range")
+            .naive_utc();
+        let columns = buckets
+            .entry(start)
+            .or_insert_with(|| vec![Vec::new(); values.len()]);
+        for (column, value) in columns.iter_mut().zip(values) {
+            column.extend(value);
+        }
+    }
+    Ok(buckets
+        .into_iter()
+        .map(|(start, columns)| {
+            let values = columns
+                .into_iter()
+                .map(|values| {
+                    let captured =
+                        values.len().min(expected) * 100 >= expected * min_capture as usize;
+                    (captured && !values.is_empty())
+                        .then(|| values.iter().sum::<f64>() / values.len() as f64)
+                })
+                .collect();
+            (start.format(TIME_FORMAT).to_string(), values)
+        })
+        .collect())
+}
+
+/// 按 `options` 把仪器导出转换为上传模板。
+pub fn transform(source: &Source, options: &InstrumentOptions) -> Result<Transformed> {
+    let range = &source.range;
+    let (height, width) = range.get_size();
+    if height < 2 {
+        return Err(invalid!("表格行数不足，无法读取数据"));
+    }
+
+    let headers: Vec<String> = (0..width)
+        .map(|col| proton::datatype_to_string(range.get((0, col))))
+        .collect();
+    let index = HeaderIndex::new(
+        headers
+            .iter()
+            .enumerate()
+            .map(|(col, header)| (col, header.as_str())),
+    );
+    let time_columns = locate_time(&index, options)?;
+    let source_unit = options.source_unit.to_string();
+    let source_cols: Vec<Option<usize>> = options
+        .columns
+        .iter()
+        .map(|column| find_column(&index, &column.name, &column.headers, &source_unit))
+        .collect();
+
+    let mut computed = Vec::new();
+    let mut missing = Vec::new();
+    // 按其他列计算的列：(列序号, 计算方法, 各项)
+    let mut derived: Vec<(usize, Derive, Vec<Term>)> = Vec::new();
+    for (i, column) in options.columns.iter().enumerate() {
+        if source_cols[i].is_some() {
+            continue;
+        }
+        let terms: Option<Vec<Term>> = column.derive.and_then(|derive| {
+            derive
+                .terms()
+                .into_iter()
+                .map(|term| {
+                    match options
+                        .columns
+                        .iter()
+                        .position(|c| c.name.eq_ignore_ascii_case(term))
+                    {
+                        Some(j) => source_cols[j].map(|_| Term::Column(j)),
+                        None => find_column(&index, term, &[], &source_unit).map(Term::Source),
+                    }
+                })
+                .collect()
+        });
+        match (column.derive, terms) {
+            (Some(derive), Some(terms)) => {
+                derived.push((i, derive, terms));
+                computed.push(column.name.clone());
+            }
+            _ if column.required => {
+                return Err(DtToolsError::MissingColumn {
+                    name: column.name.clone(),
+                });
+            }
+            _ => missing.push(column.name.clone()),
+        }
+    }
+    if source_cols.iter().all(Option::is_none) && derived.is_empty() {
+        return Err(invalid!("源表中找不到任何监测项目列"));
+    }
+
+    let mut changes = Vec::new();
+    let mut record_change = |row: usize, col: usize, before: &str, after: &str, rule: &str| {
+        changes.push(CellChange {
+            sheet: source.sheet_name.clone(),
+            address: cell_ref(col, row),
+            before: before.to_string(),
+            after: after.to_string(),
+            red_fill: false,
+            rules: vec![rule.to_string()],
+        });
+    };
+    let number = |row: usize, col: usize| -> Option<f64> {
+        let text = proton::datatype_to_string(range.get((row, col)));
+        units::normalize_number(&text, options.decimal_mark)?
+            .parse()
+            .ok()
+    };
+
+    let species = Species::default();
+    let conditions = Conditions::default();
+    let mut rows: Vec<(String, Vec<Option<f64>>)> = Vec::new();
+    let progress = progress::bar(height - 1, "读取数据", "行");
+    for row in 1..height {
+        progress.inc(1);
+        let (time_col, time_text) = match time_columns {
+            TimeColumns::Combined(col) => (col, cell_text(range.get((row, col)))),
+            TimeColumns::Split { date, time } => {
+                let date_text = cell_text(range.get((row, date)));
+                let date_text = parse_time(&date_text)
+                    .map(|time| time[..10].to_string())
+                    .unwrap_or(date_text);
+                let time_text = cell_text(range.get((row, time)));
+                (date, format!("{date_text} {time_text}").trim().to_string())
+            }
+        };
+        if time_text.is_empty() {
+            continue;
+        }
+        let time = match parse_time(&time_text) {
+            Some(time) => time,
+            // 求平均时无法确定所属时段，该行不参与
+            None if options.resample.is_some() => {
+                record_change(row, time_col, &time_text, "", "invalid_time");
+                continue;
+            }
+            None => time_text.clone(),
+        };
+        if time != time_text {
+            record_change(row, time_col, &time_text, &time, "time_format");
+        }
+
+        let mut values: Vec<Option<f64>> = source_cols
+            .iter()
+            .zip(&options.columns)
+            .map(|(col, column)| {
+                let col = (*col)?;
+                let text = proton::datatype_to_string(range.get((row, col)));
+                if text.trim().is_empty() {
+                    return None;
+                }
+                if let Some(limit) = flags::parse_below_limit(&text) {
+                    let value = options
+                        .below_limit
+                        .substitute(limit.or(column.detection_limit), "")
+                        .and_then(|value| value.parse::<f64>().ok());
+                    let after = value.map(|value| value.to_string()).unwrap_or_default();
+                    record_change(row, col, &text, &after, "below_limit");
+                    return value;
+                }
+                let value = number(row, col);
+                match value {
+                    None => record_change(row, col, &text, "", "non_numeric"),
+                    Some(measured)
+                        if options
+                            .below_limit
+                            .replaces_measured(measured, column.detection_limit) =>
+                    {
+                        let value = options
+                            .below_limit
+                            .substitute(column.detection_limit, "")
+                            .and_then(|value| value.parse::<f64>().ok());
+                        let after = value.map(|value| value.to_string()).unwrap_or_default();
+                        record_change(row, col, &text, &after, "below_limit");
+                        return value;
+                    }
+                    Some(_) => {}
+                }
+                value
+            })
+            .collect();
+        // 按检出限替换之后的值计算
+        for (i, derive, terms) in &derived {
+            let terms: Vec<Option<f64>> = terms
+                .iter()
+                .map(|term| match *term {
+                    Term::Column(j) => values[j],
+                    Term::Source(col) => {
+                        let text = proton::datatype_to_string(range.get((row, col)));
+                        match flags::parse_below_limit(&text) {
+                            Some(limit) => options
+                                .below_limit
+                                .substitute(limit, "")
+                                .and_then(|value| value.parse().ok()),
+                            None => number(row, col),
+                        }
+                    }
+                })
+                .collect();
+            values[*i] = derive.apply(&terms);
+        }
+        for value in values.iter_mut().flatten() {
+            *value = units::convert(
+                *value,
+                options.source_unit,
+                options.unit,
+                &species,
+                &conditions,
+            )?;
+        }
+        rows.push((time, values));
+    }
+    let source_rows = rows.len();
+    if let Some(period) = options.resample {
+        rows = resample(rows, period, options.interval, options.min_capture)?;
+    }
+
+    let workbook = render_template(&rows, options);
+    let table = data_table(&rows, options);
+    Ok(Transformed {
+        workbook,
+        table,
+        report: TransformReport {
+            source_sheet: source.sheet_name.clone(),
+            rows: rows.len(),
+            source_rows,
+            below_limit: changes
+                .iter()
+                .filter(|change| change.rules[0] == "below_limit")
+                .count(),
+            changes,
+            computed,
+            missing,
+        },
+    })
+}
+
+fn render_template(
+    rows: &[(String, Vec<Option<f64>>)],
+    options: &InstrumentOptions,
+) -> Spreadsheet {
+    let mut book = umya_spreadsheet::new_file();
+    let sheet = book.get_active_sheet_mut();
+
+    let template = &options.template;
+    let unit = options.unit.to_string();
+    let columns: Vec<ColumnHeader> = options
+        .columns
+        .iter()
+        .map(|column| ColumnHeader {
+            name: &column.name,
+            code: &column.code,
+            unit: &unit,
+        })
+        .collect();
+    template.render_header(
+        sheet,
+        &Vars {
+            title: &options.title,
+            station_code: &options.station_code,
+            a2_text: &options.a2_text,
+        },
+        &columns,
+    );
+    let time_style = template.time_cell_style(false);
+
+    let number_format = match options.decimals {
+        Some(0) | None => "0".to_string(),
+        Some(decimals) => format!("0.{}", "0".repeat(decimals as usize)),
+    };
+    let progress = progress::bar(rows.len(), "写入模板", "行");
+    for (i, (time, values)) in rows.iter().enumerate() {
+        progress.inc(1);
+        let time_cell = sheet.get_cell_mut(template.time_address(i).as_str());
+        time_cell.set_style(time_style.clone());
+        if template.typed_cells {
+            workbook::set_time_cell(time_cell, time);
+        } else {
+            time_cell.set_value(time);
+        }
+        for (col, value) in values.iter().enumerate() {
+            let cell = sheet.get_cell_mut(template.value_address(i, col).as_str());
+            match (value, options.decimals) {
+                (Some(value), Some(decimals)) => {
+                    let rounded: f64 = units::format_number(*value, Some(decimals))
+                        .parse()
+                        .unwrap_or(*value);
+                    cell.set_value_number(rounded);
+                    cell.get_style_mut()
+                        .get_number_format_mut()
+                        .set_format_code(number_format.as_str());
+                }
+                (Some(value), None) if template.typed_cells => {
+                    workbook::set_number_cell(cell, &units::format_number(*value, None), None);
+                }
+                (Some(value), None) => {
+                    cell.set_value(units::format_number(*value, None));
+                }
+                (None, _) => {
+                    cell.set_value("");
+                }
+            }
+        }
+    }
+    template.finish_sheet(sheet, columns.len(), rows.len());
+    book
+}
+
+fn data_table(
+    rows: &[(String, Vec<Option<f64>>)],
+    options: &InstrumentOptions,
+) -> Vec<Vec<String>> {
+    let mut table = vec![
+        std::iter::once("时间".to_string())
+            .chain(options.columns.iter().map(|c| c.name.clone()))
+            .collect::<Vec<_>>(),
+    ];
+    table.extend(rows.iter().map(|(time, values)| {
+        std::iter::once(time.clone())
+            .chain(values.iter().map(|value| {
+                value
+                    .map(|value| units::format_number(value, options.decimals))
+                    .unwrap_or_default()
+            }))
+            .collect()
+    }));
+    table
+}
+
+fn process_excel(
+    instrument: &Instrument,
+    path: &Path,
+    args: &InstrumentArgs,
+    options: &InstrumentOptions,
+    global: &GlobalArgs,
+) -> Result<report::ProcessedFile> {
+    global.check_unprocessed(path)?;
+    let source = proton::read_source(path, args.delimiter, args.encoding)?;
+    let Transformed {
+        workbook: mut book,
+        table,
+        report,
+    } = transform(&source, options)?;
+
+    debug!(
+        "工作表 '{}': 读取到 {} 行数据",
+        report.source_sheet, report.rows
+    );
+    if options.resample.is_some() {
+        info!(
+            "{} 行源数据已求平均为 {} 行",
+            report.source_rows, report.rows
+        );
+    }
+    for name in &report.computed {
+        info!("源表中没有 {name} 列，已按其他列计算");
+    }
+    if report.below_limit > 0 {
+        info!("低于检出限的值: {} 个", report.below_limit);
+    }
+    if !report.missing.is_empty() {
+        warn!(
+            "源表中找不到以下监测项目，输出留空: {}",
+            report.missing.join("、")
+        );
+    }
+
+    let output_path = global.workbook_output_path(path);
+    if global.dry_run {
+        info!(
+            "[试运行] 将输出 {} 行数据到: {}",
+            report.rows,
+            output_path.display()
+        );
+        report::print_planned_changes(&report.changes);
+        return Ok(report::ProcessedFile {
+            input: path.to_path_buf(),
+            output: output_path,
+            exports: Vec::new(),
+            rows: report.rows,
+            warnings: report.missing.len(),
+            changes: report.changes,
+            problems: Vec::new(),
+            skipped: false,
+        });
+    }
+
+    report::log_changes(&report.changes);
+    global.prepare_output(&output_path)?;
+    if !long::write_if_parquet(
+        global.format,
+        &output_path,
+        &options.station_code,
+        &table,
+        INVALID_CODES,
+    )? {
+        workbook::mark_processed(&mut book, options);
+        workbook::write(&book, &output_path, global.format)?;
+    }
+
+    let mut exports = Vec::new();
+    if let Some(delimiter) = global.csv_export() {
+        let csv_path = export::csv_path(&output_path, delimiter);
+        export::write_csv(&csv_path, &table, delimiter)?;
+        info!("数据表已导出为: {}", csv_path.display());
+        exports.push(csv_path);
+    }
+    #[cfg(feature = "plots")]
+    if global.plots {
+        let charts = crate::plot::write_charts(&output_path, &table, INVALID_CODES)?;
+        info!("已为 {} 个项目画出时间序列图", charts.len());
+        exports.extend(charts);
+    }
+
+    let codes: Vec<&str> = options.columns.iter().map(|c| c.code.as_str()).collect();
+    args.hj212
+        .send(&options.station_code, &table, &codes, INVALID_CODES)?;
+
+    #[cfg(feature = "upload")]
+    global.upload.send(&output_path)?;
+    #[cfg(feature = "post")]
+    if global.post.url.is_some() {
+        let sheet = book
+            .get_sheet(&0)
+            .map(|sheet| sheet.get_name())
+            .unwrap_or_default();
+        let sheets = vec![crate::post::SheetRows::new(sheet, &table)];
+        global
+            .post
+            .send(instrument.name, path, &output_path, sheets)?;
+    }
+    #[cfg(not(feature = "post"))]
+    let _ = instrument;
+    #[cfg(feature = "postgres")]
+    if args.db.db_url.is_some() {
+        args.db.send(&long::measurements(
+            &options.station_code,
+            &table,
+            INVALID_CODES,
+        ))?;
+    }
+    global.archive.store(path)?;
+
+    Ok(report::ProcessedFile {
+        input: path.to_path_buf(),
+        output: output_path,
+        exports,
+        rows: report.rows,
+        warnings: report.missing.len(),
+        changes: report.changes,
+        problems: Vec::new(),
+        skipped: false,
+    })
+}
+
+/// 仪器转换子命令（`dttools ocec`、`dttools metals` 等）共用的参数。
+#[derive(Debug, Clone, Args)]
+pub struct InstrumentArgs {
+    /// 输入文件（xlsx/xls/ods/csv）、目录或通配符（如 exports/*.csv）
+    #[arg(value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    /// CSV 输入的分隔符，如 `,`、`;` 或 `tab`
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = cli::parse_delimiter)]
+    pub delimiter: u8,
+
+    /// CSV 输入的编码
+    #[arg(long, value_enum, default_value_t = CsvEncoding::Auto)]
+    pub encoding: CsvEncoding,
+
+    /// 站点配置文件（默认按查找顺序找 <子命令>_config.toml 或 dttools.toml 的对应小节）
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// 站点编码，覆盖配置文件中的 station_code
+    #[arg(long, value_name = "CODE")]
+    pub station: Option<String>,
+
+    /// 浓度保留的小数位数，覆盖配置文件中的 decimals
+    #[arg(long, value_name = "N")]
+    pub decimals: Option<u32>,
+
+    /// 源数据的小数点：point 为 1,234.5，comma 为 1.234,5；覆盖配置文件中的 decimal_mark
+    #[arg(long, value_enum, value_name = "MARK")]
+    pub decimal_mark: Option<DecimalMark>,
+
+    /// 低于检出限的值（如 <0.01、ND）：drop 清空，half 写检出限的一半，zero 写 0；
+    /// 覆盖配置文件中的 below_limit
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub below_limit: Option<BelowLimitValues>,
+
+    /// 模板的浓度单位（μg/m³ 或 ng/m³），覆盖配置文件中的 unit
+    #[arg(long, value_name = "UNIT")]
+    pub unit: Option<Unit>,
+
+    /// 源数据的时间间隔（如 1m），覆盖配置文件中的 interval
+    #[arg(long, value_name = "DURATION", value_parser = proton::parse_interval)]
+    pub interval: Option<TimeDelta>,
+
+    /// 按时段（如 1h）求平均，覆盖配置文件中的 resample
+    #[arg(long, value_name = "DURATION", value_parser = proton::parse_interval)]
+    pub resample: Option<TimeDelta>,
+
+    /// 求平均时的最低数据捕获率（百分比，默认 75），有效数据不足时该时段留空
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub min_capture: Option<u32>,
+
+    #[command(flatten)]
+    pub hj212: crate::hj212::Hj212Args,
+
+    #[cfg(feature = "postgres")]
+    #[command(flatten)]
+    pub db: crate::db::DbArgs,
+}
+
+impl InstrumentArgs {
+    /// 读取站点配置，并用命令行参数覆盖其中的各项。
+    fn options(&self, instrument: &Instrument) -> Result<InstrumentOptions> {
+        let mut options = InstrumentOptions::discover(instrument, self.config.as_deref())?;
+        if let Some(station) = &self.station {
+            options.set_station_code(station)?;
+        }
+        if let Some(decimals) = self.decimals {
+            options.set_decimals(decimals)?;
+        }
+        if let Some(mark) = self.decimal_mark {
+            options.decimal_mark = mark;
+        }
+        if let Some(mode) = self.below_limit {
+            options.set_below_limit(mode)?;
+        }
+        if let Some(unit) = self.unit {
+            options.set_unit(unit)?;
+        }
+        if let Some(interval) = self.interval {
+            options.interval = interval;
+        }
+        if let Some(period) = self.resample {
+            options.resample = Some(period);
+        }
+        if let Some(min_capture) = self.min_capture {
+            options.min_capture = min_capture;
+        }
+        Ok(options)
+    }
+}
+
+/// 执行 `instrument` 对应的子命令。
+pub fn execute(instrument: &Instrument, args: &InstrumentArgs, global: &GlobalArgs) -> Result<()> {
+    if args.inputs.is_empty() {
+        info!(
+            "请提供文件名、目录或通配符作为参数，例如：dttools {} export.csv",
+            instrument.name
+        );
+        return Ok(());
+    }
+
+    let options = args.options(instrument)?;
+    let missing_codes: Vec<&str> = options
+        .columns
+        .iter()
+        .filter(|column| column.code.is_empty())
+        .map(|column| column.name.as_str())
+        .collect();
+    if !missing_codes.is_empty() {
+        warn!(
+            "以下监测项目没有配置因子编码（{} 的 [codes]），第 4 行留空: {}",
+            instrument.config_file,
+            missing_codes.join("、")
+        );
+    }
+    let inputs = batch::expand_inputs(&args.inputs, proton::INPUT_EXTENSIONS)?;
+    global.check_inputs(&inputs)?;
+    if !inputs.batch {
+        let processed = process_excel(instrument, &inputs.files[0], args, &options, global)?;
+        if global.dry_run {
+            info!("试运行完成，未写入文件: {}", processed.output.display());
+        } else {
+            info!("文件已处理并保存为: {}", processed.output.display());
+        }
+        return global.write_report(instrument.name, &[processed]);
+    }
+
+    let mut processed = Vec::new();
+    let mut state = global.resume_state(instrument.name, &options, &inputs.files)?;
+    let result = batch::run_batch(&inputs.files, global.open_failures(), &mut state, |file| {
+        let file = process_excel(instrument, file, args, &options, global)?;
+        let output = file.output.clone();
+        processed.push(file);
+        Ok(output)
+    });
+    processed.extend(state.skipped_files());
+    global.write_report(instrument.name, &processed)?;
+    global.write_manifest(instrument.name, &processed)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{ExcelDateTime, ExcelDateTimeType};
+
+    const TEST: Instrument = Instrument {
+        name: "test",
+        title: "TEST",
+        config_file: "test_config.toml",
+        columns: &[
+            ColumnDef {
+                name: "A",
+                code: "a00001",
+                headers: &["Alpha"],
+                required: true,
+                derive: None,
+            },
+            ColumnDef {
+                name: "B",
+                code: "",
+                headers: &[],
+                required: false,
+                derive: None,
+            },
+        ],
+        source_unit: Unit::NgM3,
+        unit: Unit::NgM3,
+        interval: TimeDelta::hours(1),
+        resample: None,
+    };
+
+    #[test]
+    fn configures_columns_codes_and_units() -> Result<()> {
+        let options = InstrumentOptions::from_toml_str(
+            &TEST,
+            r#"
+            unit = "μg/m³"
+            columns = ["b", "C", "A"]
+
+            [codes]
+            "C" = "a00003"
+
+            [aliases]
+            "A" = ["First"]
+            "#,
+        )?;
+        let names: Vec<_> = options.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["B", "C", "A"]);
+        assert_eq!(options.columns[1].code, "a00003");
+        assert_eq!(options.columns[2].headers, ["Alpha", "First"]);
+        assert_eq!(options.unit, Unit::UgM3);
+
+        assert!(InstrumentOptions::from_toml_str(&TEST, "[codes]\n\"D\" = \"x\"\n").is_err());
+        assert!(InstrumentOptions::from_toml_str(&TEST, "unit = \"ppbv\"\n").is_err());
+        assert!(InstrumentOptions::from_toml_str(&TEST, "columns = [\"A\", \"a\"]\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_instrument_times() {
+        assert_eq!(
+            parse_time("01/05/2026 1:00:00 PM").as_deref(),
+            Some("2026-01-05 13:00:00")
+        );
+        assert_eq!(
+            parse_time("2026/01/05 00:01").as_deref(),
+            Some("2026-01-05 00:01:00")
+        );
+        assert_eq!(
+            parse_time("46027.5").as_deref(),
+            Some("2026-01-05 12:00:00")
+        );
+        assert_eq!(parse_time("n/a"), None);
+        assert_eq!(cell_text(Some(&Data::Float(0.25))), "06:00:00");
+        // 1904 日期系统的工作簿
+        let native = ExcelDateTime::new(44_565.5, ExcelDateTimeType::DateTime, true);
+        assert_eq!(
+            cell_text(Some(&Data::DateTime(native))),
+            "2026-01-05 12:00:00"
+        );
+    }
+}
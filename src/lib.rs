@@ -1,2 +1,49 @@
+pub mod archive;
+mod batch;
+pub mod bc;
+pub mod cli;
+pub mod codes;
+pub mod config;
+pub mod console;
+#[cfg(feature = "postgres")]
+pub mod db;
+pub mod diff;
 pub mod eemcg;
+pub mod error;
+mod export;
+pub mod flags;
+pub mod hj212;
+pub mod i18n;
+pub mod instrument;
+pub mod logging;
+pub mod long;
+pub mod metals;
+#[cfg(feature = "notify")]
+pub mod notify;
+pub mod ocec;
+pub mod pivot;
+#[cfg(feature = "plots")]
+mod plot;
+#[cfg(feature = "post")]
+pub mod post;
+mod progress;
 pub mod proton;
+pub mod report;
+mod resume;
+pub mod sample;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod species;
+pub mod split;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod template;
+pub mod units;
+#[cfg(feature = "upload")]
+pub mod upload;
+pub mod validate;
+#[cfg(feature = "watch")]
+pub mod watch;
+mod workbook;
+
+pub use error::{DtToolsError, Result};
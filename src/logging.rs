@@ -0,0 +1,199 @@
+//! 日志输出：处理信息写到终端（警告和错误写到标准错误），`--log-file` 另外写一份 JSON Lines
+//! 日志，供调度程序解析。
+//!
+//! 终端默认显示一般信息；`--verbose` 还显示每条规则匹配与单元格修改，`--quiet` 只显示错误。
+//! 日志文件不受 `--quiet` 影响。
+
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+use tracing::level_filters::LevelFilter;
+use tracing::{Level, Metadata, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::time::ChronoLocal;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry};
+
+use crate::cli::GlobalArgs;
+use crate::error::{Context, DtToolsError, Result};
+
+/// 终端与日志文件的日志级别。
+fn levels(global: &GlobalArgs) -> (LevelFilter, LevelFilter) {
+    let file = if global.verbose {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    };
+    let console = if global.quiet {
+        LevelFilter::ERROR
+    } else {
+        file
+    };
+    (console, file)
+}
+
+/// 终端输出的目标：警告和错误写到标准错误，其余写到标准输出；
+/// 标准输出留给运行摘要（`--json-summary`）时全部写到标准错误。
+struct Console {
+    stderr_only: bool,
+}
+
+/// 一条终端日志。先写入缓冲，结束时暂停进度条再整体输出，避免与进度条交错。
+struct ConsoleLine {
+    buffer: Vec<u8>,
+    stderr: bool,
+}
+
+impl Write for ConsoleLine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ConsoleLine {
+    fn drop(&mut self) {
+        crate::progress::suspend(|| {
+            let _ = if self.stderr {
+                io::stderr().write_all(&self.buffer)
+            } else {
+                io::stdout().write_all(&self.buffer)
+            };
+        });
+    }
+}
+
+impl<'a> MakeWriter<'a> for Console {
+    type Writer = ConsoleLine;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ConsoleLine {
+            buffer: Vec::new(),
+            stderr: self.stderr_only,
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        ConsoleLine {
+            buffer: Vec::new(),
+            stderr: self.stderr_only || *meta.level() <= Level::WARN,
+        }
+    }
+}
+
+/// 终端输出：只有消息本身，与原先的打印格式一致。
+fn console_layer<S>(stderr_only: bool) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .without_time()
+        .with_target(false)
+        .with_level(false)
+        .with_ansi(io::stdout().is_terminal())
+        .with_writer(Console { stderr_only })
+}
+
+/// JSON Lines 日志：每行一个事件，含本地时间、级别、消息和字段。
+fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(false)
+        .with_timer(ChronoLocal::rfc_3339())
+        .with_ansi(false)
+        .with_writer(writer)
+}
+
+/// 按全局参数设置日志输出（以及是否显示进度条）。已经设置过时（如库调用方自行设置了日志）保留原设置。
+pub(crate) fn init(global: &GlobalArgs) -> Result<()> {
+    crate::i18n::init(global.lang);
+    crate::progress::init(global);
+    let (console, file) = levels(global);
+    let log_file = match &global.log_file {
+        Some(path) => {
+            crate::cli::ensure_parent_dir(path)?;
+            let out = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("无法写入日志文件: {}", path.display()))?;
+            Some(json_layer(Mutex::new(out)).with_filter(file))
+        }
+        None => None,
+    };
+    let _ = Registry::default()
+        .with(console_layer(global.json_summary).with_filter(console))
+        .with(log_file)
+        .try_init();
+    Ok(())
+}
+
+/// 报告导致程序退出的错误：设置了日志时写入日志（同时显示在终端），否则直接打印。
+pub fn report_error(message: &str, err: &DtToolsError) {
+    if tracing::dispatcher::has_been_set() {
+        tracing::error!("{message}: {err:#}");
+    } else {
+        eprintln!("{message}: {err:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn read_lines(path: &Path) -> Result<Vec<serde_json::Value>> {
+        std::fs::read_to_string(path)?
+            .lines()
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    #[test]
+    fn quiet_only_affects_console() {
+        let global = GlobalArgs {
+            quiet: true,
+            ..Default::default()
+        };
+        assert_eq!(levels(&global), (LevelFilter::ERROR, LevelFilter::INFO));
+        let global = GlobalArgs {
+            verbose: true,
+            ..Default::default()
+        };
+        assert_eq!(levels(&global), (LevelFilter::DEBUG, LevelFilter::DEBUG));
+    }
+
+    #[test]
+    fn writes_json_lines() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("run.jsonl");
+        let out = OpenOptions::new().create(true).append(true).open(&path)?;
+        let subscriber = Registry::default().with(json_layer(Mutex::new(out)));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(file = "a.xlsx", "文件已处理并保存为: processed_a.xlsx");
+            tracing::error!("处理失败: b.xlsx");
+        });
+
+        let lines = read_lines(&path)?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["file"], "a.xlsx");
+        assert_eq!(lines[0]["message"], "文件已处理并保存为: processed_a.xlsx");
+        assert_eq!(lines[1]["level"], "ERROR");
+        assert!(lines[1]["timestamp"].is_string());
+        Ok(())
+    }
+}
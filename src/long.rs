@@ -0,0 +1,232 @@
+//! 长表：每个数值一行（站点、时间、项目、数值、标记），供 `--format parquet`（需启用 `parquet`
+//! 功能）与 `--to-sqlite` 使用，便于 pandas、DuckDB 等直接读取与跨文件查询。
+//!
+//! `value` 为数值，`flag` 为 `值#标记码` 中的标记码（如 C、DL），没有标记时为空。缺失的值不写入。
+
+#[cfg(feature = "parquet")]
+use std::fs::File;
+use std::path::Path;
+#[cfg(feature = "parquet")]
+use std::sync::Arc;
+
+#[cfg(feature = "parquet")]
+use parquet::basic::Compression;
+#[cfg(feature = "parquet")]
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType};
+#[cfg(feature = "parquet")]
+use parquet::file::properties::WriterProperties;
+#[cfg(feature = "parquet")]
+use parquet::file::writer::SerializedFileWriter;
+#[cfg(feature = "parquet")]
+use parquet::schema::parser::parse_message_type;
+
+#[cfg(feature = "parquet")]
+use crate::error::DtToolsError;
+use crate::error::Result;
+use crate::flags;
+use crate::workbook::OutputFormat;
+
+/// Parquet 文件的结构，列与 `--to-sqlite` 的 `measurements` 表相同。
+#[cfg(feature = "parquet")]
+const PARQUET_SCHEMA: &str = "message measurements {
+    required binary station (STRING);
+    required binary timestamp (STRING);
+    required binary analyte (STRING);
+    required double value;
+    optional binary flag (STRING);
+}";
+
+/// 长表中的一行。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement<'a> {
+    pub station: &'a str,
+    pub timestamp: &'a str,
+    pub analyte: &'a str,
+    pub value: f64,
+    pub flag: Option<&'a str>,
+}
+
+/// 由导出 CSV 时使用的数据表（首行为表头，首列为时间）取出各个数值。空单元格、`missing`
+/// 中的缺失值代码以及无法解析为数值的单元格跳过。
+pub fn measurements<'a>(
+    station: &'a str,
+    table: &'a [Vec<String>],
+    missing: &[&str],
+) -> Vec<Measurement<'a>> {
+    let Some((header, rows)) = table.split_first() else {
+        return Vec::new();
+    };
+    let mut measurements = Vec::new();
+    for row in rows {
+        let Some((timestamp, values)) = row.split_first() else {
+            continue;
+        };
+        for (analyte, text) in header.iter().skip(1).zip(values) {
+            let text = text.trim();
+            if text.is_empty() || missing.contains(&text) {
+                continue;
+            }
+            let (value, flag) = match flags::split(text) {
+                Some((value, code)) => (value, Some(code)),
+                None => (text, None),
+            };
+            let Ok(value) = value.parse() else {
+                continue;
+            };
+            measurements.push(Measurement {
+                station,
+                timestamp,
+                analyte,
+                value,
+                flag,
+            });
+        }
+    }
+    measurements
+}
+
+/// `format` 为 `--format parquet` 时把数据表 `table`（见 [`measurements`]）写成 Parquet 长表
+/// `path` 并返回 `true`；其他格式不写出，返回 `false`，由调用方写出工作簿。
+#[cfg_attr(not(feature = "parquet"), allow(unused_variables))]
+pub(crate) fn write_if_parquet(
+    format: OutputFormat,
+    path: &Path,
+    station: &str,
+    table: &[Vec<String>],
+    missing: &[&str],
+) -> Result<bool> {
+    #[cfg(feature = "parquet")]
+    if format == OutputFormat::Parquet {
+        write_parquet(path, &measurements(station, table, missing))?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// 把长表写为 Parquet 文件（Snappy 压缩，一个行组）。
+#[cfg(feature = "parquet")]
+pub fn write_parquet<'a>(path: &Path, measurements: &[Measurement<'a>]) -> Result<()> {
+    let write_error = |err: parquet::errors::ParquetError| DtToolsError::write(path, err);
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA).map_err(write_error)?);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let file = File::create(path).map_err(|err| DtToolsError::write(path, err))?;
+    let mut writer =
+        SerializedFileWriter::new(file, schema, Arc::new(properties)).map_err(write_error)?;
+
+    let text = |field: fn(&Measurement<'a>) -> &'a str| -> Vec<ByteArray> {
+        measurements
+            .iter()
+            .map(|m| ByteArray::from(field(m)))
+            .collect()
+    };
+    let values: Vec<f64> = measurements.iter().map(|m| m.value).collect();
+    let flags: Vec<ByteArray> = measurements
+        .iter()
+        .filter_map(|m| m.flag.map(ByteArray::from))
+        .collect();
+    // flag 列的定义级别：1 为有值，0 为空
+    let flag_levels: Vec<i16> = measurements
+        .iter()
+        .map(|m| i16::from(m.flag.is_some()))
+        .collect();
+
+    let mut group = writer.next_row_group().map_err(write_error)?;
+    for strings in [
+        text(|m| m.station),
+        text(|m| m.timestamp),
+        text(|m| m.analyte),
+    ] {
+        let mut column = group
+            .next_column()
+            .map_err(write_error)?
+            .expect("schema column");
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&strings, None, None)
+            .map_err(write_error)?;
+        column.close().map_err(write_error)?;
+    }
+    let mut column = group
+        .next_column()
+        .map_err(write_error)?
+        .expect("value column");
+    column
+        .typed::<DoubleType>()
+        .write_batch(&values, None, None)
+        .map_err(write_error)?;
+    column.close().map_err(write_error)?;
+    let mut column = group
+        .next_column()
+        .map_err(write_error)?
+        .expect("flag column");
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&flags, Some(&flag_levels), None)
+        .map_err(write_error)?;
+    column.close().map_err(write_error)?;
+    group.close().map_err(write_error)?;
+    writer.close().map_err(write_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Vec<Vec<String>> {
+        [
+            ["时间", "NO₃⁻", "SO₄²⁻", "Cl⁻"],
+            ["2026-01-05 01:00:00", "1.6#C", "4.2", "-999"],
+            ["2026-01-05 02:00:00", "", "0.01#DL", "n/a"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect()
+    }
+
+    #[test]
+    fn reads_measurements_from_table() -> Result<()> {
+        let table = table();
+        let rows = measurements("4401000010003", &table, &["-999"]);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[0],
+            Measurement {
+                station: "4401000010003",
+                timestamp: "2026-01-05 01:00:00",
+                analyte: "NO₃⁻",
+                value: 1.6,
+                flag: Some("C"),
+            }
+        );
+        assert_eq!((rows[1].value, rows[1].flag), (4.2, None));
+        Ok(())
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn writes_measurements_as_parquet() -> Result<()> {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let table = table();
+        let rows = measurements("4401000010003", &table, &["-999"]);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("processed_a.parquet");
+        write_parquet(&path, &rows)?;
+        let reader =
+            SerializedFileReader::new(File::open(&path)?).map_err(DtToolsError::external)?;
+        let read: Vec<String> = reader
+            .get_row_iter(None)
+            .map_err(DtToolsError::external)?
+            .map(|row| row.map(|row| row.to_string()))
+            .collect::<Result<_, _>>()
+            .map_err(DtToolsError::external)?;
+        assert_eq!(read.len(), 3);
+        assert!(read[2].contains("analyte: \"SO₄²⁻\""), "{}", read[2]);
+        assert!(read[2].contains("flag: \"DL\""), "{}", read[2]);
+        assert!(read[1].contains("flag: null"), "{}", read[1]);
+        Ok(())
+    }
+}
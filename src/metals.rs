@@ -0,0 +1,159 @@
+//! `dttools metals`：把在线重金属分析仪（如 Xact）的导出文件转换为国家平台的上传模板。
+//!
+//! 按表头找到时间列和各元素列（`Pb`、`Pb(ng/m³)`、`Pb ng/m3` 等写法），源数据的浓度单位
+//! 默认为 ng/m³，模板单位不同时自动换算。读取、配置与输出格式见 [`crate::instrument`]，
+//! 站点配置从 `metals_config.toml`（查找顺序见 [`crate::config`]）读取：
+//!
+//! ```toml
+//! station_code = "4401000010003"
+//! unit = "μg/m³"                 # 模板的浓度单位（默认 ng/m³）
+//! columns = ["Pb", "As", "Cd"]   # 只输出这些元素
+//!
+//! [codes]                        # 元素的因子编码，内置为空
+//! "Pb" = "a20xxx"
+//! ```
+
+use chrono::TimeDelta;
+
+use crate::cli::GlobalArgs;
+use crate::error::Result;
+use crate::instrument::{self, ColumnDef, Instrument, InstrumentArgs};
+use crate::units::Unit;
+
+/// 源表中缺少时输出留空的元素列。
+const fn element(name: &'static str) -> ColumnDef {
+    ColumnDef {
+        name,
+        code: "",
+        headers: &[],
+        required: false,
+        derive: None,
+    }
+}
+
+/// 重金属分析仪的内置定义。因子编码因平台而异，须在配置文件的 `[codes]` 中给出。
+pub const INSTRUMENT: Instrument = Instrument {
+    name: "metals",
+    title: "重金属",
+    config_file: "metals_config.toml",
+    columns: &[
+        element("K"),
+        element("Ca"),
+        element("Ti"),
+        element("V"),
+        element("Cr"),
+        element("Mn"),
+        element("Fe"),
+        element("Co"),
+        element("Ni"),
+        element("Cu"),
+        element("Zn"),
+        element("As"),
+        element("Se"),
+        element("Ag"),
+        element("Cd"),
+        element("Sn"),
+        element("Sb"),
+        element("Ba"),
+        element("Hg"),
+        element("Tl"),
+        element("Pb"),
+    ],
+    source_unit: Unit::NgM3,
+    unit: Unit::NgM3,
+    interval: TimeDelta::hours(1),
+    resample: None,
+};
+
+pub fn execute(args: &InstrumentArgs, global: &GlobalArgs) -> Result<()> {
+    instrument::execute(&INSTRUMENT, args, global)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::instrument::{InstrumentOptions, transform};
+    use crate::proton::{self, CsvEncoding};
+    use crate::workbook;
+    use tempfile::tempdir;
+
+    #[test]
+    fn converts_xact_export_to_micrograms() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("xact.csv");
+        fs::write(
+            &input,
+            "Date Time,Pb (ng/m3),As ng/m3,Cd,Flow\n\
+             2026-01-05 01:00,25.4,3.1,0.8,16.7\n\
+             2026-01-05 02:00,<MDL,2.9,,16.7\n",
+        )?;
+
+        let source = proton::read_source(&input, b',', CsvEncoding::Auto)?;
+        let options = InstrumentOptions::from_toml_str(
+            &INSTRUMENT,
+            r#"
+            unit = "μg/m³"
+            columns = ["Pb", "As", "Cd", "Hg"]
+
+            [codes]
+            "Pb" = "a99051"
+            "#,
+        )?;
+        let out = transform(&source, &options)?;
+
+        assert_eq!(out.report.rows, 2);
+        assert_eq!(out.report.missing, ["Hg"]);
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("A3"), "重金属");
+        assert_eq!(sheet.get_value("B3"), "Pb");
+        assert_eq!(sheet.get_value("B4"), "a99051");
+        assert_eq!(sheet.get_value("C4"), "");
+        assert_eq!(sheet.get_value("B5"), "μg/m³");
+        assert_eq!(workbook::cell_text(sheet, 1, 6), "2026-01-05 01:00:00");
+        assert_eq!(sheet.get_value("B6"), "0.0254");
+        assert_eq!(sheet.get_value("C6"), "0.0031");
+        assert_eq!(sheet.get_value("B7"), "");
+        assert!(
+            out.report
+                .changes
+                .iter()
+                .any(|change| change.address == "B3" && change.rules == ["below_limit"])
+        );
+        assert_eq!(out.report.below_limit, 1);
+
+        let options = InstrumentOptions::from_toml_str(
+            &INSTRUMENT,
+            r#"
+            unit = "μg/m³"
+            columns = ["Pb", "As"]
+            below_limit = "half"
+
+            [mdl]
+            "Pb" = 2
+            "As" = 5
+            "#,
+        )?;
+        let out = transform(&source, &options)?;
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("B7"), "0.001");
+        // 低于检出限的实测值同样替换为检出限的一半
+        assert_eq!(sheet.get_value("C6"), "0.0025");
+        assert_eq!(sheet.get_value("B6"), "0.0254");
+        assert!(InstrumentOptions::from_toml_str(&INSTRUMENT, r#"below_limit = "flag""#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_nanograms_by_default() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("xact.csv");
+        fs::write(&input, "时间,Pb(ng/m³)\n2026-01-05 01:00:00,25.4\n")?;
+        let source = proton::read_source(&input, b',', CsvEncoding::Auto)?;
+        let out = transform(&source, &InstrumentOptions::new(&INSTRUMENT))?;
+        assert_eq!(out.table[0].len(), INSTRUMENT.columns.len() + 1);
+        assert_eq!(out.table[1][21], "25.4");
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+//! `dttools ocec`：把 Sunset OC/EC 分析仪的导出文件转换为国家平台的上传模板。
+//!
+//! 按表头找到时间、OC、EC、TC 列；源表没有 TC 列时按 OC + EC 计算。读取、配置与输出格式
+//! 见 [`crate::instrument`]，站点配置从 `ocec_config.toml`（查找顺序见 [`crate::config`]）读取：
+//!
+//! ```toml
+//! station_code = "4401000010003"
+//! decimals = 2
+//!
+//! [codes]                        # 键为 OC、EC、TC，内置为空
+//! "OC" = "a06xxx"
+//!
+//! [aliases]
+//! "EC" = ["OptEC"]
+//! ```
+
+use chrono::TimeDelta;
+
+use crate::cli::GlobalArgs;
+use crate::error::Result;
+use crate::instrument::{self, ColumnDef, Derive, Instrument, InstrumentArgs};
+use crate::units::Unit;
+
+/// OC/EC 分析仪的内置定义。Sunset 分析仪按固件版本把表头导出为 `OC ugC/m3`、
+/// `Thermal OC` 等写法。因子编码因平台而异，须在配置文件的 `[codes]` 中给出。
+pub const INSTRUMENT: Instrument = Instrument {
+    name: "ocec",
+    title: "OCEC",
+    config_file: "ocec_config.toml",
+    columns: &[
+        ColumnDef {
+            name: "OC",
+            code: "",
+            headers: &["OC(μgC/m³)", "OC ugC/m3", "Thermal OC"],
+            required: true,
+            derive: None,
+        },
+        ColumnDef {
+            name: "EC",
+            code: "",
+            headers: &["EC(μgC/m³)", "EC ugC/m3", "Thermal EC"],
+            required: true,
+            derive: None,
+        },
+        ColumnDef {
+            name: "TC",
+            code: "",
+            headers: &["TC(μgC/m³)", "TC ugC/m3", "Thermal TC"],
+            required: false,
+            derive: Some(Derive::Sum(&["OC", "EC"])),
+        },
+    ],
+    source_unit: Unit::UgM3,
+    unit: Unit::UgM3,
+    interval: TimeDelta::hours(1),
+    resample: None,
+};
+
+pub fn execute(args: &InstrumentArgs, global: &GlobalArgs) -> Result<()> {
+    instrument::execute(&INSTRUMENT, args, global)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+    use crate::error::DtToolsError;
+    use crate::instrument::{InstrumentOptions, transform};
+    use crate::proton::{self, CsvEncoding};
+    use crate::workbook;
+    use tempfile::tempdir;
+
+    /// 写一个 Sunset 导出样例（CSV，日期与时间分列，没有 TC 列）。
+    fn write_sunset_csv(path: &Path) -> Result<()> {
+        fs::write(
+            path,
+            "Sample ID,Start Date,Start Time,OC ugC/m3,EC ugC/m3\n\
+             1,01/05/2026,01:00:00,5.2,1.3\n\
+             2,01/05/2026,02:00:00,n/a,1.1\n",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn converts_sunset_export() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("ocec.csv");
+        write_sunset_csv(&input)?;
+
+        let source = proton::read_source(&input, b',', CsvEncoding::Auto)?;
+        let options = InstrumentOptions::from_toml_str(
+            &INSTRUMENT,
+            "station_code = \"4403000000001\"\n[codes]\n\"OC\" = \"a06101\"\n",
+        )?;
+        let out = transform(&source, &options)?;
+
+        assert_eq!(out.report.computed, ["TC"]);
+        assert_eq!(out.report.rows, 2);
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("A4"), "4403000000001");
+        assert_eq!(sheet.get_value("B4"), "a06101");
+        assert_eq!(sheet.get_value("C4"), "");
+        assert_eq!(sheet.get_value("D3"), "TC");
+        assert_eq!(workbook::cell_text(sheet, 1, 6), "2026-01-05 01:00:00");
+        assert_eq!(sheet.get_value("B6"), "5.2");
+        assert_eq!(sheet.get_value("D6"), "6.5");
+        // OC 不是数值时清空，TC 无法计算
+        assert_eq!(sheet.get_value("B7"), "");
+        assert_eq!(sheet.get_value("D7"), "");
+        let rules: Vec<_> = out
+            .report
+            .changes
+            .iter()
+            .map(|change| change.rules[0].as_str())
+            .collect();
+        assert_eq!(rules, ["time_format", "time_format", "non_numeric"]);
+        assert_eq!(out.table[1], ["2026-01-05 01:00:00", "5.2", "1.3", "6.5"]);
+        Ok(())
+    }
+
+    #[test]
+    fn computes_tc_after_below_limit_substitution() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("ocec.csv");
+        fs::write(
+            &input,
+            "时间,OC,EC\n2026-01-05 01:00:00,5.2,0.02\n2026-01-05 02:00:00,4.0,<0.1\n",
+        )?;
+        let source = proton::read_source(&input, b',', CsvEncoding::Auto)?;
+        let options =
+            InstrumentOptions::from_toml_str(&INSTRUMENT, "[detection_limit]\n\"EC\" = 0.05\n")?;
+        let out = transform(&source, &options)?;
+        // EC 低于检出限时按半检出限计，TC 用替换后的值
+        assert_eq!(
+            out.table[1],
+            ["2026-01-05 01:00:00", "5.2", "0.025", "5.225"]
+        );
+        assert_eq!(out.table[2], ["2026-01-05 02:00:00", "4", "0.05", "4.05"]);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_codes_and_aliases() -> Result<()> {
+        let options = InstrumentOptions::from_toml_str(
+            &INSTRUMENT,
+            r#"
+            decimals = 2
+
+            [codes]
+            "tc" = "a34099"
+
+            [aliases]
+            "EC" = ["OptEC"]
+            "时间" = ["Analysis Time"]
+            "#,
+        )?;
+        let codes: Vec<_> = options.columns.iter().map(|c| c.code.as_str()).collect();
+        assert_eq!(codes, ["", "", "a34099"]);
+        assert!(options.columns[1].headers.contains(&"OptEC".to_string()));
+        assert_eq!(options.time_aliases, ["Analysis Time"]);
+        assert_eq!(options.decimals, Some(2));
+        assert!(
+            InstrumentOptions::from_toml_str(&INSTRUMENT, "[codes]\n\"BC\" = \"x\"\n").is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_missing_columns() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("ocec.csv");
+        fs::write(&input, "时间,OC\n2026-01-05 01:00:00,1\n")?;
+        let source = proton::read_source(&input, b',', CsvEncoding::Auto)?;
+        let err = transform(&source, &InstrumentOptions::new(&INSTRUMENT)).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            DtToolsError::MissingColumn { name } if name == "EC"
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,457 @@
+//! `dttools pivot`：在长表（每行一个时间、项目与数值）与宽表（首列为时间，每个项目一列，
+//! 与上传模板的数据区、`--also-csv` 导出的数据表相同）之间转换。不同厂商导出的方向不一，
+//! 宽表可以直接交给 `dttools proton` 等转换。
+//!
+//! 长表的列按表头识别（见 [`LongHeaders`]），可带一列标记码，与数值合并为宽表中的 `值#标记`；
+//! 转为长表时再拆开。时间统一为 `YYYY-MM-DD HH:MM:SS` 并按时间排序，时间为空或无法识别的行
+//! （如上传模板的编码行、单位行）跳过。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use clap::{Args, ValueEnum};
+use tracing::{info, warn};
+
+use crate::cli::{self, GlobalArgs};
+use crate::error::{Result, invalid};
+use crate::proton::{self, CsvEncoding, ProtonOptions};
+use crate::species::normalize;
+use crate::workbook::{self, TIME_TEXT_FORMAT};
+use crate::{export, flags, split};
+
+/// 查找表头时检查的最大行数。
+const HEADER_SCAN_ROWS: usize = 20;
+
+/// 时间列的候选表头（统一写法后比较，见 [`normalize`]）。
+const TIME_HEADERS: &[&str] = &[
+    "时间",
+    "日期时间",
+    "监测时间",
+    "timestamp",
+    "time",
+    "datetime",
+];
+/// 项目列的候选表头。
+const ANALYTE_HEADERS: &[&str] = &[
+    "项目",
+    "监测项目",
+    "因子",
+    "analyte",
+    "species",
+    "parameter",
+];
+/// 数值列的候选表头。
+const VALUE_HEADERS: &[&str] = &["数值", "浓度", "value", "concentration"];
+/// 标记列的候选表头。
+const FLAG_HEADERS: &[&str] = &["标记", "flag"];
+
+/// 输出长表的表头，与 `--format parquet` 的列相同。
+pub const LONG_HEADER: [&str; 4] = ["timestamp", "analyte", "value", "flag"];
+
+/// 转换方向。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PivotDirection {
+    /// 长表转为宽表（上传模板的数据区）
+    Wide,
+    /// 宽表转为长表
+    Long,
+}
+
+/// `dttools pivot` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct PivotArgs {
+    /// 输入文件（xlsx/xls/csv/tsv）
+    #[arg(value_name = "INPUT")]
+    pub input: PathBuf,
+
+    /// 转换为宽表或长表
+    #[arg(long, value_enum)]
+    pub to: PivotDirection,
+
+    /// 要读取的工作表（默认为第一个工作表）
+    #[arg(long, value_name = "NAME")]
+    pub sheet: Option<String>,
+
+    /// CSV 输入的分隔符，如 `,`、`;` 或 `tab`
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = cli::parse_delimiter)]
+    pub delimiter: u8,
+
+    /// CSV 输入的编码
+    #[arg(long, value_enum, default_value_t = CsvEncoding::Auto)]
+    pub encoding: CsvEncoding,
+
+    #[command(flatten)]
+    pub headers: LongHeaders,
+}
+
+/// 长表中时间、项目、数值与标记列的表头；未指定时按内置的候选表头（如 `时间`/`timestamp`、
+/// `项目`/`analyte`、`数值`/`value`、`标记`/`flag`）查找。
+#[derive(Debug, Clone, Default, Args)]
+pub struct LongHeaders {
+    /// 长表中时间列的表头
+    #[arg(long, value_name = "HEADER")]
+    pub time_header: Option<String>,
+
+    /// 长表中项目列的表头
+    #[arg(long, value_name = "HEADER")]
+    pub analyte_header: Option<String>,
+
+    /// 长表中数值列的表头
+    #[arg(long, value_name = "HEADER")]
+    pub value_header: Option<String>,
+
+    /// 长表中标记列的表头（可选）
+    #[arg(long, value_name = "HEADER")]
+    pub flag_header: Option<String>,
+}
+
+/// 一次转换的结果。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pivoted {
+    /// 转换后的表，首行为表头
+    pub table: Vec<Vec<String>>,
+    /// 时间为空或无法识别而跳过的行数
+    pub skipped: usize,
+    /// 长表中同一时间、同一项目重复出现而丢弃的值个数（保留第一个）
+    pub duplicates: usize,
+}
+
+/// 第 `col` 列去掉首尾空白的文本，该行没有这一列时为空。
+fn cell(cells: &[String], col: usize) -> &str {
+    cells.get(col).map_or("", |s| s.trim())
+}
+
+/// 表头行中第一个表头为 `header`（指定时）或属于 `candidates` 的列。
+fn find_column(cells: &[String], header: Option<&str>, candidates: &[&str]) -> Option<usize> {
+    match header {
+        Some(header) => {
+            let header = normalize(header);
+            cells.iter().position(|cell| normalize(cell) == header)
+        }
+        None => {
+            let candidates: Vec<String> = candidates.iter().map(|c| normalize(c)).collect();
+            cells
+                .iter()
+                .position(|cell| candidates.contains(&normalize(cell)))
+        }
+    }
+}
+
+/// 长表转为宽表：首列为 `时间`，其后每个项目一列（按项目首次出现的顺序）。
+pub fn to_wide(rows: &[Vec<String>], headers: &LongHeaders) -> Result<Pivoted> {
+    let (header_row, [time, analyte, value], flag) = rows
+        .iter()
+        .take(HEADER_SCAN_ROWS)
+        .enumerate()
+        .find_map(|(row, cells)| {
+            let time = find_column(cells, headers.time_header.as_deref(), TIME_HEADERS)?;
+            let analyte = find_column(cells, headers.analyte_header.as_deref(), ANALYTE_HEADERS)?;
+            let value = find_column(cells, headers.value_header.as_deref(), VALUE_HEADERS)?;
+            let flag = find_column(cells, headers.flag_header.as_deref(), FLAG_HEADERS);
+            Some((row, [time, analyte, value], flag))
+        })
+        .ok_or_else(|| {
+            invalid!(
+                "找不到长表的时间、项目与数值列，请用 --time-header、--analyte-header、--value-header 指定"
+            )
+        })?;
+
+    let mut analytes: Vec<String> = Vec::new();
+    let mut values: BTreeMap<NaiveDateTime, Vec<String>> = BTreeMap::new();
+    let mut pivoted = Pivoted::default();
+    for cells in &rows[header_row + 1..] {
+        let Some(timestamp) = split::parse_time(cell(cells, time)) else {
+            if cells.iter().any(|c| !c.trim().is_empty()) {
+                pivoted.skipped += 1;
+            }
+            continue;
+        };
+        let name = cell(cells, analyte);
+        let number = cell(cells, value);
+        if name.is_empty() || number.is_empty() {
+            continue;
+        }
+        let col = match analytes.iter().position(|a| a == name) {
+            Some(col) => col,
+            None => {
+                analytes.push(name.to_string());
+                analytes.len() - 1
+            }
+        };
+        let text = match flag.map(|flag| cell(cells, flag)) {
+            Some(code) if !code.is_empty() => flags::join(number, code),
+            _ => number.to_string(),
+        };
+        let row = values.entry(timestamp).or_default();
+        if row.len() <= col {
+            row.resize(col + 1, String::new());
+        }
+        if row[col].is_empty() {
+            row[col] = text;
+        } else {
+            pivoted.duplicates += 1;
+        }
+    }
+
+    pivoted.table.push(
+        std::iter::once("时间".to_string())
+            .chain(analytes.iter().cloned())
+            .collect(),
+    );
+    for (timestamp, mut row) in values {
+        row.resize(analytes.len(), String::new());
+        pivoted.table.push(
+            std::iter::once(timestamp.format(TIME_TEXT_FORMAT).to_string())
+                .chain(row)
+                .collect(),
+        );
+    }
+    Ok(pivoted)
+}
+
+/// 宽表转为长表：每个非空值一行，列为 [`LONG_HEADER`]；`值#标记` 拆为数值与标记。表头行为
+/// 前几行中第一个含有时间列表头的行，找不到时为首行、时间列为首列。
+pub fn to_long(rows: &[Vec<String>], headers: &LongHeaders) -> Pivoted {
+    let (header_row, time) = rows
+        .iter()
+        .take(HEADER_SCAN_ROWS)
+        .enumerate()
+        .find_map(|(row, cells)| {
+            find_column(cells, headers.time_header.as_deref(), TIME_HEADERS).map(|col| (row, col))
+        })
+        .unwrap_or((0, 0));
+    let Some(header) = rows.get(header_row) else {
+        return Pivoted::default();
+    };
+
+    let mut pivoted = Pivoted::default();
+    let mut values: BTreeMap<NaiveDateTime, Vec<Vec<String>>> = BTreeMap::new();
+    for cells in &rows[header_row + 1..] {
+        let Some(timestamp) = cells.get(time).and_then(|text| split::parse_time(text)) else {
+            if cells.iter().any(|c| !c.trim().is_empty()) {
+                pivoted.skipped += 1;
+            }
+            continue;
+        };
+        let timestamp_text = timestamp.format(TIME_TEXT_FORMAT).to_string();
+        for (col, text) in cells.iter().enumerate() {
+            let analyte = cell(header, col);
+            let text = text.trim();
+            if col == time || analyte.is_empty() || text.is_empty() {
+                continue;
+            }
+            let (value, flag) = flags::split(text).unwrap_or((text, ""));
+            values.entry(timestamp).or_default().push(vec![
+                timestamp_text.clone(),
+                analyte.to_string(),
+                value.to_string(),
+                flag.to_string(),
+            ]);
+        }
+    }
+    pivoted.table.push(LONG_HEADER.map(str::to_string).to_vec());
+    pivoted.table.extend(values.into_values().flatten());
+    pivoted
+}
+
+/// 读取输入文件的第一个（或 `sheet` 指定的）工作表，单元格转为文本；日期时间单元格按
+/// `YYYY-MM-DD HH:MM:SS` 显示。
+fn read_rows(args: &PivotArgs) -> Result<Vec<Vec<String>>> {
+    let source = match &args.sheet {
+        Some(name) => {
+            let options = ProtonOptions {
+                sheets: vec![name.clone()],
+                ..Default::default()
+            };
+            proton::read_source_with(&args.input, args.delimiter, args.encoding, &options)?
+        }
+        None => proton::read_source(&args.input, args.delimiter, args.encoding)?,
+    };
+    Ok(source
+        .range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| proton::time_cell_text(Some(cell)))
+                .collect()
+        })
+        .collect())
+}
+
+/// 把表写为工作簿：时间列写为日期时间单元格，数值写为数字单元格，其余为文本。
+fn table_workbook(table: &[Vec<String>], time_col: usize) -> umya_spreadsheet::Spreadsheet {
+    let mut book = umya_spreadsheet::new_file();
+    let sheet = book.get_active_sheet_mut();
+    for (row, cells) in table.iter().enumerate() {
+        for (col, text) in cells.iter().enumerate() {
+            if text.is_empty() {
+                continue;
+            }
+            let cell = sheet.get_cell_mut((col as u32 + 1, row as u32 + 1));
+            match (row, col) {
+                (0, _) => {
+                    cell.set_value(text);
+                }
+                (_, col) if col == time_col => workbook::set_time_cell(cell, text),
+                _ => workbook::set_number_cell(cell, text, None),
+            }
+        }
+    }
+    book
+}
+
+/// 输出路径为 `.csv`/`.tsv` 时的分隔符。
+fn csv_delimiter(path: &Path) -> Option<u8> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "csv" => Some(b','),
+        "tsv" => Some(b'\t'),
+        _ => None,
+    }
+}
+
+pub fn execute(args: &PivotArgs, global: &GlobalArgs) -> Result<()> {
+    let rows = read_rows(args)?;
+    let pivoted = match args.to {
+        PivotDirection::Wide => to_wide(&rows, &args.headers)?,
+        PivotDirection::Long => to_long(&rows, &args.headers),
+    };
+    if pivoted.skipped > 0 {
+        warn!("{} 行的时间为空或无法识别，已跳过", pivoted.skipped);
+    }
+    if pivoted.duplicates > 0 {
+        warn!("{} 个值的时间与项目重复，已保留第一个", pivoted.duplicates);
+    }
+
+    let output = global.workbook_output_path(&args.input);
+    let rows = pivoted.table.len().saturating_sub(1);
+    if global.dry_run {
+        info!("[试运行] 将写出 {rows} 行到: {}", output.display());
+        return Ok(());
+    }
+    global.prepare_output(&output)?;
+    match csv_delimiter(&output) {
+        Some(delimiter) => export::write_csv(&output, &pivoted.table, delimiter)?,
+        None => workbook::write(&table_workbook(&pivoted.table, 0), &output, global.format)?,
+    }
+    info!("已写出 {rows} 行到: {}", output.display());
+    if let Some(delimiter) = global.csv_export()
+        && csv_delimiter(&output).is_none()
+    {
+        let path = export::csv_path(&output, delimiter);
+        export::write_csv(&path, &pivoted.table, delimiter)?;
+        info!("CSV 已写入: {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn pivots_long_to_wide() -> Result<()> {
+        let rows = table(&[
+            &["站点导出"],
+            &["Time", "Species", "Value", "Flag"],
+            &["2026/01/05 01:00", "NO₃⁻", "1.5", ""],
+            &["2026/01/05 00:00", "NO₃⁻", "1.2", "C"],
+            &["2026/01/05 01:00", "SO₄²⁻", "2.5", ""],
+            &["2026/01/05 01:00", "SO₄²⁻", "9", ""],
+            &["备注", "", "", ""],
+        ]);
+        let pivoted = to_wide(&rows, &LongHeaders::default())?;
+        assert_eq!(
+            pivoted.table,
+            table(&[
+                &["时间", "NO₃⁻", "SO₄²⁻"],
+                &["2026-01-05 00:00:00", "1.2#C", ""],
+                &["2026-01-05 01:00:00", "1.5", "2.5"],
+            ])
+        );
+        assert_eq!((pivoted.skipped, pivoted.duplicates), (1, 1));
+
+        let headers = LongHeaders {
+            value_header: Some("浓度值".to_string()),
+            ..Default::default()
+        };
+        assert!(to_wide(&rows, &headers).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pivots_wide_to_long_and_back() -> Result<()> {
+        // 上传模板：第 3 行为表头，第 4、5 行为编码与单位
+        let rows = table(&[
+            &["橙色和红色部分请勿改动！！！"],
+            &[],
+            &["时间", "NO₃⁻", "SO₄²⁻"],
+            &["4401000010003", "a06006", "a06005"],
+            &["时间", "μg/m³", "μg/m³"],
+            &["2026-01-05 00:00:00", "1.2#C", ""],
+            &["2026-01-05 01:00:00", "1.5", "2.5"],
+        ]);
+        let long = to_long(&rows, &LongHeaders::default());
+        assert_eq!(
+            long.table,
+            table(&[
+                &LONG_HEADER,
+                &["2026-01-05 00:00:00", "NO₃⁻", "1.2", "C"],
+                &["2026-01-05 01:00:00", "NO₃⁻", "1.5", ""],
+                &["2026-01-05 01:00:00", "SO₄²⁻", "2.5", ""],
+            ])
+        );
+        assert_eq!(long.skipped, 2);
+
+        let wide = to_wide(&long.table, &LongHeaders::default())?;
+        assert_eq!(wide.table, [&rows[2], &rows[5], &rows[6]].map(Clone::clone));
+        Ok(())
+    }
+
+    #[test]
+    fn writes_csv_or_workbook() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("long.csv");
+        std::fs::write(
+            &input,
+            "时间,项目,数值\n2026-01-05 00:00:00,NO₃⁻,1.2\n2026-01-05 00:00:00,SO₄²⁻,n.d.\n",
+        )?;
+        let args = PivotArgs {
+            input: input.clone(),
+            to: PivotDirection::Wide,
+            sheet: None,
+            delimiter: b',',
+            encoding: CsvEncoding::Auto,
+            headers: LongHeaders::default(),
+        };
+        let output = dir.path().join("wide.xlsx");
+        let global = GlobalArgs {
+            output: Some(output.clone()),
+            ..Default::default()
+        };
+        execute(&args, &global)?;
+        let book = workbook::read(&output)?;
+        let sheet = book.get_sheet(&0).expect("sheet");
+        assert_eq!(workbook::cell_text(sheet, 1, 2), "2026-01-05 00:00:00");
+        assert_eq!(sheet.get_value((2, 2)), "1.2");
+        assert_eq!(sheet.get_value((3, 2)), "n.d.");
+
+        let output = dir.path().join("wide.csv");
+        let global = GlobalArgs {
+            output: Some(output.clone()),
+            ..Default::default()
+        };
+        execute(&args, &global)?;
+        assert_eq!(
+            std::fs::read_to_string(&output)?,
+            "时间,NO₃⁻,SO₄²⁻\n2026-01-05 00:00:00,1.2,n.d.\n"
+        );
+        Ok(())
+    }
+}
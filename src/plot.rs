@@ -0,0 +1,266 @@
+//! `--plots`：为数据表中的每个项目画一张时间序列折线图（PNG），写在输出文件旁边，
+//! 不打开 Excel 就能看出尖峰与断档。
+//!
+//! 缺失的值使折线断开。标题与坐标轴文字使用系统中的中文字体（见 [`FONT_PATHS`]，
+//! 或由环境变量 `DTTOOLS_FONT` 指定）；找不到字体时只画曲线，项目名见文件名。
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{NaiveDateTime, TimeDelta};
+use plotters::coord::types::RangedDateTime;
+use plotters::prelude::*;
+use plotters::style::{FontStyle, register_font};
+use tracing::{debug, warn};
+
+use crate::error::{DtToolsError, Result};
+use crate::flags;
+use crate::workbook::TIME_TEXT_FORMAT;
+
+/// 图片尺寸（像素）。
+const SIZE: (u32, u32) = (1200, 400);
+
+/// 指定字体文件的环境变量。
+pub(crate) const FONT_ENV: &str = "DTTOOLS_FONT";
+
+/// 依次尝试的系统字体（Windows、macOS、Linux 常见的中文字体）。
+const FONT_PATHS: &[&str] = &[
+    r"C:\Windows\Fonts\msyh.ttc",
+    r"C:\Windows\Fonts\simhei.ttf",
+    r"C:\Windows\Fonts\simsun.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Light.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+];
+
+/// 注册字体（整个进程只做一次），返回是否有可用的字体。
+fn font_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let configured = std::env::var_os(FONT_ENV).map(PathBuf::from);
+        let candidates = configured
+            .iter()
+            .cloned()
+            .chain(FONT_PATHS.iter().map(PathBuf::from));
+        for path in candidates {
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            // 字体在整个进程中使用，注册后不再释放
+            let bytes: &'static [u8] = Box::leak(bytes.into_boxed_slice());
+            if register_font("sans-serif", FontStyle::Normal, bytes).is_ok() {
+                debug!("图表字体: {}", path.display());
+                return true;
+            }
+        }
+        if let Some(path) = configured {
+            warn!("无法使用 {FONT_ENV} 指定的字体: {}", path.display());
+        }
+        warn!("没有找到可用的中文字体，图表中不显示文字（可用环境变量 {FONT_ENV} 指定字体文件）");
+        false
+    })
+}
+
+/// 一个项目的折线：缺失的值把折线分为若干段。
+#[derive(Debug, Default, PartialEq)]
+struct Series {
+    segments: Vec<Vec<(NaiveDateTime, f64)>>,
+}
+
+impl Series {
+    fn points(&self) -> impl Iterator<Item = &(NaiveDateTime, f64)> {
+        self.segments.iter().flatten()
+    }
+}
+
+/// 由数据表（首行为表头，首列为时间）取出每个项目的折线，顺序同表头。空单元格、`missing`
+/// 中的缺失值代码与非数值使折线断开；带标记的值（如 `1.6#C`）取其数值。时间无法解析的行跳过。
+fn series(table: &[Vec<String>], missing: &[&str]) -> Vec<(String, Series)> {
+    let Some((header, rows)) = table.split_first() else {
+        return Vec::new();
+    };
+    let mut series: Vec<(String, Series)> = header
+        .iter()
+        .skip(1)
+        .map(|name| (name.clone(), Series::default()))
+        .collect();
+    let mut open = vec![false; series.len()];
+    for row in rows {
+        let Some((time, values)) = row.split_first() else {
+            continue;
+        };
+        let Ok(time) = NaiveDateTime::parse_from_str(time.trim(), TIME_TEXT_FORMAT) else {
+            continue;
+        };
+        for (i, (_, series)) in series.iter_mut().enumerate() {
+            let text = values.get(i).map(|text| text.trim()).unwrap_or_default();
+            let value = (!missing.contains(&text))
+                .then(|| flags::split(text).map_or(text, |(value, _)| value))
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|value| value.is_finite());
+            match value {
+                Some(value) if open[i] => series
+                    .segments
+                    .last_mut()
+                    .expect("open segment")
+                    .push((time, value)),
+                Some(value) => {
+                    series.segments.push(vec![(time, value)]);
+                    open[i] = true;
+                }
+                None => open[i] = false,
+            }
+        }
+    }
+    series
+}
+
+/// 图片路径：`<输出文件名主干>_<项目>.png`，项目名中不能用于文件名的字符替换为 `_`。
+fn chart_path(output: &Path, name: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || r#"\/:*?"<>|"#.contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    output.with_file_name(format!("{stem}_{}.png", name.trim()))
+}
+
+/// 为每个有数值的项目画一张图，写在 `output` 旁边；返回写出的图片路径。
+pub(crate) fn write_charts(
+    output: &Path,
+    table: &[Vec<String>],
+    missing: &[&str],
+) -> Result<Vec<PathBuf>> {
+    let labels = font_available();
+    let mut written = Vec::new();
+    for (name, series) in series(table, missing) {
+        if series.segments.is_empty() {
+            continue;
+        }
+        let path = chart_path(output, &name);
+        draw(&path, &name, &series, labels)
+            .map_err(|err| DtToolsError::write(&path, err.to_string()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn draw(
+    path: &Path,
+    name: &str,
+    series: &Series,
+    labels: bool,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (mut start, mut end) = (NaiveDateTime::MAX, NaiveDateTime::MIN);
+    let (mut low, mut high) = (f64::INFINITY, f64::NEG_INFINITY);
+    for &(time, value) in series.points() {
+        start = start.min(time);
+        end = end.max(time);
+        low = low.min(value);
+        high = high.max(value);
+    }
+    if start == end {
+        start -= TimeDelta::hours(1);
+        end += TimeDelta::hours(1);
+    }
+    let margin = if high > low { (high - low) * 0.05 } else { 1.0 };
+
+    let root = BitMapBackend::new(path, SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut builder = ChartBuilder::on(&root);
+    builder.margin(10);
+    if labels {
+        builder
+            .caption(name, ("sans-serif", 20))
+            .x_label_area_size(30)
+            .y_label_area_size(60);
+    }
+    let mut chart = builder.build_cartesian_2d(
+        RangedDateTime::from(start..end),
+        (low - margin)..(high + margin),
+    )?;
+    // 坐标轴刻度需要字体；没有字体时只画曲线
+    if labels {
+        chart
+            .configure_mesh()
+            .x_labels(8)
+            .x_label_formatter(&|time| time.format("%m-%d %H:%M").to_string())
+            .draw()?;
+    }
+    for segment in &series.segments {
+        if segment.len() == 1 {
+            chart.draw_series(PointSeries::of_element(
+                segment.iter().copied(),
+                2,
+                BLUE.filled(),
+                &|point, size, style| Circle::new(point, size, style),
+            ))?;
+        } else {
+            chart.draw_series(LineSeries::new(segment.iter().copied(), &BLUE))?;
+        }
+    }
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: &[[&str; 3]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn splits_series_at_missing_values() {
+        let table = table(&[
+            ["时间", "NO₃⁻", "SO₄²⁻"],
+            ["2026-01-05 01:00:00", "1.6#C", "-999"],
+            ["2026-01-05 02:00:00", "", "4.2"],
+            ["2026-01-05 03:00:00", "2", "4.4"],
+            ["2026-01-05 04:00:00", "2.5", "x"],
+        ]);
+        let series = series(&table, &["-999"]);
+        assert_eq!(series[0].0, "NO₃⁻");
+        let lengths =
+            |series: &Series| -> Vec<usize> { series.segments.iter().map(Vec::len).collect() };
+        assert_eq!(lengths(&series[0].1), [1, 2]);
+        assert_eq!(lengths(&series[1].1), [2]);
+        assert_eq!(series[0].1.segments[0][0].1, 1.6);
+    }
+
+    #[test]
+    fn writes_one_png_per_analyte() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("processed_a.xlsx");
+        let table = table(&[
+            ["时间", "NO₃⁻", "Ca²⁺/Mg²⁺"],
+            ["2026-01-05 01:00:00", "1.5", ""],
+            ["2026-01-05 02:00:00", "2", ""],
+        ]);
+        let written = write_charts(&output, &table, &[])?;
+        assert_eq!(written, [dir.path().join("processed_a_NO₃⁻.png")]);
+        let bytes = std::fs::read(&written[0])?;
+        assert!(bytes.starts_with(b"\x89PNG"));
+        assert_eq!(
+            chart_path(&output, "Ca²⁺/Mg²⁺"),
+            dir.path().join("processed_a_Ca²⁺_Mg²⁺.png")
+        );
+        Ok(())
+    }
+}
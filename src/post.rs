@@ -0,0 +1,233 @@
+//! `--post`：把处理后的数据行以 JSON 提交到数据接收接口，处理与上报一步完成。
+//!
+//! 每个输出文件提交一次，请求体见 [`Payload`]；接口返回非 2xx 状态时该文件记为失败。
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::{Context, Result, bail, invalid};
+use clap::Args;
+use serde::Serialize;
+use tracing::info;
+
+/// 未指定 `Authorization` 请求头时，从该环境变量读取令牌并以 `Bearer` 方式发送。
+pub const TOKEN_ENV: &str = "DTTOOLS_POST_TOKEN";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 错误信息中保留的响应内容长度（字符）。
+const MAX_ERROR_BODY: usize = 200;
+
+/// 提交数据相关的全局参数。
+#[derive(Debug, Clone, Default, Args)]
+pub struct PostArgs {
+    /// 把处理后的数据行以 JSON POST 到该地址（http:// 或 https://）
+    #[arg(long = "post", global = true, value_name = "URL")]
+    pub url: Option<String>,
+
+    /// 附加的请求头，如 "Authorization: Bearer <令牌>" 或 "X-Api-Key: <密钥>"（可重复）
+    #[arg(
+        long = "post-header",
+        global = true,
+        value_name = "NAME: VALUE",
+        value_parser = parse_header
+    )]
+    pub headers: Vec<(String, String)>,
+}
+
+/// 一个工作表的数据：`columns` 为首行表头，`rows` 为其余各行，空单元格为 `null`。
+#[derive(Debug, Serialize)]
+pub struct SheetRows<'a> {
+    pub name: &'a str,
+    pub columns: &'a [String],
+    pub rows: Vec<Vec<Option<&'a str>>>,
+}
+
+impl<'a> SheetRows<'a> {
+    /// 由导出 CSV 时使用的数据表构造。
+    pub fn new(name: &'a str, table: &'a [Vec<String>]) -> Self {
+        let (columns, rows) = table
+            .split_first()
+            .map_or((&[][..], &[][..]), |(head, rest)| (head.as_slice(), rest));
+        Self {
+            name,
+            columns,
+            rows: rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|value| (!value.is_empty()).then_some(value.as_str()))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 提交的请求体。
+#[derive(Debug, Serialize)]
+pub struct Payload<'a> {
+    /// `eemcg` 或 `proton`
+    pub tool: &'a str,
+    pub source: &'a Path,
+    pub output: &'a Path,
+    pub generated_at: String,
+    pub sheets: Vec<SheetRows<'a>>,
+}
+
+impl PostArgs {
+    /// 指定了 `--post` 时提交一个输出文件的数据。
+    pub(crate) fn send(
+        &self,
+        tool: &str,
+        source: &Path,
+        output: &Path,
+        sheets: Vec<SheetRows<'_>>,
+    ) -> Result<()> {
+        let Some(url) = &self.url else {
+            return Ok(());
+        };
+        let payload = Payload {
+            tool,
+            source,
+            output,
+            generated_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            sheets,
+        };
+        let rows: usize = payload.sheets.iter().map(|sheet| sheet.rows.len()).sum();
+        let status = post_json(url, &self.request_headers(), &payload)
+            .with_context(|| format!("提交数据失败: {} -> {url}", output.display()))?;
+        info!("已提交 {rows} 行数据到 {url}（HTTP {status}）");
+        Ok(())
+    }
+
+    /// `--post-header` 给出的请求头；没有 `Authorization` 时加上 [`TOKEN_ENV`] 中的令牌。
+    fn request_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers.clone();
+        let has_auth = headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("authorization"));
+        if !has_auth && let Ok(token) = std::env::var(TOKEN_ENV) {
+            headers.push(("Authorization".to_string(), format!("Bearer {token}")));
+        }
+        headers
+    }
+}
+
+/// 解析 `名称: 值` 形式的请求头。
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    let (name, header_value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("请求头的格式应为 名称: 值: {value}"))?;
+    let name = name.trim();
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+        return Err(format!("无效的请求头名称: {name}"));
+    }
+    Ok((name.to_string(), header_value.trim().to_string()))
+}
+
+/// 发送 JSON 请求，返回 HTTP 状态码；非 2xx 时返回带响应内容的错误。
+fn post_json(url: &str, headers: &[(String, String)], payload: &impl Serialize) -> Result<u16> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut request = agent.post(url);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let mut response = request
+        .send_json(payload)
+        .map_err(|err| invalid!("{err}"))?;
+    let status = response.status().as_u16();
+    if !response.status().is_success() {
+        let body = response.body_mut().read_to_string().unwrap_or_default();
+        let body: String = body.chars().take(MAX_ERROR_BODY).collect();
+        bail!("接口返回 HTTP {status}: {}", body.trim());
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// 在本地端口接收一个请求，按 `status` 应答，返回请求头与请求体。
+    fn serve_once(status: &'static str) -> (String, thread::JoinHandle<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ingest", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let length: usize = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse().ok())?
+                })
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let reply =
+                format!("HTTP/1.1 {status}\r\nContent-Length: 2\r\nConnection: close\r\n\r\nno");
+            reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            (head, String::from_utf8(body).unwrap())
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn posts_rows_with_headers() -> Result<()> {
+        let (url, server) = serve_once("200 OK");
+        let table = vec![
+            vec!["时间".to_string(), "NO₃⁻".to_string()],
+            vec!["2026-01-05 01:00:00".to_string(), String::new()],
+        ];
+        let args = PostArgs {
+            url: Some(url),
+            headers: vec![parse_header("X-Api-Key: abc").unwrap()],
+        };
+        args.send(
+            "proton",
+            Path::new("a.xlsx"),
+            Path::new("processed_a.xlsx"),
+            vec![SheetRows::new("Sheet1", &table)],
+        )?;
+
+        let (head, body) = server.join().unwrap();
+        assert!(head.to_ascii_lowercase().contains("x-api-key: abc"));
+        let body: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(body["tool"], "proton");
+        assert_eq!(body["sheets"][0]["columns"][1], "NO₃⁻");
+        assert_eq!(
+            body["sheets"][0]["rows"],
+            serde_json::json!([["2026-01-05 01:00:00", null]])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_error_status_and_bad_headers() {
+        let (url, server) = serve_once("401 Unauthorized");
+        let err = post_json(&url, &[], &serde_json::json!({})).unwrap_err();
+        assert_eq!(err.to_string(), "接口返回 HTTP 401: no");
+        server.join().unwrap();
+
+        assert!(parse_header("Authorization Bearer x").is_err());
+        assert!(parse_header("Bad Name: x").is_err());
+    }
+}
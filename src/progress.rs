@@ -0,0 +1,67 @@
+//! 进度条：大文件逐行扫描、写入单元格以及批量处理文件时显示进度，避免长时间没有输出。
+//!
+//! 只在标准输出和标准错误都是终端时显示；输出被重定向、使用 `--quiet` 或作为库调用时，
+//! 进度条为隐藏状态，不产生任何输出。
+
+use std::io::{self, IsTerminal};
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressFinish, ProgressStyle};
+
+use crate::cli::GlobalArgs;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 批量进度条与单个文件的进度条上下排列显示。
+static BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// 按全局参数决定是否显示进度条。
+pub(crate) fn init(global: &GlobalArgs) {
+    let enabled = !global.quiet && io::stdout().is_terminal() && io::stderr().is_terminal();
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// 新建一个共 `len` 步的进度条，`prefix` 说明正在做什么，`unit` 为计数单位（如“行”）。
+/// 进度条在被丢弃时清除。
+pub(crate) fn bar(len: usize, prefix: &'static str, unit: &'static str) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template(&format!(
+        "{{prefix}} [{{bar:30}}] {{human_pos}}/{{human_len}} {unit} {{msg}} ({{eta}})"
+    ))
+    .expect("valid progress template")
+    .progress_chars("=> ");
+    let bar = ProgressBar::new(len as u64)
+        .with_style(style)
+        .with_prefix(prefix)
+        .with_finish(ProgressFinish::AndClear);
+    BARS.add(bar)
+}
+
+/// 暂停绘制进度条并执行 `f`，用于在进度条上方输出日志。
+pub(crate) fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    if ENABLED.load(Ordering::Relaxed) {
+        BARS.suspend(f)
+    } else {
+        f()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_when_quiet() {
+        init(&GlobalArgs {
+            quiet: true,
+            ..Default::default()
+        });
+        let bar = bar(10, "扫描", "行");
+        assert!(bar.is_hidden());
+        bar.inc(1);
+        assert_eq!(suspend(|| 1), 1);
+    }
+}
@@ -1,38 +1,159 @@
-use std::fs;
+use std::cell::LazyCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, Write};
 use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
-use anyhow::{Context, Result, anyhow};
-use calamine::{Data, Reader, open_workbook_auto};
-use chrono::NaiveDateTime;
+use calamine::{Data, Range};
+use chrono::{FixedOffset, NaiveDateTime, TimeDelta};
+use clap::{Args, Parser};
 use regex::Regex;
+use tracing::{debug, info, warn};
+use umya_spreadsheet::Spreadsheet;
 
-type DataRow = (
-    String,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-);
+use crate::batch;
+use crate::cli::{self, GlobalArgs, Writer};
+use crate::error::{DtToolsError, Result, invalid};
+use crate::export;
+use crate::flags::{self, BelowLimitValues, DataFlag};
+use crate::i18n::Message;
+use crate::long;
+use crate::progress;
+use crate::report::{self, CellChange, Problem};
+use crate::template::{ColumnHeader, Vars};
+use crate::units::{self, DecimalMark};
+use crate::workbook;
 
-fn cell_ref(col_1_based: usize, row_1_based: usize) -> String {
-    fn col_to_name(mut col: usize) -> String {
-        let mut name = String::new();
-        while col > 0 {
-            let rem = (col - 1) % 26;
-            name.push((b'A' + rem as u8) as char);
-            col = (col - 1) / 26;
-        }
-        name.chars().rev().collect()
+mod calibration;
+mod charts;
+mod chemistry;
+mod config;
+mod correction;
+mod dedupe;
+mod headers;
+mod merge;
+mod qc;
+mod resample;
+mod source;
+mod stream;
+mod timeline;
+mod timezone;
+
+pub use calibration::{CalibrationSchedule, CalibrationWindow};
+pub use chemistry::IonBalance;
+pub use config::{CONFIG_FILE, FlaggedValues, ProtonOptions};
+pub use correction::Correction;
+pub use dedupe::Dedupe;
+pub(crate) use headers::HeaderIndex;
+pub(crate) use merge::execute as execute_merge;
+pub use merge::{MergeArgs, merge, merge_with};
+pub use qc::{Bounds, QcFlag, QcKind};
+pub(crate) use resample::DEFAULT_MIN_CAPTURE;
+pub use source::CsvEncoding;
+pub(crate) use timeline::parse_interval;
+pub use timeline::{TimeIssue, TimeIssueKind};
+
+/// 一行输出数据：时间与模板第 2 列起各分析物的浓度，顺序同 [`ANALYTES`]。
+#[derive(Debug, Clone)]
+struct DataRow {
+    /// 源表中的行号（1 起计）；补齐缺失时间点插入的行为 0
+    source_row: usize,
+    time: String,
+    values: Vec<Option<String>>,
+    /// NO₂ 浓度，只用于化学质控计算 NOR；源表中没有 NO₂ 列时为 `None`
+    no2: Option<String>,
+}
+
+/// 模板中的一个分析物列。
+struct Analyte {
+    /// 源数据中的表头
+    header: &'static str,
+    /// 缺少该列时是否报错；可选列缺失时输出留空
+    required: bool,
+}
+
+/// 模板第 2 列起的分析物，与 `ROW3_HEADERS[1..]` 一一对应。
+const ANALYTES: [Analyte; 14] = [
+    Analyte {
+        header: "SO₂(μg/m³)",
+        required: false,
+    },
+    Analyte {
+        header: "HNO₃(μg/m³)",
+        required: false,
+    },
+    Analyte {
+        header: "HNO₂(μg/m³)",
+        required: false,
+    },
+    Analyte {
+        header: "HCl(μg/m³)",
+        required: false,
+    },
+    Analyte {
+        header: "NH₃(μg/m³)",
+        required: false,
+    },
+    Analyte {
+        header: "NO₃⁻(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "SO₄²⁻(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "NH₄⁺(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "Cl⁻(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "K⁺(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "Na⁺(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "Mg²⁺(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "Ca²⁺(μg/m³)",
+        required: true,
+    },
+    Analyte {
+        header: "NO₂⁻(μg/m³)",
+        required: false,
+    },
+];
+
+/// 源数据中可选的 NO₂ 列的表头，化学质控时用于计算 NOR。
+const NO2_HEADER: &str = "NO₂(μg/m³)";
+
+/// 带 `(C)`/`(RM)` 标识的数值，如 `1.2(C)`。
+static FLAGGED_VALUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\((C|RM)\)").expect("valid regex"));
+
+fn col_to_name(mut col: usize) -> String {
+    let mut name = String::new();
+    while col > 0 {
+        let rem = (col - 1) % 26;
+        name.push((b'A' + rem as u8) as char);
+        col = (col - 1) / 26;
     }
+    name.chars().rev().collect()
+}
 
+fn cell_ref(col_1_based: usize, row_1_based: usize) -> String {
     format!("{}{}", col_to_name(col_1_based), row_1_based)
 }
 
-fn datatype_to_string(cell: Option<&Data>) -> String {
+pub(crate) fn datatype_to_string(cell: Option<&Data>) -> String {
     match cell {
         None => String::new(),
         Some(Data::Empty) => String::new(),
@@ -52,17 +173,31 @@ fn datatype_to_string(cell: Option<&Data>) -> String {
     }
 }
 
-fn processed_output_path(input: &Path) -> PathBuf {
-    let file_name = input
-        .file_name()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| "output.xlsx".to_string());
-    PathBuf::from(format!("processed_{file_name}"))
+/// 时间列的单元格文本：Excel 日期时间单元格按工作簿的日期系统（1900 或 1904）换算为
+/// `YYYY-MM-DD HH:MM:SS`，其余同 [`datatype_to_string`]。
+pub(crate) fn time_cell_text(cell: Option<&Data>) -> String {
+    match cell {
+        Some(Data::DateTime(time)) if time.is_datetime() => match time.as_datetime() {
+            Some(time) => time.format(timeline::TIME_FORMAT).to_string(),
+            None => time.as_f64().to_string(),
+        },
+        other => datatype_to_string(other),
+    }
 }
 
-fn parse_time_to_target_format(time_str: &str) -> Result<String> {
+/// 把源数据中的时间格式化为 `YYYY-MM-DD HH:MM:SS` 的站点当地时间。带时区的时间换算到
+/// `options.timezone`；不带时区的时间视为 `options.source_timezone`（未设置时即站点当地时间）。
+/// 最后按 `options.time_shift` 平移，统一为时段结束时刻。
+fn parse_time_to_target_format(time_str: &str, options: &ProtonOptions) -> Result<String> {
     let time_str = time_str.trim();
 
+    if let Some(time) = timezone::parse_with_offset(time_str) {
+        let local = time.with_timezone(&options.timezone).naive_local();
+        return Ok((local + options.time_shift)
+            .format(timeline::TIME_FORMAT)
+            .to_string());
+    }
+
     let parsed = if time_str.contains('T') {
         NaiveDateTime::parse_from_str(time_str, "%Y-%m-%dT%H:%M:%S")
             .or_else(|_| NaiveDateTime::parse_from_str(time_str, "%Y-%m-%dT%H:%M:%S%.f"))
@@ -70,269 +205,2710 @@ fn parse_time_to_target_format(time_str: &str) -> Result<String> {
         NaiveDateTime::parse_from_str(time_str, "%Y-%m-%d %H:%M:%S")
             .or_else(|_| NaiveDateTime::parse_from_str(time_str, "%Y/%m/%d %H:%M:%S"))
     } else {
-        return Err(anyhow!("无法解析时间格式: {}", time_str));
+        return Err(DtToolsError::TimeParse {
+            value: time_str.to_string(),
+        });
     };
 
-    let dt = parsed.with_context(|| format!("时间格式错误: {}", time_str))?;
+    let dt = parsed.map_err(|_| DtToolsError::TimeParse {
+        value: time_str.to_string(),
+    })?;
+    let dt = match options.source_timezone {
+        Some(source) => timezone::convert_local(dt, source, options.timezone),
+        None => dt,
+    };
 
-    Ok(dt.format("%Y-%m-%d %H:%M:%S").to_string())
+    Ok((dt + options.time_shift)
+        .format(timeline::TIME_FORMAT)
+        .to_string())
 }
 
-fn load_a2_text() -> Result<String> {
-    let config_path = Path::new("proton_config.txt");
+const ROW3_HEADERS: [&str; 15] = [
+    "离子色谱",
+    "SO₂",
+    "HNO₃",
+    "HNO₂",
+    "HCl",
+    "NH₃",
+    "NO₃⁻",
+    "SO₄²⁻",
+    "NH₄⁺",
+    "Cl⁻",
+    "K⁺",
+    "Na⁺",
+    "Mg²⁺",
+    "Ca²⁺",
+    "NO₂⁻",
+];
 
-    if config_path.exists() {
-        let content = fs::read_to_string(config_path)
-            .with_context(|| format!("无法读取配置文件: {}", config_path.display()))?;
-        Ok(content.trim().to_string())
-    } else {
-        Ok("请参考 proton_config.example.txt 创建配置文件 proton_config.txt".to_string())
+/// 第 4 行 A 列的默认站点编码；B 列起的因子编码取自编码表（[`crate::codes`]）。
+const DEFAULT_STATION_CODE: &str = "4401000010003";
+
+const ROW5_VALUES: [&str; 15] = [
+    "时间", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³",
+    "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³",
+];
+
+/// 离子色谱源数据：第一个工作表（或 CSV 文件）的名称和单元格区域。
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub sheet_name: String,
+    pub range: Range<Data>,
+}
+
+/// 读取输入文件（xlsx/xls/csv/tsv）的第一个工作表。`delimiter` 与 `encoding` 只用于 CSV。
+pub fn read_source(path: &Path, delimiter: u8, encoding: CsvEncoding) -> Result<Source> {
+    let (sheet_name, range) = source::read_first_sheet(path, delimiter, encoding)?;
+    Ok(Source { sheet_name, range })
+}
+
+/// 按 `options.sheets` 读取输入文件：为空时同 [`read_source`]；否则读取其中的各个工作表，
+/// 并按 [`join_sheets`] 合并为一个源表。
+pub fn read_source_with(
+    path: &Path,
+    delimiter: u8,
+    encoding: CsvEncoding,
+    options: &ProtonOptions,
+) -> Result<Source> {
+    if options.sheets.is_empty() {
+        return unpivot_blocks(read_source(path, delimiter, encoding)?, options);
     }
+    let sheets = options
+        .sheets
+        .iter()
+        .map(|name| {
+            let sheet = Source {
+                sheet_name: name.clone(),
+                range: source::read_sheet(path, name)?,
+            };
+            unpivot_blocks(sheet, options)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    join_sheets(&sheets, options)
 }
 
-fn process_excel(path: &Path) -> Result<PathBuf> {
-    let mut workbook =
-        open_workbook_auto(path).with_context(|| format!("无法打开文件: {}", path.display()))?;
+/// 把并排重复“时间 + 分析物”表头组的宽表（如按通道分组导出）拆为各组，并像 [`join_sheets`]
+/// 一样按时间合并为一个源表：每组从一个时间列起到下一个时间列前为止，各组中表头相同（含名称
+/// 词典中的写法）的列合并为一列，同一时间点取第一个非空值。合并后的表头行仍在原表头行，其上
+/// 各行留空，工作表名称不变。表头行中只有一个时间列，或已用 `--map`/`[columns]` 按原表直接
+/// 指定列时原样返回。
+pub fn unpivot_blocks(source: Source, options: &ProtonOptions) -> Result<Source> {
+    if options.time_column.is_some() || options.columns.iter().any(Option::is_some) {
+        return Ok(source);
+    }
+    let header_row = header_row(&source, options);
+    let cells = header_cells(&source, header_row);
+    let time_keys: Vec<String> = std::iter::once("时间")
+        .chain(options.time_aliases.iter().map(String::as_str))
+        .map(|header| options.species.key(header))
+        .collect();
+    let starts: Vec<usize> = (0..cells.len())
+        .filter(|&col| time_keys.contains(&options.species.key(&cells[col])))
+        .collect();
+    if starts.len() < 2 {
+        return Ok(source);
+    }
 
-    let sheet_names = workbook.sheet_names();
-    let sheet_name = sheet_names
-        .first()
-        .ok_or_else(|| anyhow!("工作簿中没有工作表"))?;
+    let (height, width) = source.range.get_size();
+    let blocks: Vec<Source> = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(width);
+            let mut range = Range::new(
+                (0, 0),
+                ((height - header_row - 1) as u32, (end - start - 1) as u32),
+            );
+            for row in header_row..height {
+                for col in start..end {
+                    if let Some(value) = source.range.get((row, col)) {
+                        range.set_value(
+                            ((row - header_row) as u32, (col - start) as u32),
+                            value.clone(),
+                        );
+                    }
+                }
+            }
+            Source {
+                sheet_name: format!("{}[{}]", source.sheet_name, i + 1),
+                range,
+            }
+        })
+        .collect();
+    let block_options = ProtonOptions {
+        header_row: Some(0),
+        ..options.clone()
+    };
+    Ok(Source {
+        sheet_name: source.sheet_name,
+        range: join_ranges(&blocks, &block_options, header_row)?,
+    })
+}
+
+/// 把分表导出的源数据（如阳离子、阴离子各一个工作表）按时间合并为一个源表：首行为表头，
+/// A 列为时间，其后依次为各工作表中除时间列以外的列（表头相同的列合并为一列）；某个工作表中
+/// 没有的时间点留空。
+/// 各工作表的表头行与时间列按 [`header_row`] 与表头（含别名）确定；时间按输出格式统一后
+/// 比较，合并后按时间排序；同一工作表中重复的时间点取第一个非空值并给出警告。合并后的表头
+/// 仍在 `--header-row` 指定的行（未指定时为首行），其上各行留空。合并后的工作表名称为各工作表
+/// 名称以 `+` 相连，变更记录中的单元格地址指合并后的表。
+pub fn join_sheets(sheets: &[Source], options: &ProtonOptions) -> Result<Source> {
+    if sheets.len() == 1 {
+        return Ok(sheets[0].clone());
+    }
+    if options.time_column.is_some() || options.columns.iter().any(Option::is_some) {
+        return Err(invalid!(
+            "合并多个源工作表时不能直接指定列（--map、[columns]），请改用 [aliases]"
+        ));
+    }
+    let sheet_name = sheets
+        .iter()
+        .map(|sheet| sheet.sheet_name.as_str())
+        .collect::<Vec<_>>()
+        .join("+");
+    Ok(Source {
+        sheet_name,
+        range: join_ranges(sheets, options, options.header_row.unwrap_or(0))?,
+    })
+}
+
+/// [`join_sheets`] 与 [`unpivot_blocks`] 的合并：合并后的表头在第 `top` 行（0 起计）。
+fn join_ranges(sheets: &[Source], options: &ProtonOptions, top: usize) -> Result<Range<Data>> {
+    let mut header = vec![Data::String("时间".to_string())];
+    // 表头统一写法后的比较键 -> 合并后的列号
+    let mut targets: HashMap<String, usize> = HashMap::new();
+    // 合并后的各行：统一后的时间 -> (原时间单元格, 各列的值)
+    let mut rows: BTreeMap<String, (Data, Vec<Data>)> = BTreeMap::new();
+    for sheet in sheets {
+        let header_row = header_row(sheet, options);
+        let cells = header_cells(sheet, header_row);
+        let time_col = header_index(&cells, options)
+            .find(std::iter::once("时间").chain(options.time_aliases.iter().map(String::as_str)))
+            .ok_or_else(|| DtToolsError::MissingColumn {
+                name: format!("时间（工作表 {}）", sheet.sheet_name),
+            })?;
+        // (源列号, 合并后的列号)
+        let columns: Vec<(usize, usize)> = (0..cells.len())
+            .filter(|&col| col != time_col && !cells[col].is_empty())
+            .map(|col| {
+                let target = *targets
+                    .entry(options.species.key(&cells[col]))
+                    .or_insert_with(|| {
+                        header.push(Data::String(cells[col].clone()));
+                        header.len() - 1
+                    });
+                (col, target)
+            })
+            .collect();
+
+        let (height, _) = sheet.range.get_size();
+        let mut seen = HashSet::new();
+        let mut duplicates = 0;
+        for row in header_row + 1..height {
+            let time = sheet
+                .range
+                .get((row, time_col))
+                .cloned()
+                .unwrap_or_default();
+            let text = time_cell_text(Some(&time));
+            if text.is_empty() {
+                continue;
+            }
+            let key = parse_time_to_target_format(&text, options).unwrap_or(text);
+            if !seen.insert(key.clone()) {
+                duplicates += 1;
+            }
+            let (_, values) = rows.entry(key).or_insert_with(|| (time, Vec::new()));
+            values.resize(values.len().max(header.len() - 1), Data::Empty);
+            for &(col, target) in &columns {
+                let value = sheet.range.get((row, col)).cloned().unwrap_or_default();
+                if values[target - 1] == Data::Empty {
+                    values[target - 1] = value;
+                }
+            }
+        }
+        if duplicates > 0 {
+            warn!(
+                "工作表 {} 中有 {duplicates} 行的时间点重复，合并时各列取第一个非空值",
+                sheet.sheet_name
+            );
+        }
+    }
+
+    let top = top as u32;
+    let mut range = Range::new((0, 0), (top + rows.len() as u32, header.len() as u32 - 1));
+    for (col, value) in header.into_iter().enumerate() {
+        range.set_value((top, col as u32), value);
+    }
+    for (row, (time, values)) in rows.into_values().enumerate() {
+        let row = top + row as u32 + 1;
+        range.set_value((row, 0), time);
+        for (col, value) in values.into_iter().enumerate() {
+            if value != Data::Empty {
+                range.set_value((row, col as u32 + 1), value);
+            }
+        }
+    }
+    Ok(range)
+}
 
-    let range = workbook
-        .worksheet_range(sheet_name)
-        .with_context(|| format!("无法读取工作表: {sheet_name}"))?;
+/// 一次 proton 转换的结果。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransformReport {
+    /// 源工作表名称
+    pub source_sheet: String,
+    /// 源表中的数据行数
+    pub source_rows: usize,
+    /// 输出的数据行数
+    pub rows: usize,
+    /// 源表中被改写（时间格式化）或清空（标记值、非数值）的单元格，以及输出中被标出的可疑值
+    pub changes: Vec<CellChange>,
+    /// 时间列连续性检查发现的问题；对应行的时间单元格在输出中标为红色
+    pub time_issues: Vec<TimeIssue>,
+    /// 为缺失时间点插入的数据行数（见 [`ProtonOptions::fill_gaps`]）
+    pub filled_rows: usize,
+    /// 质量检查发现的可疑值；对应单元格在输出中按类型标色（见 [`QcKind::fill`]）
+    pub qc_flags: Vec<QcFlag>,
+    /// 替换或清空的低于检出限的值个数（见 [`ProtonOptions::below_limit`]）
+    pub below_limit: usize,
+    /// 按 [`ProtonOptions::correction`] 校正的值个数，原值见输出中隐藏的“校正前数据”工作表
+    pub corrected: usize,
+    /// 时间在报告期（见 [`ProtonOptions::from`]）以外而跳过的源数据行数
+    pub outside_period: usize,
+    /// 按 [`ProtonOptions::dedupe`] 合并重复时间点时去掉的行数（合并多个源文件时见 [`merge`]）
+    pub duplicates: usize,
+    /// 化学质控指标（见 [`ProtonOptions::chemistry`]），写入输出的“离子平衡”工作表
+    pub ion_balance: Vec<IonBalance>,
+    /// 宽松模式（见 [`ProtonOptions::lenient`]）下跳过的缺失列与无法解析时间的行
+    pub problems: Vec<Problem>,
+}
 
-    let (height, width) = range.get_size();
+/// 转换输出：上传模板工作簿、对应的数据表（首行为表头）与转换报告。
+#[derive(Debug, Clone)]
+pub struct Transformed {
+    pub workbook: Spreadsheet,
+    /// 数据表（首行为表头）；流式写出时为空，以免与 `pending` 同时留在内存中
+    pub table: Vec<Vec<String>>,
+    pub report: TransformReport,
+    /// 流式写出（[`Writer::Streaming`]）时尚未写入 `workbook` 的数据行，由
+    /// [`Transformed::write_streaming`] 逐行写出；否则为空
+    pending: Vec<DataRow>,
+}
 
-    if height < 2 {
-        return Err(anyhow!("表格行数不足，无法读取数据"));
+/// 按 `options.flagged` 处理带 (C)/(RM) 标记的值；返回 `None` 表示清空。
+fn flagged_value(value: &str, options: &ProtonOptions) -> Option<String> {
+    match options.flagged {
+        FlaggedValues::Drop => None,
+        FlaggedValues::Flag => {
+            flags::parse_marked(value).map(|(number, flag)| flags::join(number, flag.letter()))
+        }
+        FlaggedValues::Invalid => Some(options.invalid_code.clone()),
     }
+}
 
-    let re = Regex::new(r"\((C|RM)\)").expect("valid regex");
+/// 校准时间表内的数值，按 [`ProtonOptions::flagged`] 处理，标记为校准（C）。
+fn calibration_value(number: &str, options: &ProtonOptions) -> Option<String> {
+    match options.flagged {
+        FlaggedValues::Drop => None,
+        FlaggedValues::Flag => Some(flags::join(number, DataFlag::Calibration.letter())),
+        FlaggedValues::Invalid => Some(options.invalid_code.clone()),
+    }
+}
 
-    let mut column_map: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+/// 源表中时间列与各分析物列的列号（0 起计），找不到时为 `None`。
+struct ColumnLayout {
+    time: Option<usize>,
+    analytes: Vec<Option<usize>>,
+    /// 可选的 NO₂ 列，见 [`DataRow::no2`]
+    no2: Option<usize>,
+}
 
-    for col in 0..width {
-        let header = datatype_to_string(range.get((0, col)));
+impl ColumnLayout {
+    /// 找不到的必需列的表头（含时间列）及其在 [`ANALYTES`] 中的序号（时间列为 `None`）。
+    fn missing_required(&self) -> Vec<(&'static str, Option<usize>)> {
+        let time = self.time.is_none().then_some(("时间", None));
+        let analytes = ANALYTES
+            .iter()
+            .zip(&self.analytes)
+            .enumerate()
+            .filter(|(_, (analyte, col))| analyte.required && col.is_none())
+            .map(|(i, (analyte, _))| (analyte.header, Some(i)));
+        time.into_iter().chain(analytes).collect()
+    }
+}
+
+/// 自动识别表头行时检查的源表前几行。
+pub const HEADER_SCAN_ROWS: usize = 20;
+
+/// 识别为表头行至少须含有的已知分析物表头数（另须含有时间列）。
+const MIN_HEADER_ANALYTES: usize = 3;
+
+/// 表头的索引，按 `options.species` 统一写法。
+fn header_index<'a>(header_cells: &[String], options: &'a ProtonOptions) -> HeaderIndex<'a> {
+    HeaderIndex::with_species(
+        header_cells
+            .iter()
+            .enumerate()
+            .map(|(col, header)| (col, header.as_str())),
+        &options.species,
+    )
+}
+
+/// 表头所在的行（0 起计）：`options.header_row` 指定的行；否则为前 [`HEADER_SCAN_ROWS`] 行中
+/// 第一个含有时间列和至少 [`MIN_HEADER_ANALYTES`] 个已知分析物表头（含别名）的行，用于跳过
+/// 标题行与空行；都不符合时为首行。
+fn header_row(source: &Source, options: &ProtonOptions) -> usize {
+    if let Some(row) = options.header_row {
+        return row;
+    }
+    let (height, _) = source.range.get_size();
+    (0..height.min(HEADER_SCAN_ROWS))
+        .find(|&row| {
+            let index = header_index(&header_cells(source, row), options);
+            let time = index
+                .find(
+                    std::iter::once("时间").chain(options.time_aliases.iter().map(String::as_str)),
+                )
+                .is_some();
+            let analytes = ANALYTES
+                .iter()
+                .zip(&options.aliases)
+                .filter(|(analyte, aliases)| {
+                    index
+                        .find(
+                            std::iter::once(analyte.header)
+                                .chain(aliases.iter().map(String::as_str)),
+                        )
+                        .is_some()
+                })
+                .count();
+            time && analytes >= MIN_HEADER_ANALYTES
+        })
+        .unwrap_or(0)
+}
+
+/// 源表表头行的表头，按列排列。
+fn header_cells(source: &Source, row: usize) -> Vec<String> {
+    let (_, width) = source.range.get_size();
+    (0..width)
+        .map(|col| datatype_to_string(source.range.get((row, col))))
+        .collect()
+}
+
+/// 按 `options.columns` 指定的列、内置表头与别名确定各列的位置。
+fn locate_columns(source: &Source, options: &ProtonOptions) -> Result<ColumnLayout> {
+    let header_cells = header_cells(source, header_row(source, options));
+    let columns = header_index(&header_cells, options);
+    let check = |col: usize| {
+        if col < header_cells.len() {
+            Ok(col)
+        } else {
+            Err(invalid!("指定的列 {} 超出源表范围", col_to_name(col + 1)))
+        }
+    };
+
+    let time = match options.time_column {
+        Some(col) => Some(check(col)?),
+        None => columns
+            .find(std::iter::once("时间").chain(options.time_aliases.iter().map(String::as_str))),
+    };
+    let analytes = ANALYTES
+        .iter()
+        .zip(&options.aliases)
+        .zip(&options.columns)
+        .map(|((analyte, aliases), column)| match column {
+            Some(col) => check(*col).map(Some),
+            None => Ok(columns
+                .find(std::iter::once(analyte.header).chain(aliases.iter().map(String::as_str)))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let no2 = columns.find([NO2_HEADER]);
+    Ok(ColumnLayout {
+        time,
+        analytes,
+        no2,
+    })
+}
+
+/// 缺少必需的列时列出源表的表头，让用户逐个输入所在的列，结果写入 `options.columns`。
+/// 直接回车放弃，返回找不到该列的错误。
+fn prompt_missing_columns(
+    source: &Source,
+    options: &mut ProtonOptions,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<()> {
+    let missing = locate_columns(source, options)?.missing_required();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let header_cells = header_cells(source, header_row(source, options));
+    writeln!(
+        output,
+        "{}",
+        Message::SheetHeaders.render(&[("sheet", &source.sheet_name)])
+    )?;
+    for (col, header) in header_cells.iter().enumerate() {
         if !header.is_empty() {
-            column_map.insert(header.trim().to_string(), col);
-        }
-    }
-
-    let time_col = *column_map
-        .get("时间")
-        .ok_or_else(|| anyhow!("找不到'时间'列"))?;
-    let no3_col = *column_map
-        .get("NO₃⁻(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'NO₃⁻(μg/m³)'列"))?;
-    let so4_col = *column_map
-        .get("SO₄²⁻(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'SO₄²⁻(μg/m³)'列"))?;
-    let nh4_col = *column_map
-        .get("NH₄⁺(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'NH₄⁺(μg/m³)'列"))?;
-    let cl_col = *column_map
-        .get("Cl⁻(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'Cl⁻(μg/m³)'列"))?;
-    let k_col = *column_map
-        .get("K⁺(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'K⁺(μg/m³)'列"))?;
-    let na_col = *column_map
-        .get("Na⁺(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'Na⁺(μg/m³)'列"))?;
-    let mg_col = *column_map
-        .get("Mg²⁺(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'Mg²⁺(μg/m³)'列"))?;
-    let ca_col = *column_map
-        .get("Ca²⁺(μg/m³)")
-        .ok_or_else(|| anyhow!("找不到'Ca²⁺(μg/m³)'列"))?;
+            writeln!(output, "  {}: {header}", col_to_name(col + 1))?;
+        }
+    }
+    for (header, index) in missing {
+        let col = loop {
+            write!(
+                output,
+                "{}",
+                Message::AskColumn.render(&[("header", &header)])
+            )?;
+            output.flush()?;
+            let mut line = String::new();
+            input.read_line(&mut line)?;
+            let line = line.trim();
+            if line.is_empty() {
+                return Err(DtToolsError::MissingColumn {
+                    name: header.to_string(),
+                });
+            }
+            match workbook::column_index(line) {
+                Some(col) if (col as usize) <= header_cells.len() => break col as usize - 1,
+                _ => writeln!(
+                    output,
+                    "{}",
+                    Message::InvalidColumn.render(&[("column", &line)])
+                )?,
+            }
+        };
+        match index {
+            Some(i) => options.columns[i] = Some(col),
+            None => options.time_column = Some(col),
+        }
+    }
+    Ok(())
+}
+
+/// 从源表读取的数据行、源表中被改写或清空的单元格、宽松模式下跳过的问题，以及报告期以外
+/// 跳过的行数。
+#[derive(Default)]
+struct Extracted {
+    rows: Vec<DataRow>,
+    /// 源表中被改写或清空的单元格及其所在的行号（1 起计），去重时据此去掉被丢弃的行的变更
+    changes: Vec<(usize, CellChange)>,
+    /// 按 [`ProtonOptions::correction`] 校正的值
+    corrected: Vec<correction::Corrected>,
+    problems: Vec<Problem>,
+    outside_period: usize,
+}
+
+fn extract_rows(source: &Source, options: &ProtonOptions) -> Result<Extracted> {
+    let range = &source.range;
+    let (height, _) = range.get_size();
+    let header = header_row(source, options);
+
+    if height < header + 2 {
+        return Err(invalid!("表格行数不足，无法读取数据"));
+    }
+    if header > 0 {
+        info!("表头位于第 {} 行: {}", header + 1, source.sheet_name);
+    }
+
+    let layout = locate_columns(source, options)?;
+    let mut problems = Vec::new();
+    for (header, index) in layout.missing_required() {
+        // 宽松模式下缺少的分析物列留空；没有时间列时无法输出任何数据
+        if !options.lenient || index.is_none() {
+            return Err(DtToolsError::MissingColumn {
+                name: header.to_string(),
+            });
+        }
+        problems.push(Problem {
+            sheet: source.sheet_name.clone(),
+            row: 0,
+            column: header.to_string(),
+            value: String::new(),
+            message: "找不到该列，输出留空".to_string(),
+        });
+    }
+    let time_col = layout.time.expect("time column located");
+    // 每个分析物在源表中的列号，可选列缺失时为 None
+    let analyte_cols = layout.analytes;
+    let no2_col = layout.no2;
 
     let mut data_rows: Vec<DataRow> = Vec::new();
+    let mut outside_period = 0;
+    // 源表中被改写或清空的单元格，试运行时输出
+    let mut changes: Vec<(usize, CellChange)> = Vec::new();
+    let mut corrected = Vec::new();
+    let mut record_change = |row: usize, col: usize, before: &str, after: &str, rule: &str| {
+        let change = CellChange {
+            sheet: source.sheet_name.clone(),
+            address: cell_ref(col + 1, row + 1),
+            before: before.to_string(),
+            after: after.to_string(),
+            red_fill: false,
+            rules: vec![rule.to_string()],
+        };
+        changes.push((row + 1, change));
+    };
 
-    for row in 1..height {
-        let time_value = datatype_to_string(range.get((row, time_col)));
+    let progress = progress::bar(height - header - 1, "读取数据", "行");
+    for row in header + 1..height {
+        progress.inc(1);
+        let time_value = time_cell_text(range.get((row, time_col)));
         if time_value.is_empty() {
             continue;
         }
 
-        let formatted_time =
-            parse_time_to_target_format(&time_value).unwrap_or_else(|_| time_value.clone());
-
-        let is_valid_number = |value: &str| -> bool {
-            let value = value.trim();
-            if value.is_empty() {
-                return false;
+        let formatted_time = match parse_time_to_target_format(&time_value, options) {
+            Ok(time) => time,
+            Err(err) if options.lenient => {
+                problems.push(Problem {
+                    sheet: source.sheet_name.clone(),
+                    row: row + 1,
+                    column: cell_ref(time_col + 1, row + 1),
+                    value: time_value,
+                    message: format!("{err}，已跳过该行"),
+                });
+                continue;
             }
-            value.parse::<f64>().is_ok()
+            Err(_) => time_value.clone(),
         };
+        // 无法解析的时间不在此过滤，留给时间列检查报告
+        let time = NaiveDateTime::parse_from_str(&formatted_time, timeline::TIME_FORMAT).ok();
+        if time.is_some_and(|time| !options.in_period(time)) {
+            outside_period += 1;
+            continue;
+        }
+        let calibrating = |species: Option<usize>| match (time, species) {
+            (Some(time), Some(species)) => options.calibration.covers(time, species),
+            _ => false,
+        };
+        if formatted_time != time_value {
+            record_change(row, time_col, &time_value, &formatted_time, "time_format");
+        }
 
-        let get_value = |col: usize| -> Option<String> {
+        // `species` 为第 3 行表头（B 列起）的序号，NO₂ 列为 `None`
+        let mut get_value = |col: usize, species: Option<usize>| -> Option<String> {
             let value = datatype_to_string(range.get((row, col)));
-            if value.is_empty() || re.is_match(&value) || !is_valid_number(&value) {
+            if value.is_empty() {
                 None
+            } else if let Some(limit) = flags::parse_below_limit(&value) {
+                let limit = limit.or_else(|| species.and_then(|i| options.detection_limit[i]));
+                let kept = options
+                    .below_limit
+                    .substitute(limit, &options.below_limit_code);
+                record_change(
+                    row,
+                    col,
+                    &value,
+                    kept.as_deref().unwrap_or(""),
+                    "below_limit",
+                );
+                kept
+            } else if FLAGGED_VALUE.is_match(&value) {
+                let kept = flagged_value(&value, options);
+                record_change(row, col, &value, kept.as_deref().unwrap_or(""), "flagged");
+                kept
+            } else if let Some(number) = units::normalize_number(&value, options.decimal_mark) {
+                if calibrating(species) {
+                    let kept = calibration_value(&number, options);
+                    record_change(
+                        row,
+                        col,
+                        &value,
+                        kept.as_deref().unwrap_or(""),
+                        "calibration",
+                    );
+                    return kept;
+                }
+                let reformatted = number != value.trim();
+                // 先做漂移校正，再与检出限比较
+                let number = match species.and_then(|i| {
+                    let correction = options.correction[i].as_ref();
+                    correction::correct(
+                        &formatted_time,
+                        i,
+                        &number,
+                        correction,
+                        &options.invalid_code,
+                    )
+                }) {
+                    Some(value) => {
+                        let new = value.value().to_string();
+                        corrected.push(value);
+                        new
+                    }
+                    None => number,
+                };
+                let limit = species.and_then(|i| options.detection_limit[i]);
+                if let Ok(measured) = number.parse::<f64>()
+                    && options.below_limit.replaces_measured(measured, limit)
+                {
+                    let kept = options
+                        .below_limit
+                        .substitute(limit, &options.below_limit_code);
+                    record_change(
+                        row,
+                        col,
+                        &value,
+                        kept.as_deref().unwrap_or(""),
+                        "below_limit",
+                    );
+                    return kept;
+                }
+                if reformatted {
+                    record_change(row, col, &value, &number, "number_format");
+                }
+                Some(number)
             } else {
-                Some(value)
+                record_change(row, col, &value, "", "non_numeric");
+                None
             }
         };
 
-        data_rows.push((
-            formatted_time,
-            get_value(no3_col),
-            get_value(so4_col),
-            get_value(nh4_col),
-            get_value(cl_col),
-            get_value(k_col),
-            get_value(na_col),
-            get_value(mg_col),
-            get_value(ca_col),
-        ));
+        let values = analyte_cols
+            .iter()
+            .enumerate()
+            .map(|(species, col)| col.and_then(|col| get_value(col, Some(species))))
+            .collect();
+        let no2 = no2_col.and_then(|col| get_value(col, None));
+        data_rows.push(DataRow {
+            source_row: row + 1,
+            time: formatted_time,
+            values,
+            no2,
+        });
     }
 
+    Ok(Extracted {
+        rows: data_rows,
+        changes,
+        corrected,
+        problems,
+        outside_period,
+    })
+}
+
+/// 按各列的小数位数修约数值；带数据标记的值（如 `1.6#C`）修约数值部分。
+fn apply_precision(data_rows: &mut [DataRow], decimals: &[Option<u32>]) {
+    for data_row in data_rows {
+        for (value, decimals) in data_row.values.iter_mut().zip(decimals) {
+            let (Some(text), Some(decimals)) = (value.as_mut(), decimals) else {
+                continue;
+            };
+            if let Ok(number) = text.trim().parse::<f64>() {
+                *text = units::format_number(number, Some(*decimals));
+            } else if let Some((number, code)) = flags::split(text)
+                && let Ok(number) = number.parse::<f64>()
+            {
+                *text = flags::join(&units::format_number(number, Some(*decimals)), code);
+            }
+        }
+    }
+}
+
+/// Excel 数字格式，如 3 位小数为 `0.000`。
+fn number_format(decimals: u32) -> String {
+    if decimals == 0 {
+        "0".to_string()
+    } else {
+        format!("0.{}", "0".repeat(decimals as usize))
+    }
+}
+
+/// 在每个缺失的时间点插入一行，各浓度列写为 `code`（为空时留空）。返回补齐后的数据行与插入的行数。
+fn fill_gaps(
+    data_rows: Vec<DataRow>,
+    time_issues: &[TimeIssue],
+    interval: TimeDelta,
+    code: &str,
+) -> (Vec<DataRow>, usize) {
+    let gaps: HashMap<usize, (&str, usize)> = time_issues
+        .iter()
+        .filter_map(|issue| match &issue.kind {
+            TimeIssueKind::Gap { previous, missing } => {
+                Some((issue.row, (previous.as_str(), *missing)))
+            }
+            _ => None,
+        })
+        .collect();
+    let value = (!code.is_empty()).then(|| code.to_string());
+
+    let mut filled = 0;
+    let mut rows = Vec::with_capacity(data_rows.len());
+    for data_row in data_rows {
+        if let Some(&(previous, missing)) = gaps.get(&data_row.source_row) {
+            for time in timeline::missing_times(previous, missing, interval) {
+                rows.push(DataRow {
+                    source_row: 0,
+                    time,
+                    values: vec![value.clone(); ANALYTES.len()],
+                    no2: None,
+                });
+                filled += 1;
+            }
+        }
+        rows.push(data_row);
+    }
+    (rows, filled)
+}
+
+/// 把单元格样式的填充设为纯色 `argb`，保留数字格式等其他样式。
+fn set_solid_fill(style: &mut umya_spreadsheet::Style, argb: &str) {
+    let fill = style.get_fill_mut().get_pattern_fill_mut();
+    fill.set_pattern_type(umya_spreadsheet::structs::PatternValues::Solid);
+    fill.get_foreground_color_mut().set_argb(argb);
+    fill.get_background_color_mut().set_argb(argb);
+}
+
+/// 模板中各监测项目列的表头（名称、因子编码、单位），顺序同第 3 行表头（B 列起）。
+pub(crate) fn column_headers(options: &ProtonOptions) -> Vec<ColumnHeader<'_>> {
+    ROW3_HEADERS[1..]
+        .iter()
+        .zip(&options.codes)
+        .zip(&ROW5_VALUES[1..])
+        .map(|((name, code), unit)| ColumnHeader { name, code, unit })
+        .collect()
+}
+
+/// 模板第 3 行 A 列的名称。
+pub(crate) const TITLE: &str = ROW3_HEADERS[0];
+
+/// 模板中一个数据单元格写入的内容。
+enum Content<'a> {
+    /// 数值及其数字格式
+    Number(f64, Option<String>),
+    /// 日期时间（`YYYY-MM-DD HH:MM:SS`），写为日期时间单元格
+    Time(&'a str),
+    Text(&'a str),
+}
+
+/// 第 `col_idx` 个监测项目的值写入的内容；`filled` 为补齐缺失时间点插入的行，保持原样。
+fn value_content<'a>(
+    value: Option<&'a str>,
+    col_idx: usize,
+    filled: bool,
+    options: &'a ProtonOptions,
+) -> Content<'a> {
+    let typed = |text: &'a str| match text.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Content::Number(number, None),
+        _ => Content::Text(text),
+    };
+    match (value, options.decimals[col_idx]) {
+        // 设置了小数位数的列写为数值并带数字格式；补齐的占位行保持原样
+        (Some(v), Some(decimals)) if !filled => match v.parse::<f64>() {
+            Ok(number) => Content::Number(number, Some(number_format(decimals))),
+            Err(_) => Content::Text(v),
+        },
+        (Some(v), _) if options.template.typed_cells && !filled => typed(v),
+        (Some(v), _) => Content::Text(v),
+        (None, _) if options.template.typed_cells => typed(&options.missing_value),
+        (None, _) => Content::Text(&options.missing_value),
+    }
+}
+
+/// 时间单元格写入的内容。
+fn time_content<'a>(time: &'a str, options: &ProtonOptions) -> Content<'a> {
+    if options.template.typed_cells {
+        Content::Time(time)
+    } else {
+        Content::Text(time)
+    }
+}
+
+/// 只有表头（固定单元格与表头行）的上传模板。
+fn header_book(options: &ProtonOptions) -> Spreadsheet {
     let mut book = umya_spreadsheet::new_file();
+    options.template.render_header(
+        book.get_active_sheet_mut(),
+        &Vars {
+            title: TITLE,
+            station_code: &options.station_code,
+            a2_text: &options.a2_text,
+        },
+        &column_headers(options),
+    );
+    book
+}
+
+fn render_template(
+    data_rows: &[DataRow],
+    options: &ProtonOptions,
+    time_issues: &[TimeIssue],
+    qc_flags: &[QcFlag],
+) -> Spreadsheet {
+    let mut book = header_book(options);
     let sheet = book.get_active_sheet_mut();
 
-    let mut red_style = umya_spreadsheet::Style::default();
-    red_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .set_pattern_type(umya_spreadsheet::structs::PatternValues::Solid);
-    red_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .get_foreground_color_mut()
-        .set_argb("ffff0000");
-    red_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .get_background_color_mut()
-        .set_argb("ffff0000");
-
-    let mut orange_style = umya_spreadsheet::Style::default();
-    orange_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .set_pattern_type(umya_spreadsheet::structs::PatternValues::Solid);
-    orange_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .get_foreground_color_mut()
-        .set_argb("ffff9900");
-    orange_style
-        .get_fill_mut()
-        .get_pattern_fill_mut()
-        .get_background_color_mut()
-        .set_argb("ffff9900");
-
-    sheet
-        .get_cell_mut("A1")
-        .set_value("橙色和红色部分请勿改动！！！");
-    sheet.get_cell_mut("A1").set_style(red_style.clone());
-
-    let a2_text = load_a2_text()?;
-    sheet.get_cell_mut("A2").set_value(a2_text);
-    sheet.get_cell_mut("A2").set_style(red_style.clone());
-
-    let row3_headers = [
-        "离子色谱",
-        "SO₂",
-        "HNO₃",
-        "HNO₂",
-        "HCl",
-        "NH₃",
-        "NO₃⁻",
-        "SO₄²⁻",
-        "NH₄⁺",
-        "Cl⁻",
-        "K⁺",
-        "Na⁺",
-        "Mg²⁺",
-        "Ca²⁺",
-        "NO₂⁻",
-    ];
-    for (i, header) in row3_headers.iter().enumerate() {
-        let addr = cell_ref(i + 1, 3);
-        sheet.get_cell_mut(addr.as_str()).set_value(*header);
-        sheet
-            .get_cell_mut(addr.as_str())
-            .set_style(orange_style.clone());
-    }
-
-    let row4_values = [
-        "4401000010003",
-        "a21026",
-        "a21511",
-        "a21510",
-        "a21024",
-        "a21001",
-        "a06006",
-        "a06005",
-        "a06009",
-        "a06008",
-        "a06013",
-        "a06012",
-        "a06011",
-        "a06010",
-        "a06019",
-    ];
-    for (i, value) in row4_values.iter().enumerate() {
-        let addr = cell_ref(i + 1, 4);
-        sheet.get_cell_mut(addr.as_str()).set_value(*value);
-        sheet
-            .get_cell_mut(addr.as_str())
-            .set_style(orange_style.clone());
+    let template = &options.template;
+    let time_style = template.time_cell_style(false);
+    let flagged_time_style = template.time_cell_style(true);
+
+    let flagged_rows: HashSet<usize> = time_issues.iter().map(|issue| issue.row).collect();
+    let flagged_cells: HashMap<(usize, usize), &QcKind> = qc_flags
+        .iter()
+        .map(|flag| ((flag.index, flag.column), &flag.kind))
+        .collect();
+    let progress = progress::bar(data_rows.len(), "写入模板", "行");
+    for (row_idx, data_row) in data_rows.iter().enumerate() {
+        progress.inc(1);
+        let time_addr = template.time_address(row_idx);
+        let filled = data_row.source_row == 0;
+        let style = if filled || flagged_rows.contains(&data_row.source_row) {
+            &flagged_time_style
+        } else {
+            &time_style
+        };
+        let time_cell = sheet.get_cell_mut(time_addr.as_str());
+        time_cell.set_style(style.clone());
+        set_content(time_cell, time_content(&data_row.time, options));
+
+        for (col_idx, value) in data_row.values.iter().enumerate() {
+            let addr = template.value_address(row_idx, col_idx);
+            let cell = sheet.get_cell_mut(addr.as_str());
+            set_content(
+                cell,
+                value_content(value.as_deref(), col_idx, filled, options),
+            );
+            if let Some(kind) = flagged_cells.get(&(row_idx, col_idx)) {
+                set_solid_fill(cell.get_style_mut(), kind.fill());
+            }
+        }
     }
+    template.finish_sheet(sheet, column_headers(options).len(), data_rows.len());
 
-    let row5_values = [
-        "时间", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³",
-        "μg/m³", "μg/m³", "μg/m³", "μg/m³", "μg/m³",
-    ];
-    for (i, value) in row5_values.iter().enumerate() {
-        let addr = cell_ref(i + 1, 5);
-        sheet.get_cell_mut(addr.as_str()).set_value(*value);
-        sheet
-            .get_cell_mut(addr.as_str())
-            .set_style(orange_style.clone());
-    }
-
-    for (row_idx, (time, no3, so4, nh4, cl, k, na, mg, ca)) in data_rows.iter().enumerate() {
-        let row = row_idx + 6;
-
-        let time_addr = cell_ref(1, row);
-        sheet.get_cell_mut(time_addr.as_str()).set_value(time);
-        sheet
-            .get_cell_mut(time_addr.as_str())
-            .set_style(orange_style.clone());
-
-        let values = [no3, so4, nh4, cl, k, na, mg, ca];
-        for (col_idx, value) in values.iter().enumerate() {
-            let addr = cell_ref(col_idx + 7, row);
-            if let Some(v) = value {
-                sheet.get_cell_mut(addr.as_str()).set_value(v);
-            } else {
-                sheet.get_cell_mut(addr.as_str()).set_value("");
+    book
+}
+
+fn set_content(cell: &mut umya_spreadsheet::Cell, content: Content) {
+    match content {
+        Content::Number(number, format) => {
+            cell.set_value_number(number);
+            if let Some(format) = format {
+                cell.get_style_mut()
+                    .get_number_format_mut()
+                    .set_format_code(format);
             }
         }
+        Content::Time(time) => workbook::set_time_cell(cell, time),
+        Content::Text(text) => {
+            cell.set_value(text);
+        }
     }
+}
+
+/// 转换输出的数据表，只在用到时生成：流式写出时 `table` 为空，由尚未写出的数据行
+/// `pending` 生成，因此只写 xlsx 时不会多占一份内存。
+fn lazy_table<'a>(
+    table: Vec<Vec<String>>,
+    pending: &'a [DataRow],
+    missing_value: &'a str,
+) -> LazyCell<Vec<Vec<String>>, impl FnOnce() -> Vec<Vec<String>> + 'a> {
+    LazyCell::new(move || {
+        if table.is_empty() {
+            data_table(pending, missing_value)
+        } else {
+            table
+        }
+    })
+}
 
-    let output_path = processed_output_path(path);
-    umya_spreadsheet::writer::xlsx::write(&book, &output_path)
-        .with_context(|| format!("无法保存文件: {}", output_path.display()))?;
+fn data_table(data_rows: &[DataRow], missing_value: &str) -> Vec<Vec<String>> {
+    let mut table = vec![
+        std::iter::once(ROW5_VALUES[0])
+            .chain(ROW3_HEADERS[1..].iter().copied())
+            .map(str::to_string)
+            .collect::<Vec<_>>(),
+    ];
+    table.extend(data_rows.iter().map(|data_row| {
+        let mut row = vec![String::new(); ROW3_HEADERS.len()];
+        row[0] = data_row.time.clone();
+        for (i, value) in data_row.values.iter().enumerate() {
+            row[i + 1] = value.clone().unwrap_or_else(|| missing_value.to_string());
+        }
+        row
+    }));
+    table
+}
 
-    Ok(output_path)
+/// 把源数据转换为上传模板。缺少必需的列时返回错误；时间列不连续时在报告中列出问题。
+pub fn transform(source: &Source, options: &ProtonOptions) -> Result<Transformed> {
+    transform_with(source, options, Writer::Memory)
 }
 
-pub fn run(args: impl IntoIterator<Item = std::ffi::OsString>) -> Result<()> {
-    let mut args = args.into_iter();
-    let _exe = args.next();
+/// 同 [`transform`]；`writer` 为 [`Writer::Streaming`] 时工作簿中只有表头，数据行由
+/// [`Transformed::write_streaming`] 写出。
+pub fn transform_with(
+    source: &Source,
+    options: &ProtonOptions,
+    writer: Writer,
+) -> Result<Transformed> {
+    if writer == Writer::Streaming {
+        stream::check(options)?;
+    }
+    let mut extracted = extract_rows(source, options)?;
+    let source_rows = extracted.rows.len();
+    let mut duplicates = 0;
+    if let Some(policy) = options.dedupe {
+        let times = extracted.rows.iter().map(|row| row.time.as_str());
+        let dropped: HashSet<usize> = dedupe::discarded(times, policy)
+            .into_iter()
+            .map(|i| extracted.rows[i].source_row)
+            .collect();
+        extracted.changes.retain(|(row, _)| !dropped.contains(row));
+        (extracted.rows, duplicates) = dedupe::apply(extracted.rows, policy)?;
+    }
+    let mut transformed = build_template(extracted, source.sheet_name.clone(), options, writer)?;
+    transformed.report.source_rows = source_rows;
+    transformed.report.duplicates = duplicates;
+    Ok(transformed)
+}
 
-    let Some(input) = args.next() else {
-        println!("请提供文件名作为参数，例如：dtproton proton202552_20260105143932.xlsx");
-        return Ok(());
+/// 由读取的数据行生成上传模板：重采样、修约、检查时间列、补齐缺失时间点并做质量检查。
+fn build_template(
+    extracted: Extracted,
+    source_sheet: String,
+    options: &ProtonOptions,
+    writer: Writer,
+) -> Result<Transformed> {
+    let Extracted {
+        rows: data_rows,
+        changes,
+        corrected,
+        problems,
+        outside_period,
+    } = extracted;
+    let mut changes: Vec<CellChange> = changes.into_iter().map(|(_, change)| change).collect();
+    let source_rows = data_rows.len();
+    let below_limit = changes
+        .iter()
+        .filter(|change| change.rules[0] == "below_limit")
+        .count();
+    // 重采样后按重采样时段检查时间连续性
+    let (mut data_rows, mut time_issues, interval) = match options.resample {
+        Some(period) => {
+            let (rows, issues) =
+                resample::resample(data_rows, period, options.interval, options.min_capture)
+                    .map_err(|err| invalid!("{err}"))?;
+            (rows, issues, period)
+        }
+        None => (data_rows, Vec::new(), options.interval),
+    };
+    apply_precision(&mut data_rows, &options.decimals);
+    time_issues.extend(timeline::check(
+        data_rows
+            .iter()
+            .map(|data_row| (data_row.source_row, data_row.time.as_str())),
+        interval,
+    ));
+    let (data_rows, filled_rows) = match &options.fill_gaps {
+        Some(code) => fill_gaps(data_rows, &time_issues, interval, code),
+        None => (data_rows, 0),
+    };
+    let qc_flags = qc::check(&data_rows, options);
+    let mut workbook = match writer {
+        Writer::Memory => render_template(&data_rows, options, &time_issues, &qc_flags),
+        Writer::Streaming => header_book(options),
+    };
+    // 可疑值不修改，以输出模板中的单元格记入变更
+    let output_sheet = workbook
+        .get_sheet(&0)
+        .map(|sheet| sheet.get_name().to_string())
+        .unwrap_or_default();
+    changes.extend(qc_flags.iter().map(|flag| {
+        let value = data_rows[flag.index].values[flag.column]
+            .clone()
+            .unwrap_or_default();
+        CellChange {
+            sheet: output_sheet.clone(),
+            address: options.template.value_address(flag.index, flag.column),
+            before: value.clone(),
+            after: value,
+            red_fill: false,
+            rules: vec![flag.kind.rule().to_string()],
+        }
+    }));
+    let ion_balance = match options.chemistry {
+        Some(tolerance) => {
+            let balances = chemistry::compute(&data_rows, tolerance, &options.missing_codes());
+            chemistry::render(&mut workbook, &balances);
+            balances
+        }
+        None => Vec::new(),
     };
+    if !corrected.is_empty() {
+        correction::render(&mut workbook, &corrected)?;
+    }
+    if options.charts {
+        charts::render(&mut workbook, &data_rows, &options.missing_codes());
+    }
+    if options.error_sheet && !problems.is_empty() {
+        report::append_problem_sheet(&mut workbook, &problems);
+    }
+    // 当量比偏离的行以“离子平衡”工作表中的单元格记入变更
+    changes.extend(
+        ion_balance
+            .iter()
+            .enumerate()
+            .filter(|(_, balance)| balance.deviates)
+            .map(|(i, balance)| {
+                let ratio = balance
+                    .ratio
+                    .map(|ratio| format!("{ratio:.2}"))
+                    .unwrap_or_default();
+                CellChange {
+                    sheet: chemistry::SHEET_NAME.to_string(),
+                    address: chemistry::ratio_address(i),
+                    before: ratio.clone(),
+                    after: ratio,
+                    red_fill: false,
+                    rules: vec!["ion_balance".to_string()],
+                }
+            }),
+    );
+    let table = match writer {
+        Writer::Memory => data_table(&data_rows, &options.missing_value),
+        Writer::Streaming => Vec::new(),
+    };
+    let rows = data_rows.len();
+    Ok(Transformed {
+        workbook,
+        table,
+        pending: match writer {
+            Writer::Memory => Vec::new(),
+            Writer::Streaming => data_rows,
+        },
+        report: TransformReport {
+            source_sheet,
+            source_rows,
+            rows,
+            changes,
+            time_issues,
+            filled_rows,
+            qc_flags,
+            below_limit,
+            outside_period,
+            corrected: corrected.len(),
+            ion_balance,
+            problems,
+            ..Default::default()
+        },
+    })
+}
 
-    let input_path = PathBuf::from(input);
-    let out = process_excel(&input_path)?;
-    println!("文件已处理并保存为: {}", out.display());
-    Ok(())
+fn process_excel(
+    path: &Path,
+    args: &ProtonArgs,
+    options: &ProtonOptions,
+    global: &GlobalArgs,
+) -> Result<report::ProcessedFile> {
+    global.check_unprocessed(path)?;
+    let source = read_source_with(path, args.delimiter, args.encoding, options)?;
+    let mut options = options.clone();
+    if args.interactive {
+        prompt_missing_columns(
+            &source,
+            &mut options,
+            &mut io::stdin().lock(),
+            &mut io::stdout(),
+        )?;
+    }
+    let options = &options;
+    let Transformed {
+        workbook: mut book,
+        table,
+        report,
+        pending,
+    } = transform_with(&source, options, global.writer)?;
+    let table = lazy_table(table, &pending, &options.missing_value);
+
+    debug!(
+        "工作表 '{}': 读取到 {} 行数据",
+        report.source_sheet, report.rows
+    );
+
+    if !report.time_issues.is_empty() {
+        warn!(
+            "时间列检查发现 {} 个问题（输出中已标红）:",
+            report.time_issues.len()
+        );
+        for issue in &report.time_issues {
+            warn!("  {issue}");
+        }
+    }
+
+    if !report.qc_flags.is_empty() {
+        warn!(
+            "质量检查发现 {} 个可疑值（输出中已标色）:",
+            report.qc_flags.len()
+        );
+        for flag in &report.qc_flags {
+            warn!("  {flag}");
+        }
+    }
+
+    let deviations: Vec<_> = report
+        .ion_balance
+        .iter()
+        .filter(|balance| balance.deviates)
+        .collect();
+    let warnings = report.time_issues.len() + report.qc_flags.len() + deviations.len();
+    if !deviations.is_empty() {
+        warn!(
+            "离子平衡检查发现 {} 行阴阳离子当量比偏离（“{}”工作表中已标色）:",
+            deviations.len(),
+            chemistry::SHEET_NAME
+        );
+        for balance in deviations {
+            warn!("  {balance}");
+        }
+    }
+
+    if options.resample.is_some() {
+        info!(
+            "已将 {} 行源数据按时段平均为 {} 行",
+            report.source_rows,
+            report.rows - report.filled_rows
+        );
+    }
+    if report.outside_period > 0 {
+        info!("跳过了报告期以外的 {} 行", report.outside_period);
+    }
+    if report.duplicates > 0 {
+        info!("去掉了 {} 个重复的时间点", report.duplicates);
+    }
+    if report.filled_rows > 0 {
+        info!("已为缺失的时间点插入 {} 行", report.filled_rows);
+    }
+    if report.below_limit > 0 {
+        info!("低于检出限的值: {} 个", report.below_limit);
+    }
+    if report.corrected > 0 {
+        info!("已按零点/跨度校正 {} 个值", report.corrected);
+    }
+    report::warn_problems(&report.problems);
+
+    let output_path = global.workbook_output_path(path);
+    if global.dry_run {
+        info!(
+            "[试运行] 将输出 {} 行数据到: {}",
+            report.rows,
+            output_path.display()
+        );
+        report::print_planned_changes(&report.changes);
+        return Ok(report::ProcessedFile {
+            input: path.to_path_buf(),
+            output: output_path,
+            exports: Vec::new(),
+            rows: report.rows,
+            warnings,
+            changes: report.changes,
+            problems: report.problems,
+            skipped: false,
+        });
+    }
+
+    report::log_changes(&report.changes);
+    global.prepare_output(&output_path)?;
+    if !long::write_if_parquet(
+        global.format,
+        &output_path,
+        &options.station_code,
+        &table,
+        &options.missing_codes(),
+    )? {
+        workbook::mark_processed(&mut book, options);
+        match global.writer {
+            Writer::Memory => workbook::write(&book, &output_path, global.format)?,
+            Writer::Streaming => stream::write(
+                &book,
+                &pending,
+                &report,
+                options,
+                global.format,
+                &output_path,
+            )?,
+        }
+    }
+
+    let mut exports = Vec::new();
+    if let Some(delimiter) = global.csv_export() {
+        let csv_path = export::csv_path(&output_path, delimiter);
+        export::write_csv(&csv_path, &table, delimiter)?;
+        info!("数据表已导出为: {}", csv_path.display());
+        exports.push(csv_path);
+    }
+    #[cfg(feature = "plots")]
+    if global.plots {
+        let charts = crate::plot::write_charts(&output_path, &table, &options.missing_codes())?;
+        info!("已为 {} 个项目画出时间序列图", charts.len());
+        exports.extend(charts);
+    }
+
+    #[cfg(feature = "upload")]
+    global.upload.send(&output_path)?;
+    #[cfg(feature = "post")]
+    if global.post.url.is_some() {
+        let sheet = book
+            .get_sheet(&0)
+            .map(|sheet| sheet.get_name())
+            .unwrap_or_default();
+        let sheets = vec![crate::post::SheetRows::new(sheet, &table)];
+        global.post.send("proton", path, &output_path, sheets)?;
+    }
+    let codes: Vec<&str> = options.codes.iter().map(String::as_str).collect();
+    args.hj212.send(
+        &options.station_code,
+        &table,
+        &codes,
+        &options.missing_codes(),
+    )?;
+    #[cfg(feature = "sqlite")]
+    if let Some(database) = &args.to_sqlite {
+        let rows = long::measurements(&options.station_code, &table, &options.missing_codes());
+        let written = crate::sqlite::append(database, &rows)?;
+        info!("已写入 {written} 个数值到: {}", database.display());
+    }
+    #[cfg(feature = "postgres")]
+    if args.db.db_url.is_some() {
+        let rows = long::measurements(&options.station_code, &table, &options.missing_codes());
+        args.db.send(&rows)?;
+    }
+    global.archive.store(path)?;
+
+    Ok(report::ProcessedFile {
+        input: path.to_path_buf(),
+        output: output_path,
+        exports,
+        rows: report.rows,
+        warnings,
+        changes: report.changes,
+        problems: report.problems,
+        skipped: false,
+    })
+}
+
+/// 目录、通配符输入中收集的文件扩展名。
+pub(crate) const INPUT_EXTENSIONS: &[&str] = &["xlsx", "xls", "ods", "csv", "tsv"];
+
+/// `dttools proton` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct ProtonArgs {
+    /// 输入文件（xlsx/xls/ods/csv）、目录或通配符（如 exports/*.xlsx）
+    #[arg(value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    /// CSV 输入的分隔符，如 `,`、`;` 或 `tab`
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = cli::parse_delimiter)]
+    pub delimiter: u8,
+
+    /// CSV 输入的编码
+    #[arg(long, value_enum, default_value_t = CsvEncoding::Auto)]
+    pub encoding: CsvEncoding,
+
+    /// 站点配置文件（站点编码、因子编码、第 2 行说明）；默认按查找顺序找 proton_config.toml
+    /// 或 dttools.toml 的 [proton]
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// 站点编码，覆盖配置文件中的 station_code
+    #[arg(long, value_name = "CODE")]
+    pub station: Option<String>,
+
+    /// 数据时间间隔（如 1h、30m），用于检查时间列是否连续；覆盖配置文件中的 interval
+    #[arg(long, value_name = "DURATION", value_parser = timeline::parse_interval)]
+    pub interval: Option<TimeDelta>,
+
+    /// 为缺失的时间点插入数据行，浓度写为 CODE（默认 -999；`--fill-gaps=` 留空）
+    #[arg(
+        long,
+        value_name = "CODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "-999"
+    )]
+    pub fill_gaps: Option<String>,
+
+    /// 缺失的浓度（空单元格、非数值、缺少的列）写入的值，如 -999；`--missing-value=` 留空，
+    /// 覆盖配置文件中的 missing_value
+    #[arg(long, value_name = "CODE")]
+    pub missing_value: Option<String>,
+
+    /// 按时段（如 1h）对高频数据求平均；此时 --interval 为源数据的间隔（如 15m）
+    #[arg(long, value_name = "DURATION", value_parser = timeline::parse_interval)]
+    pub resample: Option<TimeDelta>,
+
+    /// 重采样时的最低数据捕获率（百分比，默认 75），有效数据不足时该时段留空
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub min_capture: Option<u32>,
+
+    /// 所有浓度列保留的小数位数，覆盖配置文件中的 decimals/precision
+    #[arg(long, value_name = "N")]
+    pub decimals: Option<u32>,
+
+    /// 站点时区（如 Asia/Shanghai、+08:00），带时区的时间换算为该时区；覆盖配置文件中的 timezone
+    #[arg(long, value_name = "TZ", value_parser = timezone::parse_timezone)]
+    pub timezone: Option<FixedOffset>,
+
+    /// 不带时区的时间所用的时区（如仪器按 UTC 记录时为 UTC）；覆盖配置文件中的 source_timezone
+    #[arg(long, value_name = "TZ", value_parser = timezone::parse_timezone)]
+    pub source_timezone: Option<FixedOffset>,
+
+    /// 校准时间表（TOML，见 calibration.example.toml），时段内的值按 --flagged 处理；
+    /// 覆盖配置文件中的 calibration
+    #[arg(long, value_name = "FILE")]
+    pub calibration: Option<PathBuf>,
+
+    /// 平移时间列（如 +1h 把时段起始时刻改为结束时刻）；覆盖配置文件中的 time_shift
+    #[arg(long, value_name = "DURATION", allow_hyphen_values = true, value_parser = timeline::parse_shift)]
+    pub time_shift: Option<TimeDelta>,
+
+    /// 报告期起点（如 2026-01-01T00:00 或 2026-01-01），只输出此时刻及之后的数据行；覆盖配置文件中的 from
+    #[arg(long, value_name = "TIME", value_parser = timeline::parse_period_start)]
+    pub from: Option<NaiveDateTime>,
+
+    /// 报告期终点（含，如 2026-01-31T23:00；只写日期时含当天全天），只输出此时刻及之前的数据行；覆盖配置文件中的 to
+    #[arg(long, value_name = "TIME", value_parser = timeline::parse_period_end)]
+    pub to: Option<NaiveDateTime>,
+
+    /// 源表的表头行（1 起计）；默认在前 20 行中找含有“时间”和至少 3 个离子表头的行
+    #[arg(long, value_name = "N")]
+    pub header_row: Option<usize>,
+
+    /// 读取的源工作表（默认为第一个工作表），可重复；多个时按时间合并，如阳离子、阴离子分表时
+    /// `--source-sheet 阳离子 --source-sheet 阴离子`；覆盖配置文件中的 sheets
+    #[arg(long, value_name = "NAME")]
+    pub source_sheet: Vec<String>,
+
+    /// 指定某列在源表中的位置（如 `NO₃⁻=D`、`时间=A`），可重复；覆盖表头匹配
+    #[arg(long = "map", value_name = "项目=列")]
+    pub map: Vec<String>,
+
+    /// 找不到必需的列时列出源表表头并提示选择所在的列，而不是直接报错
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// 源数据的小数点：point 为 1,234.5，comma 为 1.234,5；覆盖配置文件中的 decimal_mark
+    #[arg(long, value_enum, value_name = "MARK")]
+    pub decimal_mark: Option<DecimalMark>,
+
+    /// 带 (C)/(RM) 标记的值：drop 清空，flag 保留为 值#标记（如 1.6#C），invalid 写配置中的无效码
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub flagged: Option<FlaggedValues>,
+
+    /// 同一时间点有多行（如重测的小时）时：first 保留第一行，last 保留最后一行，mean 取平均，
+    /// error 报错；默认全部输出并在时间检查中标出；覆盖配置文件中的 dedupe
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub dedupe: Option<Dedupe>,
+
+    /// 低于检出限的值（如 <0.01、ND）：drop 清空，half 写检出限的一半，zero 写 0，
+    /// flag 写 检出限#标记码（如 0.01#DL）；覆盖配置文件中的 below_limit
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub below_limit: Option<BelowLimitValues>,
+
+    /// 计算阴阳离子平衡、NH₄⁺ 预测值与 SOR/NOR，写入“离子平衡”工作表；
+    /// 配置文件中没有 [chemistry] 时按默认偏差 0.2 标出当量比偏离的行
+    #[arg(long)]
+    pub chemistry: bool,
+
+    /// 在输出的“图表”工作表中为每个监测项目插入一张 Excel 折线图；覆盖配置文件中的 charts
+    #[arg(long)]
+    pub charts: bool,
+
+    /// 宽松模式：缺少必需的列时该列留空、无法解析时间的行跳过，其余数据照常输出，
+    /// 有跳过的问题时以退出码 4 结束
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// 把宽松模式下跳过的问题写入输出的“错误”工作表（隐含 --lenient）
+    #[arg(long)]
+    pub error_sheet: bool,
+
+    /// 把处理后的数据追加到 SQLite 数据库的 measurements 表（不存在时创建）
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE")]
+    pub to_sqlite: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub hj212: crate::hj212::Hj212Args,
+
+    #[cfg(feature = "postgres")]
+    #[command(flatten)]
+    pub db: crate::db::DbArgs,
+}
+
+impl ProtonArgs {
+    /// 读取站点配置，并用命令行参数覆盖其中的各项。
+    fn options(&self) -> Result<ProtonOptions> {
+        let mut options = ProtonOptions::discover(self.config.as_deref())?;
+        if let Some(station) = &self.station {
+            options.set_station_code(station)?;
+        }
+        if let Some(interval) = self.interval {
+            options.interval = interval;
+        }
+        if let Some(code) = &self.fill_gaps {
+            options.fill_gaps = Some(code.clone());
+        }
+        if let Some(code) = &self.missing_value {
+            options.missing_value = code.trim().to_string();
+        }
+        if let Some(period) = self.resample {
+            options.resample = Some(period);
+        }
+        if let Some(min_capture) = self.min_capture {
+            options.min_capture = min_capture;
+        }
+        if let Some(decimals) = self.decimals {
+            options.set_decimals(decimals)?;
+        }
+        if let Some(timezone) = self.timezone {
+            options.timezone = timezone;
+        }
+        if let Some(source) = self.source_timezone {
+            options.source_timezone = Some(source);
+        }
+        if let Some(shift) = self.time_shift {
+            options.time_shift = shift;
+        }
+        if let Some(path) = &self.calibration {
+            options.calibration = CalibrationSchedule::load(path)?;
+        }
+        if let Some(from) = self.from {
+            options.from = Some(from);
+        }
+        if let Some(to) = self.to {
+            options.to = Some(to);
+        }
+        if let (Some(from), Some(to)) = (options.from, options.to)
+            && from > to
+        {
+            return Err(invalid!("--from 不能晚于 --to"));
+        }
+        if let Some(row) = self.header_row {
+            options.set_header_row(row)?;
+        }
+        if !self.source_sheet.is_empty() {
+            options.sheets = self.source_sheet.clone();
+        }
+        for mapping in &self.map {
+            let (species, column) = mapping
+                .split_once('=')
+                .ok_or_else(|| invalid!("--map 的格式应为 项目=列，如 NO₃⁻=D: {mapping}"))?;
+            options.set_column(species, column)?;
+        }
+        if let Some(mark) = self.decimal_mark {
+            options.decimal_mark = mark;
+        }
+        if let Some(flagged) = self.flagged {
+            options.flagged = flagged;
+        }
+        if let Some(mode) = self.below_limit {
+            options.below_limit = mode;
+        }
+        if let Some(policy) = self.dedupe {
+            options.dedupe = Some(policy);
+        }
+        if self.chemistry && options.chemistry.is_none() {
+            options.chemistry = Some(chemistry::DEFAULT_TOLERANCE);
+        }
+        if self.charts {
+            options.charts = true;
+        }
+        if self.lenient || self.error_sheet {
+            options.lenient = true;
+        }
+        if self.error_sheet {
+            options.error_sheet = true;
+        }
+        Ok(options)
+    }
+}
+
+pub fn execute(args: &ProtonArgs, global: &GlobalArgs) -> Result<()> {
+    if args.inputs.is_empty() {
+        info!(
+            "请提供文件名、目录或通配符作为参数，例如：dtproton proton202552_20260105143932.xlsx"
+        );
+        return Ok(());
+    }
+
+    let options = args.options()?;
+    let inputs = batch::expand_inputs(&args.inputs, INPUT_EXTENSIONS)?;
+    global.check_inputs(&inputs)?;
+    if !inputs.batch {
+        let processed = process_excel(&inputs.files[0], args, &options, global)?;
+        if global.dry_run {
+            info!("试运行完成，未写入文件: {}", processed.output.display());
+        } else {
+            info!("文件已处理并保存为: {}", processed.output.display());
+        }
+        global.write_report("proton", std::slice::from_ref(&processed))?;
+        return report::partial(processed.problems.len());
+    }
+
+    let mut processed = Vec::new();
+    let mut state = global.resume_state("proton", &options, &inputs.files)?;
+    let result = batch::run_batch(&inputs.files, global.open_failures(), &mut state, |file| {
+        let file = process_excel(file, args, &options, global)?;
+        let output = file.output.clone();
+        processed.push(file);
+        Ok(output)
+    });
+    processed.extend(state.skipped_files());
+    global.write_report("proton", &processed)?;
+    global.write_manifest("proton", &processed)?;
+    result?;
+    report::partial(processed.iter().map(|file| file.problems.len()).sum())
+}
+
+/// 独立的 `dtproton` 程序入口，参数与 `dttools proton` 相同。
+#[derive(Debug, Parser)]
+#[command(name = "dtproton", version, about = "离子色谱数据转换为上传模板")]
+struct StandaloneCli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(flatten)]
+    args: ProtonArgs,
+}
+
+pub fn run(args: impl IntoIterator<Item = std::ffi::OsString>) -> Result<()> {
+    let mut cli: StandaloneCli = cli::parse(args);
+    crate::console::init(&mut cli.global);
+    let result = crate::logging::init(&cli.global)
+        .and_then(|()| cli::check_writer("proton", true, &cli.global))
+        .and_then(|()| execute(&cli.args, &cli.global));
+    cli.global.finish("proton", &result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use calamine::{ExcelDateTime, ExcelDateTimeType};
+    use tempfile::tempdir;
+
+    const HEADERS: [&str; 9] = [
+        "时间",
+        "NO₃⁻(μg/m³)",
+        "SO₄²⁻(μg/m³)",
+        "NH₄⁺(μg/m³)",
+        "Cl⁻(μg/m³)",
+        "K⁺(μg/m³)",
+        "Na⁺(μg/m³)",
+        "Mg²⁺(μg/m³)",
+        "Ca²⁺(μg/m³)",
+    ];
+
+    fn default_args() -> ProtonArgs {
+        StandaloneCli::parse_from(["dtproton"]).args
+    }
+
+    /// 写一个离子色谱导出样例：表头 + 给定的数据行。
+    fn write_input(path: &Path, rows: &[[&str; 9]]) -> Result<()> {
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        for (col, header) in HEADERS.iter().enumerate() {
+            sheet
+                .get_cell_mut(cell_ref(col + 1, 1).as_str())
+                .set_value(*header);
+        }
+        for (row, values) in rows.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                sheet
+                    .get_cell_mut(cell_ref(col + 1, row + 2).as_str())
+                    .set_value(*value);
+            }
+        }
+        umya_spreadsheet::writer::xlsx::write(&book, path)?;
+        Ok(())
+    }
+
+    fn sample_rows() -> Vec<[&'static str; 9]> {
+        vec![
+            [
+                "2026-01-05T01:00:00",
+                "1.5",
+                "2.5",
+                "3.5",
+                "0.1",
+                "0.2",
+                "0.3",
+                "0.4",
+                "0.5",
+            ],
+            [
+                "2026/01/05 02:00:00",
+                "1.6(C)",
+                "—",
+                "3.6",
+                "0.1",
+                "0.2",
+                "0.3",
+                "0.4",
+                "0.5",
+            ],
+        ]
+    }
+
+    #[test]
+    fn converts_native_excel_dates() {
+        let serial = 46_027.0 + 1.0 / 24.0;
+        let time = |value, is_1904| {
+            time_cell_text(Some(&Data::DateTime(ExcelDateTime::new(
+                value,
+                ExcelDateTimeType::DateTime,
+                is_1904,
+            ))))
+        };
+        assert_eq!(time(serial, false), "2026-01-05 01:00:00");
+        assert_eq!(time(serial - 1462.0, true), "2026-01-05 01:00:00");
+        assert_eq!(
+            parse_time_to_target_format(&time(serial, false), &ProtonOptions::default()).ok(),
+            Some("2026-01-05 01:00:00".to_string())
+        );
+        assert_eq!(time_cell_text(Some(&Data::Float(1.5))), "1.5");
+    }
+
+    #[test]
+    fn process_reads_native_excel_dates() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let mut book = umya_spreadsheet::reader::xlsx::read(&input)?;
+        let sheet = book.get_active_sheet_mut();
+        workbook::set_time_cell(sheet.get_cell_mut("A2"), "2026-01-05 01:00:00");
+        umya_spreadsheet::writer::xlsx::write(&book, &input)?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        assert!(matches!(source.range.get((1, 0)), Some(Data::DateTime(_))));
+        let out = transform(&source, &ProtonOptions::default())?;
+        assert!(out.report.time_issues.is_empty());
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(workbook::cell_text(sheet, 1, 6), "2026-01-05 01:00:00");
+        Ok(())
+    }
+
+    #[test]
+    fn skips_rows_outside_period() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+
+        let args = StandaloneCli::parse_from(["dtproton", "--from", "2026-01-05T02:00"]).args;
+        let out = transform(&source, &args.options()?)?;
+        assert_eq!((out.report.rows, out.report.outside_period), (1, 1));
+        assert_eq!(out.table[1][0], "2026-01-05 02:00:00");
+
+        let args = StandaloneCli::parse_from(["dtproton", "--to", "2026-01-05 01:00"]).args;
+        let out = transform(&source, &args.options()?)?;
+        assert_eq!(out.table[1][0], "2026-01-05 01:00:00");
+        assert_eq!(out.report.outside_period, 1);
+
+        let args =
+            StandaloneCli::parse_from(["dtproton", "--from", "2026-01-06", "--to", "2026-01-05"])
+                .args;
+        assert!(args.options().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn marks_values_in_calibration_windows() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let options = ProtonOptions {
+            calibration: CalibrationSchedule::from_toml_str(
+                "[[window]]\nfrom = \"2026-01-05T02:00\"\nto = \"2026-01-05T03:00\"\nspecies = [\"NH₄⁺\"]",
+            )?,
+            flagged: FlaggedValues::Flag,
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[1][8], "3.5");
+        assert_eq!(out.table[2][8], "3.6#C");
+        assert_eq!(out.table[2][9], "0.1");
+        let rules: Vec<_> = out
+            .report
+            .changes
+            .iter()
+            .filter(|change| change.rules[0] == "calibration")
+            .map(|change| (change.address.as_str(), change.after.as_str()))
+            .collect();
+        assert_eq!(rules, [("D3", "3.6#C")]);
+        Ok(())
+    }
+
+    #[test]
+    fn process_writes_template() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().join("out")),
+            ..Default::default()
+        };
+
+        let output =
+            process_excel(&input, &default_args(), &ProtonOptions::default(), &global)?.output;
+        let book = umya_spreadsheet::reader::xlsx::read(&output)?;
+        let sheet = book.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("F4"), "a21001");
+        assert_eq!(workbook::cell_text(sheet, 1, 6), "2026-01-05 01:00:00");
+        assert_eq!(sheet.get_value("G6"), "1.5");
+        // 时间写为日期时间单元格，浓度写为数字
+        let time = sheet.get_cell("A6").expect("time");
+        assert_eq!(time.get_data_type(), "n");
+        assert_eq!(
+            time.get_style()
+                .get_number_format()
+                .map(|f| f.get_format_code()),
+            Some(workbook::DATETIME_FORMAT)
+        );
+        assert_eq!(sheet.get_cell("G6").expect("value").get_data_type(), "n");
+        assert_eq!(workbook::cell_text(sheet, 1, 7), "2026-01-05 02:00:00");
+        assert_eq!(sheet.get_value("G7"), "");
+        assert_eq!(sheet.get_value("H7"), "");
+        assert_eq!(sheet.get_value("I7"), "3.6");
+        assert_eq!(sheet.get_value("O6"), "");
+        Ok(())
+    }
+
+    #[test]
+    fn process_streams_template() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        let mut rows = sample_rows();
+        rows[1][3] = "612";
+        write_input(&input, &rows)?;
+        let options = ProtonOptions::from_toml_str("[range]\n\"NH₄⁺\" = { min = 0, max = 500 }\n")?;
+        let output = |name: &str, writer| -> Result<PathBuf> {
+            let global = GlobalArgs {
+                output_dir: Some(dir.path().join(name)),
+                writer,
+                also_csv: true,
+                ..Default::default()
+            };
+            Ok(process_excel(&input, &default_args(), &options, &global)?.output)
+        };
+        let memory = umya_spreadsheet::reader::xlsx::read(output("memory", Writer::Memory)?)?;
+        let path = output("streaming", Writer::Streaming)?;
+        let csv =
+            |name: &str| fs::read_to_string(dir.path().join(name).join("processed_proton.csv"));
+        assert_eq!(csv("streaming")?, csv("memory")?);
+        assert!(csv("streaming")?.contains("612"));
+        let book = umya_spreadsheet::reader::xlsx::read(&path)?;
+        assert!(workbook::processed_marker(&path)?.is_some());
+
+        let names = |book: &Spreadsheet| {
+            book.get_sheet_collection()
+                .iter()
+                .map(|sheet| sheet.get_name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&book), names(&memory));
+        let sheet = book.get_sheet(&0).expect("sheet");
+        let expected = memory.get_sheet(&0).expect("sheet");
+        for address in ["A3", "F4", "A6", "G6", "I6", "A7", "G7", "I7", "O6"] {
+            assert_eq!(
+                sheet.get_value(address),
+                expected.get_value(address),
+                "{address}"
+            );
+        }
+        let time = sheet.get_cell("A6").expect("time");
+        assert_eq!(time.get_data_type(), "n");
+        assert_eq!(
+            time.get_style()
+                .get_number_format()
+                .map(|f| f.get_format_code()),
+            Some(workbook::DATETIME_FORMAT)
+        );
+        let fill = sheet
+            .get_style("I7")
+            .get_fill()
+            .and_then(|fill| fill.get_pattern_fill())
+            .and_then(|pattern| pattern.get_foreground_color())
+            .map(|color| color.get_argb().to_string());
+        assert_eq!(fill.as_deref(), Some("ffffff00"));
+
+        let charts = ProtonOptions::from_toml_str("charts = true\n")?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        assert!(transform_with(&source, &charts, Writer::Streaming).is_err());
+
+        // 数据起始行及以下的固定单元格无法流式写出
+        let mut notes = ProtonOptions::default();
+        notes.template.cells.push(crate::template::FixedCell {
+            address: "Q6".to_string(),
+            value: "备注".to_string(),
+            style: None,
+        });
+        let err = transform_with(&source, &notes, Writer::Streaming).unwrap_err();
+        assert!(err.to_string().contains("Q6"), "{err}");
+        notes.template.cells.last_mut().expect("cell").address = "Q5".to_string();
+        assert!(transform_with(&source, &notes, Writer::Streaming).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn standalone_rejects_streaming_other_formats() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let out = dir.path().join("out");
+        let args = |format: &str| {
+            [
+                "dtproton",
+                "--quiet",
+                "--writer",
+                "streaming",
+                "--format",
+                format,
+                "--output-dir",
+            ]
+            .map(std::ffi::OsString::from)
+            .into_iter()
+            .chain([out.clone().into_os_string(), input.clone().into_os_string()])
+        };
+        let err = run(args("ods")).unwrap_err();
+        assert!(err.to_string().contains("xlsx"), "{err}");
+        assert!(!out.exists());
+
+        run(args("xlsx"))?;
+        let output = out.join("processed_proton.xlsx");
+        assert!(umya_spreadsheet::reader::xlsx::read(&output).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn process_reads_gbk_csv() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        let mut text = HEADERS.join(";");
+        for row in sample_rows() {
+            text.push('\n');
+            text.push_str(&row.join(";"));
+        }
+        fs::write(&input, encoding_rs::GB18030.encode(&text).0)?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let args = ProtonArgs {
+            delimiter: b';',
+            ..default_args()
+        };
+
+        let output = process_excel(&input, &args, &ProtonOptions::default(), &global)?.output;
+        assert_eq!(output, dir.path().join("processed_proton.xlsx"));
+        let book = umya_spreadsheet::reader::xlsx::read(&output)?;
+        let sheet = book.get_sheet(&0).expect("sheet");
+        assert_eq!(workbook::cell_text(sheet, 1, 6), "2026-01-05 01:00:00");
+        assert_eq!(sheet.get_value("H6"), "2.5");
+        assert_eq!(sheet.get_value("I7"), "3.6");
+        Ok(())
+    }
+
+    #[test]
+    fn also_csv_exports_data_table() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().to_path_buf()),
+            also_csv: true,
+            ..Default::default()
+        };
+
+        process_excel(&input, &default_args(), &ProtonOptions::default(), &global)?;
+        let csv = fs::read_to_string(dir.path().join("processed_proton.csv"))?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("时间,SO₂,"));
+        assert_eq!(
+            lines[1],
+            "2026-01-05 01:00:00,,,,,,1.5,2.5,3.5,0.1,0.2,0.3,0.4,0.5,"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn transform_source_in_memory() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let options = ProtonOptions {
+            a2_text: "说明".to_string(),
+            station_code: "4403000000001".to_string(),
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+
+        assert_eq!(out.report.rows, 2);
+        let addresses: Vec<_> = out
+            .report
+            .changes
+            .iter()
+            .map(|c| c.address.as_str())
+            .collect();
+        assert_eq!(addresses, ["A2", "A3", "B3", "C3"]);
+        assert_eq!(out.table[2][6], "");
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("A2"), "说明");
+        assert_eq!(sheet.get_value("A4"), "4403000000001");
+        assert_eq!(sheet.get_value("O4"), "a06019");
+        Ok(())
+    }
+
+    #[test]
+    fn detects_header_below_title_rows() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        let header = HEADERS.join(",");
+        fs::write(
+            &input,
+            format!(
+                "离子色谱数据导出 2026年1月\n仪器: IC-2000,时间范围\n{header}\n\
+                 2026-01-05 01:00:00,1.5,2.5,3.5,0.4,0.3,0.2,0.1,0.6\n"
+            ),
+        )?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let out = transform(&source, &ProtonOptions::default())?;
+        assert_eq!(out.report.rows, 1);
+        assert_eq!(out.table[1][0], "2026-01-05 01:00:00");
+        assert_eq!(out.table[1][6], "1.5");
+
+        // 手动指定表头行
+        let mut options = ProtonOptions::default();
+        options.set_header_row(2)?;
+        assert!(transform(&source, &options).is_err());
+        options.set_header_row(3)?;
+        assert_eq!(transform(&source, &options)?.report.rows, 1);
+        assert!(options.set_header_row(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn gas_phase_columns_when_present() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        let header = HEADERS.join(",");
+        fs::write(
+            &input,
+            format!(
+                "{header},SO₂(μg/m³),NH₃(μg/m³),NO₂⁻(μg/m³)\n\
+                 2026-01-05 01:00:00,1.5,2.5,3.5,0.4,0.3,0.2,0.1,0.6,4.2,(C),0.05\n"
+            ),
+        )?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let out = transform(&source, &ProtonOptions::default())?;
+
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("B6"), "4.2");
+        assert_eq!(sheet.get_value("C6"), "");
+        assert_eq!(sheet.get_value("F6"), "");
+        assert_eq!(sheet.get_value("G6"), "1.5");
+        assert_eq!(sheet.get_value("O6"), "0.05");
+        assert_eq!(out.table[1][1], "4.2");
+        assert_eq!(out.table[1][14], "0.05");
+        assert_eq!(out.table[1][6], "1.5");
+        let addresses: Vec<_> = out
+            .report
+            .changes
+            .iter()
+            .map(|c| c.address.as_str())
+            .collect();
+        assert_eq!(addresses, ["K2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn flags_time_gaps_in_red() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        let mut rows = sample_rows();
+        rows.push(rows[0]);
+        rows[2][0] = "2026-01-05 05:00:00";
+        write_input(&input, &rows)?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let out = transform(&source, &ProtonOptions::default())?;
+        assert_eq!(
+            out.report.time_issues,
+            [TimeIssue {
+                row: 4,
+                time: "2026-01-05 05:00:00".to_string(),
+                kind: TimeIssueKind::Gap {
+                    previous: "2026-01-05 02:00:00".to_string(),
+                    missing: 2,
+                },
+            }]
+        );
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        let fill = |addr: &str| {
+            sheet
+                .get_style(addr)
+                .get_fill()
+                .and_then(|fill| fill.get_pattern_fill())
+                .and_then(|pattern| pattern.get_foreground_color())
+                .map(|color| color.get_argb().to_string())
+        };
+        assert_eq!(fill("A7").as_deref(), Some("ffff9900"));
+        assert_eq!(fill("A8").as_deref(), Some("ffff0000"));
+
+        let options = ProtonOptions {
+            fill_gaps: Some("-999".to_string()),
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        assert_eq!(out.report.filled_rows, 2);
+        assert_eq!(out.report.rows, 5);
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(workbook::cell_text(sheet, 1, 8), "2026-01-05 03:00:00");
+        assert_eq!(sheet.get_value("G8"), "-999");
+        assert_eq!(sheet.get_value("O9"), "-999");
+        assert_eq!(workbook::cell_text(sheet, 1, 10), "2026-01-05 05:00:00");
+        assert_eq!(sheet.get_value("G10"), "1.5");
+        assert_eq!(out.table[3][0], "2026-01-05 03:00:00");
+
+        let options = ProtonOptions {
+            interval: TimeDelta::hours(3),
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        assert_eq!(out.report.time_issues.len(), 1);
+        assert!(matches!(
+            out.report.time_issues[0].kind,
+            TimeIssueKind::Irregular { .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn rounds_to_configured_decimals() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        let mut rows = sample_rows();
+        rows[0][1] = "1.23456";
+        rows[0][2] = "-0.0001";
+        write_input(&input, &rows)?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let mut options = ProtonOptions::default();
+        options.set_decimals(3)?;
+        options.decimals[7] = Some(1);
+        let out = transform(&source, &options)?;
+
+        assert_eq!(out.table[1][6], "1.235");
+        assert_eq!(out.table[1][7], "0.000");
+        assert_eq!(out.table[1][8], "3.5");
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value_number("G6"), Some(1.235));
+        assert_eq!(
+            sheet
+                .get_style("G6")
+                .get_number_format()
+                .map(|f| f.get_format_code()),
+            Some("0.000")
+        );
+        assert!(options.set_decimals(11).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn flags_out_of_range_values() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        let mut rows = sample_rows();
+        rows[1][3] = "612";
+        write_input(&input, &rows)?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let options = ProtonOptions::from_toml_str("[range]\n\"NH₄⁺\" = { min = 0, max = 500 }\n")?;
+        let out = transform(&source, &options)?;
+
+        assert_eq!(out.report.qc_flags.len(), 1);
+        let change = out.report.changes.last().expect("qc change");
+        assert_eq!(change.address, "I7");
+        assert_eq!(change.after, "612");
+        assert_eq!(change.rules, ["range"]);
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("I7"), "612");
+        let fill = sheet
+            .get_style("I7")
+            .get_fill()
+            .and_then(|fill| fill.get_pattern_fill())
+            .and_then(|pattern| pattern.get_foreground_color())
+            .map(|color| color.get_argb().to_string());
+        assert_eq!(fill.as_deref(), Some("ffffff00"));
+        Ok(())
+    }
+
+    #[test]
+    fn writes_ion_balance_sheet() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let options = ProtonOptions::from_toml_str("[chemistry]\ntolerance = 0.3\n")?;
+        let out = transform(&source, &options)?;
+
+        let balances = &out.report.ion_balance;
+        assert_eq!(balances.len(), 2);
+        // NH₄⁺ 远多于阴离子
+        assert!(balances[0].deviates);
+        assert_eq!(balances[0].nor, None);
+        // 第二行的 NO₃⁻ 带标记已清空
+        assert_eq!(balances[1].ratio, None);
+        let change = out.report.changes.last().expect("ion balance change");
+        assert_eq!(change.sheet, "离子平衡");
+        assert_eq!(change.address, "D2");
+        assert_eq!(change.rules, ["ion_balance"]);
+        let sheet = out.workbook.get_sheet_by_name("离子平衡").expect("sheet");
+        assert_eq!(workbook::cell_text(sheet, 1, 2), "2026-01-05 01:00:00");
+        assert_eq!(sheet.get_value("D3"), "");
+        Ok(())
+    }
+
+    #[test]
+    fn adds_chart_sheet_when_enabled() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        let out = transform(&source, &ProtonOptions::default())?;
+        assert!(out.workbook.get_sheet_by_name(charts::SHEET_NAME).is_none());
+
+        let options = ProtonOptions::from_toml_str("charts = true\n")?;
+        let out = transform(&source, &options)?;
+        let sheet = out
+            .workbook
+            .get_sheet_by_name(charts::SHEET_NAME)
+            .expect("chart sheet");
+        assert_eq!(sheet.get_value("B1"), "NO₃⁻");
+        assert!(!sheet.get_chart_collection().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_flagged_values_when_asked() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        let mut rows = sample_rows();
+        rows[0][3] = "3.54(RM)";
+        write_input(&input, &rows)?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+
+        let mut options = ProtonOptions {
+            flagged: FlaggedValues::Flag,
+            ..Default::default()
+        };
+        options.set_decimals(1)?;
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[2][6], "1.6#C");
+        assert_eq!(out.table[1][8], "3.5#M");
+        let change = &out.report.changes[1];
+        assert_eq!(
+            (change.after.as_str(), change.rules[0].as_str()),
+            ("3.54#M", "flagged")
+        );
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("G7"), "1.6#C");
+
+        let options = ProtonOptions {
+            flagged: FlaggedValues::Invalid,
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[2][6], "-999");
+        assert_eq!(out.table[1][8], "-999");
+        // 长表（sqlite、parquet、postgres）不写入无效码
+        let rows = long::measurements("s", &out.table, &options.missing_codes());
+        assert!(rows.iter().all(|row| row.value != -999.0));
+        assert!(rows.iter().any(|row| row.analyte.starts_with("SO₄²⁻")));
+        Ok(())
+    }
+
+    #[test]
+    fn normalizes_locale_numbers() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        let mut rows = sample_rows();
+        rows[0][1] = "0,5";
+        rows[0][2] = "1,234.5";
+        rows[1][1] = "+1.5E-1";
+        let mut text = HEADERS.join(";");
+        for row in rows {
+            text.push('\n');
+            text.push_str(&row.join(";"));
+        }
+        fs::write(&input, text)?;
+        let source = read_source(&input, b';', CsvEncoding::Auto)?;
+
+        let out = transform(&source, &ProtonOptions::default())?;
+        assert_eq!(out.table[1][6], "");
+        assert_eq!(out.table[1][7], "1234.5");
+        assert_eq!(out.table[2][6], "1.5e-1");
+        let changes: Vec<(&str, &str, &str)> = out
+            .report
+            .changes
+            .iter()
+            .filter(|change| change.rules[0] != "time_format")
+            .map(|change| {
+                (
+                    change.before.as_str(),
+                    change.after.as_str(),
+                    change.rules[0].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes[..3],
+            [
+                ("0,5", "", "non_numeric"),
+                ("1,234.5", "1234.5", "number_format"),
+                ("+1.5E-1", "1.5e-1", "number_format"),
+            ]
+        );
+
+        let options = ProtonOptions {
+            decimal_mark: DecimalMark::Comma,
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[1][6], "0.5");
+        assert_eq!(out.table[1][7], "");
+        Ok(())
+    }
+
+    #[test]
+    fn substitutes_below_limit_values() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        let mut rows = sample_rows();
+        rows[0][1] = "<0.02";
+        rows[0][4] = "ND";
+        rows[1][1] = "n.d.";
+        write_input(&input, &rows)?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+
+        let out = transform(&source, &ProtonOptions::default())?;
+        assert_eq!(out.report.below_limit, 3);
+        assert_eq!(out.table[1][6], "");
+
+        let mut options = ProtonOptions {
+            below_limit: BelowLimitValues::Half,
+            ..Default::default()
+        };
+        options.detection_limit[8] = Some(0.01);
+        // K⁺ 的实测值 0.2 低于检出限
+        options.detection_limit[9] = Some(0.25);
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[1][6], "0.01");
+        assert_eq!(out.table[1][9], "0.005");
+        assert_eq!(out.table[1][10], "0.125");
+        assert_eq!(out.report.below_limit, 5);
+        // NO₃⁻ 没有配置检出限，ND 清空
+        assert_eq!(out.table[2][6], "");
+        let change = out
+            .report
+            .changes
+            .iter()
+            .find(|change| change.before == "<0.02")
+            .expect("change");
+        assert_eq!(
+            (change.after.as_str(), change.rules[0].as_str()),
+            ("0.01", "below_limit")
+        );
+
+        // drop 不清空实测值
+        options.below_limit = BelowLimitValues::Drop;
+        assert_eq!(transform(&source, &options)?.table[1][10], "0.2");
+
+        let options = ProtonOptions {
+            below_limit: BelowLimitValues::Flag,
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[1][6], "0.02#DL");
+        Ok(())
+    }
+
+    #[test]
+    fn corrects_before_detection_limit() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        let mut rows = sample_rows();
+        rows[0][1] = "<0.02";
+        write_input(&input, &rows)?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+
+        let mut options = ProtonOptions {
+            below_limit: BelowLimitValues::Half,
+            ..Default::default()
+        };
+        options.detection_limit[8] = Some(0.01);
+        options.detection_limit[9] = Some(0.25);
+        let correction = Correction {
+            slope: 0.5,
+            offset: 0.0,
+        };
+        options.correction[8] = Some(correction);
+        options.correction[9] = Some(correction);
+        let out = transform(&source, &options)?;
+        // 替换低于检出限的值后不再校正
+        assert_eq!(out.table[1][6], "0.01");
+        // K⁺ 的实测值 0.2 校正为 0.4 后不低于检出限
+        assert_eq!(out.table[1][10], "0.4");
+        assert_eq!(out.report.below_limit, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn writes_configured_missing_value() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+
+        let options = ProtonOptions {
+            missing_value: "-999".to_string(),
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        // 空单元格、非数值与源表中没有的列一致
+        assert_eq!(out.table[2][6], "-999");
+        assert_eq!(out.table[2][7], "-999");
+        assert_eq!(out.table[1][14], "-999");
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value_number("G7"), Some(-999.0));
+        assert_eq!(sheet.get_value("O6"), "-999");
+        Ok(())
+    }
+
+    #[test]
+    fn converts_times_to_station_timezone() -> Result<()> {
+        let options = ProtonOptions::default();
+        assert_eq!(
+            parse_time_to_target_format("2026-01-05T17:00:00Z", &options)?,
+            "2026-01-06 01:00:00"
+        );
+        assert_eq!(
+            parse_time_to_target_format("2026-01-05T01:00:00+08:00", &options)?,
+            "2026-01-05 01:00:00"
+        );
+        assert_eq!(
+            parse_time_to_target_format("2026-01-05 01:00:00", &options)?,
+            "2026-01-05 01:00:00"
+        );
+
+        let options = ProtonOptions::from_toml_str("source_timezone = \"UTC\"\n")?;
+        assert_eq!(
+            parse_time_to_target_format("2026/01/05 16:00:00", &options)?,
+            "2026-01-06 00:00:00"
+        );
+
+        let options = ProtonOptions {
+            time_shift: TimeDelta::hours(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_time_to_target_format("2026-01-05 23:00:00", &options)?,
+            "2026-01-06 00:00:00"
+        );
+        assert_eq!(
+            parse_time_to_target_format("2026-01-05T15:00:00Z", &options)?,
+            "2026-01-06 00:00:00"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn matches_headers_across_firmware_versions() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        fs::write(
+            &input,
+            "Time,NO3-(ug/m3),SO4 2- (ug/m3),NH4+(ug/m3),Cl-(ug/m3),K+(ug/m3),\
+             Na+(ug/m3),Mg2+(ug/m3),Ca2+(ug/m3),Ammonia\n\
+             2026-01-05 01:00:00,1.5,2.5,3.5,0.4,0.3,0.2,0.1,0.6,4.2\n",
+        )?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        assert!(transform(&source, &ProtonOptions::default()).is_err());
+
+        let options = ProtonOptions::from_toml_str(
+            "[aliases]\n\"时间\" = [\"Time\"]\n\"NH₃\" = [\"Ammonia\"]\n",
+        )?;
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table[1][0], "2026-01-05 01:00:00");
+        assert_eq!(out.table[1][5], "4.2");
+        assert_eq!(out.table[1][7], "2.5");
+        assert_eq!(out.table[1][13], "0.6");
+        Ok(())
+    }
+
+    #[test]
+    fn maps_and_prompts_for_missing_columns() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let mut source = read_source(&input, b',', CsvEncoding::Auto)?;
+        // 把 NO₃⁻ 的表头改成拼写错误
+        source
+            .range
+            .set_value((0, 1), Data::String("NO3 (ug/m)".to_string()));
+        assert!(transform(&source, &ProtonOptions::default()).is_err());
+
+        let mut options = ProtonOptions::default();
+        options.set_column("NO3-", "b")?;
+        assert_eq!(transform(&source, &options)?.table[1][6], "1.5");
+        assert!(options.set_column("O₃", "B").is_err());
+
+        let mut options = ProtonOptions::default();
+        let mut output = Vec::new();
+        prompt_missing_columns(&source, &mut options, &mut "Z\nB\n".as_bytes(), &mut output)?;
+        assert_eq!(options.columns[5], Some(1));
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("  B: NO3 (ug/m)"));
+        assert!(output.contains("无效的列: Z"));
+
+        let mut options = ProtonOptions::default();
+        let err =
+            prompt_missing_columns(&source, &mut options, &mut "\n".as_bytes(), &mut io::sink())
+                .unwrap_err();
+        assert!(err.to_string().contains("NO₃⁻"));
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_writes_nothing() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.xlsx");
+        write_input(&input, &sample_rows())?;
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().join("out")),
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let output =
+            process_excel(&input, &default_args(), &ProtonOptions::default(), &global)?.output;
+        assert!(!output.exists());
+        assert!(!dir.path().join("out").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn lenient_mode_skips_problems() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        // 缺少 Ca²⁺ 列，第 3 行的时间无法解析
+        let mut text = HEADERS[..8].join(",");
+        text.push_str("\n2026-01-05 01:00:00,1.5,2.5,3.5,0.1,0.2,0.3,0.4");
+        text.push_str("\n05.01.2026 02:00,1.6,2.6,3.6,0.1,0.2,0.3,0.4");
+        text.push_str("\n2026-01-05 03:00:00,1.7,2.7,3.7,0.1,0.2,0.3,0.4\n");
+        fs::write(&input, text)?;
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+
+        let err = transform(&source, &ProtonOptions::default()).unwrap_err();
+        assert!(
+            matches!(err.kind(), DtToolsError::MissingColumn { name } if name == "Ca²⁺(μg/m³)")
+        );
+
+        let options = ProtonOptions {
+            lenient: true,
+            error_sheet: true,
+            ..Default::default()
+        };
+        let out = transform(&source, &options)?;
+        assert_eq!(out.report.rows, 2);
+        let problems: Vec<_> = out
+            .report
+            .problems
+            .iter()
+            .map(|problem| (problem.row, problem.column.as_str()))
+            .collect();
+        assert_eq!(problems, [(0, "Ca²⁺(μg/m³)"), (3, "A3")]);
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(workbook::cell_text(sheet, 1, 7), "2026-01-05 03:00:00");
+        assert_eq!(sheet.get_value("N7"), "");
+        let errors = out
+            .workbook
+            .get_sheet_by_name(report::PROBLEM_SHEET)
+            .expect("problem sheet");
+        assert_eq!(errors.get_value("B3"), "3");
+        assert_eq!(errors.get_value("D3"), "05.01.2026 02:00");
+
+        let args = ProtonArgs {
+            inputs: vec![input],
+            lenient: true,
+            ..default_args()
+        };
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().join("out")),
+            ..Default::default()
+        };
+        let err = execute(&args, &global).unwrap_err();
+        assert!(matches!(err, DtToolsError::Partial { problems: 2 }));
+        assert_eq!(err.exit_code(), crate::error::PARTIAL_EXIT_CODE);
+        assert!(dir.path().join("out/processed_proton.xlsx").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn joins_source_sheets_by_time() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("ions.xlsx");
+        let mut book = umya_spreadsheet::new_file_empty_worksheet();
+        let cations: [&[&str]; 4] = [
+            &[
+                "时间", HEADERS[3], HEADERS[5], HEADERS[6], HEADERS[7], HEADERS[8],
+            ],
+            &["2026-01-05 01:00:00", "1", "2", "3", "4", "5"],
+            &["2026-01-05 00:00:00", "6", "7", "8", "9", "10"],
+            &["2026-01-05 02:00:00", "11", "12", "13", "14", "15"],
+        ];
+        let anions: [&[&str]; 3] = [
+            &["时间", HEADERS[1], HEADERS[2], HEADERS[4]],
+            &["2026/01/05 00:00:00", "20", "21", "22"],
+            &["2026/01/05 01:00:00", "23", "24", "25"],
+        ];
+        for (name, rows) in [("阳离子", &cations[..]), ("阴离子", &anions[..])] {
+            let sheet = book.new_sheet(name).expect("new sheet");
+            for (row, values) in rows.iter().enumerate() {
+                for (col, value) in values.iter().enumerate() {
+                    sheet
+                        .get_cell_mut(cell_ref(col + 1, row + 1).as_str())
+                        .set_value(*value);
+                }
+            }
+        }
+        umya_spreadsheet::writer::xlsx::write(&book, &input)?;
+
+        let options = ProtonOptions {
+            sheets: vec!["阳离子".to_string(), "阴离子".to_string()],
+            ..Default::default()
+        };
+        let source = read_source_with(&input, b',', CsvEncoding::Auto, &options)?;
+        assert_eq!(source.sheet_name, "阳离子+阴离子");
+        assert_eq!(source.range.get_size(), (4, 9));
+        let text = |row: usize, col: usize| time_cell_text(source.range.get((row, col)));
+        assert_eq!(text(0, 6), HEADERS[1]);
+        assert_eq!(text(1, 0), "2026-01-05 00:00:00");
+        assert_eq!(
+            (text(1, 1), text(1, 6)),
+            ("6".to_string(), "20".to_string())
+        );
+        assert_eq!(
+            (text(2, 5), text(2, 8)),
+            ("5".to_string(), "25".to_string())
+        );
+        assert_eq!((text(3, 1), text(3, 6)), ("11".to_string(), String::new()));
+
+        let rows = extract_rows(&source, &options)?;
+        assert_eq!(rows.rows.len(), 3);
+
+        let options = ProtonOptions {
+            sheets: vec!["阳离子".to_string(), "缺失".to_string()],
+            ..Default::default()
+        };
+        assert!(read_source_with(&input, b',', CsvEncoding::Auto, &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn joined_sheets_keep_header_row() -> Result<()> {
+        let sheet = |name: &str, rows: &[&[&str]]| {
+            let mut range = Range::new((0, 0), (rows.len() as u32 - 1, 5));
+            for (row, values) in rows.iter().enumerate() {
+                for (col, value) in values.iter().enumerate() {
+                    range.set_value((row as u32, col as u32), Data::String(value.to_string()));
+                }
+            }
+            Source {
+                sheet_name: name.to_string(),
+                range,
+            }
+        };
+        let cations = sheet(
+            "阳离子",
+            &[
+                &["阳离子导出"],
+                &[
+                    "时间", HEADERS[3], HEADERS[5], HEADERS[6], HEADERS[7], HEADERS[8],
+                ],
+                &["2026-01-05 00:00:00", "1", "2", "3", "4", "5"],
+                &["2026-01-05 00:00:00", "6", "7", "8", "9", "10"],
+            ],
+        );
+        let anions = sheet(
+            "阴离子",
+            &[
+                &["阴离子导出"],
+                &["时间", HEADERS[1], HEADERS[2], HEADERS[4]],
+                &["2026-01-05 00:00:00", "20", "21", "22"],
+            ],
+        );
+        let options = ProtonOptions {
+            header_row: Some(1),
+            ..Default::default()
+        };
+        let source = join_sheets(&[cations, anions], &options)?;
+        assert_eq!(source.range.get_size(), (3, 9));
+        assert_eq!(time_cell_text(source.range.get((1, 0))), "时间");
+        let rows = extract_rows(&source, &options)?;
+        // 重复的时间点合并为一行，取第一个值
+        assert_eq!(rows.rows.len(), 1);
+        assert_eq!(rows.rows[0].values[7].as_deref(), Some("1"));
+        Ok(())
+    }
+
+    #[test]
+    fn unpivots_repeated_header_blocks() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        fs::write(
+            &input,
+            "离子色谱导出,,,,,,,,,,\n\
+             时间,NO3-(ug/m3),SO4 2- (ug/m3),NH4+(ug/m3),Cl-(ug/m3),\
+             Time,K+(ug/m3),Na+(ug/m3),Mg2+(ug/m3),Ca2+(ug/m3),NO3-(ug/m3)\n\
+             2026-01-05 01:00:00,1.5,2.5,3.5,0.4,2026/01/05 00:00:00,0.3,0.2,0.1,0.6,9\n\
+             2026-01-05 00:00:00,,2.0,3.0,0.5,2026/01/05 01:00:00,0.7,0.8,0.9,1.1,\n",
+        )?;
+        let options = ProtonOptions::from_toml_str("[aliases]\n\"时间\" = [\"Time\"]\n")?;
+        let source = read_source_with(&input, b',', CsvEncoding::Auto, &options)?;
+        assert_eq!(source.sheet_name, "proton");
+        assert_eq!(source.range.get_size(), (4, 9));
+        assert_eq!(time_cell_text(source.range.get((0, 0))), "");
+        assert_eq!(time_cell_text(source.range.get((1, 5))), "K+(ug/m3)");
+
+        let out = transform(&source, &options)?;
+        assert_eq!(out.table.len(), 3);
+        assert_eq!(out.table[1][0], "2026-01-05 00:00:00");
+        assert_eq!(out.table[1][6], "9");
+        assert_eq!(out.table[1][13], "0.6");
+        assert_eq!(out.table[2][6], "1.5");
+        assert_eq!(out.table[2][7], "2.5");
+        assert_eq!(out.table[2][13], "1.1");
+
+        // 只有一个时间列时原样返回
+        let source = read_source(&input, b',', CsvEncoding::Auto)?;
+        assert_eq!(
+            unpivot_blocks(source.clone(), &ProtonOptions::default())?
+                .range
+                .get_size(),
+            source.range.get_size()
+        );
+        Ok(())
+    }
 }
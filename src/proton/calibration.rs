@@ -0,0 +1,153 @@
+//! 校准时间表：仪器导出中并不总带 (C) 标记，按时间表把校准时段内的值视为校准数据。
+//! 格式见 `calibration.example.toml`：
+//!
+//! ```toml
+//! [[window]]
+//! from = "2026-01-05T08:00"   # 起点（含）；只写日期时为当天 0 时
+//! to = "2026-01-05T10:00"     # 终点（含）；只写日期时含当天全天
+//! species = ["NO₃⁻", "SO₄²⁻"] # 校准的监测项目，键为第 3 行表头（可选，默认全部）
+//! note = "多点校准"            # 备注（可选）
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use super::config::species_index;
+use super::timeline::{parse_period_end, parse_period_start};
+use crate::error::{Context, Result, config_error};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    #[serde(default)]
+    window: Vec<WindowEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WindowEntry {
+    from: String,
+    to: String,
+    #[serde(default)]
+    species: Vec<String>,
+    note: Option<String>,
+}
+
+/// 一个校准时段。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalibrationWindow {
+    pub from: NaiveDateTime,
+    /// 终点（含）
+    pub to: NaiveDateTime,
+    /// 校准的监测项目在第 3 行表头（B 列起）中的序号；为空表示全部
+    pub species: Vec<usize>,
+    pub note: Option<String>,
+}
+
+/// 校准时间表；时间与输出的时间列一致（时区换算与平移后）。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CalibrationSchedule {
+    pub windows: Vec<CalibrationWindow>,
+}
+
+impl CalibrationSchedule {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("无法读取校准时间表: {}", path.display()))?;
+        Self::from_toml_str(&content).with_context(|| format!("校准时间表: {}", path.display()))
+    }
+
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let file: ScheduleFile = toml::from_str(content).context("校准时间表格式错误")?;
+        let windows = file
+            .window
+            .into_iter()
+            .map(|entry| {
+                let from = parse_period_start(&entry.from).map_err(|err| config_error!("{err}"))?;
+                let to = parse_period_end(&entry.to).map_err(|err| config_error!("{err}"))?;
+                if from > to {
+                    return Err(config_error!(
+                        "[[window]] 的 from 不能晚于 to: {} ~ {}",
+                        entry.from,
+                        entry.to
+                    ));
+                }
+                let species = entry
+                    .species
+                    .iter()
+                    .map(|species| species_index("window", species))
+                    .collect::<Result<_>>()?;
+                Ok(CalibrationWindow {
+                    from,
+                    to,
+                    species,
+                    note: entry.note,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { windows })
+    }
+
+    /// `time` 时第 `species` 个监测项目是否处于校准时段。
+    pub fn covers(&self, time: NaiveDateTime, species: usize) -> bool {
+        self.windows.iter().any(|window| {
+            (window.from..=window.to).contains(&time)
+                && (window.species.is_empty() || window.species.contains(&species))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proton::timeline::TIME_FORMAT;
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, TIME_FORMAT).expect("valid time")
+    }
+
+    #[test]
+    fn parses_windows_per_species() -> Result<()> {
+        let schedule = CalibrationSchedule::from_toml_str(
+            r#"
+            [[window]]
+            from = "2026-01-05T08:00"
+            to = "2026-01-05T10:00"
+            species = ["NO3-", "SO₄²⁻"]
+            note = "多点校准"
+
+            [[window]]
+            from = "2026-01-07"
+            to = "2026-01-07"
+            "#,
+        )?;
+        assert_eq!(schedule.windows.len(), 2);
+        // 序号为 B 列起：NO₃⁻ 为 5，NH₄⁺ 为 7
+        assert!(schedule.covers(time("2026-01-05 10:00:00"), 5));
+        assert!(!schedule.covers(time("2026-01-05 10:00:00"), 7));
+        assert!(!schedule.covers(time("2026-01-05 11:00:00"), 5));
+        assert!(schedule.covers(time("2026-01-07 23:00:00"), 7));
+
+        assert!(
+            CalibrationSchedule::from_toml_str("[[window]]\nfrom = \"x\"\nto = \"x\"").is_err()
+        );
+        let err = CalibrationSchedule::from_toml_str(
+            "[[window]]\nfrom = \"2026-01-05\"\nto = \"2026-01-05\"\nspecies = [\"PM2.5\"]",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("PM2.5"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn example_loads() -> Result<()> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("calibration.example.toml");
+        let schedule = CalibrationSchedule::load(&path)?;
+        assert_eq!(schedule.windows.len(), 2);
+        assert!(schedule.windows[1].species.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+//! 在输出工作簿中加入“图表”工作表：每个监测项目一张 Excel 原生折线图，横轴为时间，
+//! 审核人员打开提交的工作簿即可查看，不必另附图片。
+//!
+//! 数据工作表中有缺测代码与 `值#标记` 形式的文本，不能直接作图；图表引用的是本工作表左侧
+//! 的数值副本：带标记的值取其数值，缺测、无效码与非数值留空，折线在该处断开。
+
+use umya_spreadsheet::Spreadsheet;
+use umya_spreadsheet::structs::drawing::charts::{
+    CategoryAxisData, GroupingValues, StringReference,
+};
+use umya_spreadsheet::structs::drawing::spreadsheet::MarkerType;
+use umya_spreadsheet::structs::{Chart, ChartType};
+
+use super::{DataRow, ROW3_HEADERS, cell_ref, col_to_name};
+use crate::flags;
+
+/// 图表工作表的名称。
+pub const SHEET_NAME: &str = "图表";
+
+/// 每张图占的行数与列数。
+const CHART_ROWS: usize = 18;
+const CHART_COLUMNS: usize = 10;
+
+/// 单元格文本对应的数值；`skip` 中的代码与非数值返回 `None`。
+fn chart_value(text: &str, skip: &[&str]) -> Option<f64> {
+    let text = text.trim();
+    if skip.contains(&text) {
+        return None;
+    }
+    flags::split(text)
+        .map_or(text, |(value, _)| value)
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+/// 在工作簿末尾追加“图表”工作表，为至少有一个数值的监测项目各画一张折线图；返回图表个数。
+/// `skip` 为不作图的代码（缺测值、无效码）。
+pub(super) fn render(book: &mut Spreadsheet, rows: &[DataRow], skip: &[&str]) -> usize {
+    let columns: Vec<(usize, Vec<Option<f64>>)> = (0..ROW3_HEADERS.len() - 1)
+        .map(|species| {
+            let values: Vec<Option<f64>> = rows
+                .iter()
+                .map(|row| {
+                    row.values
+                        .get(species)
+                        .and_then(Option::as_deref)
+                        .and_then(|text| chart_value(text, skip))
+                })
+                .collect();
+            (species, values)
+        })
+        .filter(|(_, values)| values.iter().any(Option::is_some))
+        .collect();
+    if rows.is_empty() || columns.is_empty() {
+        return 0;
+    }
+
+    let sheet = book
+        .new_sheet(SHEET_NAME)
+        .expect("template has no sheet with this name");
+    sheet.get_cell_mut("A1").set_value("时间");
+    for (i, row) in rows.iter().enumerate() {
+        sheet
+            .get_cell_mut(cell_ref(1, i + 2).as_str())
+            .set_value_string(&row.time);
+    }
+    for (col, (species, values)) in columns.iter().enumerate() {
+        let col = col + 2;
+        sheet
+            .get_cell_mut(cell_ref(col, 1).as_str())
+            .set_value(ROW3_HEADERS[species + 1]);
+        for (i, value) in values.iter().enumerate() {
+            if let Some(value) = value {
+                sheet
+                    .get_cell_mut(cell_ref(col, i + 2).as_str())
+                    .set_value_number(*value);
+            }
+        }
+    }
+
+    let last_row = rows.len() + 1;
+    let chart_col = columns.len() + 3;
+    let times = format!("{SHEET_NAME}!$A$2:$A${last_row}");
+    for (i, (species, _)) in columns.iter().enumerate() {
+        let letter = col_to_name(i + 2);
+        let values = format!("{SHEET_NAME}!${letter}$2:${letter}${last_row}");
+        let top = i * CHART_ROWS + 1;
+        let mut from = MarkerType::default();
+        from.set_coordinate(cell_ref(chart_col, top));
+        let mut to = MarkerType::default();
+        to.set_coordinate(cell_ref(chart_col + CHART_COLUMNS, top + CHART_ROWS - 1));
+
+        let name = ROW3_HEADERS[species + 1];
+        let mut chart = Chart::default();
+        chart.set_default_language("zh-CN").new_chart(
+            ChartType::LineChart,
+            from,
+            to,
+            vec![values.as_str()],
+        );
+        chart
+            .set_title(name)
+            .set_grouping(GroupingValues::Standard)
+            .set_series_title(vec![name]);
+        if let Some(line_chart) = chart.get_plot_area_mut().get_line_chart_mut() {
+            line_chart.get_show_marker_mut().set_val(false);
+        }
+        for series in chart
+            .get_area_chart_series_list_mut()
+            .get_area_chart_series_mut()
+        {
+            let mut reference = StringReference::default();
+            reference.get_formula_mut().set_address_str(&times);
+            let mut categories = CategoryAxisData::default();
+            categories.set_string_reference(reference);
+            series.set_category_axis_data(categories);
+        }
+        sheet.add_chart(chart);
+    }
+    columns.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn row(time: &str, values: [Option<&str>; 2]) -> DataRow {
+        DataRow {
+            source_row: 2,
+            time: time.to_string(),
+            values: values.iter().map(|v| v.map(str::to_string)).collect(),
+            no2: None,
+        }
+    }
+
+    #[test]
+    fn charts_numeric_copy_of_each_species() -> crate::Result<()> {
+        let rows = vec![
+            row("2026-01-05 01:00:00", [Some("1.6#C"), None]),
+            row("2026-01-05 02:00:00", [Some("-999"), Some("x")]),
+            row("2026-01-05 03:00:00", [Some("2"), None]),
+        ];
+        let mut book = umya_spreadsheet::new_file();
+        assert_eq!(render(&mut book, &rows, &["-999"]), 1);
+        let sheet = book.get_sheet_by_name(SHEET_NAME).expect("sheet");
+        assert_eq!(sheet.get_value("B1"), "SO₂");
+        assert_eq!(sheet.get_value("B2"), "1.6");
+        assert_eq!(sheet.get_value("B3"), "");
+        assert_eq!(sheet.get_value("A4"), "2026-01-05 03:00:00");
+        assert_eq!(sheet.get_value("C1"), "");
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("charts.xlsx");
+        umya_spreadsheet::writer::xlsx::write(&book, &path).expect("write xlsx");
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path)?).expect("zip");
+        let mut xml = String::new();
+        archive
+            .by_name("xl/charts/chart1.xml")
+            .expect("chart part")
+            .read_to_string(&mut xml)?;
+        assert!(xml.contains("图表!$B$2:$B$4"), "{xml}");
+        assert!(xml.contains("图表!$A$2:$A$4"), "{xml}");
+        Ok(())
+    }
+
+    #[test]
+    fn skips_sheet_without_numbers() {
+        let rows = vec![row("2026-01-05 01:00:00", [Some("-999"), None])];
+        let mut book = umya_spreadsheet::new_file();
+        assert_eq!(render(&mut book, &rows, &["-999"]), 0);
+        assert!(book.get_sheet_by_name(SHEET_NAME).is_none());
+    }
+}
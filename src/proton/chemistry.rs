@@ -0,0 +1,284 @@
+//! 化学质控：按行计算阴阳离子当量平衡、NH₄⁺ 预测值与实测值、硫氧化率（SOR）和氮氧化率（NOR），
+//! 写入输出工作簿的“离子平衡”工作表，并找出阴阳离子当量比偏离 1 超过允许偏差的行。
+//!
+//! 浓度单位均为 μg/m³。带数据标记的值（如 `1.6#C`）与补齐的行不参与计算。
+
+use std::fmt;
+
+use umya_spreadsheet::Spreadsheet;
+
+use super::{DataRow, cell_ref, number_format, set_solid_fill};
+
+/// 阴阳离子当量比允许的默认偏差（0.2 即当量比在 0.8–1.2 之间）。
+pub const DEFAULT_TOLERANCE: f64 = 0.2;
+
+/// 输出工作簿中化学质控工作表的名称。
+pub const SHEET_NAME: &str = "离子平衡";
+
+/// 偏离的当量比单元格的填充色（浅红）。
+const DEVIATION_FILL: &str = "ffffc7ce";
+
+// 各项在数据行中的序号，顺序同第 3 行表头（B 列起）
+const SO2: usize = 0;
+const NO3: usize = 5;
+const SO4: usize = 6;
+const NH4: usize = 7;
+const CL: usize = 8;
+
+/// (序号, 摩尔质量 g/mol, 电荷数)
+const CATIONS: [(usize, f64, f64); 5] = [
+    (NH4, 18.04, 1.0),
+    (9, 39.10, 1.0),
+    (10, 22.99, 1.0),
+    (11, 24.31, 2.0),
+    (12, 40.08, 2.0),
+];
+const ANIONS: [(usize, f64, f64); 4] = [
+    (NO3, 62.00, 1.0),
+    (SO4, 96.06, 2.0),
+    (CL, 35.45, 1.0),
+    (13, 46.01, 1.0),
+];
+
+const M_SO2: f64 = 64.07;
+const M_NO2: f64 = 46.01;
+
+/// 工作表的表头。
+const HEADERS: [&str; 8] = [
+    "时间",
+    "阳离子(μeq/m³)",
+    "阴离子(μeq/m³)",
+    "C/A",
+    "NH₄⁺实测(μg/m³)",
+    "NH₄⁺预测(μg/m³)",
+    "SOR",
+    "NOR",
+];
+
+/// 一个输出数据行的化学质控指标；缺少所需的数据时为 `None`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct IonBalance {
+    /// 在输出数据行中的序号（0 起计）
+    pub index: usize,
+    pub time: String,
+    /// 阳离子当量浓度之和（μeq/m³）
+    pub cations: Option<f64>,
+    /// 阴离子当量浓度之和（μeq/m³）
+    pub anions: Option<f64>,
+    /// 阳离子与阴离子当量浓度之比
+    pub ratio: Option<f64>,
+    /// NH₄⁺ 实测浓度
+    pub nh4_measured: Option<f64>,
+    /// 假定 SO₄²⁻、NO₃⁻、Cl⁻ 全部以铵盐形式存在时的 NH₄⁺ 浓度
+    pub nh4_predicted: Option<f64>,
+    /// 硫氧化率 n(SO₄²⁻)/(n(SO₄²⁻)+n(SO₂))
+    pub sor: Option<f64>,
+    /// 氮氧化率 n(NO₃⁻)/(n(NO₃⁻)+n(NO₂))，源表中没有 NO₂ 列时为 `None`
+    pub nor: Option<f64>,
+    /// 当量比偏离 1 超过允许偏差
+    pub deviates: bool,
+}
+
+impl fmt::Display for IonBalance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.time)?;
+        match (self.ratio, self.cations, self.anions) {
+            (Some(ratio), Some(cations), Some(anions)) => write!(
+                f,
+                "阴阳离子当量比 {ratio:.2}（阳离子 {cations:.3}，阴离子 {anions:.3} μeq/m³）"
+            ),
+            _ => write!(f, "阴阳离子当量比无法计算"),
+        }
+    }
+}
+
+/// 第 `column` 列的数值；空值、带标记的值为 `None`。
+fn number(values: &[Option<String>], column: usize, skip: &[&str]) -> Option<f64> {
+    parse(values.get(column)?.as_deref()?, skip)
+}
+
+/// 数值文本；`skip` 中的代码（缺失值、无效码）不是浓度。
+fn parse(text: &str, skip: &[&str]) -> Option<f64> {
+    let text = text.trim();
+    if skip.contains(&text) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// 离子当量浓度之和；NH₄⁺、SO₄²⁻、NO₃⁻ 等主要离子缺测时为 `None`，其余离子缺测时按 0 计。
+fn equivalents(
+    values: &[Option<String>],
+    ions: &[(usize, f64, f64)],
+    skip: &[&str],
+) -> Option<f64> {
+    let mut sum = 0.0;
+    for &(column, molar_mass, charge) in ions {
+        match number(values, column, skip) {
+            // μg/m³ ÷ g/mol = μmol/m³，乘以电荷数为 μeq/m³
+            Some(value) => sum += value / molar_mass * charge,
+            None if [NH4, SO4, NO3].contains(&column) => return None,
+            None => {}
+        }
+    }
+    Some(sum)
+}
+
+/// 按允许偏差 `tolerance` 计算各行的指标；补齐缺失时间点插入的行不计算，`skip` 中的代码
+/// （缺失值、无效码）按缺测处理。
+pub(super) fn compute(data_rows: &[DataRow], tolerance: f64, skip: &[&str]) -> Vec<IonBalance> {
+    data_rows
+        .iter()
+        .enumerate()
+        .filter(|(_, data_row)| data_row.source_row != 0)
+        .map(|(index, data_row)| {
+            let values = &data_row.values;
+            let cations = equivalents(values, &CATIONS, skip);
+            let anions = equivalents(values, &ANIONS, skip);
+            let ratio = match (cations, anions) {
+                (Some(cations), Some(anions)) if anions > 0.0 => Some(cations / anions),
+                _ => None,
+            };
+            let so4 = number(values, SO4, skip).map(|v| v / 96.06);
+            let no3 = number(values, NO3, skip).map(|v| v / 62.00);
+            let nh4_predicted = match (so4, no3) {
+                (Some(so4), Some(no3)) => {
+                    let cl = number(values, CL, skip).unwrap_or(0.0) / 35.45;
+                    Some(18.04 * (2.0 * so4 + no3 + cl))
+                }
+                _ => None,
+            };
+            let so2 = number(values, SO2, skip).map(|v| v / M_SO2);
+            let no2 = data_row
+                .no2
+                .as_deref()
+                .and_then(|v| parse(v, skip))
+                .map(|v| v / M_NO2);
+            IonBalance {
+                index,
+                time: data_row.time.clone(),
+                cations,
+                anions,
+                ratio,
+                nh4_measured: number(values, NH4, skip),
+                nh4_predicted,
+                sor: oxidation_ratio(so4, so2),
+                nor: oxidation_ratio(no3, no2),
+                deviates: ratio.is_some_and(|ratio| (ratio - 1.0).abs() > tolerance),
+            }
+        })
+        .collect()
+}
+
+/// 颗粒态与气态的摩尔浓度之比 n(颗粒)/(n(颗粒)+n(气态))。
+fn oxidation_ratio(particle: Option<f64>, gas: Option<f64>) -> Option<f64> {
+    let (particle, gas) = (particle?, gas?);
+    (particle + gas > 0.0).then(|| particle / (particle + gas))
+}
+
+/// 在 `book` 中添加“离子平衡”工作表，偏离的当量比标为浅红色。
+pub(super) fn render(book: &mut Spreadsheet, balances: &[IonBalance]) {
+    let sheet = book
+        .new_sheet(SHEET_NAME)
+        .expect("template has no sheet with this name");
+    for (i, header) in HEADERS.iter().enumerate() {
+        sheet
+            .get_cell_mut(cell_ref(i + 1, 1).as_str())
+            .set_value(*header);
+    }
+    for (row_idx, balance) in balances.iter().enumerate() {
+        let row = row_idx + 2;
+        sheet
+            .get_cell_mut(cell_ref(1, row).as_str())
+            .set_value(&balance.time);
+        let columns = [
+            (balance.cations, 3),
+            (balance.anions, 3),
+            (balance.ratio, 2),
+            (balance.nh4_measured, 3),
+            (balance.nh4_predicted, 3),
+            (balance.sor, 3),
+            (balance.nor, 3),
+        ];
+        for (i, (value, decimals)) in columns.into_iter().enumerate() {
+            let Some(value) = value else {
+                continue;
+            };
+            let cell = sheet.get_cell_mut(cell_ref(i + 2, row).as_str());
+            cell.set_value_number(value);
+            cell.get_style_mut()
+                .get_number_format_mut()
+                .set_format_code(number_format(decimals));
+        }
+        if balance.deviates {
+            let cell = sheet.get_cell_mut(cell_ref(4, row).as_str());
+            set_solid_fill(cell.get_style_mut(), DEVIATION_FILL);
+        }
+    }
+}
+
+/// 第 `row_idx` 条指标的当量比在“离子平衡”工作表中的单元格地址。
+pub(super) fn ratio_address(row_idx: usize) -> String {
+    cell_ref(4, row_idx + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(source_row: usize, values: &[(usize, &str)], no2: Option<&str>) -> DataRow {
+        let mut row = DataRow {
+            source_row,
+            time: "2026-01-05 01:00:00".to_string(),
+            values: vec![None; 14],
+            no2: no2.map(str::to_string),
+        };
+        for &(column, value) in values {
+            row.values[column] = Some(value.to_string());
+        }
+        row
+    }
+
+    #[test]
+    fn balanced_ammonium_salts() {
+        // 96.06 μg/m³ SO₄²⁻ (2 μeq) + 62 μg/m³ NO₃⁻ (1 μeq) 与 54.12 μg/m³ NH₄⁺ (3 μeq)
+        let rows = [row(
+            2,
+            &[(SO4, "96.06"), (NO3, "62"), (NH4, "54.12"), (SO2, "64.07")],
+            Some("46.01"),
+        )];
+        let balances = compute(&rows, DEFAULT_TOLERANCE, &[]);
+        let balance = &balances[0];
+        assert!((balance.ratio.unwrap() - 1.0).abs() < 1e-9);
+        assert!((balance.anions.unwrap() - 3.0).abs() < 1e-9);
+        assert!((balance.nh4_predicted.unwrap() - 54.12).abs() < 1e-9);
+        assert!((balance.sor.unwrap() - 0.5).abs() < 1e-9);
+        assert!((balance.nor.unwrap() - 0.5).abs() < 1e-9);
+        assert!(!balance.deviates);
+    }
+
+    #[test]
+    fn flags_deviation_and_skips_missing() {
+        let rows = [
+            row(2, &[(SO4, "96.06"), (NO3, "62"), (NH4, "18.04")], None),
+            row(3, &[(SO4, "10"), (NH4, "5")], None),
+            row(0, &[(SO4, "-999"), (NO3, "-999"), (NH4, "-999")], None),
+        ];
+        let balances = compute(&rows, DEFAULT_TOLERANCE, &[]);
+        assert_eq!(balances.len(), 2);
+        assert!(balances[0].deviates);
+        assert!((balances[0].ratio.unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(balances[0].nor, None);
+        assert_eq!(balances[1].ratio, None);
+        assert_eq!(balances[1].nh4_predicted, None);
+        assert!(!balances[1].deviates);
+
+        // 无效码按缺测处理，不计入离子平衡
+        let rows = [row(2, &[(SO4, "96.06"), (NO3, "62"), (NH4, "-999")], None)];
+        let balances = compute(&rows, DEFAULT_TOLERANCE, &["-999"]);
+        assert_eq!(
+            (balances[0].cations, balances[0].nh4_measured),
+            (None, None)
+        );
+    }
+}
@@ -0,0 +1,788 @@
+//! proton 站点配置。
+//!
+//! 配置从 `proton_config.toml`（或合并配置 `dttools.toml` 的 `[proton]`）读取，查找顺序见
+//! [`crate::config`]，可用 `--config` 指定其他文件；找不到或某项缺省时使用内置值。
+//!
+//! ```toml
+//! a2_text = "……"                 # 第 2 行说明文字（可选，缺省时读取 proton_config.txt）
+//! station_code = "4401000010003" # 站点编码，写入第 4 行 A 列（可选）
+//! timezone = "Asia/Shanghai"     # 站点时区，带时区的时间换算为此时区（可选，默认 Asia/Shanghai）
+//! source_timezone = "UTC"        # 不带时区的时间所用的时区（可选，默认即站点时区）
+//! time_shift = "+1h"             # 平移时间列，时段起始时刻改为结束时刻（可选，默认不平移）
+//! from = "2026-01-01T00:00"      # 报告期起点，只输出此时刻及之后的数据行（可选，默认不限）
+//! to = "2026-01-31"              # 报告期终点（含），只写日期时含当天全天（可选，默认不限）
+//! interval = "1h"                # 数据时间间隔，用于时间连续性检查（可选，默认 1h）
+//! fill_gaps = "-999"             # 为缺失的时间点插入数据行，浓度写为此值（可选，默认不插入；"" 为留空）
+//! missing_value = "-999"         # 缺失的浓度（空单元格、非数值、缺少的列）写入的值（可选，默认留空）
+//! resample = "1h"                # 把 interval 间隔的源数据按此时段求平均（可选，默认不重采样）
+//! min_capture = 75               # 重采样时的最低数据捕获率，百分比（可选，默认 75）
+//! decimals = 3                   # 浓度保留的小数位数（可选，默认按原值输出）
+//! decimal_mark = "comma"         # 源数据的小数点：point 为 1,234.5、comma 为 1.234,5（可选，默认 point）
+//! flagged = "flag"               # 带 (C)/(RM) 标记的值：drop 清空、flag 保留为 值#标记、invalid 写无效码（可选，默认 drop）
+//! invalid_code = "-999"          # flagged = "invalid" 时写入的值（可选，默认 "-999"）
+//! below_limit = "half"           # 低于检出限的值（<0.01、ND）：drop 清空、half 检出限的一半、zero 写 0、flag 写 检出限#标记（可选，配置了 [detection_limit] 时默认 half，否则默认 drop）
+//! below_limit_code = "DL"        # below_limit = "flag" 时的标记码（可选，默认 "DL"）
+//! dedupe = "last"                # 重复的时间点：first、last、mean 或 error（可选，默认全部输出）
+//! calibration = "calibration.toml" # 校准时间表，时段内的值按 flagged 处理，见 calibration.example.toml（可选）
+//! charts = true                  # 在“图表”工作表中为每个监测项目插入折线图（可选，默认不插入）
+//! template = "layout.toml"       # 输出模板的版式定义，见 upload_template.example.toml（可选，默认为内置版式）
+//!
+//! [codes]                        # 各监测项目的因子编码，键为第 3 行表头（可选，只需列出要改的项）
+//! "SO₂" = "a21026"
+//! "NO₂⁻" = "a06019"
+//!
+//! [columns]                      # 直接指定源表中的列，键为第 3 行表头或 "时间"（可选）
+//! "NO₃⁻" = "D"
+//!
+//! [aliases]                      # 源数据表头的别名，键为第 3 行表头或 "时间"（可选）
+//! "NO₃⁻" = ["Nitrate"]
+//!
+//! [species]                      # 名称词典中增加的写法，键为标准名称（可选，见 crate::species）
+//! "NO₃⁻" = ["NO3-N"]
+//!
+//! [precision]                    # 按监测项目覆盖 decimals（可选）
+//! "NH₃" = 2
+//!
+//! [detection_limit]              # 各监测项目的检出限（也可写作 [mdl]），用于不带检出限的 ND 与低于检出限的实测值（可选）
+//! "Cl⁻" = 0.01
+//!
+//! [range]                        # 各监测项目的合理范围，超出的值在输出中标黄（可选）
+//! "SO₄²⁻" = { min = 0, max = 500 }
+//!
+//! [correction]                   # 零点/跨度校正：校正值 = (原值 - offset) / slope，原值写入隐藏工作表（可选）
+//! "NO₃⁻" = { slope = 0.98, offset = 0.02 }
+//!
+//! [qc]                           # 突变与数值不变检查（可选，默认不检查）
+//! spike_factor = 5               # 相邻时间点的值相差超过此倍数时标紫
+//! stuck_count = 6                # 同一数值连续出现至少此次数时标灰
+//!
+//! [chemistry]                    # 计算离子平衡与 SOR/NOR，写入“离子平衡”工作表（可选，默认不计算）
+//! tolerance = 0.2                # 阴阳离子当量比偏离 1 超过此值时标色（可选，默认 0.2）
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{FixedOffset, NaiveDateTime, TimeDelta};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use super::calibration::CalibrationSchedule;
+use super::chemistry::DEFAULT_TOLERANCE;
+use super::correction::Correction;
+use super::dedupe::Dedupe;
+use super::qc::Bounds;
+use super::resample::DEFAULT_MIN_CAPTURE;
+use super::timeline::{self, DEFAULT_INTERVAL};
+use super::timezone::{self, DEFAULT_TIMEZONE};
+use super::{DEFAULT_STATION_CODE, ROW3_HEADERS};
+use crate::codes;
+use crate::config::{self, Found};
+use crate::error::{Context, Result, config_error, invalid};
+use crate::flags::{BelowLimitValues, DEFAULT_BELOW_LIMIT_CODE, DEFAULT_INVALID_CODE};
+use crate::species::Dictionary;
+use crate::template::Template;
+use crate::units::DecimalMark;
+use crate::workbook;
+
+/// 默认的站点配置文件名（位于工作目录）。
+pub const CONFIG_FILE: &str = "proton_config.toml";
+
+const A2_TEXT_FILE: &str = "proton_config.txt";
+
+/// 源数据中带 (C)/(RM) 标记（校准、维护）的值的处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlaggedValues {
+    /// 清空
+    #[default]
+    Drop,
+    /// 保留数值并加上 HJ 212 数据标记，如 `1.6#C`、`0.8#M`
+    Flag,
+    /// 写为无效码（见 `invalid_code`）
+    Invalid,
+}
+
+const DEFAULT_A2_TEXT: &str = "请参考 proton_config.example.toml 创建配置文件 proton_config.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    a2_text: Option<String>,
+    station_code: Option<String>,
+    header_row: Option<usize>,
+    #[serde(default)]
+    sheets: Vec<String>,
+    timezone: Option<String>,
+    source_timezone: Option<String>,
+    time_shift: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    interval: Option<String>,
+    fill_gaps: Option<String>,
+    missing_value: Option<String>,
+    resample: Option<String>,
+    min_capture: Option<u32>,
+    decimals: Option<u32>,
+    decimal_mark: Option<DecimalMark>,
+    flagged: Option<FlaggedValues>,
+    invalid_code: Option<String>,
+    below_limit: Option<BelowLimitValues>,
+    below_limit_code: Option<String>,
+    dedupe: Option<Dedupe>,
+    #[serde(default, alias = "mdl")]
+    detection_limit: BTreeMap<String, f64>,
+    #[serde(default)]
+    codes: BTreeMap<String, String>,
+    #[serde(default)]
+    aliases: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    species: Dictionary,
+    #[serde(default)]
+    columns: BTreeMap<String, String>,
+    #[serde(default)]
+    precision: BTreeMap<String, u32>,
+    #[serde(default)]
+    range: BTreeMap<String, Bounds>,
+    #[serde(default)]
+    correction: BTreeMap<String, Correction>,
+    #[serde(default)]
+    qc: QcSection,
+    chemistry: Option<ChemistrySection>,
+    template: Option<PathBuf>,
+    calibration: Option<PathBuf>,
+    charts: Option<bool>,
+    lenient: Option<bool>,
+    error_sheet: Option<bool>,
+}
+
+impl ConfigFile {
+    /// 把相对路径改为相对配置文件 `path` 所在的目录。
+    fn resolve_paths(&mut self, path: &Path) {
+        for file in [&mut self.template, &mut self.calibration]
+            .into_iter()
+            .flatten()
+        {
+            *file = config::resolve_path(path, file);
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QcSection {
+    spike_factor: Option<f64>,
+    stuck_count: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChemistrySection {
+    tolerance: Option<f64>,
+}
+
+/// proton 转换选项。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtonOptions {
+    /// 输出第 2 行的说明文字
+    pub a2_text: String,
+    /// 站点编码（第 4 行 A 列）
+    pub station_code: String,
+    /// 各监测项目的因子编码（第 4 行 B 列起），顺序同第 3 行表头
+    pub codes: Vec<String>,
+    /// 各监测项目在源数据中的表头别名，顺序同第 3 行表头；内置表头找不到时依次尝试
+    pub aliases: Vec<Vec<String>>,
+    /// 时间列的表头别名
+    pub time_aliases: Vec<String>,
+    /// 匹配表头时使用的名称词典
+    pub species: Dictionary,
+    /// 直接指定的各监测项目在源表中的列号（0 起计），顺序同第 3 行表头；优先于表头匹配
+    pub columns: Vec<Option<usize>>,
+    /// 直接指定的时间列列号（0 起计）
+    pub time_column: Option<usize>,
+    /// 直接指定的表头行（0 起计）；`None` 时自动识别（见 [`super::HEADER_SCAN_ROWS`]）
+    pub header_row: Option<usize>,
+    /// 源工作表名称；为空时读取第一个工作表，多个时按时间合并（如阳离子、阴离子分表导出时，
+    /// 见 [`super::read_source_with`]）
+    pub sheets: Vec<String>,
+    /// 站点时区，输出的时间为该时区的当地时间
+    pub timezone: FixedOffset,
+    /// 源数据中不带时区的时间所用的时区；`None` 表示已是站点当地时间
+    pub source_timezone: Option<FixedOffset>,
+    /// 时间列的平移量，用于把时段起始时刻统一为国家平台要求的时段结束时刻
+    pub time_shift: TimeDelta,
+    /// 相邻数据行的时间间隔（重采样时为源数据的间隔）
+    pub interval: TimeDelta,
+    /// 为缺失的时间点插入数据行时各浓度列写入的值；`None` 表示不插入
+    pub fill_gaps: Option<String>,
+    /// 输出中缺失的浓度写入的值，空字符串为留空；所有浓度列一致
+    pub missing_value: String,
+    /// 重采样时段：按此时段对源数据求平均；`None` 表示不重采样
+    pub resample: Option<TimeDelta>,
+    /// 重采样时的最低数据捕获率（百分比），有效数据不足时该时段留空
+    pub min_capture: u32,
+    /// 各监测项目保留的小数位数，顺序同第 3 行表头（B 列起）；`None` 表示按原值输出
+    pub decimals: Vec<Option<u32>>,
+    /// 源数据中数值的小数点；千位分隔符、前导 `+` 与科学计数法在检查数值前规范化
+    pub decimal_mark: DecimalMark,
+    /// 带 (C)/(RM) 标记的值的处理方式
+    pub flagged: FlaggedValues,
+    /// `flagged` 为 [`FlaggedValues::Invalid`] 时写入的值
+    pub invalid_code: String,
+    /// 低于检出限的值（如 `<0.01`、`ND`）的处理方式
+    pub below_limit: BelowLimitValues,
+    /// `below_limit` 为 [`BelowLimitValues::Flag`] 时的标记码
+    pub below_limit_code: String,
+    /// 报告期起点：时间（时区换算与平移后）早于此时刻的数据行不输出；`None` 表示不限
+    pub from: Option<NaiveDateTime>,
+    /// 报告期终点（含）：时间晚于此时刻的数据行不输出；`None` 表示不限
+    pub to: Option<NaiveDateTime>,
+    /// 校准时间表：时段内的数值按 [`flagged`](Self::flagged) 处理，如同带 (C) 标记
+    pub calibration: CalibrationSchedule,
+    /// 同一时间点有多行时的处理方式；`None` 表示全部输出，由时间列检查标出重复
+    pub dedupe: Option<Dedupe>,
+    /// 各监测项目的检出限，顺序同第 3 行表头（B 列起）；用于源数据只写 `ND` 的值，以及按
+    /// [`below_limit`](Self::below_limit) 替换低于检出限的实测值
+    pub detection_limit: Vec<Option<f64>>,
+    /// 各监测项目的合理范围，顺序同第 3 行表头（B 列起）；`None` 表示不检查
+    pub range: Vec<Option<Bounds>>,
+    /// 各监测项目的零点/跨度校正系数，顺序同第 3 行表头（B 列起）；`None` 表示不校正
+    pub correction: Vec<Option<Correction>>,
+    /// 相邻时间点的值相差超过此倍数时视为突变；`None` 表示不检查
+    pub spike_factor: Option<f64>,
+    /// 同一数值连续出现至少此次数时视为仪器卡滞；`None` 表示不检查
+    pub stuck_count: Option<usize>,
+    /// 化学质控：阴阳离子当量比允许偏离 1 的幅度；`None` 表示不计算
+    pub chemistry: Option<f64>,
+    /// 在输出的“图表”工作表中为每个监测项目插入 Excel 折线图
+    pub charts: bool,
+    /// 输出模板的版式
+    pub template: Template,
+    /// 宽松模式：缺少必需的列时该列留空、无法解析时间的行跳过，问题记入
+    /// [`TransformReport::problems`](super::TransformReport::problems)，而不是中止转换
+    pub lenient: bool,
+    /// 宽松模式下把跳过的问题写入输出的“错误”工作表
+    pub error_sheet: bool,
+}
+
+impl Default for ProtonOptions {
+    fn default() -> Self {
+        Self {
+            a2_text: DEFAULT_A2_TEXT.to_string(),
+            station_code: DEFAULT_STATION_CODE.to_string(),
+            codes: ROW3_HEADERS[1..]
+                .iter()
+                .map(|name| {
+                    codes::by_name(name)
+                        .expect("template species are registered")
+                        .code
+                        .to_string()
+                })
+                .collect(),
+            aliases: vec![Vec::new(); ROW3_HEADERS.len() - 1],
+            time_aliases: Vec::new(),
+            species: Dictionary::default(),
+            columns: vec![None; ROW3_HEADERS.len() - 1],
+            time_column: None,
+            header_row: None,
+            sheets: Vec::new(),
+            timezone: timezone::parse_timezone(DEFAULT_TIMEZONE).expect("valid default timezone"),
+            source_timezone: None,
+            time_shift: TimeDelta::zero(),
+            interval: DEFAULT_INTERVAL,
+            fill_gaps: None,
+            missing_value: String::new(),
+            resample: None,
+            min_capture: DEFAULT_MIN_CAPTURE,
+            decimals: vec![None; ROW3_HEADERS.len() - 1],
+            decimal_mark: DecimalMark::Point,
+            flagged: FlaggedValues::Drop,
+            invalid_code: DEFAULT_INVALID_CODE.to_string(),
+            below_limit: BelowLimitValues::Drop,
+            below_limit_code: DEFAULT_BELOW_LIMIT_CODE.to_string(),
+            from: None,
+            to: None,
+            calibration: CalibrationSchedule::default(),
+            dedupe: None,
+            detection_limit: vec![None; ROW3_HEADERS.len() - 1],
+            range: vec![None; ROW3_HEADERS.len() - 1],
+            correction: vec![None; ROW3_HEADERS.len() - 1],
+            spike_factor: None,
+            stuck_count: None,
+            chemistry: None,
+            charts: false,
+            template: Template::builtin(),
+            lenient: false,
+            error_sheet: false,
+        }
+    }
+}
+
+impl ProtonOptions {
+    /// 解析 TOML 配置；未出现的项使用内置值。
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(content).context("配置文件格式错误")?;
+        Self::from_config(file)
+    }
+
+    /// 由已解析的 TOML 表构造，供 `dttools config check` 逐项检查；`path` 为配置文件。
+    pub(crate) fn from_table(table: toml::Table, path: &Path) -> Result<Self> {
+        let mut file =
+            ConfigFile::deserialize(toml::Value::Table(table)).context("配置文件格式错误")?;
+        file.resolve_paths(path);
+        Self::from_config(file)
+    }
+
+    /// 从 `path` 读取配置，文件不存在时使用内置值。配置中没有 `a2_text` 时，
+    /// 沿用 `proton_config.txt` 的内容。
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_found(config::read_file(path)?)
+    }
+
+    /// 按查找顺序（见 [`crate::config`]）读取配置，`explicit` 为 `--config` 指定的文件。
+    pub fn discover(explicit: Option<&Path>) -> Result<Self> {
+        Self::from_found(config::discover(explicit, CONFIG_FILE, "proton")?)
+    }
+
+    pub(crate) fn from_found(found: Option<Found>) -> Result<Self> {
+        let mut file: ConfigFile = match &found {
+            Some(found) => found
+                .deserialize()
+                .with_context(|| format!("配置文件格式错误: {found}"))?,
+            None => ConfigFile::default(),
+        };
+        if file.a2_text.is_none() {
+            file.a2_text = Some(load_a2_text()?);
+        }
+        if let Some(found) = &found {
+            file.resolve_paths(&found.path);
+        }
+        match found {
+            Some(found) => Self::from_config(file).with_context(|| format!("配置文件: {found}")),
+            None => Self::from_config(file),
+        }
+    }
+
+    fn from_config(file: ConfigFile) -> Result<Self> {
+        let mut options = Self::default();
+        if let Some(a2_text) = file.a2_text {
+            options.a2_text = a2_text.trim().to_string();
+        }
+        if let Some(station_code) = file.station_code {
+            options.set_station_code(&station_code)?;
+        }
+        if let Some(row) = file.header_row {
+            options
+                .set_header_row(row)
+                .map_err(|err| config_error!("{err}"))?;
+        }
+        if file.sheets.iter().any(|sheet| sheet.trim().is_empty()) {
+            return Err(config_error!("sheets 中的工作表名称不能为空"));
+        }
+        options.sheets = file.sheets;
+        if let Some(zone) = file.timezone {
+            options.timezone =
+                timezone::parse_timezone(&zone).map_err(|err| config_error!("{err}"))?;
+        }
+        if let Some(zone) = file.source_timezone {
+            options.source_timezone =
+                Some(timezone::parse_timezone(&zone).map_err(|err| config_error!("{err}"))?);
+        }
+        if let Some(shift) = file.time_shift {
+            options.time_shift =
+                timeline::parse_shift(&shift).map_err(|err| config_error!("{err}"))?;
+        }
+        if let Some(from) = file.from {
+            options.from =
+                Some(timeline::parse_period_start(&from).map_err(|err| config_error!("{err}"))?);
+        }
+        if let Some(to) = file.to {
+            options.to =
+                Some(timeline::parse_period_end(&to).map_err(|err| config_error!("{err}"))?);
+        }
+        if let (Some(from), Some(to)) = (options.from, options.to)
+            && from > to
+        {
+            return Err(config_error!("from 不能晚于 to"));
+        }
+        if let Some(interval) = file.interval {
+            options.interval =
+                timeline::parse_interval(&interval).map_err(|err| config_error!("{err}"))?;
+        }
+        if let Some(code) = file.missing_value {
+            options.missing_value = code.trim().to_string();
+        }
+        if let Some(code) = file.fill_gaps {
+            options.fill_gaps = Some(code.trim().to_string());
+        }
+        if let Some(period) = file.resample {
+            options.resample =
+                Some(timeline::parse_interval(&period).map_err(|err| config_error!("{err}"))?);
+        }
+        if let Some(min_capture) = file.min_capture {
+            if min_capture > 100 {
+                return Err(config_error!(
+                    "min_capture 必须在 0 到 100 之间: {min_capture}"
+                ));
+            }
+            options.min_capture = min_capture;
+        }
+        if let Some(decimals) = file.decimals {
+            options.set_decimals(decimals)?;
+        }
+        if let Some(mark) = file.decimal_mark {
+            options.decimal_mark = mark;
+        }
+        if let Some(flagged) = file.flagged {
+            options.flagged = flagged;
+        }
+        if let Some(code) = file.invalid_code {
+            if code.trim().is_empty() {
+                return Err(config_error!("invalid_code 不能为空"));
+            }
+            options.invalid_code = code.trim().to_string();
+        }
+        if let Some(mode) = file.below_limit {
+            options.below_limit = mode;
+        }
+        if let Some(code) = file.below_limit_code {
+            if code.trim().is_empty() {
+                return Err(config_error!("below_limit_code 不能为空"));
+            }
+            options.below_limit_code = code.trim().to_string();
+        }
+        if let Some(policy) = file.dedupe {
+            options.dedupe = Some(policy);
+        }
+        for (species, &limit) in &file.detection_limit {
+            if !(limit.is_finite() && limit > 0.0) {
+                return Err(config_error!(
+                    "[detection_limit] 中 {species} 的检出限必须大于 0: {limit}"
+                ));
+            }
+            options.detection_limit[species_index("detection_limit", species)?] = Some(limit);
+        }
+        // 配置了检出限而没有指定处理方式时，低于检出限的值写为检出限的一半
+        if file.below_limit.is_none() && !file.detection_limit.is_empty() {
+            options.below_limit = BelowLimitValues::Half;
+        }
+        for (species, &decimals) in &file.precision {
+            check_decimals(decimals)?;
+            options.decimals[species_index("precision", species)?] = Some(decimals);
+        }
+        for (species, bounds) in &file.range {
+            if let (Some(min), Some(max)) = (bounds.min, bounds.max)
+                && min > max
+            {
+                return Err(config_error!(
+                    "[range] 中 {species} 的下限 {min} 大于上限 {max}"
+                ));
+            }
+            options.range[species_index("range", species)?] = Some(*bounds);
+        }
+        for (species, correction) in &file.correction {
+            correction.check(species)?;
+            options.correction[species_index("correction", species)?] = Some(*correction);
+        }
+        if let Some(factor) = file.qc.spike_factor {
+            if factor <= 1.0 {
+                return Err(config_error!("[qc] spike_factor 必须大于 1: {factor}"));
+            }
+            options.spike_factor = Some(factor);
+        }
+        if let Some(count) = file.qc.stuck_count {
+            if count < 2 {
+                return Err(config_error!("[qc] stuck_count 至少为 2: {count}"));
+            }
+            options.stuck_count = Some(count);
+        }
+        if let Some(section) = file.chemistry {
+            let tolerance = section.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+            if !(tolerance > 0.0 && tolerance < 1.0) {
+                return Err(config_error!(
+                    "[chemistry] tolerance 必须在 0 到 1 之间: {tolerance}"
+                ));
+            }
+            options.chemistry = Some(tolerance);
+        }
+        if let Some(path) = file.template {
+            options.template = Template::load(&path)?;
+        }
+        if let Some(path) = file.calibration {
+            options.calibration = CalibrationSchedule::load(&path)?;
+        }
+        if let Some(charts) = file.charts {
+            options.charts = charts;
+        }
+        if let Some(lenient) = file.lenient {
+            options.lenient = lenient;
+        }
+        if let Some(error_sheet) = file.error_sheet {
+            options.error_sheet = error_sheet;
+        }
+        options.species = file.species;
+        for (species, aliases) in file.aliases {
+            if aliases.iter().any(|alias| alias.trim().is_empty()) {
+                return Err(config_error!("[aliases] 中 {species} 的别名不能为空"));
+            }
+            if species == "时间" {
+                options.time_aliases = aliases;
+            } else {
+                options.aliases[species_index("aliases", &species)?] = aliases;
+            }
+        }
+        for (species, column) in &file.columns {
+            options.set_column(species, column)?;
+        }
+        for (species, code) in &file.codes {
+            let index = species_index("codes", species)?;
+            if code.trim().is_empty() {
+                return Err(config_error!("[codes] 中 {species} 的编码不能为空"));
+            }
+            options.codes[index] = code.trim().to_string();
+        }
+        Ok(options)
+    }
+
+    /// 所有监测项目统一保留 `decimals` 位小数（如来自 `--decimals`）。
+    pub fn set_decimals(&mut self, decimals: u32) -> Result<()> {
+        check_decimals(decimals)?;
+        self.decimals.fill(Some(decimals));
+        Ok(())
+    }
+
+    /// 指定监测项目（第 3 行表头或 `时间`）在源表中的列，如 `("NO₃⁻", "D")`（如来自 `--map`）。
+    pub fn set_column(&mut self, species: &str, column: &str) -> Result<()> {
+        let col = workbook::column_index(column.trim())
+            .ok_or_else(|| invalid!("无效的列: {column}"))? as usize
+            - 1;
+        if species.trim() == "时间" {
+            self.time_column = Some(col);
+        } else {
+            self.columns[species_index("columns", species)?] = Some(col);
+        }
+        Ok(())
+    }
+
+    /// `time` 是否在报告期（[`from`](Self::from) 至 [`to`](Self::to)，含两端）内。
+    pub fn in_period(&self, time: NaiveDateTime) -> bool {
+        self.from.is_none_or(|from| time >= from) && self.to.is_none_or(|to| time <= to)
+    }
+
+    /// 指定源表的表头行，1 起计（如来自 `--header-row`）。
+    pub fn set_header_row(&mut self, row: usize) -> Result<()> {
+        if row == 0 {
+            return Err(invalid!("表头行从 1 起计"));
+        }
+        self.header_row = Some(row - 1);
+        Ok(())
+    }
+
+    /// 输出中表示缺失或无效的值（`missing_value`、`fill_gaps` 与 `invalid_code`），写为长表、
+    /// 画图与发送报文时跳过。
+    pub(crate) fn missing_codes(&self) -> Vec<&str> {
+        std::iter::once(self.missing_value.as_str())
+            .chain(self.fill_gaps.as_deref())
+            .chain([self.invalid_code.as_str()])
+            .collect()
+    }
+
+    /// 覆盖站点编码（如来自 `--station`）。
+    pub fn set_station_code(&mut self, code: &str) -> Result<()> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Err(invalid!("站点编码不能为空"));
+        }
+        self.station_code = code.to_string();
+        Ok(())
+    }
+}
+
+/// 小数位数的上限。
+const MAX_DECIMALS: u32 = 10;
+
+fn check_decimals(decimals: u32) -> Result<()> {
+    if decimals > MAX_DECIMALS {
+        return Err(invalid!("小数位数不能超过 {MAX_DECIMALS}: {decimals}"));
+    }
+    Ok(())
+}
+
+/// 监测项目（第 3 行表头）在 B 列起的序号；按内置名称词典比较，忽略上下标等写法差异（如 `NO3-`、`Nitrate`）。
+pub(super) fn species_index(section: &str, species: &str) -> Result<usize> {
+    let dictionary = Dictionary::builtin();
+    let species_key = dictionary.key(species);
+    ROW3_HEADERS[1..]
+        .iter()
+        .position(|header| dictionary.key(header) == species_key)
+        .ok_or_else(|| config_error!("[{section}] 中未知的监测项目: {species}"))
+}
+
+fn load_a2_text() -> Result<String> {
+    match config::find_file(A2_TEXT_FILE) {
+        Some(path) => {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("无法读取配置文件: {}", path.display()))?;
+            Ok(content.trim().to_string())
+        }
+        None => Ok(DEFAULT_A2_TEXT.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DtToolsError;
+
+    #[test]
+    fn overrides_station_and_selected_codes() -> Result<()> {
+        let options = ProtonOptions::from_toml_str(
+            r#"
+            station_code = "4403000000001"
+            interval = "30m"
+            resample = "1h"
+            min_capture = 50
+            decimals = 3
+            decimal_mark = "comma"
+            flagged = "invalid"
+            invalid_code = "-888"
+            below_limit = "flag"
+            below_limit_code = "BDL"
+            dedupe = "mean"
+
+            [detection_limit]
+            "Cl⁻" = 0.01
+
+            [codes]
+            "SO₂" = "a21026x"
+
+            [precision]
+            "NH₃" = 2
+
+            [range]
+            "SO₄²⁻" = { min = 0, max = 500 }
+
+            [correction]
+            "NO₃⁻" = { slope = 0.98, offset = 0.02 }
+            "Cl⁻" = { offset = 0.1 }
+
+            [qc]
+            spike_factor = 5
+            stuck_count = 6
+
+            [chemistry]
+            "#,
+        )?;
+        assert_eq!(options.station_code, "4403000000001");
+        assert_eq!(options.codes[0], "a21026x");
+        assert_eq!(options.codes[13], "a06019");
+        assert_eq!(options.interval, TimeDelta::minutes(30));
+        assert_eq!(options.resample, Some(TimeDelta::hours(1)));
+        assert_eq!(options.min_capture, 50);
+        assert_eq!(options.decimals[0], Some(3));
+        assert_eq!(options.decimals[4], Some(2));
+        assert_eq!(options.decimal_mark, DecimalMark::Comma);
+        assert_eq!(options.flagged, FlaggedValues::Invalid);
+        assert_eq!(options.invalid_code, "-888");
+        // 长表、图表与报文都跳过无效码
+        assert!(options.missing_codes().contains(&"-888"));
+        assert_eq!(options.below_limit, BelowLimitValues::Flag);
+        assert_eq!(options.below_limit_code, "BDL");
+        assert_eq!(options.dedupe, Some(Dedupe::Mean));
+        assert_eq!(options.detection_limit[8], Some(0.01));
+        assert_eq!(
+            options.range[6],
+            Some(Bounds {
+                min: Some(0.0),
+                max: Some(500.0)
+            })
+        );
+        assert_eq!(options.range[0], None);
+        assert_eq!(
+            options.correction[5],
+            Some(Correction {
+                slope: 0.98,
+                offset: 0.02
+            })
+        );
+        assert_eq!(options.correction[8].map(|c| c.slope), Some(1.0));
+        assert_eq!(options.spike_factor, Some(5.0));
+        assert_eq!(options.stuck_count, Some(6));
+        assert_eq!(options.chemistry, Some(DEFAULT_TOLERANCE));
+        assert_eq!(options.a2_text, DEFAULT_A2_TEXT);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_species_dictionary() -> Result<()> {
+        let options = ProtonOptions::from_toml_str(
+            "[species]\n\"NO₃⁻\" = [\"NO3-N\"]\n\n[codes]\n\"Nitrate\" = \"a06007x\"\n",
+        )?;
+        assert_eq!(options.species.lookup("no3-n"), Some("NO₃⁻"));
+        assert_eq!(options.species.lookup("Sulfate"), Some("SO₄²⁻"));
+        assert_eq!(options.codes[5], "a06007x");
+
+        let err = ProtonOptions::from_toml_str("[species]\n\"NO₃⁻\" = [\"\"]\n").unwrap_err();
+        assert!(format!("{err:#}").contains("[species]"), "{err:#}");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_species() {
+        let err = ProtonOptions::from_toml_str("[codes]\n\"O₃\" = \"a05024\"\n").unwrap_err();
+        assert!(matches!(err.kind(), DtToolsError::Config { .. }));
+        assert!(format!("{err:#}").contains("O₃"));
+    }
+
+    #[test]
+    fn detection_limits_default_to_half() -> Result<()> {
+        let options = ProtonOptions::from_toml_str("[detection_limit]\n\"Cl⁻\" = 0.01\n")?;
+        assert_eq!(options.below_limit, BelowLimitValues::Half);
+        let options = ProtonOptions::from_toml_str(
+            "below_limit = \"drop\"\n[detection_limit]\n\"Cl⁻\" = 0.01\n",
+        )?;
+        assert_eq!(options.below_limit, BelowLimitValues::Drop);
+        assert_eq!(
+            ProtonOptions::from_toml_str("")?.below_limit,
+            BelowLimitValues::Drop
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reads_reporting_period() -> Result<()> {
+        let options =
+            ProtonOptions::from_toml_str("from = \"2026-01-01T00:00\"\nto = \"2026-01-31\"\n")?;
+        let time = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(options.from, Some(time("2026-01-01 00:00:00")));
+        assert_eq!(options.to, Some(time("2026-01-31 23:59:59")));
+        assert!(options.in_period(time("2026-01-31 23:00:00")));
+        assert!(!options.in_period(time("2026-02-01 00:00:00")));
+
+        let err = ProtonOptions::from_toml_str("from = \"2026-02-01\"\nto = \"2026-01-31\"\n")
+            .unwrap_err();
+        assert!(matches!(err.kind(), DtToolsError::Config { .. }));
+        assert!(ProtonOptions::from_toml_str("from = \"1月5日\"\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_paths_against_config_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let site = dir.path().join("site");
+        std::fs::create_dir(&site)?;
+        std::fs::write(
+            site.join("calibration.toml"),
+            "[[window]]\nfrom = \"2026-01-05T02:00\"\nto = \"2026-01-05T03:00\"\n",
+        )?;
+        let path = site.join(CONFIG_FILE);
+        std::fs::write(&path, "calibration = \"calibration.toml\"\n")?;
+
+        let options = ProtonOptions::load(&path)?;
+        assert_ne!(options.calibration, CalibrationSchedule::default());
+        let found = config::read_file(&path)?.expect("config file");
+        assert!(config::check::check(&found, config::Tool::Proton).is_empty());
+        Ok(())
+    }
+}
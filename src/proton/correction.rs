@@ -0,0 +1,160 @@
+//! 零点/跨度漂移校正：按最近一次零点/跨度检查得到的各项斜率与截距，把浓度校正为
+//! `(原值 - 截距) / 斜率`，原值写入输出工作簿中隐藏的“校正前数据”工作表以便追溯。
+//!
+//! 校正在读取源表时进行，先于低于检出限的判断与替换；带数据标记的值（如 `1.6#C`）、
+//! 低于检出限的值（如 `<0.01`）与无效码不校正。
+
+use serde::Deserialize;
+use umya_spreadsheet::Spreadsheet;
+use umya_spreadsheet::structs::SheetStateValues;
+
+use super::{ROW3_HEADERS, cell_ref};
+use crate::error::{Result, config_error, invalid};
+use crate::units::format_number;
+
+/// 输出工作簿中记录校正前数据的工作表名称。
+pub const SHEET_NAME: &str = "校正前数据";
+
+const HEADERS: [&str; 6] = ["时间", "项目", "原值", "校正值", "斜率", "截距"];
+
+/// 一个监测项目的校正系数。
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Correction {
+    #[serde(default = "default_slope")]
+    pub slope: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+fn default_slope() -> f64 {
+    1.0
+}
+
+impl Correction {
+    pub(super) fn check(&self, species: &str) -> Result<()> {
+        if !self.slope.is_finite() || self.slope == 0.0 || !self.offset.is_finite() {
+            return Err(config_error!(
+                "[correction] 中 {species} 的斜率必须是非 0 的有限数，截距必须是有限数"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, raw: f64) -> f64 {
+        (raw - self.offset) / self.slope
+    }
+}
+
+/// 一个被校正的值。
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Corrected {
+    time: String,
+    /// 第 3 行表头（B 列起）中的序号
+    species: usize,
+    raw: String,
+    corrected: String,
+    correction: Correction,
+}
+
+/// 按 `correction` 校正第 `species` 项（第 3 行表头 B 列起的序号）的数值文本 `text`，
+/// 返回校正记录；`text` 不是数字或等于 `skip`（如无效码）时返回 `None`。
+pub(super) fn correct(
+    time: &str,
+    species: usize,
+    text: &str,
+    correction: Option<&Correction>,
+    skip: &str,
+) -> Option<Corrected> {
+    let correction = correction?;
+    if text.trim() == skip {
+        return None;
+    }
+    let raw = text.trim().parse::<f64>().ok()?;
+    Some(Corrected {
+        time: time.to_string(),
+        species,
+        raw: text.to_string(),
+        corrected: format_number(correction.apply(raw), None),
+        correction: *correction,
+    })
+}
+
+impl Corrected {
+    pub(super) fn value(&self) -> &str {
+        &self.corrected
+    }
+}
+
+/// 在工作簿末尾追加隐藏的“校正前数据”工作表，每行一个被校正的值。
+pub(super) fn render(book: &mut Spreadsheet, corrected: &[Corrected]) -> Result<()> {
+    let sheet = book
+        .new_sheet(SHEET_NAME)
+        .map_err(|_| invalid!("模板中已有名为“{SHEET_NAME}”的工作表，无法写入校正前数据"))?;
+    sheet.set_state(SheetStateValues::Hidden);
+    for (i, header) in HEADERS.iter().enumerate() {
+        sheet
+            .get_cell_mut(cell_ref(i + 1, 1).as_str())
+            .set_value(*header);
+    }
+    for (i, value) in corrected.iter().enumerate() {
+        let row = i + 2;
+        let texts = [
+            value.time.as_str(),
+            ROW3_HEADERS[value.species + 1],
+            &value.raw,
+            &value.corrected,
+        ];
+        for (col, text) in texts.into_iter().enumerate() {
+            sheet
+                .get_cell_mut(cell_ref(col + 1, row).as_str())
+                .set_value_string(text);
+        }
+        let factors = [value.correction.slope, value.correction.offset];
+        for (col, factor) in factors.into_iter().enumerate() {
+            sheet
+                .get_cell_mut(cell_ref(col + 5, row).as_str())
+                .set_value_number(factor);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrects_plain_numbers_only() -> Result<()> {
+        let correction = Correction {
+            slope: 0.5,
+            offset: 0.02,
+        };
+        let time = "2026-01-05 01:00:00";
+        let value = correct(time, 0, "2.02", Some(&correction), "-999").expect("corrected");
+        assert_eq!(value.value(), "4");
+        assert!(correct(time, 0, "2.02", None, "-999").is_none());
+        assert!(correct(time, 0, "1.6#C", Some(&correction), "-999").is_none());
+        assert!(correct(time, 0, "-999", Some(&correction), "-999").is_none());
+
+        let mut book = umya_spreadsheet::new_file();
+        render(&mut book, &[value])?;
+        let sheet = book.get_sheet_by_name(SHEET_NAME).expect("sheet");
+        assert!(matches!(sheet.get_state(), SheetStateValues::Hidden));
+        assert_eq!(sheet.get_value("B2"), "SO₂");
+        assert_eq!(sheet.get_value("C2"), "2.02");
+        assert_eq!(sheet.get_value("E2"), "0.5");
+        // 模板中已有同名工作表时报错而不是崩溃
+        assert!(render(&mut book, &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_zero_slope() {
+        let correction = Correction {
+            slope: 0.0,
+            offset: 0.0,
+        };
+        assert!(correction.check("NO₃⁻").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! 同一时间点出现多行（如仪器重测某个小时）时的处理方式。
+
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use super::DataRow;
+use crate::error::{Result, invalid};
+use crate::units::format_number;
+
+/// 重复时间点的处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dedupe {
+    /// 保留第一次出现的行
+    First,
+    /// 保留最后一次出现的行（重测值）
+    Last,
+    /// 各列取有效数值的平均；都不是数值时取第一个非空值
+    Mean,
+    /// 报错
+    Error,
+}
+
+/// 按 `policy` 合并 `rows` 中时间相同的行，返回合并后的行与去掉的行数。合并后的行位于该时间点
+/// 第一次出现的位置，源表行号也取第一行的。
+pub(super) fn apply(rows: Vec<DataRow>, policy: Dedupe) -> Result<(Vec<DataRow>, usize)> {
+    let before = rows.len();
+    let mut groups: Vec<Vec<DataRow>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        match index.get(&row.time) {
+            Some(&i) => {
+                if policy == Dedupe::Error {
+                    return Err(invalid!(
+                        "时间 {} 重复：第 {} 行与第 {} 行",
+                        row.time,
+                        groups[i][0].source_row,
+                        row.source_row
+                    ));
+                }
+                groups[i].push(row);
+            }
+            None => {
+                index.insert(row.time.clone(), groups.len());
+                groups.push(vec![row]);
+            }
+        }
+    }
+    let rows: Vec<DataRow> = groups
+        .into_iter()
+        .map(|group| combine(group, policy))
+        .collect();
+    let removed = before - rows.len();
+    Ok((rows, removed))
+}
+
+/// 按 `policy` 去重时不进入结果的行在 `times` 中的下标：保留第一行时为各时间点之后出现的行，
+/// 保留最后一行时为之前出现的行；取平均时每一行都参与，返回空。
+pub(super) fn discarded<'a>(
+    times: impl IntoIterator<Item = &'a str>,
+    policy: Dedupe,
+) -> Vec<usize> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, time) in times.into_iter().enumerate() {
+        match index.get(time) {
+            Some(&group) => groups[group].push(i),
+            None => {
+                index.insert(time, groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+    let mut discarded: Vec<usize> = groups
+        .into_iter()
+        .flat_map(|group| match policy {
+            Dedupe::First | Dedupe::Error => group[1..].to_vec(),
+            Dedupe::Last => group[..group.len() - 1].to_vec(),
+            Dedupe::Mean => Vec::new(),
+        })
+        .collect();
+    discarded.sort_unstable();
+    discarded
+}
+
+fn combine(mut group: Vec<DataRow>, policy: Dedupe) -> DataRow {
+    if group.len() == 1 {
+        return group.pop().expect("non-empty group");
+    }
+    match policy {
+        Dedupe::First | Dedupe::Error => group.swap_remove(0),
+        Dedupe::Last => {
+            let source_row = group[0].source_row;
+            let mut last = group.pop().expect("non-empty group");
+            last.source_row = source_row;
+            last
+        }
+        Dedupe::Mean => {
+            let columns = group[0].values.len();
+            let values = (0..columns)
+                .map(|col| mean(group.iter().map(|row| row.values[col].as_deref())))
+                .collect();
+            let no2 = mean(group.iter().map(|row| row.no2.as_deref()));
+            let first = group.swap_remove(0);
+            DataRow {
+                values,
+                no2,
+                ..first
+            }
+        }
+    }
+}
+
+fn mean<'a>(values: impl Iterator<Item = Option<&'a str>>) -> Option<String> {
+    let values: Vec<&str> = values.flatten().collect();
+    let numbers: Vec<f64> = values
+        .iter()
+        .filter_map(|value| value.trim().parse::<f64>().ok())
+        .collect();
+    if numbers.is_empty() {
+        return values.first().map(|value| value.to_string());
+    }
+    Some(format_number(
+        numbers.iter().sum::<f64>() / numbers.len() as f64,
+        None,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(source_row: usize, time: &str, value: Option<&str>) -> DataRow {
+        DataRow {
+            source_row,
+            time: time.to_string(),
+            values: vec![value.map(str::to_string)],
+            no2: None,
+        }
+    }
+
+    fn sample() -> Vec<DataRow> {
+        vec![
+            row(2, "2026-01-05 00:00:00", Some("1")),
+            row(3, "2026-01-05 01:00:00", Some("2")),
+            row(4, "2026-01-05 02:00:00", Some("5")),
+            row(5, "2026-01-05 01:00:00", Some("3")),
+            row(6, "2026-01-05 01:00:00", None),
+        ]
+    }
+
+    fn values(rows: &[DataRow]) -> Vec<(usize, Option<&str>)> {
+        rows.iter()
+            .map(|row| (row.source_row, row.values[0].as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_duplicates_by_policy() -> Result<()> {
+        let (rows, removed) = apply(sample(), Dedupe::First)?;
+        assert_eq!(removed, 2);
+        assert_eq!(
+            values(&rows),
+            [(2, Some("1")), (3, Some("2")), (4, Some("5"))]
+        );
+
+        let (rows, _) = apply(sample(), Dedupe::Last)?;
+        assert_eq!(values(&rows), [(2, Some("1")), (3, None), (4, Some("5"))]);
+
+        let times: Vec<String> = sample().into_iter().map(|row| row.time).collect();
+        let times = || times.iter().map(String::as_str);
+        assert_eq!(discarded(times(), Dedupe::First), [3, 4]);
+        assert_eq!(discarded(times(), Dedupe::Last), [1, 3]);
+        assert!(discarded(times(), Dedupe::Mean).is_empty());
+
+        let (rows, _) = apply(sample(), Dedupe::Mean)?;
+        assert_eq!(
+            values(&rows),
+            [(2, Some("1")), (3, Some("2.5")), (4, Some("5"))]
+        );
+
+        let Err(err) = apply(sample(), Dedupe::Error) else {
+            panic!("duplicate times should be rejected");
+        };
+        assert!(err.to_string().contains("第 3 行与第 5 行"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn mean_keeps_first_non_numeric_value() -> Result<()> {
+        let rows = vec![
+            row(2, "2026-01-05 00:00:00", Some("1.6#C")),
+            row(3, "2026-01-05 00:00:00", Some("1.8#C")),
+        ];
+        let (rows, removed) = apply(rows, Dedupe::Mean)?;
+        assert_eq!(removed, 1);
+        assert_eq!(values(&rows), [(2, Some("1.6#C"))]);
+        Ok(())
+    }
+}
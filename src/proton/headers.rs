@@ -0,0 +1,72 @@
+//! 源数据表头的匹配。不同固件版本导出的表头写法不一（`NO₃⁻(μg/m³)`、`NO3-(ug/m3)`、
+//! `Nitrate (ug/m3)`、全角括号、多余空格等），比较前按名称词典（[`crate::species`]）统一为
+//! 同一形式；配置中的别名作为额外的候选表头。
+
+use std::collections::HashMap;
+
+use crate::species::Dictionary;
+
+/// 源表首行的表头索引。
+pub(crate) struct HeaderIndex<'a> {
+    species: &'a Dictionary,
+    columns: HashMap<String, usize>,
+}
+
+impl<'a> HeaderIndex<'a> {
+    /// `headers` 为首行各列的 (列号, 表头)，按内置词典统一写法。
+    pub(crate) fn new<'h>(headers: impl IntoIterator<Item = (usize, &'h str)>) -> Self {
+        Self::with_species(headers, Dictionary::builtin())
+    }
+
+    /// 按 `species` 统一写法；统一后重复时取第一列。
+    pub(crate) fn with_species<'h>(
+        headers: impl IntoIterator<Item = (usize, &'h str)>,
+        species: &'a Dictionary,
+    ) -> Self {
+        let mut columns = HashMap::new();
+        for (col, header) in headers {
+            let key = species.key(header);
+            if !key.is_empty() {
+                columns.entry(key).or_insert(col);
+            }
+        }
+        Self { species, columns }
+    }
+
+    /// 依次查找候选表头，返回第一个找到的列号。
+    pub(crate) fn find<'h>(&self, candidates: impl IntoIterator<Item = &'h str>) -> Option<usize> {
+        candidates
+            .into_iter()
+            .find_map(|candidate| self.columns.get(&self.species.key(candidate)).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn finds_first_matching_candidate() {
+        let index = HeaderIndex::new([(0, "时间"), (1, "Nitrate"), (2, "SO4 2- (ug/m3)")]);
+        assert_eq!(index.find(["SO₄²⁻(μg/m³)"]), Some(2));
+        assert_eq!(index.find(["NO₃⁻(μg/m³)", "nitrate"]), Some(1));
+        assert_eq!(index.find(["Cl⁻(μg/m³)"]), None);
+    }
+
+    #[test]
+    fn matches_species_names_from_the_dictionary() -> crate::error::Result<()> {
+        let index = HeaderIndex::new([(0, "Time"), (1, "Ammonium (ug/m3)"), (2, "硫酸根(μg/m³)")]);
+        assert_eq!(index.find(["NH₄⁺(μg/m³)"]), Some(1));
+        assert_eq!(index.find(["SO₄²⁻(μg/m³)"]), Some(2));
+
+        let species = Dictionary::with_entries(BTreeMap::from([(
+            "NO₃⁻".to_string(),
+            vec!["NO3-N".to_string()],
+        )]))?;
+        let index = HeaderIndex::with_species([(0, "NO3-N(ug/m3)")], &species);
+        assert_eq!(index.find(["NO₃⁻(μg/m³)"]), Some(0));
+        Ok(())
+    }
+}
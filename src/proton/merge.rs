@@ -0,0 +1,285 @@
+//! `dttools merge`：把多个离子色谱导出文件（如每天一个）合并为覆盖整个时段的一份上传模板。
+//!
+//! 各文件的数据行按输入顺序拼接后按时间排序；同一时间点出现多次时按 `--dedupe`
+//! 处理，默认保留第一次出现的行。
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use chrono::NaiveDateTime;
+use clap::Args;
+use tracing::{info, warn};
+
+use super::dedupe::{self, Dedupe};
+use super::timeline::TIME_FORMAT;
+use super::{
+    DataRow, Extracted, INPUT_EXTENSIONS, ProtonArgs, ProtonOptions, Source, Transformed,
+    build_template, extract_rows, lazy_table, read_source_with, stream,
+};
+use crate::cli::{self, GlobalArgs, Writer};
+use crate::error::{Result, bail};
+use crate::{batch, export, report, workbook};
+
+/// `dttools merge` 的参数：输出文件、输入文件以及 `dttools proton` 的各项转换参数。
+#[derive(Debug, Clone, Args)]
+pub struct MergeArgs {
+    /// 合并后的上传模板
+    #[arg(id = "merge_output", value_name = "OUTPUT")]
+    pub output: PathBuf,
+
+    #[command(flatten)]
+    pub proton: ProtonArgs,
+}
+
+/// 合并多个源的数据行并生成一份上传模板。数据行按时间排序（无法解析的时间排在最后），
+/// 重复的时间点按 [`ProtonOptions::dedupe`] 处理（默认只保留第一次出现的行），去掉的行数记在 [`super::TransformReport::duplicates`]。
+/// 时间列检查问题中的行号为合并排序后的行号（表头为第 1 行）。
+pub fn merge(sources: &[Source], options: &ProtonOptions) -> Result<Transformed> {
+    merge_with(sources, options, Writer::Memory)
+}
+
+/// 同 [`merge`]；`writer` 为 [`Writer::Streaming`] 时数据行由
+/// [`Transformed::write_streaming`] 写出。
+pub fn merge_with(
+    sources: &[Source],
+    options: &ProtonOptions,
+    writer: Writer,
+) -> Result<Transformed> {
+    if writer == Writer::Streaming {
+        stream::check(options)?;
+    }
+    let mut merged = Extracted::default();
+    // 各行所属的输入文件，去重时据此去掉被丢弃的行的变更
+    let mut rows: Vec<(usize, DataRow)> = Vec::new();
+    let mut changes = Vec::new();
+    for (i, source) in sources.iter().enumerate() {
+        let extracted = extract_rows(source, options)?;
+        rows.extend(extracted.rows.into_iter().map(|row| (i, row)));
+        changes.push(extracted.changes);
+        merged.problems.extend(extracted.problems);
+        merged.outside_period += extracted.outside_period;
+    }
+    // 稳定排序，同一时间点保持输入顺序
+    rows.sort_by_cached_key(|(_, row)| {
+        let parsed = NaiveDateTime::parse_from_str(&row.time, TIME_FORMAT).ok();
+        (parsed.is_none(), parsed)
+    });
+    let before = rows.len();
+    let policy = options.dedupe.unwrap_or(Dedupe::First);
+    let dropped: HashSet<(usize, usize)> =
+        dedupe::discarded(rows.iter().map(|(_, row)| row.time.as_str()), policy)
+            .into_iter()
+            .map(|i| (rows[i].0, rows[i].1.source_row))
+            .collect();
+    for (i, changes) in changes.into_iter().enumerate() {
+        merged.changes.extend(
+            changes
+                .into_iter()
+                .filter(|(row, _)| !dropped.contains(&(i, *row))),
+        );
+    }
+    let duplicates;
+    (merged.rows, duplicates) =
+        dedupe::apply(rows.into_iter().map(|(_, row)| row).collect(), policy)?;
+    for (i, row) in merged.rows.iter_mut().enumerate() {
+        row.source_row = i + 2;
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    for source in sources {
+        if !names.contains(&source.sheet_name) {
+            names.push(source.sheet_name.clone());
+        }
+    }
+    let mut merged = build_template(merged, names.join(", "), options, writer)?;
+    merged.report.source_rows = before;
+    merged.report.duplicates = duplicates;
+    Ok(merged)
+}
+
+pub(crate) fn execute(args: &MergeArgs, global: &GlobalArgs) -> Result<()> {
+    if global.output.is_some() || global.in_place {
+        bail!("merge 的输出文件由第一个参数指定，不能使用 --output/--in-place");
+    }
+    if args.proton.interactive {
+        bail!("merge 不支持 --interactive，请用 --map 指定列");
+    }
+    let options = args.proton.options()?;
+    let inputs = batch::expand_inputs(&args.proton.inputs, INPUT_EXTENSIONS)?;
+    let mut sources = Vec::with_capacity(inputs.files.len());
+    for file in &inputs.files {
+        global.check_unprocessed(file)?;
+        sources.push(read_source_with(
+            file,
+            args.proton.delimiter,
+            args.proton.encoding,
+            &options,
+        )?);
+    }
+    let Transformed {
+        workbook: mut book,
+        table,
+        report,
+        pending,
+    } = merge_with(&sources, &options, global.writer)?;
+    let table = lazy_table(table, &pending, &options.missing_value);
+
+    info!(
+        "已合并 {} 个文件，共 {} 行数据",
+        inputs.files.len(),
+        report.source_rows
+    );
+    if report.duplicates > 0 {
+        info!("去掉了 {} 个重复的时间点", report.duplicates);
+    }
+    if !report.time_issues.is_empty() {
+        warn!(
+            "时间列检查发现 {} 个问题（输出中已标红）:",
+            report.time_issues.len()
+        );
+        for issue in &report.time_issues {
+            warn!("  {issue}");
+        }
+    }
+    if !report.qc_flags.is_empty() {
+        warn!(
+            "质量检查发现 {} 个可疑值（输出中已标色）:",
+            report.qc_flags.len()
+        );
+        for flag in &report.qc_flags {
+            warn!("  {flag}");
+        }
+    }
+    if report.filled_rows > 0 {
+        info!("已为缺失的时间点插入 {} 行", report.filled_rows);
+    }
+    report::warn_problems(&report.problems);
+
+    let output = &args.output;
+    if global.dry_run {
+        info!(
+            "[试运行] 将输出 {} 行数据到: {}",
+            report.rows,
+            output.display()
+        );
+        report::print_planned_changes(&report.changes);
+        return report::partial(report.problems.len());
+    }
+    report::log_changes(&report.changes);
+    workbook::mark_processed(&mut book, &options);
+    cli::ensure_parent_dir(output)?;
+    match global.writer {
+        Writer::Memory => workbook::write(&book, output, global.format)?,
+        Writer::Streaming => {
+            stream::write(&book, &pending, &report, &options, global.format, output)?
+        }
+    }
+    info!(
+        "合并后的模板已保存为: {}（{} 行）",
+        output.display(),
+        report.rows
+    );
+
+    if let Some(delimiter) = global.csv_export() {
+        let csv_path = export::csv_path(output, delimiter);
+        export::write_csv(&csv_path, &table, delimiter)?;
+        info!("数据表已导出为: {}", csv_path.display());
+    }
+    #[cfg(feature = "upload")]
+    global.upload.send(output)?;
+    report::partial(report.problems.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{Data, Range};
+
+    /// 只有时间列和 NO₃⁻ 等必需列的源表，`rows` 为 (时间, NO₃⁻)。
+    fn source(name: &str, rows: &[(&str, &str)]) -> Source {
+        let headers = [
+            "时间",
+            "NO₃⁻(μg/m³)",
+            "SO₄²⁻(μg/m³)",
+            "NH₄⁺(μg/m³)",
+            "Cl⁻(μg/m³)",
+            "K⁺(μg/m³)",
+            "Na⁺(μg/m³)",
+            "Mg²⁺(μg/m³)",
+            "Ca²⁺(μg/m³)",
+        ];
+        let mut range = Range::new((0, 0), (rows.len() as u32, headers.len() as u32 - 1));
+        for (col, header) in headers.iter().enumerate() {
+            range.set_value((0, col as u32), Data::String(header.to_string()));
+        }
+        for (i, (time, value)) in rows.iter().enumerate() {
+            let row = i as u32 + 1;
+            range.set_value((row, 0), Data::String(time.to_string()));
+            for col in 1..headers.len() as u32 {
+                range.set_value((row, col), Data::String(value.to_string()));
+            }
+        }
+        Source {
+            sheet_name: name.to_string(),
+            range,
+        }
+    }
+
+    #[test]
+    fn merges_sorts_and_deduplicates() -> Result<()> {
+        let day2 = source(
+            "day2",
+            &[
+                ("2026-01-06 00:00:00", "2.0"),
+                ("2026-01-06 01:00:00", "2.1"),
+            ],
+        );
+        let day1 = source(
+            "day1",
+            &[
+                ("2026-01-05 22:00:00", "1.0"),
+                ("2026-01-05 23:00:00", "1.1"),
+                ("2026/01/06 00:00:00", "9.9"),
+            ],
+        );
+        let merged = merge(&[day2.clone(), day1.clone()], &ProtonOptions::default())?;
+
+        assert_eq!(merged.report.source_rows, 5);
+        assert_eq!(merged.report.duplicates, 1);
+        assert_eq!(merged.report.rows, 4);
+        assert_eq!(merged.report.source_sheet, "day2, day1");
+        assert!(merged.report.time_issues.is_empty());
+        let times: Vec<_> = merged.table[1..]
+            .iter()
+            .map(|row| row[0].as_str())
+            .collect();
+        assert_eq!(
+            times,
+            [
+                "2026-01-05 22:00:00",
+                "2026-01-05 23:00:00",
+                "2026-01-06 00:00:00",
+                "2026-01-06 01:00:00"
+            ]
+        );
+        // 重复的时间点保留先出现（day2）的值，被丢弃的行的变更不报告
+        assert_eq!(merged.table[3][6], "2.0");
+        let reformatted = |merged: &Transformed| {
+            merged
+                .report
+                .changes
+                .iter()
+                .any(|change| change.sheet == "day1" && change.address == "A4")
+        };
+        assert!(!reformatted(&merged));
+
+        let last = ProtonOptions {
+            dedupe: Some(Dedupe::Last),
+            ..Default::default()
+        };
+        let merged = merge(&[day2, day1], &last)?;
+        assert_eq!(merged.table[3][6], "9.9");
+        assert!(reformatted(&merged));
+        Ok(())
+    }
+}
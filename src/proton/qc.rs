@@ -0,0 +1,277 @@
+//! 数据质量检查：找出超出合理范围的浓度值、相邻时间点间的突变（spike）以及长时间不变的数值
+//! （stuck，通常是仪器故障）。可疑值保留原样输出，但在模板中按类型标色并写入变更报告，
+//! 由分析人员在上传前确认。
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use super::{DataRow, ProtonOptions, ROW3_HEADERS};
+
+/// 一个监测项目的合理范围，上下限均含本值。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bounds {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// 一个可疑的浓度值。
+#[derive(Debug, Clone, PartialEq)]
+pub struct QcFlag {
+    /// 在输出数据行中的序号（0 起计）
+    pub index: usize,
+    /// 监测项目在 B 列起的序号，顺序同第 3 行表头
+    pub column: usize,
+    pub time: String,
+    pub value: f64,
+    pub kind: QcKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QcKind {
+    /// 低于合理范围下限
+    BelowMin { min: f64 },
+    /// 高于合理范围上限
+    AboveMax { max: f64 },
+    /// 与上一时间点的值相差超过设定倍数
+    Spike { previous: f64 },
+    /// 所在的一段连续 `count` 个时间点数值完全相同
+    Stuck { count: usize },
+}
+
+impl QcKind {
+    /// 写入变更报告的规则编号。
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::BelowMin { .. } | Self::AboveMax { .. } => "range",
+            Self::Spike { .. } => "spike",
+            Self::Stuck { .. } => "stuck",
+        }
+    }
+
+    /// 输出模板中单元格的填充色：超范围为黄色，突变为紫色，不变为灰色。
+    pub fn fill(&self) -> &'static str {
+        match self {
+            Self::BelowMin { .. } | Self::AboveMax { .. } => "ffffff00",
+            Self::Spike { .. } => "ffcc99ff",
+            Self::Stuck { .. } => "ffbfbfbf",
+        }
+    }
+}
+
+impl fmt::Display for QcFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let species = ROW3_HEADERS[self.column + 1];
+        write!(f, "{} {species}={}: ", self.time, self.value)?;
+        match &self.kind {
+            QcKind::BelowMin { min } => write!(f, "低于下限 {min}"),
+            QcKind::AboveMax { max } => write!(f, "高于上限 {max}"),
+            QcKind::Spike { previous } => write!(f, "与上一时间点的值 {previous} 相比突变"),
+            QcKind::Stuck { count } => write!(f, "连续 {count} 个时间点数值不变"),
+        }
+    }
+}
+
+/// 按 `options` 中的范围、突变倍数与不变次数检查各列数值，结果按行、列排序。
+/// 补齐缺失时间点插入的行不检查，也会打断突变与不变的判断。
+pub(super) fn check(data_rows: &[DataRow], options: &ProtonOptions) -> Vec<QcFlag> {
+    // 缺失值与无效码（如 -999）不是浓度
+    let skip = options.missing_codes();
+    let mut flags = check_range(data_rows, &options.range, &skip);
+    if let Some(factor) = options.spike_factor {
+        flags.extend(check_spikes(data_rows, factor, &skip));
+    }
+    if let Some(count) = options.stuck_count {
+        flags.extend(check_stuck(data_rows, count, &skip));
+    }
+    flags.sort_by_key(|flag| (flag.index, flag.column));
+    flags
+}
+
+/// 第 `index` 行第 `column` 列的数值；补齐的行、空值、非数值和 `skip` 中的代码为 `None`。
+fn number(data_rows: &[DataRow], index: usize, column: usize, skip: &[&str]) -> Option<f64> {
+    let data_row = &data_rows[index];
+    if data_row.source_row == 0 {
+        return None;
+    }
+    let text = data_row.values.get(column)?.as_deref()?.trim();
+    if skip.contains(&text) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn flag(data_rows: &[DataRow], index: usize, column: usize, value: f64, kind: QcKind) -> QcFlag {
+    QcFlag {
+        index,
+        column,
+        time: data_rows[index].time.clone(),
+        value,
+        kind,
+    }
+}
+
+/// 检查各列数值是否在 `bounds` 给出的范围内。
+fn check_range(data_rows: &[DataRow], bounds: &[Option<Bounds>], skip: &[&str]) -> Vec<QcFlag> {
+    let mut flags = Vec::new();
+    for index in 0..data_rows.len() {
+        for (column, bounds) in bounds.iter().enumerate() {
+            let (Some(value), Some(bounds)) = (number(data_rows, index, column, skip), bounds)
+            else {
+                continue;
+            };
+            let kind = match (bounds.min, bounds.max) {
+                (Some(min), _) if value < min => QcKind::BelowMin { min },
+                (_, Some(max)) if value > max => QcKind::AboveMax { max },
+                _ => continue,
+            };
+            flags.push(flag(data_rows, index, column, value, kind));
+        }
+    }
+    flags
+}
+
+/// 与上一行相比，较大值超过较小值的 `factor` 倍时标出后一个值。只比较两个均为正数的相邻值。
+fn check_spikes(data_rows: &[DataRow], factor: f64, skip: &[&str]) -> Vec<QcFlag> {
+    let mut flags = Vec::new();
+    for index in 1..data_rows.len() {
+        for column in 0..data_rows[index].values.len() {
+            let (Some(previous), Some(value)) = (
+                number(data_rows, index - 1, column, skip),
+                number(data_rows, index, column, skip),
+            ) else {
+                continue;
+            };
+            if previous > 0.0 && value > 0.0 && previous.max(value) > previous.min(value) * factor {
+                flags.push(flag(
+                    data_rows,
+                    index,
+                    column,
+                    value,
+                    QcKind::Spike { previous },
+                ));
+            }
+        }
+    }
+    flags
+}
+
+/// 同一列中连续至少 `count` 行数值相同时，标出这一段的所有单元格。
+fn check_stuck(data_rows: &[DataRow], count: usize, skip: &[&str]) -> Vec<QcFlag> {
+    let mut flags = Vec::new();
+    let columns = data_rows
+        .first()
+        .map_or(0, |data_row| data_row.values.len());
+    for column in 0..columns {
+        let mut start = 0;
+        while start < data_rows.len() {
+            let Some(value) = number(data_rows, start, column, skip) else {
+                start += 1;
+                continue;
+            };
+            let end = (start + 1..data_rows.len())
+                .find(|&index| number(data_rows, index, column, skip) != Some(value))
+                .unwrap_or(data_rows.len());
+            let run = end - start;
+            if run >= count {
+                flags.extend((start..end).map(|index| {
+                    flag(
+                        data_rows,
+                        index,
+                        column,
+                        value,
+                        QcKind::Stuck { count: run },
+                    )
+                }));
+            }
+            start = end;
+        }
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(source_row: usize, value: &str) -> DataRow {
+        DataRow {
+            source_row,
+            time: "2026-01-05 01:00:00".to_string(),
+            values: vec![Some(value.to_string()), Some("1".to_string())],
+            no2: None,
+        }
+    }
+
+    #[test]
+    fn flags_values_outside_bounds() {
+        let rows = [row(2, "-0.5"), row(3, "12"), row(4, "600"), row(0, "-999")];
+        let bounds = [
+            Some(Bounds {
+                min: Some(0.0),
+                max: Some(500.0),
+            }),
+            None,
+        ];
+
+        let flags = check_range(&rows, &bounds, &[]);
+        let found: Vec<_> = flags.iter().map(|f| (f.index, f.kind.clone())).collect();
+        assert_eq!(
+            found,
+            [
+                (0, QcKind::BelowMin { min: 0.0 }),
+                (2, QcKind::AboveMax { max: 500.0 })
+            ]
+        );
+        assert_eq!(
+            flags[1].to_string(),
+            "2026-01-05 01:00:00 SO₂=600: 高于上限 500"
+        );
+
+        // 无效码不是浓度，不按范围检查
+        let rows = [row(2, "-999")];
+        assert!(check_range(&rows, &bounds, &["-999"]).is_empty());
+    }
+
+    #[test]
+    fn flags_spikes_and_stuck_values() {
+        let rows = [
+            row(2, "2"),
+            row(3, "2.5"),
+            row(4, "30"),
+            row(5, "3"),
+            row(0, "-999"),
+            row(7, "0.1"),
+        ];
+        let options = ProtonOptions {
+            spike_factor: Some(5.0),
+            stuck_count: Some(6),
+            ..Default::default()
+        };
+        let found: Vec<_> = check(&rows, &options)
+            .into_iter()
+            .map(|f| (f.index, f.column, f.kind))
+            .collect();
+        // 第 2 列的 1 在第 4 行被补齐行打断，只连续 4 次
+        assert_eq!(
+            found,
+            [
+                (2, 0, QcKind::Spike { previous: 2.5 }),
+                (3, 0, QcKind::Spike { previous: 30.0 }),
+            ]
+        );
+
+        let options = ProtonOptions {
+            stuck_count: Some(4),
+            ..Default::default()
+        };
+        let stuck = check(&rows, &options);
+        assert_eq!(stuck.len(), 4);
+        assert!(
+            stuck
+                .iter()
+                .all(|f| f.column == 1 && f.kind == QcKind::Stuck { count: 4 })
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! 把高频数据（如 15 分钟）按固定时段求平均，输出为时段起始时间的一行。
+//!
+//! 每个时段内只对有效数值求平均（标记值、非数值已在读取时清空）；有效数据个数占应有个数的
+//! 比例低于最低数据捕获率时，该时段该列不输出数值。
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+
+use super::DataRow;
+use super::timeline::{TIME_FORMAT, TimeIssue, TimeIssueKind};
+use crate::units::format_number;
+
+/// 默认的最低数据捕获率（百分比）。
+pub const DEFAULT_MIN_CAPTURE: u32 = 75;
+
+/// 按 `period` 对 `rows` 求平均。`interval` 为源数据的时间间隔，用于计算每个时段应有的数据个数。
+/// 无法解析时间的行不参与平均，作为问题返回。
+pub(super) fn resample(
+    rows: Vec<DataRow>,
+    period: TimeDelta,
+    interval: TimeDelta,
+    min_capture: u32,
+) -> Result<(Vec<DataRow>, Vec<TimeIssue>), String> {
+    let period_ms = period.num_milliseconds();
+    let interval_ms = interval.num_milliseconds();
+    if period_ms < interval_ms || period_ms % interval_ms != 0 {
+        return Err("重采样时段必须是数据时间间隔的整数倍".to_string());
+    }
+    let expected = (period_ms / interval_ms) as usize;
+    let period_secs = period.num_seconds();
+
+    let mut issues = Vec::new();
+    // 时段起点 -> (该时段第一行的源表行号, 各列的有效值，最后一列为 NO₂)
+    let mut buckets: BTreeMap<NaiveDateTime, (usize, Vec<Vec<f64>>)> = BTreeMap::new();
+    for row in rows {
+        let Ok(time) = NaiveDateTime::parse_from_str(&row.time, TIME_FORMAT) else {
+            issues.push(TimeIssue {
+                row: row.source_row,
+                time: row.time,
+                kind: TimeIssueKind::Unparsable,
+            });
+            continue;
+        };
+        let seconds = time.and_utc().timestamp();
+        let start = DateTime::from_timestamp(seconds - seconds.rem_euclid(period_secs), 0)
+            .expect("timestamp within range")
+            .naive_utc();
+        let (_, columns) = buckets
+            .entry(start)
+            .or_insert_with(|| (row.source_row, vec![Vec::new(); row.values.len() + 1]));
+        let row_values = row.values.iter().chain(std::iter::once(&row.no2));
+        for (values, value) in columns.iter_mut().zip(row_values) {
+            if let Some(number) = value.as_deref().and_then(|v| v.trim().parse::<f64>().ok()) {
+                values.push(number);
+            }
+        }
+    }
+
+    let rows = buckets
+        .into_iter()
+        .map(|(start, (source_row, columns))| {
+            let mut values: Vec<_> = columns
+                .into_iter()
+                .map(|values| {
+                    let captured =
+                        values.len().min(expected) * 100 >= expected * min_capture as usize;
+                    (captured && !values.is_empty()).then(|| {
+                        format_number(values.iter().sum::<f64>() / values.len() as f64, None)
+                    })
+                })
+                .collect();
+            let no2 = values.pop().flatten();
+            DataRow {
+                source_row,
+                time: start.format(TIME_FORMAT).to_string(),
+                values,
+                no2,
+            }
+        })
+        .collect();
+    Ok((rows, issues))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(source_row: usize, time: &str, value: Option<&str>) -> DataRow {
+        DataRow {
+            source_row,
+            time: time.to_string(),
+            values: vec![value.map(str::to_string)],
+            no2: None,
+        }
+    }
+
+    #[test]
+    fn averages_quarter_hours_with_capture_threshold() -> Result<(), String> {
+        let rows = vec![
+            row(2, "2026-01-05 01:00:00", Some("1")),
+            row(3, "2026-01-05 01:15:00", Some("2")),
+            row(4, "2026-01-05 01:30:00", None),
+            row(5, "2026-01-05 01:45:00", Some("3")),
+            row(6, "2026-01-05 02:00:00", Some("5")),
+            row(7, "2026-01-05 02:15:00", Some("7")),
+            row(8, "bad", Some("1")),
+        ];
+        let (out, issues) = resample(
+            rows,
+            TimeDelta::hours(1),
+            TimeDelta::minutes(15),
+            DEFAULT_MIN_CAPTURE,
+        )?;
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].time, "2026-01-05 01:00:00");
+        assert_eq!(out[0].source_row, 2);
+        assert_eq!(out[0].values, [Some("2".to_string())]);
+        // 只有 2/4 个有效值，低于 75%
+        assert_eq!(out[1].values, [None]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].row, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_period_not_multiple_of_interval() {
+        assert!(
+            resample(
+                Vec::new(),
+                TimeDelta::minutes(50),
+                TimeDelta::minutes(15),
+                75
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! 读取离子色谱导出数据：xlsx/xls 直接用 calamine 读取，
+//! CSV 按指定分隔符与编码解析后转换为同样的单元格区域。
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Context, DtToolsError, Result, invalid};
+use calamine::{Data, Range, Reader, open_workbook_auto};
+use clap::ValueEnum;
+
+/// CSV 文件编码。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum CsvEncoding {
+    /// 合法的 UTF-8 按 UTF-8 读取，否则按 GBK 读取
+    #[default]
+    Auto,
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    #[value(alias = "gb18030")]
+    Gbk,
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv") || e.eq_ignore_ascii_case("tsv"))
+}
+
+fn decode(bytes: &[u8], encoding: CsvEncoding) -> Result<String> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match encoding {
+        CsvEncoding::Utf8 => String::from_utf8(bytes.to_vec()).context("文件不是有效的 UTF-8 编码"),
+        CsvEncoding::Gbk => Ok(encoding_rs::GB18030.decode(bytes).0.into_owned()),
+        CsvEncoding::Auto => match std::str::from_utf8(bytes) {
+            Ok(s) => Ok(s.to_string()),
+            Err(_) => Ok(encoding_rs::GB18030.decode(bytes).0.into_owned()),
+        },
+    }
+}
+
+fn parse_csv(content: &str, delimiter: u8) -> Result<Range<Data>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.with_context(|| format!("CSV 第 {} 行格式错误", i + 1))?;
+        rows.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if rows.is_empty() || width == 0 {
+        return Ok(Range::empty());
+    }
+
+    let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width as u32 - 1));
+    for (row, values) in rows.into_iter().enumerate() {
+        for (col, value) in values.into_iter().enumerate() {
+            if !value.is_empty() {
+                range.set_value((row as u32, col as u32), Data::String(value));
+            }
+        }
+    }
+    Ok(range)
+}
+
+/// 读取输入文件的第一个工作表（CSV 视为一个以文件名命名的工作表），
+/// 返回工作表名称与单元格区域。
+pub(crate) fn read_first_sheet(
+    path: &Path,
+    delimiter: u8,
+    encoding: CsvEncoding,
+) -> Result<(String, Range<Data>)> {
+    if is_csv(path) {
+        let bytes = fs::read(path).map_err(|err| DtToolsError::file_open(path, err))?;
+        let content = decode(&bytes, encoding)
+            .with_context(|| format!("无法解码文件: {}", path.display()))?;
+        let delimiter = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("tsv") => b'\t',
+            _ => delimiter,
+        };
+        let range = parse_csv(&content, delimiter)
+            .with_context(|| format!("无法解析 CSV 文件: {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        return Ok((name, range));
+    }
+
+    let mut workbook =
+        open_workbook_auto(path).map_err(|err| DtToolsError::file_open(path, err))?;
+
+    let sheet_names = workbook.sheet_names();
+    let sheet_name = sheet_names
+        .first()
+        .ok_or_else(|| invalid!("工作簿中没有工作表"))?
+        .clone();
+
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .with_context(|| format!("无法读取工作表: {sheet_name}"))?;
+    Ok((sheet_name, range))
+}
+
+/// 读取工作簿中名为 `name` 的工作表。CSV 文件没有工作表，不能按名称读取。
+pub(crate) fn read_sheet(path: &Path, name: &str) -> Result<Range<Data>> {
+    if is_csv(path) {
+        return Err(invalid!("CSV 文件不能指定源工作表: {}", path.display()));
+    }
+    let mut workbook =
+        open_workbook_auto(path).map_err(|err| DtToolsError::file_open(path, err))?;
+    if !workbook.sheet_names().iter().any(|sheet| sheet == name) {
+        return Err(DtToolsError::SheetNotFound {
+            name: name.to_string(),
+        });
+    }
+    workbook
+        .worksheet_range(name)
+        .with_context(|| format!("无法读取工作表: {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_gbk_and_utf8() -> Result<()> {
+        let text = "时间,NO₃⁻(μg/m³)\n2026-01-05 01:00:00,1.5\n";
+        let (gbk, _, _) = encoding_rs::GB18030.encode(text);
+        assert_eq!(decode(&gbk, CsvEncoding::Auto)?, text);
+        let bom = [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat();
+        assert_eq!(decode(&bom, CsvEncoding::Auto)?, text);
+        assert!(decode(&gbk, CsvEncoding::Utf8).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_semicolon_csv_into_range() -> Result<()> {
+        let range = parse_csv("时间;NO₃⁻\n\"2026-01-05 01:00:00\";1,5\n", b';')?;
+        assert_eq!(range.get_size(), (2, 2));
+        assert_eq!(range.get((1, 1)), Some(&Data::String("1,5".to_string())));
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+//! `--writer streaming` 的上传模板写出：表头与其余工作表（离子平衡、校正前数据等）
+//! 由 umya 工作簿复制，数据行逐行写出，不在内存中生成整个模板工作表。
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use umya_spreadsheet::Spreadsheet;
+
+use super::{
+    Content, DataRow, ProtonOptions, TransformReport, Transformed, column_headers, time_content,
+    value_content,
+};
+use crate::cli::OutputFormat;
+use crate::error::{Result, invalid};
+use crate::progress;
+use crate::workbook::stream::{CellStyle, SheetLayout, StreamWriter, Value};
+use crate::workbook::{self, DATETIME_FORMAT, TIME_TEXT_FORMAT};
+
+impl Transformed {
+    /// 把以 [`Writer::Streaming`](crate::cli::Writer::Streaming) 转换得到的模板流式写为 xlsx，
+    /// 见 [`super::transform_with`]。
+    pub fn write_streaming(&self, path: &Path, options: &ProtonOptions) -> Result<()> {
+        write(
+            &self.workbook,
+            &self.pending,
+            &self.report,
+            options,
+            OutputFormat::Xlsx,
+            path,
+        )
+    }
+}
+
+/// 检查流式写出能否输出 `options` 要求的内容：图表无法流式写出；版式中数据起始行及以下的
+/// `[[cells]]` 固定单元格不会与数据行一起写出。
+pub(super) fn check(options: &ProtonOptions) -> Result<()> {
+    if options.charts {
+        return Err(invalid!(
+            "--writer streaming 不能写出图表工作表，请去掉 --charts 或改用 --writer memory"
+        ));
+    }
+    let template = &options.template;
+    if let Some(cell) = template
+        .cells
+        .iter()
+        .find(|cell| cell.row() >= template.start_row)
+    {
+        return Err(invalid!(
+            "--writer streaming 不能写出数据起始行（第 {} 行）及以下的固定单元格 {}，请改用 --writer memory",
+            template.start_row,
+            cell.address
+        ));
+    }
+    Ok(())
+}
+
+/// 单元格内容对应的值与样式。
+fn cell_value<'a>(content: &Content<'a>, base: &CellStyle) -> (Value<'a>, CellStyle) {
+    match content {
+        Content::Number(number, format) => (
+            Value::Number(*number),
+            match format {
+                Some(format) => base.with_number_format(format),
+                None => base.clone(),
+            },
+        ),
+        Content::Time(text) => match NaiveDateTime::parse_from_str(text, TIME_TEXT_FORMAT) {
+            Ok(time) => (
+                Value::Number(workbook::excel_serial(time)),
+                base.with_number_format(DATETIME_FORMAT),
+            ),
+            Err(_) => (Value::Text(text), base.clone()),
+        },
+        Content::Text(text) => (Value::Text(text), base.clone()),
+    }
+}
+
+/// 列宽计算用的显示文本。
+fn display_text(content: &Content) -> String {
+    match content {
+        Content::Number(number, _) => number.to_string(),
+        Content::Time(text) | Content::Text(text) => text.to_string(),
+    }
+}
+
+/// 写出 `book`（只有表头的模板及其他工作表）与数据行 `data_rows`；`report` 中的时间问题与
+/// 可疑值同 [`super::render_template`] 一样标色。只能写出 xlsx。
+pub(super) fn write(
+    book: &Spreadsheet,
+    data_rows: &[DataRow],
+    report: &TransformReport,
+    options: &ProtonOptions,
+    format: OutputFormat,
+    path: &Path,
+) -> Result<()> {
+    if format != OutputFormat::Xlsx {
+        return Err(invalid!("--writer streaming 只支持 --format xlsx"));
+    }
+    check(options)?;
+    let template = &options.template;
+    let sheets = book.get_sheet_collection();
+    let header = sheets
+        .first()
+        .ok_or_else(|| invalid!("工作簿中没有工作表"))?;
+    let columns = column_headers(options).len();
+
+    let widths = if template.auto_width {
+        let header_cells = header.get_cell_collection().into_iter().map(|cell| {
+            let coordinate = cell.get_coordinate();
+            (
+                *coordinate.get_col_num(),
+                *coordinate.get_row_num(),
+                workbook::display_text(cell),
+            )
+        });
+        let data_cells = data_rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row_idx, data_row)| {
+                let row = template.start_row + row_idx as u32;
+                let filled = data_row.source_row == 0;
+                let time = (
+                    template.time_column,
+                    row,
+                    display_text(&time_content(&data_row.time, options)),
+                );
+                let values = data_row
+                    .values
+                    .iter()
+                    .enumerate()
+                    .map(move |(col_idx, value)| {
+                        let content = value_content(value.as_deref(), col_idx, filled, options);
+                        (
+                            template.first_column + col_idx as u32,
+                            row,
+                            display_text(&content),
+                        )
+                    });
+                std::iter::once(time).chain(values)
+            });
+        template.column_widths(header_cells.chain(data_cells))
+    } else {
+        Default::default()
+    };
+    let layout = SheetLayout {
+        hidden: false,
+        widths,
+        frozen_rows: template.frozen_rows(),
+        auto_filter: template.auto_filter_range(columns, data_rows.len()),
+    };
+
+    let mut writer = StreamWriter::create(path)?;
+    writer.start_sheet(header.get_name(), &layout)?;
+    writer.copy_rows(header, 1, template.start_row.saturating_sub(1))?;
+
+    let time_style = CellStyle::of(&template.time_cell_style(false));
+    let flagged_time_style = CellStyle::of(&template.time_cell_style(true));
+    let value_style = CellStyle::default();
+    let flagged_rows: HashSet<usize> = report.time_issues.iter().map(|issue| issue.row).collect();
+    let flagged_cells: HashMap<(usize, usize), CellStyle> = report
+        .qc_flags
+        .iter()
+        .map(|flag| {
+            let style = CellStyle {
+                fill: Some(flag.kind.fill().to_string()),
+                ..Default::default()
+            };
+            ((flag.index, flag.column), style)
+        })
+        .collect();
+    let progress = progress::bar(data_rows.len(), "写入模板", "行");
+    let mut cells = Vec::with_capacity(columns + 1);
+    for (row_idx, data_row) in data_rows.iter().enumerate() {
+        progress.inc(1);
+        cells.clear();
+        let filled = data_row.source_row == 0;
+        let base = if filled || flagged_rows.contains(&data_row.source_row) {
+            &flagged_time_style
+        } else {
+            &time_style
+        };
+        let (value, style) = cell_value(&time_content(&data_row.time, options), base);
+        cells.push((template.time_column, value, writer.style(&style)));
+        for (col_idx, value) in data_row.values.iter().enumerate() {
+            let content = value_content(value.as_deref(), col_idx, filled, options);
+            let base = flagged_cells
+                .get(&(row_idx, col_idx))
+                .unwrap_or(&value_style);
+            let (value, style) = cell_value(&content, base);
+            cells.push((
+                template.first_column + col_idx as u32,
+                value,
+                writer.style(&style),
+            ));
+        }
+        writer.write_row(template.start_row + row_idx as u32, &cells)?;
+    }
+    writer.end_sheet()?;
+
+    for sheet in &sheets[1..] {
+        writer.copy_sheet(sheet)?;
+    }
+    writer.finish(book)
+}
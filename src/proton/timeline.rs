@@ -0,0 +1,275 @@
+//! 时间列连续性检查：时间应按固定间隔严格递增，缺失的时间点、重复或乱序的时间都会被报告。
+
+use std::collections::HashSet;
+use std::fmt;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+
+pub(super) const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 默认的数据时间间隔。
+pub const DEFAULT_INTERVAL: TimeDelta = TimeDelta::hours(1);
+
+/// 解析时间间隔，如 `1h`、`30m`/`30min`、`90s`、`1d`。
+pub(crate) fn parse_interval(value: &str) -> Result<TimeDelta, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("无法解析时间间隔: {value}"))?;
+    let interval = match unit.trim() {
+        "s" | "sec" => TimeDelta::try_seconds(number),
+        "m" | "min" => TimeDelta::try_minutes(number),
+        "h" | "" => TimeDelta::try_hours(number),
+        "d" => TimeDelta::try_days(number),
+        _ => return Err(format!("时间间隔的单位必须是 s、m、h 或 d: {value}")),
+    };
+    interval
+        .filter(|interval| *interval > TimeDelta::zero())
+        .ok_or_else(|| format!("时间间隔必须大于 0: {value}"))
+}
+
+/// 解析带符号的时间平移量，如 `+1h`、`-1h`、`30m`（不带符号即向后平移）；`0` 表示不平移。
+pub(crate) fn parse_shift(value: &str) -> Result<TimeDelta, String> {
+    let value = value.trim();
+    let (negative, magnitude) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    if magnitude.trim_end_matches(|c: char| c.is_ascii_alphabetic()) == "0" {
+        return Ok(TimeDelta::zero());
+    }
+    let shift = parse_interval(magnitude)?;
+    Ok(if negative { -shift } else { shift })
+}
+
+/// 解析报告期的起点，如 `2026-01-01T00:00`、`2026-01-01 00:00:00`；只有日期时为当天 0 时。
+pub(crate) fn parse_period_start(value: &str) -> Result<NaiveDateTime, String> {
+    parse_period_bound(value, NaiveTime::MIN)
+}
+
+/// 解析报告期的终点（含），格式同 [`parse_period_start`]；只有日期时为当天 23:59:59。
+pub(crate) fn parse_period_end(value: &str) -> Result<NaiveDateTime, String> {
+    parse_period_bound(
+        value,
+        NaiveTime::from_hms_opt(23, 59, 59).expect("valid time"),
+    )
+}
+
+fn parse_period_bound(value: &str, date_only: NaiveTime) -> Result<NaiveDateTime, String> {
+    let value = value.trim();
+    let normalized = value.replacen('T', " ", 1);
+    [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(&normalized, format).ok())
+    .or_else(|| {
+        ["%Y-%m-%d", "%Y/%m/%d"]
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+            .map(|date| date.and_time(date_only))
+    })
+    .ok_or_else(|| format!("无法解析时间（应为 2026-01-01T00:00 或 2026-01-01）: {value}"))
+}
+
+/// 时间列中的一个问题。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeIssue {
+    /// 源表中的行号（1 起计）
+    pub row: usize,
+    /// 该行的时间
+    pub time: String,
+    pub kind: TimeIssueKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeIssueKind {
+    /// 与上一个时间之间缺少 `missing` 个时间点
+    Gap { previous: String, missing: usize },
+    /// 与之前某行的时间重复
+    Duplicate,
+    /// 早于上一个时间
+    OutOfOrder { previous: String },
+    /// 与上一个时间的间隔不是设定间隔的整数倍
+    Irregular { previous: String },
+    /// 无法解析的时间
+    Unparsable,
+}
+
+impl fmt::Display for TimeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第 {} 行 {}: ", self.row, self.time)?;
+        match &self.kind {
+            TimeIssueKind::Gap { previous, missing } => {
+                write!(f, "与上一时间 {previous} 之间缺少 {missing} 个时间点")
+            }
+            TimeIssueKind::Duplicate => write!(f, "时间重复"),
+            TimeIssueKind::OutOfOrder { previous } => write!(f, "早于上一时间 {previous}"),
+            TimeIssueKind::Irregular { previous } => {
+                write!(f, "与上一时间 {previous} 的间隔不是设定间隔的整数倍")
+            }
+            TimeIssueKind::Unparsable => write!(f, "无法解析的时间"),
+        }
+    }
+}
+
+/// `previous` 之后按 `interval` 排列的 `missing` 个时间点，用于补齐缺失的数据行。
+pub(crate) fn missing_times(previous: &str, missing: usize, interval: TimeDelta) -> Vec<String> {
+    let Ok(previous) = NaiveDateTime::parse_from_str(previous, TIME_FORMAT) else {
+        return Vec::new();
+    };
+    (1..=missing as i32)
+        .map(|k| (previous + interval * k).format(TIME_FORMAT).to_string())
+        .collect()
+}
+
+/// 检查 `(源表行号, 时间)` 序列是否按 `interval` 严格递增。时间应已格式化为
+/// `YYYY-MM-DD HH:MM:SS`。
+pub(crate) fn check<'a>(
+    times: impl IntoIterator<Item = (usize, &'a str)>,
+    interval: TimeDelta,
+) -> Vec<TimeIssue> {
+    let mut issues = Vec::new();
+    let mut seen: HashSet<NaiveDateTime> = HashSet::new();
+    let mut latest: Option<(NaiveDateTime, &str)> = None;
+
+    for (row, time) in times {
+        let issue = |kind| TimeIssue {
+            row,
+            time: time.to_string(),
+            kind,
+        };
+        let Ok(parsed) = NaiveDateTime::parse_from_str(time, TIME_FORMAT) else {
+            issues.push(issue(TimeIssueKind::Unparsable));
+            continue;
+        };
+        if !seen.insert(parsed) {
+            issues.push(issue(TimeIssueKind::Duplicate));
+            continue;
+        }
+        if let Some((last, last_text)) = latest {
+            let previous = last_text.to_string();
+            if parsed < last {
+                issues.push(issue(TimeIssueKind::OutOfOrder { previous }));
+                continue;
+            }
+            let step = parsed - last;
+            if step != interval {
+                let step_ms = step.num_milliseconds();
+                let interval_ms = interval.num_milliseconds();
+                if step_ms % interval_ms == 0 {
+                    let missing = (step_ms / interval_ms - 1) as usize;
+                    issues.push(issue(TimeIssueKind::Gap { previous, missing }));
+                } else {
+                    issues.push(issue(TimeIssueKind::Irregular { previous }));
+                }
+            }
+        }
+        latest = Some((parsed, time));
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_interval_units() {
+        assert_eq!(parse_interval("1h"), Ok(TimeDelta::hours(1)));
+        assert_eq!(parse_interval("30min"), Ok(TimeDelta::minutes(30)));
+        assert_eq!(parse_interval("5m"), Ok(TimeDelta::minutes(5)));
+        assert!(parse_interval("0h").is_err());
+        assert!(parse_interval("1w").is_err());
+    }
+
+    #[test]
+    fn parses_signed_shifts() {
+        assert_eq!(parse_shift("+1h"), Ok(TimeDelta::hours(1)));
+        assert_eq!(parse_shift("-1h"), Ok(TimeDelta::hours(-1)));
+        assert_eq!(parse_shift("30m"), Ok(TimeDelta::minutes(30)));
+        assert_eq!(parse_shift("0"), Ok(TimeDelta::zero()));
+        assert!(parse_shift("+1w").is_err());
+    }
+
+    #[test]
+    fn parses_period_bounds() {
+        let time = |text| NaiveDateTime::parse_from_str(text, TIME_FORMAT).expect("valid time");
+        assert_eq!(
+            parse_period_start("2026-01-01T00:00"),
+            Ok(time("2026-01-01 00:00:00"))
+        );
+        assert_eq!(
+            parse_period_end("2026-01-31 23:00:00"),
+            Ok(time("2026-01-31 23:00:00"))
+        );
+        assert_eq!(
+            parse_period_start("2026/1/5"),
+            Ok(time("2026-01-05 00:00:00"))
+        );
+        assert_eq!(
+            parse_period_end("2026-01-31"),
+            Ok(time("2026-01-31 23:59:59"))
+        );
+        assert!(parse_period_start("1月5日").is_err());
+    }
+
+    #[test]
+    fn lists_missing_times_after_previous() {
+        assert_eq!(
+            missing_times("2026-01-05 23:00:00", 2, DEFAULT_INTERVAL),
+            ["2026-01-06 00:00:00", "2026-01-06 01:00:00"]
+        );
+    }
+
+    #[test]
+    fn reports_gaps_duplicates_and_disorder() {
+        let times = [
+            (2, "2026-01-05 01:00:00"),
+            (3, "2026-01-05 02:00:00"),
+            (4, "2026-01-05 05:00:00"),
+            (5, "2026-01-05 05:00:00"),
+            (6, "2026-01-05 04:00:00"),
+            (7, "2026-01-05 05:30:00"),
+            (8, "昨天"),
+        ];
+        let kinds: Vec<_> = check(times, DEFAULT_INTERVAL)
+            .into_iter()
+            .map(|issue| (issue.row, issue.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    4,
+                    TimeIssueKind::Gap {
+                        previous: "2026-01-05 02:00:00".to_string(),
+                        missing: 2
+                    }
+                ),
+                (5, TimeIssueKind::Duplicate),
+                (
+                    6,
+                    TimeIssueKind::OutOfOrder {
+                        previous: "2026-01-05 05:00:00".to_string()
+                    }
+                ),
+                (
+                    7,
+                    TimeIssueKind::Irregular {
+                        previous: "2026-01-05 05:00:00".to_string()
+                    }
+                ),
+                (8, TimeIssueKind::Unparsable),
+            ]
+        );
+    }
+}
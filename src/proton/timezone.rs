@@ -0,0 +1,105 @@
+//! 时区：源数据中带时区的时间（ISO 8601 的 `Z`、`+08:00`）以及按设定时区记录的时间，
+//! 统一换算为站点当地时间后输出。
+//!
+//! 只支持固定偏移：`UTC`、`+08:00`/`UTC+8` 这样的偏移，以及没有夏令时的几个 IANA 时区名。
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+
+/// 默认的站点时区（北京时间）。
+pub const DEFAULT_TIMEZONE: &str = "Asia/Shanghai";
+
+/// 支持的时区名及其 UTC 偏移（小时）。
+const NAMED_ZONES: [(&str, i32); 9] = [
+    ("Asia/Shanghai", 8),
+    ("Asia/Chongqing", 8),
+    ("Asia/Harbin", 8),
+    ("Asia/Hong_Kong", 8),
+    ("Asia/Macau", 8),
+    ("Asia/Taipei", 8),
+    ("Asia/Urumqi", 6),
+    ("Etc/UTC", 0),
+    ("UTC", 0),
+];
+
+/// 解析时区：IANA 时区名（见 [`NAMED_ZONES`]）、`Z`、`+08:00`、`+0800`、`+8` 或 `UTC+8`。
+pub(crate) fn parse_timezone(value: &str) -> Result<FixedOffset, String> {
+    let value = value.trim();
+    if let Some(&(_, hours)) = NAMED_ZONES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+    {
+        return Ok(FixedOffset::east_opt(hours * 3600).expect("valid offset"));
+    }
+    let error = || format!("无法识别的时区: {value}（可用 Asia/Shanghai、UTC、+08:00 等）");
+    let offset = value
+        .strip_prefix("UTC")
+        .or_else(|| value.strip_prefix("GMT"))
+        .unwrap_or(value);
+    if offset == "Z" {
+        return Ok(FixedOffset::east_opt(0).expect("valid offset"));
+    }
+    let (sign, digits) = match offset.as_bytes().first() {
+        Some(b'+') => (1, &offset[1..]),
+        Some(b'-') => (-1, &offset[1..]),
+        _ => return Err(error()),
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => (digits, "0"),
+    };
+    let hours: i32 = hours.parse().map_err(|_| error())?;
+    let minutes: i32 = minutes.parse().map_err(|_| error())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(error());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(error)
+}
+
+/// 解析带时区的时间，如 `2026-01-05T01:00:00Z`、`2026-01-05 01:00:00+08:00`；
+/// 不带时区时返回 `None`。
+pub(super) fn parse_with_offset(text: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(text)
+        .or_else(|_| DateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .or_else(|_| DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%z"))
+        .ok()
+}
+
+/// 把在 `source` 时区记录的当地时间换算为 `target` 时区的当地时间。
+pub(super) fn convert_local(
+    time: NaiveDateTime,
+    source: FixedOffset,
+    target: FixedOffset,
+) -> NaiveDateTime {
+    time - source + target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_offsets() {
+        let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(parse_timezone("Asia/Shanghai"), Ok(east8));
+        assert_eq!(parse_timezone("+08:00"), Ok(east8));
+        assert_eq!(parse_timezone("UTC+8"), Ok(east8));
+        assert_eq!(parse_timezone("+0800"), Ok(east8));
+        assert_eq!(parse_timezone("UTC"), Ok(FixedOffset::east_opt(0).unwrap()));
+        assert_eq!(
+            parse_timezone("-05:30"),
+            Ok(FixedOffset::west_opt(5 * 3600 + 1800).unwrap())
+        );
+        assert!(parse_timezone("Europe/Berlin").is_err());
+        assert!(parse_timezone("+25").is_err());
+    }
+
+    #[test]
+    fn parses_times_with_offsets() {
+        let utc = parse_with_offset("2026-01-05T01:00:00Z").expect("utc");
+        assert_eq!(utc.offset().local_minus_utc(), 0);
+        assert!(parse_with_offset("2026-01-05T01:00:00+0800").is_some());
+        assert!(parse_with_offset("2026-01-05 01:00:00+08:00").is_some());
+        assert!(parse_with_offset("2026-01-05 01:00:00").is_none());
+    }
+}
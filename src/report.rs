@@ -0,0 +1,631 @@
+//! 单元格变更记录，用于试运行输出、输出工作簿中的“变更记录”工作表和 JSON 审计报告；
+//! 以及宽松模式下跳过的数据问题（“错误”工作表）、删除的备注（“备注”工作表）和运行摘要
+//! （`--json-summary`）；处理时写出（按源文件合并）的 `manifest.json`。
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{Context, DtToolsError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+use umya_spreadsheet::Spreadsheet;
+use umya_spreadsheet::structs::SheetStateValues;
+
+/// 变更记录工作表的名称。
+pub const CHANGE_SHEET: &str = "变更记录";
+
+const CHANGE_SHEET_HEADERS: [&str; 6] = ["工作表", "单元格", "原值", "新值", "规则", "时间"];
+
+/// 数据问题工作表的名称。
+pub const PROBLEM_SHEET: &str = "错误";
+
+const PROBLEM_SHEET_HEADERS: [&str; 5] = ["工作表", "行", "列", "值", "问题"];
+
+/// 备注工作表的名称。
+pub const NOTE_SHEET: &str = "备注";
+
+const NOTE_SHEET_HEADERS: [&str; 3] = ["工作表", "单元格", "备注"];
+
+/// 旧版本输出中处理信息工作表的名称。
+pub const METADATA_SHEET: &str = "处理信息";
+
+/// 一个单元格的变更：所在工作表、A1 地址、修改前后的值、是否设置红色背景以及命中的规则。
+///
+/// 规则编号：eemcg 为 `replace#N`、`missing_value#N`、`flag#N`、`convert#N`（规则文件中的序号，1 起计）和
+/// `brackets`（删除括号内容）；proton 为 `time_format`、`flagged`（(C)/(RM) 标记）、`number_format`（千位分隔符、小数逗号等规范化）、
+/// `below_limit`（低于检出限）、`non_numeric`
+/// 以及质量检查的 `range`、`spike`、`stuck`（值不变，地址为输出模板中的单元格）。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CellChange {
+    pub sheet: String,
+    pub address: String,
+    pub before: String,
+    pub after: String,
+    pub red_fill: bool,
+    pub rules: Vec<String>,
+}
+
+/// 从单元格中删除的备注（如括号中的说明）：所在工作表、A1 地址与文字。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Note {
+    pub sheet: String,
+    pub address: String,
+    pub text: String,
+}
+
+/// 宽松模式下跳过的数据问题：源工作表、行号（1 起计；整列缺失时为 0）、列、原值与原因。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Problem {
+    pub sheet: String,
+    pub row: usize,
+    pub column: String,
+    pub value: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.row > 0 {
+            write!(f, "第 {} 行 ", self.row)?;
+        }
+        write!(f, "{}: {}", self.column, self.message)?;
+        if !self.value.is_empty() {
+            write!(f, " ({:?})", self.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// 一个输入文件的处理结果：输出路径（试运行时为将要写出的路径）、处理的数据行数、
+/// 警告数（时间列问题、可疑值等）、全部单元格变更与宽松模式下跳过的数据问题。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProcessedFile {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// 附加导出的文件（`--also-csv` 的 CSV/TSV）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exports: Vec<PathBuf>,
+    pub rows: usize,
+    pub warnings: usize,
+    pub changes: Vec<CellChange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<Problem>,
+    /// `--resume` 时此前已处理、本次跳过的文件
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+}
+
+impl ProcessedFile {
+    /// 本次跳过的文件：输出为此前写出的文件，没有变更。
+    pub fn skipped(input: &Path, output: &Path) -> Self {
+        Self {
+            input: input.to_path_buf(),
+            output: output.to_path_buf(),
+            exports: Vec::new(),
+            rows: 0,
+            warnings: 0,
+            changes: Vec::new(),
+            problems: Vec::new(),
+            skipped: true,
+        }
+    }
+}
+
+/// 本次运行处理过的文件，供结束时的运行摘要使用；克隆的副本共享同一份记录。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Recorder(Arc<Mutex<Vec<ProcessedFile>>>);
+
+impl Recorder {
+    pub(crate) fn record(&self, files: &[ProcessedFile]) {
+        self.0
+            .lock()
+            .expect("recorder lock")
+            .extend_from_slice(files);
+    }
+
+    pub(crate) fn files(&self) -> Vec<ProcessedFile> {
+        self.0.lock().expect("recorder lock").clone()
+    }
+}
+
+/// 一次运行的摘要：结果状态与退出码、处理的文件数、数据行数、单元格变更数、警告数和
+/// 宽松模式下跳过的问题数。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub tool: String,
+    /// `ok`、`partial`、`validation_failed`、`io_error` 或 `error`
+    pub status: &'static str,
+    pub exit_code: i32,
+    pub dry_run: bool,
+    pub files: usize,
+    pub failed_files: usize,
+    pub rows: usize,
+    pub changes: usize,
+    pub warnings: usize,
+    pub problems: usize,
+    /// 失败时的错误信息（含完整的错误链）
+    pub error: Option<String>,
+}
+
+impl Summary {
+    pub fn new(tool: &str, dry_run: bool, files: &[ProcessedFile], result: &Result<()>) -> Self {
+        let (status, exit_code, error) = match result {
+            Ok(()) => ("ok", 0, None),
+            Err(err) => (err.status(), err.exit_code(), Some(format!("{err:#}"))),
+        };
+        let failed_files = match result.as_ref().map_err(DtToolsError::kind) {
+            Ok(()) | Err(DtToolsError::Partial { .. }) => 0,
+            Err(DtToolsError::Batch { failed, .. }) => *failed,
+            Err(DtToolsError::Validation { files, .. }) => *files,
+            Err(_) => 1,
+        };
+        Self {
+            tool: tool.to_string(),
+            status,
+            exit_code,
+            dry_run,
+            files: files.len(),
+            failed_files,
+            rows: files.iter().map(|file| file.rows).sum(),
+            changes: files.iter().map(|file| file.changes.len()).sum(),
+            warnings: files.iter().map(|file| file.warnings).sum(),
+            problems: files.iter().map(|file| file.problems.len()).sum(),
+            error,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    tool: &'a str,
+    generated_at: String,
+    dry_run: bool,
+    files: &'a [ProcessedFile],
+}
+
+/// 把本次运行处理的全部文件及其单元格变更写成 JSON（`--report`）。
+pub(crate) fn write_json(
+    path: &Path,
+    tool: &str,
+    dry_run: bool,
+    files: &[ProcessedFile],
+) -> Result<()> {
+    let report = JsonReport {
+        tool,
+        generated_at: chrono::Local::now().to_rfc3339(),
+        dry_run,
+        files,
+    };
+    let json = serde_json::to_string_pretty(&report).context("无法生成变更报告")?;
+    fs::write(path, json + "\n").with_context(|| format!("无法写入变更报告: {}", path.display()))
+}
+
+/// 批量处理时在输出目录中写出的清单文件名。
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// 清单中的一个输出文件：路径（在清单所在目录中时为文件名）、大小、SHA-256、源文件与数据行数。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file: PathBuf,
+    pub bytes: u64,
+    pub sha256: String,
+    pub source: PathBuf,
+    pub rows: usize,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    tool: &'a str,
+    version: &'a str,
+    generated_at: String,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize)]
+struct ExistingManifest {
+    files: Vec<ManifestEntry>,
+}
+
+/// 把本次处理写出的全部文件（输出工作簿与附加导出的 CSV、图表）及其 SHA-256 写入清单，
+/// 供接收方核对数据是否完整。清单已存在时按源文件合并：本次处理过的源文件的条目被替换，
+/// 其余条目保留，因此逐个文件或监视模式下的多次运行不会互相覆盖。
+pub(crate) fn write_manifest(path: &Path, tool: &str, files: &[ProcessedFile]) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut entries = existing_manifest_entries(path);
+    entries.retain(|entry| {
+        !files
+            .iter()
+            .any(|processed| processed.input == entry.source)
+    });
+    for processed in files {
+        for output in std::iter::once(&processed.output).chain(&processed.exports) {
+            let content = fs::read(output).map_err(|err| DtToolsError::file_open(output, err))?;
+            entries.push(ManifestEntry {
+                file: output.strip_prefix(dir).unwrap_or(output).to_path_buf(),
+                bytes: content.len() as u64,
+                sha256: sha256_hex(&content),
+                source: processed.input.clone(),
+                rows: processed.rows,
+            });
+        }
+    }
+    let manifest = Manifest {
+        tool,
+        version: env!("CARGO_PKG_VERSION"),
+        generated_at: chrono::Local::now().to_rfc3339(),
+        files: entries,
+    };
+    let json = serde_json::to_string_pretty(&manifest).context("无法生成清单")?;
+    fs::write(path, json + "\n").map_err(|err| DtToolsError::write(path, err))
+}
+
+/// 读取已有清单中的条目；清单不存在时返回空，无法解析时警告并返回空（随后被整个重写）。
+fn existing_manifest_entries(path: &Path) -> Vec<ManifestEntry> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<ExistingManifest>(&text) {
+        Ok(manifest) => manifest.files,
+        Err(err) => {
+            warn!("无法解析已有清单 {}，将重新生成: {err}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+/// 内容的 SHA-256（64 位小写十六进制）。
+pub(crate) fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// 在工作簿末尾追加隐藏的“变更记录”工作表，列出每个被修改的单元格。
+/// 已有同名工作表（再次处理的输出文件）时先将其替换。
+pub(crate) fn append_change_sheet(book: &mut Spreadsheet, changes: &[CellChange], timestamp: &str) {
+    remove_sheet(book, CHANGE_SHEET);
+    let sheet = book
+        .new_sheet(CHANGE_SHEET)
+        .expect("change sheet name is free");
+    sheet.set_state(SheetStateValues::Hidden);
+    for (col, header) in CHANGE_SHEET_HEADERS.iter().enumerate() {
+        sheet.get_cell_mut((col as u32 + 1, 1)).set_value(*header);
+    }
+    for (i, change) in changes.iter().enumerate() {
+        let row = i as u32 + 2;
+        let values = [
+            change.sheet.as_str(),
+            change.address.as_str(),
+            change.before.as_str(),
+            change.after.as_str(),
+            &change.rules.join(", "),
+            timestamp,
+        ];
+        for (col, value) in values.into_iter().enumerate() {
+            sheet
+                .get_cell_mut((col as u32 + 1, row))
+                .set_value_string(value);
+        }
+    }
+}
+
+/// 删除旧版本输出中隐藏的“处理信息”工作表（处理信息现只写入自定义文档属性）。
+pub(crate) fn remove_metadata_sheet(book: &mut Spreadsheet) {
+    remove_sheet(book, METADATA_SHEET);
+}
+
+/// 删除名为 `name` 的工作表（如有），活动工作表保持不变。
+fn remove_sheet(book: &mut Spreadsheet, name: &str) {
+    if let Some(index) = book
+        .get_sheet_collection()
+        .iter()
+        .position(|sheet| sheet.get_name() == name)
+    {
+        let active = *book.get_workbook_view().get_active_tab() as usize;
+        book.remove_sheet(index).expect("sheet index is valid");
+        if active > index {
+            book.set_active_sheet((active - 1) as u32);
+        }
+    }
+}
+
+/// 在工作簿末尾追加“备注”工作表，列出从单元格中删除的备注；没有备注时不追加。
+/// 已有同名工作表（再次处理的输出文件）时先将其删除。
+pub(crate) fn append_note_sheet(book: &mut Spreadsheet, notes: &[Note]) {
+    if notes.is_empty() {
+        return;
+    }
+    remove_sheet(book, NOTE_SHEET);
+    let sheet = book.new_sheet(NOTE_SHEET).expect("note sheet name is free");
+    for (col, header) in NOTE_SHEET_HEADERS.iter().enumerate() {
+        sheet.get_cell_mut((col as u32 + 1, 1)).set_value(*header);
+    }
+    for (i, note) in notes.iter().enumerate() {
+        let row = i as u32 + 2;
+        let values = [&note.sheet, &note.address, &note.text];
+        for (col, value) in values.into_iter().enumerate() {
+            sheet
+                .get_cell_mut((col as u32 + 1, row))
+                .set_value_string(value);
+        }
+    }
+}
+
+/// 在工作簿末尾追加“错误”工作表，列出宽松模式下跳过的数据问题。
+pub(crate) fn append_problem_sheet(book: &mut Spreadsheet, problems: &[Problem]) {
+    let sheet = book
+        .new_sheet(PROBLEM_SHEET)
+        .expect("problem sheet name is free");
+    for (col, header) in PROBLEM_SHEET_HEADERS.iter().enumerate() {
+        sheet.get_cell_mut((col as u32 + 1, 1)).set_value(*header);
+    }
+    for (i, problem) in problems.iter().enumerate() {
+        let row = i as u32 + 2;
+        sheet
+            .get_cell_mut((1, row))
+            .set_value_string(&problem.sheet);
+        if problem.row > 0 {
+            sheet
+                .get_cell_mut((2, row))
+                .set_value_number(problem.row as f64);
+        }
+        sheet
+            .get_cell_mut((3, row))
+            .set_value_string(&problem.column);
+        sheet
+            .get_cell_mut((4, row))
+            .set_value_string(&problem.value);
+        sheet
+            .get_cell_mut((5, row))
+            .set_value_string(&problem.message);
+    }
+}
+
+/// 逐条警告宽松模式下跳过的数据问题。
+pub(crate) fn warn_problems(problems: &[Problem]) {
+    if problems.is_empty() {
+        return;
+    }
+    warn!("宽松模式跳过了 {} 个数据问题:", problems.len());
+    for problem in problems {
+        warn!("  {problem}");
+    }
+}
+
+/// 跳过了 `problems` 个数据问题时返回 [`DtToolsError::Partial`]，程序以部分成功的退出码结束。
+pub(crate) fn partial(problems: usize) -> Result<()> {
+    if problems > 0 {
+        Err(DtToolsError::Partial { problems })
+    } else {
+        Ok(())
+    }
+}
+
+/// 在详细日志（`--verbose`）中逐条记录单元格修改及命中的规则。
+pub(crate) fn log_changes(changes: &[CellChange]) {
+    for change in changes {
+        debug!(
+            sheet = %change.sheet,
+            address = %change.address,
+            before = %change.before,
+            after = %change.after,
+            rules = %change.rules.join(","),
+            red_fill = change.red_fill,
+            "修改单元格"
+        );
+    }
+}
+
+/// 打印试运行时将要进行的变更。
+pub(crate) fn print_planned_changes(changes: &[CellChange]) {
+    if changes.is_empty() {
+        info!("[试运行] 没有需要修改的单元格");
+        return;
+    }
+    info!("[试运行] 共 {} 个单元格将被修改:", changes.len());
+    for change in changes {
+        info!(
+            "  {}!{}: {:?} -> {:?}{}",
+            change.sheet,
+            change.address,
+            change.before,
+            change.after,
+            if change.red_fill {
+                " [红色填充]"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_report_lists_changes_per_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("changes.json");
+        let files = [ProcessedFile {
+            input: PathBuf::from("in.xlsx"),
+            output: PathBuf::from("processed_in.xlsx"),
+            exports: Vec::new(),
+            rows: 26,
+            warnings: 0,
+            changes: vec![CellChange {
+                sheet: "NMHC监测仪".to_string(),
+                address: "I4".to_string(),
+                before: "-999".to_string(),
+                after: "-999#a24041".to_string(),
+                red_fill: false,
+                rules: vec!["missing_value#1".to_string()],
+            }],
+            problems: Vec::new(),
+            skipped: false,
+        }];
+        write_json(&path, "eemcg", true, &files)?;
+
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(json["tool"], "eemcg");
+        assert_eq!(json["dry_run"], true);
+        let change = &json["files"][0]["changes"][0];
+        assert_eq!(change["address"], "I4");
+        assert_eq!(change["after"], "-999#a24041");
+        assert_eq!(change["rules"][0], "missing_value#1");
+        assert!(json["files"][0].get("problems").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_merges_runs_by_source() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let processed = |name: &str| -> Result<ProcessedFile> {
+            let output = dir.path().join(format!("processed_{name}.xlsx"));
+            fs::write(&output, name)?;
+            Ok(ProcessedFile {
+                input: PathBuf::from(format!("data/{name}.xlsx")),
+                output,
+                exports: Vec::new(),
+                rows: 1,
+                warnings: 0,
+                changes: Vec::new(),
+                problems: Vec::new(),
+                skipped: false,
+            })
+        };
+        let path = dir.path().join(MANIFEST_FILE);
+        write_manifest(&path, "proton", &[processed("a")?, processed("b")?])?;
+        let mut again = processed("b")?;
+        again.rows = 5;
+        write_manifest(&path, "proton", &[again])?;
+
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        let entries = json["files"].as_array().expect("files");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["source"], "data/a.xlsx");
+        assert_eq!(entries[1]["source"], "data/b.xlsx");
+        assert_eq!(entries[1]["rows"], 5);
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_lists_outputs_with_checksums() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("processed_in.xlsx");
+        let csv = dir.path().join("processed_in.csv");
+        fs::write(&output, b"abc")?;
+        fs::write(&csv, b"")?;
+        let files = [ProcessedFile {
+            input: PathBuf::from("data/in.xlsx"),
+            output,
+            exports: vec![csv],
+            rows: 24,
+            warnings: 0,
+            changes: Vec::new(),
+            problems: Vec::new(),
+            skipped: false,
+        }];
+        let path = dir.path().join(MANIFEST_FILE);
+        write_manifest(&path, "proton", &files)?;
+
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(json["tool"], "proton");
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        let entries = json["files"].as_array().expect("files");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["file"], "processed_in.xlsx");
+        assert_eq!(entries[0]["bytes"], 3);
+        assert_eq!(
+            entries[0]["sha256"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(entries[0]["source"], "data/in.xlsx");
+        assert_eq!(entries[1]["file"], "processed_in.csv");
+        assert_eq!(entries[1]["rows"], 24);
+        assert_eq!(
+            entries[1]["sha256"],
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn summary_counts_files_and_outcome() {
+        let file = |rows, warnings, problems| ProcessedFile {
+            input: PathBuf::from("in.csv"),
+            output: PathBuf::from("processed_in.xlsx"),
+            exports: Vec::new(),
+            rows,
+            warnings,
+            changes: vec![CellChange {
+                sheet: "Sheet1".to_string(),
+                address: "B2".to_string(),
+                before: "N/A".to_string(),
+                after: String::new(),
+                red_fill: false,
+                rules: vec!["non_numeric".to_string()],
+            }],
+            problems: vec![
+                Problem {
+                    sheet: "Sheet1".to_string(),
+                    row: 3,
+                    column: "A3".to_string(),
+                    value: "x".to_string(),
+                    message: "无法解析时间格式: x".to_string(),
+                };
+                problems
+            ],
+            skipped: false,
+        };
+        let files = [file(24, 2, 0), file(10, 1, 1)];
+
+        let ok = Summary::new("proton", false, &files, &Ok(()));
+        assert_eq!((ok.status, ok.exit_code), ("ok", 0));
+        assert_eq!((ok.files, ok.rows, ok.changes), (2, 34, 2));
+        assert_eq!((ok.warnings, ok.problems), (3, 1));
+        assert_eq!(ok.error, None);
+
+        let failed = Err(DtToolsError::Batch {
+            failed: 1,
+            total: 3,
+            first: "a.xlsx: 找不到'时间'列".to_string(),
+        });
+        let partial = Summary::new("proton", false, &files, &failed);
+        assert_eq!((partial.status, partial.exit_code), ("partial", 4));
+        assert_eq!(partial.failed_files, 1);
+        let json = serde_json::to_value(&partial).expect("serializable");
+        assert_eq!(json["error"], "1 个文件处理失败（共 3 个）");
+    }
+
+    #[test]
+    fn change_sheet_is_hidden_and_replaced() {
+        let mut book = umya_spreadsheet::new_file();
+        let change = CellChange {
+            sheet: "Sheet1".to_string(),
+            address: "C3".to_string(),
+            before: "1.2(H)".to_string(),
+            after: "1.2".to_string(),
+            red_fill: true,
+            rules: vec!["brackets".to_string()],
+        };
+        append_change_sheet(&mut book, &[change.clone(), change], "2026-01-05 10:00:00");
+        let sheet = book.get_sheet_by_name(CHANGE_SHEET).expect("change sheet");
+        assert_eq!(sheet.get_value("B3"), "C3");
+        assert_eq!(sheet.get_value("E3"), "brackets");
+        assert_eq!(sheet.get_value("F3"), "2026-01-05 10:00:00");
+
+        append_change_sheet(&mut book, &[], "2026-01-06 10:00:00");
+
+        assert_eq!(book.get_sheet_count(), 2);
+        assert_eq!(book.get_active_sheet().get_name(), "Sheet1");
+        let sheet = book.get_sheet_by_name(CHANGE_SHEET).expect("change sheet");
+        assert!(matches!(sheet.get_state(), SheetStateValues::Hidden));
+        assert_eq!(sheet.get_value("E1"), "规则");
+        assert_eq!(sheet.get_value("A2"), "");
+    }
+}
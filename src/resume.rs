@@ -0,0 +1,221 @@
+//! 批量处理的断点续传：每处理完一个文件，就把输入内容的 SHA-256、规则/配置指纹与输出路径
+//! 追加到状态文件（`.dttools_state.jsonl`，位于输出目录）。断电或崩溃后以 `--resume` 重新运行
+//! 同一批文件时，内容与规则/配置均未变、输出仍在的文件直接跳过；不指定 `--resume` 时全部处理。
+//!
+//! 状态文件每行一条记录，写入后立即落盘；最后一行因断电而不完整时忽略该行。
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::{Context, DtToolsError, Result};
+use crate::report;
+
+/// 状态文件名。
+pub const STATE_FILE: &str = ".dttools_state.jsonl";
+
+/// 状态文件中的一条记录：处理完成的输入文件。
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    tool: String,
+    sha256: String,
+    /// 规则/配置指纹，与已处理标记中的相同
+    #[serde(default)]
+    settings: String,
+    input: PathBuf,
+    output: PathBuf,
+    finished_at: String,
+}
+
+/// 本次批量处理的续传状态。默认值不跳过也不记录任何文件。
+#[derive(Debug, Default)]
+pub(crate) struct State {
+    /// 状态文件；为 `None` 时不记录
+    path: Option<PathBuf>,
+    tool: String,
+    settings: String,
+    /// 是否跳过已完成的文件
+    skip: bool,
+    /// 已完成的输入内容 SHA-256 → 输出文件
+    done: HashMap<String, PathBuf>,
+    /// 本次跳过的文件：(输入, 输出)
+    skipped: Vec<(PathBuf, PathBuf)>,
+}
+
+impl State {
+    /// 读取 `dir` 中的状态文件里 `tool` 以规则/配置指纹 `settings` 处理的记录。`skip` 为 false
+    /// 时（未指定 `--resume`）不跳过文件，`record` 为 false 时（试运行）不写入状态文件。
+    pub(crate) fn load(
+        dir: &Path,
+        tool: &str,
+        settings: &str,
+        skip: bool,
+        record: bool,
+    ) -> Result<Self> {
+        let path = dir.join(STATE_FILE);
+        let mut done = HashMap::new();
+        if skip && path.exists() {
+            let text =
+                fs::read_to_string(&path).map_err(|err| DtToolsError::file_open(&path, err))?;
+            for entry in text
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Entry>(line).ok())
+                .filter(|entry| entry.tool == tool && entry.settings == settings)
+            {
+                done.insert(entry.sha256, entry.output);
+            }
+        }
+        Ok(Self {
+            path: record.then_some(path),
+            tool: tool.to_string(),
+            settings: settings.to_string(),
+            skip,
+            done,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// 输入文件内容的 SHA-256；不跳过也不记录时不读取文件，返回 `None`。
+    pub(crate) fn digest(&self, input: &Path) -> Result<Option<String>> {
+        if !self.skip && self.path.is_none() {
+            return Ok(None);
+        }
+        let content = fs::read(input).map_err(|err| DtToolsError::file_open(input, err))?;
+        Ok(Some(report::sha256_hex(&content)))
+    }
+
+    /// 内容为 `digest` 的文件已处理完成且输出仍在时返回输出文件。
+    pub(crate) fn completed(&self, digest: &str) -> Option<&Path> {
+        if !self.skip {
+            return None;
+        }
+        self.done
+            .get(digest)
+            .map(PathBuf::as_path)
+            .filter(|output| output.exists())
+    }
+
+    /// 记下本次跳过的文件，见 [`State::skipped_files`]。
+    pub(crate) fn skip(&mut self, input: &Path, output: &Path) {
+        self.skipped
+            .push((input.to_path_buf(), output.to_path_buf()));
+    }
+
+    /// 本次跳过的文件，计入变更报告与清单（没有单元格变更）。
+    pub(crate) fn skipped_files(&self) -> Vec<report::ProcessedFile> {
+        self.skipped
+            .iter()
+            .map(|(input, output)| report::ProcessedFile::skipped(input, output))
+            .collect()
+    }
+
+    /// 记录处理完成的文件；写入失败时只记录警告（不影响本次结果，下次会重新处理）。
+    pub(crate) fn record(&mut self, digest: String, input: &Path, output: &Path) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let entry = Entry {
+            tool: self.tool.clone(),
+            sha256: digest,
+            settings: self.settings.clone(),
+            input: input.to_path_buf(),
+            output: output.to_path_buf(),
+            finished_at: chrono::Local::now().to_rfc3339(),
+        };
+        if let Err(err) = append(path, &entry) {
+            warn!("无法写入状态文件 {}: {err:#}", path.display());
+        }
+        self.done.insert(entry.sha256, entry.output);
+    }
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| DtToolsError::write(path, err))?;
+    // 上一条记录因断电没有写完时另起一行，不与本条记录连在一起
+    if file.metadata()?.len() > 0 {
+        let mut last = [0; 1];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            line.insert(0, '\n');
+        }
+    }
+    writeln!(file, "{line}").context("无法写入状态文件")?;
+    file.sync_data().context("无法写入状态文件")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_completed_files_with_existing_outputs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("a.xlsx");
+        let output = dir.path().join("processed_a.xlsx");
+        fs::write(&input, "a")?;
+        fs::write(&output, "out")?;
+
+        let mut state = State::load(dir.path(), "proton", "r1", true, true)?;
+        let digest = state.digest(&input)?.expect("digest");
+        assert_eq!(state.completed(&digest), None);
+        state.record(digest.clone(), &input, &output);
+        // 模拟断电时写了一半的记录
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(STATE_FILE))?;
+        write!(file, "{{\"tool\":\"proton\",\"sha")?;
+
+        let mut state = State::load(dir.path(), "proton", "r1", true, true)?;
+        assert_eq!(state.completed(&digest), Some(output.as_path()));
+        state.skip(&input, &output);
+        let skipped = state.skipped_files();
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].skipped && skipped[0].output == output);
+        let other = dir.path().join("b.xlsx");
+        state.record("b".repeat(64), &other, &output);
+        let state = State::load(dir.path(), "proton", "r1", true, true)?;
+        assert!(state.completed(&"b".repeat(64)).is_some());
+        assert_eq!(
+            State::load(dir.path(), "eemcg", "r1", true, true)?.completed(&digest),
+            None
+        );
+        assert_eq!(
+            State::load(dir.path(), "proton", "r1", false, true)?.completed(&digest),
+            None
+        );
+        // 规则或配置改变后不跳过
+        assert_eq!(
+            State::load(dir.path(), "proton", "r2", true, true)?.completed(&digest),
+            None
+        );
+
+        fs::remove_file(&output)?;
+        assert_eq!(state.completed(&digest), None);
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_does_not_record() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("a.xlsx");
+        fs::write(&input, "a")?;
+        let mut state = State::load(dir.path(), "proton", "r1", true, false)?;
+        let digest = state.digest(&input)?.expect("digest");
+        state.record(digest, &input, &input);
+        assert!(!dir.path().join(STATE_FILE).exists());
+        assert_eq!(State::default().digest(&input)?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+//! `dttools gen-sample`：生成接近真实导出格式的样例工作簿，便于在本地试用各个处理分支。
+//!
+//! - eemcg：`甲烷非甲烷分析仪`、`VOCs在线监测仪` 两个工作表，第 2 行为项目名称（含需替换的
+//!   `总烃(ppbv)`、`间、对-二甲苯`），第 3 行为因子编码（I3/K3/Q3/AY3 为触发缺测值改写的编码），
+//!   数据中夹有 `-999` 和 `(H)`、`(C)`、`(RM)` 等括号备注。
+//! - proton：离子色谱导出表，第 1 行为表头，数据中夹有 `(C)`/`(RM)` 标记值、`—`/`N/A`
+//!   非数值、不同写法的时间，以及缺失和重复的时间点。
+//!
+//! 数值由固定种子的伪随机数生成，同样的参数总是得到同样的文件。
+
+use std::path::PathBuf;
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use clap::{Args, ValueEnum};
+use tracing::info;
+use umya_spreadsheet::{Spreadsheet, Worksheet};
+
+use crate::cli::{self, GlobalArgs};
+use crate::codes::{self, Factor};
+use crate::error::Result;
+use crate::{export, workbook};
+
+/// 样例类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SampleKind {
+    /// VOCs/NMHC 监测仪导出（dttools eemcg 的输入）
+    Eemcg,
+    /// 离子色谱导出（dttools proton 的输入）
+    Proton,
+}
+
+/// `dttools gen-sample` 的参数。默认写到 `sample_<类型>.xlsx`，`-o` 指定其他文件。
+#[derive(Debug, Clone, Args)]
+pub struct SampleArgs {
+    /// 样例类型
+    #[arg(long, value_enum, default_value_t = SampleKind::Eemcg)]
+    pub kind: SampleKind,
+
+    /// 数据行数（按小时）
+    #[arg(long, value_name = "N", default_value_t = 24)]
+    pub rows: usize,
+
+    /// 随机数种子，换一个种子得到不同的数值
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub seed: u64,
+}
+
+/// eemcg 样例的工作表（重命名前的名称）。
+const EEMCG_SHEETS: [&str; 2] = ["甲烷非甲烷分析仪", "VOCs在线监测仪"];
+
+/// eemcg 样例 B 列起的项目：(第 2 行名称, 典型浓度 ppbv)；第 3 行的因子编码按名称取自编码表。
+/// I、K、Q、AY 列的编码为内置缺测值规则的触发编码。
+const EEMCG_COLUMNS: [(&str, f64); 50] = [
+    ("总烃(ppbv)", 2100.0),
+    ("甲烷(ppbv)", 1950.0),
+    ("非甲烷总烃(ppbv)", 150.0),
+    ("乙烷", 4.5),
+    ("乙烯", 3.2),
+    ("丙烷", 2.8),
+    ("丙烯", 0.9),
+    ("异丁烷", 1.4),
+    ("正丁烷", 1.9),
+    ("乙炔", 2.6),
+    ("反-2-丁烯", 0.12),
+    ("1-丁烯", 0.2),
+    ("顺-2-丁烯", 0.1),
+    ("环戊烷", 0.15),
+    ("异戊烷", 1.6),
+    ("正戊烷", 0.8),
+    ("反-2-戊烯", 0.06),
+    ("1-戊烯", 0.05),
+    ("顺-2-戊烯", 0.04),
+    ("2,2-二甲基丁烷", 0.07),
+    ("2,3-二甲基丁烷", 0.09),
+    ("2-甲基戊烷", 0.45),
+    ("3-甲基戊烷", 0.35),
+    ("异戊二烯", 0.3),
+    ("正己烷", 0.5),
+    ("甲基环戊烷", 0.2),
+    ("2,4-二甲基戊烷", 0.03),
+    ("苯", 0.7),
+    ("环己烷", 0.1),
+    ("2-甲基己烷", 0.12),
+    ("2,3-二甲基戊烷", 0.06),
+    ("3-甲基己烷", 0.14),
+    ("2,2,4-三甲基戊烷", 0.05),
+    ("正庚烷", 0.16),
+    ("甲基环己烷", 0.09),
+    ("2,3,4-三甲基戊烷", 0.02),
+    ("甲苯", 1.8),
+    ("2-甲基庚烷", 0.04),
+    ("3-甲基庚烷", 0.04),
+    ("正辛烷", 0.07),
+    ("乙苯", 0.45),
+    ("间、对-二甲苯", 0.9),
+    ("苯乙烯", 0.2),
+    ("邻二甲苯", 0.35),
+    ("正壬烷", 0.05),
+    ("异丙苯", 0.02),
+    ("正丙苯", 0.04),
+    ("间乙基甲苯", 0.06),
+    ("对乙基甲苯", 0.03),
+    ("1,3,5-三甲苯", 0.05),
+];
+
+/// eemcg 样例中的括号备注。
+const REMARKS: [&str; 5] = ["(H)", "(C)", "(RM)", "(N)", "(备注:校准)"];
+
+/// proton 样例的列：(表头, 典型浓度 μg/m³)，顺序与仪器导出一致。
+const PROTON_COLUMNS: [(&str, f64); 14] = [
+    ("SO₂(μg/m³)", 3.0),
+    ("HNO₃(μg/m³)", 1.2),
+    ("HNO₂(μg/m³)", 0.8),
+    ("HCl(μg/m³)", 0.4),
+    ("NH₃(μg/m³)", 8.0),
+    ("NO₃⁻(μg/m³)", 12.0),
+    ("SO₄²⁻(μg/m³)", 9.5),
+    ("NH₄⁺(μg/m³)", 6.0),
+    ("Cl⁻(μg/m³)", 0.8),
+    ("K⁺(μg/m³)", 0.3),
+    ("Na⁺(μg/m³)", 0.25),
+    ("Mg²⁺(μg/m³)", 0.04),
+    ("Ca²⁺(μg/m³)", 0.3),
+    ("NO₂⁻(μg/m³)", 0.1),
+];
+
+/// 第一个数据点的时间。
+fn start_time() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 1, 5)
+        .and_then(|date| date.and_hms_opt(1, 0, 0))
+        .expect("valid start time")
+}
+
+/// xorshift64* 伪随机数，足够生成样例数据，且不引入额外依赖。
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // 种子为 0 时 xorshift 只会输出 0
+        Self(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// [0, 1) 之间的均匀分布。
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.unit() < probability
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[(self.next_u64() % items.len() as u64) as usize]
+    }
+
+    /// 典型浓度上下浮动 ±40%。
+    fn around(&mut self, typical: f64) -> f64 {
+        typical * (0.6 + 0.8 * self.unit())
+    }
+}
+
+/// 按浓度大小保留有效位数。
+fn format_value(value: f64) -> String {
+    if value >= 100.0 {
+        format!("{value:.1}")
+    } else if value >= 1.0 {
+        format!("{value:.2}")
+    } else {
+        format!("{value:.3}")
+    }
+}
+
+fn fill_eemcg_sheet(sheet: &mut Worksheet, rows: usize, rng: &mut Rng) {
+    let title = format!("{} 小时数据（样例）", sheet.get_name());
+    sheet.get_cell_mut("A1").set_value(title);
+    sheet.get_cell_mut("A2").set_value("时间");
+    sheet.get_cell_mut("A3").set_value("因子编码");
+    let factors: Vec<&Factor> = EEMCG_COLUMNS
+        .iter()
+        .map(|(name, _)| codes::by_name(name).expect("sample species are registered"))
+        .collect();
+    for (i, ((name, _), factor)) in EEMCG_COLUMNS.iter().zip(&factors).enumerate() {
+        let col = i as u32 + 2;
+        sheet.get_cell_mut((col, 2)).set_value(*name);
+        sheet.get_cell_mut((col, 3)).set_value(factor.code);
+    }
+    for index in 0..rows {
+        let row = index as u32 + 4;
+        let time = start_time() + TimeDelta::hours(index as i64);
+        sheet
+            .get_cell_mut((1, row))
+            .set_value(time.format("%Y-%m-%d %H:%M").to_string());
+        for (i, (&(_, typical), factor)) in EEMCG_COLUMNS.iter().zip(&factors).enumerate() {
+            let col = i as u32 + 2;
+            // 有缺测编码的列（内置缺测值规则的触发列）第一行数据为缺测，保证规则一定会被触发
+            let value = if index == 0 && factor.missing.is_some() || rng.chance(0.03) {
+                "-999".to_string()
+            } else if rng.chance(0.04) {
+                format!(
+                    "{}{}",
+                    format_value(rng.around(typical)),
+                    rng.pick(&REMARKS)
+                )
+            } else {
+                format_value(rng.around(typical))
+            };
+            sheet.get_cell_mut((col, row)).set_value(value);
+        }
+    }
+}
+
+/// VOCs/NMHC 监测仪导出样例，`rows` 行小时数据。
+pub fn eemcg_sample(rows: usize, seed: u64) -> Spreadsheet {
+    let mut rng = Rng::new(seed);
+    let mut book = umya_spreadsheet::new_file_empty_worksheet();
+    for name in EEMCG_SHEETS {
+        let sheet = book.new_sheet(name).expect("sample sheet names are unique");
+        fill_eemcg_sheet(sheet, rows, &mut rng);
+    }
+    book.set_active_sheet(0);
+    book
+}
+
+/// 离子色谱导出样例的数据表（首行为表头），`rows` 行小时数据。
+/// 行数不少于 4 时跳过中间的一个时间点，不少于 8 时重复最后一行。
+pub fn proton_table(rows: usize, seed: u64) -> Vec<Vec<String>> {
+    let mut rng = Rng::new(seed);
+    let mut table = vec![
+        std::iter::once("时间")
+            .chain(PROTON_COLUMNS.iter().map(|(header, _)| *header))
+            .map(str::to_string)
+            .collect::<Vec<_>>(),
+    ];
+    let gap = (rows >= 4).then_some(rows / 2);
+    let mut hour = 0;
+    for index in 0..rows {
+        if Some(index) == gap {
+            hour += 1;
+        }
+        let time = start_time() + TimeDelta::hours(hour);
+        hour += 1;
+        // 仪器导出的时间写法并不统一
+        let format = match index % 7 {
+            3 => "%Y/%m/%d %H:%M:%S",
+            5 => "%Y-%m-%dT%H:%M:%S",
+            _ => "%Y-%m-%d %H:%M:%S",
+        };
+        let mut row = vec![time.format(format).to_string()];
+        for &(_, typical) in &PROTON_COLUMNS {
+            let value = format_value(rng.around(typical));
+            row.push(if rng.chance(0.04) {
+                format!("{value}{}", rng.pick(&["(C)", "(RM)"]))
+            } else if rng.chance(0.02) {
+                rng.pick(&["—", "N/A"]).to_string()
+            } else {
+                value
+            });
+        }
+        table.push(row);
+    }
+    if rows >= 8
+        && let Some(last) = table.last().cloned()
+    {
+        table.push(last);
+    }
+    table
+}
+
+/// 离子色谱导出样例工作簿。
+pub fn proton_sample(rows: usize, seed: u64) -> Spreadsheet {
+    let mut book = umya_spreadsheet::new_file();
+    let sheet = book.get_active_sheet_mut();
+    for (row, values) in proton_table(rows, seed).iter().enumerate() {
+        for (col, value) in values.iter().enumerate() {
+            sheet
+                .get_cell_mut((col as u32 + 1, row as u32 + 1))
+                .set_value_string(value);
+        }
+    }
+    book
+}
+
+pub fn execute(args: &SampleArgs, global: &GlobalArgs) -> Result<()> {
+    let (name, book) = match args.kind {
+        SampleKind::Eemcg => ("eemcg", eemcg_sample(args.rows, args.seed)),
+        SampleKind::Proton => ("proton", proton_sample(args.rows, args.seed)),
+    };
+    let output = match &global.output {
+        Some(output) => output.clone(),
+        None => {
+            let file = PathBuf::from(format!("sample_{name}.{}", global.format.extension()));
+            match &global.output_dir {
+                Some(dir) => dir.join(file),
+                None => file,
+            }
+        }
+    };
+    if global.dry_run {
+        info!(
+            "[试运行] 将生成 {} 行 {name} 样例: {}",
+            args.rows,
+            output.display()
+        );
+        return Ok(());
+    }
+    cli::ensure_parent_dir(&output)?;
+    workbook::write(&book, &output, global.format)?;
+    info!("已生成 {} 行 {name} 样例: {}", args.rows, output.display());
+
+    if args.kind == SampleKind::Proton
+        && let Some(delimiter) = global.csv_export()
+    {
+        let csv_path = export::csv_path(&output, delimiter);
+        export::write_csv(&csv_path, &proton_table(args.rows, args.seed), delimiter)?;
+        info!("样例已导出为: {}", csv_path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eemcg::{self, RuleSet};
+    use crate::proton::{self, CsvEncoding, ProtonOptions, TimeIssueKind};
+
+    #[test]
+    fn eemcg_sample_exercises_rules() {
+        let mut book = eemcg_sample(48, 7);
+        let again = eemcg_sample(48, 7);
+        let sheet = book.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("I3"), "a24514");
+        assert_eq!(sheet.get_value("AY3"), "a25014");
+        assert_eq!(sheet.get_value("AY4"), "-999");
+        assert_eq!(sheet.get_highest_row(), 51);
+        assert_eq!(
+            sheet.get_value("AX20"),
+            again.get_sheet(&0).expect("sheet").get_value("AX20")
+        );
+
+        let report = eemcg::transform(&mut book, &RuleSet::builtin());
+        assert_eq!(report.renamed_sheets.len(), 2);
+        let rules: Vec<_> = report
+            .changes
+            .iter()
+            .flat_map(|change| change.rules.iter().map(String::as_str))
+            .collect();
+        for rule in [
+            "replace#3",
+            "replace#5",
+            "replace#6",
+            "missing_value#4",
+            "brackets",
+        ] {
+            assert!(rules.contains(&rule), "{rule} not exercised");
+        }
+    }
+
+    #[test]
+    fn proton_sample_has_flags_and_gaps() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sample_proton.xlsx");
+        workbook::write(&proton_sample(48, 3), &path, workbook::OutputFormat::Xlsx)?;
+
+        let source = proton::read_source(&path, b',', CsvEncoding::Auto)?;
+        let out = proton::transform(&source, &ProtonOptions::default())?;
+        assert_eq!(out.report.source_rows, 49);
+        let kinds = &out.report.time_issues;
+        assert!(
+            kinds
+                .iter()
+                .any(|i| matches!(i.kind, TimeIssueKind::Gap { missing: 1, .. }))
+        );
+        assert!(kinds.iter().any(|i| i.kind == TimeIssueKind::Duplicate));
+        let rules: Vec<_> = out
+            .report
+            .changes
+            .iter()
+            .flat_map(|change| change.rules.iter().map(String::as_str))
+            .collect();
+        for rule in ["time_format", "flagged", "non_numeric"] {
+            assert!(rules.contains(&rule), "{rule} not exercised");
+        }
+        Ok(())
+    }
+}
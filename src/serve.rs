@@ -0,0 +1,456 @@
+//! `dttools serve`：以 HTTP 服务的方式按需处理上传的文件，供 LIMS 等系统直接调用。
+//!
+//! `POST /eemcg`、`/proton`、`/ocec`、`/metals`、`/bc` 接收一个文件：请求体为文件内容（文件名由
+//! `?name=` 给出），或 `multipart/form-data` 中的第一个文件。文件按该工具处理后在响应中返回输出
+//! 文件；每次请求重新查找配置（见 [`crate::config`]），修改配置后无需重启服务。`GET /health`
+//! 用于检查服务是否在运行。
+//!
+//! 响应状态：200 成功（宽松模式跳过了数据问题时带 `X-Dttools-Status: partial`），400 请求有误，
+//! 404/405 路径或方法不对，413 文件过大，422 文件无法处理，500 服务端读写失败。出错时响应体为
+//! 错误信息。请求逐个处理。
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use clap::{Args, Parser};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{info, warn};
+
+use crate::cli::GlobalArgs;
+use crate::config::Tool;
+use crate::error::{DtToolsError, Result, invalid};
+use crate::instrument::InstrumentArgs;
+use crate::{bc, eemcg, metals, ocec, proton};
+
+/// 上传文件的默认大小上限（MB）。
+const DEFAULT_MAX_UPLOAD_MB: u64 = 50;
+
+/// 未给出文件名时使用的文件名。
+const DEFAULT_NAME: &str = "upload.xlsx";
+
+/// `dttools serve` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct ServeArgs {
+    /// 监听的地址（默认只接受本机的请求；0.0.0.0 接受所有网卡）
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1")]
+    pub host: String,
+
+    /// 监听的端口
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+
+    /// 上传文件的大小上限（MB）
+    #[arg(long, value_name = "MB", default_value_t = DEFAULT_MAX_UPLOAD_MB)]
+    pub max_upload_mb: u64,
+}
+
+/// 一次请求的处理结果。
+struct Reply {
+    status: u16,
+    body: Vec<u8>,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Reply {
+    fn text(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: message.into().into_bytes(),
+            headers: vec![("Content-Type", "text/plain; charset=utf-8".to_string())],
+        }
+    }
+}
+
+pub fn execute(args: &ServeArgs, global: &GlobalArgs) -> Result<()> {
+    let address = format!("{}:{}", args.host, args.port);
+    let server = Server::http(&address).map_err(|err| invalid!("无法监听 {address}: {err}"))?;
+    info!("服务已启动: http://{address}（POST /eemcg、/proton、/ocec、/metals、/bc）");
+    let limit = args.max_upload_mb.saturating_mul(1024 * 1024);
+    for mut request in server.incoming_requests() {
+        let reply = handle(&mut request, limit, global);
+        info!("{} {} -> {}", request.method(), request.url(), reply.status);
+        let mut response = Response::from_data(reply.body).with_status_code(reply.status);
+        for (name, value) in reply.headers {
+            if let Ok(header) = Header::from_bytes(name, value) {
+                response.add_header(header);
+            }
+        }
+        if let Err(err) = request.respond(response) {
+            warn!("无法发送响应: {err}");
+        }
+    }
+    Ok(())
+}
+
+fn handle(request: &mut Request, limit: u64, global: &GlobalArgs) -> Reply {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    if path == "/health" {
+        return Reply::text(200, "ok");
+    }
+    let Some(tool) = Tool::ALL
+        .into_iter()
+        .find(|tool| path.trim_end_matches('/') == format!("/{}", tool.section()))
+    else {
+        return Reply::text(404, format!("未知的路径: {path}"));
+    };
+    if *request.method() != Method::Post {
+        return Reply::text(405, "请用 POST 上传文件");
+    }
+    if request
+        .body_length()
+        .is_some_and(|length| length as u64 > limit)
+    {
+        return Reply::text(413, format!("文件超过 {} MB", limit / 1024 / 1024));
+    }
+    let mut body = Vec::new();
+    if let Err(err) = request.as_reader().take(limit + 1).read_to_end(&mut body) {
+        return Reply::text(400, format!("无法读取请求: {err}"));
+    }
+    if body.len() as u64 > limit {
+        return Reply::text(413, format!("文件超过 {} MB", limit / 1024 / 1024));
+    }
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .map(|header| header.value.as_str().to_string())
+        .unwrap_or_default();
+    let upload = match Upload::parse(&content_type, query, body) {
+        Ok(upload) => upload,
+        Err(err) => return Reply::text(400, err.to_string()),
+    };
+    match process(tool, &upload, global) {
+        Ok(reply) => reply,
+        Err(err) => Reply::text(status(&err), format!("{err:#}")),
+    }
+}
+
+/// 错误对应的 HTTP 状态。
+fn status(err: &DtToolsError) -> u16 {
+    match err.kind() {
+        DtToolsError::Write { .. } | DtToolsError::Io(_) => 500,
+        DtToolsError::Invalid { .. } => 400,
+        _ => 422,
+    }
+}
+
+/// 上传的文件。
+#[derive(Debug, PartialEq)]
+struct Upload {
+    name: String,
+    content: Vec<u8>,
+}
+
+impl Upload {
+    /// 从请求中取出文件：`multipart/form-data` 时为第一个带文件名的部分，否则为整个请求体，
+    /// 文件名取自查询参数 `name`。
+    fn parse(content_type: &str, query: &str, body: Vec<u8>) -> Result<Self> {
+        let (name, content) = match boundary(content_type) {
+            Some(boundary) => multipart_file(&body, &boundary)
+                .ok_or_else(|| invalid!("multipart 请求中没有文件"))?,
+            None => (query_value(query, "name"), body),
+        };
+        let name = name
+            .as_deref()
+            .and_then(|name| Path::new(name).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| DEFAULT_NAME.to_string());
+        if content.is_empty() {
+            return Err(invalid!("上传的文件为空"));
+        }
+        Ok(Self { name, content })
+    }
+}
+
+/// `multipart/form-data; boundary=...` 中的分隔符。
+fn boundary(content_type: &str) -> Option<String> {
+    let (kind, params) = content_type.split_once(';')?;
+    if !kind.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// multipart 请求体中第一个带文件名的部分：(文件名, 内容)。
+fn multipart_file(body: &[u8], boundary: &str) -> Option<(Option<String>, Vec<u8>)> {
+    let delimiter = format!("--{boundary}");
+    let delimiter = delimiter.as_bytes();
+    let mut rest = &body[find(body, delimiter)? + delimiter.len()..];
+    while let Some(end) = find(rest, delimiter) {
+        let part = &rest[..end];
+        rest = &rest[end + delimiter.len()..];
+        let header_end = find(part, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let Some(name) = headers.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("content-disposition")
+                .then(|| disposition_filename(value))?
+        }) else {
+            continue;
+        };
+        let content = &part[header_end + 4..];
+        let content = content.strip_suffix(b"\r\n").unwrap_or(content);
+        return Some((Some(name), content.to_vec()));
+    }
+    None
+}
+
+/// `Content-Disposition` 中的 `filename`。
+fn disposition_filename(value: &str) -> Option<String> {
+    value.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        (key.trim() == "filename").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// 查询参数的值（解码 `%XX` 与 `+`）。
+fn query_value(query: &str, key: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == key).then(|| percent_decode(v))
+    })
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// 命令行未给出任何参数时各工具的默认参数。
+fn default_args<T: Args>() -> T {
+    #[derive(Parser)]
+    struct Defaults<T: Args> {
+        #[command(flatten)]
+        args: T,
+    }
+    Defaults::<T>::parse_from(["dttools"]).args
+}
+
+/// 每次请求使用的临时目录序号。
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(0);
+
+/// 在临时目录中处理上传的文件，返回输出文件。
+fn process(tool: Tool, upload: &Upload, global: &GlobalArgs) -> Result<Reply> {
+    let dir = std::env::temp_dir().join(format!(
+        "dttools-serve-{}-{}",
+        std::process::id(),
+        NEXT_REQUEST.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&dir)?;
+    let result = process_in(tool, upload, global, &dir);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn process_in(tool: Tool, upload: &Upload, global: &GlobalArgs, dir: &Path) -> Result<Reply> {
+    let input = dir.join(&upload.name);
+    fs::write(&input, &upload.content)?;
+    let global = GlobalArgs {
+        output: None,
+        output_dir: Some(dir.join("out")),
+        in_place: false,
+        dry_run: false,
+        report: None,
+        json_summary: false,
+        output_beside_input: false,
+        processed: Default::default(),
+        archive: Default::default(),
+        ..global.clone()
+    };
+    let inputs = vec![input.clone()];
+    let result = match tool {
+        Tool::Eemcg => eemcg::execute(
+            &eemcg::EemcgArgs {
+                inputs,
+                ..default_args()
+            },
+            &global,
+        ),
+        Tool::Proton => proton::execute(
+            &proton::ProtonArgs {
+                inputs,
+                ..default_args()
+            },
+            &global,
+        ),
+        Tool::Ocec | Tool::Metals | Tool::Bc => {
+            let args = InstrumentArgs {
+                inputs,
+                ..default_args()
+            };
+            match tool {
+                Tool::Ocec => ocec::execute(&args, &global),
+                Tool::Metals => metals::execute(&args, &global),
+                _ => bc::execute(&args, &global),
+            }
+        }
+    };
+    let partial = match result {
+        Ok(()) => false,
+        Err(DtToolsError::Partial { .. }) => true,
+        Err(err) => return Err(err),
+    };
+    let output = global.workbook_output_path(&input);
+    let body = fs::read(&output).map_err(|err| DtToolsError::file_open(&output, err))?;
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut headers = vec![
+        ("Content-Type", content_type(&output).to_string()),
+        ("Content-Disposition", content_disposition(&name)),
+    ];
+    if partial {
+        headers.push(("X-Dttools-Status", "partial".to_string()));
+    }
+    Ok(Reply {
+        status: 200,
+        body,
+        headers,
+    })
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        Some("ods") => "application/vnd.oasis.opendocument.spreadsheet",
+        Some("parquet") => "application/vnd.apache.parquet",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 附件的文件名：ASCII 部分写入 `filename`，完整的 UTF-8 文件名写入 `filename*`。
+fn content_disposition(name: &str) -> String {
+    let ascii: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded: String = name
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b".-_".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect();
+    format!("attachment; filename=\"{ascii}\"; filename*=UTF-8''{encoded}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_raw_and_multipart_uploads() -> Result<()> {
+        let upload = Upload::parse(
+            "application/octet-stream",
+            "name=%E7%AB%99%E7%82%B9.xlsx",
+            b"data".to_vec(),
+        )?;
+        assert_eq!(upload.name, "站点.xlsx");
+
+        let upload = Upload::parse("", "name=../../etc/a.xlsx", b"data".to_vec())?;
+        assert_eq!(upload.name, "a.xlsx");
+        assert_eq!(Upload::parse("", "", b"x".to_vec())?.name, DEFAULT_NAME);
+
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhi\r\n\
+--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"45vocs2.xlsx\"\r\n\
+Content-Type: application/octet-stream\r\n\r\nPK\x03\x04\r\n--XyZ--\r\n";
+        let upload = Upload::parse("multipart/form-data; boundary=XyZ", "", body.to_vec())?;
+        assert_eq!(
+            upload,
+            Upload {
+                name: "45vocs2.xlsx".to_string(),
+                content: b"PK\x03\x04".to_vec(),
+            }
+        );
+        assert!(
+            Upload::parse("multipart/form-data; boundary=XyZ", "", b"--XyZ--".to_vec()).is_err()
+        );
+        assert!(Upload::parse("", "", Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn processes_uploads_over_http() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("sample.xlsx");
+        crate::workbook::write(
+            &crate::sample::proton_sample(24, 1),
+            &input,
+            crate::workbook::OutputFormat::Xlsx,
+        )?;
+        let upload = Upload {
+            name: "sample.xlsx".to_string(),
+            content: fs::read(&input)?,
+        };
+        let global = GlobalArgs {
+            quiet: true,
+            ..Default::default()
+        };
+        let reply = process(Tool::Proton, &upload, &global)?;
+        assert_eq!(reply.status, 200);
+        assert!(reply.body.starts_with(b"PK"));
+        assert!(
+            reply
+                .headers
+                .iter()
+                .any(|(_, value)| value.contains("processed_sample.xlsx"))
+        );
+
+        let broken = Upload {
+            name: "broken.xlsx".to_string(),
+            content: b"not a workbook".to_vec(),
+        };
+        let err = process(Tool::Eemcg, &broken, &global).err().unwrap();
+        assert_eq!(status(&err), 422);
+        Ok(())
+    }
+
+    #[test]
+    fn encodes_download_names() {
+        assert_eq!(
+            content_disposition("processed_站点.xlsx"),
+            "attachment; filename=\"processed___.xlsx\"; \
+             filename*=UTF-8''processed_%E7%AB%99%E7%82%B9.xlsx"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! 监测项目名称词典：同一物种在不同仪器厂商的导出中写法不一（`间、对-二甲苯`、`m,p-xylene`，
+//! `SO₄²⁻`、`SO4 2-`、`Sulfate` 等），统一为标准名称。proton 匹配源数据表头、eemcg 的
+//! `species` 步骤改写名称都使用此词典。
+//!
+//! 内置词典见 [`BUILTIN`]；配置文件（`proton_config.toml`、`rules.toml`）中的 `[species]`
+//! 表可以增加写法或改归其他标准名称：
+//!
+//! ```toml
+//! [species]
+//! "间/对-二甲苯" = ["mp-Xylene"]   # 键为标准名称，值为其他写法
+//! "NO₃⁻" = ["NO3-N"]
+//! ```
+//!
+//! 比较前先统一写法（见 [`normalize`]），因此上下标、全角括号、大小写与空白不同的写法不必列出。
+//! 名称后带括号单位（如 `Nitrate(ug/m3)`）时按名称部分查找，单位原样保留。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+use serde::Deserialize;
+
+use crate::error::{Result, config_error};
+
+/// 内置词典：(标准名称, 其他写法)。标准名称与 proton 模板第 3 行表头、国家平台的 VOCs 名称一致。
+pub const BUILTIN: &[(&str, &[&str])] = &[
+    ("乙烷", &["ethane"]),
+    ("乙烯", &["ethylene", "ethene"]),
+    ("丙烷", &["propane"]),
+    ("丙烯", &["propylene", "propene"]),
+    ("乙炔", &["acetylene", "ethyne"]),
+    ("异丁烷", &["i-butane", "isobutane"]),
+    ("正丁烷", &["n-butane"]),
+    ("异戊烷", &["i-pentane", "isopentane"]),
+    ("正戊烷", &["n-pentane"]),
+    ("异戊二烯", &["isoprene"]),
+    ("苯", &["benzene"]),
+    ("甲苯", &["toluene"]),
+    ("乙苯", &["ethylbenzene"]),
+    (
+        "间/对-二甲苯",
+        &[
+            "间、对-二甲苯",
+            "间,对-二甲苯",
+            "间对二甲苯",
+            "m,p-xylene",
+            "m/p-xylene",
+            "m+p-xylene",
+        ],
+    ),
+    ("邻-二甲苯", &["邻二甲苯", "o-xylene"]),
+    ("苯乙烯", &["styrene"]),
+    ("1,2,4-三甲苯", &["1,2,4-trimethylbenzene"]),
+    ("1,3,5-三甲苯", &["1,3,5-trimethylbenzene"]),
+    ("NH₃", &["ammonia", "氨气"]),
+    ("HNO₃", &["nitric acid", "硝酸"]),
+    ("HNO₂", &["nitrous acid", "亚硝酸"]),
+    ("HCl", &["hydrogen chloride", "氯化氢"]),
+    ("NO₃⁻", &["NO3", "nitrate", "硝酸根", "硝酸盐"]),
+    ("SO₄²⁻", &["SO4", "sulfate", "sulphate", "硫酸根", "硫酸盐"]),
+    ("NH₄⁺", &["NH4", "ammonium", "铵根", "铵盐"]),
+    ("Cl⁻", &["chloride", "氯离子"]),
+    ("NO₂⁻", &["nitrite", "亚硝酸根"]),
+    ("K⁺", &["potassium", "钾离子"]),
+    ("Na⁺", &["sodium", "钠离子"]),
+    ("Mg²⁺", &["magnesium", "镁离子"]),
+    ("Ca²⁺", &["calcium", "钙离子"]),
+];
+
+static BUILTIN_DICTIONARY: LazyLock<Dictionary> = LazyLock::new(|| {
+    let mut dictionary = Dictionary {
+        names: HashMap::new(),
+    };
+    for (name, variants) in BUILTIN {
+        dictionary.insert(name, variants.iter().copied());
+    }
+    dictionary
+});
+
+/// 统一名称写法：去掉空白，上下标数字与正负号改为普通字符，μ 改为 u，全角括号、逗号改为半角，
+/// 并转为小写。
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '₀'..='₉' => char::from(b'0' + (c as u32 - '₀' as u32) as u8),
+            '⁰' => '0',
+            '¹' => '1',
+            '²' => '2',
+            '³' => '3',
+            '⁴'..='⁹' => char::from(b'4' + (c as u32 - '⁴' as u32) as u8),
+            '⁺' | '₊' => '+',
+            '⁻' | '₋' | '−' => '-',
+            'μ' | 'µ' => 'u',
+            '（' => '(',
+            '）' => ')',
+            '，' => ',',
+            _ => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 名称词典：统一写法后的名称 → 标准名称。默认值为内置词典。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dictionary {
+    names: HashMap<String, String>,
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        BUILTIN_DICTIONARY.clone()
+    }
+}
+
+impl Dictionary {
+    /// 内置词典。
+    pub fn builtin() -> &'static Self {
+        &BUILTIN_DICTIONARY
+    }
+
+    /// 在内置词典上加入配置的 `标准名称 → 其他写法`；已有的写法改归配置的标准名称。
+    pub fn with_entries(entries: BTreeMap<String, Vec<String>>) -> Result<Self> {
+        let mut dictionary = Self::default();
+        let mut configured: HashMap<String, &str> = HashMap::new();
+        for (name, variants) in &entries {
+            if name.trim().is_empty() || variants.iter().any(|v| v.trim().is_empty()) {
+                return Err(config_error!("[species] 中 {name} 的名称或写法不能为空"));
+            }
+            for key in std::iter::once(name).chain(variants).map(|v| normalize(v)) {
+                if let Some(other) = configured.insert(key.clone(), name)
+                    && other != name
+                {
+                    return Err(config_error!(
+                        "[species] 中 {other} 与 {name} 的写法重复: {key}"
+                    ));
+                }
+            }
+            dictionary.insert(name, variants.iter().map(String::as_str));
+        }
+        Ok(dictionary)
+    }
+
+    fn insert<'a>(&mut self, name: &'a str, variants: impl IntoIterator<Item = &'a str>) {
+        for key in std::iter::once(name).chain(variants).map(normalize) {
+            self.names.insert(key, name.to_string());
+        }
+    }
+
+    /// 名称（不含单位）对应的标准名称。
+    pub fn lookup(&self, name: &str) -> Option<&str> {
+        self.names.get(&normalize(name)).map(String::as_str)
+    }
+
+    /// 统一写法并换为标准名称后的比较键，如 `Nitrate (ug/m3)` 与 `NO₃⁻(μg/m³)` 的键相同。
+    pub fn key(&self, text: &str) -> String {
+        if let Some(name) = self.lookup(text) {
+            return normalize(name);
+        }
+        match split_unit(text) {
+            Some((name, unit)) => match self.lookup(name) {
+                Some(name) => normalize(name) + &normalize(unit),
+                None => normalize(text),
+            },
+            None => normalize(text),
+        }
+    }
+
+    /// 换为标准名称后的文本，名称后的括号单位原样保留；不在词典中或已是标准写法时返回 `None`。
+    pub fn canonical(&self, text: &str) -> Option<String> {
+        let trimmed = text.trim();
+        let canonical = match self.lookup(trimmed) {
+            Some(name) => name.to_string(),
+            None => {
+                let (name, unit) = split_unit(trimmed)?;
+                format!("{}{unit}", self.lookup(name)?)
+            }
+        };
+        (canonical != text).then_some(canonical)
+    }
+}
+
+impl<'de> Deserialize<'de> for Dictionary {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let entries = BTreeMap::<String, Vec<String>>::deserialize(deserializer)?;
+        Self::with_entries(entries).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 把 `名称(单位)` 拆为名称与括号单位；不以括号结尾或名称为空时返回 `None`。
+pub(crate) fn split_unit(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_end();
+    if !text.ends_with([')', '）']) {
+        return None;
+    }
+    let start = text.rfind(['(', '（'])?;
+    let name = text[..start].trim_end();
+    (!name.is_empty()).then_some((name, &text[start..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_sub_and_superscripts() {
+        assert_eq!(normalize("NO₃⁻(μg/m³)"), normalize("NO3-(ug/m3)"));
+        assert_eq!(normalize("SO₄²⁻(μg/m³)"), "so42-(ug/m3)");
+        assert_eq!(normalize(" NH₄⁺ （µg/m³） "), "nh4+(ug/m3)");
+        assert_eq!(normalize("Ca2+ (ug/m3)"), normalize("Ca²⁺(μg/m³)"));
+    }
+
+    #[test]
+    fn builtin_variants_are_unique() {
+        let count: usize = BUILTIN.iter().map(|(_, variants)| variants.len() + 1).sum();
+        assert_eq!(Dictionary::builtin().names.len(), count);
+    }
+
+    #[test]
+    fn maps_variants_to_canonical_names() {
+        let dictionary = Dictionary::builtin();
+        assert_eq!(dictionary.lookup("M,P-Xylene"), Some("间/对-二甲苯"));
+        assert_eq!(dictionary.lookup("间、对-二甲苯"), Some("间/对-二甲苯"));
+        assert_eq!(dictionary.lookup("SO4 2-"), Some("SO₄²⁻"));
+        assert_eq!(
+            dictionary.key("Nitrate (ug/m3)"),
+            dictionary.key("NO₃⁻(μg/m³)")
+        );
+        assert_eq!(dictionary.key("苯并(a)芘"), "苯并(a)芘");
+
+        assert_eq!(
+            dictionary.canonical(" 邻二甲苯（ppbv）").as_deref(),
+            Some("邻-二甲苯（ppbv）")
+        );
+        assert_eq!(dictionary.canonical("NH4+").as_deref(), Some("NH₄⁺"));
+        assert_eq!(dictionary.canonical("NH₄⁺"), None);
+        assert_eq!(dictionary.canonical("12.5"), None);
+        assert_eq!(dictionary.canonical("(C)"), None);
+    }
+
+    #[test]
+    fn configured_entries_extend_and_override() -> Result<()> {
+        let entries = BTreeMap::from([
+            ("间/对-二甲苯".to_string(), vec!["mp-Xylene".to_string()]),
+            ("二甲苯".to_string(), vec!["o-xylene".to_string()]),
+        ]);
+        let dictionary = Dictionary::with_entries(entries)?;
+        assert_eq!(dictionary.lookup("MP-XYLENE"), Some("间/对-二甲苯"));
+        assert_eq!(dictionary.lookup("m,p-xylene"), Some("间/对-二甲苯"));
+        assert_eq!(dictionary.lookup("o-xylene"), Some("二甲苯"));
+        assert_eq!(dictionary.lookup("邻二甲苯"), Some("邻-二甲苯"));
+
+        let err = Dictionary::with_entries(BTreeMap::from([
+            ("A".to_string(), vec!["x".to_string()]),
+            ("B".to_string(), vec!["X".to_string()]),
+        ]))
+        .unwrap_err();
+        assert!(err.to_string().contains("写法重复"), "{err}");
+        assert!(
+            Dictionary::with_entries(BTreeMap::from([("A".to_string(), vec![" ".to_string()])]))
+                .is_err()
+        );
+        Ok(())
+    }
+}
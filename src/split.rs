@@ -0,0 +1,341 @@
+//! `dttools split`：按时间列把处理后的 proton/eemcg 文件拆分为每天（或每月）一个文件，
+//! 以满足平台对上传文件大小的限制。
+//!
+//! 时间列中第一个能识别为时间的单元格之前的各行为表头，复制到每个输出文件；
+//! 时间为空或无法识别的行归入上一行所在的文件。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::{Args, ValueEnum};
+use regex::Regex;
+use tracing::{info, warn};
+use umya_spreadsheet::{Spreadsheet, Worksheet};
+
+use crate::cli::{self, GlobalArgs};
+use crate::error::{DtToolsError, Result, bail, invalid};
+use crate::workbook;
+
+/// 默认的输出文件名模板。
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}_{period}";
+
+/// 查找时间列与表头时检查的最大行数。
+const HEADER_SCAN_ROWS: u32 = 20;
+
+/// 文件名模板中的占位符，如 `{stem}`。
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(\w*)\}").expect("valid regex"));
+
+/// 拆分的时段。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SplitPeriod {
+    /// 每个自然日一个文件
+    #[default]
+    Day,
+    /// 每个自然月一个文件
+    Month,
+}
+
+/// `dttools split` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct SplitArgs {
+    /// 处理后的工作簿（proton 上传模板或 eemcg 输出）
+    #[arg(value_name = "INPUT")]
+    pub input: PathBuf,
+
+    /// 按天或按月拆分
+    #[arg(long, value_enum, default_value_t = SplitPeriod::Day)]
+    pub by: SplitPeriod,
+
+    /// 输出文件名模板（不含扩展名）：{stem} 输入文件名主干，{period} 2026-01-05 或 2026-01，
+    /// {yyyy}、{mm}、{dd} 年、月、日（按月拆分时 {dd} 为空）
+    #[arg(long, value_name = "TEMPLATE", default_value = DEFAULT_NAME_TEMPLATE)]
+    pub name_template: String,
+
+    /// 要拆分的工作表（默认为活动工作表）；其他工作表原样保留在每个输出文件中
+    #[arg(long, value_name = "NAME")]
+    pub sheet: Option<String>,
+
+    /// 时间列（如 A），默认为表头中“时间”所在的列，找不到时为 A 列
+    #[arg(long, value_name = "COLUMN", value_parser = parse_column)]
+    pub time_column: Option<u32>,
+}
+
+fn parse_column(value: &str) -> Result<u32, String> {
+    workbook::column_index(value).ok_or_else(|| format!("无效的列: {value}"))
+}
+
+/// 识别时间单元格：常见的文本写法，或 Excel 日期序列号。
+pub(crate) fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    const FORMATS: [&str; 6] = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ];
+    if let Some(time) = FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    {
+        return Some(time);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    workbook::parse_serial(value)
+}
+
+/// 一个输出文件对应的时段：(年, 月, 日)，按月拆分时日为 0。
+pub type PeriodKey = (i32, u32, u32);
+
+fn period_key(time: NaiveDateTime, by: SplitPeriod) -> PeriodKey {
+    use chrono::Datelike;
+    match by {
+        SplitPeriod::Day => (time.year(), time.month(), time.day()),
+        SplitPeriod::Month => (time.year(), time.month(), 0),
+    }
+}
+
+/// 按模板生成输出文件名主干。
+fn render_name(template: &str, stem: &str, (year, month, day): PeriodKey) -> Result<String> {
+    let mut unknown = None;
+    let name = PLACEHOLDER.replace_all(template, |caps: &regex::Captures| match &caps[1] {
+        "stem" => stem.to_string(),
+        "period" if day == 0 => format!("{year:04}-{month:02}"),
+        "period" => format!("{year:04}-{month:02}-{day:02}"),
+        "yyyy" => format!("{year:04}"),
+        "mm" => format!("{month:02}"),
+        "dd" if day == 0 => String::new(),
+        "dd" => format!("{day:02}"),
+        other => {
+            unknown.get_or_insert_with(|| other.to_string());
+            String::new()
+        }
+    });
+    if let Some(placeholder) = unknown {
+        bail!("文件名模板中有未知的占位符: {{{placeholder}}}");
+    }
+    Ok(name.into_owned())
+}
+
+/// 工作表的拆分方式：表头行数，以及每个时段包含的数据行（1 起计）。
+struct Layout {
+    header_rows: u32,
+    groups: BTreeMap<PeriodKey, Vec<u32>>,
+    /// 时间不为空但无法识别、归入上一行所在时段的行数
+    unparsed: usize,
+}
+
+/// 表头中值为“时间”的单元格所在的列；找不到时为 A 列。
+fn find_time_column(sheet: &Worksheet) -> u32 {
+    let (max_col, max_row) = sheet.get_highest_column_and_row();
+    (1..=max_row.min(HEADER_SCAN_ROWS))
+        .flat_map(|row| (1..=max_col).map(move |col| (col, row)))
+        .find(|&position| sheet.get_value(position).trim() == "时间")
+        .map_or(1, |(col, _)| col)
+}
+
+fn layout(sheet: &Worksheet, time_col: u32, by: SplitPeriod) -> Result<Layout> {
+    let max_row = sheet.get_highest_row();
+    let first = (1..=max_row)
+        .find(|&row| parse_time(&sheet.get_value((time_col, row))).is_some())
+        .ok_or_else(|| {
+            invalid!(
+                "工作表 '{}' 的 {} 列中没有可识别的时间",
+                sheet.get_name(),
+                umya_spreadsheet::helper::coordinate::string_from_column_index(&time_col)
+            )
+        })?;
+
+    let mut groups: BTreeMap<PeriodKey, Vec<u32>> = BTreeMap::new();
+    let mut unparsed = 0;
+    let mut current = None;
+    for row in first..=max_row {
+        let value = sheet.get_value((time_col, row));
+        match parse_time(&value) {
+            Some(time) => current = Some(period_key(time, by)),
+            None if !value.trim().is_empty() => unparsed += 1,
+            None => {}
+        }
+        let key = current.expect("first data row has a time");
+        groups.entry(key).or_default().push(row);
+    }
+    Ok(Layout {
+        header_rows: first - 1,
+        groups,
+        unparsed,
+    })
+}
+
+/// 删除工作表中不在 `keep` 里的数据行（从下往上按连续区间删除）。
+fn keep_rows(sheet: &mut Worksheet, header_rows: u32, keep: &[u32]) {
+    let max_row = sheet.get_highest_row();
+    let mut remove: Vec<(u32, u32)> = Vec::new();
+    let mut next = header_rows + 1;
+    for &row in keep.iter().chain(std::iter::once(&(max_row + 1))) {
+        if row > next {
+            remove.push((next, row - next));
+        }
+        next = row + 1;
+    }
+    for (start, count) in remove.into_iter().rev() {
+        sheet.remove_row(&start, &count);
+    }
+}
+
+/// 把工作簿中指定的工作表按时段拆分，返回 (时段, 工作簿, 数据行数)。
+/// `sheet` 为 `None` 时拆分活动工作表。
+pub fn split(
+    book: &Spreadsheet,
+    sheet: Option<&str>,
+    time_column: Option<u32>,
+    by: SplitPeriod,
+) -> Result<Vec<(PeriodKey, Spreadsheet, usize)>> {
+    let index = match sheet {
+        Some(name) => book
+            .get_sheet_collection()
+            .iter()
+            .position(|s| s.get_name() == name)
+            .ok_or_else(|| DtToolsError::SheetNotFound {
+                name: name.to_string(),
+            })?,
+        None => *book.get_workbook_view().get_active_tab() as usize,
+    };
+    let target = book
+        .get_sheet(&index)
+        .ok_or_else(|| invalid!("工作簿中没有工作表"))?;
+    let time_col = time_column.unwrap_or_else(|| find_time_column(target));
+    let layout = layout(target, time_col, by)?;
+    if layout.unparsed > 0 {
+        warn!(
+            "{} 行的时间无法识别，已归入上一行所在的文件",
+            layout.unparsed
+        );
+    }
+
+    Ok(layout
+        .groups
+        .into_iter()
+        .map(|(key, rows)| {
+            let mut part = book.clone();
+            let sheet = part.get_sheet_mut(&index).expect("sheet index is valid");
+            keep_rows(sheet, layout.header_rows, &rows);
+            (key, part, rows.len())
+        })
+        .collect())
+}
+
+pub fn execute(args: &SplitArgs, global: &GlobalArgs) -> Result<()> {
+    if global.output.is_some() || global.in_place {
+        bail!("split 写出多个文件，请用 --output-dir 指定输出目录");
+    }
+    let book = workbook::read(&args.input)?;
+    let parts = split(&book, args.sheet.as_deref(), args.time_column, args.by)?;
+    let stem = args
+        .input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    let dir = global.output_dir.as_deref().unwrap_or(Path::new(""));
+
+    let mut paths = Vec::with_capacity(parts.len());
+    for (key, _, _) in &parts {
+        let name = render_name(&args.name_template, &stem, *key)?;
+        let path = dir.join(format!("{name}.{}", global.format.extension()));
+        if paths.contains(&path) {
+            bail!("文件名模板使不同时段得到相同的文件名: {}", path.display());
+        }
+        paths.push(path);
+    }
+
+    for ((_, part, rows), path) in parts.iter().zip(&paths) {
+        if global.dry_run {
+            info!("[试运行] 将写出 {rows} 行到: {}", path.display());
+            continue;
+        }
+        cli::ensure_parent_dir(path)?;
+        workbook::write(part, path, global.format)?;
+        info!("已写出 {rows} 行到: {}", path.display());
+        #[cfg(feature = "upload")]
+        global.upload.send(path)?;
+    }
+    info!("共拆分为 {} 个文件", paths.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 类似 proton 上传模板：5 行表头，数据从第 6 行开始。
+    fn template(times: &[&str]) -> Spreadsheet {
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet
+            .get_cell_mut("A1")
+            .set_value("橙色和红色部分请勿改动！！！");
+        sheet.get_cell_mut("A3").set_value("时间");
+        sheet.get_cell_mut("A4").set_value("4401000010003");
+        sheet.get_cell_mut("A5").set_value("时间");
+        for (i, time) in times.iter().enumerate() {
+            let row = i as u32 + 6;
+            sheet.get_cell_mut((1, row)).set_value(*time);
+            sheet.get_cell_mut((2, row)).set_value(format!("{i}"));
+        }
+        book
+    }
+
+    #[test]
+    fn splits_by_day_and_month() -> Result<()> {
+        let book = template(&[
+            "2026-01-05 23:00:00",
+            "2026-01-06 00:00:00",
+            "备注",
+            "2026-01-06 01:00:00",
+            "2026-02-01 00:00:00",
+        ]);
+        let parts = split(&book, None, None, SplitPeriod::Day)?;
+        let keys: Vec<_> = parts.iter().map(|(key, _, rows)| (*key, *rows)).collect();
+        assert_eq!(
+            keys,
+            [((2026, 1, 5), 1), ((2026, 1, 6), 3), ((2026, 2, 1), 1)]
+        );
+        let sheet = parts[1].1.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_value("A4"), "4401000010003");
+        assert_eq!(sheet.get_value("A6"), "2026-01-06 00:00:00");
+        assert_eq!(sheet.get_value("A7"), "备注");
+        assert_eq!(sheet.get_value("B8"), "3");
+        assert_eq!(sheet.get_highest_row(), 8);
+
+        let months = split(&book, None, None, SplitPeriod::Month)?;
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].2, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn recognizes_serial_dates_and_templates() -> Result<()> {
+        assert_eq!(
+            parse_time("46027.5"),
+            NaiveDate::from_ymd_opt(2026, 1, 5).and_then(|d| d.and_hms_opt(12, 0, 0))
+        );
+        assert_eq!(parse_time("a24514"), None);
+        assert_eq!(parse_time("-999"), None);
+
+        assert_eq!(
+            render_name(DEFAULT_NAME_TEMPLATE, "proton", (2026, 1, 5))?,
+            "proton_2026-01-05"
+        );
+        assert_eq!(
+            render_name("{yyyy}{mm}{dd}_{stem}", "vocs", (2026, 1, 0))?,
+            "202601_vocs"
+        );
+        assert!(render_name("{station}_{period}", "x", (2026, 1, 5)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+//! `--to-sqlite`：把 proton 处理后的数据追加到 SQLite 数据库，跨月查询时不必重新解析 xlsx。
+//!
+//! 数据以长表（见 [`crate::long`]）写入 `measurements` 表（见 [`CREATE_TABLE`]），`flag` 没有标记时
+//! 为 NULL。同一站点、时刻与项目再次写入时以新值为准，因此重复处理同一文件不会产生重复的行。
+
+use std::path::Path;
+
+use rusqlite::{Connection, params};
+
+use crate::error::{DtToolsError, Result};
+use crate::long::Measurement;
+
+/// 表不存在时创建。
+pub const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS measurements (
+    station   TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    analyte   TEXT NOT NULL,
+    value     REAL,
+    flag      TEXT,
+    PRIMARY KEY (station, timestamp, analyte)
+)";
+
+const UPSERT: &str = "INSERT INTO measurements (station, timestamp, analyte, value, flag)
+    VALUES (?1, ?2, ?3, ?4, ?5)
+    ON CONFLICT (station, timestamp, analyte) DO UPDATE SET value = excluded.value, flag = excluded.flag";
+
+/// 把各行写入 `path`（不存在时创建数据库与表），在一个事务中完成；返回写入的行数。
+pub fn append(path: &Path, measurements: &[Measurement<'_>]) -> Result<usize> {
+    let write_error = |err: rusqlite::Error| DtToolsError::write(path, err);
+    let mut connection = Connection::open(path).map_err(write_error)?;
+    connection.execute(CREATE_TABLE, []).map_err(write_error)?;
+    let transaction = connection.transaction().map_err(write_error)?;
+    {
+        let mut statement = transaction.prepare(UPSERT).map_err(write_error)?;
+        for m in measurements {
+            statement
+                .execute(params![m.station, m.timestamp, m.analyte, m.value, m.flag])
+                .map_err(write_error)?;
+        }
+    }
+    transaction.commit().map_err(write_error)?;
+    Ok(measurements.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::long::measurements;
+
+    #[test]
+    fn appends_and_replaces_measurements() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("data.db");
+        let table: Vec<Vec<String>> = [
+            ["时间", "NO₃⁻", "SO₄²⁻"],
+            ["2026-01-05 01:00:00", "1.6#C", "4.2"],
+            ["2026-01-05 02:00:00", "", "0.01#DL"],
+        ]
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect();
+        let rows = measurements("4401000010003", &table, &[]);
+        assert_eq!(append(&path, &rows)?, 3);
+        // 重复处理同一文件时覆盖原有的行
+        let mut again = rows.clone();
+        again[1].value = 4.3;
+        append(&path, &again)?;
+
+        let connection = Connection::open(&path).map_err(DtToolsError::external)?;
+        let stored: Vec<(String, f64, Option<String>)> = connection
+            .prepare("SELECT analyte, value, flag FROM measurements ORDER BY timestamp, analyte")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect()
+            })
+            .map_err(DtToolsError::external)?;
+        assert_eq!(
+            stored,
+            [
+                ("NO₃⁻".to_string(), 1.6, Some("C".to_string())),
+                ("SO₄²⁻".to_string(), 4.3, None),
+                ("SO₄²⁻".to_string(), 0.01, Some("DL".to_string())),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,657 @@
+//! 上传模板的版式定义与渲染。
+//!
+//! 版式用 TOML 描述：命名样式、固定单元格（如第 1–2 行的说明）、表头行（每列写监测项目、
+//! 因子编码或单位）、时间列与第一个监测项目列的位置，以及数据的起始行。固定单元格与表头行
+//! 首列中的 `{title}`、`{station_code}`、`{a2_text}` 替换为对应的配置值。
+//!
+//! ```toml
+//! time_column = "A"              # 时间列
+//! first_column = "B"             # 第一个监测项目列，其余依次向右
+//! start_row = 6                  # 数据的起始行
+//! time_style = "orange"          # 时间单元格的样式（可选）
+//! flagged_time_style = "red"     # 有时间问题或补齐的行的时间单元格样式（可选）
+//! freeze_header = true           # 冻结数据起始行以上的表头（可选，默认 true）
+//! auto_filter = true             # 对表头最后一行与数据区域启用自动筛选（可选，默认 true）
+//! auto_width = true              # 按内容设置列宽（可选，默认 true）
+//! typed_cells = true             # 时间写为日期时间单元格、浓度写为数字（可选，默认 true）
+//!
+//! [styles.orange]
+//! fill = "ffff9900"              # 填充色（ARGB 或 RGB），另有 font_color、bold
+//!
+//! [[cells]]
+//! address = "A1"
+//! value = "橙色和红色部分请勿改动！！！"
+//! style = "red"
+//!
+//! [[header_rows]]
+//! row = 3
+//! label = "{title}"              # 时间列中的文字
+//! field = "name"                 # 监测项目列写 name、code 或 unit
+//! style = "orange"
+//! ```
+//!
+//! 内置版式（[`Template::builtin`]）即国家平台的上传模板，见 `upload_template.example.toml`。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::error::{Context, Result, config_error};
+use crate::workbook;
+use regex::Regex;
+use serde::Deserialize;
+use umya_spreadsheet::Worksheet;
+use umya_spreadsheet::helper::coordinate::{column_index_from_string, coordinate_from_index};
+use umya_spreadsheet::structs::{Pane, PaneStateValues, PaneValues, SheetView};
+
+/// 内置版式：第 1–2 行为红色说明，第 3–5 行为橙色的监测项目、因子编码与单位，数据从第 6 行开始。
+pub const BUILTIN: &str = r#"
+time_column = "A"
+first_column = "B"
+start_row = 6
+time_style = "orange"
+flagged_time_style = "red"
+
+[styles.red]
+fill = "ffff0000"
+
+[styles.orange]
+fill = "ffff9900"
+
+[[cells]]
+address = "A1"
+value = "橙色和红色部分请勿改动！！！"
+style = "red"
+
+[[cells]]
+address = "A2"
+value = "{a2_text}"
+style = "red"
+
+[[header_rows]]
+row = 3
+label = "{title}"
+field = "name"
+style = "orange"
+
+[[header_rows]]
+row = 4
+label = "{station_code}"
+field = "code"
+style = "orange"
+
+[[header_rows]]
+row = 5
+label = "时间"
+field = "unit"
+style = "orange"
+"#;
+
+/// A1 形式的单元格地址。
+static CELL_ADDRESS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z]{1,3}[1-9][0-9]*$").expect("valid regex"));
+
+/// 命名样式。
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleDef {
+    /// 纯色填充（ARGB 或 RGB）
+    pub fill: Option<String>,
+    /// 字体颜色（ARGB 或 RGB）
+    pub font_color: Option<String>,
+    #[serde(default)]
+    pub bold: bool,
+}
+
+impl StyleDef {
+    fn to_style(&self) -> umya_spreadsheet::Style {
+        let mut style = umya_spreadsheet::Style::default();
+        if let Some(fill) = &self.fill {
+            let pattern = style.get_fill_mut().get_pattern_fill_mut();
+            pattern.set_pattern_type(umya_spreadsheet::structs::PatternValues::Solid);
+            pattern.get_foreground_color_mut().set_argb(fill);
+            pattern.get_background_color_mut().set_argb(fill);
+        }
+        if let Some(color) = &self.font_color {
+            style.get_font_mut().get_color_mut().set_argb(color);
+        }
+        if self.bold {
+            style.get_font_mut().set_bold(true);
+        }
+        style
+    }
+}
+
+/// 写入固定文字的单元格。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixedCell {
+    pub address: String,
+    pub value: String,
+    pub style: Option<String>,
+}
+
+impl FixedCell {
+    /// 单元格所在的行（1 起计）；地址无效时为 0。
+    pub fn row(&self) -> u32 {
+        let digits = self
+            .address
+            .trim_start_matches(|c: char| c.is_ascii_alphabetic());
+        digits.parse().unwrap_or(0)
+    }
+}
+
+/// 表头行中各监测项目列写入的内容。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    /// 监测项目名称
+    Name,
+    /// 因子编码
+    Code,
+    /// 单位
+    Unit,
+}
+
+/// 一个表头行。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderRow {
+    /// 行号（1 起计）
+    pub row: u32,
+    /// 时间列中的文字
+    #[serde(default)]
+    pub label: String,
+    pub field: Field,
+    pub style: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFile {
+    #[serde(default = "default_time_column")]
+    time_column: String,
+    first_column: String,
+    start_row: u32,
+    time_style: Option<String>,
+    flagged_time_style: Option<String>,
+    #[serde(default = "enabled")]
+    freeze_header: bool,
+    #[serde(default = "enabled")]
+    auto_filter: bool,
+    #[serde(default = "enabled")]
+    auto_width: bool,
+    #[serde(default = "enabled")]
+    typed_cells: bool,
+    #[serde(default)]
+    styles: BTreeMap<String, StyleDef>,
+    #[serde(default)]
+    cells: Vec<FixedCell>,
+    #[serde(default)]
+    header_rows: Vec<HeaderRow>,
+}
+
+fn default_time_column() -> String {
+    "A".to_string()
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// 自动列宽的范围（字符数）。
+const MIN_COLUMN_WIDTH: f64 = 8.0;
+const MAX_COLUMN_WIDTH: f64 = 40.0;
+
+/// 文本的显示宽度：中日韩文字与全角符号按 2 个字符计。
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c >= '\u{2e80}' { 2 } else { 1 })
+        .sum()
+}
+
+/// 上传模板的版式。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// 时间列（1 起计）
+    pub time_column: u32,
+    /// 第一个监测项目列（1 起计）
+    pub first_column: u32,
+    /// 数据的起始行（1 起计）
+    pub start_row: u32,
+    pub time_style: Option<String>,
+    pub flagged_time_style: Option<String>,
+    /// 冻结数据起始行以上的表头
+    pub freeze_header: bool,
+    /// 对表头最后一行与数据区域启用自动筛选
+    pub auto_filter: bool,
+    /// 按表头与数据的内容设置列宽
+    pub auto_width: bool,
+    /// 时间写为日期时间单元格（`yyyy-mm-dd hh:mm:ss`）、浓度写为数字；否则均写为文本
+    pub typed_cells: bool,
+    pub styles: BTreeMap<String, StyleDef>,
+    pub cells: Vec<FixedCell>,
+    pub header_rows: Vec<HeaderRow>,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// 表头中替换的配置值。
+#[derive(Debug, Clone, Copy)]
+pub struct Vars<'a> {
+    /// 第 3 行 A 列的名称，如 `离子色谱`
+    pub title: &'a str,
+    pub station_code: &'a str,
+    pub a2_text: &'a str,
+}
+
+/// 一个监测项目列的表头。
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnHeader<'a> {
+    pub name: &'a str,
+    pub code: &'a str,
+    pub unit: &'a str,
+}
+
+/// 列字母（如 `B`、`AA`）转为列号（1 起计）。
+fn parse_column(key: &str, letters: &str) -> Result<u32> {
+    let letters = letters.trim().to_ascii_uppercase();
+    if letters.is_empty() || letters.len() > 3 || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(config_error!("{key} 必须是列字母（如 A、B）: {letters:?}"));
+    }
+    Ok(column_index_from_string(&letters))
+}
+
+/// 颜色统一为小写的 ARGB，RGB 补上不透明的 `ff`。
+fn parse_color(name: &str, color: &str) -> Result<String> {
+    let color = color.trim().trim_start_matches('#').to_ascii_lowercase();
+    if !color.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(config_error!("样式 {name} 的颜色无效: {color}"));
+    }
+    match color.len() {
+        6 => Ok(format!("ff{color}")),
+        8 => Ok(color),
+        _ => Err(config_error!("样式 {name} 的颜色无效: {color}")),
+    }
+}
+
+impl Template {
+    /// 内置的上传模板版式。
+    pub fn builtin() -> Self {
+        Self::from_toml_str(BUILTIN).expect("valid builtin template")
+    }
+
+    /// 解析 TOML 版式定义并检查其中的引用。
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let mut file: TemplateFile = toml::from_str(content).context("版式定义格式错误")?;
+        for (name, style) in &mut file.styles {
+            for color in [&mut style.fill, &mut style.font_color]
+                .into_iter()
+                .flatten()
+            {
+                *color = parse_color(name, color)?;
+            }
+        }
+        let template = Self {
+            time_column: parse_column("time_column", &file.time_column)?,
+            first_column: parse_column("first_column", &file.first_column)?,
+            start_row: file.start_row,
+            time_style: file.time_style,
+            flagged_time_style: file.flagged_time_style,
+            freeze_header: file.freeze_header,
+            auto_filter: file.auto_filter,
+            auto_width: file.auto_width,
+            typed_cells: file.typed_cells,
+            styles: file.styles,
+            cells: file.cells,
+            header_rows: file.header_rows,
+        };
+        template.check()?;
+        Ok(template)
+    }
+
+    /// 从 `path` 读取版式定义。
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("无法读取版式定义: {}", path.display()))?;
+        Self::from_toml_str(&content).with_context(|| format!("版式定义: {}", path.display()))
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.start_row == 0 {
+            return Err(config_error!("start_row 必须大于 0"));
+        }
+        if self.time_column >= self.first_column {
+            return Err(config_error!("time_column 必须在 first_column 的左侧"));
+        }
+        for cell in &self.cells {
+            if !CELL_ADDRESS.is_match(&cell.address) {
+                return Err(config_error!(
+                    "[[cells]] 中的单元格地址无效: {}",
+                    cell.address
+                ));
+            }
+        }
+        let mut rows = Vec::new();
+        for header in &self.header_rows {
+            if header.row == 0 || header.row >= self.start_row {
+                return Err(config_error!(
+                    "[[header_rows]] 的行号必须在 1 与 start_row 之间: {}",
+                    header.row
+                ));
+            }
+            if rows.contains(&header.row) {
+                return Err(config_error!("[[header_rows]] 的行号重复: {}", header.row));
+            }
+            rows.push(header.row);
+        }
+        let referenced = self
+            .cells
+            .iter()
+            .map(|cell| &cell.style)
+            .chain(self.header_rows.iter().map(|header| &header.style))
+            .chain([&self.time_style, &self.flagged_time_style])
+            .flatten();
+        for name in referenced {
+            if !self.styles.contains_key(name) {
+                return Err(config_error!("未定义的样式: {name}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 命名样式；`None` 或未定义时为默认样式。
+    pub fn style(&self, name: Option<&str>) -> umya_spreadsheet::Style {
+        name.and_then(|name| self.styles.get(name))
+            .map(StyleDef::to_style)
+            .unwrap_or_default()
+    }
+
+    /// 时间单元格的样式；`flagged` 为有时间问题或补齐的行。
+    pub fn time_cell_style(&self, flagged: bool) -> umya_spreadsheet::Style {
+        let name = if flagged {
+            self.flagged_time_style
+                .as_ref()
+                .or(self.time_style.as_ref())
+        } else {
+            self.time_style.as_ref()
+        };
+        self.style(name.map(String::as_str))
+    }
+
+    /// 第 `index` 个数据行（0 起计）的时间单元格地址。
+    pub fn time_address(&self, index: usize) -> String {
+        coordinate_from_index(&self.time_column, &(self.start_row + index as u32))
+    }
+
+    /// 第 `index` 个数据行（0 起计）第 `column` 个监测项目（0 起计）的单元格地址。
+    pub fn value_address(&self, index: usize, column: usize) -> String {
+        coordinate_from_index(
+            &(self.first_column + column as u32),
+            &(self.start_row + index as u32),
+        )
+    }
+
+    /// 在 `sheet` 中写入固定单元格与表头行。
+    pub fn render_header(&self, sheet: &mut Worksheet, vars: &Vars, columns: &[ColumnHeader]) {
+        let substitute = |text: &str| {
+            text.replace("{title}", vars.title)
+                .replace("{station_code}", vars.station_code)
+                .replace("{a2_text}", vars.a2_text)
+        };
+        for cell in &self.cells {
+            sheet
+                .get_cell_mut(cell.address.to_ascii_uppercase().as_str())
+                .set_value(substitute(&cell.value))
+                .set_style(self.style(cell.style.as_deref()));
+        }
+        for header in &self.header_rows {
+            let style = self.style(header.style.as_deref());
+            sheet
+                .get_cell_mut((self.time_column, header.row))
+                .set_value(substitute(&header.label))
+                .set_style(style.clone());
+            for (i, column) in columns.iter().enumerate() {
+                let value = match header.field {
+                    Field::Name => column.name,
+                    Field::Code => column.code,
+                    Field::Unit => column.unit,
+                };
+                sheet
+                    .get_cell_mut((self.first_column + i as u32, header.row))
+                    .set_value(value)
+                    .set_style(style.clone());
+            }
+        }
+    }
+
+    /// 写完 `rows` 行、`columns` 个监测项目后整理工作表以便查看：按内容设置列宽、冻结表头、
+    /// 对数据区域启用自动筛选（各项可在版式中关闭）。
+    pub fn finish_sheet(&self, sheet: &mut Worksheet, columns: usize, rows: usize) {
+        if self.auto_width {
+            let widths = self.column_widths(sheet.get_cell_collection().into_iter().map(|cell| {
+                let coordinate = cell.get_coordinate();
+                (
+                    *coordinate.get_col_num(),
+                    *coordinate.get_row_num(),
+                    workbook::display_text(cell),
+                )
+            }));
+            for (col, width) in widths {
+                sheet
+                    .get_column_dimension_by_number_mut(&col)
+                    .set_width(width);
+            }
+        }
+        if let Some(frozen) = self.frozen_rows() {
+            let mut pane = Pane::default();
+            pane.set_vertical_split(f64::from(frozen))
+                .set_active_pane(PaneValues::BottomLeft)
+                .set_state(PaneStateValues::Frozen);
+            pane.get_top_left_cell_mut()
+                .set_coordinate(coordinate_from_index(&1, &self.start_row));
+            let views = sheet.get_sheet_views_mut().get_sheet_view_list_mut();
+            if views.is_empty() {
+                views.push(SheetView::default());
+            }
+            views[0].set_pane(pane);
+        }
+        if let Some(range) = self.auto_filter_range(columns, rows) {
+            sheet.set_auto_filter(range);
+        }
+    }
+
+    /// 按单元格 (列, 行, 显示文本) 计算的列宽；只计入时间列与监测项目列中表头行和数据行的
+    /// 单元格，固定单元格（如第 1–2 行的长说明）不参与计算。
+    pub fn column_widths(
+        &self,
+        cells: impl IntoIterator<Item = (u32, u32, String)>,
+    ) -> BTreeMap<u32, f64> {
+        let mut widths: BTreeMap<u32, usize> = BTreeMap::new();
+        for (col, row, text) in cells {
+            let counted =
+                row >= self.start_row || self.header_rows.iter().any(|header| header.row == row);
+            if counted && (col == self.time_column || col >= self.first_column) {
+                let width = widths.entry(col).or_default();
+                *width = (*width).max(display_width(&text));
+            }
+        }
+        widths
+            .into_iter()
+            .map(|(col, width)| {
+                let width = (width as f64 + 2.0).clamp(MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH);
+                (col, width)
+            })
+            .collect()
+    }
+
+    /// 冻结的表头行数；不冻结时为 `None`。
+    pub fn frozen_rows(&self) -> Option<u32> {
+        (self.freeze_header && self.start_row > 1).then(|| self.start_row - 1)
+    }
+
+    /// 自动筛选的区域（如 `A5:O30`）；不启用时为 `None`。
+    pub fn auto_filter_range(&self, columns: usize, rows: usize) -> Option<String> {
+        if !self.auto_filter || self.start_row == 1 {
+            return None;
+        }
+        let last_column = self.first_column + columns.max(1) as u32 - 1;
+        let last_row = self.start_row + rows.max(1) as u32 - 1;
+        Some(format!(
+            "{}:{}",
+            coordinate_from_index(&self.time_column, &(self.start_row - 1)),
+            coordinate_from_index(&last_column, &last_row)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_builtin_header() {
+        let template = Template::builtin();
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        template.render_header(
+            sheet,
+            &Vars {
+                title: "OCEC",
+                station_code: "4401000010003",
+                a2_text: "说明",
+            },
+            &[ColumnHeader {
+                name: "OC",
+                code: "a06001",
+                unit: "μg/m³",
+            }],
+        );
+        assert_eq!(sheet.get_value("A2"), "说明");
+        assert_eq!(sheet.get_value("A3"), "OCEC");
+        assert_eq!(sheet.get_value("B4"), "a06001");
+        assert_eq!(sheet.get_value("A5"), "时间");
+        assert_eq!(sheet.get_value("B5"), "μg/m³");
+        assert_eq!(template.time_address(0), "A6");
+        assert_eq!(template.value_address(1, 2), "D7");
+    }
+
+    #[test]
+    fn finishes_sheet_for_review() -> Result<()> {
+        let template = Template::builtin();
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        let columns = [
+            ColumnHeader {
+                name: "NO₃⁻",
+                code: "a06001",
+                unit: "μg/m³",
+            },
+            ColumnHeader {
+                name: "硫酸根离子浓度",
+                code: "a06002",
+                unit: "μg/m³",
+            },
+        ];
+        let vars = Vars {
+            title: "离子色谱",
+            station_code: "4401000010003",
+            a2_text: "很长很长很长很长很长很长很长很长很长很长很长很长很长很长的说明",
+        };
+        template.render_header(sheet, &vars, &columns);
+        workbook::set_time_cell(sheet.get_cell_mut("A6"), "2024-01-01 00:00:00");
+        sheet.get_cell_mut("A7").set_value("2024-01-01 01:00:00");
+        template.finish_sheet(sheet, columns.len(), 2);
+
+        assert_eq!(
+            sheet
+                .get_auto_filter()
+                .map(|filter| filter.get_range().get_range()),
+            Some("A5:C7".to_string())
+        );
+        let pane = sheet.get_sheet_views_mut().get_sheet_view_list()[0]
+            .get_pane()
+            .expect("frozen pane");
+        assert_eq!(*pane.get_vertical_split(), 5.0);
+        assert_eq!(pane.get_top_left_cell().to_string(), "A6");
+        // 时间列按时间文本而不是第 2 行的说明设置列宽
+        let width = |col: u32| {
+            sheet
+                .get_column_dimension_by_number(&col)
+                .map(|column| *column.get_width())
+        };
+        assert_eq!(width(1), Some(21.0));
+        assert_eq!(width(2), Some(MIN_COLUMN_WIDTH));
+        assert_eq!(width(3), Some(16.0));
+
+        let plain = Template::from_toml_str(&format!(
+            "freeze_header = false\nauto_filter = false\nauto_width = false\n{BUILTIN}"
+        ))?;
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        plain.render_header(sheet, &vars, &columns);
+        plain.finish_sheet(sheet, columns.len(), 0);
+        assert!(sheet.get_auto_filter().is_none());
+        assert!(
+            sheet.get_sheet_views_mut().get_sheet_view_list()[0]
+                .get_pane()
+                .is_none()
+        );
+        assert!(
+            sheet
+                .get_column_dimensions()
+                .iter()
+                .all(|column| *column.get_width() != 21.0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn example_matches_builtin() -> Result<()> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("upload_template.example.toml");
+        assert_eq!(Template::load(&path)?, Template::builtin());
+        Ok(())
+    }
+
+    #[test]
+    fn custom_layout_and_errors() -> Result<()> {
+        let template = Template::from_toml_str(
+            r##"
+            time_column = "B"
+            first_column = "D"
+            start_row = 3
+
+            [styles.head]
+            fill = "#CCCCCC"
+            bold = true
+
+            [[header_rows]]
+            row = 1
+            label = "{station_code}"
+            field = "code"
+            style = "head"
+            "##,
+        )?;
+        assert_eq!(template.time_address(0), "B3");
+        assert_eq!(template.value_address(0, 1), "E3");
+
+        assert_eq!(template.styles["head"].fill.as_deref(), Some("ffcccccc"));
+
+        let invalid = [
+            "first_column = \"B\"\nstart_row = 6\n[styles.x]\nfill = \"red\"\n",
+            "first_column = \"B\"\nstart_row = 6\ntime_style = \"red\"\n",
+            "first_column = \"A\"\nstart_row = 6\n",
+            "first_column = \"B\"\nstart_row = 3\n[[header_rows]]\nrow = 3\nfield = \"name\"\n",
+            "first_column = \"B\"\nstart_row = 6\n[[cells]]\naddress = \"1A\"\nvalue = \"\"\n",
+        ];
+        for content in invalid {
+            assert!(Template::from_toml_str(content).is_err(), "{content}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+//! 浓度单位换算：体积比 ppbv、以碳计的 ppbC 与质量浓度 μg/m³、ng/m³。
+//!
+//! - ppbC = ppbv × 碳原子数
+//! - μg/m³ = ppbv × 分子量 ÷ 摩尔体积，摩尔体积 = R × T ÷ P（默认 25 ℃、101.325 kPa，即 24.47 L/mol）
+//! - ng/m³ = μg/m³ × 1000
+//!
+//! 另有源数据中数值文本的规范化（千位分隔符、小数逗号、前导 `+`、科学计数法）与输出的格式化。
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Result, invalid};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// 理想气体常数，kPa·L/(mol·K)。
+const GAS_CONSTANT: f64 = 8.314_462_618;
+
+/// 未指定小数位数时最多保留的小数位数。
+const DEFAULT_DECIMALS: usize = 4;
+
+/// 浓度单位。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Unit {
+    Ppbv,
+    PpbC,
+    UgM3,
+    NgM3,
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "ppbv" | "ppb" => Ok(Self::Ppbv),
+            "ppbC" | "ppbc" => Ok(Self::PpbC),
+            "μg/m³" | "µg/m³" | "ug/m3" | "μg/m3" => Ok(Self::UgM3),
+            "ng/m³" | "ng/m3" => Ok(Self::NgM3),
+            other => Err(format!(
+                "未知的浓度单位: {other}（可选 ppbv、ppbC、μg/m³、ng/m³）"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Unit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ppbv => "ppbv",
+            Self::PpbC => "ppbC",
+            Self::UgM3 => "μg/m³",
+            Self::NgM3 => "ng/m³",
+        })
+    }
+}
+
+/// 换算质量浓度时假定的温度与压力。
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Conditions {
+    /// 温度，℃
+    pub temperature: f64,
+    /// 压力，kPa
+    pub pressure: f64,
+}
+
+impl Default for Conditions {
+    fn default() -> Self {
+        Self {
+            temperature: 25.0,
+            pressure: 101.325,
+        }
+    }
+}
+
+impl Conditions {
+    /// 摩尔体积，L/mol。
+    pub fn molar_volume(&self) -> f64 {
+        GAS_CONSTANT * (self.temperature + 273.15) / self.pressure
+    }
+}
+
+/// 换算所需的物种参数：分子量（g/mol，换算 μg/m³ 时需要）与碳原子数（换算 ppbC 时需要）。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Species {
+    pub molecular_weight: Option<f64>,
+    pub carbon_number: Option<u32>,
+}
+
+impl Species {
+    fn molecular_weight(&self) -> Result<f64> {
+        self.molecular_weight
+            .filter(|mw| *mw > 0.0)
+            .ok_or_else(|| invalid!("换算 μg/m³ 需要大于 0 的 molecular_weight"))
+    }
+
+    fn carbon_number(&self) -> Result<f64> {
+        self.carbon_number
+            .filter(|n| *n > 0)
+            .map(f64::from)
+            .ok_or_else(|| invalid!("换算 ppbC 需要大于 0 的 carbon_number"))
+    }
+}
+
+/// 把 `value` 从 `from` 换算为 `to`。
+pub fn convert(
+    value: f64,
+    from: Unit,
+    to: Unit,
+    species: &Species,
+    conditions: &Conditions,
+) -> Result<f64> {
+    if from == to {
+        return Ok(value);
+    }
+    // ng/m³ 先折算为 μg/m³；两种质量浓度之间换算不需要物种参数
+    let (value, from) = match from {
+        Unit::NgM3 => (value / 1000.0, Unit::UgM3),
+        other => (value, other),
+    };
+    if to == Unit::NgM3 {
+        return Ok(convert(value, from, Unit::UgM3, species, conditions)? * 1000.0);
+    }
+    if from == to {
+        return Ok(value);
+    }
+    let ppbv = match from {
+        Unit::Ppbv => value,
+        Unit::PpbC => value / species.carbon_number()?,
+        Unit::UgM3 | Unit::NgM3 => {
+            value * conditions.molar_volume() / species.molecular_weight()?
+        }
+    };
+    Ok(match to {
+        Unit::Ppbv => ppbv,
+        Unit::PpbC => ppbv * species.carbon_number()?,
+        Unit::UgM3 | Unit::NgM3 => ppbv * species.molecular_weight()? / conditions.molar_volume(),
+    })
+}
+
+/// 源数据中数值的小数点。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalMark {
+    /// `1,234.5`：小数点为 `.`，千位分隔符为 `,`
+    #[default]
+    Point,
+    /// `1.234,5`：小数点为 `,`，千位分隔符为 `.`
+    Comma,
+}
+
+/// 两种写法都可用作千位分隔符的空格与撇号（如 `1 234,5`、`1'234.5`）。
+const GROUP_SPACES: [char; 4] = [' ', '\'', '\u{a0}', '\u{202f}'];
+
+/// 把源数据中的数值文本规范为 Rust 可解析的形式：去掉前导 `+` 与千位分隔符，小数逗号改为
+/// 小数点，科学计数法（如 `1.5E-3`）保留。千位分隔符须每 3 位一组，因此按 `.` 为小数点时
+/// `0,5` 不是数值。不是数值时返回 `None`。
+pub fn normalize_number(text: &str, mark: DecimalMark) -> Option<String> {
+    let (decimal, group) = match mark {
+        DecimalMark::Point => ('.', ','),
+        DecimalMark::Comma => (',', '.'),
+    };
+    let text = text.trim();
+    let (sign, unsigned) = match text.strip_prefix('+') {
+        Some(rest) => ("", rest),
+        None => match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text),
+        },
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(pos) => (&unsigned[..pos], Some(&unsigned[pos + 1..])),
+        None => (unsigned, None),
+    };
+    let (integer, fraction) = match mantissa.split_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    let groups: Vec<&str> = integer
+        .split(|c: char| c == group || GROUP_SPACES.contains(&c))
+        .collect();
+    let grouped = groups.len() > 1;
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    let valid_groups = groups.iter().enumerate().all(|(i, part)| {
+        digits(part)
+            && match (grouped, i) {
+                (false, _) => true,
+                (true, 0) => (1..=3).contains(&part.len()),
+                (true, _) => part.len() == 3,
+            }
+    });
+    if !valid_groups || !fraction.is_none_or(digits) {
+        return None;
+    }
+    let integer = groups.concat();
+    let fraction = fraction.unwrap_or_default();
+    if integer.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    let mut normalized = format!("{sign}{integer}");
+    if !fraction.is_empty() {
+        normalized.push('.');
+        normalized.push_str(fraction);
+    }
+    if let Some(exponent) = exponent {
+        let exponent: i32 = exponent
+            .strip_prefix('+')
+            .unwrap_or(exponent)
+            .parse()
+            .ok()?;
+        normalized.push_str(&format!("e{exponent}"));
+    }
+    normalized
+        .parse::<f64>()
+        .is_ok_and(f64::is_finite)
+        .then_some(normalized)
+}
+
+/// 格式化数值：指定 `decimals` 时保留固定位数，否则最多保留 4 位小数并去掉末尾的 0。
+pub fn format_number(value: f64, decimals: Option<u32>) -> String {
+    let text = match decimals {
+        Some(decimals) => format!("{value:.*}", decimals as usize),
+        None => {
+            let text = format!("{value:.DEFAULT_DECIMALS$}");
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+    };
+    // 修约为 0 的负数不保留负号
+    match text.strip_prefix('-') {
+        Some(unsigned) if unsigned.trim_start_matches(['0', '.']).is_empty() => {
+            unsigned.to_string()
+        }
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_numbers() {
+        let point = |text| normalize_number(text, DecimalMark::Point);
+        let comma = |text| normalize_number(text, DecimalMark::Comma);
+        assert_eq!(point("1,234.5").as_deref(), Some("1234.5"));
+        assert_eq!(point(" +0.5 ").as_deref(), Some("0.5"));
+        assert_eq!(point("-1 234 567").as_deref(), Some("-1234567"));
+        assert_eq!(point("1.5E-3").as_deref(), Some("1.5e-3"));
+        assert_eq!(point(".5").as_deref(), Some(".5"));
+        assert_eq!(point("0,5"), None);
+        assert_eq!(point("1,23.4"), None);
+        assert_eq!(comma("0,5").as_deref(), Some("0.5"));
+        assert_eq!(comma("1.234,5").as_deref(), Some("1234.5"));
+        assert_eq!(comma("2,5e+2").as_deref(), Some("2.5e2"));
+        assert_eq!(comma("1,2,3"), None);
+        for text in [
+            "", "+", "-", "N/A", "1e", "e5", "1.5(C)", "--1", "inf", "NaN",
+        ] {
+            assert_eq!(point(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn converts_between_units() -> Result<()> {
+        let toluene = Species {
+            molecular_weight: Some(92.14),
+            carbon_number: Some(7),
+        };
+        let standard = Conditions::default();
+        assert!((standard.molar_volume() - 24.465).abs() < 1e-3);
+
+        assert_eq!(
+            convert(2.0, Unit::Ppbv, Unit::PpbC, &toluene, &standard)?,
+            14.0
+        );
+        let ug = convert(1.0, Unit::Ppbv, Unit::UgM3, &toluene, &standard)?;
+        assert_eq!(format_number(ug, Some(3)), "3.766");
+        let back = convert(ug * 7.0, Unit::UgM3, Unit::PpbC, &toluene, &standard)?;
+        assert!((back - 49.0).abs() < 1e-9);
+
+        let methane = Species {
+            carbon_number: Some(1),
+            ..Default::default()
+        };
+        assert!(convert(1.0, Unit::Ppbv, Unit::UgM3, &methane, &standard).is_err());
+
+        let none = Species::default();
+        assert_eq!(
+            convert(12.5, Unit::NgM3, Unit::UgM3, &none, &standard)?,
+            0.0125
+        );
+        assert_eq!(
+            convert(0.5, Unit::UgM3, Unit::NgM3, &none, &standard)?,
+            500.0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_unit_names() {
+        assert_eq!("ppb".parse(), Ok(Unit::Ppbv));
+        assert_eq!("ug/m3".parse(), Ok(Unit::UgM3));
+        assert_eq!("ng/m³".parse(), Ok(Unit::NgM3));
+        assert!("ppm".parse::<Unit>().is_err());
+    }
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(format_number(0.1 + 0.2, None), "0.3");
+        assert_eq!(format_number(2.0, None), "2");
+        assert_eq!(format_number(1.0 / 3.0, None), "0.3333");
+        assert_eq!(format_number(1.23456, Some(3)), "1.235");
+        assert_eq!(format_number(-0.0001, Some(3)), "0.000");
+    }
+}
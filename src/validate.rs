@@ -0,0 +1,619 @@
+//! `dttools validate`：检查准备上传的模板文件是否符合国家平台的格式。
+//!
+//! 按所选模板（proton、ocec、metals、bc）的版式与站点配置检查第一个工作表：表头行的文字、
+//! 站点编码与因子编码（`a` 加 5 位数字，并与配置及编码表一致）、时间格式与顺序、数值是否可解析且在
+//! 合理范围内，以及无效码 `-999` 与 `值#标记码` 的写法。发现问题时列出全部问题并返回错误。
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use chrono::NaiveDateTime;
+use clap::{Args, ValueEnum};
+use regex::Regex;
+use serde::Serialize;
+use tracing::info;
+use umya_spreadsheet::Worksheet;
+use umya_spreadsheet::helper::coordinate::coordinate_from_index;
+
+use crate::cli::GlobalArgs;
+use crate::error::{DtToolsError, Result, invalid};
+use crate::flags::{self, DataFlag};
+use crate::instrument::{Instrument, InstrumentOptions};
+use crate::proton::{self, Bounds, ProtonOptions};
+use crate::species::Dictionary;
+use crate::template::{Field, Template};
+use crate::{batch, bc, codes, metals, ocec, workbook};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 缺测、无效数据的无效码。
+const INVALID_CODE: &str = "-999";
+
+/// 国家平台的因子编码，如 `a21026`。
+static FACTOR_CODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^a\d{5}$").expect("valid regex"));
+
+/// 校验所依据的模板。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Schema {
+    /// 离子色谱（dttools proton）
+    Proton,
+    /// OC/EC（dttools ocec）
+    Ocec,
+    /// 重金属（dttools metals）
+    Metals,
+    /// 黑碳（dttools bc）
+    Bc,
+}
+
+impl Schema {
+    fn instrument(self) -> Option<&'static Instrument> {
+        match self {
+            Self::Proton => None,
+            Self::Ocec => Some(&ocec::INSTRUMENT),
+            Self::Metals => Some(&metals::INSTRUMENT),
+            Self::Bc => Some(&bc::INSTRUMENT),
+        }
+    }
+}
+
+/// 模板中的一个监测项目列。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedColumn {
+    pub name: String,
+    /// 配置的因子编码；为空时只检查格式
+    pub code: String,
+    pub unit: String,
+    /// 合理范围；`None` 表示只检查非负
+    pub range: Option<Bounds>,
+}
+
+/// 上传文件应有的版式与表头。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expected {
+    pub template: Template,
+    /// 第 3 行 A 列的名称
+    pub title: String,
+    pub columns: Vec<ExpectedColumn>,
+    /// 配置的缺失值写法（空单元格与 `-999` 之外）
+    pub missing_value: String,
+    /// 数据标记（C、M、N）与因子编码之外接受的标记码，如低于检出限的 `DL`
+    pub flag_codes: Vec<String>,
+    /// 按编码表检查没有配置编码的列时使用的名称词典（含配置的 `[species]`）
+    pub species: Dictionary,
+}
+
+impl Expected {
+    /// 按 `schema` 与站点配置（`config` 为 `None` 时按查找顺序查找默认配置）构造。
+    pub fn load(schema: Schema, config: Option<&Path>) -> Result<Self> {
+        match schema.instrument() {
+            None => Ok(Self::from_proton(&ProtonOptions::discover(config)?)),
+            Some(instrument) => Ok(Self::from_instrument(&InstrumentOptions::discover(
+                instrument, config,
+            )?)),
+        }
+    }
+
+    pub fn from_proton(options: &ProtonOptions) -> Self {
+        Self {
+            template: options.template.clone(),
+            title: proton::TITLE.to_string(),
+            columns: proton::column_headers(options)
+                .into_iter()
+                .zip(&options.range)
+                .map(|(header, range)| ExpectedColumn {
+                    name: header.name.to_string(),
+                    code: header.code.to_string(),
+                    unit: header.unit.to_string(),
+                    range: *range,
+                })
+                .collect(),
+            missing_value: options.missing_value.clone(),
+            flag_codes: vec![options.below_limit_code.clone()],
+            species: options.species.clone(),
+        }
+    }
+
+    pub fn from_instrument(options: &InstrumentOptions) -> Self {
+        Self {
+            template: options.template.clone(),
+            title: options.title.clone(),
+            columns: options
+                .columns
+                .iter()
+                .map(|column| ExpectedColumn {
+                    name: column.name.clone(),
+                    code: column.code.clone(),
+                    unit: options.unit.to_string(),
+                    range: None,
+                })
+                .collect(),
+            missing_value: String::new(),
+            flag_codes: Vec::new(),
+            species: Dictionary::default(),
+        }
+    }
+}
+
+/// 问题的类别。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// 表头文字或站点编码
+    Header,
+    /// 因子编码
+    Code,
+    /// 时间格式或顺序
+    Time,
+    /// 非数值、负值或超出合理范围
+    Value,
+    /// 无效码或数据标记的写法
+    Flag,
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Header => "表头",
+            Self::Code => "因子编码",
+            Self::Time => "时间",
+            Self::Value => "数值",
+            Self::Flag => "标记",
+        })
+    }
+}
+
+/// 一个问题。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub address: String,
+    pub kind: FindingKind,
+    /// 单元格中的值
+    pub value: String,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}: {:?}",
+            self.address, self.kind, self.message, self.value
+        )
+    }
+}
+
+/// 检查 `sheet` 是否符合 `expected`，按表头、数据行的顺序返回全部问题。
+pub fn validate(sheet: &Worksheet, expected: &Expected) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut report = |col: u32, row: u32, kind: FindingKind, value: &str, message: String| {
+        findings.push(Finding {
+            address: coordinate_from_index(&col, &row),
+            kind,
+            value: value.to_string(),
+            message,
+        });
+    };
+    let template = &expected.template;
+    let value = |col: u32, row: u32| workbook::cell_text(sheet, col, row).trim().to_string();
+
+    for cell in &template.cells {
+        let actual = sheet.get_value(cell.address.to_ascii_uppercase().as_str());
+        // 说明文字因站点而异，只检查固定的文字
+        if !cell.value.contains('{') && actual.trim() != cell.value {
+            let (col, row) = cell_position(&cell.address);
+            report(
+                col,
+                row,
+                FindingKind::Header,
+                &actual,
+                format!("应为 {:?}", cell.value),
+            );
+        }
+    }
+
+    for header in &template.header_rows {
+        let row = header.row;
+        let label = value(template.time_column, row);
+        if header.label.contains("{station_code}") {
+            if label.is_empty() || !label.chars().all(|c| c.is_ascii_digit()) {
+                report(
+                    template.time_column,
+                    row,
+                    FindingKind::Header,
+                    &label,
+                    "站点编码应为数字".to_string(),
+                );
+            }
+        } else {
+            let expected_label = header.label.replace("{title}", &expected.title);
+            if !expected_label.contains('{') && label != expected_label {
+                report(
+                    template.time_column,
+                    row,
+                    FindingKind::Header,
+                    &label,
+                    format!("应为 {expected_label:?}"),
+                );
+            }
+        }
+
+        for (i, column) in expected.columns.iter().enumerate() {
+            let col = template.first_column + i as u32;
+            let actual = value(col, row);
+            match header.field {
+                Field::Name | Field::Unit => {
+                    let wanted = if header.field == Field::Name {
+                        &column.name
+                    } else {
+                        &column.unit
+                    };
+                    if &actual != wanted {
+                        report(
+                            col,
+                            row,
+                            FindingKind::Header,
+                            &actual,
+                            format!("应为 {wanted:?}"),
+                        );
+                    }
+                }
+                Field::Code => {
+                    if !FACTOR_CODE.is_match(&actual) {
+                        report(
+                            col,
+                            row,
+                            FindingKind::Code,
+                            &actual,
+                            format!("{} 的因子编码应为 a 加 5 位数字", column.name),
+                        );
+                    } else if !column.code.is_empty() && actual != column.code {
+                        report(
+                            col,
+                            row,
+                            FindingKind::Code,
+                            &actual,
+                            format!("与配置中 {} 的因子编码 {} 不一致", column.name, column.code),
+                        );
+                    } else if column.code.is_empty()
+                        && let Some(factor) =
+                            codes::mismatch(&column.name, &actual, &expected.species)
+                    {
+                        report(
+                            col,
+                            row,
+                            FindingKind::Code,
+                            &actual,
+                            format!(
+                                "与编码表中 {} 的因子编码 {} 不一致",
+                                column.name, factor.code
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        let extra = template.first_column + expected.columns.len() as u32;
+        let actual = value(extra, row);
+        if !actual.is_empty() {
+            report(
+                extra,
+                row,
+                FindingKind::Header,
+                &actual,
+                "多余的列".to_string(),
+            );
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut previous: Option<NaiveDateTime> = None;
+    for row in template.start_row..=sheet.get_highest_row() {
+        let values: Vec<String> = (0..expected.columns.len())
+            .map(|i| value(template.first_column + i as u32, row))
+            .collect();
+        let time_text = value(template.time_column, row);
+        if time_text.is_empty() {
+            if values.iter().any(|value| !value.is_empty()) {
+                report(
+                    template.time_column,
+                    row,
+                    FindingKind::Time,
+                    "",
+                    "有数据的行缺少时间".to_string(),
+                );
+            }
+            continue;
+        }
+        match NaiveDateTime::parse_from_str(&time_text, TIME_FORMAT) {
+            Ok(time) if time.format(TIME_FORMAT).to_string() == time_text => {
+                if !seen.insert(time) {
+                    report(
+                        template.time_column,
+                        row,
+                        FindingKind::Time,
+                        &time_text,
+                        "时间重复".to_string(),
+                    );
+                } else if previous.is_some_and(|previous| time < previous) {
+                    report(
+                        template.time_column,
+                        row,
+                        FindingKind::Time,
+                        &time_text,
+                        "时间早于上一行".to_string(),
+                    );
+                }
+                previous = Some(time);
+            }
+            _ => report(
+                template.time_column,
+                row,
+                FindingKind::Time,
+                &time_text,
+                "时间格式应为 YYYY-MM-DD HH:MM:SS".to_string(),
+            ),
+        }
+
+        for (i, (text, column)) in values.iter().zip(&expected.columns).enumerate() {
+            if *text == expected.missing_value {
+                continue;
+            }
+            if let Some((kind, message)) = check_value(text, column.range, &expected.flag_codes) {
+                report(template.first_column + i as u32, row, kind, text, message);
+            }
+        }
+    }
+    findings
+}
+
+/// 单元格地址（已通过版式检查）拆为列号与行号。
+fn cell_position(address: &str) -> (u32, u32) {
+    let split = address
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(address.len());
+    let (letters, digits) = address.split_at(split);
+    (
+        workbook::column_index(letters).unwrap_or(1),
+        digits.parse().unwrap_or(1),
+    )
+}
+
+/// 检查一个浓度单元格；空单元格、`-999` 与 `值#标记码` 都是有效的写法，标记码为数据标记、
+/// 因子编码或 `flag_codes` 之一。
+fn check_value(
+    text: &str,
+    range: Option<Bounds>,
+    flag_codes: &[String],
+) -> Option<(FindingKind, String)> {
+    if text.is_empty() || text == INVALID_CODE {
+        return None;
+    }
+    if text.contains('(') {
+        return Some((
+            FindingKind::Flag,
+            "仪器的括号标记应转换为 值#标记码".to_string(),
+        ));
+    }
+    if text.contains('#') {
+        return match flags::split(text) {
+            None => Some((FindingKind::Flag, "标记应写为 值#标记码".to_string())),
+            Some((_, code))
+                if code.parse::<DataFlag>().is_ok()
+                    || FACTOR_CODE.is_match(code)
+                    || flag_codes.iter().any(|known| known == code) =>
+            {
+                None
+            }
+            Some((_, code)) => Some((FindingKind::Flag, format!("未知的标记码: {code}"))),
+        };
+    }
+    let Ok(value) = text.parse::<f64>() else {
+        return Some((FindingKind::Value, "不是数值".to_string()));
+    };
+    if !value.is_finite() {
+        return Some((FindingKind::Value, "不是有限的数值".to_string()));
+    }
+    if [-99.0, -999.0, -9999.0].contains(&value) {
+        return Some((FindingKind::Flag, format!("无效码应写为 {INVALID_CODE}")));
+    }
+    if value < 0.0 {
+        return Some((FindingKind::Value, "浓度不能为负".to_string()));
+    }
+    let range = range?;
+    let below = range.min.is_some_and(|min| value < min);
+    let above = range.max.is_some_and(|max| value > max);
+    (below || above).then(|| {
+        let bound = |bound: Option<f64>| bound.map(|b| b.to_string()).unwrap_or_default();
+        (
+            FindingKind::Value,
+            format!("超出合理范围 [{}, {}]", bound(range.min), bound(range.max)),
+        )
+    })
+}
+
+/// `dttools validate` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct ValidateArgs {
+    /// 要检查的上传文件（xlsx/xls/ods）、目录或通配符
+    #[arg(value_name = "FILE", required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// 文件对应的模板
+    #[arg(long, value_enum)]
+    pub schema: Schema,
+
+    /// 站点配置文件，用于因子编码、合理范围与版式（默认为该模板的配置文件，如 proton_config.toml）
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+}
+
+pub fn execute(args: &ValidateArgs, global: &GlobalArgs) -> Result<()> {
+    let expected = Expected::load(args.schema, args.config.as_deref())?;
+    let inputs = batch::expand_inputs(&args.inputs, &["xlsx", "xls", "ods"])?;
+
+    let mut failed = 0;
+    let mut total = 0;
+    for path in &inputs.files {
+        let book = workbook::read(path)?;
+        let sheet = book
+            .get_sheet(&0)
+            .ok_or_else(|| invalid!("工作簿中没有工作表: {}", path.display()))?;
+        let findings = validate(sheet, &expected);
+        if findings.is_empty() {
+            info!("校验通过: {}", path.display());
+            continue;
+        }
+        failed += 1;
+        total += findings.len();
+        if !global.quiet {
+            // 标准输出留给运行摘要时，问题列表写到标准错误
+            let mut out: Box<dyn Write> = if global.json_summary {
+                Box::new(io::stderr())
+            } else {
+                Box::new(io::stdout())
+            };
+            writeln!(out, "{}: {} 个问题", path.display(), findings.len())?;
+            for finding in &findings {
+                writeln!(out, "  {finding}")?;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(DtToolsError::Validation {
+            files: failed,
+            findings: total,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proton::{CsvEncoding, read_source, transform};
+    use tempfile::tempdir;
+
+    fn kinds(findings: &[Finding]) -> Vec<(&str, FindingKind)> {
+        findings
+            .iter()
+            .map(|finding| (finding.address.as_str(), finding.kind))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_proton_output() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("proton.csv");
+        std::fs::write(
+            &input,
+            "时间,NO₃⁻(μg/m³),SO₄²⁻(μg/m³),NH₄⁺(μg/m³),Cl⁻(μg/m³),K⁺(μg/m³),Na⁺(μg/m³),Mg²⁺(μg/m³),Ca²⁺(μg/m³)\n\
+             2026-01-05 01:00:00,1.2,3.4,1.1,0.2,0.1,0.3,0.05,0.2(C)\n",
+        )?;
+        let options = ProtonOptions::default();
+        let out = transform(&read_source(&input, b',', CsvEncoding::Auto)?, &options)?;
+        let sheet = out.workbook.get_sheet(&0).expect("sheet");
+        assert_eq!(validate(sheet, &Expected::from_proton(&options)), []);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_header_time_and_value_problems() {
+        let options = ProtonOptions::default();
+        let expected = Expected::from_proton(&options);
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        let headers = proton::column_headers(&options);
+        expected.template.render_header(
+            sheet,
+            &crate::template::Vars {
+                title: proton::TITLE,
+                station_code: "4401000010003",
+                a2_text: "",
+            },
+            &headers,
+        );
+        sheet.get_cell_mut("B3").set_value("SO2");
+        sheet.get_cell_mut("C4").set_value("a2151");
+        let rows = [
+            ["2026-01-05 01:00:00", "-999", "1.6#C", "-999#a24041"],
+            ["2026/01/05 02:00", "-9999", "1.6#X", "1.6(C)"],
+            ["2026-01-05 01:00:00", "-1", "n/a", ""],
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            for (col, value) in row.iter().enumerate() {
+                sheet
+                    .get_cell_mut((col as u32 + 1, i as u32 + 6))
+                    .set_value(*value);
+            }
+        }
+
+        let findings = validate(sheet, &expected);
+        assert_eq!(
+            kinds(&findings),
+            [
+                ("B3", FindingKind::Header),
+                ("C4", FindingKind::Code),
+                ("A7", FindingKind::Time),
+                ("B7", FindingKind::Flag),
+                ("C7", FindingKind::Flag),
+                ("D7", FindingKind::Flag),
+                ("A8", FindingKind::Time),
+                ("B8", FindingKind::Value),
+                ("C8", FindingKind::Value),
+            ]
+        );
+        assert_eq!(findings[6].message, "时间重复");
+    }
+
+    #[test]
+    fn checks_codes_against_registry_without_configured_code() {
+        let options = ProtonOptions::default();
+        let mut expected = Expected::from_proton(&options);
+        expected.columns[5].code.clear();
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        expected.template.render_header(
+            sheet,
+            &crate::template::Vars {
+                title: proton::TITLE,
+                station_code: "4401000010003",
+                a2_text: "",
+            },
+            &proton::column_headers(&options),
+        );
+        assert_eq!(validate(sheet, &expected), []);
+
+        sheet.get_cell_mut("G4").set_value("a06005");
+        let findings = validate(sheet, &expected);
+        assert_eq!(kinds(&findings), [("G4", FindingKind::Code)]);
+        assert!(findings[0].message.contains("a06006"), "{findings:?}");
+
+        // 配置了编码时以配置为准，不再与编码表比较
+        expected.columns[5].code = "a06005".to_string();
+        assert_eq!(validate(sheet, &expected), []);
+    }
+
+    #[test]
+    fn checks_configured_codes_and_ranges() {
+        assert_eq!(
+            check_value(
+                "600",
+                Some(Bounds {
+                    min: Some(0.0),
+                    max: Some(500.0)
+                }),
+                &[]
+            ),
+            Some((FindingKind::Value, "超出合理范围 [0, 500]".to_string()))
+        );
+        assert_eq!(check_value("0.8#M", None, &[]), None);
+        assert_eq!(check_value("1.5", None, &[]), None);
+        let codes = ["DL".to_string()];
+        assert_eq!(check_value("0.01#DL", None, &codes), None);
+        assert!(check_value("0.01#DL", None, &[]).is_some());
+    }
+}
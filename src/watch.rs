@@ -0,0 +1,529 @@
+//! `dttools watch`：监视仪器导出目录，自动处理新文件。
+//!
+//! 处理成功的原始文件移入 `archive/`，输出写入 `outbox/`。仍在写入的文件（大小或修改时间在
+//! `--settle` 秒内有变化）暂不处理；处理失败的文件按 2、4、8… 秒的间隔重试，超过 `--retries`
+//! 次后放弃，直到文件再次被修改。仍无法打开的文件（损坏的压缩包等）移入 `quarantine/`，
+//! 旁边写一份错误说明。
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::{Args, Subcommand};
+use notify::{RecursiveMode, Watcher};
+use tracing::{error, info, warn};
+
+use crate::cli::GlobalArgs;
+use crate::error::{Context, DtToolsError, Result, invalid};
+use crate::{batch, report};
+use crate::{eemcg, proton};
+
+/// 处理成功的原始文件移入的子目录。
+pub const ARCHIVE_DIR: &str = "archive";
+/// 输出文件写入的子目录。
+pub const OUTBOX_DIR: &str = "outbox";
+
+/// 没有文件系统事件时重新检查目录的间隔。
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `dttools watch` 的参数。
+#[derive(Debug, Clone, Args)]
+pub struct WatchArgs {
+    /// 监视的目录
+    #[arg(value_name = "DIR")]
+    pub dir: PathBuf,
+
+    /// 文件大小和修改时间保持不变多少秒后才处理，避免读到仍在写入的文件
+    #[arg(long, value_name = "SECONDS", default_value_t = 2)]
+    pub settle: u64,
+
+    /// 处理失败后的重试次数
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub retries: u32,
+
+    /// 只处理目录中已有的文件，处理完即退出
+    #[arg(long)]
+    pub once: bool,
+
+    /// 处理新文件所用的工具及其参数
+    #[command(subcommand)]
+    pub tool: WatchTool,
+}
+
+/// 监视目录时使用的工具。
+#[derive(Debug, Clone, Subcommand)]
+pub enum WatchTool {
+    /// VOCs/NMHC 工作表重命名与单元格修正
+    Eemcg(eemcg::EemcgArgs),
+    /// 离子色谱数据转换为上传模板
+    Proton(Box<proton::ProtonArgs>),
+}
+
+impl WatchTool {
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Eemcg(_) => eemcg::INPUT_EXTENSIONS,
+            Self::Proton(_) => proton::INPUT_EXTENSIONS,
+        }
+    }
+
+    fn process(&self, file: &Path, global: &GlobalArgs) -> Result<()> {
+        let inputs = vec![file.to_path_buf()];
+        match self {
+            Self::Eemcg(args) => eemcg::execute(
+                &eemcg::EemcgArgs {
+                    inputs,
+                    ..args.clone()
+                },
+                global,
+            ),
+            Self::Proton(args) => proton::execute(
+                &proton::ProtonArgs {
+                    inputs,
+                    ..(**args).clone()
+                },
+                global,
+            ),
+        }
+    }
+}
+
+/// 文件的大小与修改时间，用于判断文件是否仍在写入。
+type Stamp = (u64, Option<SystemTime>);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+#[derive(Debug)]
+struct Pending {
+    stamp: Stamp,
+    /// 上次发现文件变化的时间
+    changed_at: Instant,
+    failures: u32,
+    /// 失败后下次重试的时间
+    retry_at: Instant,
+    /// 已处理但留在原处（试运行），修改前不再处理
+    held: bool,
+}
+
+/// 待处理文件队列：记录每个文件最近一次变化的时间与失败次数。
+#[derive(Debug)]
+struct Queue {
+    settle: Duration,
+    retries: u32,
+    pending: HashMap<PathBuf, Pending>,
+}
+
+impl Queue {
+    fn new(settle: Duration, retries: u32) -> Self {
+        Self {
+            settle,
+            retries,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 根据目录中的文件更新队列，返回已稳定、可以处理的文件。
+    fn ready(&mut self, files: &[PathBuf], now: Instant) -> Vec<PathBuf> {
+        self.pending.retain(|path, _| files.contains(path));
+        let mut ready = Vec::new();
+        for path in files {
+            let Some(current) = stamp(path) else {
+                continue;
+            };
+            let entry = self.pending.entry(path.clone()).or_insert(Pending {
+                stamp: current,
+                changed_at: now,
+                failures: 0,
+                retry_at: now,
+                held: false,
+            });
+            if entry.stamp != current {
+                // 文件又被修改：重新等待稳定，并重置失败次数
+                *entry = Pending {
+                    stamp: current,
+                    changed_at: now,
+                    failures: 0,
+                    retry_at: now,
+                    held: false,
+                };
+            }
+            if !entry.held
+                && entry.failures <= self.retries
+                && now >= entry.retry_at
+                && now.duration_since(entry.changed_at) >= self.settle
+            {
+                ready.push(path.clone());
+            }
+        }
+        ready
+    }
+
+    /// 记录一次处理失败，返回是否还会重试；重试前的等待时间逐次加倍。
+    fn failed(&mut self, path: &Path, now: Instant) -> bool {
+        match self.pending.get_mut(path) {
+            Some(entry) => {
+                entry.failures += 1;
+                entry.retry_at = now + batch::backoff(entry.failures);
+                entry.failures <= self.retries
+            }
+            None => false,
+        }
+    }
+
+    fn done(&mut self, path: &Path) {
+        self.pending.remove(path);
+    }
+
+    /// 记录已处理但仍留在目录中的文件。
+    fn hold(&mut self, path: &Path) {
+        if let Some(entry) = self.pending.get_mut(path) {
+            entry.held = true;
+        }
+    }
+}
+
+/// 目录中待处理的文件：扩展名符合、不是 Excel 的临时锁文件（`~$` 开头）。
+fn scan(dir: &Path, extensions: &[&str]) -> Result<Vec<PathBuf>> {
+    batch::list_dir(dir, |path| {
+        batch::has_extension(path, extensions)
+            && !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("~$"))
+    })
+}
+
+/// 一轮处理的结果。
+#[derive(Debug, Default)]
+struct Round {
+    total: usize,
+    failed: usize,
+    /// 第一个失败的文件及其错误
+    first: Option<String>,
+    /// 宽松模式下跳过的数据问题数
+    problems: usize,
+}
+
+impl Round {
+    /// `--once` 的结果：有文件失败时同批量处理一样返回 [`DtToolsError::Batch`]，
+    /// 否则跳过了数据问题时返回 [`DtToolsError::Partial`]。
+    fn finish(self) -> Result<()> {
+        match self.first {
+            Some(first) => Err(DtToolsError::Batch {
+                failed: self.failed,
+                total: self.total,
+                first,
+            }),
+            None => report::partial(self.problems),
+        }
+    }
+}
+
+/// 处理一轮：检查目录，处理已稳定的文件。只有无法读取目录时返回错误。
+fn process_ready(
+    args: &WatchArgs,
+    global: &GlobalArgs,
+    queue: &mut Queue,
+    now: Instant,
+) -> Result<Round> {
+    let files = scan(&args.dir, args.tool.extensions())?;
+    let mut round = Round::default();
+    for file in queue.ready(&files, now) {
+        round.total += 1;
+        // 宽松模式下跳过了数据问题：输出已写出，同成功一样归档
+        let result = args
+            .tool
+            .process(&file, global)
+            .or_else(|err| match err.kind() {
+                DtToolsError::Partial { problems } => {
+                    round.problems += problems;
+                    Ok(())
+                }
+                _ => Err(err),
+            });
+        match result {
+            Ok(()) if global.dry_run => queue.hold(&file),
+            // 已由 --archive-dir 归档；硬链接归档时原文件仍在，修改前不再处理
+            Ok(()) if global.archive.archive_dir.is_some() => {
+                if file.exists() {
+                    queue.hold(&file);
+                } else {
+                    queue.done(&file);
+                }
+            }
+            Ok(()) => {
+                match batch::move_into(&file, &args.dir.join(ARCHIVE_DIR)) {
+                    Ok(archived) => info!("原始文件已归档: {}", archived.display()),
+                    // 留在原处，修改前不再处理
+                    Err(err) => error!("无法归档原始文件: {}: {err:#}", file.display()),
+                }
+                queue.hold(&file);
+            }
+            Err(err) => {
+                if queue.failed(&file, now) {
+                    warn!("处理失败，稍后重试: {}: {err:#}", file.display());
+                    continue;
+                }
+                round.failed += 1;
+                round
+                    .first
+                    .get_or_insert_with(|| format!("{}: {err:#}", file.display()));
+                if batch::is_open_failure(&err) && !global.dry_run {
+                    match batch::quarantine(&file, &err, args.retries + 1) {
+                        Ok(moved) => {
+                            error!("无法打开，已移入隔离目录: {}: {err:#}", moved.display())
+                        }
+                        Err(moved) => error!(
+                            "无法打开，也无法移入隔离目录（文件修改后会再次处理）: {}: {err:#}; {moved:#}",
+                            file.display()
+                        ),
+                    }
+                } else {
+                    error!(
+                        "处理失败 {} 次，已放弃（文件修改后会再次处理）: {}: {err:#}",
+                        args.retries + 1,
+                        file.display()
+                    );
+                }
+            }
+        }
+    }
+    Ok(round)
+}
+
+pub fn execute(args: &WatchArgs, global: &GlobalArgs) -> Result<()> {
+    if !args.dir.is_dir() {
+        return Err(invalid!("监视的目录不存在: {}", args.dir.display()));
+    }
+    if global.in_place {
+        return Err(invalid!(
+            "watch 不支持 --in-place，原始文件会移入 {ARCHIVE_DIR}/"
+        ));
+    }
+    if global.output.is_some() {
+        return Err(invalid!(
+            "watch 不支持 --output，输出写入 {OUTBOX_DIR}/ 或 --output-dir"
+        ));
+    }
+    let global = GlobalArgs {
+        output_dir: Some(
+            global
+                .output_dir
+                .clone()
+                .unwrap_or_else(|| args.dir.join(OUTBOX_DIR)),
+        ),
+        ..global.clone()
+    };
+
+    if args.once {
+        let mut queue = Queue::new(Duration::ZERO, 0);
+        return process_ready(args, &global, &mut queue, Instant::now())?.finish();
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .context("无法创建目录监视")?;
+    watcher
+        .watch(&args.dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("无法监视目录: {}", args.dir.display()))?;
+    info!("正在监视 {}（Ctrl+C 退出）", args.dir.display());
+
+    let mut queue = Queue::new(Duration::from_secs(args.settle), args.retries);
+    loop {
+        // 文件系统事件只用于尽快唤醒；是否处理由目录检查决定
+        if let Ok(Err(err)) = rx.recv_timeout(POLL_INTERVAL) {
+            warn!("目录监视出错: {err}");
+        }
+        while rx.try_recv().is_ok() {}
+        // 目录暂时无法读取（网络盘断开等）时下一轮再试
+        if let Err(err) = process_ready(args, &global, &mut queue, Instant::now()) {
+            warn!("无法读取监视的目录，稍后重试: {err:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use tempfile::tempdir;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        args: WatchArgs,
+    }
+
+    #[test]
+    fn waits_until_files_settle_and_limits_retries() -> Result<()> {
+        let dir = tempdir()?;
+        let file = dir.path().join("a.csv");
+        fs::write(&file, "x")?;
+        let files = vec![file.clone()];
+        let start = Instant::now();
+        let mut queue = Queue::new(Duration::from_secs(2), 1);
+
+        assert!(queue.ready(&files, start).is_empty());
+        assert_eq!(queue.ready(&files, start + Duration::from_secs(2)), files);
+
+        // 失败后等待 2 秒再重试
+        let failed_at = start + Duration::from_secs(2);
+        assert!(queue.failed(&file, failed_at));
+        assert!(
+            queue
+                .ready(&files, failed_at + Duration::from_secs(1))
+                .is_empty()
+        );
+        assert_eq!(
+            queue.ready(&files, failed_at + Duration::from_secs(2)),
+            files
+        );
+        assert!(!queue.failed(&file, failed_at + Duration::from_secs(2)));
+        assert!(
+            queue
+                .ready(&files, start + Duration::from_secs(10))
+                .is_empty()
+        );
+
+        // 文件被修改后重新计时并重置失败次数
+        fs::write(&file, "xy")?;
+        let later = start + Duration::from_secs(20);
+        assert!(queue.ready(&files, later).is_empty());
+        assert_eq!(queue.ready(&files, later + Duration::from_secs(2)), files);
+        Ok(())
+    }
+
+    #[test]
+    fn once_processes_and_archives_existing_files() -> Result<()> {
+        let dir = tempdir()?;
+        let mut book = umya_spreadsheet::new_file();
+        book.get_active_sheet_mut()
+            .get_cell_mut("A3")
+            .set_value("x(1)");
+        let input = dir.path().join("export.xlsx");
+        umya_spreadsheet::writer::xlsx::write(&book, &input)?;
+        fs::write(dir.path().join("~$export.xlsx"), "lock")?;
+
+        let cli = TestCli::parse_from([
+            "watch",
+            dir.path().to_str().expect("utf-8 path"),
+            "--once",
+            "eemcg",
+        ]);
+        execute(&cli.args, &GlobalArgs::default())?;
+
+        assert!(!input.exists());
+        assert!(dir.path().join(ARCHIVE_DIR).join("export.xlsx").exists());
+        assert!(
+            dir.path()
+                .join(OUTBOX_DIR)
+                .join("processed_export.xlsx")
+                .exists()
+        );
+        assert!(dir.path().join("~$export.xlsx").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_watching_when_archiving_fails() -> Result<()> {
+        let dir = tempdir()?;
+        let mut book = umya_spreadsheet::new_file();
+        book.get_active_sheet_mut()
+            .get_cell_mut("A3")
+            .set_value("x(1)");
+        let input = dir.path().join("export.xlsx");
+        umya_spreadsheet::writer::xlsx::write(&book, &input)?;
+        // archive 是普通文件，无法移入
+        fs::write(dir.path().join(ARCHIVE_DIR), "")?;
+
+        let cli = TestCli::parse_from(["watch", dir.path().to_str().expect("utf-8 path"), "eemcg"]);
+        let global = GlobalArgs {
+            output_dir: Some(dir.path().join(OUTBOX_DIR)),
+            ..Default::default()
+        };
+        let mut queue = Queue::new(Duration::ZERO, 0);
+        process_ready(&cli.args, &global, &mut queue, Instant::now())?;
+        assert!(input.exists());
+        assert!(
+            dir.path()
+                .join(OUTBOX_DIR)
+                .join("processed_export.xlsx")
+                .exists()
+        );
+        // 留在原处的文件修改前不再处理
+        let files = scan(dir.path(), eemcg::INPUT_EXTENSIONS)?;
+        assert!(queue.ready(&files, Instant::now()).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn archives_partial_results() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("export.csv");
+        let header = "时间,NO₃⁻(μg/m³),SO₄²⁻(μg/m³),NH₄⁺(μg/m³),Cl⁻(μg/m³),K⁺(μg/m³),Na⁺(μg/m³),Mg²⁺(μg/m³),Ca²⁺(μg/m³)";
+        let text = format!("{header}\n2026-01-05 01:00:00,1,2,3,4,5,6,7,8\nbad,1,2,3,4,5,6,7,8\n");
+        fs::write(&input, text)?;
+
+        let cli = TestCli::parse_from([
+            "watch",
+            dir.path().to_str().expect("utf-8 path"),
+            "--once",
+            "proton",
+            "--lenient",
+        ]);
+        let err = execute(&cli.args, &GlobalArgs::default()).unwrap_err();
+        assert!(
+            matches!(err.kind(), DtToolsError::Partial { problems: 1 }),
+            "{err}"
+        );
+        assert!(!input.exists());
+        assert!(dir.path().join(ARCHIVE_DIR).join("export.csv").exists());
+        assert!(
+            dir.path()
+                .join(OUTBOX_DIR)
+                .join("processed_export.xlsx")
+                .exists()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn quarantines_files_that_cannot_be_opened() -> Result<()> {
+        let dir = tempdir()?;
+        let input = dir.path().join("broken.xlsx");
+        fs::write(&input, "not a zip archive")?;
+
+        let cli = TestCli::parse_from([
+            "watch",
+            dir.path().to_str().expect("utf-8 path"),
+            "--once",
+            "eemcg",
+        ]);
+        // --once 同批量处理一样以失败结束
+        let err = execute(&cli.args, &GlobalArgs::default()).unwrap_err();
+        assert!(
+            matches!(
+                err.kind(),
+                DtToolsError::Batch {
+                    failed: 1,
+                    total: 1,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+
+        let quarantine = dir.path().join(batch::QUARANTINE_DIR);
+        assert!(!input.exists());
+        assert!(quarantine.join("broken.xlsx").exists());
+        let sidecar = fs::read_to_string(quarantine.join("broken.xlsx.error.txt"))?;
+        assert!(sidecar.contains("broken.xlsx"), "{sidecar}");
+        Ok(())
+    }
+}
@@ -0,0 +1,474 @@
+//! 工作簿的读取与写出：xlsx 用 umya 直接读写，xls/ods 等其他格式通过 calamine 导入，
+//! 输出支持 xlsx 和 ods；`--format parquet`（`parquet` 功能）不输出工作簿，由 proton 与各仪器
+//! 改写为长表（见 [`crate::long`]）。
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::error::{Context, DtToolsError, Result, invalid};
+use crate::report;
+use calamine::{Data, Reader, open_workbook_auto};
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use clap::ValueEnum;
+use regex::Regex;
+use umya_spreadsheet::structs::custom_properties::CustomDocumentProperty;
+use umya_spreadsheet::{Cell, Spreadsheet, Worksheet};
+
+mod ods;
+pub(crate) mod stream;
+
+/// 输出工作簿格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Office Open XML（.xlsx）
+    #[default]
+    Xlsx,
+    /// OpenDocument 电子表格（.ods）
+    Ods,
+    /// Parquet 长表（.parquet），每个数值一行；仅用于 proton 与各仪器的转换
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// xlsx 的写出方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Writer {
+    /// 在内存中生成整个工作簿后写出
+    #[default]
+    Memory,
+    /// 逐行写出数据，不在内存中保留整个工作簿（见 [`stream`]）；仅用于 proton 与 merge
+    Streaming,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Xlsx => "xlsx",
+            OutputFormat::Ods => "ods",
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+fn is_xlsx(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("xlsx") || e.eq_ignore_ascii_case("xlsm"))
+}
+
+/// 读取工作簿。xlsx 保留全部内容与样式；xls、ods 等格式只导入单元格的值，
+/// 活动工作表为第一个工作表。
+pub(crate) fn read(path: &Path) -> Result<Spreadsheet> {
+    if is_xlsx(path) {
+        return umya_spreadsheet::reader::xlsx::read(path)
+            .map_err(|err| DtToolsError::file_open(path, err));
+    }
+
+    let mut workbook =
+        open_workbook_auto(path).map_err(|err| DtToolsError::file_open(path, err))?;
+    let mut book = umya_spreadsheet::new_file_empty_worksheet();
+    for name in workbook.sheet_names() {
+        let range = workbook
+            .worksheet_range(&name)
+            .with_context(|| format!("无法读取工作表: {name}"))?;
+        let sheet = book
+            .new_sheet(&name)
+            .map_err(|e| invalid!("无法创建工作表 {name}: {e}"))?;
+        // used_cells 的行列号相对于区域起点
+        let (start_row, start_col) = range.start().unwrap_or_default();
+        for (row, col, value) in range.used_cells() {
+            let cell = sheet.get_cell_mut((start_col + col as u32 + 1, start_row + row as u32 + 1));
+            match value {
+                Data::String(s) => {
+                    cell.set_value(s.as_str());
+                }
+                Data::Float(n) => {
+                    cell.set_value_number(*n);
+                }
+                Data::Int(n) => {
+                    cell.set_value_number(*n as f64);
+                }
+                Data::Bool(b) => {
+                    cell.set_value_bool(*b);
+                }
+                Data::DateTime(dt) => {
+                    cell.set_value_number(dt.as_f64());
+                }
+                // ods 的日期时间单元格读回为带显示格式的日期时间单元格
+                Data::DateTimeIso(s) => match NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+                {
+                    Ok(time) => set_time_cell(cell, &time.format(TIME_TEXT_FORMAT).to_string()),
+                    Err(_) => {
+                        cell.set_value(s.as_str());
+                    }
+                },
+                Data::DurationIso(s) => {
+                    cell.set_value(s.as_str());
+                }
+                Data::Error(e) => {
+                    cell.set_value(format!("{e:?}"));
+                }
+                Data::Empty => {}
+            }
+        }
+    }
+    if book.get_sheet_count() == 0 {
+        return Err(invalid!("工作簿中没有工作表"));
+    }
+    book.set_active_sheet(0);
+    Ok(book)
+}
+
+/// 解析列名（如 `AB`，不区分大小写），返回 1 起计的列号。
+pub(crate) fn column_index(letters: &str) -> Option<u32> {
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    letters.chars().try_fold(0u32, |acc, c| {
+        acc.checked_mul(26)?
+            .checked_add(c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+    })
+}
+
+/// 日期时间单元格的显示格式。
+pub(crate) const DATETIME_FORMAT: &str = "yyyy-mm-dd hh:mm:ss";
+
+/// 文本形式的时间，与 [`DATETIME_FORMAT`] 显示的一致。
+pub(crate) const TIME_TEXT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Excel（1900 日期系统）日期序列号的起点。
+fn excel_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1899, 12, 30)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("valid date")
+}
+
+/// Excel（1900 日期系统）日期序列号对应的时间，按秒取整；小于 1 的序列号为一天中的时刻。
+pub(crate) fn serial_datetime(serial: f64) -> NaiveDateTime {
+    excel_epoch() + TimeDelta::seconds((serial * 86_400.0).round() as i64)
+}
+
+/// 文本形式的日期序列号（1950 年至 2100 年之间）对应的时间，用于读出为数字的日期单元格；
+/// 不是这一范围内的数时返回 `None`。
+pub(crate) fn parse_serial(text: &str) -> Option<NaiveDateTime> {
+    let serial: f64 = text.trim().parse().ok()?;
+    (18_264.0..73_051.0)
+        .contains(&serial)
+        .then(|| serial_datetime(serial))
+}
+
+/// 时间对应的 Excel 日期序列号（1900 年 3 月之后）。
+pub(crate) fn excel_serial(time: NaiveDateTime) -> f64 {
+    (time - excel_epoch()).num_seconds() as f64 / 86_400.0
+}
+
+/// 把 `YYYY-MM-DD HH:MM:SS` 形式的时间写为带显示格式的日期时间单元格；无法解析时写为文本。
+pub(crate) fn set_time_cell(cell: &mut Cell, text: &str) {
+    match NaiveDateTime::parse_from_str(text, TIME_TEXT_FORMAT) {
+        Ok(time) => {
+            cell.set_value_number(excel_serial(time));
+            cell.get_style_mut()
+                .get_number_format_mut()
+                .set_format_code(DATETIME_FORMAT);
+        }
+        Err(_) => {
+            cell.set_value(text);
+        }
+    }
+}
+
+/// 把数值文本写为数字单元格，`format` 为数字格式（如 `0.00`）；不是数值时写为文本。
+pub(crate) fn set_number_cell(cell: &mut Cell, text: &str, format: Option<&str>) {
+    match text.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => {
+            cell.set_value_number(number);
+            if let Some(format) = format {
+                cell.get_style_mut()
+                    .get_number_format_mut()
+                    .set_format_code(format);
+            }
+        }
+        _ => {
+            cell.set_value(text);
+        }
+    }
+}
+
+/// 由 [`set_time_cell`] 写入的日期时间单元格（数字格式为 [`DATETIME_FORMAT`]）的时间。
+pub(crate) fn cell_datetime(cell: &Cell) -> Option<NaiveDateTime> {
+    let is_datetime = cell
+        .get_style()
+        .get_number_format()
+        .is_some_and(|format| format.get_format_code() == DATETIME_FORMAT);
+    if !is_datetime || cell.get_data_type() != "n" {
+        return None;
+    }
+    cell.get_value_number().map(serial_datetime)
+}
+
+/// 单元格显示的文本：日期时间单元格按 `YYYY-MM-DD HH:MM:SS` 显示，其余同 `get_value`。
+pub(crate) fn display_text(cell: &Cell) -> String {
+    match cell_datetime(cell) {
+        Some(time) => time.format(TIME_TEXT_FORMAT).to_string(),
+        None => cell.get_value().to_string(),
+    }
+}
+
+/// 指定位置单元格显示的文本，见 [`display_text`]。
+pub(crate) fn cell_text(sheet: &Worksheet, col: u32, row: u32) -> String {
+    sheet
+        .get_cell((col, row))
+        .map(display_text)
+        .unwrap_or_default()
+}
+
+/// 已处理标记：写入输出 xlsx 的自定义文档属性（ods 为 `meta.xml` 中的用户自定义元数据），
+/// 值为 `<dttools 版本>,<规则/配置指纹>`。
+pub(crate) const PROCESSED_PROPERTY: &str = "dttools:processed";
+
+/// 与已处理标记一同写入的自定义文档属性：版本、规则/配置指纹、处理时间与操作人。
+const VERSION_PROPERTY: &str = "dttools:version";
+const RULES_PROPERTY: &str = "dttools:rules_hash";
+const TIME_PROPERTY: &str = "dttools:processed_at";
+const OPERATOR_PROPERTY: &str = "dttools:operator";
+
+/// `docProps/custom.xml` 中处理标记的值。
+static PROCESSED_VALUE: LazyLock<Regex> = LazyLock::new(|| {
+    let pattern = format!(
+        r#"name="{}"[^>]*>\s*<vt:lpwstr>([^<]*)</vt:lpwstr>"#,
+        regex::escape(PROCESSED_PROPERTY)
+    );
+    Regex::new(&pattern).expect("valid regex")
+});
+
+/// ods 的 `meta.xml` 中处理标记的值。
+static ODS_PROCESSED_VALUE: LazyLock<Regex> = LazyLock::new(|| {
+    let pattern = format!(
+        r#"meta:name="{}"[^>]*>([^<]*)</meta:user-defined>"#,
+        regex::escape(PROCESSED_PROPERTY)
+    );
+    Regex::new(&pattern).expect("valid regex")
+});
+
+/// 在工作簿中写入（或更新）已处理标记与处理信息，便于追溯每个上传的文件：dttools 版本、
+/// 规则/配置指纹、处理时间与操作人（环境变量 USER，Windows 上为 USERNAME）写入自定义文档
+/// 属性，不增加工作表，以免改变上传模板的结构；旧版本输出中的“处理信息”工作表被删除。
+/// `settings` 为本次使用的规则或配置。
+pub(crate) fn mark_processed(book: &mut Spreadsheet, settings: &impl fmt::Debug) {
+    let version = env!("CARGO_PKG_VERSION");
+    let hash = fingerprint(&format!("{settings:?}"));
+    let time = chrono::Local::now().format(TIME_TEXT_FORMAT).to_string();
+    let operator = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let marker = format!("{version},{hash}");
+    let properties = [
+        (PROCESSED_PROPERTY, marker.as_str()),
+        (VERSION_PROPERTY, version),
+        (RULES_PROPERTY, &hash),
+        (TIME_PROPERTY, &time),
+        (OPERATOR_PROPERTY, &operator),
+    ];
+
+    let custom = book.get_properties_mut().get_custom_properties_mut();
+    let mut list = custom.get_custom_document_property_list().to_vec();
+    list.retain(|property| {
+        !properties
+            .iter()
+            .any(|(name, _)| property.get_name() == *name)
+    });
+    for (name, value) in properties {
+        let mut property = CustomDocumentProperty::default();
+        property.set_name(name).set_value_string(value);
+        list.push(property);
+    }
+    custom.set_custom_document_property_list(list);
+
+    report::remove_metadata_sheet(book);
+}
+
+/// 读取输入文件中的已处理标记。只检查 xlsx/xlsm 与 ods，其他格式（xls、CSV）没有存放标记的
+/// 地方，返回 `None`；文件无法按 zip 打开时也返回 `None`，由之后的读取报告错误。
+pub(crate) fn processed_marker(path: &Path) -> Result<Option<String>> {
+    let (entry, pattern) = if is_xlsx(path) {
+        ("docProps/custom.xml", &*PROCESSED_VALUE)
+    } else if carries_marker(path) {
+        ("meta.xml", &*ODS_PROCESSED_VALUE)
+    } else {
+        return Ok(None);
+    };
+    let file = File::open(path).map_err(|err| DtToolsError::file_open(path, err))?;
+    let Ok(mut archive) = zip::ZipArchive::new(file) else {
+        return Ok(None);
+    };
+    let Ok(mut entry) = archive.by_name(entry) else {
+        return Ok(None);
+    };
+    let mut xml = String::new();
+    entry
+        .read_to_string(&mut xml)
+        .with_context(|| format!("无法读取文档属性: {}", path.display()))?;
+    Ok(pattern.captures(&xml).map(|caps| caps[1].to_string()))
+}
+
+/// 文件格式能否带有已处理标记（xlsx/xlsm 与 ods）。
+pub(crate) fn carries_marker(path: &Path) -> bool {
+    is_xlsx(path)
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("ods"))
+}
+
+/// 文本的 FNV-1a 64 位指纹（16 位十六进制）。
+pub(crate) fn fingerprint(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// 按指定格式写出工作簿。
+pub(crate) fn write(book: &Spreadsheet, path: &Path, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Xlsx => umya_spreadsheet::writer::xlsx::write(book, path)
+            .map_err(|err| DtToolsError::write(path, err)),
+        OutputFormat::Ods => ods::write(book, path).map_err(|err| DtToolsError::write(path, err)),
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => Err(invalid!(
+            "--format parquet 只能用于 proton 与各仪器的转换: {}",
+            path.display()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::{Reader, SheetVisible};
+    use umya_spreadsheet::structs::SheetStateValues;
+
+    use super::*;
+
+    #[test]
+    fn detects_processed_marker() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("a.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        write(&book, &path, OutputFormat::Xlsx)?;
+        assert_eq!(processed_marker(&path)?, None);
+
+        mark_processed(&mut book, &"rules");
+        mark_processed(&mut book, &"rules");
+        write(&book, &path, OutputFormat::Xlsx)?;
+        let marker = processed_marker(&path)?.expect("marker");
+        assert_eq!(
+            marker,
+            format!("{},{}", env!("CARGO_PKG_VERSION"), fingerprint("\"rules\""))
+        );
+        let back = umya_spreadsheet::reader::xlsx::read(&path)?;
+        let properties = back
+            .get_properties()
+            .get_custom_properties()
+            .get_custom_document_property_list();
+        assert_eq!(properties.len(), 5);
+        let rules = properties
+            .iter()
+            .find(|property| property.get_name() == RULES_PROPERTY)
+            .expect("rules hash property");
+        assert_eq!(rules.get_value(), fingerprint("\"rules\""));
+
+        assert_eq!(back.get_sheet_count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn detects_processed_marker_in_ods() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("a.ods");
+        let mut book = umya_spreadsheet::new_file();
+        write(&book, &path, OutputFormat::Ods)?;
+        assert_eq!(processed_marker(&path)?, None);
+
+        mark_processed(&mut book, &"rules");
+        write(&book, &path, OutputFormat::Ods)?;
+        let marker = processed_marker(&path)?.expect("marker");
+        assert!(marker.ends_with(&fingerprint("\"rules\"")));
+        assert!(!carries_marker(Path::new("processed_a.csv")));
+        Ok(())
+    }
+
+    #[test]
+    fn writes_typed_cells() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("typed.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        set_time_cell(sheet.get_cell_mut("A1"), "2026-01-05 01:30:00");
+        set_time_cell(sheet.get_cell_mut("A2"), "不是时间");
+        set_number_cell(sheet.get_cell_mut("B1"), "1.50", Some("0.00"));
+        set_number_cell(sheet.get_cell_mut("B2"), "<DL", Some("0.00"));
+        write(&book, &path, OutputFormat::Xlsx)?;
+
+        let back = read(&path)?;
+        let sheet = back.get_sheet(&0).expect("sheet");
+        let serial = sheet.get_value_number("A1").expect("serial");
+        assert!((serial - 46027.0625).abs() < 1e-9);
+        assert_eq!(
+            parse_serial(" 46027.0625 ").map(|time| time.format(TIME_TEXT_FORMAT).to_string()),
+            Some("2026-01-05 01:30:00".to_string())
+        );
+        assert_eq!(parse_serial("12.5"), None);
+        assert_eq!(cell_text(sheet, 1, 1), "2026-01-05 01:30:00");
+        assert_eq!(cell_text(sheet, 1, 2), "不是时间");
+        assert_eq!(sheet.get_value_number("B1"), Some(1.5));
+        assert_eq!(
+            sheet
+                .get_style("B1")
+                .get_number_format()
+                .map(|f| f.get_format_code()),
+            Some("0.00")
+        );
+        assert_eq!(sheet.get_value("B2"), "<DL");
+        Ok(())
+    }
+
+    #[test]
+    fn ods_round_trip_through_calamine() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_active_sheet_mut();
+        sheet.set_name("NMHC监测仪");
+        sheet.get_cell_mut("A1").set_value("总烃(ppbC) & <备注>");
+        sheet.get_cell_mut("C2").set_value_number(1.5);
+        set_time_cell(sheet.get_cell_mut("D2"), "2026-01-05 01:00:00");
+        sheet
+            .get_cell_mut("B2")
+            .get_style_mut()
+            .set_background_color("ffff0000");
+        book.new_sheet("隐藏")
+            .expect("new sheet")
+            .set_state(SheetStateValues::Hidden);
+
+        let path = dir.path().join("out.ods");
+        write(&book, &path, OutputFormat::Ods)?;
+
+        let back = read(&path)?;
+        let sheet = back.get_sheet_by_name("NMHC监测仪").expect("sheet");
+        assert_eq!(sheet.get_value("A1"), "总烃(ppbC) & <备注>");
+        assert_eq!(sheet.get_value_number("C2"), Some(1.5));
+        assert_eq!(cell_text(sheet, 4, 2), "2026-01-05 01:00:00");
+
+        let ods: calamine::Ods<_> = calamine::open_workbook(&path)?;
+        let visible: Vec<_> = ods
+            .sheets_metadata()
+            .iter()
+            .map(|sheet| sheet.visible)
+            .collect();
+        assert_eq!(visible, [SheetVisible::Visible, SheetVisible::Hidden]);
+        Ok(())
+    }
+}
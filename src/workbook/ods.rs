@@ -0,0 +1,174 @@
+//! 最小的 OpenDocument 电子表格（.ods）写出器：写出工作表名称、单元格的值
+//! （数值/日期时间/文本）、纯色填充以及隐藏状态，足以保留红色/橙色标记和变更记录。
+//! 自定义文档属性（如已处理标记）写为 `meta.xml` 中的用户自定义元数据。
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+use umya_spreadsheet::structs::{PatternValues, SheetStateValues};
+use umya_spreadsheet::{Cell, Spreadsheet};
+use zip::CompressionMethod;
+use zip::write::SimpleFileOptions;
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 单元格的纯色填充，返回 `#rrggbb`。
+fn solid_fill(cell: &Cell) -> Option<String> {
+    let pattern = cell.get_style().get_fill()?.get_pattern_fill()?;
+    if *pattern.get_pattern_type() != PatternValues::Solid {
+        return None;
+    }
+    let argb = pattern.get_foreground_color()?.get_argb();
+    (argb.len() >= 6).then(|| format!("#{}", argb[argb.len() - 6..].to_ascii_lowercase()))
+}
+
+fn cell_xml(cell: &Cell, style: Option<&str>) -> String {
+    let style = style
+        .map(|name| format!(r#" table:style-name="{name}""#))
+        .unwrap_or_default();
+    if let Some(time) = super::cell_datetime(cell) {
+        return format!(
+            r#"<table:table-cell{style} office:value-type="date" office:date-value="{}"><text:p>{}</text:p></table:table-cell>"#,
+            time.format("%Y-%m-%dT%H:%M:%S"),
+            time.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    let value = cell.get_value();
+    match cell.get_value_number() {
+        Some(number) if cell.get_data_type() == "n" => format!(
+            r#"<table:table-cell{style} office:value-type="float" office:value="{number}"><text:p>{}</text:p></table:table-cell>"#,
+            escape(&value)
+        ),
+        _ if value.is_empty() => format!("<table:table-cell{style}/>"),
+        _ => format!(
+            r#"<table:table-cell{style} office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+            escape(&value)
+        ),
+    }
+}
+
+fn content_xml(book: &Spreadsheet) -> String {
+    // 每种填充颜色对应一个自动样式 ce1、ce2 ...
+    let mut styles: BTreeMap<String, String> = BTreeMap::new();
+    let mut tables = String::new();
+
+    for sheet in book.get_sheet_collection() {
+        let (max_col, max_row) = sheet.get_highest_column_and_row();
+        let hidden = matches!(
+            sheet.get_state(),
+            SheetStateValues::Hidden | SheetStateValues::VeryHidden
+        );
+        tables.push_str(&format!(
+            r#"<table:table table:name="{}"{}><table:table-column table:number-columns-repeated="{}"/>"#,
+            escape(sheet.get_name()),
+            if hidden {
+                r#" table:style-name="ta_hidden""#
+            } else {
+                ""
+            },
+            max_col.max(1)
+        ));
+        for row in 1..=max_row {
+            tables.push_str("<table:table-row>");
+            for col in 1..=max_col.max(1) {
+                match sheet.get_cell((col, row)) {
+                    Some(cell) => {
+                        let style = solid_fill(cell).map(|color| {
+                            let next = format!("ce{}", styles.len() + 1);
+                            styles.entry(color).or_insert(next).clone()
+                        });
+                        tables.push_str(&cell_xml(cell, style.as_deref()));
+                    }
+                    None => tables.push_str("<table:table-cell/>"),
+                }
+            }
+            tables.push_str("</table:table-row>");
+        }
+        if max_row == 0 {
+            tables.push_str("<table:table-row><table:table-cell/></table:table-row>");
+        }
+        tables.push_str("</table:table>");
+    }
+
+    let mut automatic_styles = String::from(
+        r#"<style:style style:name="ta_hidden" style:family="table"><style:table-properties table:display="false"/></style:style>"#,
+    );
+    for (color, name) in &styles {
+        automatic_styles.push_str(&format!(
+            r#"<style:style style:name="{name}" style:family="table-cell"><style:table-cell-properties fo:background-color="{color}"/></style:style>"#
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.2"><office:automatic-styles>{automatic_styles}</office:automatic-styles><office:body><office:spreadsheet>{tables}</office:spreadsheet></office:body></office:document-content>
+"#
+    )
+}
+
+/// `meta.xml`：工作簿的自定义文档属性写为 `meta:user-defined`。
+fn meta_xml(book: &Spreadsheet) -> String {
+    let properties: String = book
+        .get_properties()
+        .get_custom_properties()
+        .get_custom_document_property_list()
+        .iter()
+        .map(|property| {
+            format!(
+                r#"<meta:user-defined meta:name="{}">{}</meta:user-defined>"#,
+                escape(property.get_name()),
+                escape(&property.get_value())
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" office:version="1.2"><office:meta>{properties}</office:meta></office:document-meta>
+"#
+    )
+}
+
+pub(super) fn write(book: &Spreadsheet, path: &Path) -> Result<()> {
+    let mut zip = zip::ZipWriter::new(File::create(path)?);
+    // mimetype 必须是第一个条目且不压缩
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("META-INF/manifest.xml", deflated)?;
+    zip.write_all(MANIFEST.as_bytes())?;
+    zip.start_file("content.xml", deflated)?;
+    zip.write_all(content_xml(book).as_bytes())?;
+    zip.start_file("meta.xml", deflated)?;
+    zip.write_all(meta_xml(book).as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
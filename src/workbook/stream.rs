@@ -0,0 +1,669 @@
+//! 流式 xlsx 写出器（`--writer streaming`）：工作表逐行写入 zip 条目，不在内存中保留整个
+//! 工作簿，用于很长的输出（如一年的分钟数据）。支持数值与文本单元格（文本写为内联字符串，
+//! 日期时间为带数字格式的序列号）、纯色填充、字体颜色与加粗、数字格式、列宽、冻结表头、
+//! 自动筛选、隐藏的工作表与自定义文档属性；较小的工作表（表头、离子平衡、校正前数据等）可由
+//! umya 工作簿直接复制。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use umya_spreadsheet::helper::coordinate::{coordinate_from_index, string_from_column_index};
+use umya_spreadsheet::structs::{PatternValues, SheetStateValues};
+use umya_spreadsheet::{Cell, Spreadsheet, Style, Worksheet};
+use zip::CompressionMethod;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::error::{DtToolsError, Result};
+
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+/// 自定义文档属性的 fmtid。
+const CUSTOM_FMTID: &str = "{D5CDD505-2E9C-101B-9397-08002B2CF9AE}";
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            // XML 1.0 不允许的控制字符
+            '\u{0}'..='\u{8}' | '\u{b}' | '\u{c}' | '\u{e}'..='\u{1f}' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 单元格的值。日期时间写为 Excel 序列号并使用日期时间数字格式。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Value<'a> {
+    Text(&'a str),
+    Number(f64),
+}
+
+/// 单元格样式，写出时合并为 `styles.xml` 中的一个单元格格式。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct CellStyle {
+    /// 纯色填充（ARGB）
+    pub fill: Option<String>,
+    /// 字体颜色（ARGB）
+    pub font_color: Option<String>,
+    pub bold: bool,
+    /// 数字格式，如 `0.00`、`yyyy-mm-dd hh:mm:ss`
+    pub number_format: Option<String>,
+}
+
+impl CellStyle {
+    /// umya 样式中本写出器支持的部分。
+    pub(crate) fn of(style: &Style) -> Self {
+        let fill = style
+            .get_fill()
+            .and_then(|fill| fill.get_pattern_fill())
+            .filter(|pattern| *pattern.get_pattern_type() == PatternValues::Solid)
+            .and_then(|pattern| pattern.get_foreground_color())
+            .map(|color| color.get_argb().to_string())
+            .filter(|argb| !argb.is_empty());
+        let font = style.get_font();
+        let font_color = font
+            .map(|font| font.get_color().get_argb().to_string())
+            .filter(|argb| !argb.is_empty());
+        let number_format = style
+            .get_number_format()
+            .map(|format| format.get_format_code().to_string())
+            .filter(|code| code != "General");
+        Self {
+            fill,
+            font_color,
+            bold: font.is_some_and(|font| *font.get_bold()),
+            number_format,
+        }
+    }
+
+    /// 改用 `number_format` 的样式。
+    pub(crate) fn with_number_format(&self, number_format: &str) -> Self {
+        Self {
+            number_format: Some(number_format.to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+/// 一个工作表的版式。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SheetLayout {
+    pub hidden: bool,
+    /// 列号（1 起计）→ 列宽
+    pub widths: BTreeMap<u32, f64>,
+    /// 冻结的表头行数
+    pub frozen_rows: Option<u32>,
+    /// 自动筛选的区域，如 `A5:O30`
+    pub auto_filter: Option<String>,
+}
+
+/// 已写出的工作表。
+struct SheetEntry {
+    name: String,
+    hidden: bool,
+    auto_filter: Option<String>,
+}
+
+/// 流式 xlsx 写出器：依次 [`start_sheet`](Self::start_sheet)、[`write_row`](Self::write_row)、
+/// [`end_sheet`](Self::end_sheet)，或用 [`copy_sheet`](Self::copy_sheet) 复制整个工作表，
+/// 最后 [`finish`](Self::finish)。
+pub(crate) struct StreamWriter {
+    path: PathBuf,
+    zip: ZipWriter<BufWriter<File>>,
+    styles: Vec<CellStyle>,
+    style_ids: HashMap<CellStyle, usize>,
+    sheets: Vec<SheetEntry>,
+    /// 当前工作表已写出的最后一行
+    last_row: u32,
+    row: String,
+}
+
+impl StreamWriter {
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).map_err(|err| DtToolsError::write(path, err))?;
+        let default = CellStyle::default();
+        Ok(Self {
+            path: path.to_path_buf(),
+            zip: ZipWriter::new(BufWriter::new(file)),
+            styles: vec![default.clone()],
+            style_ids: HashMap::from([(default, 0)]),
+            sheets: Vec::new(),
+            last_row: 0,
+            row: String::new(),
+        })
+    }
+
+    fn write_error(&self, err: impl std::error::Error + Send + Sync + 'static) -> DtToolsError {
+        DtToolsError::write(&self.path, err)
+    }
+
+    fn start_file(&mut self, name: &str) -> Result<()> {
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(true);
+        self.zip
+            .start_file(name, options)
+            .map_err(|err| self.write_error(err))
+    }
+
+    fn write_str(&mut self, text: &str) -> Result<()> {
+        self.zip
+            .write_all(text.as_bytes())
+            .map_err(|err| self.write_error(err))
+    }
+
+    /// 样式对应的单元格格式编号。
+    pub(crate) fn style(&mut self, style: &CellStyle) -> usize {
+        if let Some(&id) = self.style_ids.get(style) {
+            return id;
+        }
+        self.styles.push(style.clone());
+        self.style_ids.insert(style.clone(), self.styles.len() - 1);
+        self.styles.len() - 1
+    }
+
+    /// 开始写一个工作表；上一个工作表须已 [`end_sheet`](Self::end_sheet)。
+    pub(crate) fn start_sheet(&mut self, name: &str, layout: &SheetLayout) -> Result<()> {
+        let index = self.sheets.len();
+        self.sheets.push(SheetEntry {
+            name: name.to_string(),
+            hidden: layout.hidden,
+            auto_filter: layout.auto_filter.clone(),
+        });
+        self.last_row = 0;
+        self.start_file(&format!("xl/worksheets/sheet{}.xml", index + 1))?;
+
+        let mut xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="{MAIN_NS}" xmlns:r="{REL_NS}"><sheetViews><sheetView workbookViewId="0"{}>"#,
+            if index == 0 {
+                r#" tabSelected="1""#
+            } else {
+                ""
+            }
+        );
+        if let Some(rows) = layout.frozen_rows {
+            let _ = write!(
+                xml,
+                r#"<pane ySplit="{rows}" topLeftCell="{}" activePane="bottomLeft" state="frozen"/>"#,
+                coordinate_from_index(&1, &(rows + 1))
+            );
+        }
+        xml.push_str("</sheetView></sheetViews><sheetFormatPr defaultRowHeight=\"15\"/>");
+        if !layout.widths.is_empty() {
+            xml.push_str("<cols>");
+            for (col, width) in &layout.widths {
+                let _ = write!(
+                    xml,
+                    r#"<col min="{col}" max="{col}" width="{width}" customWidth="1"/>"#
+                );
+            }
+            xml.push_str("</cols>");
+        }
+        xml.push_str("<sheetData>");
+        self.write_str(&xml)
+    }
+
+    /// 写出一行：(列号, 值, 单元格格式编号)，列号从 1 起计且递增；行号须大于已写出的行。
+    pub(crate) fn write_row(&mut self, row: u32, cells: &[(u32, Value<'_>, usize)]) -> Result<()> {
+        debug_assert!(row > self.last_row, "rows must be written in order");
+        self.last_row = row;
+        if cells.is_empty() {
+            return Ok(());
+        }
+        let mut xml = std::mem::take(&mut self.row);
+        xml.clear();
+        let _ = write!(xml, r#"<row r="{row}">"#);
+        for &(col, value, style) in cells {
+            let reference = format!("{}{row}", string_from_column_index(&col));
+            let style = if style == 0 {
+                String::new()
+            } else {
+                format!(r#" s="{style}""#)
+            };
+            match value {
+                Value::Number(number) if number.is_finite() => {
+                    let _ = write!(xml, r#"<c r="{reference}"{style}><v>{number}</v></c>"#);
+                }
+                Value::Text("") => {
+                    let _ = write!(xml, r#"<c r="{reference}"{style}/>"#);
+                }
+                Value::Number(number) => {
+                    let _ = write!(
+                        xml,
+                        r#"<c r="{reference}"{style} t="inlineStr"><is><t>{number}</t></is></c>"#
+                    );
+                }
+                Value::Text(text) => {
+                    let _ = write!(
+                        xml,
+                        r#"<c r="{reference}"{style} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                        escape(text)
+                    );
+                }
+            }
+        }
+        xml.push_str("</row>");
+        let result = self.write_str(&xml);
+        self.row = xml;
+        result
+    }
+
+    /// 结束当前工作表。
+    pub(crate) fn end_sheet(&mut self) -> Result<()> {
+        let mut xml = String::from("</sheetData>");
+        if let Some(range) = self
+            .sheets
+            .last()
+            .and_then(|sheet| sheet.auto_filter.clone())
+        {
+            let _ = write!(xml, r#"<autoFilter ref="{range}"/>"#);
+        }
+        xml.push_str("</worksheet>");
+        self.write_str(&xml)
+    }
+
+    /// 按行写出 umya 工作表中的单元格（值、数字格式、填充与字体），从第 `first_row` 行起，
+    /// 到第 `last_row` 行为止。
+    pub(crate) fn copy_rows(
+        &mut self,
+        sheet: &Worksheet,
+        first_row: u32,
+        last_row: u32,
+    ) -> Result<()> {
+        let mut rows: BTreeMap<u32, Vec<&Cell>> = BTreeMap::new();
+        for cell in sheet.get_cell_collection() {
+            let row = *cell.get_coordinate().get_row_num();
+            if (first_row..=last_row).contains(&row) {
+                rows.entry(row).or_default().push(cell);
+            }
+        }
+        for (row, mut cells) in rows {
+            cells.sort_by_key(|cell| *cell.get_coordinate().get_col_num());
+            let values: Vec<(u32, String, bool, usize)> = cells
+                .iter()
+                .map(|cell| {
+                    let style = self.style(&CellStyle::of(cell.get_style()));
+                    let number = cell.get_data_type() == "n" && !cell.get_value().is_empty();
+                    (
+                        *cell.get_coordinate().get_col_num(),
+                        cell.get_value().into_owned(),
+                        number,
+                        style,
+                    )
+                })
+                .collect();
+            let cells: Vec<(u32, Value<'_>, usize)> = values
+                .iter()
+                .map(|(col, text, number, style)| {
+                    let value = match text.parse::<f64>() {
+                        Ok(value) if *number => Value::Number(value),
+                        _ => Value::Text(text),
+                    };
+                    (*col, value, *style)
+                })
+                .collect();
+            self.write_row(row, &cells)?;
+        }
+        Ok(())
+    }
+
+    /// 复制整个 umya 工作表（单元格、列宽与隐藏状态）。
+    pub(crate) fn copy_sheet(&mut self, sheet: &Worksheet) -> Result<()> {
+        let layout = SheetLayout {
+            hidden: matches!(
+                sheet.get_state(),
+                SheetStateValues::Hidden | SheetStateValues::VeryHidden
+            ),
+            widths: sheet
+                .get_column_dimensions()
+                .iter()
+                .filter(|column| *column.get_width() > 0.0)
+                .map(|column| (*column.get_col_num(), *column.get_width()))
+                .collect(),
+            frozen_rows: None,
+            auto_filter: sheet
+                .get_auto_filter()
+                .map(|filter| filter.get_range().get_range()),
+        };
+        self.start_sheet(sheet.get_name(), &layout)?;
+        self.copy_rows(sheet, 1, u32::MAX)?;
+        self.end_sheet()
+    }
+
+    /// 写出工作簿、样式与文档属性（`book` 中的自定义文档属性），完成文件。
+    pub(crate) fn finish(mut self, book: &Spreadsheet) -> Result<()> {
+        let properties: Vec<(String, String)> = book
+            .get_properties()
+            .get_custom_properties()
+            .get_custom_document_property_list()
+            .iter()
+            .map(|property| {
+                (
+                    property.get_name().to_string(),
+                    property.get_value().into_owned(),
+                )
+            })
+            .collect();
+
+        let styles = self.styles_xml();
+        self.start_file("xl/styles.xml")?;
+        self.write_str(&styles)?;
+        let workbook = self.workbook_xml();
+        self.start_file("xl/workbook.xml")?;
+        self.write_str(&workbook)?;
+
+        let mut rels = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="{PACKAGE_REL_NS}">"#
+        );
+        for i in 1..=self.sheets.len() {
+            let _ = write!(
+                rels,
+                r#"<Relationship Id="rId{i}" Type="{REL_NS}/worksheet" Target="worksheets/sheet{i}.xml"/>"#
+            );
+        }
+        let _ = write!(
+            rels,
+            r#"<Relationship Id="rId{}" Type="{REL_NS}/styles" Target="styles.xml"/></Relationships>"#,
+            self.sheets.len() + 1
+        );
+        self.start_file("xl/_rels/workbook.xml.rels")?;
+        self.write_str(&rels)?;
+
+        let mut root_rels = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="{PACKAGE_REL_NS}"><Relationship Id="rId1" Type="{REL_NS}/officeDocument" Target="xl/workbook.xml"/>"#
+        );
+        if !properties.is_empty() {
+            let _ = write!(
+                root_rels,
+                r#"<Relationship Id="rId2" Type="{REL_NS}/custom-properties" Target="docProps/custom.xml"/>"#
+            );
+            let mut custom = String::from(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes">"#,
+            );
+            for (i, (name, value)) in properties.iter().enumerate() {
+                let _ = write!(
+                    custom,
+                    r#"<property fmtid="{CUSTOM_FMTID}" pid="{}" name="{}"><vt:lpwstr>{}</vt:lpwstr></property>"#,
+                    i + 2,
+                    escape(name),
+                    escape(value)
+                );
+            }
+            custom.push_str("</Properties>");
+            self.start_file("docProps/custom.xml")?;
+            self.write_str(&custom)?;
+        }
+        root_rels.push_str("</Relationships>");
+        self.start_file("_rels/.rels")?;
+        self.write_str(&root_rels)?;
+
+        let mut types = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
+        );
+        for i in 1..=self.sheets.len() {
+            let _ = write!(
+                types,
+                r#"<Override PartName="/xl/worksheets/sheet{i}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+            );
+        }
+        if !properties.is_empty() {
+            types.push_str(r#"<Override PartName="/docProps/custom.xml" ContentType="application/vnd.openxmlformats-officedocument.custom-properties+xml"/>"#);
+        }
+        types.push_str("</Types>");
+        self.start_file("[Content_Types].xml")?;
+        self.write_str(&types)?;
+
+        let path = self.path.clone();
+        let mut file = self
+            .zip
+            .finish()
+            .map_err(|err| DtToolsError::write(&path, err))?;
+        file.flush().map_err(|err| DtToolsError::write(&path, err))
+    }
+
+    fn workbook_xml(&self) -> String {
+        let mut xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="{MAIN_NS}" xmlns:r="{REL_NS}"><bookViews><workbookView/></bookViews><sheets>"#
+        );
+        for (i, sheet) in self.sheets.iter().enumerate() {
+            let _ = write!(
+                xml,
+                r#"<sheet name="{}" sheetId="{}"{} r:id="rId{}"/>"#,
+                escape(&sheet.name),
+                i + 1,
+                if sheet.hidden {
+                    r#" state="hidden""#
+                } else {
+                    ""
+                },
+                i + 1
+            );
+        }
+        xml.push_str("</sheets>");
+        let filters: Vec<String> = self
+            .sheets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, sheet)| {
+                let range = sheet.auto_filter.as_ref()?.replace(':', ":$");
+                Some(format!(
+                    r#"<definedName name="_xlnm._FilterDatabase" localSheetId="{i}" hidden="1">'{}'!${}</definedName>"#,
+                    escape(&sheet.name.replace('\'', "''")),
+                    absolute(&range)
+                ))
+            })
+            .collect();
+        if !filters.is_empty() {
+            let _ = write!(xml, "<definedNames>{}</definedNames>", filters.concat());
+        }
+        xml.push_str("</workbook>");
+        xml
+    }
+
+    fn styles_xml(&self) -> String {
+        let mut formats: Vec<&str> = Vec::new();
+        let mut fonts: Vec<(Option<&str>, bool)> = vec![(None, false)];
+        let mut fills: Vec<&str> = Vec::new();
+        let mut xfs = String::new();
+        for style in &self.styles {
+            let format_id = match style.number_format.as_deref() {
+                Some(code) => {
+                    let index = formats.iter().position(|f| *f == code).unwrap_or_else(|| {
+                        formats.push(code);
+                        formats.len() - 1
+                    });
+                    164 + index
+                }
+                None => 0,
+            };
+            let font = (style.font_color.as_deref(), style.bold);
+            let font_id = fonts.iter().position(|f| *f == font).unwrap_or_else(|| {
+                fonts.push(font);
+                fonts.len() - 1
+            });
+            // 0、1 为 Excel 保留的 none 与 gray125 填充
+            let fill_id = match style.fill.as_deref() {
+                Some(fill) => {
+                    2 + fills.iter().position(|f| *f == fill).unwrap_or_else(|| {
+                        fills.push(fill);
+                        fills.len() - 1
+                    })
+                }
+                None => 0,
+            };
+            let _ = write!(
+                xfs,
+                r#"<xf numFmtId="{format_id}" fontId="{font_id}" fillId="{fill_id}" borderId="0" xfId="0"{}{}{}/>"#,
+                if format_id > 0 {
+                    r#" applyNumberFormat="1""#
+                } else {
+                    ""
+                },
+                if font_id > 0 { r#" applyFont="1""# } else { "" },
+                if fill_id > 0 { r#" applyFill="1""# } else { "" },
+            );
+        }
+
+        let mut xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="{MAIN_NS}">"#
+        );
+        if !formats.is_empty() {
+            let _ = write!(xml, r#"<numFmts count="{}">"#, formats.len());
+            for (i, code) in formats.iter().enumerate() {
+                let _ = write!(
+                    xml,
+                    r#"<numFmt numFmtId="{}" formatCode="{}"/>"#,
+                    164 + i,
+                    escape(code)
+                );
+            }
+            xml.push_str("</numFmts>");
+        }
+        let _ = write!(xml, r#"<fonts count="{}">"#, fonts.len());
+        for (color, bold) in &fonts {
+            xml.push_str("<font>");
+            if *bold {
+                xml.push_str("<b/>");
+            }
+            if let Some(color) = color {
+                let _ = write!(xml, r#"<color rgb="{color}"/>"#);
+            }
+            xml.push_str(r#"<sz val="11"/><name val="Calibri"/></font>"#);
+        }
+        let _ = write!(
+            xml,
+            r#"</fonts><fills count="{}"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill>"#,
+            fills.len() + 2
+        );
+        for fill in &fills {
+            let _ = write!(
+                xml,
+                r#"<fill><patternFill patternType="solid"><fgColor rgb="{fill}"/><bgColor rgb="{fill}"/></patternFill></fill>"#
+            );
+        }
+        let _ = write!(
+            xml,
+            r#"</fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="{}">{xfs}</cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#,
+            self.styles.len()
+        );
+        xml
+    }
+}
+
+/// 区域引用中各单元格地址改为绝对引用：`A5:$O30` → `A$5:$O$30`（列前的 `$` 由调用方加上）。
+fn absolute(range: &str) -> String {
+    let mut out = String::with_capacity(range.len() + 4);
+    let mut previous_alpha = false;
+    for c in range.chars() {
+        if c.is_ascii_digit() && previous_alpha {
+            out.push('$');
+        }
+        previous_alpha = c.is_ascii_alphabetic();
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::Reader;
+
+    use super::*;
+
+    #[test]
+    fn writes_readable_workbook() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("stream.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        super::super::mark_processed(&mut book, &"settings");
+        let hidden = book.new_sheet("隐藏").expect("sheet name is free");
+        hidden.set_state(SheetStateValues::Hidden);
+        hidden.get_cell_mut("A4").set_value("操作人");
+
+        let mut writer = StreamWriter::create(&path)?;
+        let red = writer.style(&CellStyle {
+            fill: Some("ffff0000".to_string()),
+            bold: true,
+            ..Default::default()
+        });
+        let time =
+            writer.style(&CellStyle::default().with_number_format(super::super::DATETIME_FORMAT));
+        let layout = SheetLayout {
+            widths: BTreeMap::from([(1, 21.0)]),
+            frozen_rows: Some(1),
+            auto_filter: Some("A1:B3".to_string()),
+            ..Default::default()
+        };
+        writer.start_sheet("数据", &layout)?;
+        writer.write_row(
+            1,
+            &[(1, Value::Text("时间"), 0), (2, Value::Text("<NO₃⁻>"), red)],
+        )?;
+        writer.write_row(
+            2,
+            &[
+                (1, Value::Number(46027.0), time),
+                (2, Value::Number(1.5), 0),
+            ],
+        )?;
+        writer.write_row(
+            3,
+            &[(1, Value::Text(" 备注 "), 0), (2, Value::Text("-99"), 0)],
+        )?;
+        writer.end_sheet()?;
+        for sheet in book.get_sheet_collection().iter().skip(1) {
+            writer.copy_sheet(sheet)?;
+        }
+        writer.finish(&book)?;
+
+        assert!(super::super::processed_marker(&path)?.is_some());
+        let back = umya_spreadsheet::reader::xlsx::read(&path)?;
+        assert_eq!(back.get_sheet_count(), 2);
+        let sheet = back.get_sheet(&0).expect("sheet");
+        assert_eq!(sheet.get_name(), "数据");
+        assert_eq!(sheet.get_value("B1"), "<NO₃⁻>");
+        assert_eq!(super::super::cell_text(sheet, 1, 2), "2026-01-05 00:00:00");
+        assert_eq!(
+            sheet.get_cell("B2").and_then(Cell::get_value_number),
+            Some(1.5)
+        );
+        assert_eq!(sheet.get_value("A3"), " 备注 ");
+        let fill = CellStyle::of(sheet.get_cell("B1").expect("B1").get_style());
+        assert_eq!(fill.fill.as_deref(), Some("ffff0000"));
+        assert!(fill.bold);
+        assert!(sheet.get_auto_filter().is_some());
+        let hidden = back.get_sheet(&1).expect("hidden sheet");
+        assert!(matches!(hidden.get_state(), SheetStateValues::Hidden));
+        assert_eq!(hidden.get_value("A4"), "操作人");
+
+        let mut calamine = calamine::open_workbook_auto(&path).map_err(DtToolsError::external)?;
+        let range = calamine
+            .worksheet_range("数据")
+            .map_err(DtToolsError::external)?;
+        assert_eq!(range.get_size(), (3, 2));
+        assert_eq!(range.get((1, 1)), Some(&calamine::Data::Float(1.5)));
+        Ok(())
+    }
+
+    #[test]
+    fn makes_ranges_absolute() {
+        assert_eq!(absolute("A5:$O30"), "A$5:$O$30");
+    }
+}
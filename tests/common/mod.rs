@@ -0,0 +1,122 @@
+//! 回归测试的公共部分：读取 `tests/fixtures/` 下的样例，并把处理结果与存档的预期工作簿比较。
+//!
+//! 预期工作簿需要更新时（有意修改了输出），运行
+//! `UPDATE_GOLDEN=1 cargo test --test golden` 重新生成，并检查生成文件的差异后一并提交。
+
+use std::path::PathBuf;
+
+use dttools::diff;
+use umya_spreadsheet::structs::PatternValues;
+use umya_spreadsheet::{Spreadsheet, Worksheet};
+
+/// 设置该环境变量时改为写出预期工作簿。
+const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+/// 失败信息中最多列出的差异数。
+const MAX_REPORTED: usize = 30;
+
+/// `tests/fixtures/` 下的文件路径。
+pub fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// 单元格的纯色填充（ARGB，大写）；没有填充时为 `None`。
+fn fill(sheet: &Worksheet, col: u32, row: u32) -> Option<String> {
+    let pattern = sheet
+        .get_cell((col, row))?
+        .get_style()
+        .get_fill()?
+        .get_pattern_fill()?;
+    if *pattern.get_pattern_type() != PatternValues::Solid {
+        return None;
+    }
+    Some(
+        pattern
+            .get_foreground_color()?
+            .get_argb()
+            .to_ascii_uppercase(),
+    )
+}
+
+/// 两个工作表中填充不同的单元格：(地址, 预期, 实际)。
+fn fill_differences(expected: &Worksheet, actual: &Worksheet) -> Vec<String> {
+    let mut cells: Vec<(u32, u32)> = [expected, actual]
+        .into_iter()
+        .flat_map(|sheet| sheet.get_cell_collection())
+        .map(|cell| {
+            let coordinate = cell.get_coordinate();
+            (*coordinate.get_row_num(), *coordinate.get_col_num())
+        })
+        .collect();
+    cells.sort_unstable();
+    cells.dedup();
+    cells
+        .into_iter()
+        .filter_map(|(row, col)| {
+            let (want, got) = (fill(expected, col, row), fill(actual, col, row));
+            (want != got).then(|| {
+                format!(
+                    "{}!{}: 填充 预期 {want:?}，实际 {got:?}",
+                    actual.get_name(),
+                    umya_spreadsheet::helper::coordinate::coordinate_from_index(&col, &row)
+                )
+            })
+        })
+        .collect()
+}
+
+/// 断言处理结果与 `tests/fixtures/<expected>` 一致：工作表名称与顺序、各单元格的值和填充色。
+/// 设置了 `UPDATE_GOLDEN` 时改为用处理结果覆盖预期工作簿。
+pub fn assert_golden(actual: &Spreadsheet, expected: &str) {
+    let path = fixture(expected);
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        umya_spreadsheet::writer::xlsx::write(actual, &path)
+            .unwrap_or_else(|err| panic!("无法写入预期工作簿 {}: {err}", path.display()));
+        return;
+    }
+    let expected = umya_spreadsheet::reader::xlsx::read(&path).unwrap_or_else(|err| {
+        panic!(
+            "无法读取预期工作簿 {}: {err}（新样例请先运行 {UPDATE_ENV}=1 cargo test --test golden 生成）",
+            path.display()
+        )
+    });
+
+    let names = |book: &Spreadsheet| -> Vec<String> {
+        book.get_sheet_collection()
+            .iter()
+            .map(|sheet| sheet.get_name().to_string())
+            .collect()
+    };
+    let mut problems = Vec::new();
+    if names(&expected) != names(actual) {
+        problems.push(format!(
+            "工作表 预期 {:?}，实际 {:?}",
+            names(&expected),
+            names(actual)
+        ));
+    }
+    let values = diff::compare(&expected, actual, &[], 0.0);
+    problems.extend(values.cells.iter().map(|cell| {
+        format!(
+            "{}!{}: 值 预期 {:?}，实际 {:?}",
+            cell.sheet, cell.address, cell.left, cell.right
+        )
+    }));
+    for sheet in expected.get_sheet_collection() {
+        if let Some(other) = actual.get_sheet_by_name(sheet.get_name()) {
+            problems.extend(fill_differences(sheet, other));
+        }
+    }
+
+    if !problems.is_empty() {
+        let shown = problems.len().min(MAX_REPORTED);
+        panic!(
+            "输出与 {} 不一致（共 {} 处，列出前 {shown} 处）:\n  {}\n确认是有意的修改后运行 {UPDATE_ENV}=1 cargo test --test golden 更新预期工作簿",
+            path.display(),
+            problems.len(),
+            problems[..shown].join("\n  ")
+        );
+    }
+}
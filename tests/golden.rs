@@ -0,0 +1,54 @@
+//! 回归测试：用 `tests/fixtures/` 下的样例运行 eemcg、proton 的转换，并与存档的预期工作簿
+//! 逐个单元格比较值和填充色。
+
+mod common;
+
+use common::{assert_golden, fixture};
+use dttools::eemcg::{self, RuleSet};
+use dttools::proton::{self, CsvEncoding, ProtonOptions};
+
+fn eemcg_output(rules: &RuleSet) -> umya_spreadsheet::Spreadsheet {
+    let mut book =
+        umya_spreadsheet::reader::xlsx::read(fixture("eemcg/nmhc.xlsx")).expect("read fixture");
+    eemcg::transform(&mut book, rules);
+    book
+}
+
+fn proton_output(options: &ProtonOptions) -> umya_spreadsheet::Spreadsheet {
+    let source = proton::read_source(&fixture("proton/hourly.csv"), b',', CsvEncoding::Auto)
+        .expect("read fixture");
+    proton::transform(&source, options)
+        .expect("transform")
+        .workbook
+}
+
+#[test]
+fn eemcg_builtin_rules() {
+    assert_golden(
+        &eemcg_output(&RuleSet::builtin()),
+        "eemcg/nmhc.expected.xlsx",
+    );
+}
+
+#[test]
+fn eemcg_flag_codes_and_conversion() {
+    let rules = RuleSet::load(&fixture("eemcg/nmhc.rules.toml")).expect("load rules");
+    assert_golden(&eemcg_output(&rules), "eemcg/nmhc.rules.expected.xlsx");
+}
+
+#[test]
+fn proton_default_options() {
+    assert_golden(
+        &proton_output(&ProtonOptions::default()),
+        "proton/hourly.expected.xlsx",
+    );
+}
+
+#[test]
+fn proton_site_config() {
+    let options = ProtonOptions::load(&fixture("proton/hourly.config.toml")).expect("load config");
+    assert_golden(
+        &proton_output(&options),
+        "proton/hourly.config.expected.xlsx",
+    );
+}